@@ -75,6 +75,8 @@ pub enum Error {
     InvalidNodeOperation(SysNodeType),
     /// Attribute operation failed
     AttributeError,
+    /// Invalid value for an attribute
+    InvalidArgument,
     /// Permission denied for operation
     PermissionDenied,
     /// Other internal error
@@ -93,6 +95,7 @@ impl core::fmt::Display for Error {
                 write!(f, "Invalid operation for node type: {:?}", ty)
             }
             Error::AttributeError => write!(f, "Attribute error"),
+            Error::InvalidArgument => write!(f, "Invalid value for the attribute"),
             Error::PermissionDenied => write!(f, "Permission denied for operation"),
             Error::InternalError(msg) => write!(f, "Internal error: {}", msg),
             Error::AlreadyExists => write!(f, "The systree item already exists"),
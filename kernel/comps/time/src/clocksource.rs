@@ -14,7 +14,10 @@ use alloc::sync::Arc;
 use core::{cmp::max, ops::Add, time::Duration};
 
 use aster_util::coeff::Coeff;
-use ostd::sync::{LocalIrqDisabled, RwLock};
+use ostd::{
+    arch::timer::TIMER_FREQ,
+    sync::{LocalIrqDisabled, RwLock},
+};
 
 use crate::NANOS_PER_SECOND;
 
@@ -50,12 +53,58 @@ use crate::NANOS_PER_SECOND;
 ///
 /// If using this `ClockSource`, you must ensure its internal instant will be updated
 /// at least once within a time interval of not more than `max_delay_secs.
+///
+/// # Adjustments
+/// The frequency of the `ClockSource` can be adjusted to compensate for the drift of the
+/// counter, and a time offset can be slewed gradually so that the measured time never jumps
+/// (e.g., as required by NTP clients). See [`ClockSource::set_freq_adjustment`] and
+/// [`ClockSource::set_slew_offset`]. The raw time, which is read by
+/// [`ClockSource::read_raw_instant`], is not affected by the adjustments.
 pub struct ClockSource {
     read_cycles: Arc<dyn Fn() -> u64 + Sync + Send>,
     base: ClockSourceBase,
+    /// The `Coeff` that converts cycles into nanoseconds, without any adjustments.
+    raw_coeff: Coeff,
+    /// A record to an `Instant` and the corresponding cycles of this `ClockSource`, together
+    /// with the adjustments applied since the record.
+    state: RwLock<ClockSourceState, LocalIrqDisabled>,
+}
+
+/// The maximum frequency adjustment of a `ClockSource`, in parts per billion.
+///
+/// This covers both the adjustment of the tick length (up to 10%) and the frequency offset (up
+/// to 500 parts per million) that can be made by NTP clients.
+pub const MAX_FREQ_ADJUSTMENT_PPB: i64 = 100_500_000;
+
+/// The rate at which a time offset is slewed, in parts per billion.
+///
+/// In other words, the time offset is slewed by 0.5 milliseconds per second.
+const SLEW_RATE_PPB: i64 = 500_000;
+
+/// The interval between two updates of a `ClockSource` when slewing, in nanoseconds.
+///
+/// When slewing, the `ClockSource` is updated at every timer tick. The slewing rate is lowered
+/// when the remaining time offset is smaller than what can be slewed within the interval, so
+/// that the slewing does not overshoot.
+const SLEW_INTERVAL_NANOS: i64 = (NANOS_PER_SECOND as u64 / TIMER_FREQ) as i64;
+
+struct ClockSourceState {
+    last_instant: Instant,
+    last_cycles: u64,
+    /// The raw instant corresponding to `last_cycles`.
+    last_raw_instant: Instant,
+    /// The frequency adjustment, in parts per billion.
+    freq_adjustment: i64,
+    /// The `Coeff` that converts cycles into nanoseconds, with the frequency adjustment.
     coeff: Coeff,
-    /// A record to an `Instant` and the corresponding cycles of this `ClockSource`.
-    last_record: RwLock<(Instant, u64), LocalIrqDisabled>,
+    /// The `Coeff` that converts cycles into nanoseconds, with the frequency adjustment and the
+    /// slewing rate.
+    ///
+    /// The time since the last record is calculated linearly with this `Coeff`, both in the
+    /// kernel and in the vDSO.
+    slew_coeff: Coeff,
+    /// The remaining time offset to slew, in nanoseconds.
+    slew_offset: i64,
 }
 
 impl ClockSource {
@@ -73,12 +122,20 @@ impl ClockSource {
         let base = ClockSourceBase::new(freq, max_delay_secs);
         // Too big `max_delay_secs` will lead to a low resolution Coeff.
         debug_assert!(max_delay_secs < 600);
-        let coeff = Coeff::new(NANOS_PER_SECOND as u64, freq, max_delay_secs * freq);
+        let coeff = base.coeff(0);
         Self {
             read_cycles,
             base,
-            coeff,
-            last_record: RwLock::new((Instant::zero(), 0)),
+            raw_coeff: coeff,
+            state: RwLock::new(ClockSourceState {
+                last_instant: Instant::zero(),
+                last_cycles: 0,
+                last_raw_instant: Instant::zero(),
+                freq_adjustment: 0,
+                coeff,
+                slew_coeff: coeff,
+                slew_offset: 0,
+            }),
         }
     }
 
@@ -88,26 +145,44 @@ impl ClockSource {
     /// recorded cycles stored in the clocksource. Then `ClockSource` will convert
     /// the passed cycles into passed time and calculate the current instant.
     ///
-    /// Returns the calculated instant and instant cycles.
-    fn calculate_instant(&self) -> (Instant, u64) {
-        let (instant_cycles, last_instant, last_cycles) = {
-            let last_record = self.last_record.read();
-            let (last_instant, last_cycles) = *last_record;
-            (self.read_cycles(), last_instant, last_cycles)
-        };
+    /// Returns the calculated instant and the slewed offset since the last record.
+    fn calculate_instant(&self, state: &ClockSourceState, instant_cycles: u64) -> (Instant, i64) {
+        let delta_cycles = instant_cycles - state.last_cycles;
+        let delta_nanos = self.cycles_to_nanos_lossy(&state.slew_coeff, delta_cycles);
 
-        let delta_nanos = {
-            let delta_cycles = instant_cycles - last_cycles;
-            self.cycles_to_nanos_lossy(delta_cycles)
+        let slewed = if state.slew_offset == 0 {
+            0
+        } else {
+            // This cannot overflow since the nanoseconds are bounded by the maximum delay.
+            delta_nanos as i64 - self.cycles_to_nanos_lossy(&state.coeff, delta_cycles) as i64
         };
-        let duration = Duration::from_nanos(delta_nanos);
-        (last_instant + duration, instant_cycles)
+
+        (
+            state.last_instant + Duration::from_nanos(delta_nanos),
+            slewed,
+        )
+    }
+
+    /// Calculates the `Coeff` with the slewing rate for the remaining time offset.
+    fn calculate_slew_coeff(&self, state: &ClockSourceState) -> Coeff {
+        if state.slew_offset == 0 {
+            return state.coeff;
+        }
+
+        let max_rate = state
+            .slew_offset
+            .unsigned_abs()
+            .saturating_mul(NANOS_PER_SECOND as u64)
+            / SLEW_INTERVAL_NANOS as u64;
+        let slew_rate = (SLEW_RATE_PPB as u64).min(max_rate).max(1) as i64;
+        self.base
+            .coeff(state.freq_adjustment + slew_rate * state.slew_offset.signum())
     }
 
-    fn cycles_to_nanos_lossy(&self, cycles: u64) -> u64 {
+    fn cycles_to_nanos_lossy(&self, coeff: &Coeff, cycles: u64) -> u64 {
         let max_cycles = self.base.max_delay_secs * self.base.freq;
         if cycles <= max_cycles {
-            self.coeff * cycles
+            *coeff * cycles
         } else {
             log::warn!(
                 "The clock source becomes not reliable since an \
@@ -115,13 +190,28 @@ impl ClockSource {
                 cycles,
                 max_cycles
             );
-            self.coeff * max_cycles
+            *coeff * max_cycles
         }
     }
 
-    /// Uses an input instant and cycles to update the `last_record` in the `ClockSource`.
-    fn update_last_record(&self, record: (Instant, u64)) {
-        *self.last_record.write() = record;
+    /// Updates the `last_record` in the `ClockSource` to the current instant and cycles.
+    ///
+    /// The slewed offset is deducted from the remaining time offset to slew.
+    fn update_last_record(&self, state: &mut ClockSourceState) {
+        let instant_cycles = self.read_cycles();
+        let (instant, slewed) = self.calculate_instant(state, instant_cycles);
+        state.last_raw_instant = self.calculate_raw_instant(state, instant_cycles);
+        state.last_instant = instant;
+        state.last_cycles = instant_cycles;
+        // The slewing may overshoot if the update is late, which is reverted later.
+        state.slew_offset -= slewed;
+        state.slew_coeff = self.calculate_slew_coeff(state);
+    }
+
+    fn calculate_raw_instant(&self, state: &ClockSourceState, instant_cycles: u64) -> Instant {
+        let delta_cycles = instant_cycles - state.last_cycles;
+        let delta_nanos = self.cycles_to_nanos_lossy(&self.raw_coeff, delta_cycles);
+        state.last_raw_instant + Duration::from_nanos(delta_nanos)
     }
 
     /// Reads current cycles of the `ClockSource`.
@@ -131,7 +221,18 @@ impl ClockSource {
 
     /// Returns the last instant and last cycles recorded in the `ClockSource`.
     pub fn last_record(&self) -> (Instant, u64) {
-        return *self.last_record.read();
+        let state = self.state.read();
+        (state.last_instant, state.last_cycles)
+    }
+
+    /// Returns the last instant and last cycles recorded in the `ClockSource`, together with
+    /// the coeff to calculate the time since the record.
+    ///
+    /// The three values are read atomically, so the time calculated with them is the same as
+    /// [`Self::read_instant`].
+    pub fn last_record_with_coeff(&self) -> (Instant, u64, Coeff) {
+        let state = self.state.read();
+        (state.last_instant, state.last_cycles, state.slew_coeff)
     }
 
     /// Returns the maximum delay seconds for updating of the `ClockSource`.
@@ -139,9 +240,13 @@ impl ClockSource {
         self.base.max_delay_secs
     }
 
-    /// Returns the reference to the generated cycles coeff of the `ClockSource`.
-    pub fn coeff(&self) -> &Coeff {
-        &self.coeff
+    /// Returns the generated cycles coeff of the `ClockSource`.
+    ///
+    /// The coeff includes the frequency adjustment and, if a time offset is being slewed, the
+    /// slewing rate. The time calculated linearly with the coeff from the last record is the
+    /// same as [`Self::read_instant`].
+    pub fn coeff(&self) -> Coeff {
+        self.state.read().slew_coeff
     }
 
     /// Returns the frequency of the counter used in the `ClockSource`.
@@ -149,20 +254,79 @@ impl ClockSource {
         self.base.freq
     }
 
+    /// Returns the frequency adjustment of the `ClockSource`, in parts per billion.
+    pub fn freq_adjustment(&self) -> i64 {
+        self.state.read().freq_adjustment
+    }
+
+    /// Sets the frequency adjustment of the `ClockSource`, in parts per billion.
+    ///
+    /// The adjustment will be clamped to [`MAX_FREQ_ADJUSTMENT_PPB`]. The time measured before
+    /// this method is called is not affected.
+    pub fn set_freq_adjustment(&self, freq_adjustment: i64) {
+        let freq_adjustment =
+            freq_adjustment.clamp(-MAX_FREQ_ADJUSTMENT_PPB, MAX_FREQ_ADJUSTMENT_PPB);
+
+        let mut state = self.state.write();
+        self.update_last_record(&mut state);
+        state.freq_adjustment = freq_adjustment;
+        state.coeff = self.base.coeff(freq_adjustment);
+        state.slew_coeff = self.calculate_slew_coeff(&state);
+    }
+
+    /// Returns the remaining time offset to slew, in nanoseconds.
+    pub fn slew_offset(&self) -> i64 {
+        self.state.read().slew_offset
+    }
+
+    /// Starts to slew the time of the `ClockSource` by `offset` nanoseconds.
+    ///
+    /// The time offset is slewed by speeding up (if `offset` is positive) or slowing down (if
+    /// `offset` is negative) the `ClockSource` slightly, until the whole offset is applied. The
+    /// remaining offset of the previous slewing is discarded and returned.
+    pub fn set_slew_offset(&self, offset: i64) -> i64 {
+        let mut state = self.state.write();
+        self.update_last_record(&mut state);
+        let old_offset = core::mem::replace(&mut state.slew_offset, offset);
+        state.slew_coeff = self.calculate_slew_coeff(&state);
+        old_offset
+    }
+
+    /// Returns whether a time offset is being slewed.
+    ///
+    /// When slewing, the `ClockSource` should be updated at every timer tick, so that the
+    /// slewing rate is adjusted in time and the slewing does not overshoot.
+    pub fn is_slewing(&self) -> bool {
+        self.state.read().slew_offset != 0
+    }
+
     /// Calibrates the recorded `Instant` to zero, and record the instant cycles.
     pub(crate) fn calibrate(&self, instant_cycles: u64) {
-        self.update_last_record((Instant::zero(), instant_cycles));
+        let mut state = self.state.write();
+        state.last_instant = Instant::zero();
+        state.last_cycles = instant_cycles;
+        state.last_raw_instant = Instant::zero();
     }
 
     /// Gets the instant to update the internal instant in the `ClockSource`.
     pub(crate) fn update(&self) {
-        let (instant, instant_cycles) = self.calculate_instant();
-        self.update_last_record((instant, instant_cycles));
+        let mut state = self.state.write();
+        self.update_last_record(&mut state);
     }
 
     /// Reads the instant corresponding to the current time.
     pub(crate) fn read_instant(&self) -> Instant {
-        self.calculate_instant().0
+        let state = self.state.read();
+        self.calculate_instant(&state, self.read_cycles()).0
+    }
+
+    /// Reads the raw instant corresponding to the current time.
+    ///
+    /// Unlike [`Self::read_instant`], the raw instant is affected by neither the frequency
+    /// adjustment nor the slewing.
+    pub(crate) fn read_raw_instant(&self) -> Instant {
+        let state = self.state.read();
+        self.calculate_raw_instant(&state, self.read_cycles())
     }
 }
 
@@ -238,4 +402,11 @@ impl ClockSourceBase {
             max_delay_secs,
         }
     }
+
+    /// Calculates the `Coeff` that converts cycles into nanoseconds, with a frequency
+    /// adjustment in parts per billion.
+    fn coeff(&self, freq_adjustment: i64) -> Coeff {
+        let nanos_per_second = (NANOS_PER_SECOND as i64 + freq_adjustment) as u64;
+        Coeff::new(nanos_per_second, self.freq, self.max_delay_secs * self.freq)
+    }
 }
@@ -10,8 +10,9 @@ extern crate alloc;
 use alloc::sync::Arc;
 use core::time::Duration;
 
+use aster_util::coeff::Coeff;
 use clocksource::ClockSource;
-pub use clocksource::Instant;
+pub use clocksource::{Instant, MAX_FREQ_ADJUSTMENT_PPB};
 use component::{init_component, ComponentInitError};
 use ostd::sync::Mutex;
 use rtc::Driver;
@@ -22,7 +23,7 @@ mod rtc;
 mod tsc;
 
 pub const NANOS_PER_SECOND: u32 = 1_000_000_000;
-pub static VDSO_DATA_HIGH_RES_UPDATE_FN: Once<Arc<dyn Fn(Instant, u64) + Sync + Send>> =
+pub static VDSO_DATA_HIGH_RES_UPDATE_FN: Once<Arc<dyn Fn(Instant, u64, Coeff) + Sync + Send>> =
     Once::new();
 static RTC_DRIVER: Once<Arc<dyn Driver + Send + Sync>> = Once::new();
 
@@ -88,7 +89,38 @@ pub fn read_monotonic_time() -> Duration {
     Duration::new(instant.secs(), instant.nanos())
 }
 
+/// Return the monotonic raw time from the tsc clocksource, which is not adjusted.
+pub fn read_monotonic_raw_time() -> Duration {
+    let instant = tsc::read_raw_instant();
+    Duration::new(instant.secs(), instant.nanos())
+}
+
 /// Return the tsc clocksource.
 pub fn default_clocksource() -> Arc<ClockSource> {
     tsc::CLOCK.get().unwrap().clone()
 }
+
+/// Return the frequency adjustment of the tsc clocksource, in parts per billion.
+pub fn freq_adjustment() -> i64 {
+    tsc::CLOCK.get().unwrap().freq_adjustment()
+}
+
+/// Set the frequency adjustment of the tsc clocksource, in parts per billion.
+///
+/// The adjustment is clamped to [`MAX_FREQ_ADJUSTMENT_PPB`].
+pub fn set_freq_adjustment(freq_adjustment: i64) {
+    tsc::set_freq_adjustment(freq_adjustment);
+}
+
+/// Return the remaining time offset to slew of the tsc clocksource, in nanoseconds.
+pub fn slew_offset() -> i64 {
+    tsc::CLOCK.get().unwrap().slew_offset()
+}
+
+/// Start to slew the tsc clocksource by `offset` nanoseconds.
+///
+/// The monotonic time is sped up or slowed down slightly until the whole offset is applied, so
+/// it never jumps. Return the remaining offset of the previous slewing, which is discarded.
+pub fn set_slew_offset(offset: i64) -> i64 {
+    tsc::set_slew_offset(offset)
+}
@@ -53,14 +53,39 @@ pub(super) fn read_instant() -> Instant {
     clock.read_instant()
 }
 
+/// Read a raw `Instant` of tsc clocksource, which is not adjusted.
+pub(super) fn read_raw_instant() -> Instant {
+    let clock = CLOCK.get().unwrap();
+    clock.read_raw_instant()
+}
+
+/// Set the frequency adjustment of tsc clocksource, in parts per billion.
+pub(super) fn set_freq_adjustment(freq_adjustment: i64) {
+    let clock = CLOCK.get().unwrap();
+    clock.set_freq_adjustment(freq_adjustment);
+    update_vdso_data(clock);
+}
+
+/// Start to slew tsc clocksource by an offset in nanoseconds.
+///
+/// Return the remaining offset of the previous slewing.
+pub(super) fn set_slew_offset(offset: i64) -> i64 {
+    let clock = CLOCK.get().unwrap();
+    let old_offset = clock.set_slew_offset(offset);
+    update_vdso_data(clock);
+    old_offset
+}
+
 fn update_clocksource() {
     let clock = CLOCK.get().unwrap();
     clock.update();
+    update_vdso_data(clock);
+}
 
-    // Update vdso data.
+fn update_vdso_data(clock: &ClockSource) {
     if let Some(update_fn) = VDSO_DATA_HIGH_RES_UPDATE_FN.get() {
-        let (last_instant, last_cycles) = clock.last_record();
-        update_fn(last_instant, last_cycles);
+        let (last_instant, last_cycles, coeff) = clock.last_record_with_coeff();
+        update_fn(last_instant, last_cycles, coeff);
     }
 }
 
@@ -79,7 +104,8 @@ fn init_timer() {
     let update = move || {
         let counter = TSC_UPDATE_COUNTER.fetch_add(1, Ordering::Relaxed);
 
-        if counter % delay_counts == 0 {
+        // When slewing, update the clocksource at every tick to stop the slewing in time.
+        if counter % delay_counts == 0 || CLOCK.get().unwrap().is_slewing() {
             update_clocksource();
         }
     };
@@ -0,0 +1,69 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::string::String;
+use core::mem::offset_of;
+
+use aster_util::safe_ptr::SafePtr;
+use ostd::Pod;
+
+use crate::transport::{ConfigManager, VirtioTransport};
+
+bitflags::bitflags! {
+    pub struct FsFeatures: u64 {
+        /// The device has the notification virtqueue.
+        const NOTIFICATION = 1 << 0;
+    }
+}
+
+/// The maximum length of the tag, in bytes.
+pub const VIRTIO_FS_TAG_LEN: usize = 36;
+
+#[derive(Debug, Pod, Clone, Copy)]
+#[repr(C)]
+pub struct VirtioFsConfig {
+    /// The name of the file system, encoded in UTF-8 and padded with NUL bytes.
+    pub tag: [u8; VIRTIO_FS_TAG_LEN],
+    /// The number of request virtqueues.
+    pub num_request_queues: u32,
+    /// The size of the buffers of the notification virtqueue.
+    pub notify_buf_size: u32,
+}
+
+impl VirtioFsConfig {
+    pub(super) fn new_manager(transport: &dyn VirtioTransport) -> ConfigManager<Self> {
+        let safe_ptr = transport
+            .device_config_mem()
+            .map(|mem| SafePtr::new(mem, 0));
+        let bar_space = transport.device_config_bar();
+        ConfigManager::new(safe_ptr, bar_space)
+    }
+
+    /// Returns the tag, which is not NUL-terminated if it is `VIRTIO_FS_TAG_LEN` bytes long.
+    pub fn tag(&self) -> String {
+        let len = self
+            .tag
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(VIRTIO_FS_TAG_LEN);
+        String::from_utf8_lossy(&self.tag[..len]).into_owned()
+    }
+}
+
+impl ConfigManager<VirtioFsConfig> {
+    pub(super) fn read_config(&self) -> VirtioFsConfig {
+        let mut fs_config = VirtioFsConfig::new_uninit();
+        for (i, byte) in fs_config.tag.iter_mut().enumerate() {
+            *byte = self
+                .read_once::<u8>(offset_of!(VirtioFsConfig, tag) + i)
+                .unwrap();
+        }
+        fs_config.num_request_queues = self
+            .read_once::<u32>(offset_of!(VirtioFsConfig, num_request_queues))
+            .unwrap();
+        fs_config.notify_buf_size = self
+            .read_once::<u32>(offset_of!(VirtioFsConfig, notify_buf_size))
+            .unwrap();
+
+        fs_config
+    }
+}
@@ -0,0 +1,255 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{boxed::Box, collections::BTreeMap, string::String, sync::Arc};
+use core::fmt::Debug;
+
+use log::{debug, info, warn};
+use ostd::{
+    arch::trap::TrapFrame,
+    mm::{DmaDirection, DmaStream, DmaStreamSlice, FrameAllocOptions, VmIo, PAGE_SIZE},
+    sync::{SpinLock, WaitQueue},
+};
+
+use super::{
+    config::{FsFeatures, VirtioFsConfig},
+    register_device,
+};
+use crate::{
+    device::VirtioDeviceError,
+    queue::VirtQueue,
+    transport::{ConfigManager, VirtioTransport},
+};
+
+const QUEUE_SIZE: u16 = 64;
+/// The high-priority queue, which is used for the requests that have no replies.
+const QUEUE_HIPRIO: u16 = 0;
+/// The first request queue. The other request queues are not used.
+const QUEUE_REQUEST: u16 = 1;
+
+/// A virtio-fs device.
+///
+/// The requests are submitted to the device as FUSE requests, which are built by the caller.
+/// Each request takes one device-readable buffer for the request and one device-writable
+/// buffer for the reply.
+pub struct FileSystemDevice {
+    config_manager: ConfigManager<VirtioFsConfig>,
+    tag: String,
+    transport: SpinLock<Box<dyn VirtioTransport>>,
+    hiprio_queue: SpinLock<VirtQueue>,
+    request_queue: SpinLock<VirtQueue>,
+    /// The requests submitted to the request queue, indexed by the tokens.
+    submitted_requests: SpinLock<BTreeMap<u16, Arc<SubmittedRequest>>>,
+    /// The buffers submitted to the high-priority queue, indexed by the tokens.
+    ///
+    /// The buffers are kept alive until the device has used them.
+    submitted_oneway: SpinLock<BTreeMap<u16, DmaStream>>,
+    /// The wait queue of the requests that are waiting for free descriptors or replies.
+    wait_queue: WaitQueue,
+}
+
+impl Debug for FileSystemDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FileSystemDevice")
+            .field("config", &self.config_manager.read_config())
+            .field("transport", &self.transport)
+            .field("hiprio_queue", &self.hiprio_queue)
+            .field("request_queue", &self.request_queue)
+            .finish()
+    }
+}
+
+impl FileSystemDevice {
+    pub(crate) fn negotiate_features(features: u64) -> u64 {
+        let mut features = FsFeatures::from_bits_truncate(features);
+        // The notifications from the device (e.g., lock releases) are not supported.
+        features.remove(FsFeatures::NOTIFICATION);
+        features.bits()
+    }
+
+    pub(crate) fn init(mut transport: Box<dyn VirtioTransport>) -> Result<(), VirtioDeviceError> {
+        let config_manager = VirtioFsConfig::new_manager(transport.as_ref());
+        let config = config_manager.read_config();
+        debug!("virtio_fs_config = {:?}", config);
+        if config.num_request_queues == 0 {
+            return Err(VirtioDeviceError::QueuesAmountDoNotMatch(0, 1));
+        }
+
+        let hiprio_queue =
+            SpinLock::new(VirtQueue::new(QUEUE_HIPRIO, QUEUE_SIZE, transport.as_mut()).unwrap());
+        let request_queue =
+            SpinLock::new(VirtQueue::new(QUEUE_REQUEST, QUEUE_SIZE, transport.as_mut()).unwrap());
+
+        let device = Arc::new(Self {
+            config_manager,
+            tag: config.tag(),
+            transport: SpinLock::new(transport),
+            hiprio_queue,
+            request_queue,
+            submitted_requests: SpinLock::new(BTreeMap::new()),
+            submitted_oneway: SpinLock::new(BTreeMap::new()),
+            wait_queue: WaitQueue::new(),
+        });
+
+        // Register irq callbacks
+        let mut transport = device.transport.disable_irq().lock();
+        let handle_hiprio = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_hiprio_irq()
+        };
+        let handle_request = {
+            let device = device.clone();
+            move |_: &TrapFrame| device.handle_request_irq()
+        };
+        transport
+            .register_queue_callback(QUEUE_HIPRIO, Box::new(handle_hiprio), false)
+            .unwrap();
+        transport
+            .register_queue_callback(QUEUE_REQUEST, Box::new(handle_request), false)
+            .unwrap();
+        transport
+            .register_cfg_callback(Box::new(config_space_change))
+            .unwrap();
+        transport.finish_init();
+        drop(transport);
+
+        info!("[Virtio]: Found virtio-fs device with tag {:?}", device.tag);
+        register_device(device.tag.clone(), device);
+
+        Ok(())
+    }
+
+    /// Returns the tag, which is the name of the file system to be mounted.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Sends a request and waits for the reply.
+    ///
+    /// The reply is written to `reply`, whose length is the maximum length of the reply.
+    /// On success, the length of the reply is returned.
+    pub fn send_request(&self, request: &[u8], reply: &mut [u8]) -> ostd::Result<usize> {
+        let request_stream = new_dma_stream(request.len(), DmaDirection::ToDevice)?;
+        let request_slice = DmaStreamSlice::new(&request_stream, 0, request.len());
+        request_slice.write_bytes(0, request)?;
+        request_slice.sync()?;
+        let reply_stream = new_dma_stream(reply.len(), DmaDirection::FromDevice)?;
+        let reply_slice = DmaStreamSlice::new(&reply_stream, 0, reply.len());
+
+        let submitted_request = Arc::new(SubmittedRequest::new());
+        self.wait_queue.wait_until(|| {
+            let mut queue = self.request_queue.disable_irq().lock();
+            if queue.available_desc() < 2 {
+                return None;
+            }
+            let token = queue
+                .add_dma_buf(&[&request_slice], &[&reply_slice])
+                .expect("add queue failed");
+            self.submitted_requests
+                .disable_irq()
+                .lock()
+                .insert(token, submitted_request.clone());
+            if queue.should_notify() {
+                queue.notify();
+            }
+            Some(())
+        });
+
+        let reply_len = self
+            .wait_queue
+            .wait_until(|| *submitted_request.reply_len.disable_irq().lock());
+        let reply_len = (reply_len as usize).min(reply.len());
+        reply_slice.sync()?;
+        reply_slice.read_bytes(0, &mut reply[..reply_len])?;
+        Ok(reply_len)
+    }
+
+    /// Sends a request that has no reply (e.g., `FUSE_FORGET`) without waiting.
+    pub fn send_oneway(&self, request: &[u8]) -> ostd::Result<()> {
+        let request_stream = new_dma_stream(request.len(), DmaDirection::ToDevice)?;
+        let request_slice = DmaStreamSlice::new(&request_stream, 0, request.len());
+        request_slice.write_bytes(0, request)?;
+        request_slice.sync()?;
+
+        self.wait_queue.wait_until(|| {
+            let mut queue = self.hiprio_queue.disable_irq().lock();
+            if queue.available_desc() < 1 {
+                return None;
+            }
+            let token = queue
+                .add_dma_buf(&[&request_slice], &[])
+                .expect("add queue failed");
+            self.submitted_oneway
+                .disable_irq()
+                .lock()
+                .insert(token, request_stream.clone());
+            if queue.should_notify() {
+                queue.notify();
+            }
+            Some(())
+        });
+        Ok(())
+    }
+
+    fn handle_request_irq(&self) {
+        loop {
+            let (submitted_request, len) = {
+                let mut queue = self.request_queue.lock();
+                let Ok((token, len)) = queue.pop_used() else {
+                    break;
+                };
+                let Some(submitted_request) = self.submitted_requests.lock().remove(&token) else {
+                    warn!("Virtio-FS: unknown token {} in the request queue", token);
+                    continue;
+                };
+                (submitted_request, len)
+            };
+            *submitted_request.reply_len.lock() = Some(len);
+        }
+        self.wait_queue.wake_all();
+    }
+
+    fn handle_hiprio_irq(&self) {
+        loop {
+            let mut queue = self.hiprio_queue.lock();
+            let Ok((token, _)) = queue.pop_used() else {
+                break;
+            };
+            if self.submitted_oneway.lock().remove(&token).is_none() {
+                warn!(
+                    "Virtio-FS: unknown token {} in the high-priority queue",
+                    token
+                );
+            }
+        }
+        self.wait_queue.wake_all();
+    }
+}
+
+/// A request submitted to the request queue.
+#[derive(Debug)]
+struct SubmittedRequest {
+    /// The length of the reply written by the device, or `None` if the request is not used.
+    reply_len: SpinLock<Option<u32>>,
+}
+
+impl SubmittedRequest {
+    fn new() -> Self {
+        Self {
+            reply_len: SpinLock::new(None),
+        }
+    }
+}
+
+// TODO: Reuse the DMA buffers instead of allocating them for each request.
+fn new_dma_stream(len: usize, direction: DmaDirection) -> ostd::Result<DmaStream> {
+    let segment = FrameAllocOptions::new()
+        .zeroed(false)
+        .alloc_segment(len.div_ceil(PAGE_SIZE))?;
+    let stream =
+        DmaStream::map(segment.into(), direction, false).map_err(|_| ostd::Error::IoError)?;
+    Ok(stream)
+}
+
+fn config_space_change(_: &TrapFrame) {
+    debug!("Virtio-FS device configuration space change");
+}
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The virtio-fs device, which shares a directory of the host through the FUSE protocol.
+//!
+//! The driver only delivers the FUSE requests and the replies. The FUSE protocol itself
+//! is handled by the file system in the kernel.
+
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+
+use ostd::sync::SpinLock;
+use spin::Once;
+
+use self::device::FileSystemDevice;
+
+pub mod config;
+pub mod device;
+
+pub const DEVICE_NAME: &str = "Virtio-FS";
+
+/// Registers a virtio-fs device with its tag.
+pub fn register_device(tag: String, device: Arc<FileSystemDevice>) {
+    FS_DEVICE_TABLE
+        .get()
+        .unwrap()
+        .disable_irq()
+        .lock()
+        .insert(tag, device);
+}
+
+/// Gets the virtio-fs device with the tag.
+pub fn get_device(tag: &str) -> Option<Arc<FileSystemDevice>> {
+    let lock = FS_DEVICE_TABLE.get().unwrap().disable_irq().lock();
+    lock.get(tag).cloned()
+}
+
+pub fn all_devices() -> Vec<(String, Arc<FileSystemDevice>)> {
+    let fs_devs = FS_DEVICE_TABLE.get().unwrap().disable_irq().lock();
+    fs_devs
+        .iter()
+        .map(|(tag, device)| (tag.clone(), device.clone()))
+        .collect()
+}
+
+pub fn init() {
+    FS_DEVICE_TABLE.call_once(|| SpinLock::new(BTreeMap::new()));
+}
+
+static FS_DEVICE_TABLE: Once<SpinLock<BTreeMap<String, Arc<FileSystemDevice>>>> = Once::new();
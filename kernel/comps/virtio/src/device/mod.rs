@@ -6,6 +6,7 @@ use crate::queue::QueueError;
 
 pub mod block;
 pub mod console;
+pub mod filesystem;
 pub mod input;
 pub mod network;
 pub mod socket;
@@ -36,6 +37,7 @@ pub enum VirtioDeviceType {
     Pstore = 22,
     IOMMU = 23,
     Memory = 24,
+    FileSystem = 26,
 }
 
 #[derive(Debug)]
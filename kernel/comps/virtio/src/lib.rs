@@ -17,6 +17,7 @@ use component::{init_component, ComponentInitError};
 use device::{
     block::device::BlockDevice,
     console::device::ConsoleDevice,
+    filesystem::{self, device::FileSystemDevice},
     input::device::InputDevice,
     network::device::NetworkDevice,
     socket::{self, device::SocketDevice},
@@ -38,6 +39,8 @@ fn virtio_component_init() -> Result<(), ComponentInitError> {
     transport::init();
     // For vsock table static init
     socket::init();
+    // For virtio-fs table static init
+    filesystem::init();
     while let Some(mut transport) = pop_device_transport() {
         // Reset device
         transport
@@ -68,6 +71,7 @@ fn virtio_component_init() -> Result<(), ComponentInitError> {
             VirtioDeviceType::Network => NetworkDevice::init(transport),
             VirtioDeviceType::Console => ConsoleDevice::init(transport),
             VirtioDeviceType::Socket => SocketDevice::init(transport),
+            VirtioDeviceType::FileSystem => FileSystemDevice::init(transport),
             _ => {
                 warn!("[Virtio]: Found unimplemented device:{:?}", device_type);
                 Ok(())
@@ -103,6 +107,9 @@ fn negotiate_features(transport: &mut Box<dyn VirtioTransport>) {
         VirtioDeviceType::Input => InputDevice::negotiate_features(device_specified_features),
         VirtioDeviceType::Console => ConsoleDevice::negotiate_features(device_specified_features),
         VirtioDeviceType::Socket => SocketDevice::negotiate_features(device_specified_features),
+        VirtioDeviceType::FileSystem => {
+            FileSystemDevice::negotiate_features(device_specified_features)
+        }
         _ => device_specified_features,
     };
     let mut support_feature = Feature::from_bits_truncate(features);
@@ -112,3 +112,45 @@ pub mod udp {
         }
     }
 }
+
+pub mod raw {
+    /// An error returned by [`RawIpSocket::send`].
+    ///
+    /// [`RawIpSocket::send`]: crate::socket::RawIpSocket::send
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SendError {
+        BufferFull,
+        /// The packet is too large.
+        TooLarge,
+    }
+
+    /// An error returned by [`RawIpSocket::recv`].
+    ///
+    /// [`RawIpSocket::recv`]: crate::socket::RawIpSocket::recv
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvError {
+        /// No packets are available.
+        Exhausted,
+    }
+}
+
+pub mod packet {
+    /// An error returned by [`PacketSocket::send`].
+    ///
+    /// [`PacketSocket::send`]: crate::socket::PacketSocket::send
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum SendError {
+        BufferFull,
+        /// The frame is too large.
+        TooLarge,
+    }
+
+    /// An error returned by [`PacketSocket::recv`].
+    ///
+    /// [`PacketSocket::recv`]: crate::socket::PacketSocket::recv
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum RecvError {
+        /// No frames are available.
+        Exhausted,
+    }
+}
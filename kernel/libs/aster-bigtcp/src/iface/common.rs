@@ -14,8 +14,11 @@ use int_to_c_enum::TryFromInt;
 use ostd::sync::{SpinLock, SpinLockGuard};
 use smoltcp::{
     iface::{packet::Packet, Context},
-    phy::Device,
-    wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv4Packet},
+    phy::{Device, TxToken},
+    wire::{
+        EthernetAddress, EthernetFrame, HardwareAddress, IpAddress, IpEndpoint, IpListenEndpoint,
+        IpVersion, Ipv4Address, Ipv6Address, ETHERNET_HEADER_LEN,
+    },
 };
 
 use super::{
@@ -28,7 +31,7 @@ use super::{
 use crate::{
     errors::BindError,
     ext::Ext,
-    socket::{TcpListenerBg, UdpSocketBg},
+    socket::{PacketFrame, PacketSocketBg, PacketType, RawIpSocketBg, TcpListenerBg, UdpSocketBg},
     socket_table::SocketTable,
 };
 
@@ -37,10 +40,12 @@ pub struct IfaceCommon<E: Ext> {
     name: String,
     type_: InterfaceType,
     flags: InterfaceFlags,
+    hw_addr: Option<EthernetAddress>,
 
     interface: SpinLock<PollableIface<E>, BottomHalfDisabled>,
-    used_ports: SpinLock<BTreeMap<u16, PortState>, BottomHalfDisabled>,
+    used_ports: SpinLock<BTreeMap<(IpAddress, u16), PortState>, BottomHalfDisabled>,
     sockets: SpinLock<SocketTable<E>, BottomHalfDisabled>,
+    packet_sockets: SpinLock<Vec<Arc<PacketSocketBg<E>>>, BottomHalfDisabled>,
     sched_poll: E::ScheduleNextPoll,
 }
 
@@ -54,14 +59,22 @@ impl<E: Ext> IfaceCommon<E> {
     ) -> Self {
         let index = INTERFACE_INDEX_ALLOCATOR.fetch_add(1, Ordering::Relaxed);
 
+        let hw_addr = if let HardwareAddress::Ethernet(ether_addr) = interface.hardware_addr() {
+            Some(ether_addr)
+        } else {
+            None
+        };
+
         Self {
             index,
             name,
             type_,
             flags,
+            hw_addr,
             interface: SpinLock::new(PollableIface::new(interface)),
             used_ports: SpinLock::new(BTreeMap::new()),
             sockets: SpinLock::new(SocketTable::new()),
+            packet_sockets: SpinLock::new(Vec::new()),
             sched_poll,
         }
     }
@@ -82,6 +95,10 @@ impl<E: Ext> IfaceCommon<E> {
         self.flags
     }
 
+    pub(super) fn hw_addr(&self) -> Option<EthernetAddress> {
+        self.hw_addr
+    }
+
     pub(super) fn ipv4_addr(&self) -> Option<Ipv4Address> {
         self.interface.lock().ipv4_addr()
     }
@@ -90,6 +107,14 @@ impl<E: Ext> IfaceCommon<E> {
         self.interface.lock().prefix_len()
     }
 
+    pub(super) fn ipv6_addr(&self) -> Option<Ipv6Address> {
+        self.interface.lock().ipv6_addr()
+    }
+
+    pub(super) fn ipv6_prefix_len(&self) -> Option<u8> {
+        self.interface.lock().ipv6_prefix_len()
+    }
+
     pub(super) fn sched_poll(&self) -> &E::ScheduleNextPoll {
         &self.sched_poll
     }
@@ -100,7 +125,7 @@ impl<E: Ext> IfaceCommon<E> {
 // FIXME: This allocator is specific to each network namespace.
 pub static INTERFACE_INDEX_ALLOCATOR: AtomicU32 = AtomicU32::new(1);
 
-// Lock order: `interface` -> `sockets`
+// Lock order: `interface` -> `sockets` -> `packet_sockets`
 impl<E: Ext> IfaceCommon<E> {
     /// Acquires the lock to the interface.
     pub(crate) fn interface(&self) -> SpinLockGuard<'_, PollableIface<E>, BottomHalfDisabled> {
@@ -120,11 +145,13 @@ impl<E: Ext> IfaceCommon<E> {
     pub(super) fn bind(
         &self,
         iface: Arc<dyn Iface<E>>,
+        addr: IpAddress,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
-        let (port, can_reuse) = self.bind_port(config)?;
+        let (port, can_reuse) = self.bind_port(addr, config)?;
         Ok(BoundPort {
             iface,
+            addr,
             port,
             can_reuse: AtomicBool::new(can_reuse),
         })
@@ -136,11 +163,12 @@ impl<E: Ext> IfaceCommon<E> {
     ///
     /// See <https://en.wikipedia.org/wiki/Ephemeral_port>.
     fn alloc_ephemeral_port(
-        used_ports: &mut BTreeMap<u16, PortState>,
+        used_ports: &mut BTreeMap<(IpAddress, u16), PortState>,
+        addr: IpAddress,
         _can_reuse: bool,
     ) -> Option<u16> {
         for port in IP_LOCAL_PORT_START..=IP_LOCAL_PORT_END {
-            if let Entry::Vacant(..) = used_ports.entry(port) {
+            if let Entry::Vacant(..) = used_ports.entry((addr, port)) {
                 return Some(port);
             }
         }
@@ -151,20 +179,20 @@ impl<E: Ext> IfaceCommon<E> {
         None
     }
 
-    fn bind_port(&self, config: BindPortConfig) -> Result<(u16, bool), BindError> {
+    fn bind_port(&self, addr: IpAddress, config: BindPortConfig) -> Result<(u16, bool), BindError> {
         let mut used_ports = self.used_ports.lock();
         let config_can_reuse = config.can_reuse();
 
         let port = if let Some(port) = config.port() {
             port
         } else {
-            match Self::alloc_ephemeral_port(&mut used_ports, config_can_reuse) {
+            match Self::alloc_ephemeral_port(&mut used_ports, addr, config_can_reuse) {
                 Some(port) => port,
                 None => return Err(BindError::Exhausted),
             }
         };
 
-        if let Some(port_state) = used_ports.get_mut(&port) {
+        if let Some(port_state) = used_ports.get_mut(&(addr, port)) {
             // FIXME: If the socket is not a backlog socket,
             // we should check whether there is a listening socket on the port.
             // If there is, the socket cannot be bound to that port.
@@ -180,16 +208,16 @@ impl<E: Ext> IfaceCommon<E> {
             }
         } else {
             let port_state = PortState::new(config_can_reuse);
-            used_ports.insert(port, port_state);
+            used_ports.insert((addr, port), port_state);
         };
 
         Ok((port, config_can_reuse))
     }
 
     /// Releases the port so that it can be used again.
-    fn release_port(&self, port: u16, can_reuse: bool) {
+    fn release_port(&self, addr: IpAddress, port: u16, can_reuse: bool) {
         let mut used_ports = self.used_ports.lock();
-        if let Entry::Occupied(mut entry) = used_ports.entry(port) {
+        if let Entry::Occupied(mut entry) = used_ports.entry((addr, port)) {
             let port_state = entry.get_mut();
             port_state.nsocket -= 1;
             if can_reuse {
@@ -208,6 +236,11 @@ impl<E: Ext> IfaceCommon<E> {
         sockets.insert_udp_socket(socket);
     }
 
+    pub(crate) fn register_raw_socket(&self, socket: Arc<RawIpSocketBg<E>>) {
+        let mut sockets = self.sockets.lock();
+        sockets.insert_raw_socket(socket);
+    }
+
     pub(crate) fn remove_tcp_listener(&self, socket: &Arc<TcpListenerBg<E>>) {
         let mut sockets = self.sockets.lock();
         let removed = sockets.remove_listener(socket.listener_key());
@@ -219,6 +252,180 @@ impl<E: Ext> IfaceCommon<E> {
         let removed = sockets.remove_udp_socket(socket);
         debug_assert!(removed.is_some());
     }
+
+    pub(crate) fn remove_raw_socket(&self, socket: &Arc<RawIpSocketBg<E>>) {
+        let mut sockets = self.sockets.lock();
+        let removed = sockets.remove_raw_socket(socket);
+        debug_assert!(removed.is_some());
+    }
+
+    pub(crate) fn register_packet_socket(&self, socket: Arc<PacketSocketBg<E>>) {
+        let mut packet_sockets = self.packet_sockets.lock();
+        debug_assert!(!packet_sockets
+            .iter()
+            .any(|packet_socket| Arc::ptr_eq(packet_socket, &socket)));
+        packet_sockets.push(socket);
+    }
+
+    pub(crate) fn remove_packet_socket(&self, socket: &Arc<PacketSocketBg<E>>) {
+        let mut packet_sockets = self.packet_sockets.lock();
+        let index = packet_sockets
+            .iter()
+            .position(|packet_socket| Arc::ptr_eq(packet_socket, socket));
+        debug_assert!(index.is_some());
+        if let Some(index) = index {
+            packet_sockets.swap_remove(index);
+        }
+    }
+}
+
+/// The information of a link-layer frame that is used to deliver the frame to packet sockets.
+struct FrameInfo {
+    protocol: u16,
+    pkt_type: PacketType,
+    src_hw_addr: Option<EthernetAddress>,
+    header_len: usize,
+}
+
+/// The link-layer protocol of IPv4 packets.
+const ETH_P_IP: u16 = 0x0800;
+/// The link-layer protocol of IPv6 packets.
+const ETH_P_IPV6: u16 = 0x86DD;
+
+impl<E: Ext> IfaceCommon<E> {
+    /// Delivers a copy of a link-layer frame to packet sockets.
+    ///
+    /// This should be called for every frame that the iface sends or receives.
+    pub(super) fn deliver_frame(&self, frame: &[u8], is_outgoing: bool) {
+        let packet_sockets = self.packet_sockets.lock();
+        self.deliver_frame_to(&packet_sockets, frame, is_outgoing, None);
+    }
+
+    fn deliver_frame_to(
+        &self,
+        packet_sockets: &[Arc<PacketSocketBg<E>>],
+        frame: &[u8],
+        is_outgoing: bool,
+        sender: Option<&Arc<PacketSocketBg<E>>>,
+    ) {
+        if packet_sockets.is_empty() {
+            return;
+        }
+
+        let Some(info) = self.parse_frame(frame, is_outgoing) else {
+            return;
+        };
+
+        for socket in packet_sockets {
+            // Similar to Linux, a frame is not looped back to the socket that sends it.
+            if sender.is_some_and(|sender| Arc::ptr_eq(sender, socket)) {
+                continue;
+            }
+
+            socket.process(info.protocol, info.pkt_type, || PacketFrame {
+                protocol: info.protocol,
+                pkt_type: info.pkt_type,
+                src_hw_addr: info.src_hw_addr,
+                header_len: info.header_len,
+                data: frame.to_vec(),
+            });
+        }
+    }
+
+    fn parse_frame(&self, frame: &[u8], is_outgoing: bool) -> Option<FrameInfo> {
+        let Some(hw_addr) = self.hw_addr else {
+            // There is no link-layer header. Infer the protocol from the IP version.
+            let protocol = match IpVersion::of_packet(frame).ok()? {
+                IpVersion::Ipv4 => ETH_P_IP,
+                IpVersion::Ipv6 => ETH_P_IPV6,
+            };
+            let pkt_type = if is_outgoing {
+                PacketType::Outgoing
+            } else {
+                PacketType::Host
+            };
+
+            return Some(FrameInfo {
+                protocol,
+                pkt_type,
+                src_hw_addr: None,
+                header_len: 0,
+            });
+        };
+
+        let ether_frame = EthernetFrame::new_checked(frame).ok()?;
+        let dst_addr = ether_frame.dst_addr();
+
+        let pkt_type = if is_outgoing {
+            PacketType::Outgoing
+        } else if dst_addr.is_broadcast() {
+            PacketType::Broadcast
+        } else if dst_addr.is_multicast() {
+            PacketType::Multicast
+        } else if dst_addr == hw_addr {
+            PacketType::Host
+        } else {
+            PacketType::OtherHost
+        };
+
+        Some(FrameInfo {
+            protocol: u16::from(ether_frame.ethertype()),
+            pkt_type,
+            src_hw_addr: Some(ether_frame.src_addr()),
+            header_len: ETHERNET_HEADER_LEN,
+        })
+    }
+
+    /// Sends the frames queued in packet sockets and returns whether any frames are sent.
+    fn dispatch_packet_sockets<D: Device + ?Sized>(&self, device: &mut D) -> bool {
+        let packet_sockets = self.packet_sockets.lock();
+        let mut did_something = false;
+
+        for socket in packet_sockets.iter() {
+            while socket.need_dispatch() {
+                let Some(tx_token) = device.transmit(get_network_timestamp()) else {
+                    return did_something;
+                };
+                let Some(frame) = socket.dequeue_tx() else {
+                    break;
+                };
+
+                tx_token.consume(frame.len(), |buffer| buffer.copy_from_slice(&frame));
+                self.deliver_frame_to(&packet_sockets, &frame, true, Some(socket));
+
+                did_something = true;
+            }
+        }
+
+        did_something
+    }
+}
+
+/// A [`TxToken`] that delivers a copy of the outgoing frame to packet sockets.
+pub(super) struct CaptureTxToken<'a, T, E: Ext> {
+    inner: T,
+    common: &'a IfaceCommon<E>,
+}
+
+impl<'a, T, E: Ext> CaptureTxToken<'a, T, E> {
+    pub(super) fn new(inner: T, common: &'a IfaceCommon<E>) -> Self {
+        Self { inner, common }
+    }
+}
+
+impl<T: TxToken, E: Ext> TxToken for CaptureTxToken<'_, T, E> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let common = self.common;
+
+        self.inner.consume(len, |buffer| {
+            let result = f(buffer);
+            common.deliver_frame(buffer, true);
+            result
+        })
+    }
 }
 
 impl<E: Ext> IfaceCommon<E> {
@@ -234,7 +441,7 @@ impl<E: Ext> IfaceCommon<E> {
             &'pkt [u8],
             &'cx mut Context,
             D::TxToken<'tx>,
-            Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
+            Option<(&'pkt [u8], D::TxToken<'tx>)>,
         >,
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
@@ -248,6 +455,8 @@ impl<E: Ext> IfaceCommon<E> {
         context.poll_ingress(device, &mut process_phy, &mut dispatch_phy);
         context.poll_egress(device, &mut dispatch_phy);
 
+        let did_dispatch_frames = self.dispatch_packet_sockets(device);
+
         // Insert new connections and remove dead connections.
         for action in socket_actions.into_iter() {
             match action {
@@ -263,7 +472,16 @@ impl<E: Ext> IfaceCommon<E> {
 
         // Note that only TCP connections can have timers set, so as far as the time to poll is
         // concerned, we only need to consider TCP connections.
-        interface.next_poll_at_ms()
+        let next_poll_at_ms = interface.next_poll_at_ms();
+
+        // Frames sent by packet sockets may be looped back to the iface itself (e.g., if the iface
+        // is a loopback iface). So we need to poll again as soon as possible to receive them.
+        if did_dispatch_frames {
+            let now_ms = get_network_timestamp().total_millis() as u64;
+            return Some(next_poll_at_ms.map_or(now_ms, |next_ms| next_ms.min(now_ms)));
+        }
+
+        next_poll_at_ms
     }
 }
 
@@ -274,6 +492,7 @@ impl<E: Ext> IfaceCommon<E> {
 // FIXME: TCP and UDP ports are independent. Find a way to track the protocol here.
 pub struct BoundPort<E: Ext> {
     iface: Arc<dyn Iface<E>>,
+    addr: IpAddress,
     port: u16,
     can_reuse: AtomicBool,
 }
@@ -289,13 +508,37 @@ impl<E: Ext> BoundPort<E> {
         self.port
     }
 
+    /// Returns the bound IP address.
+    pub fn addr(&self) -> IpAddress {
+        self.addr
+    }
+
     /// Returns the bound endpoint.
+    ///
+    /// If the port is bound to the unspecified address, the address of the endpoint is also
+    /// unspecified.
     pub fn endpoint(&self) -> Option<IpEndpoint> {
-        let ip_addr = {
-            let ipv4_addr = self.iface().ipv4_addr()?;
-            IpAddress::Ipv4(ipv4_addr)
-        };
-        Some(IpEndpoint::new(ip_addr, self.port))
+        if !self.addr.is_unspecified() && !self.iface().has_ip_addr(&self.addr) {
+            return None;
+        }
+        Some(IpEndpoint::new(self.addr, self.port))
+    }
+
+    /// Returns the endpoint that the socket should listen at.
+    ///
+    /// Unlike [`Self::endpoint`], the address is `None` if the port is bound to the unspecified
+    /// address, so that packets destined for any address of the iface will be accepted.
+    pub fn listen_endpoint(&self) -> Option<IpListenEndpoint> {
+        let endpoint = self.endpoint()?;
+        Some(IpListenEndpoint {
+            addr: (!endpoint.addr.is_unspecified()).then_some(endpoint.addr),
+            port: endpoint.port,
+        })
+    }
+
+    /// Returns whether the port can be reused.
+    pub fn can_reuse(&self) -> bool {
+        self.can_reuse.load(Ordering::Relaxed)
     }
 
     /// Sets whether the port can be reused.
@@ -307,7 +550,7 @@ impl<E: Ext> BoundPort<E> {
             return;
         }
 
-        if let Some(port_state) = used_ports.get_mut(&self.port) {
+        if let Some(port_state) = used_ports.get_mut(&(self.addr, self.port)) {
             if can_reuse {
                 port_state.nreuse += 1;
             } else {
@@ -323,7 +566,7 @@ impl<E: Ext> Drop for BoundPort<E> {
     fn drop(&mut self) {
         self.iface
             .common()
-            .release_port(self.port, *self.can_reuse.get_mut());
+            .release_port(self.addr, self.port, *self.can_reuse.get_mut());
     }
 }
 
@@ -2,7 +2,7 @@
 
 use alloc::sync::Arc;
 
-use smoltcp::wire::Ipv4Address;
+use smoltcp::wire::{EthernetAddress, IpAddress, Ipv4Address, Ipv6Address};
 
 use super::{port::BindPortConfig, BoundPort, InterfaceFlags, InterfaceType};
 use crate::{errors::BindError, ext::Ext};
@@ -27,6 +27,10 @@ impl<E: Ext> dyn Iface<E> {
     /// After binding the socket to the iface, the iface will handle all packets to and from the
     /// socket.
     ///
+    /// The socket will be bound to `addr`, which should be one of the addresses owned by the iface
+    /// (see [`Self::ipv4_addr`] and [`Self::ipv6_addr`]). Ports are allocated independently for
+    /// each address.
+    ///
     /// If [`BindPortConfig::Ephemeral`] is specified, the iface will pick up an ephemeral port for
     /// the socket.
     ///
@@ -35,10 +39,11 @@ impl<E: Ext> dyn Iface<E> {
     /// <https://github.com/smoltcp-rs/smoltcp/issues/779>.
     pub fn bind(
         self: &Arc<Self>,
+        addr: IpAddress,
         config: BindPortConfig,
     ) -> core::result::Result<BoundPort<E>, BindError> {
         let common = self.common();
-        common.bind(self.clone(), config)
+        common.bind(self.clone(), addr, config)
     }
 
     /// Returns the interface index.
@@ -63,6 +68,11 @@ impl<E: Ext> dyn Iface<E> {
         self.common().flags()
     }
 
+    /// Returns the hardware address of the iface, if the link layer has one.
+    pub fn hw_addr(&self) -> Option<EthernetAddress> {
+        self.common().hw_addr()
+    }
+
     /// Gets the IPv4 address of the iface, if any.
     ///
     /// FIXME: One iface may have multiple IPv4 addresses.
@@ -78,6 +88,30 @@ impl<E: Ext> dyn Iface<E> {
         self.common().prefix_len()
     }
 
+    /// Gets the IPv6 address of the iface, if any.
+    ///
+    /// FIXME: One iface may have multiple IPv6 addresses (e.g., a link-local address and a global
+    /// address).
+    pub fn ipv6_addr(&self) -> Option<Ipv6Address> {
+        self.common().ipv6_addr()
+    }
+
+    /// Retrieves the prefix length of the interface's IPv6 address.
+    ///
+    /// Both [`Self::ipv6_addr`] and this method will either return `Some(_)`
+    /// or both will return `None`.
+    pub fn ipv6_prefix_len(&self) -> Option<u8> {
+        self.common().ipv6_prefix_len()
+    }
+
+    /// Returns whether the IP address is owned by the iface.
+    pub fn has_ip_addr(&self, addr: &IpAddress) -> bool {
+        match addr {
+            IpAddress::Ipv4(ipv4_addr) => self.ipv4_addr() == Some(*ipv4_addr),
+            IpAddress::Ipv6(ipv6_addr) => self.ipv6_addr() == Some(*ipv6_addr),
+        }
+    }
+
     /// Returns a reference to the associated [`ScheduleNextPoll`].
     pub fn sched_poll(&self) -> &E::ScheduleNextPoll {
         self.common().sched_poll()
@@ -5,11 +5,16 @@ use alloc::{collections::btree_map::BTreeMap, string::String, sync::Arc};
 use aster_softirq::BottomHalfDisabled;
 use ostd::sync::SpinLock;
 use smoltcp::{
-    iface::{packet::Packet, Config, Context},
+    iface::{
+        packet::{IpPayload, Packet},
+        Config, Context,
+    },
     phy::{Device, DeviceCapabilities, TxToken},
     wire::{
         self, ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetFrame, EthernetProtocol,
-        EthernetRepr, IpAddress, Ipv4Address, Ipv4AddressExt, Ipv4Cidr, Ipv4Packet,
+        EthernetRepr, Icmpv6Packet, Icmpv6Repr, IpAddress, IpProtocol, Ipv4Address, Ipv4AddressExt,
+        Ipv4Cidr, Ipv6Address, Ipv6Cidr, Ipv6Packet, Ipv6Repr, NdiscNeighborFlags, NdiscRepr,
+        RawHardwareAddress,
     },
 };
 
@@ -17,7 +22,7 @@ use crate::{
     device::{NotifyDevice, WithDevice},
     ext::Ext,
     iface::{
-        common::{IfaceCommon, InterfaceType},
+        common::{CaptureTxToken, IfaceCommon, InterfaceType},
         iface::internal::IfaceInternal,
         time::get_network_timestamp,
         Iface, InterfaceFlags, ScheduleNextPoll,
@@ -29,14 +34,28 @@ pub struct EtherIface<D, E: Ext> {
     common: IfaceCommon<E>,
     ether_addr: EthernetAddress,
     arp_table: SpinLock<BTreeMap<Ipv4Address, EthernetAddress>, BottomHalfDisabled>,
+    neighbor_table: SpinLock<BTreeMap<Ipv6Address, EthernetAddress>, BottomHalfDisabled>,
+}
+
+/// A link-layer packet that is generated to resolve or advertise addresses.
+enum NeighborRepr {
+    /// An ARP packet for IPv4.
+    Arp(ArpRepr),
+    /// A Neighbor Discovery packet for IPv6.
+    ///
+    /// The Neighbor Discovery Protocol is built on ICMPv6, so the IP header is included here.
+    Ndisc(EthernetAddress, Ipv6Repr, NdiscRepr<'static>),
 }
 
 impl<D: WithDevice, E: Ext> EtherIface<D, E> {
+    #[expect(clippy::too_many_arguments)]
     pub fn new(
         driver: D,
         ether_addr: EthernetAddress,
         ip_cidr: Ipv4Cidr,
         gateway: Ipv4Address,
+        ipv6_cidr: Ipv6Cidr,
+        ipv6_gateway: Ipv6Address,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         flags: InterfaceFlags,
@@ -49,12 +68,17 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             interface.update_ip_addrs(|ip_addrs| {
                 debug_assert!(ip_addrs.is_empty());
                 ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                ip_addrs.push(wire::IpCidr::Ipv6(ipv6_cidr)).unwrap();
             });
             interface
                 .routes_mut()
                 .add_default_ipv4_route(gateway)
                 .unwrap();
             interface
+                .routes_mut()
+                .add_default_ipv6_route(ipv6_gateway)
+                .unwrap();
+            interface
         });
 
         let common = IfaceCommon::new(name, InterfaceType::ETHER, flags, interface, sched_poll);
@@ -64,6 +88,7 @@ impl<D: WithDevice, E: Ext> EtherIface<D, E> {
             common,
             ether_addr,
             arp_table: SpinLock::new(BTreeMap::new()),
+            neighbor_table: SpinLock::new(BTreeMap::new()),
         })
     }
 }
@@ -83,7 +108,9 @@ where
             let next_poll = self.common.poll(
                 &mut *device,
                 |data, iface_cx, tx_token| self.process(data, iface_cx, tx_token),
-                |pkt, iface_cx, tx_token| self.dispatch(pkt, iface_cx, tx_token),
+                |pkt, iface_cx, tx_token| {
+                    self.dispatch(pkt, iface_cx, CaptureTxToken::new(tx_token, &self.common))
+                },
             );
             device.notify_poll_end();
             self.common.sched_poll().schedule_next_poll(next_poll);
@@ -102,40 +129,54 @@ impl<D, E: Ext> EtherIface<D, E> {
         data: &'pkt [u8],
         iface_cx: &mut Context,
         tx_token: T,
-    ) -> Option<(Ipv4Packet<&'pkt [u8]>, T)> {
-        match self.parse_ip_or_process_arp(data, iface_cx) {
+    ) -> Option<(&'pkt [u8], T)> {
+        self.common.deliver_frame(data, false);
+
+        match self.parse_ip_or_process_neighbor(data, iface_cx) {
             Ok(pkt) => Some((pkt, tx_token)),
-            Err(Some(arp)) => {
-                Self::emit_arp(&arp, tx_token);
+            Err(Some(neighbor)) => {
+                let tx_token = CaptureTxToken::new(tx_token, &self.common);
+                self.emit_neighbor(&neighbor, &iface_cx.caps, tx_token);
                 None
             }
             Err(None) => None,
         }
     }
 
-    fn parse_ip_or_process_arp<'pkt>(
+    fn parse_ip_or_process_neighbor<'pkt>(
         &self,
         data: &'pkt [u8],
         iface_cx: &mut Context,
-    ) -> Result<Ipv4Packet<&'pkt [u8]>, Option<ArpRepr>> {
+    ) -> Result<&'pkt [u8], Option<NeighborRepr>> {
         // Parse the Ethernet header. Ignore the packet if the header is ill-formed.
         let frame = EthernetFrame::new_checked(data).map_err(|_| None)?;
         let repr = EthernetRepr::parse(&frame).map_err(|_| None)?;
 
-        // Ignore the Ethernet frame if it is not sent to us.
-        if !repr.dst_addr.is_broadcast() && repr.dst_addr != self.ether_addr {
+        // Ignore the Ethernet frame if it is not sent to us. Note that IPv6 relies on multicast
+        // frames to resolve addresses, so multicast frames are accepted as well.
+        if !repr.dst_addr.is_broadcast()
+            && !repr.dst_addr.is_multicast()
+            && repr.dst_addr != self.ether_addr
+        {
             return Err(None);
         }
 
         // Ignore the Ethernet frame if the protocol is not supported.
         match repr.ethertype {
-            EthernetProtocol::Ipv4 => {
-                Ok(Ipv4Packet::new_checked(frame.payload()).map_err(|_| None)?)
+            EthernetProtocol::Ipv4 => Ok(frame.payload()),
+            EthernetProtocol::Ipv6 => {
+                let pkt = Ipv6Packet::new_checked(frame.payload()).map_err(|_| None)?;
+                match self.parse_ndisc(&pkt, iface_cx) {
+                    Some(ndisc) => Err(self.process_ndisc(repr.src_addr, &ndisc, iface_cx).map(
+                        |(ip_repr, ndisc)| NeighborRepr::Ndisc(repr.src_addr, ip_repr, ndisc),
+                    )),
+                    None => Ok(frame.payload()),
+                }
             }
             EthernetProtocol::Arp => {
                 let pkt = ArpPacket::new_checked(frame.payload()).map_err(|_| None)?;
                 let arp = ArpRepr::parse(&pkt).map_err(|_| None)?;
-                Err(self.process_arp(&arp, iface_cx))
+                Err(self.process_arp(&arp, iface_cx).map(NeighborRepr::Arp))
             }
             _ => Err(None),
         }
@@ -197,22 +238,128 @@ impl<D, E: Ext> EtherIface<D, E> {
         }
     }
 
+    /// Parses the Neighbor Discovery message in the IPv6 packet, if any.
+    fn parse_ndisc<'pkt>(
+        &self,
+        pkt: &Ipv6Packet<&'pkt [u8]>,
+        iface_cx: &Context,
+    ) -> Option<(Ipv6Repr, NdiscRepr<'pkt>)> {
+        let ip_repr = Ipv6Repr::parse(pkt).ok()?;
+        if ip_repr.next_header != IpProtocol::Icmpv6 {
+            return None;
+        }
+
+        let icmp_pkt = Icmpv6Packet::new_checked(pkt.payload()).ok()?;
+        let icmp_repr = Icmpv6Repr::parse(
+            &ip_repr.src_addr,
+            &ip_repr.dst_addr,
+            &icmp_pkt,
+            &iface_cx.checksum_caps(),
+        )
+        .ok()?;
+
+        match icmp_repr {
+            Icmpv6Repr::Ndisc(ndisc) => Some((ip_repr, ndisc)),
+            _ => None,
+        }
+    }
+
+    fn process_ndisc(
+        &self,
+        src_ether_addr: EthernetAddress,
+        (ip_repr, ndisc_repr): &(Ipv6Repr, NdiscRepr),
+        iface_cx: &mut Context,
+    ) -> Option<(Ipv6Repr, NdiscRepr<'static>)> {
+        // Neighbor Discovery messages must not be forwarded by routers. See
+        // <https://datatracker.ietf.org/doc/html/rfc4861#section-7.1.1>.
+        if ip_repr.hop_limit != NDISC_HOP_LIMIT || !src_ether_addr.is_unicast() {
+            return None;
+        }
+
+        match ndisc_repr {
+            NdiscRepr::NeighborAdvert {
+                target_addr,
+                lladdr,
+                ..
+            } => {
+                // Ignore the NDISC packet if the target address is not unicast or not local.
+                if target_addr.is_multicast()
+                    || !iface_cx.in_same_network(&IpAddress::Ipv6(*target_addr))
+                {
+                    return None;
+                }
+
+                // Insert the mapping between the Ethernet address and the IP address.
+                //
+                // TODO: Remove the mapping if it expires.
+                let ether_addr = lladdr
+                    .and_then(parse_ether_lladdr)
+                    .unwrap_or(src_ether_addr);
+                self.neighbor_table.lock().insert(*target_addr, ether_addr);
+
+                None
+            }
+            NdiscRepr::NeighborSolicit {
+                target_addr,
+                lladdr,
+            } => {
+                // Ignore the NDISC packet if we do not own the target address.
+                if iface_cx.ipv6_addr().is_none_or(|addr| addr != *target_addr) {
+                    return None;
+                }
+
+                // Duplicate address detection is not supported. Ignore probes from hosts that do
+                // not have an address yet.
+                if ip_repr.src_addr.is_unspecified() {
+                    return None;
+                }
+
+                // The soliciting host has told us its Ethernet address, so we can remember it.
+                let ether_addr = lladdr
+                    .and_then(parse_ether_lladdr)
+                    .unwrap_or(src_ether_addr);
+                self.neighbor_table
+                    .lock()
+                    .insert(ip_repr.src_addr, ether_addr);
+
+                let ndisc_repr = NdiscRepr::NeighborAdvert {
+                    flags: NdiscNeighborFlags::SOLICITED | NdiscNeighborFlags::OVERRIDE,
+                    target_addr: *target_addr,
+                    lladdr: Some(RawHardwareAddress::from_bytes(self.ether_addr.as_bytes())),
+                };
+                let ip_repr = Ipv6Repr {
+                    src_addr: *target_addr,
+                    dst_addr: ip_repr.src_addr,
+                    next_header: IpProtocol::Icmpv6,
+                    payload_len: Icmpv6Repr::Ndisc(ndisc_repr).buffer_len(),
+                    hop_limit: NDISC_HOP_LIMIT,
+                };
+
+                Some((ip_repr, ndisc_repr))
+            }
+            _ => None,
+        }
+    }
+
     fn dispatch<T: TxToken>(&self, pkt: &Packet, iface_cx: &mut Context, tx_token: T) {
-        match self.resolve_ether_or_generate_arp(pkt, iface_cx) {
+        match self.resolve_ether_or_generate_neighbor(pkt, iface_cx) {
             Ok(ether) => Self::emit_ip(&ether, pkt, &iface_cx.caps, tx_token),
-            Err(Some(arp)) => Self::emit_arp(&arp, tx_token),
+            Err(Some(neighbor)) => self.emit_neighbor(&neighbor, &iface_cx.caps, tx_token),
             Err(None) => (),
         }
     }
 
-    fn resolve_ether_or_generate_arp(
+    fn resolve_ether_or_generate_neighbor(
         &self,
         pkt: &Packet,
         iface_cx: &mut Context,
-    ) -> Result<EthernetRepr, Option<ArpRepr>> {
+    ) -> Result<EthernetRepr, Option<NeighborRepr>> {
         // Resolve the next-hop IP address.
         let next_hop_ip = match iface_cx.route(&pkt.ip_repr().dst_addr(), iface_cx.now()) {
             Some(IpAddress::Ipv4(next_hop_ip)) => next_hop_ip,
+            Some(IpAddress::Ipv6(next_hop_ip)) => {
+                return self.resolve_ether_or_generate_ndisc(next_hop_ip, iface_cx);
+            }
             None => return Err(None),
         };
 
@@ -225,13 +372,13 @@ impl<D, E: Ext> EtherIface<D, E> {
             // If the next-hop Ethernet address cannot be resolved, we drop the original packet and
             // send an ARP packet instead. The upper layer should be responsible for detecting the
             // packet loss and retrying later to see if the Ethernet address is ready.
-            return Err(Some(ArpRepr::EthernetIpv4 {
+            return Err(Some(NeighborRepr::Arp(ArpRepr::EthernetIpv4 {
                 operation: ArpOperation::Request,
                 source_hardware_addr: self.ether_addr,
                 source_protocol_addr: iface_cx.ipv4_addr().unwrap_or(Ipv4Address::UNSPECIFIED),
                 target_hardware_addr: EthernetAddress::BROADCAST,
                 target_protocol_addr: next_hop_ip,
-            }));
+            })));
         };
 
         Ok(EthernetRepr {
@@ -241,6 +388,51 @@ impl<D, E: Ext> EtherIface<D, E> {
         })
     }
 
+    fn resolve_ether_or_generate_ndisc(
+        &self,
+        next_hop_ip: Ipv6Address,
+        iface_cx: &mut Context,
+    ) -> Result<EthernetRepr, Option<NeighborRepr>> {
+        // Resolve the next-hop Ethernet address.
+        let next_hop_ether = if next_hop_ip.is_multicast() {
+            multicast_ether_addr(&next_hop_ip)
+        } else if let Some(next_hop_ether) = self.neighbor_table.lock().get(&next_hop_ip) {
+            *next_hop_ether
+        } else {
+            // Similar to ARP, we drop the original packet and send a Neighbor Solicitation message
+            // to the solicited-node multicast address instead. See
+            // <https://datatracker.ietf.org/doc/html/rfc4861#section-7.2.2>.
+            let Some(src_addr) = iface_cx.ipv6_addr() else {
+                return Err(None);
+            };
+            let dst_addr = solicited_node_addr(&next_hop_ip);
+
+            let ndisc_repr = NdiscRepr::NeighborSolicit {
+                target_addr: next_hop_ip,
+                lladdr: Some(RawHardwareAddress::from_bytes(self.ether_addr.as_bytes())),
+            };
+            let ip_repr = Ipv6Repr {
+                src_addr,
+                dst_addr,
+                next_header: IpProtocol::Icmpv6,
+                payload_len: Icmpv6Repr::Ndisc(ndisc_repr).buffer_len(),
+                hop_limit: NDISC_HOP_LIMIT,
+            };
+
+            return Err(Some(NeighborRepr::Ndisc(
+                multicast_ether_addr(&dst_addr),
+                ip_repr,
+                ndisc_repr,
+            )));
+        };
+
+        Ok(EthernetRepr {
+            src_addr: self.ether_addr,
+            dst_addr: next_hop_ether,
+            ethertype: EthernetProtocol::Ipv6,
+        })
+    }
+
     /// Consumes the token and emits an IP packet.
     fn emit_ip<T: TxToken>(
         ether_repr: &EthernetRepr,
@@ -265,6 +457,28 @@ impl<D, E: Ext> EtherIface<D, E> {
         );
     }
 
+    /// Consumes the token and emits an ARP packet or a Neighbor Discovery packet.
+    fn emit_neighbor<T: TxToken>(
+        &self,
+        neighbor_repr: &NeighborRepr,
+        caps: &DeviceCapabilities,
+        tx_token: T,
+    ) {
+        match neighbor_repr {
+            NeighborRepr::Arp(arp_repr) => Self::emit_arp(arp_repr, tx_token),
+            NeighborRepr::Ndisc(dst_ether_addr, ip_repr, ndisc_repr) => {
+                let ether_repr = EthernetRepr {
+                    src_addr: self.ether_addr,
+                    dst_addr: *dst_ether_addr,
+                    ethertype: EthernetProtocol::Ipv6,
+                };
+                let pkt =
+                    Packet::new_ipv6(*ip_repr, IpPayload::Icmpv6(Icmpv6Repr::Ndisc(*ndisc_repr)));
+                Self::emit_ip(&ether_repr, &pkt, caps, tx_token);
+            }
+        }
+    }
+
     /// Consumes the token and emits an ARP packet.
     fn emit_arp<T: TxToken>(arp_repr: &ArpRepr, tx_token: T) {
         let ether_repr = match arp_repr {
@@ -289,3 +503,43 @@ impl<D, E: Ext> EtherIface<D, E> {
         });
     }
 }
+
+/// The hop limit that must be used by all Neighbor Discovery messages.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc4861#section-4>.
+const NDISC_HOP_LIMIT: u8 = 255;
+
+/// Parses the link-layer address option of a Neighbor Discovery message.
+fn parse_ether_lladdr(lladdr: RawHardwareAddress) -> Option<EthernetAddress> {
+    if lladdr.len() != 6 {
+        return None;
+    }
+
+    let ether_addr = EthernetAddress::from_bytes(lladdr.as_bytes());
+    ether_addr.is_unicast().then_some(ether_addr)
+}
+
+/// Returns the solicited-node multicast address of an IPv6 address.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc4291#section-2.7.1>.
+fn solicited_node_addr(addr: &Ipv6Address) -> Ipv6Address {
+    let octets = addr.octets();
+    Ipv6Address::new(
+        0xff02,
+        0,
+        0,
+        0,
+        0,
+        1,
+        0xff00 | octets[13] as u16,
+        u16::from_be_bytes([octets[14], octets[15]]),
+    )
+}
+
+/// Maps an IPv6 multicast address to an Ethernet multicast address.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc2464#section-7>.
+fn multicast_ether_addr(addr: &Ipv6Address) -> EthernetAddress {
+    let octets = addr.octets();
+    EthernetAddress([0x33, 0x33, octets[12], octets[13], octets[14], octets[15]])
+}
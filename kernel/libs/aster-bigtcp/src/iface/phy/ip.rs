@@ -5,14 +5,14 @@ use alloc::{string::String, sync::Arc};
 use smoltcp::{
     iface::Config,
     phy::{Device, TxToken},
-    wire::{self, Ipv4Cidr, Ipv4Packet},
+    wire::{self, Ipv4Cidr, Ipv6Cidr},
 };
 
 use crate::{
     device::WithDevice,
     ext::Ext,
     iface::{
-        common::{IfaceCommon, InterfaceFlags, InterfaceType},
+        common::{CaptureTxToken, IfaceCommon, InterfaceFlags, InterfaceType},
         iface::internal::IfaceInternal,
         time::get_network_timestamp,
         Iface, ScheduleNextPoll,
@@ -28,6 +28,7 @@ impl<D: WithDevice, E: Ext> IpIface<D, E> {
     pub fn new(
         driver: D,
         ip_cidr: Ipv4Cidr,
+        ipv6_cidr: Ipv6Cidr,
         name: String,
         sched_poll: E::ScheduleNextPoll,
         type_: InterfaceType,
@@ -41,6 +42,7 @@ impl<D: WithDevice, E: Ext> IpIface<D, E> {
             interface.update_ip_addrs(|ip_addrs| {
                 debug_assert!(ip_addrs.is_empty());
                 ip_addrs.push(wire::IpCidr::Ipv4(ip_cidr)).unwrap();
+                ip_addrs.push(wire::IpCidr::Ipv6(ipv6_cidr)).unwrap();
             });
             interface
         });
@@ -62,8 +64,12 @@ impl<D: WithDevice + 'static, E: Ext> Iface<E> for IpIface<D, E> {
         self.driver.with(|device| {
             let next_poll = self.common.poll(
                 device,
-                |data, _iface_cx, tx_token| Some((Ipv4Packet::new_checked(data).ok()?, tx_token)),
+                |data, _iface_cx, tx_token| {
+                    self.common.deliver_frame(data, false);
+                    Some((data, tx_token))
+                },
                 |pkt, iface_cx, tx_token| {
+                    let tx_token = CaptureTxToken::new(tx_token, &self.common);
                     let ip_repr = pkt.ip_repr();
                     tx_token.consume(ip_repr.buffer_len(), |buffer| {
                         ip_repr.emit(&mut buffer[..], &iface_cx.checksum_caps());
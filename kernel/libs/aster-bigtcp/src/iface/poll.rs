@@ -9,9 +9,10 @@ use smoltcp::{
     },
     phy::{ChecksumCapabilities, Device, RxToken, TxToken},
     wire::{
-        Icmpv4DstUnreachable, Icmpv4Repr, IpAddress, IpProtocol, IpRepr, Ipv4Address, Ipv4Packet,
-        Ipv4Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket, UdpRepr, IPV4_HEADER_LEN,
-        IPV4_MIN_MTU,
+        Icmpv4DstUnreachable, Icmpv4Packet, Icmpv4Repr, Icmpv6DstUnreachable, Icmpv6Repr,
+        IpAddress, IpProtocol, IpRepr, IpVersion, Ipv4Address, Ipv4Packet, Ipv4Repr, Ipv6Address,
+        Ipv6Packet, Ipv6Repr, TcpControl, TcpPacket, TcpRepr, UdpPacket, UdpRepr, IPV4_HEADER_LEN,
+        IPV4_MIN_MTU, IPV6_HEADER_LEN, IPV6_MIN_MTU,
     },
 };
 
@@ -51,6 +52,16 @@ impl<'a, E: Ext> PollContext<'a, E> {
     }
 }
 
+/// The reason why a destination is unreachable.
+///
+/// This will be translated to an ICMPv4 or ICMPv6 code, depending on the version of the IP packet
+/// that triggers the ICMP message.
+#[derive(Debug, Clone, Copy)]
+enum UnreachableReason {
+    Host,
+    Port,
+}
+
 // This works around <https://github.com/rust-lang/rust/issues/49601>.
 // See the issue above for details.
 pub(super) trait FnHelper<A, B, C, O>: FnMut(A, B, C) -> O {}
@@ -68,7 +79,7 @@ impl<E: Ext> PollContext<'_, E> {
             &'pkt [u8],
             &'cx mut Context,
             D::TxToken<'tx>,
-            Option<(Ipv4Packet<&'pkt [u8]>, D::TxToken<'tx>)>,
+            Option<(&'pkt [u8], D::TxToken<'tx>)>,
         >,
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
@@ -79,7 +90,7 @@ impl<E: Ext> PollContext<'_, E> {
                     return;
                 };
 
-                let Some(reply) = self.parse_and_process_ipv4(pkt) else {
+                let Some(reply) = self.parse_and_process_ip(pkt) else {
                     return;
                 };
 
@@ -88,6 +99,14 @@ impl<E: Ext> PollContext<'_, E> {
         }
     }
 
+    fn parse_and_process_ip<'pkt>(&mut self, pkt: &'pkt [u8]) -> Option<Packet<'pkt>> {
+        // Ignore the packet if the IP version is unknown.
+        match IpVersion::of_packet(pkt).ok()? {
+            IpVersion::Ipv4 => self.parse_and_process_ipv4(Ipv4Packet::new_checked(pkt).ok()?),
+            IpVersion::Ipv6 => self.parse_and_process_ipv6(Ipv6Packet::new_checked(pkt).ok()?),
+        }
+    }
+
     fn parse_and_process_ipv4<'pkt>(
         &mut self,
         pkt: Ipv4Packet<&'pkt [u8]>,
@@ -99,10 +118,15 @@ impl<E: Ext> PollContext<'_, E> {
             return self.generate_icmp_unreachable(
                 &IpRepr::Ipv4(repr),
                 pkt.payload(),
-                Icmpv4DstUnreachable::HostUnreachable,
+                UnreachableReason::Host,
             );
         }
 
+        // Deliver a copy of the packet to raw sockets.
+        let header_len = pkt.header_len() as usize;
+        let total_len = pkt.total_len() as usize;
+        self.process_raw(&repr, &pkt.as_ref()[..total_len], header_len);
+
         let checksum_caps = self.iface.context().checksum_caps();
         match repr.next_header {
             IpProtocol::Tcp => {
@@ -111,6 +135,43 @@ impl<E: Ext> PollContext<'_, E> {
             IpProtocol::Udp => {
                 self.parse_and_process_udp(&IpRepr::Ipv4(repr), pkt.payload(), &checksum_caps)
             }
+            IpProtocol::Icmp => self.parse_and_process_icmpv4(&repr, pkt.payload(), &checksum_caps),
+            _ => None,
+        }
+    }
+
+    fn parse_and_process_ipv6<'pkt>(
+        &mut self,
+        pkt: Ipv6Packet<&'pkt [u8]>,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the IP header. Ignore the packet if the header is ill-formed.
+        let repr = Ipv6Repr::parse(&pkt).ok()?;
+
+        // Multicast packets are handled by the link layer (e.g., Neighbor Discovery) or ignored.
+        //
+        // TODO: Deliver multicast UDP packets to the sockets that join the multicast group.
+        if repr.dst_addr.is_multicast() {
+            return None;
+        }
+
+        if !self.is_unicast_local(IpAddress::Ipv6(repr.dst_addr)) {
+            return self.generate_icmp_unreachable(
+                &IpRepr::Ipv6(repr),
+                pkt.payload(),
+                UnreachableReason::Host,
+            );
+        }
+
+        // TODO: Support IPv6 extension headers. Currently, packets with extension headers are
+        // silently dropped.
+        let checksum_caps = self.iface.context().checksum_caps();
+        match repr.next_header {
+            IpProtocol::Tcp => {
+                self.parse_and_process_tcp(&IpRepr::Ipv6(repr), pkt.payload(), &checksum_caps)
+            }
+            IpProtocol::Udp => {
+                self.parse_and_process_udp(&IpRepr::Ipv6(repr), pkt.payload(), &checksum_caps)
+            }
             _ => None,
         }
     }
@@ -220,8 +281,19 @@ impl<E: Ext> PollContext<'_, E> {
 
         // Process packets that request to create new connections second.
         if tcp_repr.control == TcpControl::Syn && tcp_repr.ack_number.is_none() {
+            // A listener bound to the destination address takes precedence over a listener bound
+            // to the unspecified address.
             let listener_key = ListenerKey::new(ip_repr.dst_addr(), tcp_repr.dst_port);
-            if let Some(listener) = self.sockets.lookup_listener(&listener_key) {
+            let unspecified_addr = match ip_repr.dst_addr() {
+                IpAddress::Ipv4(_) => IpAddress::Ipv4(Ipv4Address::UNSPECIFIED),
+                IpAddress::Ipv6(_) => IpAddress::Ipv6(Ipv6Address::UNSPECIFIED),
+            };
+            let unspecified_key = ListenerKey::new(unspecified_addr, tcp_repr.dst_port);
+            if let Some(listener) = self
+                .sockets
+                .lookup_listener(&listener_key)
+                .or_else(|| self.sockets.lookup_listener(&unspecified_key))
+            {
                 let (processed, new_tcp_conn) =
                     listener.process(&mut self.iface, ip_repr, tcp_repr);
 
@@ -265,11 +337,7 @@ impl<E: Ext> PollContext<'_, E> {
         .ok()?;
 
         if !self.process_udp(ip_repr, &udp_repr, udp_pkt.payload()) {
-            return self.generate_icmp_unreachable(
-                ip_repr,
-                ip_payload,
-                Icmpv4DstUnreachable::PortUnreachable,
-            );
+            return self.generate_icmp_unreachable(ip_repr, ip_payload, UnreachableReason::Port);
         }
 
         None
@@ -292,11 +360,65 @@ impl<E: Ext> PollContext<'_, E> {
         processed
     }
 
+    fn parse_and_process_icmpv4<'pkt>(
+        &mut self,
+        ip_repr: &Ipv4Repr,
+        ip_payload: &'pkt [u8],
+        checksum_caps: &ChecksumCapabilities,
+    ) -> Option<Packet<'pkt>> {
+        // Parse the ICMP header. Ignore the packet if the header is ill-formed.
+        let icmp_pkt = Icmpv4Packet::new_checked(ip_payload).ok()?;
+        let icmp_repr = Icmpv4Repr::parse(&icmp_pkt, checksum_caps).ok()?;
+
+        // Only echo requests are answered here. Other ICMP messages (including echo replies) are
+        // only visible to raw sockets and ping sockets.
+        //
+        // TODO: Deliver ICMP error messages to the TCP and UDP sockets that cause them.
+        let Icmpv4Repr::EchoRequest {
+            ident,
+            seq_no,
+            data,
+        } = icmp_repr
+        else {
+            return None;
+        };
+
+        if !ip_repr.dst_addr.is_unicast() {
+            return None;
+        }
+
+        let reply_repr = Icmpv4Repr::EchoReply {
+            ident,
+            seq_no,
+            data,
+        };
+        Some(Packet::new_ipv4(
+            Ipv4Repr {
+                src_addr: ip_repr.dst_addr,
+                dst_addr: ip_repr.src_addr,
+                next_header: IpProtocol::Icmp,
+                payload_len: reply_repr.buffer_len(),
+                hop_limit: 64,
+            },
+            IpPayload::Icmpv4(reply_repr),
+        ))
+    }
+
+    fn process_raw(&mut self, ip_repr: &Ipv4Repr, packet: &[u8], header_len: usize) -> bool {
+        let mut processed = false;
+
+        for socket in self.sockets.raw_socket_iter() {
+            processed |= socket.process(ip_repr, packet, header_len);
+        }
+
+        processed
+    }
+
     fn generate_icmp_unreachable<'pkt>(
         &self,
         ip_repr: &IpRepr,
         ip_payload: &'pkt [u8],
-        reason: Icmpv4DstUnreachable,
+        reason: UnreachableReason,
     ) -> Option<Packet<'pkt>> {
         if !ip_repr.src_addr().is_unicast() || !ip_repr.dst_addr().is_unicast() {
             return None;
@@ -312,29 +434,66 @@ impl<E: Ext> PollContext<'_, E> {
             return None;
         }
 
-        let IpRepr::Ipv4(ipv4_repr) = ip_repr;
+        match ip_repr {
+            IpRepr::Ipv4(ipv4_repr) => {
+                let reason = match reason {
+                    UnreachableReason::Host => Icmpv4DstUnreachable::HostUnreachable,
+                    UnreachableReason::Port => Icmpv4DstUnreachable::PortUnreachable,
+                };
 
-        let reply_len = icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, IPV4_HEADER_LEN);
-        let icmp_repr = Icmpv4Repr::DstUnreachable {
-            reason,
-            header: *ipv4_repr,
-            data: &ip_payload[..reply_len],
-        };
+                let reply_len =
+                    icmp_reply_payload_len(ip_payload.len(), IPV4_MIN_MTU, IPV4_HEADER_LEN);
+                let icmp_repr = Icmpv4Repr::DstUnreachable {
+                    reason,
+                    header: *ipv4_repr,
+                    data: &ip_payload[..reply_len],
+                };
 
-        Some(Packet::new_ipv4(
-            Ipv4Repr {
-                src_addr: self
-                    .iface
-                    .context()
-                    .ipv4_addr()
-                    .unwrap_or(Ipv4Address::UNSPECIFIED),
-                dst_addr: ipv4_repr.src_addr,
-                next_header: IpProtocol::Icmp,
-                payload_len: icmp_repr.buffer_len(),
-                hop_limit: 64,
-            },
-            IpPayload::Icmpv4(icmp_repr),
-        ))
+                Some(Packet::new_ipv4(
+                    Ipv4Repr {
+                        src_addr: self
+                            .iface
+                            .context()
+                            .ipv4_addr()
+                            .unwrap_or(Ipv4Address::UNSPECIFIED),
+                        dst_addr: ipv4_repr.src_addr,
+                        next_header: IpProtocol::Icmp,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv4(icmp_repr),
+                ))
+            }
+            IpRepr::Ipv6(ipv6_repr) => {
+                let reason = match reason {
+                    UnreachableReason::Host => Icmpv6DstUnreachable::AddrUnreachable,
+                    UnreachableReason::Port => Icmpv6DstUnreachable::PortUnreachable,
+                };
+
+                let reply_len =
+                    icmp_reply_payload_len(ip_payload.len(), IPV6_MIN_MTU, IPV6_HEADER_LEN);
+                let icmp_repr = Icmpv6Repr::DstUnreachable {
+                    reason,
+                    header: *ipv6_repr,
+                    data: &ip_payload[..reply_len],
+                };
+
+                Some(Packet::new_ipv6(
+                    Ipv6Repr {
+                        src_addr: self
+                            .iface
+                            .context()
+                            .ipv6_addr()
+                            .unwrap_or(Ipv6Address::UNSPECIFIED),
+                        dst_addr: ipv6_repr.src_addr,
+                        next_header: IpProtocol::Icmpv6,
+                        payload_len: icmp_repr.buffer_len(),
+                        hop_limit: 64,
+                    },
+                    IpPayload::Icmpv6(icmp_repr),
+                ))
+            }
+        }
     }
 
     /// Returns whether the destination address is the unicast address of a local interface.
@@ -348,6 +507,11 @@ impl<E: Ext> PollContext<'_, E> {
                 .context()
                 .ipv4_addr()
                 .is_some_and(|addr| addr == dst_addr),
+            IpAddress::Ipv6(dst_addr) => self
+                .iface
+                .context()
+                .ipv6_addr()
+                .is_some_and(|addr| addr == dst_addr),
         }
     }
 }
@@ -359,13 +523,13 @@ impl<E: Ext> PollContext<'_, E> {
         Q: FnMut(&Packet, &mut Context, D::TxToken<'_>),
     {
         while let Some(tx_token) = device.transmit(self.iface.context().now()) {
-            if !self.dispatch_ipv4(tx_token, dispatch_phy) {
+            if !self.dispatch_ip(tx_token, dispatch_phy) {
                 break;
             }
         }
     }
 
-    fn dispatch_ipv4<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> bool
+    fn dispatch_ip<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> bool
     where
         T: TxToken,
         Q: FnMut(&Packet, &mut Context, T),
@@ -376,9 +540,15 @@ impl<E: Ext> PollContext<'_, E> {
             return did_something_tcp;
         };
 
-        let (did_something_udp, _tx_token) = self.dispatch_udp(tx_token, dispatch_phy);
+        let (did_something_udp, tx_token) = self.dispatch_udp(tx_token, dispatch_phy);
 
-        did_something_tcp || did_something_udp
+        let Some(tx_token) = tx_token else {
+            return did_something_tcp || did_something_udp;
+        };
+
+        let (did_something_raw, _tx_token) = self.dispatch_raw(tx_token, dispatch_phy);
+
+        did_something_tcp || did_something_udp || did_something_raw
     }
 
     fn dispatch_tcp<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
@@ -560,4 +730,74 @@ impl<E: Ext> PollContext<'_, E> {
 
         (did_something, tx_token)
     }
+
+    fn dispatch_raw<T, Q>(&mut self, tx_token: T, dispatch_phy: &mut Q) -> (bool, Option<T>)
+    where
+        T: TxToken,
+        Q: FnMut(&Packet, &mut Context, T),
+    {
+        let mut tx_token = Some(tx_token);
+        let mut did_something = false;
+
+        for socket in self.sockets.raw_socket_iter() {
+            if !socket.need_dispatch() {
+                continue;
+            }
+
+            let Some((ip_repr, ip_payload)) = socket.dequeue_tx() else {
+                continue;
+            };
+
+            did_something = true;
+
+            let packet = Packet::new_ipv4(ip_repr, IpPayload::Raw(&ip_payload));
+            if !self.is_unicast_local(IpAddress::Ipv4(ip_repr.dst_addr)) {
+                dispatch_phy(&packet, self.iface.context_mut(), tx_token.take().unwrap());
+            } else if let Some(reply) = self.process_ip_until_outgoing(self.emit_ip(&packet)) {
+                let reply = Ipv4Packet::new_unchecked(reply.as_slice());
+                if let Ok(reply_repr) = Ipv4Repr::parse(&reply, &ChecksumCapabilities::ignored()) {
+                    dispatch_phy(
+                        &Packet::new_ipv4(reply_repr, IpPayload::Raw(reply.payload())),
+                        self.iface.context_mut(),
+                        tx_token.take().unwrap(),
+                    );
+                }
+            }
+
+            if tx_token.is_none() {
+                break;
+            }
+        }
+
+        (did_something, tx_token)
+    }
+
+    /// Processes an IP packet destined for a local address, and keeps processing the replies as
+    /// long as they are also destined for local addresses.
+    ///
+    /// This returns the first reply that needs to leave the local machine, if any.
+    fn process_ip_until_outgoing(&mut self, mut packet: Vec<u8>) -> Option<Vec<u8>> {
+        loop {
+            let reply = self.parse_and_process_ip(&packet)?;
+            let new_packet = self.emit_ip(&reply);
+
+            if !self.is_unicast_local(reply.ip_repr().dst_addr()) {
+                return Some(new_packet);
+            }
+
+            packet = new_packet;
+        }
+    }
+
+    /// Emits an IP packet to bytes.
+    fn emit_ip(&self, packet: &Packet) -> Vec<u8> {
+        let context = self.iface.context();
+
+        let ip_repr = packet.ip_repr();
+        let mut data = vec![0; ip_repr.buffer_len()];
+        ip_repr.emit(&mut data[..], &context.checksum_caps());
+        packet.emit_payload(&ip_repr, &mut data[ip_repr.header_len()..], &context.caps);
+
+        data
+    }
 }
@@ -42,7 +42,20 @@ impl<E: Ext> PollableIface<E> {
     pub(super) fn prefix_len(&self) -> Option<u8> {
         self.interface
             .ip_addrs()
-            .first()
+            .iter()
+            .find(|ip_addr| matches!(ip_addr, smoltcp::wire::IpCidr::Ipv4(_)))
+            .map(|ip_addr| ip_addr.prefix_len())
+    }
+
+    pub(super) fn ipv6_addr(&self) -> Option<smoltcp::wire::Ipv6Address> {
+        self.interface.ipv6_addr()
+    }
+
+    pub(super) fn ipv6_prefix_len(&self) -> Option<u8> {
+        self.interface
+            .ip_addrs()
+            .iter()
+            .find(|ip_addr| matches!(ip_addr, smoltcp::wire::IpCidr::Ipv6(_)))
             .map(|ip_addr| ip_addr.prefix_len())
     }
 
@@ -1,11 +1,17 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod common;
+mod packet;
+mod raw;
 mod tcp_conn;
 mod tcp_listen;
 mod udp;
 
 pub use common::NeedIfacePoll;
+pub(crate) use packet::PacketSocketBg;
+pub use packet::{PacketConfig, PacketFrame, PacketProtocol, PacketSocket, PacketType};
+pub(crate) use raw::RawIpSocketBg;
+pub use raw::{RawIpConfig, RawIpPacket, RawIpSocket};
 pub use tcp_conn::{ConnectState, RawTcpSocketExt, TcpConnection};
 pub(crate) use tcp_conn::{TcpConnectionBg, TcpProcessResult};
 pub use tcp_listen::TcpListener;
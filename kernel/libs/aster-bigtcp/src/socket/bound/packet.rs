@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_softirq::BottomHalfDisabled;
+use ostd::sync::SpinLock;
+use smoltcp::wire::EthernetAddress;
+
+use super::raw::PacketQueue;
+use crate::{
+    errors::packet::{RecvError, SendError},
+    ext::Ext,
+    iface::Iface,
+    socket::{
+        event::{SocketEventObserver, SocketEvents},
+        unbound::{PACKET_RECV_BUF_LEN, PACKET_SEND_BUF_LEN},
+    },
+};
+
+/// A packet socket.
+///
+/// A packet socket receives a copy of every link-layer frame that the iface sends or receives,
+/// as long as the frame matches its [`PacketConfig`]. It can also send link-layer frames built
+/// by the user directly to the device, bypassing the network stack.
+pub struct PacketSocket<E: Ext>(Arc<PacketSocketBg<E>>);
+
+/// The filter of the frames received by a [`PacketSocket`].
+#[derive(Debug, Clone, Copy)]
+pub struct PacketConfig {
+    /// The link-layer protocols that the socket is interested in.
+    pub protocol: PacketProtocol,
+}
+
+/// The link-layer protocols (e.g., the EtherTypes) that a [`PacketSocket`] is interested in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketProtocol {
+    /// No frames are received. The socket can only send frames.
+    Disabled,
+    /// Frames of all protocols are received, including outgoing frames.
+    All,
+    /// Only incoming frames of the specified protocol are received.
+    Only(u16),
+}
+
+/// The type of a link-layer frame.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L26>.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    /// The frame is sent to us.
+    Host = 0,
+    /// The frame is sent to all hosts.
+    Broadcast = 1,
+    /// The frame is sent to a multicast group.
+    Multicast = 2,
+    /// The frame is sent to another host.
+    OtherHost = 3,
+    /// The frame is sent by us.
+    Outgoing = 4,
+}
+
+/// A link-layer frame received by a [`PacketSocket`].
+#[derive(Debug)]
+pub struct PacketFrame {
+    /// The link-layer protocol of the frame.
+    pub protocol: u16,
+    /// The type of the frame.
+    pub pkt_type: PacketType,
+    /// The source hardware address, if the link layer has one.
+    pub src_hw_addr: Option<EthernetAddress>,
+    /// The length of the link-layer header.
+    ///
+    /// The header is at the beginning of [`Self::data`].
+    pub header_len: usize,
+    /// The bytes of the frame, including the link-layer header.
+    pub data: Vec<u8>,
+}
+
+/// Background states of a [`PacketSocket`].
+pub(crate) struct PacketSocketBg<E: Ext> {
+    iface: Arc<dyn Iface<E>>,
+    config: PacketConfig,
+    rx_queue: SpinLock<PacketQueue<PacketFrame>, BottomHalfDisabled>,
+    tx_queue: SpinLock<PacketQueue<Vec<u8>>, BottomHalfDisabled>,
+    need_dispatch: AtomicBool,
+    observer: E::PacketEventObserver,
+}
+
+impl<E: Ext> PacketSocketBg<E> {
+    /// Tries to deliver a copy of a frame to the socket and returns whether the frame is
+    /// delivered.
+    ///
+    /// `make_frame` is only called if the socket is interested in the frame.
+    pub(crate) fn process(
+        &self,
+        protocol: u16,
+        pkt_type: PacketType,
+        make_frame: impl FnOnce() -> PacketFrame,
+    ) -> bool {
+        match self.config.protocol {
+            PacketProtocol::Disabled => return false,
+            PacketProtocol::All => (),
+            // Similar to Linux, outgoing frames are only delivered to sockets that are interested
+            // in all protocols.
+            PacketProtocol::Only(_) if pkt_type == PacketType::Outgoing => return false,
+            PacketProtocol::Only(config_protocol) if config_protocol != protocol => return false,
+            PacketProtocol::Only(_) => (),
+        }
+
+        let frame = make_frame();
+        let len = frame.data.len();
+
+        if !self.rx_queue.lock().push(frame, len, PACKET_RECV_BUF_LEN) {
+            // The receive queue is full. Drop the frame.
+            return false;
+        }
+
+        self.observer.on_events(SocketEvents::CAN_RECV);
+
+        true
+    }
+
+    /// Dequeues an outgoing frame.
+    pub(crate) fn dequeue_tx(&self) -> Option<Vec<u8>> {
+        let mut tx_queue = self.tx_queue.lock();
+
+        let frame = tx_queue.pop(Vec::len);
+        self.need_dispatch
+            .store(!tx_queue.is_empty(), Ordering::Relaxed);
+
+        if frame.is_some() {
+            self.observer.on_events(SocketEvents::CAN_SEND);
+        }
+
+        frame
+    }
+
+    /// Returns whether the socket _may_ generate an outgoing frame.
+    ///
+    /// The check is intended to be lock-free and fast, but may have false positives.
+    pub(crate) fn need_dispatch(&self) -> bool {
+        self.need_dispatch.load(Ordering::Relaxed)
+    }
+}
+
+impl<E: Ext> PacketSocket<E> {
+    /// Creates a packet socket on the iface.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new(
+        iface: Arc<dyn Iface<E>>,
+        config: PacketConfig,
+        observer: E::PacketEventObserver,
+    ) -> Self {
+        let bg = Arc::new(PacketSocketBg {
+            iface,
+            config,
+            rx_queue: SpinLock::new(PacketQueue::new()),
+            tx_queue: SpinLock::new(PacketQueue::new()),
+            need_dispatch: AtomicBool::new(false),
+            observer,
+        });
+
+        bg.iface.common().register_packet_socket(bg.clone());
+
+        Self(bg)
+    }
+
+    /// Returns a reference to the iface.
+    pub fn iface(&self) -> &Arc<dyn Iface<E>> {
+        &self.0.iface
+    }
+
+    /// Returns the configuration of the socket.
+    pub fn config(&self) -> &PacketConfig {
+        &self.0.config
+    }
+
+    /// Sends a link-layer frame, which must include the link-layer header.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn send(&self, frame: Vec<u8>) -> Result<(), SendError> {
+        if frame.len() > PACKET_SEND_BUF_LEN {
+            return Err(SendError::TooLarge);
+        }
+
+        let mut tx_queue = self.0.tx_queue.lock();
+
+        let len = frame.len();
+        if !tx_queue.push(frame, len, PACKET_SEND_BUF_LEN) {
+            return Err(SendError::BufferFull);
+        }
+
+        self.0.need_dispatch.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Receives a link-layer frame.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn recv(&self) -> Result<PacketFrame, RecvError> {
+        self.0
+            .rx_queue
+            .lock()
+            .pop(|frame| frame.data.len())
+            .ok_or(RecvError::Exhausted)
+    }
+
+    /// Returns whether there are received frames.
+    pub fn can_recv(&self) -> bool {
+        !self.0.rx_queue.lock().is_empty()
+    }
+
+    /// Returns whether there is room to send frames.
+    pub fn can_send(&self) -> bool {
+        self.0.tx_queue.lock().total_len() < PACKET_SEND_BUF_LEN
+    }
+}
+
+impl<E: Ext> Drop for PacketSocket<E> {
+    fn drop(&mut self) {
+        // A packet socket can be removed immediately.
+        self.0.iface.common().remove_packet_socket(&self.0);
+    }
+}
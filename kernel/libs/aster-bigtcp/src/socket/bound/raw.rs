@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_softirq::BottomHalfDisabled;
+use ostd::sync::SpinLock;
+use smoltcp::wire::{IpProtocol, Ipv4Address, Ipv4Repr};
+
+use crate::{
+    errors::raw::{RecvError, SendError},
+    ext::Ext,
+    iface::Iface,
+    socket::{
+        event::{SocketEventObserver, SocketEvents},
+        unbound::{RAW_RECV_BUF_LEN, RAW_SEND_BUF_LEN},
+    },
+};
+
+/// A raw IPv4 socket.
+///
+/// Unlike TCP and UDP sockets, raw sockets are not bound to ports. Instead, a raw socket receives
+/// a copy of every incoming IP packet that matches its [`RawIpConfig`], and it sends IP packets
+/// whose payloads (or, in some cases, whole packets) are built by the user.
+pub struct RawIpSocket<E: Ext>(Arc<RawIpSocketBg<E>>);
+
+/// The filter and the format of the packets received by a [`RawIpSocket`].
+#[derive(Debug, Clone, Copy)]
+pub struct RawIpConfig {
+    /// The protocol in the IP header that the socket is interested in.
+    pub protocol: IpProtocol,
+    /// The local address that the socket is bound to.
+    ///
+    /// If this is `Some(_)`, only packets destined for the address are received.
+    pub local_addr: Option<Ipv4Address>,
+    /// Whether the IP header is kept in the received packets.
+    pub recv_header: bool,
+    /// The identifier of ICMP echo messages.
+    ///
+    /// If this is `Some(_)`, the socket is an ICMP ping socket and only receives ICMP echo replies
+    /// with the specified identifier.
+    pub echo_ident: Option<u16>,
+}
+
+/// Background states of a [`RawIpSocket`].
+pub(crate) struct RawIpSocketBg<E: Ext> {
+    iface: Arc<dyn Iface<E>>,
+    config: RawIpConfig,
+    rx_queue: SpinLock<PacketQueue<RawIpPacket>, BottomHalfDisabled>,
+    tx_queue: SpinLock<PacketQueue<(Ipv4Repr, Vec<u8>)>, BottomHalfDisabled>,
+    need_dispatch: AtomicBool,
+    observer: E::RawEventObserver,
+}
+
+/// A packet received by a [`RawIpSocket`].
+pub struct RawIpPacket {
+    /// The source address of the packet.
+    pub src_addr: Ipv4Address,
+    /// The bytes of the packet.
+    ///
+    /// Whether the bytes contain the IP header depends on [`RawIpConfig::recv_header`].
+    pub data: Vec<u8>,
+}
+
+/// A queue of packets whose total length is limited.
+pub(super) struct PacketQueue<T> {
+    packets: VecDeque<T>,
+    total_len: usize,
+}
+
+impl<T> PacketQueue<T> {
+    pub(super) const fn new() -> Self {
+        Self {
+            packets: VecDeque::new(),
+            total_len: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, packet: T, len: usize, capacity: usize) -> bool {
+        if self.total_len + len > capacity {
+            return false;
+        }
+
+        self.packets.push_back(packet);
+        self.total_len += len;
+        true
+    }
+
+    pub(super) fn pop(&mut self, len_fn: impl FnOnce(&T) -> usize) -> Option<T> {
+        let packet = self.packets.pop_front()?;
+        self.total_len -= len_fn(&packet);
+        Some(packet)
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub(super) fn total_len(&self) -> usize {
+        self.total_len
+    }
+}
+
+/// ICMPv4 echo reply message type.
+const ICMPV4_ECHO_REPLY: u8 = 0;
+
+impl<E: Ext> RawIpSocketBg<E> {
+    /// Tries to process an incoming packet and returns whether the packet is processed.
+    ///
+    /// Note that a raw socket only receives a _copy_ of the packet, so the packet should also be
+    /// processed by the rest of the network stack regardless of the return value.
+    pub(crate) fn process(&self, ip_repr: &Ipv4Repr, packet: &[u8], header_len: usize) -> bool {
+        if ip_repr.next_header != self.config.protocol {
+            return false;
+        }
+
+        if self
+            .config
+            .local_addr
+            .is_some_and(|local_addr| local_addr != ip_repr.dst_addr)
+        {
+            return false;
+        }
+
+        let payload = &packet[header_len..];
+        if let Some(echo_ident) = self.config.echo_ident {
+            if payload.len() < 8
+                || payload[0] != ICMPV4_ECHO_REPLY
+                || u16::from_be_bytes([payload[4], payload[5]]) != echo_ident
+            {
+                return false;
+            }
+        }
+
+        let data = if self.config.recv_header {
+            packet.to_vec()
+        } else {
+            payload.to_vec()
+        };
+        let len = data.len();
+
+        let raw_packet = RawIpPacket {
+            src_addr: ip_repr.src_addr,
+            data,
+        };
+        if !self.rx_queue.lock().push(raw_packet, len, RAW_RECV_BUF_LEN) {
+            // The receive queue is full. Drop the packet.
+            return false;
+        }
+
+        self.observer.on_events(SocketEvents::CAN_RECV);
+
+        true
+    }
+
+    /// Dequeues an outgoing packet.
+    pub(crate) fn dequeue_tx(&self) -> Option<(Ipv4Repr, Vec<u8>)> {
+        let mut tx_queue = self.tx_queue.lock();
+
+        let packet = tx_queue.pop(|(_, payload)| payload.len());
+        self.need_dispatch
+            .store(!tx_queue.is_empty(), Ordering::Relaxed);
+
+        if packet.is_some() {
+            self.observer.on_events(SocketEvents::CAN_SEND);
+        }
+
+        packet
+    }
+
+    /// Returns whether the socket _may_ generate an outgoing packet.
+    ///
+    /// The check is intended to be lock-free and fast, but may have false positives.
+    pub(crate) fn need_dispatch(&self) -> bool {
+        self.need_dispatch.load(Ordering::Relaxed)
+    }
+}
+
+impl<E: Ext> RawIpSocket<E> {
+    /// Creates a raw socket on the iface.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn new(
+        iface: Arc<dyn Iface<E>>,
+        config: RawIpConfig,
+        observer: E::RawEventObserver,
+    ) -> Self {
+        let bg = Arc::new(RawIpSocketBg {
+            iface,
+            config,
+            rx_queue: SpinLock::new(PacketQueue::new()),
+            tx_queue: SpinLock::new(PacketQueue::new()),
+            need_dispatch: AtomicBool::new(false),
+            observer,
+        });
+
+        bg.iface.common().register_raw_socket(bg.clone());
+
+        Self(bg)
+    }
+
+    /// Returns a reference to the iface.
+    pub fn iface(&self) -> &Arc<dyn Iface<E>> {
+        &self.0.iface
+    }
+
+    /// Returns the configuration of the socket.
+    pub fn config(&self) -> &RawIpConfig {
+        &self.0.config
+    }
+
+    /// Sends an IP packet with the specified header and payload.
+    ///
+    /// Polling the iface is _always_ required after this method succeeds.
+    pub fn send(&self, ip_repr: Ipv4Repr, payload: Vec<u8>) -> Result<(), SendError> {
+        if payload.len() > RAW_SEND_BUF_LEN {
+            return Err(SendError::TooLarge);
+        }
+
+        let mut tx_queue = self.0.tx_queue.lock();
+
+        let len = payload.len();
+        if !tx_queue.push((ip_repr, payload), len, RAW_SEND_BUF_LEN) {
+            return Err(SendError::BufferFull);
+        }
+
+        self.0.need_dispatch.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Receives an IP packet.
+    ///
+    /// Polling the iface is _not_ required after this method succeeds.
+    pub fn recv(&self) -> Result<RawIpPacket, RecvError> {
+        self.0
+            .rx_queue
+            .lock()
+            .pop(|packet| packet.data.len())
+            .ok_or(RecvError::Exhausted)
+    }
+
+    /// Returns whether there are received packets.
+    pub fn can_recv(&self) -> bool {
+        !self.0.rx_queue.lock().is_empty()
+    }
+
+    /// Returns whether there is room to send packets.
+    pub fn can_send(&self) -> bool {
+        self.0.tx_queue.lock().total_len() < RAW_SEND_BUF_LEN
+    }
+}
+
+impl<E: Ext> Drop for RawIpSocket<E> {
+    fn drop(&mut self) {
+        // A raw socket can be removed immediately.
+        self.0.iface.common().remove_raw_socket(&self.0);
+    }
+}
@@ -315,7 +315,8 @@ impl<E: Ext> TcpConnection<E> {
 
             option.apply(&mut socket);
 
-            if let Err(err) = socket.connect(interface.context_mut(), remote_endpoint, bound.port())
+            if let Err(err) =
+                socket.connect(interface.context_mut(), remote_endpoint, local_endpoint)
             {
                 return Err((bound, err.into()));
             }
@@ -73,13 +73,17 @@ impl<E: Ext> TcpListener<E> {
         option: &RawTcpOption,
         observer: E::TcpEventObserver,
     ) -> Result<Self, (BoundPort<E>, ListenError)> {
-        let Some(local_endpoint) = bound.endpoint() else {
+        let (Some(local_endpoint), Some(listen_endpoint)) =
+            (bound.endpoint(), bound.listen_endpoint())
+        else {
             return Err((bound, ListenError::Unaddressable));
         };
 
         let iface = bound.iface().clone();
         let mut sockets = iface.common().sockets();
 
+        // If the port is bound to the unspecified address, the key also has the unspecified
+        // address, which is the fallback when looking up the listener for incoming connections.
         let listener_key = ListenerKey::new(local_endpoint.addr, local_endpoint.port);
 
         if sockets.lookup_listener(&listener_key).is_some() {
@@ -91,7 +95,7 @@ impl<E: Ext> TcpListener<E> {
 
             option.apply(&mut socket);
 
-            if let Err(err) = socket.listen(local_endpoint) {
+            if let Err(err) = socket.listen(listen_endpoint) {
                 return Err((bound, err.into()));
             }
 
@@ -233,10 +237,15 @@ impl<E: Ext> TcpListenerBg<E> {
             socket
         };
 
+        // The new connection is bound to the address that the peer connects to, which differs
+        // from the address of the listener if the listener is bound to the unspecified address.
         let conn = TcpConnection::new_cyclic(
             self.bound
                 .iface()
-                .bind(BindPortConfig::Backlog(self.bound.port()))
+                .bind(
+                    ip_repr.dst_addr(),
+                    BindPortConfig::Backlog(self.bound.port()),
+                )
                 .unwrap(),
             |weak| {
                 TcpConnectionInner::new(
@@ -106,14 +106,14 @@ impl<E: Ext> UdpSocket<E> {
         bound: BoundPort<E>,
         observer: E::UdpEventObserver,
     ) -> Result<Self, (BoundPort<E>, smoltcp::socket::udp::BindError)> {
-        let Some(local_endpoint) = bound.endpoint() else {
+        let Some(listen_endpoint) = bound.listen_endpoint() else {
             return Err((bound, smoltcp::socket::udp::BindError::Unaddressable));
         };
 
         let socket = {
             let mut socket = new_udp_socket();
 
-            if let Err(err) = socket.bind(local_endpoint) {
+            if let Err(err) = socket.bind(listen_endpoint) {
                 return Err((bound, err));
             }
 
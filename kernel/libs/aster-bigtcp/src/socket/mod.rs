@@ -6,11 +6,16 @@ mod option;
 mod unbound;
 
 pub use bound::{
-    ConnectState, NeedIfacePoll, RawTcpSocketExt, TcpConnection, TcpListener, UdpSocket,
+    ConnectState, NeedIfacePoll, PacketConfig, PacketFrame, PacketProtocol, PacketSocket,
+    PacketType, RawIpConfig, RawIpPacket, RawIpSocket, RawTcpSocketExt, TcpConnection, TcpListener,
+    UdpSocket,
+};
+pub(crate) use bound::{
+    PacketSocketBg, RawIpSocketBg, TcpConnectionBg, TcpListenerBg, TcpProcessResult, UdpSocketBg,
 };
-pub(crate) use bound::{TcpConnectionBg, TcpListenerBg, TcpProcessResult, UdpSocketBg};
 pub use event::{SocketEventObserver, SocketEvents};
 pub use option::{RawTcpOption, RawTcpSetOption};
 pub use unbound::{
-    RawUdpSocket, TCP_RECV_BUF_LEN, TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
+    RawUdpSocket, PACKET_RECV_BUF_LEN, PACKET_SEND_BUF_LEN, RAW_RECV_BUF_LEN, RAW_SEND_BUF_LEN,
+    TCP_RECV_BUF_LEN, TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
 };
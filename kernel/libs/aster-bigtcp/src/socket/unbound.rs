@@ -50,3 +50,11 @@ pub const TCP_SEND_BUF_LEN: usize = 65536 * 2;
 pub const UDP_SEND_PAYLOAD_LEN: usize = 65536;
 pub const UDP_RECV_PAYLOAD_LEN: usize = 65536;
 const UDP_METADATA_LEN: usize = 256;
+
+// Raw socket buffer sizes:
+pub const RAW_SEND_BUF_LEN: usize = 65536;
+pub const RAW_RECV_BUF_LEN: usize = 65536;
+
+// Packet socket buffer sizes:
+pub const PACKET_SEND_BUF_LEN: usize = 65536;
+pub const PACKET_RECV_BUF_LEN: usize = 212992;
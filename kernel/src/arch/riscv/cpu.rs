@@ -153,6 +153,7 @@ impl TryFrom<&CpuExceptionInfo> for PageFaultInfo {
         Ok(PageFaultInfo {
             address: value.page_fault_addr,
             required_perms,
+            is_user: true,
         })
     }
 }
@@ -14,7 +14,11 @@ use ostd::{
 };
 
 use crate::{
-    arch::cpu::cpuid::VendorInfo, cpu::LinuxAbi, thread::exception::PageFaultInfo,
+    arch::cpu::cpuid::VendorInfo,
+    cpu::LinuxAbi,
+    prelude::Errno,
+    process::posix_thread::{PtraceStop, PtraceStopKind},
+    thread::exception::PageFaultInfo,
     vm::perms::VmPerms,
 };
 
@@ -132,6 +136,106 @@ impl SigContext {
     }
 }
 
+/// Represents the user registers that are visible to the tracer.
+///
+/// This is used by `PTRACE_GETREGS` and `PTRACE_SETREGS`.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/arch/x86/include/asm/user_64.h#L69>
+#[derive(Clone, Copy, Debug, Default, Pod)]
+#[repr(C)]
+pub struct UserRegs {
+    r15: usize,
+    r14: usize,
+    r13: usize,
+    r12: usize,
+    rbp: usize,
+    rbx: usize,
+    r11: usize,
+    r10: usize,
+    r9: usize,
+    r8: usize,
+    rax: usize,
+    rcx: usize,
+    rdx: usize,
+    rsi: usize,
+    rdi: usize,
+    orig_rax: usize,
+    rip: usize,
+    cs: usize,
+    rflags: usize,
+    rsp: usize,
+    ss: usize,
+    fs_base: usize,
+    gs_base: usize,
+    ds: usize,
+    es: usize,
+    fs: usize,
+    gs: usize,
+}
+
+impl UserRegs {
+    // The segment selectors of the user code and data segments in Linux.
+    const USER_CS: usize = 0x33;
+    const USER_SS: usize = 0x2b;
+
+    /// Creates the user registers from the user context.
+    ///
+    /// This is also used to fill the registers in the `NT_PRSTATUS` note of core dumps.
+    pub fn from_user_ctx(user_ctx: &UserContext) -> Self {
+        let mut regs = Self {
+            orig_rax: usize::MAX,
+            cs: Self::USER_CS,
+            ss: Self::USER_SS,
+            fs_base: user_ctx.fsbase(),
+            gs_base: user_ctx.gsbase(),
+            ..Default::default()
+        };
+        let gp_regs = user_ctx.general_regs();
+        copy_gp_regs!(gp_regs, regs);
+
+        regs
+    }
+
+    /// Creates the user registers from the tracee's context in the ptrace-stop.
+    pub fn from_ptrace_stop(stop: &PtraceStop) -> Self {
+        let mut regs = Self::from_user_ctx(stop.user_ctx());
+
+        // Linux saves the syscall number in `orig_rax` and sets `rax` to `-ENOSYS` before the
+        // syscall is executed.
+        match stop.kind() {
+            PtraceStopKind::SyscallEnter(syscall_num) => {
+                regs.orig_rax = syscall_num;
+                regs.rax = -(Errno::ENOSYS as i32) as usize;
+            }
+            PtraceStopKind::SyscallExit(syscall_num) => regs.orig_rax = syscall_num,
+            PtraceStopKind::Signal(_) | PtraceStopKind::Exec => (),
+        }
+
+        regs
+    }
+
+    /// Writes the user registers to the tracee's context in the ptrace-stop.
+    ///
+    /// The segment registers cannot be changed, and only the user-modifiable bits in `rflags` take
+    /// effect.
+    pub fn write_to_ptrace_stop(&self, stop: &mut PtraceStop) {
+        // CF, PF, AF, ZF, SF, TF, DF, OF, RF and AC.
+        const USER_RFLAGS_MASK: usize = 0x0005_0dd5;
+
+        let is_syscall_entry = matches!(stop.kind(), PtraceStopKind::SyscallEnter(_));
+        if is_syscall_entry {
+            stop.set_syscall_num(self.orig_rax);
+        }
+
+        let user_ctx = stop.user_ctx_mut();
+        let old_rflags = user_ctx.general_regs().rflags;
+
+        let gp_regs = user_ctx.general_regs_mut();
+        copy_gp_regs!(self, gp_regs);
+        gp_regs.rflags = (old_rflags & !USER_RFLAGS_MASK) | (self.rflags & USER_RFLAGS_MASK);
+    }
+}
+
 impl From<&RawPageFaultInfo> for PageFaultInfo {
     fn from(raw_info: &RawPageFaultInfo) -> Self {
         let required_perms = if raw_info
@@ -148,6 +252,7 @@ impl From<&RawPageFaultInfo> for PageFaultInfo {
         PageFaultInfo {
             address: raw_info.addr,
             required_perms,
+            is_user: true,
         }
     }
 }
@@ -18,6 +18,9 @@ impl From<&CpuException> for FaultSignal {
     fn from(exception: &CpuException) -> Self {
         let (num, code, addr) = match exception {
             CpuException::DivisionError => (SIGFPE, FPE_INTDIV, None),
+            // Single-stepping and breakpoints are reported to the debugger via `SIGTRAP`.
+            CpuException::Debug => (SIGTRAP, TRAP_TRACE, None),
+            CpuException::BreakPoint => (SIGTRAP, SI_KERNEL, None),
             CpuException::X87FloatingPointException | CpuException::SIMDFloatingPointException => {
                 (SIGFPE, FPE_FLTDIV, None)
             }
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+mod mqueue;
 mod null;
 mod pty;
 mod random;
@@ -18,7 +19,10 @@ pub use random::Random;
 pub use urandom::Urandom;
 
 use crate::{
-    fs::device::{add_node, Device, DeviceId, DeviceType},
+    fs::{
+        device::{add_node, Device, DeviceId, DeviceType},
+        fuse::FuseDevice,
+    },
     prelude::*,
 };
 
@@ -53,10 +57,15 @@ pub fn init() -> Result<()> {
     let urandom = Arc::new(urandom::Urandom);
     add_node(urandom, "urandom")?;
 
+    let fuse = Arc::new(FuseDevice);
+    add_node(fuse, "fuse")?;
+
     pty::init()?;
 
     shm::init()?;
 
+    mqueue::init()?;
+
     Ok(())
 }
 
@@ -74,6 +83,7 @@ pub fn get_device(devid: DeviceId) -> Result<Arc<dyn Device>> {
         (5, 0) => Ok(Arc::new(tty::TtyDevice)),
         (1, 8) => Ok(Arc::new(random::Random)),
         (1, 9) => Ok(Arc::new(urandom::Urandom)),
+        (10, 229) => Ok(Arc::new(FuseDevice)),
         _ => return_errno_with_message!(Errno::EINVAL, "the device ID is invalid or unsupported"),
     }
 }
@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::{
+        fs_resolver::{FsPath, FsResolver},
+        utils::{InodeMode, InodeType},
+    },
+    ipc::init_ipc_ns,
+    prelude::*,
+};
+
+/// Initializes "/dev/mqueue" for POSIX message queue usage.
+pub fn init() -> Result<()> {
+    let dev_path = {
+        let fs = FsResolver::new();
+        fs.lookup(&FsPath::try_from("/dev")?)?
+    };
+
+    // Create the "mqueue" directory under "/dev" and mount the mqueue file system of the initial
+    // IPC namespace on it.
+    let mqueue_path = dev_path.new_fs_child(
+        "mqueue",
+        InodeType::Dir,
+        InodeMode::from_bits_truncate(0o1777),
+    )?;
+    mqueue_path.mount(init_ipc_ns().mqueue_root().fs())?;
+    log::debug!("Mount mqueue at \"/dev/mqueue\"");
+    Ok(())
+}
@@ -7,6 +7,7 @@ use crate::{
     fs::{
         device::{Device, DeviceId, DeviceType},
         inode_handle::FileIo,
+        utils::StatusFlags,
     },
     prelude::*,
     process::signal::{PollHandle, Pollable},
@@ -45,14 +46,14 @@ impl Pollable for Random {
 }
 
 impl FileIo for Random {
-    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+    fn read(&self, writer: &mut VmWriter, _status_flags: StatusFlags) -> Result<usize> {
         let mut buf = vec![0; writer.avail()];
         let size = Self::getrandom(buf.as_mut_slice());
         writer.write_fallible(&mut buf.as_slice().into())?;
         size
     }
 
-    fn write(&self, reader: &mut VmReader) -> Result<usize> {
+    fn write(&self, reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
         Ok(reader.remain())
     }
 }
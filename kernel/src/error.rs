@@ -2,9 +2,11 @@
 
 #![expect(dead_code)]
 
+use int_to_c_enum::TryFromInt;
+
 /// Error number.
 #[repr(i32)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TryFromInt)]
 pub enum Errno {
     EPERM = 1,    /* Operation not permitted */
     ENOENT = 2,   /* No such file or directory */
@@ -335,6 +337,7 @@ impl From<aster_systree::Error> for Error {
             NotFound => Error::new(Errno::ENOENT),
             InvalidNodeOperation(_) => Error::new(Errno::EINVAL),
             AttributeError => Error::new(Errno::EIO),
+            InvalidArgument => Error::new(Errno::EINVAL),
             PermissionDenied => Error::new(Errno::EACCES),
             InternalError(msg) => Error::with_message(Errno::EIO, msg),
             AlreadyExists => Error::new(Errno::EEXIST),
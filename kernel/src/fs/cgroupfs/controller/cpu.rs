@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{format, sync::Arc};
+
+use aster_systree::{Error, Result};
+use ostd::mm::{VmReader, VmWriter};
+
+use super::{parse_limit, read_value, write_value, MAX};
+use crate::sched::FairGroup;
+
+/// The `cpu` controller, which distributes the CPU time among cgroups.
+///
+/// The controller is backed by a [`FairGroup`] of the fair scheduling class, so it only affects
+/// the threads scheduled by the fair scheduling class.
+#[derive(Debug)]
+pub(in crate::fs::cgroupfs) struct CpuController {
+    group: Arc<FairGroup>,
+}
+
+impl CpuController {
+    const MIN_WEIGHT: u64 = 1;
+    const MAX_WEIGHT: u64 = 10000;
+
+    /// The minimum quota and period, in microseconds.
+    const MIN_QUOTA_US: u64 = 1000;
+    const MIN_PERIOD_US: u64 = 1000;
+    /// The maximum period, in microseconds.
+    const MAX_PERIOD_US: u64 = 1_000_000;
+
+    const NSEC_PER_USEC: u64 = 1000;
+
+    pub(in crate::fs::cgroupfs) fn new(parent: Option<&CpuController>) -> Self {
+        Self {
+            group: FairGroup::new(parent.map(|parent| parent.group.clone())),
+        }
+    }
+
+    /// Returns the scheduling group of the cgroup.
+    pub(in crate::fs::cgroupfs) fn group(&self) -> &Arc<FairGroup> {
+        &self.group
+    }
+
+    pub(in crate::fs::cgroupfs) fn read_attr(
+        &self,
+        name: &str,
+        writer: &mut VmWriter,
+    ) -> Result<usize> {
+        match name {
+            "cpu.weight" => write_value(writer, &format!("{}\n", self.group.weight())),
+            "cpu.max" => {
+                let (quota_ns, period_ns) = self.group.bandwidth();
+                let period_us = period_ns / Self::NSEC_PER_USEC;
+                let value = match quota_ns {
+                    Some(quota_ns) => format!("{} {}\n", quota_ns / Self::NSEC_PER_USEC, period_us),
+                    None => format!("{} {}\n", MAX, period_us),
+                };
+                write_value(writer, &value)
+            }
+            _ => Err(Error::AttributeError),
+        }
+    }
+
+    /// Writes an attribute of the controller.
+    ///
+    /// If `cpu.weight` is written, the caller should apply the new weight to the threads in the
+    /// cgroup and its descendants.
+    pub(in crate::fs::cgroupfs) fn write_attr(
+        &self,
+        name: &str,
+        reader: &mut VmReader,
+    ) -> Result<usize> {
+        let (value, len) = read_value(reader)?;
+
+        match name {
+            "cpu.weight" => {
+                let weight = value.parse::<u64>().map_err(|_| Error::InvalidArgument)?;
+                if !(Self::MIN_WEIGHT..=Self::MAX_WEIGHT).contains(&weight) {
+                    return Err(Error::InvalidArgument);
+                }
+                self.group.set_weight(weight);
+            }
+            "cpu.max" => {
+                // The format is "$MAX $PERIOD" or "$MAX", where the period is unchanged in the
+                // latter case.
+                let mut values = value.split_whitespace();
+                let quota_us = parse_limit(values.next().ok_or(Error::InvalidArgument)?)?;
+                let period_us = match values.next() {
+                    Some(period) => period.parse::<u64>().map_err(|_| Error::InvalidArgument)?,
+                    None => self.group.bandwidth().1 / Self::NSEC_PER_USEC,
+                };
+                if values.next().is_some()
+                    || quota_us.is_some_and(|quota_us| quota_us < Self::MIN_QUOTA_US)
+                    || !(Self::MIN_PERIOD_US..=Self::MAX_PERIOD_US).contains(&period_us)
+                {
+                    return Err(Error::InvalidArgument);
+                }
+
+                let quota_ns = quota_us
+                    .map(|quota_us| quota_us.checked_mul(Self::NSEC_PER_USEC))
+                    .map(|quota_ns| quota_ns.ok_or(Error::InvalidArgument))
+                    .transpose()?;
+                self.group
+                    .set_bandwidth(quota_ns, period_us * Self::NSEC_PER_USEC);
+            }
+            _ => return Err(Error::AttributeError),
+        }
+
+        Ok(len)
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use aster_systree::{Error, Result};
+use ostd::mm::{VmReader, VmWriter, PAGE_SIZE};
+
+use super::{format_limit, parse_limit, read_value, write_value, MAX};
+use crate::{prelude::Errno, process::Process};
+
+/// The `memory` controller, which limits the memory usage of a cgroup.
+///
+/// Only the pages committed to anonymous VMOs are charged. The memory usage of the descendant
+/// cgroups is also counted, so the limit of a cgroup applies to its whole subtree.
+///
+/// FIXME: There is no reclaim or OOM handling. An allocation that would exceed the limit simply
+/// fails.
+#[derive(Debug)]
+pub(in crate::fs::cgroupfs) struct MemoryController {
+    parent: Option<Arc<MemoryController>>,
+    /// The maximum memory usage in bytes, or `u64::MAX` if there is no limit.
+    max: AtomicU64,
+    /// The current memory usage in bytes.
+    current: AtomicU64,
+}
+
+impl MemoryController {
+    pub(in crate::fs::cgroupfs) fn new(parent: Option<Arc<MemoryController>>) -> Arc<Self> {
+        Arc::new(Self {
+            parent,
+            max: AtomicU64::new(u64::MAX),
+            current: AtomicU64::new(0),
+        })
+    }
+
+    /// Charges `bytes` to the cgroup and its ancestors if no limits will be exceeded.
+    ///
+    /// This method returns whether the bytes are charged.
+    fn try_charge(&self, bytes: u64) -> bool {
+        for memory in self.ancestors() {
+            memory.current.fetch_add(bytes, Ordering::Relaxed);
+        }
+
+        if self.ancestors().any(|memory| {
+            memory.current.load(Ordering::Relaxed) > memory.max.load(Ordering::Relaxed)
+        }) {
+            self.uncharge(bytes);
+            return false;
+        }
+
+        true
+    }
+
+    /// Uncharges `bytes` from the cgroup and its ancestors.
+    fn uncharge(&self, bytes: u64) {
+        for memory in self.ancestors() {
+            let old = memory.current.fetch_sub(bytes, Ordering::Relaxed);
+            debug_assert!(old >= bytes);
+        }
+    }
+
+    /// Returns an iterator over the controller and the controllers of the ancestors.
+    fn ancestors(&self) -> impl Iterator<Item = &MemoryController> {
+        core::iter::successors(Some(self), |memory| memory.parent.as_deref())
+    }
+
+    pub(in crate::fs::cgroupfs) fn read_attr(
+        &self,
+        name: &str,
+        writer: &mut VmWriter,
+    ) -> Result<usize> {
+        match name {
+            "memory.current" => {
+                let current = self.current.load(Ordering::Relaxed);
+                write_value(writer, &format_limit(Some(current)))
+            }
+            "memory.max" => {
+                let max = self.max.load(Ordering::Relaxed);
+                write_value(writer, &format_limit((max != u64::MAX).then_some(max)))
+            }
+            _ => Err(Error::AttributeError),
+        }
+    }
+
+    pub(in crate::fs::cgroupfs) fn write_attr(
+        &self,
+        name: &str,
+        reader: &mut VmReader,
+    ) -> Result<usize> {
+        match name {
+            "memory.max" => {
+                let (value, len) = read_value(reader)?;
+                let max = parse_bytes(&value)?.unwrap_or(u64::MAX);
+                self.max.store(max, Ordering::Relaxed);
+                Ok(len)
+            }
+            _ => Err(Error::AttributeError),
+        }
+    }
+}
+
+/// Parses a memory size, which may have a `K`, `M`, or `G` suffix.
+///
+/// The size is rounded down to the page size. `None` is returned if the value is `max`.
+fn parse_bytes(value: &str) -> Result<Option<u64>> {
+    if value == MAX {
+        return Ok(None);
+    }
+
+    let (digits, shift) = match value.as_bytes().last() {
+        Some(b'K' | b'k') => (&value[..value.len() - 1], 10),
+        Some(b'M' | b'm') => (&value[..value.len() - 1], 20),
+        Some(b'G' | b'g') => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    let bytes = parse_limit(digits)?
+        .and_then(|size| size.checked_mul(1 << shift))
+        .ok_or(Error::InvalidArgument)?;
+
+    Ok(Some(bytes / PAGE_SIZE as u64 * PAGE_SIZE as u64))
+}
+
+/// The memory charged to the `memory` controller of a cgroup.
+///
+/// The charged memory is uncharged when the object is dropped.
+#[derive(Debug)]
+pub struct MemoryCharge {
+    memory: Arc<MemoryController>,
+    bytes: AtomicU64,
+}
+
+impl MemoryCharge {
+    /// Creates an empty charge to the cgroup of the current process.
+    ///
+    /// This method returns `None` if the current process is in the root cgroup, or if the current
+    /// task is not associated with a process.
+    pub fn new_for_current() -> Option<Self> {
+        let process = Process::current()?;
+        let cgroup = process.cgroup().lock();
+
+        cgroup.as_ref().map(|cgroup| Self {
+            memory: cgroup.memory().clone(),
+            bytes: AtomicU64::new(0),
+        })
+    }
+
+    /// Charges more memory.
+    ///
+    /// If the limit of the cgroup or its ancestors would be exceeded, this method fails with
+    /// [`Errno::ENOMEM`].
+    pub fn try_charge(&self, bytes: usize) -> crate::Result<()> {
+        if !self.memory.try_charge(bytes as u64) {
+            return_errno_with_message!(Errno::ENOMEM, "the memory limit of the cgroup is exceeded");
+        }
+
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Uncharges the memory that was charged with [`Self::try_charge`].
+    pub fn uncharge(&self, bytes: usize) {
+        let old = self.bytes.fetch_sub(bytes as u64, Ordering::Relaxed);
+        debug_assert!(old >= bytes as u64);
+
+        self.memory.uncharge(bytes as u64);
+    }
+}
+
+impl Drop for MemoryCharge {
+    fn drop(&mut self) {
+        self.memory.uncharge(*self.bytes.get_mut());
+    }
+}
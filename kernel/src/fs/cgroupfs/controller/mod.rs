@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The resource controllers of cgroups.
+//!
+//! The `cpu`, `memory`, and `pids` controllers are supported. They are enabled in every non-root
+//! cgroup, so there is no need to enable them via `cgroup.subtree_control`. Like Linux, the root
+//! cgroup is not limited by any controllers.
+
+use alloc::string::String;
+
+use aster_systree::{Error, Result};
+use ostd::mm::{FallibleVmRead, FallibleVmWrite, VmReader, VmWriter};
+
+mod cpu;
+mod memory;
+mod pids;
+
+pub use memory::MemoryCharge;
+
+pub(super) use self::{cpu::CpuController, memory::MemoryController, pids::PidsController};
+
+/// The names of the supported controllers, as listed in `cgroup.controllers`.
+pub(super) const CONTROLLER_NAMES: &str = "cpu memory pids\n";
+
+/// The value of limits that means "no limit".
+const MAX: &str = "max";
+
+/// Writes the value of an attribute.
+pub(super) fn write_value(writer: &mut VmWriter, value: &str) -> Result<usize> {
+    writer
+        .write_fallible(&mut value.as_bytes().into())
+        .map_err(|_| Error::AttributeError)
+}
+
+/// Reads the new value of an attribute, with the surrounding whitespace trimmed.
+pub(super) fn read_value(reader: &mut VmReader) -> Result<(String, usize)> {
+    let mut buffer = [0u8; 64];
+    let mut writer = VmWriter::from(&mut buffer[..]);
+    let read_len = reader
+        .read_fallible(&mut writer)
+        .map_err(|_| Error::AttributeError)?;
+
+    let value = core::str::from_utf8(&buffer[..read_len]).map_err(|_| Error::InvalidArgument)?;
+    Ok((String::from(value.trim()), read_len))
+}
+
+/// Parses a limit, where `max` means that there is no limit.
+fn parse_limit(value: &str) -> Result<Option<u64>> {
+    if value == MAX {
+        return Ok(None);
+    }
+    value
+        .parse::<u64>()
+        .map(Some)
+        .map_err(|_| Error::InvalidArgument)
+}
+
+/// Formats a limit, where `None` means that there is no limit.
+fn format_limit(limit: Option<u64>) -> String {
+    match limit {
+        Some(limit) => alloc::format!("{}\n", limit),
+        None => alloc::format!("{}\n", MAX),
+    }
+}
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use aster_systree::{Error, Result};
+use ostd::mm::{VmReader, VmWriter};
+
+use super::{format_limit, parse_limit, read_value, write_value};
+
+/// The `pids` controller, which limits the number of tasks in a cgroup.
+///
+/// The tasks in the descendant cgroups are also counted, so the limit of a cgroup applies to its
+/// whole subtree.
+#[derive(Debug)]
+pub(in crate::fs::cgroupfs) struct PidsController {
+    parent: Option<Arc<PidsController>>,
+    /// The maximum number of tasks, or `u64::MAX` if there is no limit.
+    max: AtomicU64,
+    /// The current number of tasks.
+    current: AtomicU64,
+}
+
+impl PidsController {
+    pub(in crate::fs::cgroupfs) fn new(parent: Option<Arc<PidsController>>) -> Arc<Self> {
+        Arc::new(Self {
+            parent,
+            max: AtomicU64::new(u64::MAX),
+            current: AtomicU64::new(0),
+        })
+    }
+
+    /// Charges `num` tasks to the cgroup and its ancestors if no limits will be exceeded.
+    ///
+    /// This method returns whether the tasks are charged.
+    pub(in crate::fs::cgroupfs) fn try_charge(&self, num: u64) -> bool {
+        self.charge(num);
+
+        if self
+            .ancestors()
+            .any(|pids| pids.current.load(Ordering::Relaxed) > pids.max.load(Ordering::Relaxed))
+        {
+            self.uncharge(num);
+            return false;
+        }
+
+        true
+    }
+
+    /// Charges `num` tasks to the cgroup and its ancestors, even if the limits are exceeded.
+    pub(in crate::fs::cgroupfs) fn charge(&self, num: u64) {
+        for pids in self.ancestors() {
+            pids.current.fetch_add(num, Ordering::Relaxed);
+        }
+    }
+
+    /// Uncharges `num` tasks from the cgroup and its ancestors.
+    pub(in crate::fs::cgroupfs) fn uncharge(&self, num: u64) {
+        for pids in self.ancestors() {
+            let old = pids.current.fetch_sub(num, Ordering::Relaxed);
+            debug_assert!(old >= num);
+        }
+    }
+
+    /// Returns an iterator over the controller and the controllers of the ancestors.
+    fn ancestors(&self) -> impl Iterator<Item = &PidsController> {
+        core::iter::successors(Some(self), |pids| pids.parent.as_deref())
+    }
+
+    pub(in crate::fs::cgroupfs) fn read_attr(
+        &self,
+        name: &str,
+        writer: &mut VmWriter,
+    ) -> Result<usize> {
+        match name {
+            "pids.current" => {
+                let current = self.current.load(Ordering::Relaxed);
+                write_value(writer, &format_limit(Some(current)))
+            }
+            "pids.max" => {
+                let max = self.max.load(Ordering::Relaxed);
+                write_value(writer, &format_limit((max != u64::MAX).then_some(max)))
+            }
+            _ => Err(Error::AttributeError),
+        }
+    }
+
+    pub(in crate::fs::cgroupfs) fn write_attr(
+        &self,
+        name: &str,
+        reader: &mut VmReader,
+    ) -> Result<usize> {
+        match name {
+            "pids.max" => {
+                let (value, len) = read_value(reader)?;
+                let max = parse_limit(&value)?.unwrap_or(u64::MAX);
+                self.max.store(max, Ordering::Relaxed);
+                Ok(len)
+            }
+            _ => Err(Error::AttributeError),
+        }
+    }
+}
@@ -80,6 +80,7 @@ impl FsType for CgroupFsType {
 
     fn create(
         &self,
+        _source: &CStr,
         _args: Option<CString>,
         _disk: Option<Arc<dyn BlockDevice>>,
         _ctx: &Context,
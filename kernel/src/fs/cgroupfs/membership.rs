@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The membership of processes in cgroups.
+//!
+//! Each process belongs to exactly one cgroup, which is recorded in [`Process::cgroup`]. The
+//! threads of a process always belong to the same cgroup as the process.
+
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
+
+use aster_systree::{Error, Result, SysObj, SysStr};
+use ostd::mm::{VmReader, VmWriter};
+
+use super::{
+    controller::{read_value, write_value},
+    CgroupNode,
+};
+use crate::{
+    current_thread,
+    fs::utils::{FileSystem, Permission},
+    process::{posix_thread::AsPosixThread, process_table, Pid, Process},
+    thread::AsThread,
+};
+
+/// Reads `cgroup.procs`, which lists the PIDs of the processes in the cgroup.
+///
+/// The root cgroup is specified by `None`.
+pub(super) fn read_procs(cgroup: Option<&CgroupNode>, writer: &mut VmWriter) -> Result<usize> {
+    let current_thread = current_thread!();
+    let pid_ns = current_thread.as_posix_thread().unwrap().pid_ns();
+
+    let mut procs = String::new();
+    for process in all_processes() {
+        // Zombie processes are not listed, since all their threads have exited.
+        if process.status().is_zombie() || !is_member(&process, cgroup) {
+            continue;
+        }
+
+        // Processes that are invisible in the PID namespace are not listed.
+        let pid = pid_ns.to_local(process.pid());
+        if pid != 0 {
+            procs.push_str(&format!("{}\n", pid));
+        }
+    }
+
+    write_value(writer, &procs)
+}
+
+/// Writes `cgroup.procs`, which moves a process to the cgroup.
+///
+/// The root cgroup is specified by `None`. The PID of zero means the current process.
+pub(super) fn write_procs(cgroup: Option<&CgroupNode>, reader: &mut VmReader) -> Result<usize> {
+    let (value, len) = read_value(reader)?;
+    let pid = value.parse::<Pid>().map_err(|_| Error::InvalidArgument)?;
+
+    let current_thread = current_thread!();
+    let posix_thread = current_thread.as_posix_thread().unwrap();
+    let process = if pid == 0 {
+        posix_thread.process()
+    } else {
+        posix_thread
+            .pid_ns()
+            .get_process(pid)
+            .ok_or(Error::NotFound)?
+    };
+
+    check_migrate_permission(&process, cgroup)?;
+    migrate(&process, cgroup.map(CgroupNode::this));
+
+    Ok(len)
+}
+
+/// Checks whether the current thread can move the process to the new cgroup.
+///
+/// Like Linux, the current thread must be able to write `cgroup.procs` of the common ancestor of
+/// the old and the new cgroups, in addition to that of the new cgroup.
+fn check_migrate_permission(process: &Process, new_cgroup: Option<&CgroupNode>) -> Result<()> {
+    let old_path = cgroup_path(process.cgroup().lock().as_deref());
+    let new_path = cgroup_path(new_cgroup);
+    let common_len = old_path
+        .iter()
+        .zip(new_path.iter())
+        .take_while(|(old_name, new_name)| old_name == new_name)
+        .count();
+
+    let mut inode = super::singleton().root_inode();
+    for name in &new_path[..common_len] {
+        inode = inode.lookup(name).map_err(|_| Error::NotFound)?;
+    }
+    inode
+        .lookup("cgroup.procs")
+        .and_then(|procs| procs.check_permission(Permission::MAY_WRITE))
+        .map_err(|_| Error::PermissionDenied)
+}
+
+/// Returns the names of the cgroups from the root (exclusive) to the cgroup (inclusive).
+///
+/// The root cgroup is specified by `None`.
+fn cgroup_path(cgroup: Option<&CgroupNode>) -> Vec<SysStr> {
+    let Some(cgroup) = cgroup else {
+        return Vec::new();
+    };
+
+    let mut names = vec![cgroup.name().clone()];
+    let mut parent = cgroup.parent();
+    while let Some(node) = parent.filter(|node| !node.is_root()) {
+        names.push(node.name().clone());
+        parent = node.parent();
+    }
+    names.reverse();
+    names
+}
+
+/// Applies the weight of the cgroup to the threads in the cgroup and its descendants.
+pub(super) fn refresh_fair_groups(cgroup: &CgroupNode) {
+    for process in all_processes() {
+        let process_cgroup = process.cgroup().lock();
+        let Some(process_cgroup) = process_cgroup.as_ref() else {
+            continue;
+        };
+        if !process_cgroup.is_descendant_of(cgroup) {
+            continue;
+        }
+
+        for task in process.tasks().lock().as_slice() {
+            let thread = task.as_thread().unwrap();
+            thread
+                .sched_attr()
+                .set_group(Some(process_cgroup.fair_group().clone()));
+        }
+    }
+}
+
+/// Moves the process and all its threads to the new cgroup.
+///
+/// The threads are charged to the `pids` controller of the new cgroup even if its limit is
+/// exceeded, which is the same as Linux. The memory that has been charged to the old cgroup is
+/// not moved.
+fn migrate(process: &Process, new_cgroup: Option<Arc<CgroupNode>>) {
+    // Lock order: cgroup of process -> tasks of process
+    let mut cgroup = process.cgroup().lock();
+    let tasks = process.tasks().lock();
+
+    let num_threads = tasks
+        .as_slice()
+        .iter()
+        .filter(|task| !task.as_thread().unwrap().is_exited())
+        .count() as u64;
+    if let Some(old_cgroup) = cgroup.as_ref() {
+        old_cgroup.uncharge_pids(num_threads);
+    }
+    if let Some(new_cgroup) = new_cgroup.as_ref() {
+        new_cgroup.charge_pids(num_threads);
+    }
+
+    let fair_group = new_cgroup
+        .as_ref()
+        .map(|new_cgroup| new_cgroup.fair_group().clone());
+    for task in tasks.as_slice() {
+        let thread = task.as_thread().unwrap();
+        thread.sched_attr().set_group(fair_group.clone());
+    }
+
+    *cgroup = new_cgroup;
+}
+
+fn is_member(process: &Process, cgroup: Option<&CgroupNode>) -> bool {
+    let process_cgroup = process.cgroup().lock();
+    match (process_cgroup.as_deref(), cgroup) {
+        (None, None) => true,
+        (Some(process_cgroup), Some(cgroup)) => core::ptr::eq(process_cgroup, cgroup),
+        _ => false,
+    }
+}
+
+/// Collects all processes so that the process table is not locked while inspecting them.
+fn all_processes() -> Vec<Arc<Process>> {
+    process_table::process_table_mut().iter().cloned().collect()
+}
@@ -2,14 +2,26 @@
 
 use alloc::sync::Arc;
 
+use aster_systree::SysObj;
+pub use controller::MemoryCharge;
 use fs::CgroupFs;
 pub use inode::CgroupInode;
 use spin::Once;
+pub use systree_node::CgroupNode;
 
-use crate::fs::cgroupfs::{fs::CgroupFsType, systree_node::CgroupSystem};
+use crate::{
+    fs::{
+        cgroupfs::{fs::CgroupFsType, systree_node::CgroupSystem},
+        file_handle::FileLike,
+        utils::systree_inode::{SysTreeInodeTy, SysTreeNodeKind},
+    },
+    prelude::*,
+};
 
+mod controller;
 mod fs;
 mod inode;
+mod membership;
 mod systree_node;
 
 static CGROUP_SINGLETON: Once<Arc<CgroupFs>> = Once::new();
@@ -27,3 +39,29 @@ pub fn init() {
     let cgroup_fs_type = CgroupFsType::new(cgroup_root);
     super::registry::register(cgroup_fs_type).unwrap();
 }
+
+/// Returns the cgroup that a directory in the cgroup file system refers to.
+///
+/// The root cgroup is represented by `None`. If the file is not a directory in the cgroup file
+/// system, this function fails with [`Errno::EBADF`].
+pub fn cgroup_of_file(file: &dyn FileLike) -> Result<Option<Arc<CgroupNode>>> {
+    let not_cgroup_err = || Error::with_message(Errno::EBADF, "the file is not a cgroup directory");
+
+    let inode_handle = file.as_inode_or_err().map_err(|_| not_cgroup_err())?;
+    let inode = inode_handle.path().inode();
+    let Some(cgroup_inode) = inode.downcast_ref::<CgroupInode>() else {
+        return Err(not_cgroup_err());
+    };
+    let SysTreeNodeKind::Branch(node) = cgroup_inode.node_kind() else {
+        return Err(not_cgroup_err());
+    };
+
+    if node.as_any().is::<CgroupSystem>() {
+        return Ok(None);
+    }
+    let cgroup = node
+        .as_any()
+        .downcast_ref::<CgroupNode>()
+        .ok_or_else(not_cgroup_err)?;
+    Ok(Some(cgroup.this()))
+}
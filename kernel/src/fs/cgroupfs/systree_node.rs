@@ -13,6 +13,12 @@ use aster_systree::{
 use inherit_methods_macro::inherit_methods;
 use ostd::mm::{VmReader, VmWriter};
 
+use super::{
+    controller::{write_value, CpuController, MemoryController, PidsController, CONTROLLER_NAMES},
+    membership,
+};
+use crate::{prelude::Errno, sched::FairGroup};
+
 /// The root of a cgroup hierarchy, serving as the entry point to
 /// the entire cgroup control system.
 ///
@@ -31,6 +37,9 @@ pub struct CgroupSystem {
 #[derive(Debug)]
 pub struct CgroupNode {
     fields: BranchNodeFields<CgroupNode, Self>,
+    cpu: CpuController,
+    memory: Arc<MemoryController>,
+    pids: Arc<PidsController>,
 }
 
 #[inherit_methods(from = "self.fields")]
@@ -68,6 +77,10 @@ impl CgroupSystem {
             SysPerms::DEFAULT_RW_ATTR_PERMS,
         );
         builder.add(SysStr::from("cpu.stat"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+        builder.add(
+            SysStr::from("cgroup.procs"),
+            SysPerms::DEFAULT_RW_ATTR_PERMS,
+        );
 
         let attrs = builder.build().expect("Failed to build attribute set");
         Arc::new_cyclic(|weak_self| {
@@ -78,7 +91,7 @@ impl CgroupSystem {
 }
 
 impl CgroupNode {
-    pub(super) fn new(name: SysStr) -> Arc<Self> {
+    pub(super) fn new(name: SysStr, parent: Option<&CgroupNode>) -> Arc<Self> {
         let mut builder = SysAttrSetBuilder::new();
         // TODO: Add more attributes as needed. The normal cgroup node may have
         // more attributes than the unified one.
@@ -99,13 +112,91 @@ impl CgroupNode {
             SysPerms::DEFAULT_RW_ATTR_PERMS,
         );
         builder.add(SysStr::from("cpu.stat"), SysPerms::DEFAULT_RO_ATTR_PERMS);
+        builder.add(
+            SysStr::from("cgroup.procs"),
+            SysPerms::DEFAULT_RW_ATTR_PERMS,
+        );
+        builder.add(SysStr::from("cpu.weight"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        builder.add(SysStr::from("cpu.max"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        builder.add(
+            SysStr::from("memory.current"),
+            SysPerms::DEFAULT_RO_ATTR_PERMS,
+        );
+        builder.add(SysStr::from("memory.max"), SysPerms::DEFAULT_RW_ATTR_PERMS);
+        builder.add(
+            SysStr::from("pids.current"),
+            SysPerms::DEFAULT_RO_ATTR_PERMS,
+        );
+        builder.add(SysStr::from("pids.max"), SysPerms::DEFAULT_RW_ATTR_PERMS);
 
         let attrs = builder.build().expect("Failed to build attribute set");
         Arc::new_cyclic(|weak_self| {
             let fields = BranchNodeFields::new(name, attrs, weak_self.clone());
-            CgroupNode { fields }
+            CgroupNode {
+                fields,
+                cpu: CpuController::new(parent.map(|parent| &parent.cpu)),
+                memory: MemoryController::new(parent.map(|parent| parent.memory.clone())),
+                pids: PidsController::new(parent.map(|parent| parent.pids.clone())),
+            }
         })
     }
+
+    /// Charges `num` threads to the `pids` controller of the cgroup.
+    ///
+    /// If the limit of the cgroup or its ancestors would be exceeded, this method fails with
+    /// [`Errno::EAGAIN`].
+    pub fn try_charge_pids(&self, num: u64) -> crate::Result<()> {
+        if !self.pids.try_charge(num) {
+            return_errno_with_message!(
+                Errno::EAGAIN,
+                "the limit of the `pids` controller is reached"
+            );
+        }
+        Ok(())
+    }
+
+    /// Uncharges `num` threads from the `pids` controller of the cgroup.
+    pub fn uncharge_pids(&self, num: u64) {
+        self.pids.uncharge(num);
+    }
+
+    /// Returns the scheduling group of the `cpu` controller of the cgroup.
+    pub fn fair_group(&self) -> &Arc<FairGroup> {
+        self.cpu.group()
+    }
+
+    /// Charges `num` threads to the `pids` controller of the cgroup, even if the limit is
+    /// exceeded.
+    ///
+    /// This is used to migrate threads to the cgroup, which never fails due to the limit.
+    pub(super) fn charge_pids(&self, num: u64) {
+        self.pids.charge(num);
+    }
+
+    pub(super) fn memory(&self) -> &Arc<MemoryController> {
+        &self.memory
+    }
+
+    /// Returns whether the cgroup is `ancestor` or one of its descendants.
+    pub(super) fn is_descendant_of(&self, ancestor: &CgroupNode) -> bool {
+        if self.id() == ancestor.id() {
+            return true;
+        }
+
+        let mut parent = self.parent();
+        while let Some(node) = parent {
+            if node.id() == ancestor.id() {
+                return true;
+            }
+            parent = node.parent();
+        }
+        false
+    }
+
+    /// Returns the cgroup as a reference-counted pointer.
+    pub(super) fn this(&self) -> Arc<CgroupNode> {
+        self.fields.weak_self().upgrade().unwrap()
+    }
 }
 
 inherit_sys_branch_node!(CgroupSystem, fields, {
@@ -117,14 +208,21 @@ inherit_sys_branch_node!(CgroupSystem, fields, {
         // This method should be a no-op for `RootNode`.
     }
 
-    fn read_attr(&self, _name: &str, _writer: &mut VmWriter) -> Result<usize> {
-        // TODO: Add support for reading attributes.
-        Err(Error::AttributeError)
+    fn read_attr(&self, name: &str, writer: &mut VmWriter) -> Result<usize> {
+        match name {
+            "cgroup.controllers" => write_value(writer, CONTROLLER_NAMES),
+            "cgroup.procs" => membership::read_procs(None, writer),
+            // TODO: Add support for reading other attributes.
+            _ => Err(Error::AttributeError),
+        }
     }
 
-    fn write_attr(&self, _name: &str, _reader: &mut VmReader) -> Result<usize> {
-        // TODO: Add support for writing attributes.
-        Err(Error::AttributeError)
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        match name {
+            "cgroup.procs" => membership::write_procs(None, reader),
+            // TODO: Add support for writing other attributes.
+            _ => Err(Error::AttributeError),
+        }
     }
 
     fn perms(&self) -> SysPerms {
@@ -132,21 +230,39 @@ inherit_sys_branch_node!(CgroupSystem, fields, {
     }
 
     fn create_child(&self, name: &str) -> Result<Arc<dyn SysObj>> {
-        let new_child = CgroupNode::new(name.to_string().into());
+        let new_child = CgroupNode::new(name.to_string().into(), None);
         self.add_child(new_child.clone())?;
         Ok(new_child)
     }
 });
 
 inherit_sys_branch_node!(CgroupNode, fields, {
-    fn read_attr(&self, _name: &str, _writer: &mut VmWriter) -> Result<usize> {
-        // TODO: Add support for reading attributes.
-        Err(Error::AttributeError)
+    fn read_attr(&self, name: &str, writer: &mut VmWriter) -> Result<usize> {
+        match name {
+            "cgroup.controllers" => write_value(writer, CONTROLLER_NAMES),
+            "cgroup.procs" => membership::read_procs(Some(self), writer),
+            _ if name.starts_with("cpu.") => self.cpu.read_attr(name, writer),
+            _ if name.starts_with("memory.") => self.memory.read_attr(name, writer),
+            _ if name.starts_with("pids.") => self.pids.read_attr(name, writer),
+            // TODO: Add support for reading other attributes.
+            _ => Err(Error::AttributeError),
+        }
     }
 
-    fn write_attr(&self, _name: &str, _reader: &mut VmReader) -> Result<usize> {
-        // TODO: Add support for writing attributes.
-        Err(Error::AttributeError)
+    fn write_attr(&self, name: &str, reader: &mut VmReader) -> Result<usize> {
+        match name {
+            "cgroup.procs" => membership::write_procs(Some(self), reader),
+            "cpu.weight" => {
+                let len = self.cpu.write_attr(name, reader)?;
+                membership::refresh_fair_groups(self);
+                Ok(len)
+            }
+            _ if name.starts_with("cpu.") => self.cpu.write_attr(name, reader),
+            _ if name.starts_with("memory.") => self.memory.write_attr(name, reader),
+            _ if name.starts_with("pids.") => self.pids.write_attr(name, reader),
+            // TODO: Add support for writing other attributes.
+            _ => Err(Error::AttributeError),
+        }
     }
 
     fn perms(&self) -> SysPerms {
@@ -154,7 +270,7 @@ inherit_sys_branch_node!(CgroupNode, fields, {
     }
 
     fn create_child(&self, name: &str) -> Result<Arc<dyn SysObj>> {
-        let new_child = CgroupNode::new(name.to_string().into());
+        let new_child = CgroupNode::new(name.to_string().into(), Some(self));
         self.add_child(new_child.clone())?;
         Ok(new_child)
     }
@@ -114,6 +114,7 @@ impl FsType for DevPtsType {
 
     fn create(
         &self,
+        _source: &CStr,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
         _ctx: &Context,
@@ -11,10 +11,13 @@ use super::{
     fs::ExfatFS,
     inode::FatAttr,
     upcase_table::ExfatUpcaseTable,
-    utils::{calc_checksum_16, DosTimestamp},
+    utils::calc_checksum_16,
 };
 use crate::{
-    fs::utils::{InodeMode, InodeType},
+    fs::{
+        fat_common::DosTimestamp,
+        utils::{InodeMode, InodeType},
+    },
     prelude::*,
     vm::vmo::Vmo,
 };
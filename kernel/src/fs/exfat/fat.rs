@@ -7,19 +7,11 @@ use super::{
     constants::{EXFAT_FIRST_CLUSTER, EXFAT_RESERVED_CLUSTERS},
     fs::ExfatFS,
 };
+pub use crate::fs::fat_common::{ClusterID, FatValue};
 use crate::prelude::*;
 
-pub type ClusterID = u32;
 pub(super) const FAT_ENTRY_SIZE: usize = size_of::<ClusterID>();
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-pub enum FatValue {
-    Free,
-    Next(ClusterID),
-    Bad,
-    EndOfChain,
-}
-
 const EXFAT_EOF_CLUSTER: ClusterID = 0xFFFFFFFF;
 const EXFAT_BAD_CLUSTER: ClusterID = 0xFFFFFFF7;
 const EXFAT_FREE_CLUSTER: ClusterID = 0;
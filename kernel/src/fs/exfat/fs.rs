@@ -460,6 +460,7 @@ impl FsType for ExfatType {
 
     fn create(
         &self,
+        _source: &CStr,
         _args: Option<CString>,
         disk: Option<Arc<dyn BlockDevice>>,
         ctx: &Context,
@@ -23,16 +23,16 @@ use super::{
     },
     fat::{ClusterAllocator, ClusterID, ExfatChainPosition, FatChainFlags},
     fs::{ExfatMountOptions, EXFAT_ROOT_INO},
-    utils::{make_hash_index, DosTimestamp},
 };
 use crate::{
     events::IoEvents,
     fs::{
         exfat::{dentry::ExfatDentryIterator, fat::ExfatChain, fs::ExfatFS},
+        fat_common::{make_hash_index, DosTimestamp},
         path::{is_dot, is_dot_or_dotdot, is_dotdot},
         utils::{
-            CachePage, DirentVisitor, Extension, Inode, InodeMode, InodeType, IoctlCmd, Metadata,
-            MknodType, PageCache, PageCacheBackend,
+            copy_from_page_cache, CachePage, DirentVisitor, Extension, Inode, InodeMode, InodeType,
+            IoctlCmd, Metadata, MknodType, PageCache, PageCacheBackend,
         },
     },
     prelude::*,
@@ -1416,6 +1416,27 @@ impl Inode for ExfatInode {
         Ok(write_len)
     }
 
+    fn copy_file_range(
+        &self,
+        offset: usize,
+        dst: &Arc<dyn Inode>,
+        dst_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        let (page_cache, file_size) = {
+            let inner = self.inner.read();
+            if inner.inode_type.is_directory() {
+                return_errno!(Errno::EISDIR)
+            }
+            (inner.page_cache.pages().dup(), inner.size)
+        };
+        let copied_len =
+            copy_from_page_cache(&page_cache, file_size, offset, dst, dst_offset, len)?;
+
+        self.inner.write().update_atime()?;
+        Ok(copied_len)
+    }
+
     fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
         let fs = self.inner.read().fs();
         let fs_guard = fs.lock();
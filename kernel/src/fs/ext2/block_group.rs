@@ -9,6 +9,7 @@ use super::{
     inode::{Inode, InodeDesc, RawInode},
     prelude::*,
     super_block::SuperBlock,
+    utils::{crc16, crc32c},
 };
 
 /// Blocks are clustered into block groups in order to reduce fragmentation and minimise
@@ -42,10 +43,19 @@ impl BlockGroup {
                 let descriptor = {
                     // Read the block group descriptor
                     // TODO: if the main is corrupted, should we load the backup?
-                    let offset = idx * core::mem::size_of::<RawGroupDescriptor>();
-                    let raw_descriptor = group_descriptors_segment
-                        .read_val::<RawGroupDescriptor>(offset)
+                    let desc_size = super_block.desc_size();
+                    let mut raw_descriptor = RawGroupDescriptor::new_zeroed();
+                    group_descriptors_segment
+                        .read_bytes(
+                            idx * desc_size,
+                            &mut raw_descriptor.as_bytes_mut()[..desc_size],
+                        )
                         .unwrap();
+                    if super_block.has_group_desc_csum()
+                        && raw_descriptor.checksum != raw_descriptor.calc_checksum(idx, super_block)
+                    {
+                        return_errno_with_message!(Errno::EBADMSG, "bad group descriptor checksum");
+                    }
                     GroupDescriptor::from(raw_descriptor)
                 };
 
@@ -58,14 +68,23 @@ impl BlockGroup {
                     Ok(IdAlloc::from_bytes_with_capacity(&buf, capacity))
                 };
 
-                let block_bitmap = get_bitmap(
-                    descriptor.block_bitmap_bid,
-                    super_block.blocks_per_group() as usize,
-                )?;
-                let inode_bitmap = get_bitmap(
-                    descriptor.inode_bitmap_bid,
-                    super_block.inodes_per_group() as usize,
-                )?;
+                // The bitmaps of the uninitialized groups are not present on the device.
+                let block_bitmap = if descriptor.flags.contains(GroupFlags::BLOCK_UNINIT) {
+                    init_block_bitmap(idx, &descriptor, super_block)
+                } else {
+                    get_bitmap(
+                        descriptor.block_bitmap_bid,
+                        super_block.blocks_per_group() as usize,
+                    )?
+                };
+                let inode_bitmap = if descriptor.flags.contains(GroupFlags::INODE_UNINIT) {
+                    IdAlloc::with_capacity(super_block.inodes_per_group() as usize)
+                } else {
+                    get_bitmap(
+                        descriptor.inode_bitmap_bid,
+                        super_block.inodes_per_group() as usize,
+                    )?
+                };
 
                 GroupMetadata {
                     descriptor,
@@ -127,17 +146,25 @@ impl BlockGroup {
     /// This method may load the raw inode metadata from block device.
     fn load_inode(&self, inode_idx: u32) -> Result<Arc<Inode>> {
         let fs = self.fs();
+        let ino = inode_idx + self.idx as u32 * fs.inodes_per_group() + 1;
         let raw_inode = {
             let offset = (inode_idx as usize) * fs.inode_size();
+            let mut slot = vec![0u8; fs.inode_size()];
             self.raw_inodes_cache
                 .pages()
-                .read_val::<RawInode>(offset)
-                .unwrap()
+                .read_bytes(offset, &mut slot)
+                .unwrap();
+            let raw_inode = RawInode::from_bytes(&slot[..core::mem::size_of::<RawInode>()]);
+            if let Some(seed) = fs.inode_csum_seed(ino, raw_inode.generation) {
+                if !RawInode::verify_checksum(&slot, seed) {
+                    return_errno_with_message!(Errno::EBADMSG, "bad inode checksum");
+                }
+            }
+            raw_inode
         };
         let inode_desc = Dirty::new(InodeDesc::try_from(raw_inode)?);
-        let ino = inode_idx + self.idx as u32 * fs.inodes_per_group() + 1;
 
-        Ok(Inode::new(ino, self.idx, inode_desc, Arc::downgrade(&fs)))
+        Inode::new(ino, self.idx, inode_desc, Arc::downgrade(&fs))
     }
 
     /// Inserts the inode into the inode cache.
@@ -152,6 +179,8 @@ impl BlockGroup {
     }
 
     /// Allocates and returns an inode index.
+    ///
+    /// The raw inode of the allocated index is reset to zeros.
     pub fn alloc_inode(&self, is_dir: bool) -> Option<u32> {
         // The fast path
         if self.bg_impl.inner.read().metadata.free_inodes_count() == 0 {
@@ -159,7 +188,27 @@ impl BlockGroup {
         }
 
         // The slow path
-        self.bg_impl.inner.write().metadata.alloc_inode(is_dir)
+        let fs = self.fs();
+        let inode_idx = {
+            let mut inner = self.bg_impl.inner.write();
+            let inode_idx = inner.metadata.alloc_inode(is_dir)?;
+            // The inodes after the highest used one are not initialized.
+            let unused = (fs.inodes_per_group() - inode_idx - 1) as u16;
+            if inner.metadata.descriptor.itable_unused > unused {
+                inner.metadata.descriptor.itable_unused = unused;
+            }
+            inode_idx
+        };
+
+        // The inode table may not be initialized, so the stale inode should be cleared.
+        let inode_size = fs.inode_size();
+        let mut slot = vec![0u8; inode_size];
+        RawInode::init_slot(&mut slot);
+        self.raw_inodes_cache
+            .pages()
+            .write_bytes(inode_idx as usize * inode_size, &slot)
+            .unwrap();
+        Some(inode_idx)
     }
 
     /// Frees the allocated inode idx.
@@ -211,39 +260,87 @@ impl BlockGroup {
 
     /// Writes back the raw inode metadata to the raw inode metadata cache.
     pub fn sync_raw_inode(&self, inode_idx: u32, raw_inode: &RawInode) {
-        let offset = (inode_idx as usize) * self.fs().inode_size();
+        let fs = self.fs();
+        let offset = (inode_idx as usize) * fs.inode_size();
+        let ino = inode_idx + self.idx as u32 * fs.inodes_per_group() + 1;
+        let Some(seed) = fs.inode_csum_seed(ino, raw_inode.generation) else {
+            self.raw_inodes_cache
+                .pages()
+                .write_val(offset, raw_inode)
+                .unwrap();
+            return;
+        };
+
+        // The checksum covers the whole slot, including the fields unknown to us.
+        let mut slot = vec![0u8; fs.inode_size()];
+        self.raw_inodes_cache
+            .pages()
+            .read_bytes(offset, &mut slot)
+            .unwrap();
+        slot[..core::mem::size_of::<RawInode>()].copy_from_slice(raw_inode.as_bytes());
+        RawInode::update_checksum(&mut slot, seed);
         self.raw_inodes_cache
             .pages()
-            .write_val(offset, raw_inode)
+            .write_bytes(offset, &slot)
             .unwrap();
     }
 
     /// Writes back the metadata of this group.
-    pub fn sync_metadata(&self) -> Result<()> {
+    pub fn sync_metadata(&self, super_block: &SuperBlock) -> Result<()> {
         if !self.bg_impl.inner.read().metadata.is_dirty() {
             return Ok(());
         }
 
         let mut inner = self.bg_impl.inner.write();
         let fs = self.fs();
+
+        // The bitmaps are padded with ones to fill the blocks.
+        let to_block = |bitmap: &IdAlloc, len: usize| -> Vec<u8> {
+            let mut buf = vec![0xffu8; BLOCK_SIZE];
+            buf[..len].copy_from_slice(&bitmap.as_bytes()[..len]);
+            buf
+        };
+        let inode_bitmap = to_block(
+            &inner.metadata.inode_bitmap,
+            (super_block.inodes_per_group() / 8) as usize,
+        );
+        let block_bitmap = to_block(
+            &inner.metadata.block_bitmap,
+            (super_block.blocks_per_group() / 8) as usize,
+        );
+
         // Writes back the descriptor.
-        let raw_descriptor = RawGroupDescriptor::from(&inner.metadata.descriptor);
-        self.fs().sync_group_descriptor(self.idx, &raw_descriptor)?;
+        let mut raw_descriptor = RawGroupDescriptor::from(&inner.metadata.descriptor);
+        if super_block.has_metadata_csum() {
+            let seed = super_block.checksum_seed();
+            let inode_bitmap_csum = crc32c(
+                seed,
+                &inode_bitmap[..(super_block.inodes_per_group() / 8) as usize],
+            );
+            let block_bitmap_csum = crc32c(
+                seed,
+                &block_bitmap[..(super_block.blocks_per_group() / 8) as usize],
+            );
+            raw_descriptor.inode_bitmap_csum = inode_bitmap_csum as u16;
+            raw_descriptor.block_bitmap_csum = block_bitmap_csum as u16;
+            if super_block.desc_size() >= core::mem::size_of::<RawGroupDescriptor>() {
+                raw_descriptor.inode_bitmap_csum_hi = (inode_bitmap_csum >> 16) as u16;
+                raw_descriptor.block_bitmap_csum_hi = (block_bitmap_csum >> 16) as u16;
+            }
+        }
+        if super_block.has_group_desc_csum() {
+            raw_descriptor.checksum = raw_descriptor.calc_checksum(self.idx, super_block);
+        }
+        fs.sync_group_descriptor(self.idx, &raw_descriptor)?;
 
         let mut bio_waiter = BioWaiter::new();
         // Writes back the inode bitmap.
-        let inode_bitmap_bid = Bid::new(inner.metadata.descriptor.inode_bitmap_bid as u64);
-        bio_waiter.concat(fs.block_device().write_bytes_async(
-            inode_bitmap_bid.to_offset(),
-            inner.metadata.inode_bitmap.as_bytes(),
-        )?);
+        let inode_bitmap_bid = inner.metadata.descriptor.inode_bitmap_bid;
+        bio_waiter.concat(fs.write_metadata_async(inode_bitmap_bid, &inode_bitmap)?);
 
         // Writes back the block bitmap.
-        let block_bitmap_bid = Bid::new(inner.metadata.descriptor.block_bitmap_bid as u64);
-        bio_waiter.concat(fs.block_device().write_bytes_async(
-            block_bitmap_bid.to_offset(),
-            inner.metadata.block_bitmap.as_bytes(),
-        )?);
+        let block_bitmap_bid = inner.metadata.descriptor.block_bitmap_bid;
+        bio_waiter.concat(fs.write_metadata_async(block_bitmap_bid, &block_bitmap)?);
 
         // Waits for the completion of all submitted bios.
         bio_waiter.wait().ok_or_else(|| {
@@ -335,16 +432,10 @@ impl PageCacheBackend for BlockGroupImpl {
 
     fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
         let bid = self.inode_table_bid + idx as Ext2Bid;
-        let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
-        // This requires an additional copy to the pooled bio segment.
-        bio_segment
-            .writer()
-            .unwrap()
-            .write_fallible(&mut frame.reader().to_fallible())?;
-        self.fs
-            .upgrade()
-            .unwrap()
-            .write_blocks_async(bid, bio_segment)
+        // This requires an additional copy to the buffer.
+        let mut block = vec![0u8; BLOCK_SIZE];
+        frame.reader().read(&mut VmWriter::from(&mut block[..]));
+        self.fs.upgrade().unwrap().write_metadata_async(bid, &block)
     }
 
     fn npages(&self) -> usize {
@@ -376,6 +467,7 @@ impl GroupMetadata {
         if is_dir {
             self.inc_dirs();
         }
+        self.descriptor.flags.remove(GroupFlags::INODE_UNINIT);
         Some(inode_idx as u32)
     }
 
@@ -400,6 +492,7 @@ impl GroupMetadata {
                 continue;
             };
             self.dec_free_blocks(current_count as u16);
+            self.descriptor.flags.remove(GroupFlags::BLOCK_UNINIT);
             return Some((range.start as Ext2Bid)..(range.end as Ext2Bid));
         }
         None
@@ -454,6 +547,50 @@ impl GroupMetadata {
     }
 }
 
+/// Initializes the block bitmap of a group with the `GroupFlags::BLOCK_UNINIT` flag.
+///
+/// Such a group contains no data blocks, so only the blocks of the metadata are in use.
+fn init_block_bitmap(idx: usize, desc: &GroupDescriptor, super_block: &SuperBlock) -> IdAlloc {
+    let blocks_per_group = super_block.blocks_per_group();
+    let mut bitmap = IdAlloc::with_capacity(blocks_per_group as usize);
+    let group_range = {
+        let start = idx as Ext2Bid * blocks_per_group;
+        start..start + blocks_per_group
+    };
+
+    // The superblock and the group descriptor table.
+    if idx == 0 || super_block.is_backup_group(idx) {
+        let gdt_blocks = (super_block.block_groups_count() as usize * super_block.desc_size())
+            .div_ceil(BLOCK_SIZE) as Ext2Bid;
+        let super_block_bid = super_block.bid(idx).to_raw() as Ext2Bid;
+        let end = super_block_bid + 1 + gdt_blocks + super_block.reserved_gdt_blocks();
+        for bid in super_block_bid..end {
+            bitmap.alloc_specific((bid - group_range.start) as usize);
+        }
+    }
+
+    // The bitmaps and the inode table, which may be placed in other groups.
+    let inode_table_blocks = (super_block.inodes_per_group() as usize * super_block.inode_size())
+        .div_ceil(BLOCK_SIZE) as Ext2Bid;
+    let metadata_blocks = [
+        desc.block_bitmap_bid..desc.block_bitmap_bid + 1,
+        desc.inode_bitmap_bid..desc.inode_bitmap_bid + 1,
+        desc.inode_table_bid..desc.inode_table_bid + inode_table_blocks,
+    ];
+    for bid in metadata_blocks.into_iter().flatten() {
+        if group_range.contains(&bid) {
+            bitmap.alloc_specific((bid - group_range.start) as usize);
+        }
+    }
+
+    // The blocks beyond the end of the device.
+    for bid in super_block.total_blocks().max(group_range.start)..group_range.end {
+        bitmap.alloc_specific((bid - group_range.start) as usize);
+    }
+
+    bitmap
+}
+
 /// The in-memory rust block group descriptor.
 ///
 /// The block group descriptor contains information regarding where important data
@@ -472,6 +609,10 @@ struct GroupDescriptor {
     free_inodes_count: u16,
     /// Number of directories in group
     dirs_count: u16,
+    /// Flags of the group
+    flags: GroupFlags,
+    /// Number of unused inodes at the end of the inode table
+    itable_unused: u16,
 }
 
 impl From<RawGroupDescriptor> for GroupDescriptor {
@@ -483,15 +624,32 @@ impl From<RawGroupDescriptor> for GroupDescriptor {
             free_blocks_count: desc.free_blocks_count,
             free_inodes_count: desc.free_inodes_count,
             dirs_count: desc.dirs_count,
+            flags: GroupFlags::from_bits_truncate(desc.flags),
+            itable_unused: desc.itable_unused,
         }
     }
 }
 
-const_assert!(core::mem::size_of::<RawGroupDescriptor>() == 32);
+bitflags! {
+    /// Flags of the block group.
+    struct GroupFlags: u16 {
+        /// The inode table and the inode bitmap are not initialized.
+        const INODE_UNINIT = 1 << 0;
+        /// The block bitmap is not initialized.
+        const BLOCK_UNINIT = 1 << 1;
+        /// The inode table is zeroed.
+        const ITABLE_ZEROED = 1 << 2;
+    }
+}
+
+const_assert!(core::mem::size_of::<RawGroupDescriptor>() == 64);
 
 /// The raw block group descriptor.
 ///
 /// The table starts on the first block following the superblock.
+///
+/// Only the first 32 bytes are present on the device if the
+/// `FeatureInCompatSet::BIT64` is not set.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod)]
 pub(super) struct RawGroupDescriptor {
@@ -501,8 +659,51 @@ pub(super) struct RawGroupDescriptor {
     pub free_blocks_count: u16,
     pub free_inodes_count: u16,
     pub dirs_count: u16,
-    pad: u16,
-    reserved: [u32; 3],
+    pub flags: u16,
+    /// Snapshot exclusion bitmap.
+    pub exclude_bitmap: u32,
+    /// Low 16 bits of the block bitmap checksum.
+    pub block_bitmap_csum: u16,
+    /// Low 16 bits of the inode bitmap checksum.
+    pub inode_bitmap_csum: u16,
+    pub itable_unused: u16,
+    /// Checksum of the descriptor.
+    pub checksum: u16,
+    ///
+    /// This fields are valid if the FeatureInCompatSet::BIT64 is set.
+    ///
+    pub block_bitmap_hi: u32,
+    pub inode_bitmap_hi: u32,
+    pub inode_table_hi: u32,
+    pub free_blocks_count_hi: u16,
+    pub free_inodes_count_hi: u16,
+    pub dirs_count_hi: u16,
+    pub itable_unused_hi: u16,
+    pub exclude_bitmap_hi: u32,
+    pub block_bitmap_csum_hi: u16,
+    pub inode_bitmap_csum_hi: u16,
+    reserved: u32,
+}
+
+impl RawGroupDescriptor {
+    /// Calculates the checksum of the descriptor of the `idx`-th block group.
+    fn calc_checksum(&self, idx: usize, super_block: &SuperBlock) -> u16 {
+        let bytes = &self.as_bytes()[..super_block.desc_size()];
+        let offset = core::mem::offset_of!(Self, checksum);
+        let idx = (idx as u32).to_le_bytes();
+        if super_block.has_metadata_csum() {
+            let mut crc = crc32c(super_block.checksum_seed(), &idx);
+            crc = crc32c(crc, &bytes[..offset]);
+            crc = crc32c(crc, &[0u8; 2]);
+            crc = crc32c(crc, &bytes[offset + 2..]);
+            crc as u16
+        } else {
+            let mut crc = crc16(!0, super_block.uuid());
+            crc = crc16(crc, &idx);
+            crc = crc16(crc, &bytes[..offset]);
+            crc16(crc, &bytes[offset + 2..])
+        }
+    }
 }
 
 impl From<&GroupDescriptor> for RawGroupDescriptor {
@@ -514,8 +715,9 @@ impl From<&GroupDescriptor> for RawGroupDescriptor {
             free_blocks_count: desc.free_blocks_count,
             free_inodes_count: desc.free_inodes_count,
             dirs_count: desc.dirs_count,
-            pad: 0u16,
-            reserved: [0u32; 3],
+            flags: desc.flags.bits(),
+            itable_unused: desc.itable_unused,
+            ..Self::new_zeroed()
         }
     }
 }
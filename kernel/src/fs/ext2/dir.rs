@@ -2,7 +2,7 @@
 
 #![expect(dead_code)]
 
-use super::{inode::MAX_FNAME_LEN, prelude::*};
+use super::{inode::MAX_FNAME_LEN, prelude::*, utils::crc32c};
 
 /// The data structure in a directory's data block. It is stored in a linked list.
 ///
@@ -20,6 +20,11 @@ impl DirEntry {
     const ALIGN: usize = 4;
     const HEADER_LEN: usize = core::mem::size_of::<DirEntryHeader>();
     const PARENT_OFFSET: usize = Self::HEADER_LEN + Self::ALIGN;
+    /// The length of the checksum tail at the end of each block,
+    /// which is present if the metadata checksums are enabled.
+    const CSUM_TAIL_LEN: usize = Self::HEADER_LEN + core::mem::size_of::<u32>();
+    /// The type indicator of the checksum tail.
+    const CSUM_TAIL_TYPE: u8 = 0xDE;
 
     /// Constructs a new `DirEntry` object with the specified inode (`ino`),
     /// name (`name`), and file type (`inode_type`).
@@ -104,6 +109,16 @@ impl DirEntryHeader {
             inode_type: DirEntryFileType::from(inode_type) as _,
         }
     }
+
+    /// Constructs the header of the checksum tail.
+    fn csum_tail() -> Self {
+        Self {
+            ino: 0,
+            record_len: DirEntry::CSUM_TAIL_LEN as _,
+            name_len: 0,
+            inode_type: DirEntry::CSUM_TAIL_TYPE,
+        }
+    }
 }
 
 /// The type indicator in the `DirEntry`.
@@ -159,9 +174,13 @@ pub struct DirEntryReader<'a> {
 
 /// An iterator for iterating `DirEntryItem` from the
 /// page cache given a start offset.
+///
+/// The checksum tails are always skipped.
 pub(super) struct DirEntryIter<'a> {
     page_cache: &'a PageCache,
     offset: usize,
+    /// Whether to include the unused entries, whose inode numbers are zero.
+    include_unused: bool,
 }
 
 impl<'a> DirEntryReader<'a> {
@@ -179,17 +198,29 @@ impl<'a> DirEntryReader<'a> {
         DirEntryIter {
             page_cache: self.page_cache,
             offset: self.from_offset,
+            include_unused: false,
         }
     }
 
-    /// Returns an iterator for iterating `DirEntry`s.
-    pub fn iter_entries(&'a mut self) -> impl Iterator<Item = DirEntry> + 'a {
+    /// Returns an iterator for iterating `DirEntryItem`s, including the unused ones.
+    fn iter_with_unused(&self) -> DirEntryIter<'a> {
+        DirEntryIter {
+            include_unused: true,
+            ..self.iter()
+        }
+    }
+
+    /// Returns an iterator for iterating `DirEntry`s along with their offsets.
+    pub fn iter_entries(&'a mut self) -> impl Iterator<Item = (usize, DirEntry)> + 'a {
         let iter = self.iter();
         iter.filter_map(|entry_item| match self.read_name(&entry_item) {
-            Ok(name_buf) => Some(DirEntry {
-                header: entry_item.header,
-                name: CStr256::from(name_buf),
-            }),
+            Ok(name_buf) => Some((
+                entry_item.offset,
+                DirEntry {
+                    header: entry_item.header,
+                    name: CStr256::from(name_buf),
+                },
+            )),
             Err(_) => None,
         })
     }
@@ -246,21 +277,25 @@ impl<'a> DirEntryReader<'a> {
 }
 
 impl DirEntryIter<'_> {
-    /// Reads a `DirEntryItem` at the current offset.
+    /// Reads the next `DirEntryItem` from the current offset.
     fn read_entry_item(&mut self) -> Result<DirEntryItem> {
-        if self.offset >= self.page_cache.pages().size() {
-            return_errno!(Errno::ENOENT);
-        }
+        loop {
+            if self.offset >= self.page_cache.pages().size() {
+                return_errno!(Errno::ENOENT);
+            }
 
-        let header = self.read_header()?;
-        let record_len = header.record_len as usize;
-        let item = DirEntryItem {
-            header,
-            offset: self.offset,
-        };
+            let header = self.read_header()?;
+            let item = DirEntryItem {
+                header,
+                offset: self.offset,
+            };
 
-        self.offset += record_len;
-        Ok(item)
+            self.offset += item.record_len();
+            if item.is_csum_tail() || (item.is_unused() && !self.include_unused) {
+                continue;
+            }
+            return Ok(item);
+        }
     }
 
     /// Reads the header of the entry from the page cache.
@@ -269,8 +304,12 @@ impl DirEntryIter<'_> {
             .page_cache
             .pages()
             .read_val::<DirEntryHeader>(self.offset)?;
-        if header.ino == 0 {
-            return_errno!(Errno::ENOENT);
+        let record_len = header.record_len as usize;
+        if record_len < DirEntry::HEADER_LEN
+            || record_len % DirEntry::ALIGN != 0
+            || self.offset % BLOCK_SIZE + record_len > BLOCK_SIZE
+        {
+            return_errno_with_message!(Errno::EUCLEAN, "bad directory entry");
         }
         Ok(header)
     }
@@ -341,8 +380,28 @@ impl DirEntryItem {
     }
 
     /// Returns the length of the gap between the current entry and the next entry.
+    ///
+    /// The whole record of an unused entry is a gap.
     pub fn gap_len(&self) -> usize {
-        self.record_len() - self.actual_len()
+        if self.is_unused() {
+            self.record_len()
+        } else {
+            self.record_len() - self.actual_len()
+        }
+    }
+
+    /// Returns whether the entry is unused.
+    fn is_unused(&self) -> bool {
+        self.header.ino == 0
+    }
+
+    /// Returns whether the entry is the checksum tail at the end of a block.
+    fn is_csum_tail(&self) -> bool {
+        self.is_unused()
+            && self.header.inode_type == DirEntry::CSUM_TAIL_TYPE
+            && self.header.name_len == 0
+            && self.record_len() == DirEntry::CSUM_TAIL_LEN
+            && self.offset % BLOCK_SIZE == BLOCK_SIZE - DirEntry::CSUM_TAIL_LEN
     }
 }
 
@@ -350,15 +409,18 @@ impl DirEntryItem {
 pub struct DirEntryWriter<'a> {
     page_cache: &'a PageCache,
     offset: usize,
+    /// Whether each block ends with a checksum tail.
+    has_csum_tail: bool,
     name_buf: Option<[u8; MAX_FNAME_LEN]>,
 }
 
 impl<'a> DirEntryWriter<'a> {
     /// Constructs a writer with the given page cache and offset.
-    pub(super) fn new(page_cache: &'a PageCache, from_offset: usize) -> Self {
+    pub(super) fn new(page_cache: &'a PageCache, from_offset: usize, has_csum_tail: bool) -> Self {
         Self {
             page_cache,
             offset: from_offset,
+            has_csum_tail,
             name_buf: None,
         }
     }
@@ -392,8 +454,9 @@ impl<'a> DirEntryWriter<'a> {
         debug_assert_eq!(self.offset, DirEntry::PARENT_OFFSET);
 
         let mut parent_header = DirEntryHeader::new(parent_ino, InodeType::Dir, 2);
-        parent_header.record_len = (BLOCK_SIZE - self.offset) as _;
-        self.write_entry(&parent_header, "..")
+        parent_header.record_len = (self.block_end(0) - self.offset) as _;
+        self.write_entry(&parent_header, "..")?;
+        self.write_csum_tail(0)
     }
 
     /// Appends a new `DirEntry` starting from the current offset.
@@ -411,7 +474,7 @@ impl<'a> DirEntryWriter<'a> {
         debug_assert_eq!(header.name_len as usize, name_len);
         let name_bytes = name.as_bytes();
         let mut entry_item_with_enough_gap = None;
-        for entry_item in DirEntryReader::new(self.page_cache, self.offset).iter() {
+        for entry_item in DirEntryReader::new(self.page_cache, self.offset).iter_with_unused() {
             if entry_item_with_enough_gap.is_none()
                 && entry_item.gap_len() >= header.record_len as usize
             {
@@ -422,6 +485,7 @@ impl<'a> DirEntryWriter<'a> {
             }
 
             if check_existence
+                && !entry_item.is_unused()
                 && entry_item.name_len() == name_len
                 && self.read_name(&entry_item)? == name_bytes
             {
@@ -443,6 +507,13 @@ impl<'a> DirEntryWriter<'a> {
         mut header: DirEntryHeader,
         name: &str,
     ) -> Result<()> {
+        // Reuse the unused entry.
+        if entry_with_enough_gap.is_unused() {
+            header.record_len = entry_with_enough_gap.record_len() as u16;
+            self.offset = entry_with_enough_gap.offset;
+            return self.write_entry(&header, name);
+        }
+
         // Write in the gap between existing entries.
         header.record_len = entry_with_enough_gap.gap_len() as u16;
         entry_with_enough_gap.set_record_len(entry_with_enough_gap.actual_len());
@@ -457,46 +528,66 @@ impl<'a> DirEntryWriter<'a> {
         let old_size = self.page_cache.pages().size();
         let new_size = old_size + BLOCK_SIZE;
         self.page_cache.resize(new_size)?;
-        header.record_len = BLOCK_SIZE as _;
+        header.record_len = (self.block_end(old_size) - old_size) as _;
 
         self.offset = old_size;
-        self.write_entry(&header, name)
+        self.write_entry(&header, name)?;
+        self.write_csum_tail(old_size)
     }
 
     /// Removes and returns an existing `DirEntry` indicated by `name`.
+    ///
+    /// The record of the removed entry is merged into the previous entry in the same block.
+    /// If it is the first entry in the block, it is marked as unused instead.
     pub fn remove_entry(&mut self, name: &str) -> Result<DirEntryItem> {
-        let mut pre_entry_item = None;
         let name_len = name.len();
         let name_bytes = name.as_bytes();
-        let mut iter = DirEntryReader::new(self.page_cache, DirEntry::PARENT_OFFSET).iter();
-        let Some(target_entry_item) = iter.find(|entry| {
-            if entry.offset < self.offset {
-                pre_entry_item = Some(*entry);
+        let block_offset = self.offset.align_down(BLOCK_SIZE);
+        let mut pre_entry_item = None;
+        let mut target_entry_item = None;
+        for entry_item in DirEntryReader::new(self.page_cache, block_offset).iter_with_unused() {
+            if entry_item.offset >= self.offset {
+                if entry_item.offset == self.offset {
+                    target_entry_item = Some(entry_item);
+                }
+                break;
             }
-
-            entry.offset == self.offset
+            pre_entry_item = Some(entry_item);
+        }
+        let Some(target_entry_item) = target_entry_item.filter(|entry| {
+            !entry.is_unused()
                 && entry.name_len() == name_len
                 && self.read_name(entry).unwrap() == name_bytes
         }) else {
             return_errno!(Errno::ENOENT);
         };
-        let is_last_entry = iter.next().is_none();
 
-        let mut pre_entry_item = pre_entry_item.unwrap();
-        let pre_offset = pre_entry_item.offset;
-        if is_last_entry {
-            // Shrink the size.
-            let new_size = pre_offset.align_up(BLOCK_SIZE);
-            self.page_cache.resize(new_size)?;
-            pre_entry_item.set_record_len(new_size - pre_offset);
-            self.offset = pre_offset;
-            self.write_header_only(&pre_entry_item.header)?;
-        } else {
+        if let Some(mut pre_entry_item) = pre_entry_item {
             // Update the previous entry.
             pre_entry_item
                 .set_record_len(pre_entry_item.record_len() + target_entry_item.record_len());
-            self.offset = pre_offset;
+            self.offset = pre_entry_item.offset;
             self.write_header_only(&pre_entry_item.header)?;
+        } else {
+            let mut unused_header = target_entry_item.header;
+            unused_header.ino = 0;
+            self.offset = target_entry_item.offset;
+            self.write_header_only(&unused_header)?;
+        }
+
+        // Shrink the size if the trailing blocks become empty.
+        let old_size = self.page_cache.pages().size();
+        let mut new_size = old_size;
+        while new_size > BLOCK_SIZE
+            && DirEntryReader::new(self.page_cache, new_size - BLOCK_SIZE)
+                .iter()
+                .next()
+                .is_none()
+        {
+            new_size -= BLOCK_SIZE;
+        }
+        if new_size < old_size {
+            self.page_cache.resize(new_size)?;
         }
 
         Ok(target_entry_item)
@@ -530,6 +621,75 @@ impl<'a> DirEntryWriter<'a> {
         Ok(())
     }
 
+    /// Converts the blocks of a hash-indexed directory into linear ones.
+    ///
+    /// The root of the index is hidden behind "..", and each of the other index
+    /// blocks is covered by an unused entry, so they are already valid linear blocks,
+    /// except that the checksum tails are missing.
+    pub fn deindex(&mut self) -> Result<()> {
+        if !self.has_csum_tail {
+            return Ok(());
+        }
+
+        let page_cache = self.page_cache;
+        let pages = page_cache.pages();
+        let mut parent_header = pages.read_val::<DirEntryHeader>(DirEntry::PARENT_OFFSET)?;
+        if parent_header.record_len as usize == BLOCK_SIZE - DirEntry::PARENT_OFFSET {
+            parent_header.record_len -= DirEntry::CSUM_TAIL_LEN as u16;
+            pages.write_val(DirEntry::PARENT_OFFSET, &parent_header)?;
+            self.write_csum_tail(0)?;
+        }
+
+        for block_offset in (BLOCK_SIZE..pages.size()).step_by(BLOCK_SIZE) {
+            let mut header = pages.read_val::<DirEntryHeader>(block_offset)?;
+            if header.ino == 0 && header.record_len as usize == BLOCK_SIZE {
+                header.record_len -= DirEntry::CSUM_TAIL_LEN as u16;
+                pages.write_val(block_offset, &header)?;
+                self.write_csum_tail(block_offset)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the checksum in the tail of the directory block, if the tail is present.
+    pub fn update_csum_tail(block: &mut [u8], seed: u32) {
+        let tail_offset = BLOCK_SIZE - DirEntry::CSUM_TAIL_LEN;
+        let csum_offset = tail_offset + DirEntry::HEADER_LEN;
+        if block[tail_offset..csum_offset] != *DirEntryHeader::csum_tail().as_bytes() {
+            return;
+        }
+
+        let csum = crc32c(seed, &block[..tail_offset]);
+        block[csum_offset..].copy_from_slice(&csum.to_le_bytes());
+    }
+
+    /// Writes the checksum tail of the block at `block_offset`, if it is required.
+    ///
+    /// The checksum is filled in when the block is written back.
+    fn write_csum_tail(&mut self, block_offset: usize) -> Result<()> {
+        if !self.has_csum_tail {
+            return Ok(());
+        }
+
+        let tail_offset = block_offset + BLOCK_SIZE - DirEntry::CSUM_TAIL_LEN;
+        self.page_cache
+            .pages()
+            .write_val(tail_offset, &DirEntryHeader::csum_tail())?;
+        self.page_cache
+            .pages()
+            .write_val(tail_offset + DirEntry::HEADER_LEN, &0u32)?;
+        Ok(())
+    }
+
+    /// Returns the end offset of the entries in the block at `block_offset`.
+    fn block_end(&self, block_offset: usize) -> usize {
+        if self.has_csum_tail {
+            block_offset + BLOCK_SIZE - DirEntry::CSUM_TAIL_LEN
+        } else {
+            block_offset + BLOCK_SIZE
+        }
+    }
+
     /// Reads the name of the entry from the page cache to the inner buffer.
     fn read_name(&mut self, item: &DirEntryItem) -> Result<&[u8]> {
         if self.name_buf.is_none() {
@@ -0,0 +1,564 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The extent tree introduced by Ext4.
+//!
+//! An extent maps a run of consecutive file blocks to consecutive device blocks.
+//! The extents of an inode are organized as a B+ tree, whose root node is stored in
+//! the block pointers of the inode and whose other nodes occupy one block each.
+//!
+//! The extents are loaded into memory together with the inode. When the extents are
+//! modified, the tree is rebuilt from scratch on the next sync, reusing the blocks of
+//! the old nodes.
+
+use ostd::const_assert;
+
+use super::{
+    block_ptr::{BlockPtrs, Ext2Bid},
+    fs::Ext2,
+    prelude::*,
+    utils::crc32c,
+};
+
+/// The magic number of the extent header.
+const EXTENT_MAGIC: u16 = 0xf30a;
+
+/// The maximum depth of the tree.
+const MAX_DEPTH: u16 = 5;
+
+/// The maximum length of an initialized extent.
+const MAX_WRITTEN_LEN: Ext2Bid = 32768;
+
+/// The maximum length of an uninitialized extent.
+const MAX_UNWRITTEN_LEN: Ext2Bid = 32767;
+
+const HEADER_SIZE: usize = core::mem::size_of::<ExtentHeader>();
+
+const ENTRY_SIZE: usize = core::mem::size_of::<RawExtent>();
+
+/// The number of entries in the root node.
+const ROOT_CAPACITY: usize = (core::mem::size_of::<BlockPtrs>() - HEADER_SIZE) / ENTRY_SIZE;
+
+/// The number of entries in a non-root node.
+const NODE_CAPACITY: usize = (BLOCK_SIZE - HEADER_SIZE) / ENTRY_SIZE;
+
+/// The mapping of a run of file blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum BlockMapping {
+    /// The file blocks are mapped to the range of device blocks.
+    Mapped(Range<Ext2Bid>),
+    /// The file blocks are mapped to the range of device blocks,
+    /// which have not been initialized and should be read as zeros.
+    Unwritten(Range<Ext2Bid>),
+    /// The file blocks are not mapped and should be read as zeros.
+    ///
+    /// The value is the number of file blocks.
+    Hole(Ext2Bid),
+}
+
+impl BlockMapping {
+    /// Returns the number of file blocks.
+    pub fn len(&self) -> Ext2Bid {
+        match self {
+            Self::Mapped(range) | Self::Unwritten(range) => range.len() as Ext2Bid,
+            Self::Hole(len) => *len,
+        }
+    }
+}
+
+/// The in-memory extent tree of an inode.
+#[derive(Debug)]
+pub(super) struct ExtentTree {
+    /// The extents indexed by their first file blocks.
+    extents: BTreeMap<Ext2Bid, Extent>,
+    /// The blocks to hold the non-root nodes.
+    node_bids: Vec<Ext2Bid>,
+    /// The block group to allocate the node blocks from.
+    block_group_idx: usize,
+    /// The seed of the node checksums, or `None` if the checksums are disabled.
+    csum_seed: Option<u32>,
+    is_dirty: bool,
+    fs: Weak<Ext2>,
+}
+
+impl ExtentTree {
+    /// Creates an empty tree.
+    pub fn new(block_group_idx: usize, csum_seed: Option<u32>, fs: Weak<Ext2>) -> Self {
+        Self {
+            extents: BTreeMap::new(),
+            node_bids: Vec::new(),
+            block_group_idx,
+            csum_seed,
+            is_dirty: false,
+            fs,
+        }
+    }
+
+    /// Loads the tree whose root node is stored in `root`.
+    pub fn load(
+        root: &BlockPtrs,
+        block_group_idx: usize,
+        csum_seed: Option<u32>,
+        fs: Weak<Ext2>,
+    ) -> Result<Self> {
+        let mut tree = Self::new(block_group_idx, csum_seed, fs);
+        let depth = ExtentHeader::from_bytes(&root.as_bytes()[..HEADER_SIZE]).depth;
+        if depth > MAX_DEPTH {
+            return_errno_with_message!(Errno::EUCLEAN, "the extent tree is too deep");
+        }
+        tree.load_node(root.as_bytes(), ROOT_CAPACITY, depth)?;
+        Ok(tree)
+    }
+
+    /// Loads the extents in the node and its descendants.
+    fn load_node(&mut self, node: &[u8], capacity: usize, depth: u16) -> Result<()> {
+        let header = ExtentHeader::from_bytes(&node[..HEADER_SIZE]);
+        if header.magic != EXTENT_MAGIC
+            || header.depth != depth
+            || header.max as usize > capacity
+            || header.entries > header.max
+        {
+            return_errno_with_message!(Errno::EUCLEAN, "bad extent header");
+        }
+
+        let entries = node[HEADER_SIZE..HEADER_SIZE + header.entries as usize * ENTRY_SIZE]
+            .chunks_exact(ENTRY_SIZE);
+        for entry in entries {
+            if depth == 0 {
+                let extent = Extent::from(RawExtent::from_bytes(entry));
+                if extent.start_hi != 0 {
+                    return_errno_with_message!(Errno::EFBIG, "the extent is beyond 32-bit");
+                }
+                if extent.len > 0 {
+                    self.extents.insert(extent.block, extent);
+                }
+            } else {
+                let index = RawExtentIndex::from_bytes(entry);
+                if index.leaf_hi != 0 {
+                    return_errno_with_message!(Errno::EFBIG, "the extent node is beyond 32-bit");
+                }
+                let child = self.read_node(index.leaf_lo)?;
+                self.node_bids.push(index.leaf_lo);
+                self.load_node(&child, NODE_CAPACITY, depth - 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a non-root node from the device.
+    fn read_node(&self, bid: Ext2Bid) -> Result<Vec<u8>> {
+        let mut node = vec![0u8; BLOCK_SIZE];
+        self.fs().read_metadata(bid, &mut node)?;
+
+        if let Some(seed) = self.csum_seed {
+            let max = ExtentHeader::from_bytes(&node[..HEADER_SIZE]).max as usize;
+            if max > NODE_CAPACITY {
+                return_errno_with_message!(Errno::EUCLEAN, "bad extent header");
+            }
+            let offset = HEADER_SIZE + max * ENTRY_SIZE;
+            let checksum = u32::from_le_bytes(node[offset..offset + 4].try_into().unwrap());
+            if checksum != crc32c(seed, &node[..offset]) {
+                return_errno_with_message!(Errno::EBADMSG, "bad extent block checksum");
+            }
+        }
+        Ok(node)
+    }
+
+    /// Returns the mapping of the file blocks at the beginning of the `range`.
+    ///
+    /// The returned mapping may cover only a part of the `range`.
+    ///
+    /// # Panics
+    ///
+    /// If the `range` is empty, this method will panic.
+    pub fn map(&self, range: Range<Ext2Bid>) -> BlockMapping {
+        assert!(!range.is_empty());
+
+        if let Some(extent) = self.find(range.start) {
+            let start = extent.start + (range.start - extent.block);
+            let len = (extent.end() - range.start).min(range.len() as Ext2Bid);
+            return if extent.unwritten {
+                BlockMapping::Unwritten(start..start + len)
+            } else {
+                BlockMapping::Mapped(start..start + len)
+            };
+        }
+
+        let hole_end = self
+            .extents
+            .range(range.clone())
+            .next()
+            .map_or(range.end, |(block, _)| *block);
+        BlockMapping::Hole(hole_end - range.start)
+    }
+
+    /// Allocates the device blocks for the unmapped file blocks starting from `block`.
+    ///
+    /// Returns the allocated device blocks, which may be fewer than `count` if
+    /// insufficient consecutive blocks are available.
+    pub fn alloc(&mut self, block: Ext2Bid, count: Ext2Bid) -> Result<Range<Ext2Bid>> {
+        debug_assert!(self.map(block..block + count) == BlockMapping::Hole(count));
+        let count = count.min(MAX_WRITTEN_LEN);
+        self.reserve(1)?;
+
+        // Attempts to place the blocks right after the previous extent.
+        let fs = self.fs();
+        let block_group_idx = self
+            .extents
+            .range(..block)
+            .next_back()
+            .map_or(self.block_group_idx, |(_, extent)| {
+                ((extent.start + extent.len) / fs.blocks_per_group()) as usize
+            });
+        let device_range = fs
+            .alloc_blocks(block_group_idx, count)
+            .ok_or_else(|| Error::with_message(Errno::ENOSPC, "no space for file blocks"))?;
+        self.insert(block, device_range.clone());
+        Ok(device_range)
+    }
+
+    /// Marks the unwritten file blocks in the `range` as written.
+    ///
+    /// # Panics
+    ///
+    /// If the `range` is not covered by a single unwritten extent,
+    /// this method will panic.
+    pub fn mark_written(&mut self, range: Range<Ext2Bid>) -> Result<()> {
+        let extent = *self.find(range.start).unwrap();
+        assert!(extent.unwritten && range.end <= extent.end());
+        // At most two extents are added by the split.
+        self.reserve(2)?;
+        self.extents.remove(&extent.block);
+
+        // Splits the extent into the unwritten parts and the written part.
+        if range.start > extent.block {
+            let left = Extent {
+                len: range.start - extent.block,
+                ..extent
+            };
+            self.extents.insert(left.block, left);
+        }
+        if range.end < extent.end() {
+            let offset = range.end - extent.block;
+            let right = Extent {
+                block: range.end,
+                len: extent.len - offset,
+                start: extent.start + offset,
+                ..extent
+            };
+            self.extents.insert(right.block, right);
+        }
+        let start = extent.start + (range.start - extent.block);
+        self.insert(range.start, start..start + range.len() as Ext2Bid);
+        Ok(())
+    }
+
+    /// Unmaps the file blocks starting from `new_blocks`.
+    ///
+    /// Returns the device blocks that are no longer used by the file data.
+    pub fn truncate(&mut self, new_blocks: Ext2Bid) -> Vec<Range<Ext2Bid>> {
+        let mut freed_ranges = Vec::new();
+        if let Some((_, extent)) = self.extents.range_mut(..new_blocks).next_back() {
+            if extent.end() > new_blocks {
+                let kept_len = new_blocks - extent.block;
+                freed_ranges.push(extent.start + kept_len..extent.start + extent.len);
+                extent.len = kept_len;
+            }
+        }
+        for (_, extent) in self.extents.split_off(&new_blocks) {
+            freed_ranges.push(extent.start..extent.start + extent.len);
+        }
+
+        if !freed_ranges.is_empty() {
+            self.is_dirty = true;
+        }
+        freed_ranges
+    }
+
+    /// Reserves the node blocks for at least `additional` more extents.
+    ///
+    /// This ensures that writing back the tree does not need to allocate blocks.
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        let nodes_count = nodes_needed(self.extents.len() + additional);
+        while self.node_bids.len() < nodes_count {
+            let bid = self
+                .fs()
+                .alloc_blocks(self.block_group_idx, 1)
+                .ok_or_else(|| Error::with_message(Errno::ENOSPC, "no space for extent nodes"))?
+                .start;
+            self.node_bids.push(bid);
+            self.is_dirty = true;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of blocks used by the file data and the non-root nodes.
+    pub fn blocks_count(&self) -> Ext2Bid {
+        let data_blocks: Ext2Bid = self.extents.values().map(|extent| extent.len).sum();
+        data_blocks + self.node_bids.len() as Ext2Bid
+    }
+
+    /// Returns true if the tree has not been written back.
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    /// Writes back the non-root nodes to the device and the root node to `root`.
+    pub fn sync(&mut self, root: &mut BlockPtrs) -> Result<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+
+        self.reserve(0)?;
+        let fs = self.fs();
+        let nodes_count = nodes_needed(self.extents.len());
+        for bid in self.node_bids.drain(nodes_count..) {
+            fs.free_blocks(bid..bid + 1)?;
+        }
+
+        // Builds the tree level by level, starting from the leaves.
+        let mut entries: Vec<(Ext2Bid, RawExtent)> = self
+            .extents
+            .values()
+            .map(|extent| (extent.block, RawExtent::from(extent)))
+            .collect();
+        let mut node_bids = self.node_bids.iter();
+        let mut bio_waiter = BioWaiter::new();
+        let mut depth = 0;
+        while entries.len() > ROOT_CAPACITY {
+            let mut indexes = Vec::with_capacity(entries.len().div_ceil(NODE_CAPACITY));
+            for chunk in entries.chunks(NODE_CAPACITY) {
+                let bid = *node_bids.next().unwrap();
+                let mut node = vec![0u8; BLOCK_SIZE];
+                fill_node(&mut node, chunk, NODE_CAPACITY, depth);
+                if let Some(seed) = self.csum_seed {
+                    let offset = HEADER_SIZE + NODE_CAPACITY * ENTRY_SIZE;
+                    let checksum = crc32c(seed, &node[..offset]);
+                    node[offset..offset + 4].copy_from_slice(&checksum.to_le_bytes());
+                }
+                bio_waiter.concat(fs.write_metadata_async(bid, &node)?);
+
+                // The index points to the node with its first file block.
+                let first_block = chunk[0].0;
+                let index = RawExtentIndex {
+                    block: first_block,
+                    leaf_lo: bid,
+                    leaf_hi: 0,
+                    unused: 0,
+                };
+                indexes.push((first_block, RawExtent::from_bytes(index.as_bytes())));
+            }
+            entries = indexes;
+            depth += 1;
+        }
+        fill_node(root.as_bytes_mut(), &entries, ROOT_CAPACITY, depth);
+
+        bio_waiter
+            .wait()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "failed to sync the extent tree"))?;
+        self.is_dirty = false;
+        Ok(())
+    }
+
+    /// Maps the unmapped file blocks starting from `block` to the `device_range`.
+    fn insert(&mut self, block: Ext2Bid, device_range: Range<Ext2Bid>) {
+        let mut block = block;
+        let mut start = device_range.start;
+        let mut len = device_range.len() as Ext2Bid;
+        self.is_dirty = true;
+
+        // Merges into the previous extent if possible.
+        if let Some((_, prev)) = self.extents.range_mut(..block).next_back() {
+            if !prev.unwritten && prev.end() == block && prev.start + prev.len == start {
+                let merged_len = len.min(MAX_WRITTEN_LEN - prev.len);
+                prev.len += merged_len;
+                block += merged_len;
+                start += merged_len;
+                len -= merged_len;
+            }
+        }
+        if len == 0 {
+            return;
+        }
+
+        // Merges the next extent if possible.
+        if let Some(next) = self.extents.get(&(block + len)).copied() {
+            if !next.unwritten && start + len == next.start && len + next.len <= MAX_WRITTEN_LEN {
+                self.extents.remove(&next.block);
+                len += next.len;
+            }
+        }
+
+        while len > 0 {
+            let extent_len = len.min(MAX_WRITTEN_LEN);
+            self.extents.insert(
+                block,
+                Extent {
+                    block,
+                    len: extent_len,
+                    start,
+                    start_hi: 0,
+                    unwritten: false,
+                },
+            );
+            block += extent_len;
+            start += extent_len;
+            len -= extent_len;
+        }
+    }
+
+    /// Returns the extent containing the file `block`.
+    fn find(&self, block: Ext2Bid) -> Option<&Extent> {
+        self.extents
+            .range(..=block)
+            .next_back()
+            .map(|(_, extent)| extent)
+            .filter(|extent| block < extent.end())
+    }
+
+    fn fs(&self) -> Arc<Ext2> {
+        self.fs.upgrade().unwrap()
+    }
+}
+
+/// Initializes the root node of an empty tree in `root`.
+pub(super) fn init_root(root: &mut BlockPtrs) {
+    *root = BlockPtrs::default();
+    fill_node(root.as_bytes_mut(), &[], ROOT_CAPACITY, 0);
+}
+
+/// Returns the number of non-root nodes to hold `extents_count` extents.
+fn nodes_needed(extents_count: usize) -> usize {
+    let mut nodes_count = 0;
+    let mut entries_count = extents_count;
+    while entries_count > ROOT_CAPACITY {
+        entries_count = entries_count.div_ceil(NODE_CAPACITY);
+        nodes_count += entries_count;
+    }
+    nodes_count
+}
+
+/// Fills the header and the entries of a node.
+fn fill_node(node: &mut [u8], entries: &[(Ext2Bid, RawExtent)], capacity: usize, depth: u16) {
+    let header = ExtentHeader {
+        magic: EXTENT_MAGIC,
+        entries: entries.len() as u16,
+        max: capacity as u16,
+        depth,
+        generation: 0,
+    };
+    node[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+    for (i, (_, entry)) in entries.iter().enumerate() {
+        let offset = HEADER_SIZE + i * ENTRY_SIZE;
+        node[offset..offset + ENTRY_SIZE].copy_from_slice(entry.as_bytes());
+    }
+}
+
+/// The in-memory extent.
+#[derive(Clone, Copy, Debug)]
+struct Extent {
+    /// The first file block.
+    block: Ext2Bid,
+    /// The number of blocks.
+    len: Ext2Bid,
+    /// The first device block.
+    start: Ext2Bid,
+    /// The high 16 bits of the first device block.
+    start_hi: u16,
+    /// Whether the blocks have not been initialized.
+    unwritten: bool,
+}
+
+impl Extent {
+    /// Returns the file block after the last one.
+    fn end(&self) -> Ext2Bid {
+        self.block + self.len
+    }
+}
+
+impl From<RawExtent> for Extent {
+    fn from(raw: RawExtent) -> Self {
+        let (len, unwritten) = if raw.len as Ext2Bid > MAX_WRITTEN_LEN {
+            (raw.len as Ext2Bid - MAX_WRITTEN_LEN, true)
+        } else {
+            (raw.len as Ext2Bid, false)
+        };
+        Self {
+            block: raw.block,
+            len,
+            start: raw.start_lo,
+            start_hi: raw.start_hi,
+            unwritten,
+        }
+    }
+}
+
+impl From<&Extent> for RawExtent {
+    fn from(extent: &Extent) -> Self {
+        debug_assert!(
+            extent.len
+                <= if extent.unwritten {
+                    MAX_UNWRITTEN_LEN
+                } else {
+                    MAX_WRITTEN_LEN
+                }
+        );
+        Self {
+            block: extent.block,
+            len: if extent.unwritten {
+                (extent.len + MAX_WRITTEN_LEN) as u16
+            } else {
+                extent.len as u16
+            },
+            start_hi: extent.start_hi,
+            start_lo: extent.start,
+        }
+    }
+}
+
+const_assert!(core::mem::size_of::<ExtentHeader>() == 12);
+
+/// The header of each node.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct ExtentHeader {
+    magic: u16,
+    /// Number of valid entries.
+    entries: u16,
+    /// Capacity of entries.
+    max: u16,
+    /// Depth of the node, where the leaf nodes have a depth of 0.
+    depth: u16,
+    generation: u32,
+}
+
+const_assert!(core::mem::size_of::<RawExtentIndex>() == ENTRY_SIZE);
+
+/// The entry of an internal node.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawExtentIndex {
+    /// The first file block covered by the child node.
+    block: u32,
+    /// Low 32 bits of the block of the child node.
+    leaf_lo: u32,
+    /// High 16 bits of the block of the child node.
+    leaf_hi: u16,
+    unused: u16,
+}
+
+const_assert!(core::mem::size_of::<RawExtent>() == 12);
+
+/// The entry of a leaf node.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawExtent {
+    /// The first file block.
+    block: u32,
+    /// Number of blocks. If it is larger than 32768, the extent is uninitialized
+    /// and the actual length is `len - 32768`.
+    len: u16,
+    /// High 16 bits of the first device block.
+    start_hi: u16,
+    /// Low 32 bits of the first device block.
+    start_lo: u32,
+}
@@ -2,16 +2,24 @@
 
 #![expect(dead_code)]
 
+use ostd::mm::io_util::HasVmReaderWriter;
+use spin::Once;
+
 use super::{
     block_group::{BlockGroup, RawGroupDescriptor},
     block_ptr::Ext2Bid,
     inode::{FilePerm, Inode, InodeDesc, RawInode},
+    journal::Journal,
     prelude::*,
-    super_block::{RawSuperBlock, SuperBlock, SUPER_BLOCK_OFFSET},
+    super_block::{FeatureInCompatSet, RawSuperBlock, SuperBlock, SUPER_BLOCK_OFFSET},
+    utils::crc32c,
 };
-use crate::fs::{
-    registry::{FsProperties, FsType},
-    utils::FileSystem,
+use crate::{
+    fs::{
+        registry::{FsProperties, FsType},
+        utils::FileSystem,
+    },
+    thread::work_queue::work_item::WorkItem,
 };
 
 /// The root inode number.
@@ -27,29 +35,56 @@ pub struct Ext2 {
     blocks_per_group: Ext2Bid,
     inode_size: usize,
     block_size: usize,
+    desc_size: usize,
+    /// The seed of the metadata checksums, or `None` if the checksums are disabled.
+    csum_seed: Option<u32>,
     group_descriptors_segment: USegment,
+    /// The journal, which is loaded after the block groups if the filesystem has one.
+    journal: Once<Journal>,
     self_ref: Weak<Self>,
 }
 
 impl Ext2 {
     /// Opens and loads an Ext2 from the `block_device`.
+    ///
+    /// If the journal needs recovery, it is replayed before the filesystem is used.
     pub fn open(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
+        let ext2 = Self::load(block_device.clone())?;
+        if !ext2.super_block().needs_recovery() {
+            return Ok(ext2);
+        }
+
+        ext2.journal().unwrap().recover()?;
+        // The journal may contain the superblock, so it is read again after the recovery.
+        let mut raw_super_block = block_device.read_val::<RawSuperBlock>(SUPER_BLOCK_OFFSET)?;
+        raw_super_block.feature_incompat &= !FeatureInCompatSet::RECOVER.bits();
+        raw_super_block.update_checksum();
+        block_device.write_val(SUPER_BLOCK_OFFSET, &raw_super_block)?;
+        block_device.sync()?;
+
+        // The loaded metadata may be stale, so the filesystem is loaded again.
+        drop(ext2);
+        Self::load(block_device)
+    }
+
+    /// Loads an Ext2 from the `block_device` without recovering the journal.
+    fn load(block_device: Arc<dyn BlockDevice>) -> Result<Arc<Self>> {
         // Load the superblock
         // TODO: if the main superblock is corrupted, should we load the backup?
         let super_block = {
             let raw_super_block = block_device.read_val::<RawSuperBlock>(SUPER_BLOCK_OFFSET)?;
             SuperBlock::try_from(raw_super_block)?
         };
-        assert_eq!(
-            super_block.block_size(),
-            BLOCK_SIZE,
-            "currently only support 4096-byte block size"
-        );
+        if super_block.block_size() != BLOCK_SIZE {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "currently only support 4096-byte block size"
+            );
+        }
 
         let group_descriptors_segment: USegment = {
-            let npages = ((super_block.block_groups_count() as usize)
-                * core::mem::size_of::<RawGroupDescriptor>())
-            .div_ceil(BLOCK_SIZE);
+            let npages = ((super_block.block_groups_count() as usize) * super_block.desc_size())
+                .div_ceil(BLOCK_SIZE);
             let segment = FrameAllocOptions::new()
                 .zeroed(false)
                 .alloc_segment(npages)?;
@@ -84,22 +119,53 @@ impl Ext2 {
             Ok(block_groups)
         };
 
+        let mut load_result = Ok(());
         let ext2 = Arc::new_cyclic(|weak_ref| Self {
             inodes_per_group: super_block.inodes_per_group(),
             blocks_per_group: super_block.blocks_per_group(),
             inode_size: super_block.inode_size(),
             block_size: super_block.block_size(),
+            desc_size: super_block.desc_size(),
+            csum_seed: super_block
+                .has_metadata_csum()
+                .then(|| super_block.checksum_seed()),
             block_groups: load_block_groups(
                 weak_ref.clone(),
                 block_device.as_ref(),
                 &group_descriptors_segment,
             )
-            .unwrap(),
+            .unwrap_or_else(|err| {
+                load_result = Err(err);
+                Vec::new()
+            }),
             block_device,
             super_block: RwMutex::new(Dirty::new(super_block)),
             group_descriptors_segment,
+            journal: Once::new(),
             self_ref: weak_ref.clone(),
         });
+        load_result?;
+
+        let journal_ino = ext2.super_block().journal_ino();
+        if let Some(journal_ino) = journal_ino {
+            if journal_ino == 0 {
+                return_errno_with_message!(Errno::EINVAL, "external journals are not supported");
+            }
+            let journal_inode = ext2.lookup_inode(journal_ino)?;
+            let commit_work = {
+                let weak_ext2 = ext2.self_ref.clone();
+                WorkItem::new(Box::new(move || {
+                    let Some(ext2) = weak_ext2.upgrade() else {
+                        return;
+                    };
+                    if let Err(err) = ext2.commit_journal() {
+                        warn!("failed to commit the journal: {:?}", err);
+                    }
+                }))
+            };
+            let journal = Journal::load(&journal_inode, ext2.block_device.clone(), commit_work)?;
+            ext2.journal.call_once(|| journal);
+        }
         Ok(ext2)
     }
 
@@ -133,6 +199,26 @@ impl Ext2 {
         self.super_block.read()
     }
 
+    /// Returns the seed of the checksums of the inode metadata,
+    /// or `None` if the metadata checksums are disabled.
+    pub(super) fn inode_csum_seed(&self, ino: u32, generation: u32) -> Option<u32> {
+        self.csum_seed.map(|seed| {
+            let crc = crc32c(seed, &ino.to_le_bytes());
+            crc32c(crc, &generation.to_le_bytes())
+        })
+    }
+
+    /// Returns the seed of the metadata checksums,
+    /// or `None` if the metadata checksums are disabled.
+    pub(super) fn csum_seed(&self) -> Option<u32> {
+        self.csum_seed
+    }
+
+    /// Returns the journal, or `None` if the filesystem has no journal.
+    pub(super) fn journal(&self) -> Option<&Journal> {
+        self.journal.get()
+    }
+
     /// Returns the root inode.
     pub fn root_inode(&self) -> Result<Arc<Inode>> {
         self.lookup_inode(ROOT_INO)
@@ -155,8 +241,16 @@ impl Ext2 {
         let (block_group_idx, ino) =
             self.alloc_ino(dir_block_group_idx, inode_type == InodeType::Dir)?;
         let inode = {
-            let inode_desc = InodeDesc::new(inode_type, file_perm);
-            Inode::new(ino, block_group_idx, inode_desc, self.self_ref.clone())
+            let mut inode_desc = InodeDesc::new(inode_type, file_perm);
+            if matches!(inode_type, InodeType::File | InodeType::Dir)
+                && self
+                    .super_block()
+                    .feature_incompat()
+                    .contains(FeatureInCompatSet::EXTENTS)
+            {
+                inode_desc.init_extents();
+            }
+            Inode::new(ino, block_group_idx, inode_desc, self.self_ref.clone())?
         };
         let block_group = &self.block_groups[block_group_idx];
         block_group.insert_cache(self.inode_idx(ino), inode.clone());
@@ -214,9 +308,9 @@ impl Ext2 {
         block_group_idx: usize,
         raw_descriptor: &RawGroupDescriptor,
     ) -> Result<()> {
-        let offset = block_group_idx * core::mem::size_of::<RawGroupDescriptor>();
+        let offset = block_group_idx * self.desc_size;
         self.group_descriptors_segment
-            .write_val(offset, raw_descriptor)?;
+            .write_bytes(offset, &raw_descriptor.as_bytes()[..self.desc_size])?;
         Ok(())
     }
 
@@ -282,6 +376,10 @@ impl Ext2 {
 
     /// Frees a range of blocks.
     pub(super) fn free_blocks(&self, range: Range<Ext2Bid>) -> Result<()> {
+        if let Some(journal) = self.journal() {
+            journal.forget(range.clone());
+        }
+
         let mut current_range = range.clone();
         while !current_range.is_empty() {
             let (_, block_group) = self.block_group_of_bid(current_range.start)?;
@@ -303,12 +401,20 @@ impl Ext2 {
     }
 
     /// Reads contiguous blocks starting from the `bid` synchronously.
+    ///
+    /// The blocks that are in the journal but not yet written to the device are read
+    /// from the journal.
     pub(super) fn read_blocks(&self, bid: Ext2Bid, bio_segment: BioSegment) -> Result<()> {
         let status = self
             .block_device
-            .read_blocks(Bid::new(bid as u64), bio_segment)?;
+            .read_blocks(Bid::new(bid as u64), bio_segment.clone())?;
         match status {
-            BioStatus::Complete => Ok(()),
+            BioStatus::Complete => {
+                if let Some(journal) = self.journal() {
+                    journal.copy_blocks(bid, &bio_segment);
+                }
+                Ok(())
+            }
             err_status => Err(Error::from(err_status)),
         }
     }
@@ -319,6 +425,14 @@ impl Ext2 {
         bid: Ext2Bid,
         bio_segment: BioSegment,
     ) -> Result<BioWaiter> {
+        if self
+            .journal()
+            .is_some_and(|journal| journal.contains(bid..bid + bio_segment.nblocks() as Ext2Bid))
+        {
+            self.read_blocks(bid, bio_segment)?;
+            return Ok(BioWaiter::new());
+        }
+
         let waiter = self
             .block_device
             .read_blocks_async(Bid::new(bid as u64), bio_segment)?;
@@ -348,34 +462,89 @@ impl Ext2 {
         Ok(waiter)
     }
 
+    /// Reads the metadata blocks starting from the `bid` to the `buf`.
+    pub(super) fn read_metadata(&self, bid: Ext2Bid, buf: &mut [u8]) -> Result<()> {
+        let bio_segment =
+            BioSegment::alloc(buf.len().div_ceil(BLOCK_SIZE), BioDirection::FromDevice);
+        self.read_blocks(bid, bio_segment.clone())?;
+        bio_segment.reader().unwrap().read(&mut VmWriter::from(buf));
+        Ok(())
+    }
+
+    /// Writes the metadata blocks starting from the `bid` asynchronously.
+    ///
+    /// If the filesystem has a journal, the blocks are added to the running transaction
+    /// instead, and they are written to the device when the transaction is committed.
+    pub(super) fn write_metadata_async(&self, bid: Ext2Bid, buf: &[u8]) -> Result<BioWaiter> {
+        debug_assert!(buf.len() % BLOCK_SIZE == 0);
+        if let Some(journal) = self.journal() {
+            journal.start().add_blocks(bid, buf)?;
+            return Ok(BioWaiter::new());
+        }
+
+        let waiter = self
+            .block_device
+            .write_bytes_async(bid as usize * BLOCK_SIZE, buf)?;
+        Ok(waiter)
+    }
+
+    /// Commits the running transaction of the journal, if the filesystem has one.
+    ///
+    /// The main superblock is also written back during the commit. This method must not be
+    /// called with the lock of any inode held, since the data of the inodes are written back.
+    pub(super) fn commit_journal(&self) -> Result<()> {
+        let Some(journal) = self.journal() else {
+            return Ok(());
+        };
+        // In the `data=ordered` mode, the data must be written back before the metadata
+        // referring to them are committed.
+        for inode in journal.take_inodes() {
+            inode.sync_data()?;
+        }
+        let raw_super_block = RawSuperBlock::from(self.super_block.read().deref().deref());
+        journal.commit(&raw_super_block)
+    }
+
     /// Writes back the metadata to the block device.
     pub fn sync_metadata(&self) -> Result<()> {
         // If the superblock is clean, the block groups must be clean.
         if !self.super_block.read().is_dirty() {
-            return Ok(());
+            return self.commit_journal();
+        }
+
+        // Makes room for the metadata below, which are written in the same transaction.
+        if self.journal().is_some_and(|journal| journal.needs_commit()) {
+            self.commit_journal()?;
         }
+        let handle = self.journal().map(|journal| journal.start());
 
         let mut super_block = self.super_block.write();
         // Writes back the metadata of block groups
         for block_group in &self.block_groups {
-            block_group.sync_metadata()?;
+            block_group.sync_metadata(&super_block)?;
         }
 
         // Writes back the main superblock and group descriptor table.
+        // With a journal, the main superblock is written when the journal is committed.
         let mut bio_waiter = BioWaiter::new();
         let raw_super_block = RawSuperBlock::from((*super_block).deref());
-        bio_waiter.concat(
-            self.block_device
-                .write_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?,
-        );
+        if self.journal().is_none() {
+            bio_waiter.concat(
+                self.block_device
+                    .write_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?,
+            );
+        }
+        let mut group_descriptors = vec![0u8; self.group_descriptors_segment.size()];
+        self.group_descriptors_segment
+            .read_bytes(0, &mut group_descriptors)?;
+        bio_waiter.concat(self.write_metadata_async(
+            super_block.group_descriptors_bid(0).to_raw() as Ext2Bid,
+            &group_descriptors,
+        )?);
         let group_descriptors_bio_segment = BioSegment::new_from_segment(
             self.group_descriptors_segment.clone(),
             BioDirection::ToDevice,
         );
-        bio_waiter.concat(self.block_device.write_blocks_async(
-            super_block.group_descriptors_bid(0),
-            group_descriptors_bio_segment.clone(),
-        )?);
         bio_waiter
             .wait()
             .ok_or_else(|| Error::with_message(Errno::EIO, "failed to sync main metadata"))?;
@@ -387,6 +556,7 @@ impl Ext2 {
             if super_block.is_backup_group(idx as usize) {
                 let mut bio_waiter = BioWaiter::new();
                 raw_super_block_backup.block_group_idx = idx as u16;
+                raw_super_block_backup.update_checksum();
                 bio_waiter.concat(self.block_device.write_bytes_async(
                     super_block.bid(idx as usize).to_offset(),
                     raw_super_block_backup.as_bytes(),
@@ -403,7 +573,9 @@ impl Ext2 {
 
         // Reset to clean.
         super_block.clear_dirty();
-        Ok(())
+        drop(super_block);
+        drop(handle);
+        self.commit_journal()
     }
 
     /// Writes back all the cached inodes to the block device.
@@ -448,10 +620,70 @@ impl FsType for Ext2Type {
 
     fn create(
         &self,
-        _args: Option<CString>,
+        _source: &CStr,
+        args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+        _ctx: &Context,
+    ) -> Result<Arc<dyn FileSystem>> {
+        check_mount_options(args)?;
+        Ext2::open(disk.unwrap()).map(|fs| fs as _)
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysBranchNode>> {
+        None
+    }
+}
+
+/// The Ext3 filesystems are served by the Ext2 driver,
+/// whose journal is used in the `data=ordered` mode.
+pub(super) struct Ext3Type;
+
+impl FsType for Ext3Type {
+    fn name(&self) -> &'static str {
+        "ext3"
+    }
+
+    fn create(
+        &self,
+        _source: &CStr,
+        args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+        _ctx: &Context,
+    ) -> Result<Arc<dyn FileSystem>> {
+        check_mount_options(args)?;
+        Ext2::open(disk.unwrap()).map(|fs| fs as _)
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysBranchNode>> {
+        None
+    }
+}
+
+/// The Ext4 filesystems are served by the Ext2 driver
+/// as long as their features are supported.
+pub(super) struct Ext4Type;
+
+impl FsType for Ext4Type {
+    fn name(&self) -> &'static str {
+        "ext4"
+    }
+
+    fn create(
+        &self,
+        _source: &CStr,
+        args: Option<CString>,
         disk: Option<Arc<dyn BlockDevice>>,
         _ctx: &Context,
     ) -> Result<Arc<dyn FileSystem>> {
+        check_mount_options(args)?;
         Ext2::open(disk.unwrap()).map(|fs| fs as _)
     }
 
@@ -463,3 +695,23 @@ impl FsType for Ext2Type {
         None
     }
 }
+
+/// Checks the mount options.
+///
+/// Only the `data=ordered` mode of journaling is supported. The `data=writeback` mode
+/// is also accepted since the ordered mode provides stronger guarantees.
+fn check_mount_options(args: Option<CString>) -> Result<()> {
+    let Some(args) = args else {
+        return Ok(());
+    };
+    for option in args.to_string_lossy().split(',') {
+        match option.split_once('=') {
+            Some(("data", "ordered" | "writeback")) => (),
+            Some(("data", _)) => {
+                return_errno_with_message!(Errno::EINVAL, "not supported data mode")
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
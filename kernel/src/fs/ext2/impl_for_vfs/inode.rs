@@ -111,6 +111,16 @@ impl Inode for Ext2Inode {
         self.write_direct_at(offset, reader)
     }
 
+    fn copy_file_range(
+        &self,
+        offset: usize,
+        dst: &Arc<dyn Inode>,
+        dst_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        self.copy_file_range(offset, dst, dst_offset, len)
+    }
+
     fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
         Ok(self.create(name, type_, mode.into())?)
     }
@@ -177,6 +187,7 @@ impl Inode for Ext2Inode {
 
     fn sync_all(&self) -> Result<()> {
         self.sync_all()?;
+        self.fs().commit_journal()?;
         self.fs().block_device().sync()?;
         Ok(())
     }
@@ -113,11 +113,9 @@ impl IndirectBlockCache {
         for _ in 0..num {
             let (bid, block) = self.cache.pop_lru().unwrap();
             if block.is_dirty() {
-                let bio_segment = BioSegment::new_from_segment(
-                    Segment::<()>::from(block.frame.clone()).into(),
-                    BioDirection::ToDevice,
-                );
-                bio_waiter.concat(self.fs().write_blocks_async(bid, bio_segment)?);
+                let mut buf = vec![0u8; BLOCK_SIZE];
+                block.frame.read_bytes(0, &mut buf)?;
+                bio_waiter.concat(self.fs().write_metadata_async(bid, &buf)?);
             }
         }
 
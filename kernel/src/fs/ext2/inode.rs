@@ -12,18 +12,20 @@ use ostd::{const_assert, mm::io_util::HasVmReaderWriter};
 use super::{
     block_ptr::{BidPath, BlockPtrs, Ext2Bid, BID_SIZE, MAX_BLOCK_PTRS},
     dir::{DirEntryHeader, DirEntryItem, DirEntryReader, DirEntryWriter},
+    extent::{self, BlockMapping, ExtentTree},
     fs::Ext2,
     indirect_block_cache::{IndirectBlock, IndirectBlockCache},
     prelude::*,
-    utils::now,
+    super_block::FeatureRoCompatSet,
+    utils::{crc32c, now},
     xattr::Xattr,
 };
 use crate::{
     fs::{
         path::{is_dot, is_dot_or_dotdot, is_dotdot},
         utils::{
-            Extension, FallocMode, Inode as _, InodeMode, Metadata, Permission, XattrName,
-            XattrNamespace, XattrSetFlags,
+            copy_from_page_cache, Extension, FallocMode, Inode as VfsInode, InodeMode, Metadata,
+            Permission, XattrName, XattrNamespace, XattrSetFlags,
         },
     },
     process::{posix_thread::AsPosixThread, Gid, Uid},
@@ -35,6 +37,12 @@ pub const MAX_FNAME_LEN: usize = 255;
 /// Max path length of the fast symlink.
 pub const MAX_FAST_SYMLINK_LEN: usize = MAX_BLOCK_PTRS * BID_SIZE;
 
+/// Max hard links of a directory.
+///
+/// If `FeatureRoCompatSet::DIR_NLINK` is set, a directory with more subdirectories
+/// has a hard links count of 1, meaning that the count is unknown.
+const MAX_DIR_LINKS: u16 = 65000;
+
 /// The Ext2 inode.
 pub struct Inode {
     ino: u32,
@@ -52,18 +60,36 @@ impl Inode {
         block_group_idx: usize,
         desc: Dirty<InodeDesc>,
         fs: Weak<Ext2>,
-    ) -> Arc<Self> {
-        Arc::new_cyclic(|weak_self| Self {
+    ) -> Result<Arc<Self>> {
+        let csum_seed = fs.upgrade().unwrap().inode_csum_seed(ino, desc.generation);
+        let extent_tree = if desc.flags.contains(FileFlags::EXTENTS) {
+            Some(ExtentTree::load(
+                &desc.block_ptrs,
+                block_group_idx,
+                csum_seed,
+                fs.clone(),
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Arc::new_cyclic(|weak_self| Self {
             ino,
             type_: desc.type_,
             block_group_idx,
             xattr: desc
                 .acl
                 .map(|acl| Xattr::new(acl, weak_self.clone(), fs.clone())),
-            inner: RwMutex::new(InodeInner::new(desc, weak_self.clone(), fs.clone())),
+            inner: RwMutex::new(InodeInner::new(
+                desc,
+                extent_tree,
+                csum_seed,
+                weak_self.clone(),
+                fs.clone(),
+            )),
             fs,
             extension: Extension::new(),
-        })
+        }))
     }
 
     pub fn ino(&self) -> u32 {
@@ -86,6 +112,11 @@ impl Inode {
         self.inner.read().page_cache.pages().dup()
     }
 
+    /// Returns the device block IDs of the file blocks, which must have no holes.
+    pub(super) fn device_bids(&self) -> Result<Vec<Ext2Bid>> {
+        self.inner.read().device_bids()
+    }
+
     pub fn metadata(&self) -> Metadata {
         let inner = self.inner.read();
         Metadata {
@@ -314,7 +345,7 @@ impl Inode {
         self_inner.set_mtime(now);
         self_inner.set_ctime(now);
         dir_inner.dec_hard_links();
-        dir_inner.dec_hard_links(); // For "."
+        dir_inner.dec_dir_self_link();
 
         Ok(())
     }
@@ -413,7 +444,7 @@ impl Inode {
 
         dst_inner.dec_hard_links();
         if dst_inode_typ == InodeType::Dir {
-            dst_inner.dec_hard_links(); // For "."
+            dst_inner.dec_dir_self_link();
         }
         dst_inner.set_ctime(now);
         drop(self_inner);
@@ -588,7 +619,7 @@ impl Inode {
         dst_inner.set_ctime(now);
 
         if is_dir {
-            dst_inner.dec_hard_links(); // For "."
+            dst_inner.dec_dir_self_link();
             let mut src_inner = write_guards.pop().unwrap();
             src_inner.set_parent_ino(target.ino)?;
             src_inner.set_ctime(now);
@@ -615,14 +646,16 @@ impl Inode {
 
             let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
                 let mut dir_entry_reader = DirEntryReader::new(&inner.page_cache, *offset);
-                for dir_entry in dir_entry_reader.iter_entries() {
+                for (entry_offset, dir_entry) in dir_entry_reader.iter_entries() {
+                    // The unused entries are skipped, so the offset is not accumulated.
+                    let next_offset = entry_offset + dir_entry.record_len();
                     visitor.visit(
                         dir_entry.name(),
                         dir_entry.ino() as u64,
                         dir_entry.type_(),
-                        dir_entry.record_len(),
+                        next_offset,
                     )?;
-                    *offset += dir_entry.record_len();
+                    *offset = next_offset;
                 }
 
                 Ok(())
@@ -689,6 +722,29 @@ impl Inode {
         Ok(bytes_read)
     }
 
+    pub fn copy_file_range(
+        &self,
+        offset: usize,
+        dst: &Arc<dyn VfsInode>,
+        dst_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        if self.type_ != InodeType::File {
+            return_errno!(Errno::EISDIR);
+        }
+
+        let (page_cache, file_size) = {
+            let inner = self.inner.read();
+            (inner.page_cache.pages().dup(), inner.file_size())
+        };
+        let bytes_copied =
+            copy_from_page_cache(&page_cache, file_size, offset, dst, dst_offset, len)?;
+
+        self.set_atime(now());
+
+        Ok(bytes_copied)
+    }
+
     // The offset and the length of buffer must be multiples of the block size.
     pub fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         if self.type_ != InodeType::File {
@@ -956,9 +1012,15 @@ struct InodeInner {
 }
 
 impl InodeInner {
-    pub fn new(desc: Dirty<InodeDesc>, weak_self: Weak<Inode>, fs: Weak<Ext2>) -> Self {
+    pub fn new(
+        desc: Dirty<InodeDesc>,
+        extent_tree: Option<ExtentTree>,
+        csum_seed: Option<u32>,
+        weak_self: Weak<Inode>,
+        fs: Weak<Ext2>,
+    ) -> Self {
         let num_page_bytes = desc.num_page_bytes();
-        let inode_impl = InodeImpl::new(desc, weak_self, fs);
+        let inode_impl = InodeImpl::new(desc, extent_tree, csum_seed, weak_self, fs);
         Self {
             page_cache: PageCache::with_capacity(
                 num_page_bytes,
@@ -1063,7 +1125,7 @@ impl InodeInner {
 
     pub fn read_link(&self) -> Result<String> {
         let file_size = self.inode_impl.file_size();
-        if file_size <= MAX_FAST_SYMLINK_LEN {
+        if self.inode_impl.desc.is_fast_symlink() {
             return self.inode_impl.read_link();
         }
 
@@ -1077,7 +1139,8 @@ impl InodeInner {
 
     fn init_dir(&mut self, self_ino: u32, parent_ino: u32) -> Result<()> {
         debug_assert_eq!(self.inode_type(), InodeType::Dir);
-        DirEntryWriter::new(&self.page_cache, 0).init_dir(self_ino, parent_ino)?;
+        DirEntryWriter::new(&self.page_cache, 0, self.has_csum_tail())
+            .init_dir(self_ino, parent_ino)?;
         self.inc_hard_links(); // for ".."
         Ok(())
     }
@@ -1101,8 +1164,23 @@ impl InodeInner {
         name: &str,
         check_existence: bool,
     ) -> Result<()> {
+        let is_dir = inode_type == InodeType::Dir;
+        let is_parent = name == "..";
+        if is_dir
+            && !is_parent
+            && self.hard_links() >= MAX_DIR_LINKS
+            && !self
+                .fs()
+                .super_block()
+                .feature_ro_compat()
+                .contains(FeatureRoCompatSet::DIR_NLINK)
+        {
+            return_errno_with_message!(Errno::EMLINK, "too many subdirectories");
+        }
+
+        self.deindex_dir()?;
         let entry_header = DirEntryHeader::new(ino, inode_type, name.len());
-        DirEntryWriter::new(&self.page_cache, 0).append_new_entry(
+        DirEntryWriter::new(&self.page_cache, 0, self.has_csum_tail()).append_new_entry(
             entry_header,
             name,
             check_existence,
@@ -1114,29 +1192,31 @@ impl InodeInner {
             self.inode_impl.resize(page_cache_size)?;
         }
 
-        let is_dir = inode_type == InodeType::Dir;
-        let is_parent = name == "..";
         if is_dir && !is_parent {
-            self.inc_hard_links(); // for ".."
+            self.inode_impl.inc_dir_links(); // for ".."
         }
         Ok(())
     }
 
     pub fn remove_entry_at(&mut self, name: &str, offset: usize) -> Result<()> {
-        let removed_entry = DirEntryWriter::new(&self.page_cache, offset).remove_entry(name)?;
+        self.deindex_dir()?;
+        let removed_entry = DirEntryWriter::new(&self.page_cache, offset, self.has_csum_tail())
+            .remove_entry(name)?;
         let file_size = self.file_size();
         let page_cache_size = self.page_cache.pages().size();
         if page_cache_size < file_size {
             self.inode_impl.resize(page_cache_size)?;
         }
         if removed_entry.type_() == InodeType::Dir {
-            self.dec_hard_links(); // for ".."
+            self.inode_impl.dec_dir_links(); // for ".."
         }
         Ok(())
     }
 
     pub fn rename_entry_at(&mut self, old_name: &str, new_name: &str, offset: usize) -> Result<()> {
-        DirEntryWriter::new(&self.page_cache, offset).rename_entry(old_name, new_name)?;
+        self.deindex_dir()?;
+        DirEntryWriter::new(&self.page_cache, offset, self.has_csum_tail())
+            .rename_entry(old_name, new_name)?;
         let file_size = self.file_size();
         let page_cache_size = self.page_cache.pages().size();
         if page_cache_size != file_size {
@@ -1146,13 +1226,41 @@ impl InodeInner {
     }
 
     pub fn set_parent_ino(&mut self, parent_ino: u32) -> Result<()> {
+        self.deindex_dir()?;
         let mut entry_item = self.find_entry_item("..").unwrap();
         entry_item.set_ino(parent_ino);
-        DirEntryWriter::new(&self.page_cache, entry_item.offset())
+        DirEntryWriter::new(&self.page_cache, entry_item.offset(), self.has_csum_tail())
             .write_header_only(entry_item.header())?;
         Ok(())
     }
 
+    /// Drops the link of a removed directory for ".".
+    pub fn dec_dir_self_link(&mut self) {
+        // The links may have been fixed to 1 due to too many subdirectories.
+        if self.hard_links() > 0 {
+            self.dec_hard_links();
+        }
+    }
+
+    /// Returns whether the blocks of the directory end with checksum tails.
+    fn has_csum_tail(&self) -> bool {
+        self.inode_impl.block_manager.dir_csum_seed.is_some()
+    }
+
+    /// Converts the hash-indexed directory into a linear one.
+    ///
+    /// The hash index is not maintained here, so it must be dropped before
+    /// the directory is modified. Otherwise, the stale index would mislead Linux.
+    fn deindex_dir(&mut self) -> Result<()> {
+        if !self.file_flags().contains(FileFlags::INDEX_DIR) {
+            return Ok(());
+        }
+
+        DirEntryWriter::new(&self.page_cache, 0, self.has_csum_tail()).deindex()?;
+        self.inode_impl.remove_file_flags(FileFlags::INDEX_DIR);
+        Ok(())
+    }
+
     pub fn sync_data(&self) -> Result<()> {
         // Writes back the data in page cache.
         let file_size = self.file_size();
@@ -1187,6 +1295,8 @@ impl InodeInner {
     pub fn device_id(&self) -> u64;
     pub fn set_device_id(&mut self, device_id: u64);
     pub fn sync_metadata(&mut self) -> Result<()>;
+    pub fn fs(&self) -> Arc<Ext2>;
+    pub fn device_bids(&self) -> Result<Vec<Ext2Bid>>;
 }
 
 struct InodeImpl {
@@ -1198,11 +1308,20 @@ struct InodeImpl {
 }
 
 impl InodeImpl {
-    pub fn new(desc: Dirty<InodeDesc>, weak_self: Weak<Inode>, fs: Weak<Ext2>) -> Self {
+    pub fn new(
+        desc: Dirty<InodeDesc>,
+        extent_tree: Option<ExtentTree>,
+        csum_seed: Option<u32>,
+        weak_self: Weak<Inode>,
+        fs: Weak<Ext2>,
+    ) -> Self {
         let block_manager = InodeBlockManager {
             nblocks: AtomicUsize::new(desc.blocks_count() as _),
             block_ptrs: RwMutex::new(desc.block_ptrs),
             indirect_blocks: RwMutex::new(IndirectBlockCache::new(fs.clone())),
+            extent_tree: extent_tree.map(RwMutex::new),
+            is_dir: desc.type_ == InodeType::Dir,
+            dir_csum_seed: csum_seed.filter(|_| desc.type_ == InodeType::Dir),
             fs,
         };
         Self {
@@ -1230,6 +1349,25 @@ impl InodeImpl {
         self.inode().fs()
     }
 
+    /// Returns the device block IDs of the file blocks, which must have no holes.
+    pub fn device_bids(&self) -> Result<Vec<Ext2Bid>> {
+        let nblocks = self.desc.size.div_ceil(BLOCK_SIZE) as Ext2Bid;
+        if nblocks == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut device_bids = Vec::with_capacity(nblocks as usize);
+        for mapping in self.block_manager.mappings(0..nblocks)? {
+            match mapping {
+                BlockMapping::Mapped(device_range) if device_range.start != 0 => {
+                    device_bids.extend(device_range)
+                }
+                _ => return_errno_with_message!(Errno::EUCLEAN, "the file has holes"),
+            }
+        }
+        Ok(device_bids)
+    }
+
     pub fn file_perm(&self) -> FilePerm {
         self.desc.perm
     }
@@ -1258,6 +1396,10 @@ impl InodeImpl {
         self.desc.flags
     }
 
+    pub fn remove_file_flags(&mut self, flags: FileFlags) {
+        self.desc.flags.remove(flags);
+    }
+
     pub fn hard_links(&self) -> u16 {
         self.desc.hard_links
     }
@@ -1271,6 +1413,22 @@ impl InodeImpl {
         self.desc.hard_links -= 1;
     }
 
+    /// Increases the hard links of the directory for a new subdirectory.
+    pub fn inc_dir_links(&mut self) {
+        match self.desc.hard_links {
+            1 => {}
+            MAX_DIR_LINKS.. => self.desc.hard_links = 1,
+            _ => self.desc.hard_links += 1,
+        }
+    }
+
+    /// Decreases the hard links of the directory for a removed subdirectory.
+    pub fn dec_dir_links(&mut self) {
+        if self.desc.hard_links > 1 {
+            self.desc.hard_links -= 1;
+        }
+    }
+
     pub fn blocks_count(&self) -> Ext2Bid {
         self.desc.blocks_count()
     }
@@ -1339,7 +1497,12 @@ impl InodeImpl {
     }
 
     pub fn sync_metadata(&mut self) -> Result<()> {
-        if !self.desc.is_dirty() {
+        let is_extent_tree_dirty = self
+            .block_manager
+            .extent_tree
+            .as_ref()
+            .is_some_and(|extent_tree| extent_tree.read().is_dirty());
+        if !self.desc.is_dirty() && !is_extent_tree_dirty {
             return Ok(());
         }
 
@@ -1358,8 +1521,18 @@ impl InodeImpl {
             }
         }
 
+        if let Some(extent_tree) = self.block_manager.extent_tree.as_ref() {
+            let mut extent_tree = extent_tree.write();
+            extent_tree.sync(&mut self.desc.block_ptrs)?;
+            let xattr_blocks = self.desc.acl.filter(|acl| acl.to_raw() != 0).is_some();
+            self.desc.blocks_count = extent_tree.blocks_count() + xattr_blocks as Ext2Bid;
+        }
         self.block_manager.indirect_blocks.write().evict_all()?;
-        inode.fs().sync_inode(inode.ino(), &self.desc)?;
+        let fs = inode.fs();
+        fs.sync_inode(inode.ino(), &self.desc)?;
+        if let Some(journal) = fs.journal() {
+            journal.add_inode(&inode);
+        }
         self.desc.clear_dirty();
         Ok(())
     }
@@ -1390,7 +1563,11 @@ impl InodeImpl {
             if new_blocks - old_blocks > self.fs().super_block().free_blocks_count() {
                 return_errno_with_message!(Errno::ENOSPC, "not enough free blocks");
             }
-            self.expand_blocks(old_blocks..new_blocks)?;
+            if self.block_manager.extent_tree.is_some() {
+                self.expand_extents(old_blocks..new_blocks)?;
+            } else {
+                self.expand_blocks(old_blocks..new_blocks)?;
+            }
         }
 
         // Expands the size
@@ -1414,6 +1591,32 @@ impl InodeImpl {
         Ok(())
     }
 
+    /// Expands inode blocks mapped by the extent tree.
+    ///
+    /// The blocks in the `range` may have been preallocated by Linux, so only
+    /// the unmapped ones are allocated.
+    fn expand_extents(&mut self, range: Range<Ext2Bid>) -> Result<()> {
+        let mut extent_tree = self.block_manager.extent_tree.as_ref().unwrap().write();
+        let mut current_range = range.clone();
+        while !current_range.is_empty() {
+            let expand_cnt = match extent_tree.map(current_range.clone()) {
+                BlockMapping::Hole(len) => match extent_tree.alloc(current_range.start, len) {
+                    Ok(device_range) => device_range.len() as Ext2Bid,
+                    Err(e) => {
+                        for device_range in extent_tree.truncate(range.start) {
+                            self.fs().free_blocks(device_range).unwrap();
+                        }
+                        return Err(e);
+                    }
+                },
+                mapping => mapping.len(),
+            };
+            current_range.start += expand_cnt;
+        }
+
+        Ok(())
+    }
+
     /// Attempts to expand a range of blocks and returns the number of consecutive
     /// blocks successfully allocated.
     ///
@@ -1643,7 +1846,14 @@ impl InodeImpl {
         let old_blocks = self.desc.blocks_count();
 
         // Shrinks block count if necessary
-        if new_blocks < old_blocks {
+        if let Some(extent_tree) = self.block_manager.extent_tree.as_ref() {
+            // The blocks beyond the size may have been preallocated by Linux.
+            let freed_ranges = extent_tree.write().truncate(new_blocks);
+            let fs = self.fs();
+            for device_range in freed_ranges {
+                fs.free_blocks(device_range).unwrap();
+            }
+        } else if new_blocks < old_blocks {
             self.shrink_blocks(new_blocks..old_blocks);
         }
 
@@ -1832,6 +2042,13 @@ struct InodeBlockManager {
     /// frequent reads access the `InodeDesc` copy without locking.
     block_ptrs: RwMutex<BlockPtrs>,
     indirect_blocks: RwMutex<IndirectBlockCache>,
+    /// The extent tree, which replaces the block pointers if present.
+    extent_tree: Option<RwMutex<ExtentTree>>,
+    /// Whether the blocks belong to a directory.
+    is_dir: bool,
+    /// The seed of the checksums in the directory blocks,
+    /// or `None` if it is not a directory or the checksums are disabled.
+    dir_csum_seed: Option<u32>,
     fs: Weak<Ext2>,
 }
 
@@ -1846,7 +2063,11 @@ impl InodeBlockManager {
         debug_assert!(nblocks * BLOCK_SIZE <= writer.avail());
         let mut bio_waiter = BioWaiter::new();
 
-        for dev_range in DeviceRangeReader::new(self, bid..bid + nblocks as Ext2Bid)? {
+        for mapping in self.mappings(bid..bid + nblocks as Ext2Bid)? {
+            let BlockMapping::Mapped(dev_range) = mapping else {
+                writer.fill_zeros(mapping.len() as usize * BLOCK_SIZE)?;
+                continue;
+            };
             let start_bid = dev_range.start as Ext2Bid;
             let range_nblocks = dev_range.len();
 
@@ -1870,7 +2091,11 @@ impl InodeBlockManager {
     pub fn read_block_async(&self, bid: Ext2Bid, frame: &CachePage) -> Result<BioWaiter> {
         let mut bio_waiter = BioWaiter::new();
 
-        for dev_range in DeviceRangeReader::new(self, bid..bid + 1 as Ext2Bid)? {
+        for mapping in self.mappings(bid..bid + 1 as Ext2Bid)? {
+            let BlockMapping::Mapped(dev_range) = mapping else {
+                frame.writer().fill_zeros(BLOCK_SIZE);
+                continue;
+            };
             let start_bid = dev_range.start as Ext2Bid;
             // TODO: Should we allocate the bio segment from the pool on reads?
             // This may require an additional copy to the requested frame in the completion callback.
@@ -1895,7 +2120,7 @@ impl InodeBlockManager {
         debug_assert_eq!(nblocks * BLOCK_SIZE, reader.remain());
         let mut bio_waiter = BioWaiter::new();
 
-        for dev_range in DeviceRangeReader::new(self, bid..bid + nblocks as Ext2Bid)? {
+        for dev_range in self.device_ranges_for_write(bid..bid + nblocks as Ext2Bid)? {
             let start_bid = dev_range.start as Ext2Bid;
             let range_nblocks = dev_range.len();
 
@@ -1919,8 +2144,19 @@ impl InodeBlockManager {
     pub fn write_block_async(&self, bid: Ext2Bid, frame: &CachePage) -> Result<BioWaiter> {
         let mut bio_waiter = BioWaiter::new();
 
-        for dev_range in DeviceRangeReader::new(self, bid..bid + 1 as Ext2Bid)? {
+        for dev_range in self.device_ranges_for_write(bid..bid + 1 as Ext2Bid)? {
             let start_bid = dev_range.start as Ext2Bid;
+            // The directory blocks are metadata, which are written through the journal.
+            if self.is_dir {
+                let mut block = vec![0u8; BLOCK_SIZE];
+                frame.reader().read(&mut VmWriter::from(&mut block[..]));
+                if let Some(seed) = self.dir_csum_seed {
+                    DirEntryWriter::update_csum_tail(&mut block, seed);
+                }
+                bio_waiter.concat(self.fs().write_metadata_async(start_bid, &block)?);
+                continue;
+            }
+
             let bio_segment = BioSegment::alloc(1, BioDirection::ToDevice);
             // This requires an additional copy to the pooled bio segment.
             bio_segment
@@ -1934,6 +2170,51 @@ impl InodeBlockManager {
         Ok(bio_waiter)
     }
 
+    /// Returns the mappings of the file blocks in the `range`.
+    fn mappings(&self, range: Range<Ext2Bid>) -> Result<Vec<BlockMapping>> {
+        let Some(extent_tree) = self.extent_tree.as_ref() else {
+            let device_range_reader = DeviceRangeReader::new(self, range)?;
+            return Ok(device_range_reader.map(BlockMapping::Mapped).collect());
+        };
+
+        let extent_tree = extent_tree.read();
+        let mut mappings = Vec::new();
+        let mut current_range = range;
+        while !current_range.is_empty() {
+            let mapping = extent_tree.map(current_range.clone());
+            current_range.start += mapping.len();
+            mappings.push(mapping);
+        }
+        Ok(mappings)
+    }
+
+    /// Returns the device ranges of the file blocks in the `range` to be written.
+    ///
+    /// The holes are allocated and the unwritten blocks are marked as written.
+    fn device_ranges_for_write(&self, range: Range<Ext2Bid>) -> Result<Vec<Range<Ext2Bid>>> {
+        let Some(extent_tree) = self.extent_tree.as_ref() else {
+            return Ok(DeviceRangeReader::new(self, range)?.collect());
+        };
+
+        let mut extent_tree = extent_tree.write();
+        let mut device_ranges = Vec::new();
+        let mut current_range = range;
+        while !current_range.is_empty() {
+            let device_range = match extent_tree.map(current_range.clone()) {
+                BlockMapping::Mapped(device_range) => device_range,
+                BlockMapping::Unwritten(device_range) => {
+                    let start = current_range.start;
+                    extent_tree.mark_written(start..start + device_range.len() as Ext2Bid)?;
+                    device_range
+                }
+                BlockMapping::Hole(len) => extent_tree.alloc(current_range.start, len)?,
+            };
+            current_range.start += device_range.len() as Ext2Bid;
+            device_ranges.push(device_range);
+        }
+        Ok(device_ranges)
+    }
+
     pub fn nblocks(&self) -> usize {
         self.nblocks.load(Ordering::Acquire)
     }
@@ -2121,6 +2402,8 @@ pub(super) struct InodeDesc {
     flags: FileFlags,
     /// Pointers to blocks.
     block_ptrs: BlockPtrs,
+    /// File version (for NFS).
+    generation: u32,
     /// File or directory acl block.
     acl: Option<Bid>,
 }
@@ -2130,28 +2413,35 @@ impl TryFrom<RawInode> for InodeDesc {
 
     fn try_from(inode: RawInode) -> Result<Self> {
         let inode_type = InodeType::from_raw_mode(inode.mode)?;
+        let flags = FileFlags::from_bits(inode.flags)
+            .ok_or(Error::with_message(Errno::EINVAL, "invalid file flags"))?;
+        let osd2 = &inode.os_dependent_2;
         Ok(Self {
             type_: inode_type,
             perm: FilePerm::from_raw_mode(inode.mode)?,
-            uid: ((inode.os_dependent_2.uid_high as u32) << 16) | inode.uid as u32,
-            gid: ((inode.os_dependent_2.gid_high as u32) << 16) | inode.gid as u32,
-            size: if inode_type == InodeType::File {
-                ((inode.size_high as usize) << 32) | inode.size_low as usize
-            } else {
-                inode.size_low as usize
-            },
+            uid: ((osd2.uid_high as u32) << 16) | inode.uid as u32,
+            gid: ((osd2.gid_high as u32) << 16) | inode.gid as u32,
+            size: ((inode.size_high as usize) << 32) | inode.size_low as usize,
             atime: Duration::from(inode.atime),
             ctime: Duration::from(inode.ctime),
             mtime: Duration::from(inode.mtime),
             dtime: Duration::from(inode.dtime),
             hard_links: inode.hard_links,
-            blocks_count: inode.blocks_count,
-            flags: FileFlags::from_bits(inode.flags)
-                .ok_or(Error::with_message(Errno::EINVAL, "invalid file flags"))?,
+            blocks_count: {
+                let blocks = ((osd2.blocks_high as u64) << 32) | inode.blocks_count as u64;
+                if flags.contains(FileFlags::HUGE_FILE) {
+                    blocks as Ext2Bid
+                } else {
+                    (blocks / SECTORS_PER_BLOCK) as Ext2Bid
+                }
+            },
+            flags,
             block_ptrs: inode.block_ptrs,
+            generation: inode.generation,
             acl: match inode_type {
-                InodeType::File => Some(Bid::new(inode.file_acl as _)),
-                InodeType::Dir => Some(Bid::new(inode.size_high as _)),
+                InodeType::File | InodeType::Dir => Some(Bid::new(
+                    ((osd2.file_acl_high as u64) << 32) | inode.file_acl as u64,
+                )),
                 _ => None,
             },
         })
@@ -2176,6 +2466,7 @@ impl InodeDesc {
             blocks_count: 0,
             flags: FileFlags::empty(),
             block_ptrs: BlockPtrs::default(),
+            generation: 0,
             acl: match type_ {
                 InodeType::File | InodeType::Dir => Some(Bid::new(0)),
                 _ => None,
@@ -2190,18 +2481,31 @@ impl InodeDesc {
     /// Returns the actual number of blocks utilized.
     ///
     /// Ext2 allows the `block_count` to exceed the actual number of blocks utilized.
+    /// Ext4 allows the `block_count` to be less than it if the file has holes.
     pub fn blocks_count(&self) -> Ext2Bid {
-        let blocks = self.size_to_blocks(self.size);
-        assert!(blocks <= self.blocks_count);
-        blocks
+        self.size_to_blocks(self.size)
     }
 
     fn size_to_blocks(&self, size: usize) -> Ext2Bid {
-        if self.type_ == InodeType::SymLink && size <= MAX_FAST_SYMLINK_LEN {
+        if self.type_ == InodeType::SymLink
+            && size <= MAX_FAST_SYMLINK_LEN
+            && !self.flags.contains(FileFlags::EXTENTS)
+        {
             return 0;
         }
         size.div_ceil(BLOCK_SIZE) as Ext2Bid
     }
+
+    /// Returns whether the target path of the symlink is stored in the block pointers.
+    fn is_fast_symlink(&self) -> bool {
+        self.type_ == InodeType::SymLink && self.size_to_blocks(self.size) == 0
+    }
+
+    /// Makes the blocks mapped by an extent tree, which is initially empty.
+    pub fn init_extents(&mut self) {
+        self.flags.insert(FileFlags::EXTENTS);
+        extent::init_root(&mut self.block_ptrs);
+    }
 }
 
 bitflags! {
@@ -2279,11 +2583,32 @@ bitflags! {
         const DIR_SYNC = 1 << 16;
         /// Top of directory hierarchies.
         const TOP_DIR = 1 << 17;
+        /// The blocks count is in the unit of filesystem blocks, not sectors.
+        const HUGE_FILE = 1 << 18;
+        /// The blocks are mapped by an extent tree.
+        const EXTENTS = 1 << 19;
+        /// Verity protected file.
+        const VERITY = 1 << 20;
+        /// The inode stores a large extended attribute value.
+        const EA_INODE = 1 << 21;
+        /// Blocks allocated beyond EOF (deprecated).
+        const EOF_BLOCKS = 1 << 22;
+        /// Direct access.
+        const DAX = 1 << 25;
+        /// The data is stored in the inode.
+        const INLINE_DATA = 1 << 28;
+        /// Create with parent's project Id.
+        const PROJ_INHERIT = 1 << 29;
+        /// Casefolded directory.
+        const CASEFOLD = 1 << 30;
         /// Reserved for ext2 lib.
         const RESERVED = 1 << 31;
     }
 }
 
+/// The number of 512-byte sectors in a block, which is the unit of `RawInode::blocks_count`.
+const SECTORS_PER_BLOCK: u64 = (BLOCK_SIZE / 512) as u64;
+
 const_assert!(core::mem::size_of::<RawInode>() == 128);
 
 /// The raw inode on device.
@@ -2307,6 +2632,7 @@ pub(super) struct RawInode {
     /// Low 16 bits of Group Id.
     pub gid: u16,
     pub hard_links: u16,
+    /// Lower 32 bits of the number of sectors.
     pub blocks_count: u32,
     /// File flags.
     pub flags: u32,
@@ -2320,8 +2646,7 @@ pub(super) struct RawInode {
     /// In revision 1, File ACL.
     pub file_acl: u32,
     /// In revision 0, this field is reserved.
-    /// In revision 1, Upper 32 bits of file size (if feature bit set)
-    /// if it's a file, Directory ACL if it's a directory.
+    /// In revision 1, Upper 32 bits of file size (if feature bit set).
     pub size_high: u32,
     /// Fragment address.
     pub frag_addr: u32,
@@ -2329,8 +2654,94 @@ pub(super) struct RawInode {
     pub os_dependent_2: Osd2,
 }
 
+impl RawInode {
+    /// The offset of `Osd2::checksum_lo` in the raw inode.
+    const CHECKSUM_LO_OFFSET: usize =
+        core::mem::offset_of!(RawInode, os_dependent_2) + core::mem::offset_of!(Osd2, checksum_lo);
+    /// The offset of the size of the extra fields, which follow the raw inode.
+    const EXTRA_ISIZE_OFFSET: usize = core::mem::size_of::<RawInode>();
+    /// The offset of the high 16 bits of the checksum in the extra fields.
+    const CHECKSUM_HI_OFFSET: usize = Self::EXTRA_ISIZE_OFFSET + 2;
+    /// The size of the extra fields known by Linux.
+    const EXTRA_ISIZE: usize = 32;
+
+    /// Initializes a slot in the inode table.
+    ///
+    /// The slot may be larger than the raw inode, in which case
+    /// the extra fields are all cleared and marked as present.
+    pub fn init_slot(slot: &mut [u8]) {
+        slot.fill(0);
+        if slot.len() > Self::EXTRA_ISIZE_OFFSET {
+            let extra_isize = Self::EXTRA_ISIZE.min(slot.len() - Self::EXTRA_ISIZE_OFFSET) as u16;
+            slot[Self::EXTRA_ISIZE_OFFSET..Self::EXTRA_ISIZE_OFFSET + 2]
+                .copy_from_slice(&extra_isize.to_le_bytes());
+        }
+    }
+
+    /// Verifies the checksum of a slot in the inode table.
+    pub fn verify_checksum(slot: &[u8], seed: u32) -> bool {
+        let checksum = Self::calc_checksum(slot, seed);
+        let checksum_lo = read_u16(slot, Self::CHECKSUM_LO_OFFSET);
+        if Self::has_checksum_hi(slot) {
+            let checksum_hi = read_u16(slot, Self::CHECKSUM_HI_OFFSET);
+            checksum == ((checksum_hi as u32) << 16) | checksum_lo as u32
+        } else {
+            checksum as u16 == checksum_lo
+        }
+    }
+
+    /// Updates the checksum of a slot in the inode table.
+    pub fn update_checksum(slot: &mut [u8], seed: u32) {
+        let checksum = Self::calc_checksum(slot, seed);
+        slot[Self::CHECKSUM_LO_OFFSET..Self::CHECKSUM_LO_OFFSET + 2]
+            .copy_from_slice(&(checksum as u16).to_le_bytes());
+        if Self::has_checksum_hi(slot) {
+            slot[Self::CHECKSUM_HI_OFFSET..Self::CHECKSUM_HI_OFFSET + 2]
+                .copy_from_slice(&((checksum >> 16) as u16).to_le_bytes());
+        }
+    }
+
+    /// Calculates the checksum of the whole slot, where the checksum fields are zeroed.
+    fn calc_checksum(slot: &[u8], seed: u32) -> u32 {
+        let mut crc = crc32c(seed, &slot[..Self::CHECKSUM_LO_OFFSET]);
+        crc = crc32c(crc, &[0u8; 2]);
+        crc = crc32c(
+            crc,
+            &slot[Self::CHECKSUM_LO_OFFSET + 2..Self::EXTRA_ISIZE_OFFSET],
+        );
+        if slot.len() > Self::EXTRA_ISIZE_OFFSET {
+            crc = crc32c(
+                crc,
+                &slot[Self::EXTRA_ISIZE_OFFSET..Self::CHECKSUM_HI_OFFSET],
+            );
+            if Self::has_checksum_hi(slot) {
+                crc = crc32c(crc, &[0u8; 2]);
+            } else {
+                crc = crc32c(
+                    crc,
+                    &slot[Self::CHECKSUM_HI_OFFSET..Self::CHECKSUM_HI_OFFSET + 2],
+                );
+            }
+            crc = crc32c(crc, &slot[Self::CHECKSUM_HI_OFFSET + 2..]);
+        }
+        crc
+    }
+
+    /// Returns whether the high 16 bits of the checksum are present in the slot.
+    fn has_checksum_hi(slot: &[u8]) -> bool {
+        slot.len() > Self::EXTRA_ISIZE_OFFSET
+            && read_u16(slot, Self::EXTRA_ISIZE_OFFSET) as usize
+                >= Self::CHECKSUM_HI_OFFSET + 2 - Self::EXTRA_ISIZE_OFFSET
+    }
+}
+
 impl From<&InodeDesc> for RawInode {
     fn from(inode: &InodeDesc) -> Self {
+        let sectors = inode.blocks_count as u64 * SECTORS_PER_BLOCK;
+        let acl = match inode.acl {
+            Some(acl) if matches!(inode.type_, InodeType::File | InodeType::Dir) => acl.to_raw(),
+            _ => 0,
+        };
         Self {
             mode: inode.type_ as u16 | inode.perm.bits(),
             uid: inode.uid as u16,
@@ -2341,18 +2752,16 @@ impl From<&InodeDesc> for RawInode {
             dtime: UnixTime::from(inode.dtime),
             gid: inode.gid as u16,
             hard_links: inode.hard_links,
-            blocks_count: inode.blocks_count,
-            flags: inode.flags.bits(),
+            blocks_count: sectors as u32,
+            // The blocks count is always written in sectors.
+            flags: (inode.flags - FileFlags::HUGE_FILE).bits(),
             block_ptrs: inode.block_ptrs,
-            file_acl: match inode.acl {
-                Some(acl) if inode.type_ == InodeType::File => acl.to_raw() as u32,
-                _ => Default::default(),
-            },
-            size_high: match inode.acl {
-                Some(acl) if inode.type_ == InodeType::Dir => acl.to_raw() as u32,
-                _ => Default::default(),
-            },
+            generation: inode.generation,
+            file_acl: acl as u32,
+            size_high: (inode.size >> 32) as u32,
             os_dependent_2: Osd2 {
+                blocks_high: (sectors >> 32) as u16,
+                file_acl_high: (acl >> 32) as u16,
                 uid_high: (inode.uid >> 16) as u16,
                 gid_high: (inode.gid >> 16) as u16,
                 ..Default::default()
@@ -2366,16 +2775,21 @@ impl From<&InodeDesc> for RawInode {
 #[repr(C)]
 #[derive(Clone, Copy, Default, Debug, Pod)]
 pub(super) struct Osd2 {
-    /// Fragment number.
-    pub frag_num: u8,
-    /// Fragment size.
-    pub frag_size: u8,
-    pad1: u16,
+    /// High 16 bits of the number of sectors.
+    pub blocks_high: u16,
+    /// High 16 bits of File ACL.
+    pub file_acl_high: u16,
     /// High 16 bits of User Id.
     pub uid_high: u16,
     /// High 16 bits of Group Id.
     pub gid_high: u16,
-    reserved2: u32,
+    /// Low 16 bits of the inode checksum.
+    pub checksum_lo: u16,
+    reserved: u16,
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
 }
 
 fn is_block_aligned(offset: usize) -> bool {
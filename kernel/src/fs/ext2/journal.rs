@@ -0,0 +1,880 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The JBD2 journal used by Ext3 and Ext4.
+//!
+//! The metadata blocks written between two commits form a transaction. When a transaction
+//! is committed, its blocks are first written to the journal and followed by a commit block.
+//! After that, the blocks are written to their locations (i.e., checkpointed) and the journal
+//! is emptied. If a crash happens in between, the committed transactions are replayed on the
+//! next mount.
+//!
+//! The blocks written by a [`JournalHandle`] always belong to the same transaction, so a
+//! transaction never outgrows the journal and is never split. The running transaction is
+//! committed when it is synced, when it grows large, or periodically.
+//!
+//! The file data are not journaled, which corresponds to the `data=ordered` mode. The data of
+//! the inodes whose metadata are in the running transaction are written back before the
+//! transaction is committed, and the flush issued before the commit block makes them durable.
+
+use core::fmt::Debug;
+
+use ostd::{const_assert, mm::io_util::HasVmReaderWriter};
+
+use super::{
+    block_ptr::Ext2Bid,
+    inode::Inode,
+    prelude::*,
+    super_block::{FeatureInCompatSet as FsFeatureInCompatSet, RawSuperBlock, SUPER_BLOCK_OFFSET},
+    utils::{crc32c, now},
+};
+use crate::{
+    thread::work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+    time::{
+        clocks::MonotonicClock,
+        timer::{Timeout, Timer},
+    },
+};
+
+/// The interval after which the running transaction is committed.
+///
+/// This is the default value of the `commit` mount option in Linux.
+const COMMIT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The magic number of the journal blocks.
+const JBD2_MAGIC: u32 = 0xc03b3998;
+
+/// The block types of the journal.
+const DESCRIPTOR_BLOCK: u32 = 1;
+const COMMIT_BLOCK: u32 = 2;
+const SUPER_BLOCK_V1: u32 = 3;
+const SUPER_BLOCK_V2: u32 = 4;
+const REVOKE_BLOCK: u32 = 5;
+
+/// The flags of the block tags.
+///
+/// The first four bytes of the block are replaced with zeros since they equal to `JBD2_MAGIC`.
+const TAG_ESCAPE: u32 = 1 << 0;
+/// The block has the same uuid as the previous one, so the uuid is omitted.
+const TAG_SAME_UUID: u32 = 1 << 1;
+/// The last tag in the descriptor block.
+const TAG_LAST: u32 = 1 << 3;
+
+/// The checksum type for the `CSUM_V2` and `CSUM_V3` features.
+const CHECKSUM_TYPE_CRC32C: u8 = 4;
+
+const HEADER_SIZE: usize = core::mem::size_of::<RawHeader>();
+const UUID_SIZE: usize = 16;
+/// The size of the checksum at the end of the descriptor and revoke blocks.
+const TAIL_SIZE: usize = core::mem::size_of::<u32>();
+/// The offset of the checksum in the commit block.
+const COMMIT_CHECKSUM_OFFSET: usize = HEADER_SIZE + 4;
+/// The offset of the byte count in the revoke block.
+const REVOKE_COUNT_OFFSET: usize = HEADER_SIZE;
+
+/// The journal of the filesystem.
+///
+/// It lives in an inode whose blocks are allocated when the filesystem is created.
+pub(super) struct Journal {
+    /// The device block IDs of the journal blocks.
+    bids: Vec<Ext2Bid>,
+    /// The log occupies the journal blocks in `first..max_len` circularly.
+    first: u32,
+    max_len: u32,
+    /// The journal superblock.
+    ///
+    /// The lock is held during the whole commit or recovery, so they are serialized.
+    super_block: Mutex<RawJournalSuperBlock>,
+    feature_incompat: FeatureInCompatSet,
+    /// The seed of the checksums, or `None` if the checksums are disabled.
+    csum_seed: Option<u32>,
+    /// The metadata blocks of the running transaction, indexed by their locations.
+    running: Mutex<BTreeMap<Ext2Bid, Box<[u8]>>>,
+    /// The inodes whose metadata are in the running transaction, indexed by their numbers.
+    running_inodes: Mutex<BTreeMap<u32, Weak<Inode>>>,
+    /// The lock held by the handles for reading and by the commit for writing.
+    ///
+    /// So the running transaction is never committed in the middle of a handle.
+    updates: RwMutex<()>,
+    /// The metadata blocks of the committing transaction, which are not checkpointed yet.
+    committing: Mutex<Arc<BTreeMap<Ext2Bid, Box<[u8]>>>>,
+    /// The work item that commits the running transaction in the background.
+    commit_work: Arc<WorkItem>,
+    /// The timer that fires the `commit_work` after [`COMMIT_INTERVAL`] since the running
+    /// transaction starts.
+    commit_timer: Arc<Timer>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Journal {
+    /// Loads the journal stored in the `inode`.
+    ///
+    /// The `commit_work` should commit the running transaction, which is submitted when the
+    /// transaction grows large or becomes old.
+    pub fn load(
+        inode: &Inode,
+        block_device: Arc<dyn BlockDevice>,
+        commit_work: Arc<WorkItem>,
+    ) -> Result<Self> {
+        let bids = inode.device_bids()?;
+        let Some(&first_bid) = bids.first() else {
+            return_errno_with_message!(Errno::EUCLEAN, "the journal is empty");
+        };
+        let super_block =
+            block_device.read_val::<RawJournalSuperBlock>(first_bid as usize * BLOCK_SIZE)?;
+        if super_block.header.magic() != JBD2_MAGIC {
+            return_errno_with_message!(Errno::EINVAL, "bad journal magic number");
+        }
+        if u32::from_be(super_block.block_size) as usize != BLOCK_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "not supported journal block size");
+        }
+        let max_len = u32::from_be(super_block.max_len);
+        let first = u32::from_be(super_block.first);
+        if max_len as usize > bids.len() || first == 0 || first >= max_len {
+            return_errno_with_message!(Errno::EUCLEAN, "bad journal layout");
+        }
+
+        let feature_incompat = match super_block.header.block_type() {
+            // The features are valid in the version 2 only.
+            SUPER_BLOCK_V1 => FeatureInCompatSet::empty(),
+            SUPER_BLOCK_V2 => {
+                let features =
+                    FeatureInCompatSet::from_bits(u32::from_be(super_block.feature_incompat))
+                        .ok_or(Error::with_message(
+                            Errno::EINVAL,
+                            "invalid journal feature incompat set",
+                        ))?;
+                if !FeatureInCompatSet::SUPPORTED.contains(features)
+                    || super_block.feature_ro_compat != 0
+                {
+                    return_errno_with_message!(Errno::EINVAL, "not supported journal features");
+                }
+                features
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "bad journal superblock"),
+        };
+
+        let csum_seed = if feature_incompat.has_checksum() {
+            if super_block.checksum_type != CHECKSUM_TYPE_CRC32C {
+                return_errno_with_message!(Errno::EINVAL, "not supported journal checksum type");
+            }
+            if u32::from_be(super_block.checksum) != super_block.calc_checksum() {
+                return_errno_with_message!(Errno::EBADMSG, "bad journal superblock checksum");
+            }
+            Some(crc32c(!0, &super_block.uuid))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            bids,
+            first,
+            max_len,
+            super_block: Mutex::new(super_block),
+            feature_incompat,
+            csum_seed,
+            running: Mutex::new(BTreeMap::new()),
+            running_inodes: Mutex::new(BTreeMap::new()),
+            updates: RwMutex::new(()),
+            committing: Mutex::new(Arc::new(BTreeMap::new())),
+            commit_timer: {
+                let commit_work = commit_work.clone();
+                MonotonicClock::timer_manager().create_timer(move || {
+                    submit_work_item(commit_work.clone(), WorkPriority::Normal);
+                })
+            },
+            commit_work,
+            block_device,
+        })
+    }
+
+    /// Starts a handle, whose blocks are committed in the same transaction.
+    ///
+    /// The running transaction cannot be committed until the handle is dropped, so the handle
+    /// must not be held across a commit.
+    pub fn start(&self) -> JournalHandle<'_> {
+        JournalHandle {
+            journal: self,
+            _updates: self.updates.read(),
+        }
+    }
+
+    /// Adds an inode whose metadata are in the running transaction.
+    ///
+    /// The data of the inode are written back before the transaction is committed.
+    pub fn add_inode(&self, inode: &Arc<Inode>) {
+        self.running_inodes
+            .lock()
+            .insert(inode.ino(), Arc::downgrade(inode));
+    }
+
+    /// Takes the inodes whose data must be written back before the running transaction is
+    /// committed.
+    pub fn take_inodes(&self) -> Vec<Arc<Inode>> {
+        core::mem::take(&mut *self.running_inodes.lock())
+            .into_values()
+            .filter_map(|inode| inode.upgrade())
+            .collect()
+    }
+
+    /// Returns whether the running transaction is large enough to be committed.
+    ///
+    /// Like Linux, a commit is triggered when the transaction occupies a quarter of the journal,
+    /// which leaves room for the blocks written before the commit starts.
+    pub fn needs_commit(&self) -> bool {
+        self.running.lock().len() >= self.max_transaction_blocks() / 4
+    }
+
+    /// Returns the maximum number of the metadata blocks in a transaction.
+    ///
+    /// Each transaction consists of the descriptor blocks, the metadata blocks and a commit
+    /// block, which must fit in the journal.
+    fn max_transaction_blocks(&self) -> usize {
+        let capacity = (self.max_len - self.first) as usize;
+        let tags_per_descriptor = self.tags_per_descriptor();
+        (capacity.saturating_sub(2) * tags_per_descriptor / (tags_per_descriptor + 1)).max(1)
+    }
+
+    /// Removes the blocks in the `range` from the transactions, since they are freed.
+    ///
+    /// The freed blocks may be reused as data blocks, which must not be overwritten
+    /// by the stale metadata. So it waits for the committing transaction to finish.
+    pub fn forget(&self, range: Range<Ext2Bid>) {
+        self.running.lock().retain(|bid, _| !range.contains(bid));
+        if self.committing.lock().range(range).next().is_some() {
+            drop(self.super_block.lock());
+        }
+    }
+
+    /// Copies the blocks of the transactions to the `bio_segment`, which is read from
+    /// the contiguous blocks starting from the `bid`.
+    ///
+    /// The blocks of the transactions are newer than those on the device.
+    pub fn copy_blocks(&self, bid: Ext2Bid, bio_segment: &BioSegment) {
+        let range = bid..bid + bio_segment.nblocks() as Ext2Bid;
+        let committing = self.committing.lock().clone();
+        let running = self.running.lock();
+        // The blocks of the running transaction overwrite those of the committing one.
+        for (&block_bid, block) in committing.range(range.clone()).chain(running.range(range)) {
+            let mut writer = bio_segment.writer().unwrap();
+            writer
+                .skip((block_bid - bid) as usize * BLOCK_SIZE)
+                .limit(BLOCK_SIZE);
+            writer.write(&mut VmReader::from(&block[..]));
+        }
+    }
+
+    /// Returns whether any block in the `range` belongs to the transactions.
+    pub fn contains(&self, range: Range<Ext2Bid>) -> bool {
+        self.running.lock().range(range.clone()).next().is_some()
+            || self.committing.lock().range(range).next().is_some()
+    }
+
+    /// Replays the committed transactions in the journal and empties it.
+    pub fn recover(&self) -> Result<()> {
+        let mut super_block = self.super_block.lock();
+        let start = u32::from_be(super_block.start);
+        if start == 0 {
+            return Ok(());
+        }
+        let first_sequence = u32::from_be(super_block.sequence);
+
+        // Finds the end of the log and collects the revoked blocks of the committed
+        // transactions, along with the sequence of the latest transaction revoking them.
+        let mut revoked = BTreeMap::new();
+        let mut pending_revoked = Vec::new();
+        let mut sequence = first_sequence;
+        let mut idx = start;
+        loop {
+            let block = self.read_block(idx)?;
+            let header = RawHeader::from_bytes(&block[..HEADER_SIZE]);
+            if header.magic() != JBD2_MAGIC || header.sequence() != sequence {
+                break;
+            }
+            match header.block_type() {
+                DESCRIPTOR_BLOCK if self.verify_tail(&block) => {
+                    let ntags = self.parse_tags(&block).len() as u32;
+                    idx = self.log_idx(idx + ntags);
+                }
+                REVOKE_BLOCK if self.verify_tail(&block) => {
+                    pending_revoked.extend(self.parse_revoked(&block)?);
+                }
+                COMMIT_BLOCK if self.verify_commit(&block) => {
+                    for bid in pending_revoked.drain(..) {
+                        revoked.insert(bid, sequence);
+                    }
+                    sequence = sequence.wrapping_add(1);
+                }
+                _ => break,
+            }
+            idx = self.log_idx(idx + 1);
+        }
+        let end_sequence = sequence;
+
+        // Replays the blocks that are not revoked by the same or later transactions.
+        let mut sequence = first_sequence;
+        let mut idx = start;
+        while sequence != end_sequence {
+            let block = self.read_block(idx)?;
+            idx = self.log_idx(idx + 1);
+            let header = RawHeader::from_bytes(&block[..HEADER_SIZE]);
+            match header.block_type() {
+                DESCRIPTOR_BLOCK => {
+                    let mut bio_waiter = BioWaiter::new();
+                    for tag in self.parse_tags(&block) {
+                        let mut data = self.read_block(idx)?;
+                        idx = self.log_idx(idx + 1);
+                        if revoked.get(&tag.bid).is_some_and(|&revoked_sequence| {
+                            revoked_sequence.wrapping_sub(sequence) as i32 >= 0
+                        }) {
+                            continue;
+                        }
+                        if !self.verify_tag(&tag, &data, sequence) {
+                            warn!("bad checksum of the block {} in the journal", tag.bid);
+                            continue;
+                        }
+                        if tag.flags & TAG_ESCAPE != 0 {
+                            data[..4].copy_from_slice(&JBD2_MAGIC.to_be_bytes());
+                        }
+                        bio_waiter.concat(
+                            self.block_device
+                                .write_bytes_async(tag.bid as usize * BLOCK_SIZE, &data)?,
+                        );
+                    }
+                    bio_waiter.wait().ok_or_else(|| {
+                        Error::with_message(Errno::EIO, "failed to replay the journal")
+                    })?;
+                }
+                COMMIT_BLOCK => sequence = sequence.wrapping_add(1),
+                _ => (),
+            }
+        }
+        self.flush()?;
+
+        super_block.sequence = end_sequence.wrapping_add(1).to_be();
+        super_block.start = 0;
+        self.write_super_block(&mut super_block)?;
+        self.flush()
+    }
+
+    /// Commits the running transaction and writes back the `raw_super_block` of the filesystem.
+    ///
+    /// The data of the inodes in the transaction should have been written back, see
+    /// [`Self::take_inodes`].
+    pub fn commit(&self, raw_super_block: &RawSuperBlock) -> Result<()> {
+        let mut super_block = self.super_block.lock();
+        let blocks = {
+            // Waits for the running handles to finish.
+            let _updates = self.updates.write();
+            core::mem::take(&mut *self.running.lock())
+        };
+        self.commit_timer.cancel();
+        if blocks.is_empty() {
+            return self.write_fs_super_block(raw_super_block, false);
+        }
+        let blocks = Arc::new(blocks);
+        *self.committing.lock() = blocks.clone();
+
+        // The blocks are added only if they fit in the journal, see `JournalHandle::add_blocks`.
+        debug_assert!(blocks.len() <= self.max_transaction_blocks());
+        let transaction: Vec<(Ext2Bid, &[u8])> = blocks
+            .iter()
+            .map(|(&bid, block)| (bid, &block[..]))
+            .collect();
+        self.commit_transaction(&mut super_block, &transaction, raw_super_block)?;
+
+        *self.committing.lock() = Arc::new(BTreeMap::new());
+        Ok(())
+    }
+
+    fn commit_transaction(
+        &self,
+        super_block: &mut RawJournalSuperBlock,
+        blocks: &[(Ext2Bid, &[u8])],
+        raw_super_block: &RawSuperBlock,
+    ) -> Result<()> {
+        let sequence = u32::from_be(super_block.sequence);
+
+        // Writes the descriptor and metadata blocks to the log.
+        let mut bio_waiter = BioWaiter::new();
+        let mut idx = self.first;
+        for descriptor_blocks in blocks.chunks(self.tags_per_descriptor()) {
+            let mut descriptor = vec![0u8; BLOCK_SIZE];
+            descriptor[..HEADER_SIZE]
+                .copy_from_slice(RawHeader::new(DESCRIPTOR_BLOCK, sequence).as_bytes());
+            let mut offset = HEADER_SIZE;
+            let mut log_idx = idx + 1;
+            for (tag_idx, &(bid, block)) in descriptor_blocks.iter().enumerate() {
+                let mut data = block.to_vec();
+                let mut flags = 0;
+                if data[..4] == JBD2_MAGIC.to_be_bytes() {
+                    data[..4].fill(0);
+                    flags |= TAG_ESCAPE;
+                }
+                if tag_idx > 0 {
+                    flags |= TAG_SAME_UUID;
+                }
+                if tag_idx == descriptor_blocks.len() - 1 {
+                    flags |= TAG_LAST;
+                }
+                let tag = BlockTag {
+                    bid: bid as u64,
+                    flags,
+                    checksum: self.calc_tag_checksum(&data, sequence),
+                };
+                offset += self.write_tag(&mut descriptor[offset..], &tag);
+                if tag_idx == 0 {
+                    descriptor[offset..offset + UUID_SIZE].copy_from_slice(&super_block.uuid);
+                    offset += UUID_SIZE;
+                }
+                bio_waiter.concat(self.write_block_async(log_idx, &data)?);
+                log_idx += 1;
+            }
+            self.update_tail(&mut descriptor);
+            bio_waiter.concat(self.write_block_async(idx, &descriptor)?);
+            idx = log_idx;
+        }
+
+        // Marks the journal as non-empty.
+        super_block.start = self.first.to_be();
+        bio_waiter.concat(self.write_super_block_async(super_block)?);
+        self.write_fs_super_block(raw_super_block, true)?;
+        bio_waiter
+            .wait()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "failed to write the journal"))?;
+        self.flush()?;
+
+        // Writes the commit block after the other blocks of the transaction are durable.
+        let mut commit = vec![0u8; BLOCK_SIZE];
+        commit[..HEADER_SIZE].copy_from_slice(RawHeader::new(COMMIT_BLOCK, sequence).as_bytes());
+        let commit_time = now();
+        commit[48..56].copy_from_slice(&commit_time.as_secs().to_be_bytes());
+        commit[56..60].copy_from_slice(&commit_time.subsec_nanos().to_be_bytes());
+        if let Some(seed) = self.csum_seed {
+            let checksum = crc32c(seed, &commit);
+            commit[COMMIT_CHECKSUM_OFFSET..COMMIT_CHECKSUM_OFFSET + 4]
+                .copy_from_slice(&checksum.to_be_bytes());
+        }
+        self.write_block_async(idx, &commit)?
+            .wait()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "failed to write the commit block"))?;
+        self.flush()?;
+
+        // Checkpoints the transaction.
+        let mut bio_waiter = BioWaiter::new();
+        for &(bid, block) in blocks {
+            bio_waiter.concat(
+                self.block_device
+                    .write_bytes_async(bid as usize * BLOCK_SIZE, block)?,
+            );
+        }
+        bio_waiter
+            .wait()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "failed to checkpoint the journal"))?;
+        self.flush()?;
+
+        // Empties the journal.
+        super_block.sequence = sequence.wrapping_add(1).to_be();
+        super_block.start = 0;
+        self.write_super_block(super_block)?;
+        self.write_fs_super_block(raw_super_block, false)
+    }
+
+    /// Writes the superblock of the filesystem, with the flag indicating
+    /// whether the journal needs recovery.
+    fn write_fs_super_block(
+        &self,
+        raw_super_block: &RawSuperBlock,
+        needs_recovery: bool,
+    ) -> Result<()> {
+        let mut raw_super_block = *raw_super_block;
+        if needs_recovery {
+            raw_super_block.feature_incompat |= FsFeatureInCompatSet::RECOVER.bits();
+        } else {
+            raw_super_block.feature_incompat &= !FsFeatureInCompatSet::RECOVER.bits();
+        }
+        raw_super_block.update_checksum();
+        self.block_device
+            .write_bytes_async(SUPER_BLOCK_OFFSET, raw_super_block.as_bytes())?
+            .wait()
+            .ok_or_else(|| Error::with_message(Errno::EIO, "failed to write the superblock"))?;
+        Ok(())
+    }
+
+    fn write_super_block(&self, super_block: &mut RawJournalSuperBlock) -> Result<()> {
+        self.write_super_block_async(super_block)?
+            .wait()
+            .ok_or_else(|| {
+                Error::with_message(Errno::EIO, "failed to write the journal superblock")
+            })?;
+        Ok(())
+    }
+
+    fn write_super_block_async(&self, super_block: &mut RawJournalSuperBlock) -> Result<BioWaiter> {
+        if self.csum_seed.is_some() {
+            super_block.checksum = super_block.calc_checksum().to_be();
+        }
+        let waiter = self
+            .block_device
+            .write_bytes_async(self.bids[0] as usize * BLOCK_SIZE, super_block.as_bytes())?;
+        Ok(waiter)
+    }
+
+    /// Reads the block at `idx` of the journal.
+    fn read_block(&self, idx: u32) -> Result<Vec<u8>> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        self.block_device
+            .read_bytes(self.bids[idx as usize] as usize * BLOCK_SIZE, &mut block)?;
+        Ok(block)
+    }
+
+    /// Writes the block at `idx` of the journal asynchronously.
+    fn write_block_async(&self, idx: u32, block: &[u8]) -> Result<BioWaiter> {
+        let waiter = self
+            .block_device
+            .write_bytes_async(self.bids[idx as usize] as usize * BLOCK_SIZE, block)?;
+        Ok(waiter)
+    }
+
+    fn flush(&self) -> Result<()> {
+        match self.block_device.sync()? {
+            BioStatus::Complete => Ok(()),
+            err_status => Err(Error::from(err_status)),
+        }
+    }
+
+    /// Returns the index of the log block, which wraps around at the end of the journal.
+    fn log_idx(&self, idx: u32) -> u32 {
+        if idx >= self.max_len {
+            idx - self.max_len + self.first
+        } else {
+            idx
+        }
+    }
+
+    fn tag_size(&self) -> usize {
+        if self.feature_incompat.contains(FeatureInCompatSet::CSUM_V3) {
+            return 16;
+        }
+        let mut size = 8;
+        if self.feature_incompat.contains(FeatureInCompatSet::CSUM_V2) {
+            size += 2;
+        }
+        if self.feature_incompat.contains(FeatureInCompatSet::BIT64) {
+            size += 4;
+        }
+        size
+    }
+
+    fn tail_size(&self) -> usize {
+        if self.csum_seed.is_some() {
+            TAIL_SIZE
+        } else {
+            0
+        }
+    }
+
+    /// Returns the number of tags that fit in a descriptor block.
+    fn tags_per_descriptor(&self) -> usize {
+        (BLOCK_SIZE - HEADER_SIZE - UUID_SIZE - self.tail_size()) / self.tag_size()
+    }
+
+    fn parse_tags(&self, descriptor: &[u8]) -> Vec<BlockTag> {
+        let tag_size = self.tag_size();
+        let end = BLOCK_SIZE - self.tail_size();
+        let mut tags = Vec::new();
+        let mut offset = HEADER_SIZE;
+        while offset + tag_size <= end {
+            let tag_bytes = &descriptor[offset..offset + tag_size];
+            let tag = if self.feature_incompat.contains(FeatureInCompatSet::CSUM_V3) {
+                BlockTag {
+                    bid: read_be32(tag_bytes, 0) as u64 | ((read_be32(tag_bytes, 8) as u64) << 32),
+                    flags: read_be32(tag_bytes, 4),
+                    checksum: read_be32(tag_bytes, 12),
+                }
+            } else {
+                let bid_hi = if self.feature_incompat.contains(FeatureInCompatSet::BIT64) {
+                    read_be32(tag_bytes, 8) as u64
+                } else {
+                    0
+                };
+                BlockTag {
+                    bid: read_be32(tag_bytes, 0) as u64 | (bid_hi << 32),
+                    flags: u16::from_be_bytes(tag_bytes[6..8].try_into().unwrap()) as u32,
+                    checksum: u16::from_be_bytes(tag_bytes[4..6].try_into().unwrap()) as u32,
+                }
+            };
+            offset += tag_size;
+            if tag.flags & TAG_SAME_UUID == 0 {
+                offset += UUID_SIZE;
+            }
+            let is_last = tag.flags & TAG_LAST != 0;
+            tags.push(tag);
+            if is_last {
+                break;
+            }
+        }
+        tags
+    }
+
+    /// Writes the `tag` to the `buf` and returns the number of written bytes.
+    fn write_tag(&self, buf: &mut [u8], tag: &BlockTag) -> usize {
+        let tag_size = self.tag_size();
+        let buf = &mut buf[..tag_size];
+        buf[0..4].copy_from_slice(&(tag.bid as u32).to_be_bytes());
+        if self.feature_incompat.contains(FeatureInCompatSet::CSUM_V3) {
+            buf[4..8].copy_from_slice(&tag.flags.to_be_bytes());
+            buf[8..12].copy_from_slice(&((tag.bid >> 32) as u32).to_be_bytes());
+            buf[12..16].copy_from_slice(&tag.checksum.to_be_bytes());
+        } else {
+            buf[4..6].copy_from_slice(&(tag.checksum as u16).to_be_bytes());
+            buf[6..8].copy_from_slice(&(tag.flags as u16).to_be_bytes());
+            if self.feature_incompat.contains(FeatureInCompatSet::BIT64) {
+                buf[8..12].copy_from_slice(&((tag.bid >> 32) as u32).to_be_bytes());
+            }
+        }
+        tag_size
+    }
+
+    fn parse_revoked(&self, revoke: &[u8]) -> Result<Vec<u64>> {
+        let count = read_be32(revoke, REVOKE_COUNT_OFFSET) as usize;
+        if count > BLOCK_SIZE - self.tail_size() {
+            return_errno_with_message!(Errno::EUCLEAN, "bad journal revoke block");
+        }
+        let start = REVOKE_COUNT_OFFSET + core::mem::size_of::<u32>();
+        let revoked = if self.feature_incompat.contains(FeatureInCompatSet::BIT64) {
+            revoke[start..count.max(start)]
+                .chunks_exact(8)
+                .map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap()))
+                .collect()
+        } else {
+            revoke[start..count.max(start)]
+                .chunks_exact(4)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()) as u64)
+                .collect()
+        };
+        Ok(revoked)
+    }
+
+    fn calc_tag_checksum(&self, data: &[u8], sequence: u32) -> u32 {
+        let Some(seed) = self.csum_seed else {
+            return 0;
+        };
+        let crc = crc32c(seed, &sequence.to_be_bytes());
+        let checksum = crc32c(crc, data);
+        if self.feature_incompat.contains(FeatureInCompatSet::CSUM_V3) {
+            checksum
+        } else {
+            checksum & 0xffff
+        }
+    }
+
+    fn verify_tag(&self, tag: &BlockTag, data: &[u8], sequence: u32) -> bool {
+        self.csum_seed.is_none() || tag.checksum == self.calc_tag_checksum(data, sequence)
+    }
+
+    /// Updates the checksum at the end of the descriptor or revoke `block`.
+    fn update_tail(&self, block: &mut [u8]) {
+        if let Some(seed) = self.csum_seed {
+            let checksum = crc32c(seed, block);
+            block[BLOCK_SIZE - TAIL_SIZE..].copy_from_slice(&checksum.to_be_bytes());
+        }
+    }
+
+    fn verify_tail(&self, block: &[u8]) -> bool {
+        let Some(seed) = self.csum_seed else {
+            return true;
+        };
+        let mut block = block.to_vec();
+        let checksum = read_be32(&block, BLOCK_SIZE - TAIL_SIZE);
+        block[BLOCK_SIZE - TAIL_SIZE..].fill(0);
+        checksum == crc32c(seed, &block)
+    }
+
+    fn verify_commit(&self, block: &[u8]) -> bool {
+        let Some(seed) = self.csum_seed else {
+            return true;
+        };
+        let mut block = block.to_vec();
+        let checksum = read_be32(&block, COMMIT_CHECKSUM_OFFSET);
+        block[COMMIT_CHECKSUM_OFFSET..COMMIT_CHECKSUM_OFFSET + 4].fill(0);
+        checksum == crc32c(seed, &block)
+    }
+}
+
+impl Debug for Journal {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Journal")
+            .field("len", &self.bids.len())
+            .field("feature_incompat", &self.feature_incompat)
+            .field("running_blocks", &self.running.lock().len())
+            .finish()
+    }
+}
+
+/// A handle of the journal, which adds blocks to the running transaction.
+///
+/// All the blocks added by a handle belong to the same transaction, so they are committed or
+/// replayed atomically.
+pub(super) struct JournalHandle<'a> {
+    journal: &'a Journal,
+    _updates: RwMutexReadGuard<'a, ()>,
+}
+
+impl JournalHandle<'_> {
+    /// Adds the metadata blocks starting from the `bid` to the running transaction.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOSPC`] if the running transaction would outgrow the
+    /// journal, in which case no blocks are added. The transaction must be committed first.
+    pub fn add_blocks(&self, bid: Ext2Bid, buf: &[u8]) -> Result<()> {
+        debug_assert!(buf.len() % BLOCK_SIZE == 0);
+        let journal = self.journal;
+        let mut running = journal.running.lock();
+
+        let num_new_blocks = (0..(buf.len() / BLOCK_SIZE) as Ext2Bid)
+            .filter(|idx| !running.contains_key(&(bid + idx)))
+            .count();
+        if running.len() + num_new_blocks > journal.max_transaction_blocks() {
+            return_errno_with_message!(Errno::ENOSPC, "the transaction is too large");
+        }
+
+        let is_new_transaction = running.is_empty();
+        for (idx, block) in buf.chunks(BLOCK_SIZE).enumerate() {
+            running.insert(bid + idx as Ext2Bid, block.into());
+        }
+        drop(running);
+
+        if is_new_transaction {
+            journal
+                .commit_timer
+                .set_timeout(Timeout::After(COMMIT_INTERVAL));
+        }
+        if journal.needs_commit() {
+            submit_work_item(journal.commit_work.clone(), WorkPriority::Normal);
+        }
+        Ok(())
+    }
+}
+
+/// The tag describing a metadata block in the descriptor block.
+#[derive(Debug)]
+struct BlockTag {
+    bid: u64,
+    flags: u32,
+    checksum: u32,
+}
+
+fn read_be32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+bitflags! {
+    /// Incompatible feature set of the journal.
+    struct FeatureInCompatSet: u32 {
+        /// The journal has revoke blocks
+        const REVOKE = 1 << 0;
+        /// The block numbers in the journal are 64 bits
+        const BIT64 = 1 << 1;
+        /// The commit blocks are written without waiting for the other blocks
+        const ASYNC_COMMIT = 1 << 2;
+        /// The journal blocks have the version 2 checksums
+        const CSUM_V2 = 1 << 3;
+        /// The journal blocks have the version 3 checksums
+        const CSUM_V3 = 1 << 4;
+        /// The journal has a fast commit area
+        const FAST_COMMIT = 1 << 5;
+
+        /// The incompatible features supported by this driver.
+        const SUPPORTED = Self::REVOKE.bits
+            | Self::BIT64.bits
+            | Self::ASYNC_COMMIT.bits
+            | Self::CSUM_V2.bits
+            | Self::CSUM_V3.bits;
+    }
+}
+
+impl FeatureInCompatSet {
+    fn has_checksum(&self) -> bool {
+        self.intersects(Self::CSUM_V2 | Self::CSUM_V3)
+    }
+}
+
+const_assert!(core::mem::size_of::<RawJournalSuperBlock>() == 1024);
+
+/// The header of the journal blocks.
+///
+/// All the fields of the journal are stored in big-endian.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawHeader {
+    magic: u32,
+    block_type: u32,
+    sequence: u32,
+}
+
+impl RawHeader {
+    fn new(block_type: u32, sequence: u32) -> Self {
+        Self {
+            magic: JBD2_MAGIC.to_be(),
+            block_type: block_type.to_be(),
+            sequence: sequence.to_be(),
+        }
+    }
+
+    fn magic(&self) -> u32 {
+        u32::from_be(self.magic)
+    }
+
+    fn block_type(&self) -> u32 {
+        u32::from_be(self.block_type)
+    }
+
+    fn sequence(&self) -> u32 {
+        u32::from_be(self.sequence)
+    }
+}
+
+/// The raw journal superblock, which is the first block of the journal.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod)]
+struct RawJournalSuperBlock {
+    header: RawHeader,
+    block_size: u32,
+    /// Total number of blocks in the journal.
+    max_len: u32,
+    /// First block of the log.
+    first: u32,
+    /// Sequence of the first transaction expected in the log.
+    sequence: u32,
+    /// Block of the start of the log, or zero if the journal is empty.
+    start: u32,
+    /// Error value set by `jbd2_journal_abort`.
+    errno: u32,
+    feature_compat: u32,
+    feature_incompat: u32,
+    feature_ro_compat: u32,
+    uuid: [u8; 16],
+    nr_users: u32,
+    dynsuper: u32,
+    max_transaction: u32,
+    max_trans_data: u32,
+    checksum_type: u8,
+    padding2: [u8; 3],
+    /// Number of the fast commit blocks.
+    num_fc_blocks: u32,
+    /// Block of the head of the log, which is only maintained when the journal is empty.
+    head: u32,
+    padding: [u32; 40],
+    checksum: u32,
+    /// Ids of the filesystems sharing the journal.
+    users: [u8; 768],
+}
+
+impl RawJournalSuperBlock {
+    fn calc_checksum(&self) -> u32 {
+        let mut super_block = *self;
+        super_block.checksum = 0;
+        crc32c(!0, super_block.as_bytes())
+    }
+}
@@ -14,6 +14,13 @@
 //!    stored in PageCache, which accelerates the performance of data access.
 //! 3. Compatible with queue-based block device. The filesystem can submits multiple
 //!    BIO requests to be block device at once, thereby enhancing I/O performance.
+//! 4. Compatible with the default features of Ext4, including extents, 64-bit block
+//!    group descriptors, flexible block groups, metadata checksums and hash-indexed
+//!    directories (whose index is dropped once they are modified).
+//!    So the filesystem is also registered as "ext4".
+//! 5. Journaling in the `data=ordered` mode. The metadata updates are committed to the
+//!    JBD2 journal on sync, and the journal is replayed on mount if needed.
+//!    So the filesystem is also registered as "ext3".
 //!
 //! # Example
 //!
@@ -42,15 +49,17 @@ pub use fs::Ext2;
 pub use inode::{FilePerm, Inode};
 pub use super_block::{SuperBlock, MAGIC_NUM};
 
-use crate::fs::ext2::fs::Ext2Type;
+use crate::fs::ext2::fs::{Ext2Type, Ext3Type, Ext4Type};
 
 mod block_group;
 mod block_ptr;
 mod dir;
+mod extent;
 mod fs;
 mod impl_for_vfs;
 mod indirect_block_cache;
 mod inode;
+mod journal;
 mod prelude;
 mod super_block;
 mod utils;
@@ -59,4 +68,368 @@ mod xattr;
 pub(super) fn init() {
     let ext2_type = Arc::new(Ext2Type);
     super::registry::register(ext2_type).unwrap();
+    let ext3_type = Arc::new(Ext3Type);
+    super::registry::register(ext3_type).unwrap();
+    let ext4_type = Arc::new(Ext4Type);
+    super::registry::register(ext4_type).unwrap();
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::fmt::Debug;
+
+    use aster_block::{
+        bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+        BlockDevice, BlockDeviceMeta, BLOCK_SIZE,
+    };
+    use ostd::{
+        mm::{io_util::HasVmReaderWriter, FrameAllocOptions, Segment, VmIo, VmWriter, PAGE_SIZE},
+        prelude::*,
+    };
+
+    use super::{
+        super_block::{
+            FeatureCompatSet, FeatureInCompatSet, FeatureRoCompatSet, RawSuperBlock,
+            SUPER_BLOCK_OFFSET,
+        },
+        Ext2, SuperBlock,
+    };
+    use crate::{
+        fs::utils::{FileSystem, Inode, InodeMode, InodeType},
+        prelude::*,
+    };
+
+    const SECTOR_SIZE: usize = 512;
+
+    /// The magic number and the type of the JBD2 commit blocks.
+    const JBD2_MAGIC: u32 = 0xc03b3998;
+    const JBD2_COMMIT_BLOCK: u32 = 2;
+
+    /// An Ext4 image with the default features of `mkfs.ext4`.
+    ///
+    /// It contains `big`, a file filled with `BIG_FILE_LINE`; `sparse`, a file whose even blocks
+    /// start with their indexes and whose extents do not fit in the inode; and `htree`, a
+    /// directory whose entries `file_1` to `file_500` are indexed by hashes.
+    static EXT4_IMAGE: &[u8] = include_bytes!("../../../../test/build/ext4.img");
+    const BIG_FILE_LINE: &[u8] = b"0123456789abcde\n";
+    const BIG_FILE_SIZE: usize = 1024 * 1024;
+    const SPARSE_FILE_LAST_BLOCK: usize = 14;
+    const NUM_HTREE_FILES: usize = 500;
+
+    /// An Ext4 image whose journal needs recovery.
+    ///
+    /// The journal holds a committed transaction that overwrites the data of `replayed` and
+    /// `revoked` with `JOURNALED_BYTE`, a committed transaction that revokes the block of
+    /// `revoked`, and an uncommitted transaction that overwrites the data of `uncommitted`.
+    static JOURNAL_IMAGE: &[u8] = include_bytes!("../../../../test/build/ext4_journal.img");
+    const ORIGINAL_DATA: &[u8] = b"before\n";
+    const JOURNALED_BYTE: u8 = b'A';
+
+    /// A block device backed by memory, which records the writes to it.
+    struct MemoryDisk {
+        segment: Segment<()>,
+        /// The offsets and the data of the writes, in the order they are completed.
+        writes: SpinLock<Vec<(usize, Vec<u8>)>>,
+    }
+
+    impl MemoryDisk {
+        /// Creates a disk from the image, with the `writes` applied in order.
+        fn new(image: &[u8], writes: &[(usize, Vec<u8>)]) -> Arc<Self> {
+            let segment = FrameAllocOptions::new()
+                .zeroed(false)
+                .alloc_segment(image.len().div_ceil(PAGE_SIZE))
+                .unwrap();
+            segment.write_bytes(0, image).unwrap();
+            for (offset, data) in writes {
+                segment.write_bytes(*offset, data).unwrap();
+            }
+            Arc::new(Self {
+                segment,
+                writes: SpinLock::new(Vec::new()),
+            })
+        }
+
+        fn sectors_count(&self) -> usize {
+            self.segment.size() / SECTOR_SIZE
+        }
+
+        fn needs_recovery(&self) -> bool {
+            let raw_super_block = self
+                .segment
+                .read_val::<RawSuperBlock>(SUPER_BLOCK_OFFSET)
+                .unwrap();
+            SuperBlock::try_from(raw_super_block)
+                .unwrap()
+                .needs_recovery()
+        }
+    }
+
+    impl Debug for MemoryDisk {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct("MemoryDisk")
+                .field("blocks_count", &self.sectors_count())
+                .finish()
+        }
+    }
+
+    impl BlockDevice for MemoryDisk {
+        fn enqueue(&self, bio: SubmittedBio) -> core::prelude::v1::Result<(), BioEnqueueError> {
+            let mut cur_device_ofs = bio.sid_range().start.to_raw() as usize * SECTOR_SIZE;
+            for seg in bio.segments() {
+                match bio.type_() {
+                    BioType::Read => {
+                        seg.writer()
+                            .unwrap()
+                            .write(self.segment.reader().skip(cur_device_ofs));
+                    }
+                    BioType::Write => {
+                        let mut data = vec![0u8; seg.nbytes()];
+                        seg.reader()
+                            .unwrap()
+                            .read(&mut VmWriter::from(data.as_mut_slice()));
+                        self.segment.write_bytes(cur_device_ofs, &data).unwrap();
+                        self.writes.lock().push((cur_device_ofs, data));
+                    }
+                    _ => (),
+                }
+                cur_device_ofs += seg.nbytes();
+            }
+            bio.complete(BioStatus::Complete);
+            Ok(())
+        }
+
+        fn metadata(&self) -> BlockDeviceMeta {
+            BlockDeviceMeta {
+                max_nr_segments_per_bio: usize::MAX,
+                nr_sectors: self.sectors_count(),
+            }
+        }
+    }
+
+    fn is_commit_block(block: &[u8]) -> bool {
+        let read_be32 =
+            |offset: usize| u32::from_be_bytes(block[offset..offset + 4].try_into().unwrap());
+        read_be32(0) == JBD2_MAGIC && read_be32(4) == JBD2_COMMIT_BLOCK
+    }
+
+    fn big_file_content(size: usize) -> Vec<u8> {
+        BIG_FILE_LINE.iter().copied().cycle().take(size).collect()
+    }
+
+    fn sparse_file_content() -> Vec<u8> {
+        let mut content = Vec::new();
+        for idx in (0..=SPARSE_FILE_LAST_BLOCK).step_by(2) {
+            content.resize(idx * BLOCK_SIZE, 0);
+            content.extend_from_slice(format!("block {:02}\n", idx).as_bytes());
+        }
+        content
+    }
+
+    fn list_dir(dir: &Arc<dyn Inode>) -> Vec<String> {
+        let mut names = Vec::new();
+        dir.readdir_at(0, &mut names).unwrap();
+        names.retain(|name| name != "." && name != "..");
+        names.sort();
+        names
+    }
+
+    fn read_file(dir: &Arc<dyn Inode>, name: &str) -> Vec<u8> {
+        let file = dir.lookup(name).unwrap();
+        let mut buf = vec![0u8; file.size()];
+        let len = file.read_bytes_at(0, &mut buf).unwrap();
+        assert_eq!(len, buf.len());
+        buf
+    }
+
+    #[ktest]
+    fn recover_journal() {
+        let disk = MemoryDisk::new(JOURNAL_IMAGE, &[]);
+        assert!(disk.needs_recovery());
+
+        let ext2 = Ext2::open(disk.clone()).unwrap();
+        assert!(!disk.needs_recovery());
+        let root = FileSystem::root_inode(ext2.as_ref());
+        // The committed transactions are replayed, except for the revoked block.
+        assert_eq!(
+            read_file(&root, "replayed"),
+            [JOURNALED_BYTE; ORIGINAL_DATA.len()]
+        );
+        assert_eq!(read_file(&root, "revoked"), ORIGINAL_DATA);
+        // The uncommitted transaction is discarded.
+        assert_eq!(read_file(&root, "uncommitted"), ORIGINAL_DATA);
+
+        // The journal is emptied, so the replayed data are read from their locations.
+        drop((root, ext2));
+        let ext2 = Ext2::open(disk.clone()).unwrap();
+        let root = FileSystem::root_inode(ext2.as_ref());
+        assert_eq!(
+            read_file(&root, "replayed"),
+            [JOURNALED_BYTE; ORIGINAL_DATA.len()]
+        );
+    }
+
+    #[ktest]
+    fn write_and_sync() {
+        let disk = MemoryDisk::new(JOURNAL_IMAGE, &[]);
+        let ext2 = Ext2::open(disk.clone()).unwrap();
+        let root = FileSystem::root_inode(ext2.as_ref());
+
+        let data: Vec<u8> = (0..3 * BLOCK_SIZE + 100).map(|i| (i % 251) as u8).collect();
+        let file = root
+            .create(
+                "file",
+                InodeType::File,
+                InodeMode::from_bits_truncate(0o644),
+            )
+            .unwrap();
+        file.write_bytes_at(0, &data).unwrap();
+        let dir = root
+            .create("dir", InodeType::Dir, InodeMode::from_bits_truncate(0o755))
+            .unwrap();
+        dir.create(
+            "child",
+            InodeType::File,
+            InodeMode::from_bits_truncate(0o644),
+        )
+        .unwrap();
+        root.unlink("replayed").unwrap();
+
+        ext2.sync().unwrap();
+        assert!(!disk.needs_recovery());
+        let writes = disk.writes.lock().clone();
+
+        // The metadata are checkpointed after the commit, so they are seen after remounting.
+        let check = |disk: &Arc<MemoryDisk>| {
+            let ext2 = Ext2::open(disk.clone()).unwrap();
+            let root = FileSystem::root_inode(ext2.as_ref());
+            assert_eq!(read_file(&root, "file"), data);
+            assert!(root.lookup("dir").unwrap().lookup("child").is_ok());
+            assert!(root
+                .lookup("replayed")
+                .is_err_and(|err| err.error() == Errno::ENOENT));
+        };
+        drop((file, dir, root, ext2));
+        check(&disk);
+
+        // If a crash happens right after the commit block is written, the metadata are
+        // recovered from the journal. In the `data=ordered` mode, the data have been
+        // written before the commit, so the file is intact.
+        let commit_idx = writes
+            .iter()
+            .position(|(_, data)| data.chunks(BLOCK_SIZE).any(is_commit_block))
+            .expect("no transaction is committed");
+        let crashed_disk = MemoryDisk::new(JOURNAL_IMAGE, &writes[..=commit_idx]);
+        assert!(crashed_disk.needs_recovery());
+        check(&crashed_disk);
+        assert!(!crashed_disk.needs_recovery());
+    }
+
+    #[ktest]
+    fn read_ext4() {
+        let disk = MemoryDisk::new(EXT4_IMAGE, &[]);
+        let ext2 = Ext2::open(disk).unwrap();
+        {
+            let super_block = ext2.super_block();
+            assert!(super_block
+                .feature_compat()
+                .contains(FeatureCompatSet::DIR_INDEX));
+            assert!(super_block.feature_incompat().contains(
+                FeatureInCompatSet::EXTENTS
+                    | FeatureInCompatSet::BIT64
+                    | FeatureInCompatSet::FLEX_BG
+            ));
+            assert!(super_block.feature_ro_compat().contains(
+                FeatureRoCompatSet::METADATA_CSUM
+                    | FeatureRoCompatSet::HUGE_FILE
+                    | FeatureRoCompatSet::DIR_NLINK
+            ));
+        }
+
+        let root = FileSystem::root_inode(ext2.as_ref());
+        assert_eq!(read_file(&root, "big"), big_file_content(BIG_FILE_SIZE));
+        assert_eq!(read_file(&root, "sparse"), sparse_file_content());
+
+        let htree = root.lookup("htree").unwrap();
+        let mut expected: Vec<String> = (1..=NUM_HTREE_FILES)
+            .map(|i| format!("file_{}", i))
+            .collect();
+        expected.sort();
+        assert_eq!(list_dir(&htree), expected);
+        for name in expected.iter() {
+            htree.lookup(name).unwrap();
+        }
+        assert!(htree
+            .lookup("file_0")
+            .is_err_and(|err| err.error() == Errno::ENOENT));
+    }
+
+    #[ktest]
+    fn write_ext4() {
+        let disk = MemoryDisk::new(EXT4_IMAGE, &[]);
+        let ext2 = Ext2::open(disk.clone()).unwrap();
+        let root = FileSystem::root_inode(ext2.as_ref());
+        let mode = InodeMode::from_bits_truncate(0o644);
+
+        // Grow a file mapped by extents.
+        let big = root.lookup("big").unwrap();
+        let big_content = big_file_content(BIG_FILE_SIZE + 2 * BLOCK_SIZE + 1);
+        big.write_bytes_at(BIG_FILE_SIZE, &big_content[BIG_FILE_SIZE..])
+            .unwrap();
+
+        // Fill the holes of a file whose extents are in an extent block.
+        let sparse = root.lookup("sparse").unwrap();
+        let mut sparse_content = sparse_file_content();
+        for idx in (1..SPARSE_FILE_LAST_BLOCK).step_by(2) {
+            let data = format!("hole {:02}\n", idx);
+            let offset = idx * BLOCK_SIZE;
+            sparse.write_bytes_at(offset, data.as_bytes()).unwrap();
+            sparse_content[offset..offset + data.len()].copy_from_slice(data.as_bytes());
+        }
+
+        // Modify the hash-indexed directory.
+        let htree = root.lookup("htree").unwrap();
+        for i in (1..=NUM_HTREE_FILES).step_by(2) {
+            htree.unlink(&format!("file_{}", i)).unwrap();
+        }
+        for i in 1..=100 {
+            htree
+                .create(&format!("new_file_{}", i), InodeType::File, mode)
+                .unwrap();
+        }
+        let mut expected: Vec<String> = (2..=NUM_HTREE_FILES)
+            .step_by(2)
+            .map(|i| format!("file_{}", i))
+            .chain((1..=100).map(|i| format!("new_file_{}", i)))
+            .collect();
+        expected.sort();
+        assert_eq!(list_dir(&htree), expected);
+
+        // The checksums of the metadata are verified when they are loaded again.
+        ext2.sync().unwrap();
+        drop((big, sparse, htree, root, ext2));
+        let ext2 = Ext2::open(disk).unwrap();
+        let root = FileSystem::root_inode(ext2.as_ref());
+        assert_eq!(read_file(&root, "big"), big_content);
+        assert_eq!(read_file(&root, "sparse"), sparse_content);
+        let htree = root.lookup("htree").unwrap();
+        assert_eq!(list_dir(&htree), expected);
+        for name in expected.iter() {
+            htree.lookup(name).unwrap();
+        }
+    }
+
+    #[ktest]
+    fn bad_checksums() {
+        let open_corrupted = |offset: usize| {
+            let corrupted = vec![!EXT4_IMAGE[offset]];
+            Ext2::open(MemoryDisk::new(EXT4_IMAGE, &[(offset, corrupted)]))
+        };
+
+        // A byte of the volume name in the superblock.
+        assert!(open_corrupted(SUPER_BLOCK_OFFSET + 120)
+            .is_err_and(|err| err.error() == Errno::EBADMSG));
+        // A byte of the free blocks count in the first group descriptor, which follows
+        // the superblock in the next block.
+        assert!(open_corrupted(BLOCK_SIZE + 0x0C).is_err_and(|err| err.error() == Errno::EBADMSG));
+    }
 }
@@ -2,7 +2,7 @@
 
 use ostd::const_assert;
 
-use super::{inode::RawInode, prelude::*};
+use super::{inode::RawInode, prelude::*, utils::crc32c};
 
 /// The magic number of Ext2.
 pub const MAGIC_NUM: u16 = 0xef53;
@@ -12,6 +12,15 @@ pub const SUPER_BLOCK_OFFSET: usize = 1024;
 
 const SUPER_BLOCK_SIZE: usize = 1024;
 
+/// The size of the block group descriptor.
+const GROUP_DESC_SIZE: usize = 32;
+
+/// The size of the block group descriptor if the FeatureInCompatSet::BIT64 is set.
+const GROUP_DESC_SIZE_64BIT: usize = 64;
+
+/// The checksum type for the FeatureRoCompatSet::METADATA_CSUM.
+const CHECKSUM_TYPE_CRC32C: u8 = 1;
+
 /// The in-memory rust superblock.
 ///
 /// It contains all information about the layout of the Ext2.
@@ -93,6 +102,21 @@ pub struct SuperBlock {
     prealloc_file_blocks: u8,
     /// Number of blocks to preallocate for directories.
     prealloc_dir_blocks: u8,
+    /// Number of blocks reserved for the growth of the group descriptor table.
+    reserved_gdt_blocks: u32,
+    /// Size of the group descriptor.
+    desc_size: usize,
+    /// Block groups containing the superblock backups.
+    ///
+    /// This field is valid if the FeatureCompatSet::SPARSE_SUPER2 is set.
+    backup_bgs: [u32; 2],
+    /// Seed of the metadata checksums.
+    checksum_seed: u32,
+    /// The raw superblock loaded from the device.
+    ///
+    /// It keeps the fields that are not interpreted by this driver (e.g., the fields of
+    /// the journal), so that they are preserved when the superblock is written back.
+    raw: RawSuperBlock,
 }
 
 impl TryFrom<RawSuperBlock> for SuperBlock {
@@ -160,20 +184,72 @@ impl TryFrom<RawSuperBlock> for SuperBlock {
                 inode_size
             },
             block_group_idx: sb.block_group_idx as _,
-            feature_compat: FeatureCompatSet::from_bits(sb.feature_compat).ok_or(
-                Error::with_message(Errno::EINVAL, "invalid feature compat set"),
-            )?,
-            feature_incompat: FeatureInCompatSet::from_bits(sb.feature_incompat).ok_or(
-                Error::with_message(Errno::EINVAL, "invalid feature incompat set"),
-            )?,
-            feature_ro_compat: FeatureRoCompatSet::from_bits(sb.feature_ro_compat).ok_or(
-                Error::with_message(Errno::EINVAL, "invalid feature ro compat set"),
-            )?,
+            // The compatible features can be safely ignored if unknown.
+            feature_compat: FeatureCompatSet::from_bits_truncate(sb.feature_compat),
+            feature_incompat: {
+                let features = FeatureInCompatSet::from_bits(sb.feature_incompat).ok_or(
+                    Error::with_message(Errno::EINVAL, "invalid feature incompat set"),
+                )?;
+                // The journal is replayed before the filesystem is used.
+                if features.contains(FeatureInCompatSet::RECOVER)
+                    && sb.feature_compat & FeatureCompatSet::HAS_JOURNAL.bits() == 0
+                {
+                    return_errno_with_message!(Errno::EINVAL, "the journal is missing");
+                }
+                if !FeatureInCompatSet::SUPPORTED.contains(features - FeatureInCompatSet::RECOVER) {
+                    return_errno_with_message!(Errno::EINVAL, "not supported incompat features");
+                }
+                features
+            },
+            feature_ro_compat: {
+                let features = FeatureRoCompatSet::from_bits(sb.feature_ro_compat).ok_or(
+                    Error::with_message(Errno::EINVAL, "invalid feature ro compat set"),
+                )?;
+                // TODO: Mount the filesystem as read-only instead of refusing it.
+                if !FeatureRoCompatSet::SUPPORTED.contains(features) {
+                    return_errno_with_message!(Errno::EINVAL, "not supported ro compat features");
+                }
+                if features.contains(FeatureRoCompatSet::METADATA_CSUM) {
+                    if sb.checksum_type != CHECKSUM_TYPE_CRC32C {
+                        return_errno_with_message!(Errno::EINVAL, "not supported checksum type");
+                    }
+                    if sb.checksum != sb.calc_checksum() {
+                        return_errno_with_message!(Errno::EBADMSG, "bad superblock checksum");
+                    }
+                }
+                features
+            },
             uuid: sb.uuid,
             volume_name: sb.volume_name,
             last_mounted_dir: sb.last_mounted_dir,
             prealloc_file_blocks: sb.prealloc_file_blocks,
             prealloc_dir_blocks: sb.prealloc_dir_blocks,
+            reserved_gdt_blocks: sb.reserved_gdt_blocks as _,
+            desc_size: {
+                if sb.feature_incompat & FeatureInCompatSet::BIT64.bits() == 0 {
+                    GROUP_DESC_SIZE
+                } else {
+                    // Block numbers are kept in 32 bits. The 64-bit layout is supported as long
+                    // as the high halves are not used.
+                    if sb.blocks_count_hi != 0
+                        || sb.reserved_blocks_count_hi != 0
+                        || sb.free_blocks_count_hi != 0
+                    {
+                        return_errno_with_message!(Errno::EFBIG, "too many blocks");
+                    }
+                    if sb.desc_size as usize != GROUP_DESC_SIZE_64BIT {
+                        return_errno_with_message!(Errno::EINVAL, "not supported descriptor size");
+                    }
+                    GROUP_DESC_SIZE_64BIT
+                }
+            },
+            backup_bgs: sb.backup_bgs,
+            checksum_seed: if sb.feature_incompat & FeatureInCompatSet::CSUM_SEED.bits() != 0 {
+                sb.checksum_seed
+            } else {
+                crc32c(!0, &sb.uuid)
+            },
+            raw: sb,
         })
     }
 }
@@ -216,7 +292,22 @@ impl SuperBlock {
 
     /// Returns the number of block groups.
     pub fn block_groups_count(&self) -> u32 {
-        self.blocks_count / self.blocks_per_group
+        (self.blocks_count - self.first_data_block.to_raw() as u32).div_ceil(self.blocks_per_group)
+    }
+
+    /// Returns the size of the block group descriptor.
+    pub fn desc_size(&self) -> usize {
+        self.desc_size
+    }
+
+    /// Returns the number of blocks reserved for the growth of the group descriptor table.
+    pub fn reserved_gdt_blocks(&self) -> u32 {
+        self.reserved_gdt_blocks
+    }
+
+    /// Returns the 128-bit uuid of the volume.
+    pub fn uuid(&self) -> &[u8; 16] {
+        &self.uuid
     }
 
     /// Returns the filesystem state.
@@ -244,6 +335,35 @@ impl SuperBlock {
         self.feature_ro_compat
     }
 
+    /// Returns whether the metadata is protected by checksums.
+    pub fn has_metadata_csum(&self) -> bool {
+        self.feature_ro_compat
+            .contains(FeatureRoCompatSet::METADATA_CSUM)
+    }
+
+    /// Returns whether the block group descriptors are protected by checksums.
+    pub fn has_group_desc_csum(&self) -> bool {
+        self.feature_ro_compat
+            .intersects(FeatureRoCompatSet::METADATA_CSUM | FeatureRoCompatSet::GDT_CSUM)
+    }
+
+    /// Returns the seed of the metadata checksums.
+    pub fn checksum_seed(&self) -> u32 {
+        self.checksum_seed
+    }
+
+    /// Returns the inode number of the journal, or `None` if the filesystem has no journal.
+    pub fn journal_ino(&self) -> Option<u32> {
+        self.feature_compat
+            .contains(FeatureCompatSet::HAS_JOURNAL)
+            .then_some(self.raw.journal_ino)
+    }
+
+    /// Returns whether the journal needs to be replayed.
+    pub fn needs_recovery(&self) -> bool {
+        self.feature_incompat.contains(FeatureInCompatSet::RECOVER)
+    }
+
     /// Returns the number of free blocks.
     pub fn free_blocks_count(&self) -> u32 {
         self.free_blocks_count
@@ -279,6 +399,12 @@ impl SuperBlock {
     pub(super) fn is_backup_group(&self, block_group_idx: usize) -> bool {
         if block_group_idx == 0 {
             false
+        } else if self
+            .feature_compat
+            .contains(FeatureCompatSet::SPARSE_SUPER2)
+        {
+            // The backup groups are recorded in the superblock.
+            self.backup_bgs.contains(&(block_group_idx as u32))
         } else if self
             .feature_ro_compat
             .contains(FeatureRoCompatSet::SPARSE_SUPER)
@@ -339,6 +465,16 @@ bitflags! {
         const RESIZE_INO = 1 << 4;
         /// Directories use hash index
         const DIR_INDEX = 1 << 5;
+        /// Lazy block group initialization (unused)
+        const LAZY_BG = 1 << 6;
+        /// Exclude bitmap (unused)
+        const EXCLUDE_BITMAP = 1 << 8;
+        /// Only two backups of the superblock and the group descriptor table
+        const SPARSE_SUPER2 = 1 << 9;
+        /// Fast commits are supported
+        const FAST_COMMIT = 1 << 10;
+        /// Inodes of orphan files are stored in a file
+        const ORPHAN_FILE = 1 << 12;
     }
 }
 
@@ -355,6 +491,35 @@ bitflags! {
         const JOURNAL_DEV = 1 << 3;
         /// Metablock block group
         const META_BG = 1 << 4;
+        /// Files use extent trees
+        const EXTENTS = 1 << 6;
+        /// Block numbers are 64-bit
+        const BIT64 = 1 << 7;
+        /// Multiple mount protection
+        const MMP = 1 << 8;
+        /// Flexible block groups
+        const FLEX_BG = 1 << 9;
+        /// Inodes can be used to store large extended attribute values
+        const EA_INODE = 1 << 10;
+        /// Data in directory entry
+        const DIRDATA = 1 << 12;
+        /// Metadata checksum seed is stored in the superblock
+        const CSUM_SEED = 1 << 13;
+        /// Large directory (>2GB or 3-level htree)
+        const LARGEDIR = 1 << 14;
+        /// Data in inode
+        const INLINE_DATA = 1 << 15;
+        /// Encrypted inodes are present
+        const ENCRYPT = 1 << 16;
+        /// Directories can be case-insensitive
+        const CASEFOLD = 1 << 17;
+
+        /// The incompatible features supported by this driver.
+        const SUPPORTED = Self::FILETYPE.bits
+            | Self::EXTENTS.bits
+            | Self::BIT64.bits
+            | Self::FLEX_BG.bits
+            | Self::CSUM_SEED.bits;
     }
 }
 
@@ -367,6 +532,38 @@ bitflags! {
         const LARGE_FILE = 1 << 1;
         /// Directory contents are stored in the form of a Binary Tree
         const BTREE_DIR = 1 << 2;
+        /// File sizes can be represented in units of logical blocks
+        const HUGE_FILE = 1 << 3;
+        /// Group descriptors have checksums
+        const GDT_CSUM = 1 << 4;
+        /// The 32000 subdirectory limit does not apply
+        const DIR_NLINK = 1 << 5;
+        /// Inodes have room for extra fields
+        const EXTRA_ISIZE = 1 << 6;
+        /// Quota is handled transactionally with the journal
+        const QUOTA = 1 << 8;
+        /// Bigalloc (cluster-based allocation)
+        const BIGALLOC = 1 << 9;
+        /// All metadata have checksums
+        const METADATA_CSUM = 1 << 10;
+        /// Read-only file system image
+        const READONLY = 1 << 12;
+        /// Project quotas are supported
+        const PROJECT = 1 << 13;
+        /// Verity inodes may be present
+        const VERITY = 1 << 15;
+        /// Orphan file may be non-empty
+        const ORPHAN_PRESENT = 1 << 16;
+
+        /// The readonly-compatible features supported by this driver.
+        const SUPPORTED = Self::SPARSE_SUPER.bits
+            | Self::LARGE_FILE.bits
+            | Self::BTREE_DIR.bits
+            | Self::HUGE_FILE.bits
+            | Self::GDT_CSUM.bits
+            | Self::DIR_NLINK.bits
+            | Self::EXTRA_ISIZE.bits
+            | Self::METADATA_CSUM.bits;
     }
 }
 
@@ -421,7 +618,7 @@ const_assert!(core::mem::size_of::<RawSuperBlock>() == SUPER_BLOCK_SIZE);
 
 /// The raw superblock, it must be exactly 1024 bytes in length.
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Pod, Default)]
+#[derive(Clone, Copy, Debug, Pod)]
 pub(super) struct RawSuperBlock {
     pub inodes_count: u32,
     pub blocks_count: u32,
@@ -465,7 +662,8 @@ pub(super) struct RawSuperBlock {
     pub algorithm_usage_bitmap: u32,
     pub prealloc_file_blocks: u8,
     pub prealloc_dir_blocks: u8,
-    padding1: u16,
+    /// Number of reserved GDT entries for future filesystem expansion.
+    pub reserved_gdt_blocks: u16,
     ///
     /// This fields are for journaling support in Ext3.
     ///
@@ -481,18 +679,80 @@ pub(super) struct RawSuperBlock {
     pub hash_seed: [u32; 4],
     /// Default hash version to use
     pub def_hash_version: u8,
-    reserved_char_pad: u8,
-    reserved_word_pad: u16,
+    /// Whether `jnl_blocks` contains a backup of the journal inode's blocks.
+    pub jnl_backup_type: u8,
+    /// Size of the group descriptor if the FeatureInCompatSet::BIT64 is set.
+    pub desc_size: u16,
     /// Default mount options.
     pub default_mount_opts: u32,
     /// First metablock block group.
     pub first_meta_bg: u32,
-    reserved: Reserved,
+    ///
+    /// This fields are for Ext4.
+    ///
+    /// When the filesystem was created.
+    pub mkfs_time: UnixTime,
+    /// Backup of the journal inode's `block` array and size.
+    pub jnl_blocks: [u32; 17],
+    /// High 32 bits of the block count.
+    pub blocks_count_hi: u32,
+    /// High 32 bits of the reserved block count.
+    pub reserved_blocks_count_hi: u32,
+    /// High 32 bits of the free block count.
+    pub free_blocks_count_hi: u32,
+    /// All inodes have at least this many bytes of extra space.
+    pub min_extra_isize: u16,
+    /// New inodes should reserve this many bytes of extra space.
+    pub want_extra_isize: u16,
+    /// Miscellaneous flags.
+    pub flags: u32,
+    /// RAID stride.
+    pub raid_stride: u16,
+    /// Seconds to wait in multi-mount prevention checking.
+    pub mmp_interval: u16,
+    /// Block for multi-mount protection data.
+    pub mmp_block: u64,
+    /// RAID stripe width.
+    pub raid_stripe_width: u32,
+    /// The number to left-shift 1 to obtain the size of a flexible block group.
+    pub log_groups_per_flex: u8,
+    /// Metadata checksum algorithm type.
+    pub checksum_type: u8,
+    reserved_pad: u16,
+    /// Number of KiB written to this filesystem over its lifetime.
+    pub kbytes_written: u64,
+    /// Fields of snapshots, error tracking, quotas and orphan files.
+    reserved1: [u32; 51],
+    /// Block groups containing the superblock backups if the
+    /// FeatureCompatSet::SPARSE_SUPER2 is set.
+    pub backup_bgs: [u32; 2],
+    /// Fields of encryption, lost+found and project quotas.
+    reserved2: [u32; 7],
+    /// Checksum seed if the FeatureInCompatSet::CSUM_SEED is set.
+    pub checksum_seed: u32,
+    reserved3: [u32; 98],
+    /// Checksum of the superblock.
+    pub checksum: u32,
+}
+
+impl RawSuperBlock {
+    /// Calculates the checksum of the superblock.
+    pub fn calc_checksum(&self) -> u32 {
+        let offset = core::mem::offset_of!(Self, checksum);
+        crc32c(!0, &self.as_bytes()[..offset])
+    }
+
+    /// Updates the checksum of the superblock if the FeatureRoCompatSet::METADATA_CSUM is set.
+    pub fn update_checksum(&mut self) {
+        if self.feature_ro_compat & FeatureRoCompatSet::METADATA_CSUM.bits() != 0 {
+            self.checksum = self.calc_checksum();
+        }
+    }
 }
 
 impl From<&SuperBlock> for RawSuperBlock {
     fn from(sb: &SuperBlock) -> Self {
-        Self {
+        let mut raw = Self {
             inodes_count: sb.inodes_count,
             blocks_count: sb.blocks_count,
             reserved_blocks_count: sb.reserved_blocks_count,
@@ -520,7 +780,9 @@ impl From<&SuperBlock> for RawSuperBlock {
             first_ino: sb.first_ino,
             inode_size: sb.inode_size as u16,
             block_group_idx: sb.block_group_idx as u16,
-            feature_compat: sb.feature_compat.bits(),
+            // The unknown compatible features are kept as they are.
+            feature_compat: sb.feature_compat.bits()
+                | (sb.raw.feature_compat & !FeatureCompatSet::all().bits()),
             feature_incompat: sb.feature_incompat.bits(),
             feature_ro_compat: sb.feature_ro_compat.bits(),
             uuid: sb.uuid,
@@ -528,17 +790,10 @@ impl From<&SuperBlock> for RawSuperBlock {
             last_mounted_dir: sb.last_mounted_dir,
             prealloc_file_blocks: sb.prealloc_file_blocks,
             prealloc_dir_blocks: sb.prealloc_dir_blocks,
-            ..Default::default()
-        }
-    }
-}
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, Pod)]
-struct Reserved([u32; 190]);
-
-impl Default for Reserved {
-    fn default() -> Self {
-        Self([0u32; 190])
+            reserved_gdt_blocks: sb.reserved_gdt_blocks as u16,
+            ..sb.raw
+        };
+        raw.update_checksum();
+        raw
     }
 }
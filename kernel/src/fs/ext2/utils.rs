@@ -31,6 +31,65 @@ macro_rules! impl_ipo_for {
 
 impl_ipo_for!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, isize, usize);
 
+/// Computes the CRC32C (Castagnoli) checksum of `data`, starting from `crc`.
+///
+/// Like `ext4_chksum` in Linux, neither the initial value nor the result is inverted,
+/// so the checksum of one buffer can be used as the seed of the next one.
+pub fn crc32c(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC32C_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the CRC16 (ANSI) checksum of `data`, starting from `crc`.
+///
+/// It is used by the older `GDT_CSUM` feature to checksum block group descriptors.
+pub fn crc16(crc: u16, data: &[u8]) -> u16 {
+    data.iter().fold(crc, |crc, &byte| {
+        CRC16_TABLE[((crc ^ byte as u16) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
 /// The `Dirty` wraps a value of type `T` with functions similar to that of a rw-lock,
 /// but simply sets a dirty flag on `write()`.
 pub struct Dirty<T: Debug> {
@@ -2,7 +2,7 @@
 
 use ostd::mm::io_util::HasVmReaderWriter;
 
-use super::{block_ptr::Ext2Bid, prelude::*, Ext2, Inode};
+use super::{block_ptr::Ext2Bid, prelude::*, utils::crc32c, Ext2, Inode};
 use crate::fs::utils::{XattrName, XattrNamespace, XattrSetFlags, XATTR_NAME_MAX_LEN};
 
 const EXT2_XATTR_MAGIC: u32 = 0xEA020000;
@@ -16,7 +16,9 @@ struct XattrHeader {
     ref_count: u32,
     nblocks: u32,
     hash: u32,
-    reserved: [u32; 4],
+    /// Checksum of the block, valid if the metadata checksums are enabled.
+    checksum: u32,
+    reserved: [u32; 3],
 }
 
 const XATTR_HEADER_SIZE: usize = size_of::<XattrHeader>();
@@ -132,8 +134,8 @@ impl Xattr {
             self.inode().set_acl(new_bid);
         // Need to load the xattr block from device
         } else if cache.header.is_none() {
-            fs.block_device().read_blocks(
-                cache.bid,
+            fs.read_blocks(
+                cache.bid.to_raw() as Ext2Bid,
                 BioSegment::new_from_segment(self.blocks_buf.clone(), BioDirection::FromDevice),
             )?;
 
@@ -328,15 +330,33 @@ impl Xattr {
     pub fn flush(&self) -> Result<()> {
         let cache = self.cache.upread();
         if cache.is_dirty() {
-            self.fs().block_device().write_blocks(
-                cache.bid,
-                BioSegment::new_from_segment(self.blocks_buf.clone(), BioDirection::ToDevice),
-            )?;
+            if let Some(seed) = self.fs().csum_seed() {
+                let checksum = self.calc_checksum(cache.bid, seed)?;
+                self.blocks_buf
+                    .write_val(core::mem::offset_of!(XattrHeader, checksum), &checksum)?;
+            }
+            let mut blocks = vec![0u8; self.blocks_buf.size()];
+            self.blocks_buf.read_bytes(0, &mut blocks)?;
+            self.fs()
+                .write_metadata_async(cache.bid.to_raw() as Ext2Bid, &blocks)?
+                .wait()
+                .ok_or_else(|| Error::with_message(Errno::EIO, "failed to flush the xattrs"))?;
             cache.upgrade().clear_dirty();
         }
         Ok(())
     }
 
+    /// Calculates the checksum of the xattr block, where the checksum field is zeroed.
+    fn calc_checksum(&self, bid: Bid, seed: u32) -> Result<u32> {
+        let mut block = vec![0u8; BLOCK_SIZE];
+        self.blocks_buf.read_bytes(0, &mut block)?;
+        let offset = core::mem::offset_of!(XattrHeader, checksum);
+        block[offset..offset + core::mem::size_of::<u32>()].fill(0);
+
+        let crc = crc32c(seed, &bid.to_raw().to_le_bytes());
+        Ok(crc32c(crc, &block))
+    }
+
     pub fn free(&self) -> Result<()> {
         let cache = self.cache.upread();
         let bid = cache.bid.to_raw() as Ext2Bid;
@@ -482,6 +502,7 @@ impl Default for XattrHeader {
             nblocks: XATTR_NBLOCKS as _,
             ref_count: Default::default(),
             hash: Default::default(),
+            checksum: Default::default(),
             reserved: Default::default(),
         }
     }
@@ -12,6 +12,7 @@ use crate::{
     net::socket::Socket,
     prelude::*,
     process::{signal::Pollable, Gid, Uid},
+    vm::vmo::Vmo,
 };
 
 /// The basic operations defined on a file
@@ -100,6 +101,17 @@ pub trait FileLike: Pollable + Send + Sync + Any {
         return_errno_with_message!(Errno::EOPNOTSUPP, "fallocate is not supported");
     }
 
+    /// Returns the VMO to be mapped at the given offset of the file.
+    ///
+    /// This is used by `mmap` for files that are not mapped via their inodes (e.g., files that
+    /// share their kernel data structures with user space). `None` means that the file should be
+    /// mapped via its inode.
+    ///
+    /// The VMO is always mapped as shared, even if `MAP_PRIVATE` is specified.
+    fn mmap_vmo(&self, offset: usize) -> Result<Option<Vmo>> {
+        Ok(None)
+    }
+
     fn as_socket(&self) -> Option<&dyn Socket> {
         None
     }
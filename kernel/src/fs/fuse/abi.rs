@@ -0,0 +1,410 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The wire format of the FUSE protocol.
+//!
+//! The structures follow `include/uapi/linux/fuse.h` of Linux at protocol version 7.31.
+
+#![expect(dead_code)]
+
+use align_ext::AlignExt;
+use ostd::Pod;
+
+use crate::prelude::*;
+
+/// The major version of the protocol.
+pub(super) const FUSE_KERNEL_VERSION: u32 = 7;
+/// The minor version of the protocol.
+pub(super) const FUSE_KERNEL_MINOR_VERSION: u32 = 31;
+
+/// The node ID of the root directory.
+pub(super) const FUSE_ROOT_ID: u64 = 1;
+
+/// The minimum size of the buffer that the daemon reads requests with.
+pub(super) const FUSE_MIN_READ_BUFFER: usize = 8192;
+
+/// The size of `fuse_init_out` before protocol version 7.23.
+pub(super) const FUSE_COMPAT_22_INIT_OUT_SIZE: usize = 24;
+
+/// The default maximum number of pages in a single read or write request.
+pub(super) const FUSE_DEFAULT_MAX_PAGES_PER_REQ: usize = 32;
+/// The upper limit of `max_pages` that the daemon can negotiate.
+pub(super) const FUSE_MAX_MAX_PAGES: usize = 256;
+
+/// The magic number reported by `statfs`.
+pub(super) const FUSE_SUPER_MAGIC: u64 = 0x65735546;
+
+/// The flag of `fuse_getattr_in` indicating that `fh` is valid.
+pub(super) const FUSE_GETATTR_FH: u32 = 1 << 0;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum FuseOpcode {
+    Lookup = 1,
+    Forget = 2,
+    Getattr = 3,
+    Setattr = 4,
+    Readlink = 5,
+    Mknod = 8,
+    Mkdir = 9,
+    Unlink = 10,
+    Rmdir = 11,
+    Rename = 12,
+    Open = 14,
+    Read = 15,
+    Write = 16,
+    Statfs = 17,
+    Release = 18,
+    Fsync = 20,
+    Flush = 25,
+    Init = 26,
+    Opendir = 27,
+    Readdir = 28,
+    Releasedir = 29,
+    Fsyncdir = 30,
+    Create = 35,
+    Destroy = 38,
+}
+
+bitflags! {
+    /// The flags negotiated by `FUSE_INIT`.
+    pub(super) struct FuseInitFlags: u32 {
+        const ASYNC_READ = 1 << 0;
+        const POSIX_LOCKS = 1 << 1;
+        const FILE_OPS = 1 << 2;
+        const ATOMIC_O_TRUNC = 1 << 3;
+        const EXPORT_SUPPORT = 1 << 4;
+        const BIG_WRITES = 1 << 5;
+        const DONT_MASK = 1 << 6;
+        const SPLICE_WRITE = 1 << 7;
+        const SPLICE_MOVE = 1 << 8;
+        const SPLICE_READ = 1 << 9;
+        const FLOCK_LOCKS = 1 << 10;
+        const HAS_IOCTL_DIR = 1 << 11;
+        const AUTO_INVAL_DATA = 1 << 12;
+        const DO_READDIRPLUS = 1 << 13;
+        const READDIRPLUS_AUTO = 1 << 14;
+        const ASYNC_DIO = 1 << 15;
+        const WRITEBACK_CACHE = 1 << 16;
+        const NO_OPEN_SUPPORT = 1 << 17;
+        const PARALLEL_DIROPS = 1 << 18;
+        const HANDLE_KILLPRIV = 1 << 19;
+        const POSIX_ACL = 1 << 20;
+        const ABORT_ERROR = 1 << 21;
+        const MAX_PAGES = 1 << 22;
+        const CACHE_SYMLINKS = 1 << 23;
+        const NO_OPENDIR_SUPPORT = 1 << 24;
+        const EXPLICIT_INVAL_DATA = 1 << 25;
+    }
+}
+
+bitflags! {
+    /// The flags returned by `FUSE_OPEN` and `FUSE_CREATE`.
+    pub(super) struct FuseOpenFlags: u32 {
+        /// Bypasses the page cache for this open file.
+        const DIRECT_IO = 1 << 0;
+        /// Does not invalidate the data cache on open.
+        const KEEP_CACHE = 1 << 1;
+        /// The file is not seekable.
+        const NONSEEKABLE = 1 << 2;
+        /// Allows caching this directory.
+        const CACHE_DIR = 1 << 3;
+        /// The file is stream-like (no file position at all).
+        const STREAM = 1 << 4;
+    }
+}
+
+bitflags! {
+    /// The attributes to be set by `FUSE_SETATTR`.
+    pub(super) struct FuseSetattrValid: u32 {
+        const MODE = 1 << 0;
+        const UID = 1 << 1;
+        const GID = 1 << 2;
+        const SIZE = 1 << 3;
+        const ATIME = 1 << 4;
+        const MTIME = 1 << 5;
+        const FH = 1 << 6;
+        const ATIME_NOW = 1 << 7;
+        const MTIME_NOW = 1 << 8;
+        const LOCKOWNER = 1 << 9;
+        const CTIME = 1 << 10;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseAttr {
+    pub ino: u64,
+    pub size: u64,
+    pub blocks: u64,
+    pub atime: u64,
+    pub mtime: u64,
+    pub ctime: u64,
+    pub atimensec: u32,
+    pub mtimensec: u32,
+    pub ctimensec: u32,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u32,
+    pub blksize: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseInHeader {
+    pub len: u32,
+    pub opcode: u32,
+    pub unique: u64,
+    pub nodeid: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: u32,
+    pub padding: u32,
+}
+
+/// The code of a notification from the daemon, which is carried in the `error` field of
+/// [`FuseOutHeader`] with a zero `unique` field.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromInt)]
+pub(super) enum FuseNotifyCode {
+    Poll = 1,
+    InvalInode = 2,
+    InvalEntry = 3,
+    Store = 4,
+    Retrieve = 5,
+    Delete = 6,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseOutHeader {
+    pub len: u32,
+    pub error: i32,
+    pub unique: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseInitIn {
+    pub major: u32,
+    pub minor: u32,
+    pub max_readahead: u32,
+    pub flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseInitOut {
+    pub major: u32,
+    pub minor: u32,
+    pub max_readahead: u32,
+    pub flags: u32,
+    pub max_background: u16,
+    pub congestion_threshold: u16,
+    pub max_write: u32,
+    pub time_gran: u32,
+    pub max_pages: u16,
+    pub map_alignment: u16,
+    pub unused: [u32; 8],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseEntryOut {
+    pub nodeid: u64,
+    pub generation: u64,
+    pub entry_valid: u64,
+    pub attr_valid: u64,
+    pub entry_valid_nsec: u32,
+    pub attr_valid_nsec: u32,
+    pub attr: FuseAttr,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseForgetIn {
+    pub nlookup: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseGetattrIn {
+    pub getattr_flags: u32,
+    pub dummy: u32,
+    pub fh: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseAttrOut {
+    pub attr_valid: u64,
+    pub attr_valid_nsec: u32,
+    pub dummy: u32,
+    pub attr: FuseAttr,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseSetattrIn {
+    pub valid: u32,
+    pub padding: u32,
+    pub fh: u64,
+    pub size: u64,
+    pub lock_owner: u64,
+    pub atime: u64,
+    pub mtime: u64,
+    pub ctime: u64,
+    pub atimensec: u32,
+    pub mtimensec: u32,
+    pub ctimensec: u32,
+    pub mode: u32,
+    pub unused4: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub unused5: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseMknodIn {
+    pub mode: u32,
+    pub rdev: u32,
+    pub umask: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseMkdirIn {
+    pub mode: u32,
+    pub umask: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseRenameIn {
+    pub newdir: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseOpenIn {
+    pub flags: u32,
+    pub unused: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseCreateIn {
+    pub flags: u32,
+    pub mode: u32,
+    pub umask: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseOpenOut {
+    pub fh: u64,
+    pub open_flags: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseReleaseIn {
+    pub fh: u64,
+    pub flags: u32,
+    pub release_flags: u32,
+    pub lock_owner: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseFlushIn {
+    pub fh: u64,
+    pub unused: u32,
+    pub padding: u32,
+    pub lock_owner: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseReadIn {
+    pub fh: u64,
+    pub offset: u64,
+    pub size: u32,
+    pub read_flags: u32,
+    pub lock_owner: u64,
+    pub flags: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseWriteIn {
+    pub fh: u64,
+    pub offset: u64,
+    pub size: u32,
+    pub write_flags: u32,
+    pub lock_owner: u64,
+    pub flags: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseWriteOut {
+    pub size: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseFsyncIn {
+    pub fh: u64,
+    pub fsync_flags: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseKstatfs {
+    pub blocks: u64,
+    pub bfree: u64,
+    pub bavail: u64,
+    pub files: u64,
+    pub ffree: u64,
+    pub bsize: u32,
+    pub namelen: u32,
+    pub frsize: u32,
+    pub padding: u32,
+    pub spare: [u32; 6],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseStatfsOut {
+    pub st: FuseKstatfs,
+}
+
+/// The fixed-size part of a directory entry returned by `FUSE_READDIR`.
+///
+/// The entry is followed by `namelen` bytes of name, and padded to 8 bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct FuseDirent {
+    pub ino: u64,
+    pub off: u64,
+    pub namelen: u32,
+    pub type_: u32,
+}
+
+impl FuseDirent {
+    /// Returns the size of the entry including the name and the padding.
+    pub(super) fn record_len(&self) -> usize {
+        (size_of::<Self>() + self.namelen as usize).align_up(8)
+    }
+}
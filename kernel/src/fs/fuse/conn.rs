@@ -0,0 +1,579 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use ostd::{sync::WaitQueue, task::Task};
+
+use super::abi::{
+    FuseInHeader, FuseInitFlags, FuseInitIn, FuseInitOut, FuseNotifyCode, FuseOpcode,
+    FuseOutHeader, FUSE_COMPAT_22_INIT_OUT_SIZE, FUSE_DEFAULT_MAX_PAGES_PER_REQ,
+    FUSE_KERNEL_MINOR_VERSION, FUSE_KERNEL_VERSION, FUSE_MAX_MAX_PAGES, FUSE_MIN_READ_BUFFER,
+};
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::{posix_thread::AsPosixThread, signal::Pollee},
+};
+
+/// A connection between the kernel and a FUSE daemon.
+///
+/// A connection is created when `/dev/fuse` is opened. The requests of the file system
+/// are queued in the connection, and the daemon reads the requests from the device and
+/// writes the replies back. The connection is aborted when the device file is closed or
+/// the file system is dropped, after which all the requests fail.
+///
+/// A connection may instead deliver the requests through a [`FuseTransport`] (e.g., a
+/// virtio-fs device), in which case the requests are never queued.
+pub(super) struct FuseConn {
+    state: SpinLock<ConnState>,
+    transport: Option<Arc<dyn FuseTransport>>,
+    /// The wait queue of the requests that are waiting for replies or the initialization.
+    wait_queue: WaitQueue,
+    pollee: Pollee,
+    next_unique: AtomicU64,
+    is_mounted: AtomicBool,
+}
+
+impl Debug for FuseConn {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FuseConn").finish_non_exhaustive()
+    }
+}
+
+struct ConnState {
+    /// The requests that have not been read by the daemon.
+    pending: VecDeque<Arc<FuseRequest>>,
+    /// The requests that have been read by the daemon but have not been replied to.
+    processing: BTreeMap<u64, Arc<FuseRequest>>,
+    /// The parameters negotiated by `FUSE_INIT`, or `None` if it has not completed.
+    init: Option<FuseInitInfo>,
+    is_aborted: bool,
+}
+
+/// The parameters of a connection negotiated by `FUSE_INIT`.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FuseInitInfo {
+    pub minor: u32,
+    pub flags: FuseInitFlags,
+    /// The maximum size of the data in a `FUSE_WRITE` request.
+    pub max_write: usize,
+    /// The maximum size of the data in a `FUSE_READ` reply.
+    pub max_read: usize,
+}
+
+struct FuseRequest {
+    unique: u64,
+    opcode: FuseOpcode,
+    /// The request, including the header, as it is read by the daemon.
+    data: Vec<u8>,
+    /// The payload of the reply, or the error replied by the daemon.
+    reply: SpinLock<Option<Result<Vec<u8>>>>,
+}
+
+/// A transport that delivers the requests to a FUSE server directly.
+pub(super) trait FuseTransport: Send + Sync {
+    /// Sends a request and waits for the reply.
+    ///
+    /// The reply, including the header, is written to `reply`, whose length is the maximum
+    /// length of the reply. On success, the length of the reply is returned.
+    fn send_request(&self, request: &[u8], reply: &mut [u8]) -> Result<usize>;
+
+    /// Sends a request that has no reply.
+    fn send_oneway(&self, request: &[u8]) -> Result<()>;
+}
+
+impl FuseConn {
+    pub(super) fn new() -> Arc<Self> {
+        Self::new_inner(None)
+    }
+
+    /// Creates a connection that delivers the requests through the transport.
+    pub(super) fn with_transport(transport: Arc<dyn FuseTransport>) -> Arc<Self> {
+        Self::new_inner(Some(transport))
+    }
+
+    fn new_inner(transport: Option<Arc<dyn FuseTransport>>) -> Arc<Self> {
+        Arc::new(Self {
+            state: SpinLock::new(ConnState {
+                pending: VecDeque::new(),
+                processing: BTreeMap::new(),
+                init: None,
+                is_aborted: false,
+            }),
+            transport,
+            wait_queue: WaitQueue::new(),
+            pollee: Pollee::new(),
+            // Like Linux, the unique ID 0 is reserved for notifications.
+            next_unique: AtomicU64::new(1),
+            is_mounted: AtomicBool::new(false),
+        })
+    }
+
+    /// Marks the connection as mounted and starts the initialization.
+    ///
+    /// The `FUSE_INIT` request is queued without waiting for the reply, since the daemon
+    /// usually starts reading requests only after the mount completes. The other requests
+    /// wait until the initialization completes.
+    ///
+    /// If the connection has a transport, the initialization completes before this method
+    /// returns.
+    pub(super) fn mount(&self) -> Result<()> {
+        if self.is_mounted.swap(true, Ordering::AcqRel) {
+            return_errno_with_message!(Errno::EINVAL, "the FUSE connection is already mounted");
+        }
+
+        let init_in = FuseInitIn {
+            major: FUSE_KERNEL_VERSION,
+            minor: FUSE_KERNEL_MINOR_VERSION,
+            max_readahead: (FUSE_DEFAULT_MAX_PAGES_PER_REQ * PAGE_SIZE) as u32,
+            flags: (FuseInitFlags::BIG_WRITES
+                | FuseInitFlags::AUTO_INVAL_DATA
+                | FuseInitFlags::MAX_PAGES)
+                .bits(),
+        };
+
+        if let Some(transport) = self.transport.as_ref() {
+            let reply = self.transact(
+                transport.as_ref(),
+                FuseOpcode::Init,
+                0,
+                &[init_in.as_bytes()],
+            );
+            self.complete_init(reply);
+            self.wait_initialized()?;
+            return Ok(());
+        }
+
+        self.queue_request(FuseOpcode::Init, 0, &[init_in.as_bytes()], true)?;
+        Ok(())
+    }
+
+    /// Sends a request and waits for the reply.
+    ///
+    /// The arguments are concatenated after the request header. On success, the payload of
+    /// the reply is returned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns the error replied by the daemon, [`Errno::ENOTCONN`] if the
+    /// connection is aborted, or [`Errno::EINTR`] if the waiting is interrupted by a signal.
+    pub(super) fn send(&self, opcode: FuseOpcode, nodeid: u64, args: &[&[u8]]) -> Result<Vec<u8>> {
+        self.wait_initialized()?;
+        if let Some(transport) = self.transport.as_ref() {
+            return self.transact(transport.as_ref(), opcode, nodeid, args);
+        }
+
+        let request = self.queue_request(opcode, nodeid, args, true)?;
+        let res = self.wait_queue.pause_until(|| {
+            if let Some(reply) = request.reply.lock().take() {
+                return Some(reply);
+            }
+            if self.state.lock().is_aborted {
+                return Some(Err(Error::with_message(
+                    Errno::ENOTCONN,
+                    "the FUSE connection is aborted",
+                )));
+            }
+            None
+        });
+
+        match res {
+            Ok(reply) => reply,
+            Err(err) => {
+                // If the daemon has read the request, the reply will be dropped when it arrives.
+                self.withdraw_request(&request);
+                Err(err)
+            }
+        }
+    }
+
+    /// Sends a request without waiting for the reply.
+    ///
+    /// This is used for `FUSE_FORGET`, which has no reply, and for the requests that are
+    /// sent when the resources are dropped (e.g., `FUSE_RELEASE`), whose replies are ignored.
+    pub(super) fn send_background(
+        &self,
+        opcode: FuseOpcode,
+        nodeid: u64,
+        args: &[&[u8]],
+    ) -> Result<()> {
+        let expects_reply = opcode != FuseOpcode::Forget;
+
+        if let Some(transport) = self.transport.as_ref() {
+            self.check_aborted()?;
+            if !expects_reply {
+                let (_, data) = self.build_request(opcode, nodeid, args);
+                return transport.send_oneway(&data);
+            }
+            // The transport has no queue, so the reply is waited for and ignored.
+            self.transact(transport.as_ref(), opcode, nodeid, args)?;
+            return Ok(());
+        }
+
+        self.queue_request(opcode, nodeid, args, expects_reply)?;
+        Ok(())
+    }
+
+    /// Tells the daemon that the file system is unmounted, if the connection has a transport.
+    ///
+    /// A transport (e.g., a virtio-fs device) outlives the file system, so the daemon must
+    /// release the state of the file system before it is mounted again.
+    pub(super) fn destroy(&self) {
+        if self.transport.is_none() || self.state.lock().init.is_none() {
+            return;
+        }
+        if let Err(err) = self.send(FuseOpcode::Destroy, 0, &[]) {
+            debug!("FUSE: failed to destroy the file system: {:?}", err);
+        }
+    }
+
+    /// Returns the parameters negotiated by `FUSE_INIT`, waiting for the initialization to
+    /// complete.
+    pub(super) fn init_info(&self) -> Result<FuseInitInfo> {
+        self.wait_initialized()
+    }
+
+    fn wait_initialized(&self) -> Result<FuseInitInfo> {
+        self.wait_queue.pause_until(|| {
+            let state = self.state.lock();
+            if state.is_aborted {
+                return Some(Err(Error::with_message(
+                    Errno::ENOTCONN,
+                    "the FUSE connection is aborted",
+                )));
+            }
+            state.init.map(Ok)
+        })?
+    }
+
+    fn check_aborted(&self) -> Result<()> {
+        if self.state.lock().is_aborted {
+            return_errno_with_message!(Errno::ENOTCONN, "the FUSE connection is aborted");
+        }
+        Ok(())
+    }
+
+    /// Sends a request through the transport and waits for the reply.
+    fn transact(
+        &self,
+        transport: &dyn FuseTransport,
+        opcode: FuseOpcode,
+        nodeid: u64,
+        args: &[&[u8]],
+    ) -> Result<Vec<u8>> {
+        self.check_aborted()?;
+
+        let (unique, data) = self.build_request(opcode, nodeid, args);
+        let max_payload_len = match opcode {
+            FuseOpcode::Read | FuseOpcode::Readdir => self
+                .state
+                .lock()
+                .init
+                .map_or(PAGE_SIZE, |info| info.max_read),
+            _ => PAGE_SIZE,
+        };
+        let mut reply = vec![0u8; size_of::<FuseOutHeader>() + max_payload_len];
+        let len = transport.send_request(&data, &mut reply)?;
+
+        let (header, payload) = decode_reply(&mut VmReader::from(&reply[..len]))?;
+        if header.unique != unique {
+            return_errno_with_message!(Errno::EIO, "the reply is for another request");
+        }
+        reply_to_result(&header, payload)
+    }
+
+    /// Builds a request, returning its unique ID and its bytes.
+    fn build_request(&self, opcode: FuseOpcode, nodeid: u64, args: &[&[u8]]) -> (u64, Vec<u8>) {
+        let (uid, gid, pid) = current_ids();
+        let len = size_of::<FuseInHeader>() + args.iter().map(|arg| arg.len()).sum::<usize>();
+        let unique = self.next_unique.fetch_add(1, Ordering::Relaxed);
+        let header = FuseInHeader {
+            len: len as u32,
+            opcode: opcode as u32,
+            unique,
+            nodeid,
+            uid,
+            gid,
+            pid,
+            padding: 0,
+        };
+
+        let mut data = Vec::with_capacity(len);
+        data.extend_from_slice(header.as_bytes());
+        for arg in args {
+            data.extend_from_slice(arg);
+        }
+        (unique, data)
+    }
+
+    fn queue_request(
+        &self,
+        opcode: FuseOpcode,
+        nodeid: u64,
+        args: &[&[u8]],
+        expects_reply: bool,
+    ) -> Result<Arc<FuseRequest>> {
+        let (unique, data) = self.build_request(opcode, nodeid, args);
+        let request = Arc::new(FuseRequest {
+            // A request without a reply is never looked up by its unique ID.
+            unique: if expects_reply { unique } else { 0 },
+            opcode,
+            data,
+            reply: SpinLock::new(None),
+        });
+
+        let mut state = self.state.lock();
+        if state.is_aborted {
+            return_errno_with_message!(Errno::ENOTCONN, "the FUSE connection is aborted");
+        }
+        state.pending.push_back(request.clone());
+        drop(state);
+
+        self.pollee.notify(IoEvents::IN);
+
+        Ok(request)
+    }
+
+    fn withdraw_request(&self, request: &Arc<FuseRequest>) {
+        let mut state = self.state.lock();
+        if let Some(pos) = state.pending.iter().position(|r| Arc::ptr_eq(r, request)) {
+            state.pending.remove(pos);
+            if state.pending.is_empty() {
+                self.pollee.invalidate();
+            }
+        }
+    }
+
+    /// Reads a request for the daemon.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EAGAIN`] if there are no pending requests, and
+    /// [`Errno::ENODEV`] if the connection is aborted.
+    pub(super) fn try_read_request(&self, writer: &mut VmWriter) -> Result<usize> {
+        if writer.avail() < FUSE_MIN_READ_BUFFER {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for requests");
+        }
+
+        loop {
+            let mut state = self.state.lock();
+            if state.is_aborted {
+                return_errno_with_message!(Errno::ENODEV, "the FUSE connection is aborted");
+            }
+            let Some(request) = state.pending.pop_front() else {
+                return_errno_with_message!(Errno::EAGAIN, "no FUSE requests are pending");
+            };
+            if state.pending.is_empty() {
+                self.pollee.invalidate();
+            }
+
+            // Like Linux, a request that does not fit in the buffer fails with `EIO`.
+            if request.data.len() > writer.avail() {
+                drop(state);
+                self.complete_request(&request, Err(Error::new(Errno::EIO)));
+                continue;
+            }
+            if request.unique != 0 {
+                state.processing.insert(request.unique, request.clone());
+            }
+            drop(state);
+
+            let mut reader = VmReader::from(request.data.as_slice());
+            if let Err(err) = writer.write_fallible(&mut reader) {
+                self.state.lock().processing.remove(&request.unique);
+                self.complete_request(&request, Err(Error::new(Errno::EIO)));
+                return Err(err.into());
+            }
+            return Ok(request.data.len());
+        }
+    }
+
+    /// Writes a reply from the daemon.
+    pub(super) fn write_reply(&self, reader: &mut VmReader) -> Result<usize> {
+        let len = reader.remain();
+        let (header, payload) = decode_reply(reader)?;
+        if header.unique == 0 {
+            handle_notification(header.error)?;
+            return Ok(len);
+        }
+
+        let Some(request) = self.state.lock().processing.remove(&header.unique) else {
+            return_errno_with_message!(Errno::ENOENT, "the request to reply to is not found");
+        };
+        self.complete_request(&request, reply_to_result(&header, payload));
+
+        Ok(len)
+    }
+
+    fn complete_request(&self, request: &FuseRequest, reply: Result<Vec<u8>>) {
+        if request.opcode == FuseOpcode::Init {
+            self.complete_init(reply);
+            return;
+        }
+
+        *request.reply.lock() = Some(reply);
+        self.wait_queue.wake_all();
+    }
+
+    fn complete_init(&self, reply: Result<Vec<u8>>) {
+        let Ok(payload) = reply else {
+            warn!("FUSE: the daemon fails the initialization");
+            self.abort();
+            return;
+        };
+        if payload.len() < FUSE_COMPAT_22_INIT_OUT_SIZE {
+            warn!("FUSE: the initialization reply is too short");
+            self.abort();
+            return;
+        }
+
+        // The fields that are not replied by older daemons are zeros.
+        let mut init_out = FuseInitOut::new_zeroed();
+        let copy_len = payload.len().min(size_of::<FuseInitOut>());
+        init_out.as_bytes_mut()[..copy_len].copy_from_slice(&payload[..copy_len]);
+        if init_out.major != FUSE_KERNEL_VERSION {
+            warn!(
+                "FUSE: the protocol version {} is unsupported",
+                init_out.major
+            );
+            self.abort();
+            return;
+        }
+
+        let flags = FuseInitFlags::from_bits_truncate(init_out.flags);
+        let max_pages = if flags.contains(FuseInitFlags::MAX_PAGES) {
+            (init_out.max_pages as usize).clamp(1, FUSE_MAX_MAX_PAGES)
+        } else {
+            FUSE_DEFAULT_MAX_PAGES_PER_REQ
+        };
+        let max_read = max_pages * PAGE_SIZE;
+        let info = FuseInitInfo {
+            minor: init_out.minor.min(FUSE_KERNEL_MINOR_VERSION),
+            flags,
+            max_write: (init_out.max_write as usize).clamp(PAGE_SIZE, max_read),
+            max_read,
+        };
+
+        let mut state = self.state.lock();
+        if !state.is_aborted {
+            state.init = Some(info);
+        }
+        drop(state);
+
+        self.wait_queue.wake_all();
+    }
+
+    /// Aborts the connection.
+    ///
+    /// All the requests fail with [`Errno::ENOTCONN`], and the daemon reads [`Errno::ENODEV`].
+    pub(super) fn abort(&self) {
+        let mut state = self.state.lock();
+        if state.is_aborted {
+            return;
+        }
+        state.is_aborted = true;
+        state.pending.clear();
+        state.processing.clear();
+        drop(state);
+
+        self.pollee.notify(IoEvents::IN | IoEvents::ERR);
+        self.wait_queue.wake_all();
+    }
+
+    pub(super) fn check_io_events(&self) -> IoEvents {
+        let state = self.state.lock();
+        if state.is_aborted {
+            IoEvents::IN | IoEvents::OUT | IoEvents::ERR
+        } else if !state.pending.is_empty() {
+            IoEvents::IN | IoEvents::OUT
+        } else {
+            IoEvents::OUT
+        }
+    }
+
+    pub(super) fn pollee(&self) -> &Pollee {
+        &self.pollee
+    }
+}
+
+/// Returns the file system user ID, the file system group ID and the process ID of the
+/// current thread, which are reported to the daemon in the request header.
+fn current_ids() -> (u32, u32, u32) {
+    Task::current()
+        .and_then(|task| {
+            task.as_posix_thread().map(|posix_thread| {
+                let credentials = posix_thread.credentials();
+                (
+                    u32::from(credentials.fsuid()),
+                    u32::from(credentials.fsgid()),
+                    posix_thread.process().pid(),
+                )
+            })
+        })
+        .unwrap_or((0, 0, 0))
+}
+
+/// Decodes a reply into the header and the payload.
+fn decode_reply(reader: &mut VmReader) -> Result<(FuseOutHeader, Vec<u8>)> {
+    let len = reader.remain();
+    if len < size_of::<FuseOutHeader>() {
+        return_errno_with_message!(Errno::EINVAL, "the reply is too short");
+    }
+    let header = reader.read_val::<FuseOutHeader>()?;
+    if header.len as usize != len {
+        return_errno_with_message!(Errno::EINVAL, "the reply length is invalid");
+    }
+    if header.unique == 0 {
+        // A notification carries its code in the error field.
+        return Ok((header, read_payload(reader, len)?));
+    }
+    // Like Linux, the error must be a negated errno and must not carry any payload.
+    if header.error > 0 || header.error <= -512 {
+        return_errno_with_message!(Errno::EINVAL, "the reply error is invalid");
+    }
+    if header.error != 0 && len != size_of::<FuseOutHeader>() {
+        return_errno_with_message!(Errno::EINVAL, "an error reply carries payload");
+    }
+
+    Ok((header, read_payload(reader, len)?))
+}
+
+fn read_payload(reader: &mut VmReader, len: usize) -> Result<Vec<u8>> {
+    let mut payload = vec![0u8; len - size_of::<FuseOutHeader>()];
+    reader.read_fallible(&mut VmWriter::from(payload.as_mut_slice()))?;
+    Ok(payload)
+}
+
+/// Handles a notification from the daemon.
+///
+/// Nothing is cached beyond the validity periods replied by the daemon, so the
+/// invalidations need no action and the stored data can be dropped. Retrieving the cached
+/// data is not supported, since its reply would never come.
+fn handle_notification(code: i32) -> Result<()> {
+    let Ok(code) = FuseNotifyCode::try_from(code) else {
+        return_errno_with_message!(Errno::EINVAL, "the FUSE notification is invalid");
+    };
+
+    match code {
+        FuseNotifyCode::Poll
+        | FuseNotifyCode::InvalInode
+        | FuseNotifyCode::InvalEntry
+        | FuseNotifyCode::Store
+        | FuseNotifyCode::Delete => {
+            debug!("FUSE: ignore the notification {:?}", code);
+            Ok(())
+        }
+        FuseNotifyCode::Retrieve => {
+            return_errno_with_message!(Errno::ENOSYS, "FUSE_NOTIFY_RETRIEVE is not supported")
+        }
+    }
+}
+
+fn reply_to_result(header: &FuseOutHeader, payload: Vec<u8>) -> Result<Vec<u8>> {
+    if header.error == 0 {
+        Ok(payload)
+    } else {
+        let errno = Errno::try_from(-header.error).unwrap_or(Errno::EIO);
+        Err(Error::new(errno))
+    }
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::conn::FuseConn;
+use crate::{
+    events::IoEvents,
+    fs::{
+        device::{Device, DeviceId, DeviceType},
+        inode_handle::FileIo,
+        utils::StatusFlags,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// The `/dev/fuse` device.
+///
+/// Each open of the device creates a new [`FuseDevFile`] with a new connection.
+pub struct FuseDevice;
+
+impl Device for FuseDevice {
+    fn type_(&self) -> DeviceType {
+        DeviceType::MiscDevice
+    }
+
+    fn id(&self) -> DeviceId {
+        // The same value as Linux
+        DeviceId::new(10, 229)
+    }
+
+    fn open(&self) -> Result<Option<Arc<dyn FileIo>>> {
+        Ok(Some(Arc::new(FuseDevFile::new())))
+    }
+}
+
+impl Pollable for FuseDevice {
+    fn poll(&self, _mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        IoEvents::empty()
+    }
+}
+
+impl FileIo for FuseDevice {
+    fn read(&self, _writer: &mut VmWriter, _status_flags: StatusFlags) -> Result<usize> {
+        return_errno_with_message!(Errno::EPERM, "the FUSE device is not opened");
+    }
+
+    fn write(&self, _reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        return_errno_with_message!(Errno::EPERM, "the FUSE device is not opened");
+    }
+}
+
+/// An opened `/dev/fuse`, through which the daemon serves a FUSE connection.
+///
+/// The connection is aborted when the file is closed.
+pub(super) struct FuseDevFile {
+    conn: Arc<FuseConn>,
+}
+
+impl FuseDevFile {
+    fn new() -> Self {
+        Self {
+            conn: FuseConn::new(),
+        }
+    }
+
+    pub(super) fn conn(&self) -> &Arc<FuseConn> {
+        &self.conn
+    }
+}
+
+impl Drop for FuseDevFile {
+    fn drop(&mut self) {
+        self.conn.abort();
+    }
+}
+
+impl Pollable for FuseDevFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.conn
+            .pollee()
+            .poll_with(mask, poller, || self.conn.check_io_events())
+    }
+}
+
+impl FileIo for FuseDevFile {
+    fn read(&self, writer: &mut VmWriter, status_flags: StatusFlags) -> Result<usize> {
+        if status_flags.contains(StatusFlags::O_NONBLOCK) {
+            self.conn.try_read_request(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.conn.try_read_request(writer))
+        }
+    }
+
+    fn write(&self, reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        self.conn.write_reply(reader)
+    }
+}
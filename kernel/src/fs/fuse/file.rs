@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    abi::{FuseFlushIn, FuseOpcode, FuseOpenFlags, FuseOpenOut, FuseReleaseIn},
+    conn::FuseConn,
+    inode::FuseInode,
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        inode_handle::FileIo,
+        utils::{AccessMode, Inode, StatusFlags},
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// A handle of a node opened by `FUSE_OPEN` or `FUSE_CREATE`.
+///
+/// The handle is released by `FUSE_RELEASE` when it is dropped.
+pub(super) struct FuseFileHandle {
+    pub(super) fh: u64,
+    pub(super) access_mode: AccessMode,
+    pub(super) open_flags: FuseOpenFlags,
+    nodeid: u64,
+    conn: Arc<FuseConn>,
+}
+
+impl FuseFileHandle {
+    pub(super) fn new(
+        conn: Arc<FuseConn>,
+        nodeid: u64,
+        access_mode: AccessMode,
+        open_out: &FuseOpenOut,
+    ) -> Self {
+        Self {
+            fh: open_out.fh,
+            access_mode,
+            open_flags: FuseOpenFlags::from_bits_truncate(open_out.open_flags),
+            nodeid,
+            conn,
+        }
+    }
+
+    pub(super) fn is_writable(&self) -> bool {
+        self.access_mode.is_writable()
+    }
+
+    /// Returns whether the file opened with the handle bypasses the page cache.
+    pub(super) fn is_direct_io(&self) -> bool {
+        self.open_flags.contains(FuseOpenFlags::DIRECT_IO)
+    }
+}
+
+impl Drop for FuseFileHandle {
+    fn drop(&mut self) {
+        let release_in = FuseReleaseIn {
+            fh: self.fh,
+            flags: self.access_mode as u32,
+            ..Default::default()
+        };
+        let _ =
+            self.conn
+                .send_background(FuseOpcode::Release, self.nodeid, &[release_in.as_bytes()]);
+    }
+}
+
+/// An opened regular file of a FUSE file system.
+///
+/// Like Linux, each opened file has its own handle, so the daemon can keep the state of each
+/// opened file. When the file is closed, the dirty pages are written back, the file is flushed
+/// by `FUSE_FLUSH`, and then the handle is released.
+///
+/// Unlike Linux, which flushes the file whenever a file descriptor is closed, the file is
+/// flushed only when the last file descriptor is closed, since the file system is not notified
+/// of the other ones.
+pub(super) struct FuseFile {
+    inode: Arc<FuseInode>,
+    handle: Arc<FuseFileHandle>,
+}
+
+impl FuseFile {
+    pub(super) fn new(inode: Arc<FuseInode>, handle: Arc<FuseFileHandle>) -> Self {
+        Self { inode, handle }
+    }
+}
+
+impl Drop for FuseFile {
+    fn drop(&mut self) {
+        if self.handle.is_writable() {
+            if let Err(err) = self.inode.write_back_pages() {
+                warn!("FUSE: failed to write back the pages: {:?}", err);
+            }
+        }
+
+        let flush_in = FuseFlushIn {
+            fh: self.handle.fh,
+            ..Default::default()
+        };
+        let _ = self.inode.conn().send_background(
+            FuseOpcode::Flush,
+            self.inode.nodeid(),
+            &[flush_in.as_bytes()],
+        );
+    }
+}
+
+impl Pollable for FuseFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for FuseFile {
+    fn read(&self, _writer: &mut VmWriter, _status_flags: StatusFlags) -> Result<usize> {
+        unreachable!("the file is always read at the offsets")
+    }
+
+    fn write(&self, _reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        unreachable!("the file is always written at the offsets")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let is_direct = status_flags.contains(StatusFlags::O_DIRECT);
+        self.inode
+            .read_file(&self.handle, offset, writer, is_direct)
+    }
+
+    fn write_at(
+        &self,
+        offset: usize,
+        reader: &mut VmReader,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        // The offset is ignored if the file is opened with `O_APPEND`.
+        let offset = if status_flags.contains(StatusFlags::O_APPEND) {
+            self.inode.size()
+        } else {
+            offset
+        };
+        self.inode.write_file(&self.handle, offset, reader)
+    }
+}
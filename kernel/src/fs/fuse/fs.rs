@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::BlockDevice;
+
+use super::{
+    abi::{FuseEntryOut, FuseOpcode, FuseStatfsOut, FUSE_ROOT_ID, FUSE_SUPER_MAGIC},
+    conn::FuseConn,
+    dev::FuseDevFile,
+    inode::{parse_reply, FuseInode},
+};
+use crate::{
+    fs::{
+        file_table::{get_file_fast, FileDesc},
+        registry::{FsProperties, FsType},
+        utils::{FileSystem, FsFlags, Inode, InodeMode, InodeType, SuperBlock, NAME_MAX},
+    },
+    prelude::*,
+};
+
+/// A file system served by a userspace daemon through a FUSE connection.
+pub struct FuseFS {
+    conn: Arc<FuseConn>,
+    mount_options: FuseMountOptions,
+    root: Arc<FuseInode>,
+    /// The inodes that are alive, indexed by the node IDs.
+    ///
+    /// Each lookup of a node replied by the daemon is counted by the inode, and the count
+    /// is forgotten when the inode is dropped.
+    inodes: Mutex<BTreeMap<u64, Weak<FuseInode>>>,
+    this: Weak<FuseFS>,
+}
+
+impl Debug for FuseFS {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FuseFS")
+            .field("mount_options", &self.mount_options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FuseFS {
+    pub(super) fn new(conn: Arc<FuseConn>, mount_options: FuseMountOptions) -> Result<Arc<Self>> {
+        conn.mount()?;
+
+        let fs = Arc::new_cyclic(|weak_fs| Self {
+            root: FuseInode::new_root(weak_fs.clone(), conn.clone(), &mount_options),
+            conn,
+            mount_options,
+            inodes: Mutex::new(BTreeMap::new()),
+            this: weak_fs.clone(),
+        });
+        Ok(fs)
+    }
+
+    pub(super) fn mount_options(&self) -> &FuseMountOptions {
+        &self.mount_options
+    }
+
+    /// Gets the inode of the node in an entry reply, creating it if it is not alive.
+    ///
+    /// The lookup count of the node is increased by one.
+    pub(super) fn get_or_create_inode(&self, entry: &FuseEntryOut) -> Result<Arc<FuseInode>> {
+        // Like Linux, a zero node ID is a negative entry.
+        if entry.nodeid == 0 {
+            return_errno_with_message!(Errno::ENOENT, "the entry does not exist");
+        }
+        if entry.nodeid == FUSE_ROOT_ID {
+            // The root node is never forgotten, so its lookups need not be counted.
+            self.root
+                .update_attr(&entry.attr, entry.attr_valid, entry.attr_valid_nsec);
+            return Ok(self.root.clone());
+        }
+
+        let mut inodes = self.inodes.lock();
+        if let Some(inode) = inodes.get(&entry.nodeid).and_then(Weak::upgrade) {
+            drop(inodes);
+            inode.inc_nlookup();
+            inode.update_attr(&entry.attr, entry.attr_valid, entry.attr_valid_nsec);
+            return Ok(inode);
+        }
+
+        let inode = FuseInode::new(
+            self.this.clone(),
+            self.conn.clone(),
+            entry.nodeid,
+            &entry.attr,
+            entry.attr_valid,
+            entry.attr_valid_nsec,
+        )?;
+        inodes.insert(entry.nodeid, Arc::downgrade(&inode));
+        Ok(inode)
+    }
+
+    /// Removes the node from the inode table if its inode is no longer alive.
+    pub(super) fn remove_inode(&self, nodeid: u64) {
+        let mut inodes = self.inodes.lock();
+        if inodes
+            .get(&nodeid)
+            .is_some_and(|inode| inode.strong_count() == 0)
+        {
+            inodes.remove(&nodeid);
+        }
+    }
+}
+
+impl Drop for FuseFS {
+    fn drop(&mut self) {
+        self.conn.destroy();
+        // The daemon sees `ENODEV` from the device and exits.
+        self.conn.abort();
+    }
+}
+
+impl FileSystem for FuseFS {
+    fn sync(&self) -> Result<()> {
+        // The data is written through to the daemon.
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let statfs = self
+            .conn
+            .send(FuseOpcode::Statfs, FUSE_ROOT_ID, &[])
+            .and_then(|payload| parse_reply::<FuseStatfsOut>(&payload));
+        let Ok(FuseStatfsOut { st }) = statfs else {
+            // Like Linux, a daemon that does not reply reports an empty file system.
+            return SuperBlock::new(FUSE_SUPER_MAGIC, PAGE_SIZE, NAME_MAX);
+        };
+
+        let mut sb = SuperBlock::new(FUSE_SUPER_MAGIC, st.bsize as usize, st.namelen as usize);
+        sb.blocks = st.blocks as usize;
+        sb.bfree = st.bfree as usize;
+        sb.bavail = st.bavail as usize;
+        sb.files = st.files as usize;
+        sb.ffree = st.ffree as usize;
+        sb.frsize = st.frsize as usize;
+        sb
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::empty()
+    }
+}
+
+// Mount options
+//
+// Like Linux, `fd`, `rootmode`, `user_id` and `group_id` are required by `fuse`, but not by
+// `virtiofs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuseMountOptions {
+    /// The file descriptor of the opened `/dev/fuse`.
+    pub(super) fd: Option<FileDesc>,
+    /// The permissions of the root directory.
+    pub(super) root_mode: Option<InodeMode>,
+    /// The owner of the file system, which is reported to the daemon.
+    pub(super) user_id: Option<u32>,
+    pub(super) group_id: Option<u32>,
+    /// The maximum size of a read request.
+    pub(super) max_read: Option<usize>,
+}
+
+impl FuseMountOptions {
+    pub fn parse(args: Option<CString>) -> Result<Self> {
+        let parse_u32 = |value: &str| -> Result<u32> {
+            value
+                .parse::<u32>()
+                .map_err(|_| Error::with_message(Errno::EINVAL, "invalid mount option value"))
+        };
+
+        let mut fd = None;
+        let mut root_mode = None;
+        let mut user_id = None;
+        let mut group_id = None;
+        let mut max_read = None;
+        let args = args.map(|args| args.to_string_lossy().into_owned());
+        for option in args.iter().flat_map(|args| args.split(',')) {
+            match option.split_once('=') {
+                Some(("fd", value)) => fd = Some(parse_u32(value)? as FileDesc),
+                Some(("rootmode", value)) => {
+                    let mode = u32::from_str_radix(value, 8)
+                        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid rootmode"))?;
+                    root_mode = Some(mode);
+                }
+                Some(("user_id", value)) => user_id = Some(parse_u32(value)?),
+                Some(("group_id", value)) => group_id = Some(parse_u32(value)?),
+                Some(("max_read", value)) => max_read = Some(parse_u32(value)? as usize),
+                // The permissions are always checked by the VFS, and the other options
+                // (e.g., `allow_other`) have no effect.
+                _ => (),
+            }
+        }
+
+        if let Some(root_mode) = root_mode {
+            if InodeType::from_raw_mode(root_mode as u16)? != InodeType::Dir {
+                return_errno_with_message!(Errno::EINVAL, "the root must be a directory")
+            }
+        }
+
+        Ok(Self {
+            fd,
+            root_mode: root_mode.map(|mode| InodeMode::from_bits_truncate(mode as u16)),
+            user_id,
+            group_id,
+            max_read,
+        })
+    }
+}
+
+pub(super) struct FuseType;
+
+impl FsType for FuseType {
+    fn name(&self) -> &'static str {
+        "fuse"
+    }
+
+    fn create(
+        &self,
+        _source: &CStr,
+        args: Option<CString>,
+        _disk: Option<Arc<dyn BlockDevice>>,
+        ctx: &Context,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let mount_options = FuseMountOptions::parse(args)?;
+        let FuseMountOptions {
+            fd: Some(fd),
+            root_mode: Some(_),
+            user_id: Some(_),
+            group_id: Some(_),
+            ..
+        } = mount_options
+        else {
+            return_errno_with_message!(Errno::EINVAL, "missing fuse mount options")
+        };
+
+        let conn = {
+            let mut file_table = ctx.thread_local.borrow_file_table_mut();
+            let file = get_file_fast!(&mut file_table, fd);
+            file.as_inode_or_err()?
+                .file_io()
+                .and_then(|file_io| file_io.downcast_ref::<FuseDevFile>())
+                .map(|dev_file| dev_file.conn().clone())
+                .ok_or_else(|| {
+                    Error::with_message(Errno::EINVAL, "the file is not an opened /dev/fuse")
+                })?
+        };
+
+        FuseFS::new(conn, mount_options).map(|fs| fs as _)
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::HAS_SUBTYPE
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysBranchNode>> {
+        None
+    }
+}
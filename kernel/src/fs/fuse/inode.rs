@@ -0,0 +1,1063 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use aster_block::bio::BioWaiter;
+use aster_rights::Full;
+use ostd::mm::{io_util::HasVmReaderWriter, VmIo};
+
+use super::{
+    abi::{
+        FuseAttr, FuseAttrOut, FuseCreateIn, FuseDirent, FuseEntryOut, FuseForgetIn, FuseFsyncIn,
+        FuseGetattrIn, FuseMkdirIn, FuseMknodIn, FuseOpcode, FuseOpenIn, FuseOpenOut, FuseReadIn,
+        FuseReleaseIn, FuseRenameIn, FuseSetattrIn, FuseSetattrValid, FuseWriteIn, FuseWriteOut,
+        FUSE_ROOT_ID,
+    },
+    conn::FuseConn,
+    file::{FuseFile, FuseFileHandle},
+    fs::{FuseFS, FuseMountOptions},
+};
+use crate::{
+    fs::{
+        inode_handle::FileIo,
+        utils::{
+            AccessMode, CachePage, CreationFlags, DirentVisitor, Extension, FileSystem, Inode,
+            InodeMode, InodeType, Metadata, MknodType, PageCache, PageCacheBackend, StatusFlags,
+            NAME_MAX,
+        },
+    },
+    prelude::*,
+    process::{Gid, Uid},
+    time::clocks::MonotonicCoarseClock,
+    vm::vmo::Vmo,
+};
+
+/// An inode of a [`FuseFS`], which stands for a node of the daemon.
+///
+/// The regular files are read through the page cache, unless the daemon opens them with
+/// `FOPEN_DIRECT_IO`. The writes are always sent to the daemon immediately, and the written
+/// range of the page cache is invalidated. The page cache is also invalidated when the
+/// size or the modification time reported by the daemon changes.
+pub struct FuseInode {
+    this: Weak<FuseInode>,
+    nodeid: u64,
+    ino: u64,
+    type_: InodeType,
+    /// The number of lookups of the node replied by the daemon.
+    ///
+    /// The lookups are forgotten by `FUSE_FORGET` when the inode is dropped.
+    nlookup: AtomicU64,
+    attr: RwMutex<CachedAttr>,
+    /// The handles of the opened files of the node.
+    ///
+    /// Each opened file has its own handle (see [`FuseFile`]). The page cache is shared by all
+    /// the opened files, so the pages are read and written with any suitable handle of them.
+    handles: Mutex<Vec<Weak<FuseFileHandle>>>,
+    /// The handle used by the page cache if no opened file has a suitable handle (e.g., when
+    /// a mapped file is written back after it is closed).
+    ///
+    /// The handle is released when the inode is dropped.
+    cache_handle: Mutex<Option<Arc<FuseFileHandle>>>,
+    /// The page cache of a regular file.
+    page_cache: Option<PageCache>,
+    conn: Arc<FuseConn>,
+    fs: Weak<FuseFS>,
+    extension: Extension,
+}
+
+impl Debug for FuseInode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FuseInode")
+            .field("nodeid", &self.nodeid)
+            .field("ino", &self.ino)
+            .field("type_", &self.type_)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CachedAttr {
+    attr: FuseAttr,
+    /// The time until which the attributes are valid, on the monotonic clock.
+    valid_until: Duration,
+}
+
+impl PageCacheBackend for FuseInode {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let mut writer = frame.writer().to_fallible();
+        let handle = self.get_handle(false)?;
+        let read_len = self.read_from_daemon(&handle, idx * PAGE_SIZE, &mut writer)?;
+        frame
+            .writer()
+            .skip(read_len)
+            .fill_zeros(PAGE_SIZE - read_len);
+        Ok(BioWaiter::new())
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let offset = idx * PAGE_SIZE;
+        let size = self.attr.read().attr.size as usize;
+        if offset >= size {
+            return Ok(BioWaiter::new());
+        }
+
+        let mut buf = vec![0u8; (size - offset).min(PAGE_SIZE)];
+        frame.read_bytes(0, &mut buf)?;
+        let handle = self.get_handle(true)?;
+        let write_len = self.send_write(&handle, offset, &buf)?;
+        if write_len < buf.len() {
+            return_errno_with_message!(Errno::EIO, "the page is not fully written");
+        }
+        Ok(BioWaiter::new())
+    }
+
+    fn npages(&self) -> usize {
+        (self.attr.read().attr.size as usize).div_ceil(PAGE_SIZE)
+    }
+}
+
+impl FuseInode {
+    pub(super) fn new_root(
+        fs: Weak<FuseFS>,
+        conn: Arc<FuseConn>,
+        mount_options: &FuseMountOptions,
+    ) -> Arc<Self> {
+        // The attributes are fetched from the daemon after the connection is initialized.
+        let root_mode = mount_options
+            .root_mode
+            .unwrap_or(InodeMode::from_bits_truncate(0o755));
+        let attr = FuseAttr {
+            ino: FUSE_ROOT_ID,
+            mode: InodeType::Dir as u32 | root_mode.bits() as u32,
+            nlink: 2,
+            uid: mount_options.user_id.unwrap_or(0),
+            gid: mount_options.group_id.unwrap_or(0),
+            ..Default::default()
+        };
+        Arc::new_cyclic(|weak_self| Self {
+            this: weak_self.clone(),
+            nodeid: FUSE_ROOT_ID,
+            ino: FUSE_ROOT_ID,
+            type_: InodeType::Dir,
+            nlookup: AtomicU64::new(0),
+            attr: RwMutex::new(CachedAttr {
+                attr,
+                valid_until: Duration::ZERO,
+            }),
+            handles: Mutex::new(Vec::new()),
+            cache_handle: Mutex::new(None),
+            page_cache: None,
+            conn,
+            fs,
+            extension: Extension::new(),
+        })
+    }
+
+    pub(super) fn new(
+        fs: Weak<FuseFS>,
+        conn: Arc<FuseConn>,
+        nodeid: u64,
+        attr: &FuseAttr,
+        attr_valid: u64,
+        attr_valid_nsec: u32,
+    ) -> Result<Arc<Self>> {
+        let type_ = InodeType::from_raw_mode(attr.mode as u16)?;
+        let size = attr.size as usize;
+        Ok(Arc::new_cyclic(|weak_self| Self {
+            this: weak_self.clone(),
+            nodeid,
+            ino: attr.ino,
+            type_,
+            nlookup: AtomicU64::new(1),
+            attr: RwMutex::new(CachedAttr {
+                attr: *attr,
+                valid_until: valid_until(attr_valid, attr_valid_nsec),
+            }),
+            handles: Mutex::new(Vec::new()),
+            cache_handle: Mutex::new(None),
+            page_cache: (type_ == InodeType::File)
+                .then(|| PageCache::with_capacity(size, weak_self.clone() as _).unwrap()),
+            conn,
+            fs,
+            extension: Extension::new(),
+        }))
+    }
+
+    fn get_fs(&self) -> Arc<FuseFS> {
+        self.fs.upgrade().unwrap()
+    }
+
+    pub(super) fn nodeid(&self) -> u64 {
+        self.nodeid
+    }
+
+    pub(super) fn conn(&self) -> &Arc<FuseConn> {
+        &self.conn
+    }
+
+    pub(super) fn inc_nlookup(&self) {
+        self.nlookup.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Updates the cached attributes with the ones replied by the daemon.
+    ///
+    /// If the size or the modification time is changed, the file has been modified, and the
+    /// page cache is invalidated.
+    pub(super) fn update_attr(&self, attr: &FuseAttr, attr_valid: u64, attr_valid_nsec: u32) {
+        let mut cached = self.attr.write();
+        let old_attr = cached.attr;
+        cached.attr = *attr;
+        cached.valid_until = valid_until(attr_valid, attr_valid_nsec);
+        drop(cached);
+
+        let Some(page_cache) = self.page_cache.as_ref() else {
+            return;
+        };
+        if old_attr.size == attr.size
+            && old_attr.mtime == attr.mtime
+            && old_attr.mtimensec == attr.mtimensec
+        {
+            return;
+        }
+        if let Err(err) = page_cache.resize(attr.size as usize) {
+            warn!("FUSE: failed to resize the page cache: {:?}", err);
+        }
+        // The dirty pages are discarded, since they are based on the stale content and writing
+        // them back would overwrite the changes made by the daemon.
+        let size = page_cache.pages().size();
+        if let Err(err) = invalidate_pages(page_cache, 0..size) {
+            warn!("FUSE: failed to invalidate the page cache: {:?}", err);
+        }
+    }
+
+    /// Marks the cached attributes as stale, so that they are fetched again on next use.
+    fn invalidate_attr(&self) {
+        self.attr.write().valid_until = Duration::ZERO;
+    }
+
+    /// Returns the attributes, which are fetched from the daemon if the cached ones expire.
+    fn attr(&self) -> FuseAttr {
+        let cached = *self.attr.read();
+        if MonotonicCoarseClock::get().read_time() < cached.valid_until {
+            return cached.attr;
+        }
+
+        match self.getattr() {
+            Ok(attr) => attr,
+            Err(err) => {
+                debug!("FUSE: failed to get the attributes: {:?}", err);
+                cached.attr
+            }
+        }
+    }
+
+    fn getattr(&self) -> Result<FuseAttr> {
+        let getattr_in = FuseGetattrIn::default();
+        let payload = self
+            .conn
+            .send(FuseOpcode::Getattr, self.nodeid, &[getattr_in.as_bytes()])?;
+        let attr_out = parse_reply::<FuseAttrOut>(&payload)?;
+        self.update_attr(
+            &attr_out.attr,
+            attr_out.attr_valid,
+            attr_out.attr_valid_nsec,
+        );
+        Ok(attr_out.attr)
+    }
+
+    fn setattr(&self, setattr_in: &FuseSetattrIn) -> Result<()> {
+        let payload = self
+            .conn
+            .send(FuseOpcode::Setattr, self.nodeid, &[setattr_in.as_bytes()])?;
+        let attr_out = parse_reply::<FuseAttrOut>(&payload)?;
+        self.update_attr(
+            &attr_out.attr,
+            attr_out.attr_valid,
+            attr_out.attr_valid_nsec,
+        );
+        Ok(())
+    }
+
+    fn set_time(&self, valid: FuseSetattrValid, time: Duration) {
+        let secs = time.as_secs();
+        let nsecs = time.subsec_nanos();
+        let mut setattr_in = FuseSetattrIn {
+            valid: valid.bits(),
+            ..Default::default()
+        };
+        if valid.contains(FuseSetattrValid::ATIME) {
+            (setattr_in.atime, setattr_in.atimensec) = (secs, nsecs);
+        }
+        if valid.contains(FuseSetattrValid::MTIME) {
+            (setattr_in.mtime, setattr_in.mtimensec) = (secs, nsecs);
+        }
+        if valid.contains(FuseSetattrValid::CTIME) {
+            (setattr_in.ctime, setattr_in.ctimensec) = (secs, nsecs);
+        }
+        if let Err(err) = self.setattr(&setattr_in) {
+            debug!("FUSE: failed to set the timestamps: {:?}", err);
+        }
+    }
+
+    /// Opens the file with a new handle, which is used only by the opened file.
+    fn open_file(&self, access_mode: AccessMode, status_flags: StatusFlags) -> Result<FuseFile> {
+        let handle = Arc::new(self.send_open(access_mode, status_flags)?);
+
+        let mut handles = self.handles.lock();
+        handles.retain(|handle| handle.strong_count() > 0);
+        handles.push(Arc::downgrade(&handle));
+        drop(handles);
+
+        Ok(FuseFile::new(self.this.upgrade().unwrap(), handle))
+    }
+
+    /// Returns a handle for the page cache, opening the file if there is no suitable one.
+    fn get_handle(&self, need_write: bool) -> Result<Arc<FuseFileHandle>> {
+        let is_suitable = |handle: &FuseFileHandle| !need_write || handle.is_writable();
+
+        if let Some(handle) = self
+            .handles
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|handle| is_suitable(handle))
+        {
+            return Ok(handle);
+        }
+
+        let mut cache_handle = self.cache_handle.lock();
+        if let Some(handle) = cache_handle.as_ref().filter(|handle| is_suitable(handle)) {
+            return Ok(handle.clone());
+        }
+
+        // The file is opened for both reading and writing if possible, so that one handle
+        // can serve both the reads and the writes of the page cache.
+        let handle = match self.send_open(AccessMode::O_RDWR, StatusFlags::empty()) {
+            Err(err) if matches!(err.error(), Errno::EACCES | Errno::EPERM | Errno::EROFS) => {
+                let access_mode = if need_write {
+                    AccessMode::O_WRONLY
+                } else {
+                    AccessMode::O_RDONLY
+                };
+                self.send_open(access_mode, StatusFlags::empty())?
+            }
+            res => res?,
+        };
+        let handle = Arc::new(handle);
+        *cache_handle = Some(handle.clone());
+        Ok(handle)
+    }
+
+    /// Returns all the handles of the node.
+    fn all_handles(&self) -> Vec<Arc<FuseFileHandle>> {
+        let mut handles: Vec<_> = self
+            .handles
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        handles.extend(self.cache_handle.lock().clone());
+        handles
+    }
+
+    fn send_open(
+        &self,
+        access_mode: AccessMode,
+        status_flags: StatusFlags,
+    ) -> Result<FuseFileHandle> {
+        let open_in = FuseOpenIn {
+            flags: access_mode as u32 | status_flags.bits(),
+            unused: 0,
+        };
+        let payload = self
+            .conn
+            .send(FuseOpcode::Open, self.nodeid, &[open_in.as_bytes()])?;
+        let open_out = parse_reply::<FuseOpenOut>(&payload)?;
+        Ok(FuseFileHandle::new(
+            self.conn.clone(),
+            self.nodeid,
+            access_mode,
+            &open_out,
+        ))
+    }
+
+    /// Returns whether the file bypasses the page cache.
+    fn is_direct_io(&self) -> Result<bool> {
+        Ok(self.get_handle(false)?.is_direct_io())
+    }
+
+    fn max_read(&self) -> Result<usize> {
+        let max_read = self.conn.init_info()?.max_read;
+        let fs = self.get_fs();
+        Ok(match fs.mount_options().max_read {
+            Some(limit) if limit > 0 => max_read.min(limit),
+            _ => max_read,
+        })
+    }
+
+    /// Reads the file from the daemon with the handle, bypassing the page cache.
+    fn read_from_daemon(
+        &self,
+        handle: &FuseFileHandle,
+        offset: usize,
+        writer: &mut VmWriter,
+    ) -> Result<usize> {
+        let max_read = self.max_read()?;
+
+        let mut read_len = 0;
+        while writer.avail() > 0 {
+            let size = writer.avail().min(max_read);
+            let read_in = FuseReadIn {
+                fh: handle.fh,
+                offset: (offset + read_len) as u64,
+                size: size as u32,
+                flags: handle.access_mode as u32,
+                ..Default::default()
+            };
+            let res = self
+                .conn
+                .send(FuseOpcode::Read, self.nodeid, &[read_in.as_bytes()])
+                .and_then(|data| {
+                    let data = &data[..data.len().min(size)];
+                    writer.write_fallible(&mut VmReader::from(data))?;
+                    Ok(data.len())
+                });
+            match res {
+                Ok(len) => {
+                    read_len += len;
+                    if len < size {
+                        break;
+                    }
+                }
+                Err(_) if read_len > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(read_len)
+    }
+
+    /// Writes the file to the daemon with the handle, bypassing the page cache.
+    fn write_to_daemon(
+        &self,
+        handle: &FuseFileHandle,
+        offset: usize,
+        reader: &mut VmReader,
+    ) -> Result<usize> {
+        let max_write = self.conn.init_info()?.max_write;
+
+        let mut buf = vec![0u8; reader.remain().min(max_write)];
+        let mut write_len = 0;
+        while reader.remain() > 0 {
+            let size = reader.remain().min(max_write);
+            let res = reader
+                .read_fallible(&mut VmWriter::from(&mut buf[..size]))
+                .map_err(Error::from)
+                .and_then(|_| self.send_write(handle, offset + write_len, &buf[..size]));
+            match res {
+                Ok(len) => {
+                    write_len += len;
+                    if len < size {
+                        break;
+                    }
+                }
+                Err(_) if write_len > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(write_len)
+    }
+
+    fn send_write(&self, handle: &FuseFileHandle, offset: usize, data: &[u8]) -> Result<usize> {
+        let write_in = FuseWriteIn {
+            fh: handle.fh,
+            offset: offset as u64,
+            size: data.len() as u32,
+            flags: handle.access_mode as u32,
+            ..Default::default()
+        };
+        let payload =
+            self.conn
+                .send(FuseOpcode::Write, self.nodeid, &[write_in.as_bytes(), data])?;
+        let write_out = parse_reply::<FuseWriteOut>(&payload)?;
+        Ok((write_out.size as usize).min(data.len()))
+    }
+
+    /// Updates the cached size and invalidates the page cache after the range is written.
+    fn complete_write(&self, range: core::ops::Range<usize>) -> Result<()> {
+        let Some(page_cache) = self.page_cache.as_ref() else {
+            return Ok(());
+        };
+
+        let mut cached = self.attr.write();
+        let is_extended = range.end as u64 > cached.attr.size;
+        if is_extended {
+            cached.attr.size = range.end as u64;
+        }
+        drop(cached);
+
+        if is_extended {
+            page_cache.resize(range.end)?;
+        }
+        invalidate_pages(page_cache, range)
+    }
+
+    /// Reads the file with the handle, through the page cache unless it is bypassed.
+    pub(super) fn read_file(
+        &self,
+        handle: &FuseFileHandle,
+        offset: usize,
+        writer: &mut VmWriter,
+        is_direct: bool,
+    ) -> Result<usize> {
+        let Some(page_cache) = self.page_cache.as_ref() else {
+            return self.read_from_daemon(handle, offset, writer);
+        };
+        if is_direct || handle.is_direct_io() {
+            return self.read_from_daemon(handle, offset, writer);
+        }
+
+        let (read_off, read_len) = {
+            // The attributes are refreshed first, so that stale pages are invalidated.
+            let file_size = self.size();
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + writer.avail());
+            (start, end - start)
+        };
+        page_cache.pages().read(read_off, writer)?;
+        Ok(read_len)
+    }
+
+    /// Writes the file with the handle.
+    ///
+    /// The page cache is write-through, so the data is always sent to the daemon directly.
+    pub(super) fn write_file(
+        &self,
+        handle: &FuseFileHandle,
+        offset: usize,
+        reader: &mut VmReader,
+    ) -> Result<usize> {
+        if reader.remain() == 0 {
+            return Ok(0);
+        }
+
+        // The pages dirtied via the memory mappings are written back first. Otherwise, they
+        // would overwrite the newly written data when they are written back later.
+        if let Some(page_cache) = self.page_cache.as_ref() {
+            page_cache.evict_range(offset..offset + reader.remain())?;
+        }
+
+        let write_len = self.write_to_daemon(handle, offset, reader)?;
+        self.complete_write(offset..offset + write_len)?;
+        Ok(write_len)
+    }
+
+    /// Writes back the pages dirtied via the memory mappings.
+    pub(super) fn write_back_pages(&self) -> Result<()> {
+        let Some(page_cache) = self.page_cache.as_ref() else {
+            return Ok(());
+        };
+        page_cache.evict_range(0..page_cache.pages().size())
+    }
+
+    fn lookup_entry(&self, opcode: FuseOpcode, args: &[&[u8]]) -> Result<Arc<FuseInode>> {
+        let payload = self.conn.send(opcode, self.nodeid, args)?;
+        let entry = parse_reply::<FuseEntryOut>(&payload)?;
+        self.get_fs().get_or_create_inode(&entry)
+    }
+
+    fn mknod_entry(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<FuseInode>> {
+        let mknod_in = FuseMknodIn {
+            mode: type_ as u32 | mode.bits() as u32,
+            ..Default::default()
+        };
+        self.lookup_entry(
+            FuseOpcode::Mknod,
+            &[mknod_in.as_bytes(), name.as_bytes(), b"\0"],
+        )
+    }
+
+    fn create_file(&self, name: &str, mode: InodeMode) -> Result<Arc<FuseInode>> {
+        let create_in = FuseCreateIn {
+            flags: AccessMode::O_RDWR as u32
+                | (CreationFlags::O_CREAT | CreationFlags::O_EXCL).bits(),
+            mode: InodeType::File as u32 | mode.bits() as u32,
+            ..Default::default()
+        };
+        let payload = match self.conn.send(
+            FuseOpcode::Create,
+            self.nodeid,
+            &[create_in.as_bytes(), name.as_bytes(), b"\0"],
+        ) {
+            // Like Linux, fall back to `FUSE_MKNOD` if the daemon does not implement
+            // `FUSE_CREATE`.
+            Err(err) if err.error() == Errno::ENOSYS => {
+                return self.mknod_entry(name, InodeType::File, mode);
+            }
+            res => res?,
+        };
+
+        if payload.len() < size_of::<FuseEntryOut>() + size_of::<FuseOpenOut>() {
+            return_errno_with_message!(Errno::EIO, "the create reply is too short");
+        }
+        let (entry, open_out) = payload.split_at(size_of::<FuseEntryOut>());
+        let entry = FuseEntryOut::from_bytes(entry);
+        let open_out = FuseOpenOut::from_bytes(open_out);
+
+        let inode = self.get_fs().get_or_create_inode(&entry)?;
+        // The file will be opened again by the VFS, so the handle is kept for the page cache.
+        let handle = FuseFileHandle::new(
+            self.conn.clone(),
+            inode.nodeid,
+            AccessMode::O_RDWR,
+            open_out,
+        );
+        *inode.cache_handle.lock() = Some(Arc::new(handle));
+        Ok(inode)
+    }
+
+    fn opendir(&self) -> Result<u64> {
+        let open_in = FuseOpenIn {
+            flags: AccessMode::O_RDONLY as u32,
+            unused: 0,
+        };
+        let payload = self
+            .conn
+            .send(FuseOpcode::Opendir, self.nodeid, &[open_in.as_bytes()])?;
+        Ok(parse_reply::<FuseOpenOut>(&payload)?.fh)
+    }
+
+    fn releasedir(&self, fh: u64) {
+        let release_in = FuseReleaseIn {
+            fh,
+            ..Default::default()
+        };
+        let _ = self.conn.send_background(
+            FuseOpcode::Releasedir,
+            self.nodeid,
+            &[release_in.as_bytes()],
+        );
+    }
+
+    /// Reads the entries starting from `offset`, which is the offset of the last entry
+    /// replied by the daemon.
+    fn read_dirents(
+        &self,
+        fh: u64,
+        offset: &mut usize,
+        visitor: &mut dyn DirentVisitor,
+    ) -> Result<()> {
+        loop {
+            let read_in = FuseReadIn {
+                fh,
+                offset: *offset as u64,
+                size: PAGE_SIZE as u32,
+                ..Default::default()
+            };
+            let payload =
+                self.conn
+                    .send(FuseOpcode::Readdir, self.nodeid, &[read_in.as_bytes()])?;
+            if payload.is_empty() {
+                return Ok(());
+            }
+
+            let mut pos = 0;
+            while pos + size_of::<FuseDirent>() <= payload.len() {
+                let dirent = FuseDirent::from_bytes(&payload[pos..]);
+                let name_start = pos + size_of::<FuseDirent>();
+                let name_end = name_start + dirent.namelen as usize;
+                if name_end > payload.len() {
+                    return_errno_with_message!(Errno::EIO, "the directory entry is truncated");
+                }
+
+                // The offsets are used as the offsets of the opened directory, so they
+                // must increase.
+                let next_offset = dirent.off as usize;
+                if next_offset <= *offset {
+                    warn!("FUSE: the directory offsets do not increase");
+                    return Ok(());
+                }
+
+                let name = String::from_utf8_lossy(&payload[name_start..name_end]);
+                let type_ = InodeType::from_raw_mode((dirent.type_ << 12) as u16)
+                    .unwrap_or(InodeType::Unknown);
+                visitor.visit(&name, dirent.ino, type_, next_offset)?;
+                *offset = next_offset;
+                pos += dirent.record_len();
+            }
+        }
+    }
+
+    fn fsync(&self, is_datasync: bool) -> Result<()> {
+        self.write_back_pages()?;
+
+        let handles = self.all_handles();
+        for handle in handles.iter().filter(|handle| handle.is_writable()) {
+            let fsync_in = FuseFsyncIn {
+                fh: handle.fh,
+                fsync_flags: is_datasync as u32,
+                padding: 0,
+            };
+            match self
+                .conn
+                .send(FuseOpcode::Fsync, self.nodeid, &[fsync_in.as_bytes()])
+            {
+                // The daemon does not need to sync anything.
+                Err(err) if err.error() == Errno::ENOSYS => return Ok(()),
+                res => res?,
+            };
+        }
+        Ok(())
+    }
+
+    fn check_dir(&self) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+        Ok(())
+    }
+}
+
+impl Inode for FuseInode {
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn size(&self) -> usize {
+        self.attr().size as usize
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let setattr_in = FuseSetattrIn {
+            valid: FuseSetattrValid::SIZE.bits(),
+            size: new_size as u64,
+            ..Default::default()
+        };
+        self.setattr(&setattr_in)
+    }
+
+    fn metadata(&self) -> Metadata {
+        let attr = self.attr();
+        let blk_size = if attr.blksize != 0 {
+            attr.blksize as usize
+        } else {
+            PAGE_SIZE
+        };
+
+        Metadata {
+            dev: 0,
+            ino: self.ino,
+            size: attr.size as usize,
+            blk_size,
+            blocks: (attr.blocks as usize * 512).div_ceil(blk_size),
+            atime: Duration::new(attr.atime, attr.atimensec),
+            mtime: Duration::new(attr.mtime, attr.mtimensec),
+            ctime: Duration::new(attr.ctime, attr.ctimensec),
+            type_: self.type_,
+            mode: InodeMode::from_bits_truncate(attr.mode as u16),
+            nlinks: attr.nlink as usize,
+            uid: Uid::new(attr.uid),
+            gid: Gid::new(attr.gid),
+            rdev: attr.rdev as u64,
+        }
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(InodeMode::from_bits_truncate(self.attr().mode as u16))
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        let setattr_in = FuseSetattrIn {
+            valid: FuseSetattrValid::MODE.bits(),
+            mode: mode.bits() as u32,
+            ..Default::default()
+        };
+        self.setattr(&setattr_in)
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(Uid::new(self.attr().uid))
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        let setattr_in = FuseSetattrIn {
+            valid: FuseSetattrValid::UID.bits(),
+            uid: uid.into(),
+            ..Default::default()
+        };
+        self.setattr(&setattr_in)
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(Gid::new(self.attr().gid))
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        let setattr_in = FuseSetattrIn {
+            valid: FuseSetattrValid::GID.bits(),
+            gid: gid.into(),
+            ..Default::default()
+        };
+        self.setattr(&setattr_in)
+    }
+
+    fn atime(&self) -> Duration {
+        let attr = self.attr();
+        Duration::new(attr.atime, attr.atimensec)
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.set_time(FuseSetattrValid::ATIME, time);
+    }
+
+    fn mtime(&self) -> Duration {
+        let attr = self.attr();
+        Duration::new(attr.mtime, attr.mtimensec)
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.set_time(FuseSetattrValid::MTIME, time);
+    }
+
+    fn ctime(&self) -> Duration {
+        let attr = self.attr();
+        Duration::new(attr.ctime, attr.ctimensec)
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.set_time(FuseSetattrValid::CTIME, time);
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.get_fs()
+    }
+
+    fn page_cache(&self) -> Option<Vmo<Full>> {
+        let page_cache = self.page_cache.as_ref()?;
+        match self.is_direct_io() {
+            Ok(false) => Some(page_cache.pages().dup()),
+            _ => None,
+        }
+    }
+
+    fn open(
+        &self,
+        access_mode: AccessMode,
+        status_flags: StatusFlags,
+    ) -> Option<Result<Arc<dyn FileIo>>> {
+        if self.type_ != InodeType::File || status_flags.contains(StatusFlags::O_PATH) {
+            return None;
+        }
+        Some(
+            self.open_file(access_mode, status_flags)
+                .map(|file| Arc::new(file) as _),
+        )
+    }
+
+    // The opened files are read and written via `FuseFile`. The methods below are used when
+    // the file is accessed without being opened (e.g., when a program is loaded).
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let handle = self.get_handle(false)?;
+        self.read_file(&handle, offset, writer, false)
+    }
+
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let handle = self.get_handle(false)?;
+        self.read_file(&handle, offset, writer, true)
+    }
+
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write_direct_at(offset, reader)
+    }
+
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let handle = self.get_handle(true)?;
+        self.write_file(&handle, offset, reader)
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        self.check_dir()?;
+        if name.len() > NAME_MAX {
+            return_errno!(Errno::ENAMETOOLONG)
+        }
+
+        let inode = match type_ {
+            InodeType::File => self.create_file(name, mode)?,
+            InodeType::Dir => {
+                let mkdir_in = FuseMkdirIn {
+                    mode: mode.bits() as u32,
+                    umask: 0,
+                };
+                self.lookup_entry(
+                    FuseOpcode::Mkdir,
+                    &[mkdir_in.as_bytes(), name.as_bytes(), b"\0"],
+                )?
+            }
+            InodeType::NamedPipe | InodeType::Socket => self.mknod_entry(name, type_, mode)?,
+            _ => return_errno_with_message!(Errno::EPERM, "the file type is not supported"),
+        };
+        self.invalidate_attr();
+        Ok(inode)
+    }
+
+    fn mknod(&self, name: &str, mode: InodeMode, type_: MknodType) -> Result<Arc<dyn Inode>> {
+        match type_ {
+            MknodType::NamedPipeNode => self.create(name, InodeType::NamedPipe, mode),
+            _ => return_errno_with_message!(Errno::EPERM, "fuse does not support device files"),
+        }
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        self.check_dir()?;
+
+        let fh = self.opendir()?;
+        let mut iterate_offset = offset;
+        let res = self.read_dirents(fh, &mut iterate_offset, visitor);
+        self.releasedir(fh);
+
+        match res {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        self.check_dir()?;
+        self.conn
+            .send(FuseOpcode::Unlink, self.nodeid, &[name.as_bytes(), b"\0"])?;
+        self.invalidate_attr();
+        Ok(())
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        self.check_dir()?;
+        self.conn
+            .send(FuseOpcode::Rmdir, self.nodeid, &[name.as_bytes(), b"\0"])?;
+        self.invalidate_attr();
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        self.check_dir()?;
+        if name.len() > NAME_MAX {
+            return_errno!(Errno::ENAMETOOLONG)
+        }
+        let inode = self.lookup_entry(FuseOpcode::Lookup, &[name.as_bytes(), b"\0"])?;
+        Ok(inode)
+    }
+
+    fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        let Some(target) = target.downcast_ref::<FuseInode>() else {
+            return_errno_with_message!(Errno::EXDEV, "not same fs")
+        };
+        self.check_dir()?;
+        target.check_dir()?;
+        if new_name.len() > NAME_MAX {
+            return_errno!(Errno::ENAMETOOLONG)
+        }
+
+        let rename_in = FuseRenameIn {
+            newdir: target.nodeid,
+        };
+        self.conn.send(
+            FuseOpcode::Rename,
+            self.nodeid,
+            &[
+                rename_in.as_bytes(),
+                old_name.as_bytes(),
+                b"\0",
+                new_name.as_bytes(),
+                b"\0",
+            ],
+        )?;
+        self.invalidate_attr();
+        target.invalidate_attr();
+        Ok(())
+    }
+
+    fn read_link(&self) -> Result<String> {
+        if self.type_ != InodeType::SymLink {
+            return_errno!(Errno::EINVAL)
+        }
+        let payload = self.conn.send(FuseOpcode::Readlink, self.nodeid, &[])?;
+        String::from_utf8(payload)
+            .map_err(|_| Error::with_message(Errno::EIO, "the link target is not UTF-8"))
+    }
+
+    fn sync_all(&self) -> Result<()> {
+        self.fsync(false)
+    }
+
+    fn sync_data(&self) -> Result<()> {
+        self.fsync(true)
+    }
+
+    // The daemon may change the file system at any time, and there is no way to revalidate
+    // the cached dentries, so every path lookup goes to the daemon.
+    fn is_dentry_cacheable(&self) -> bool {
+        false
+    }
+
+    fn extension(&self) -> Option<&Extension> {
+        Some(&self.extension)
+    }
+}
+
+impl Drop for FuseInode {
+    fn drop(&mut self) {
+        // The handle must be released before the node is forgotten.
+        drop(self.cache_handle.get_mut().take());
+
+        let nlookup = self.nlookup.load(Ordering::Relaxed);
+        if nlookup > 0 {
+            let forget_in = FuseForgetIn { nlookup };
+            let _ =
+                self.conn
+                    .send_background(FuseOpcode::Forget, self.nodeid, &[forget_in.as_bytes()]);
+        }
+
+        if let Some(fs) = self.fs.upgrade() {
+            fs.remove_inode(self.nodeid);
+        }
+    }
+}
+
+/// Invalidates the range of the page cache without writing back the dirty pages.
+fn invalidate_pages(page_cache: &PageCache, range: core::ops::Range<usize>) -> Result<()> {
+    page_cache.discard_range(range.clone());
+    page_cache.pages().decommit(range)
+}
+
+/// Parses the fixed-size payload of a reply.
+pub(super) fn parse_reply<T: Pod>(payload: &[u8]) -> Result<T> {
+    if payload.len() < size_of::<T>() {
+        return_errno_with_message!(Errno::EIO, "the reply is too short");
+    }
+    Ok(T::from_bytes(payload))
+}
+
+fn valid_until(secs: u64, nsecs: u32) -> Duration {
+    let timeout = Duration::from_secs(secs).saturating_add(Duration::from_nanos(nsecs as u64));
+    MonotonicCoarseClock::get()
+        .read_time()
+        .saturating_add(timeout)
+}
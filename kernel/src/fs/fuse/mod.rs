@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! FUSE, which forwards the file system operations to a userspace daemon.
+//!
+//! The daemon opens `/dev/fuse`, mounts a `fuse` (or `fuse.<subtype>`) file system with the
+//! file descriptor in the mount options, and then serves the requests read from the device.
+//!
+//! A `virtiofs` file system is served by a daemon on the host instead, and the requests are
+//! delivered through the virtio-fs device whose tag is the mount source.
+
+mod abi;
+mod conn;
+mod dev;
+mod file;
+mod fs;
+mod inode;
+mod virtio_fs;
+
+use alloc::sync::Arc;
+
+pub use dev::FuseDevice;
+pub use fs::{FuseFS, FuseMountOptions};
+pub use inode::FuseInode;
+
+use crate::fs::fuse::{fs::FuseType, virtio_fs::VirtioFsType};
+
+pub(super) fn init() {
+    let fuse_type = Arc::new(FuseType);
+    super::registry::register(fuse_type).unwrap();
+    let virtio_fs_type = Arc::new(VirtioFsType);
+    super::registry::register(virtio_fs_type).unwrap();
+}
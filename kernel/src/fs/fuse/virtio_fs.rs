@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_block::BlockDevice;
+use aster_virtio::device::filesystem::{device::FileSystemDevice, get_device};
+
+use super::{
+    conn::{FuseConn, FuseTransport},
+    fs::{FuseFS, FuseMountOptions},
+};
+use crate::{
+    fs::{
+        registry::{FsProperties, FsType},
+        utils::FileSystem,
+    },
+    prelude::*,
+};
+
+impl FuseTransport for FileSystemDevice {
+    fn send_request(&self, request: &[u8], reply: &mut [u8]) -> Result<usize> {
+        Ok(FileSystemDevice::send_request(self, request, reply)?)
+    }
+
+    fn send_oneway(&self, request: &[u8]) -> Result<()> {
+        Ok(FileSystemDevice::send_oneway(self, request)?)
+    }
+}
+
+/// The file systems mounted from the virtio-fs devices, indexed by the tags.
+///
+/// Like Linux, mounting the same device again shares the file system, since the daemon
+/// serves only one file system for each device. The shared file system cannot be mounted
+/// with different options.
+static MOUNTED_FS: Mutex<BTreeMap<String, Weak<FuseFS>>> = Mutex::new(BTreeMap::new());
+
+pub(super) struct VirtioFsType;
+
+impl FsType for VirtioFsType {
+    fn name(&self) -> &'static str {
+        "virtiofs"
+    }
+
+    fn create(
+        &self,
+        source: &CStr,
+        args: Option<CString>,
+        _disk: Option<Arc<dyn BlockDevice>>,
+        _ctx: &Context,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let tag = source
+            .to_str()
+            .map_err(|_| Error::with_message(Errno::EINVAL, "the tag is not UTF-8"))?;
+        let Some(device) = get_device(tag) else {
+            return_errno_with_message!(Errno::ENOENT, "no virtio-fs device has the tag")
+        };
+        let mount_options = FuseMountOptions::parse(args)?;
+
+        let mut mounted_fs = MOUNTED_FS.lock();
+        if let Some(fs) = mounted_fs.get(tag).and_then(Weak::upgrade) {
+            if *fs.mount_options() != mount_options {
+                return_errno_with_message!(
+                    Errno::EBUSY,
+                    "the device is already mounted with different options"
+                );
+            }
+            return Ok(fs);
+        }
+
+        let fs = FuseFS::new(FuseConn::with_transport(device), mount_options)?;
+        mounted_fs.insert(tag.to_string(), Arc::downgrade(&fs));
+        Ok(fs)
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysBranchNode>> {
+        None
+    }
+}
@@ -26,7 +26,7 @@ impl InodeHandle<Rights> {
         let file_io = if let Some(device) = inode.as_device() {
             device.open()?
         } else {
-            None
+            inode.open(access_mode, status_flags).transpose()?
         };
 
         let inner = Arc::new(InodeHandle_ {
@@ -36,6 +36,9 @@ impl InodeHandle<Rights> {
             access_mode,
             status_flags: AtomicU32::new(status_flags.bits()),
         });
+        if !status_flags.contains(StatusFlags::O_PATH) {
+            inner.path.notify_events(FsEvents::OPEN);
+        }
         Ok(Self(inner, Rights::from(access_mode)))
     }
 
@@ -16,6 +16,7 @@ use crate::{
     events::IoEvents,
     fs::{
         file_handle::FileLike,
+        notify::FsEvents,
         path::Path,
         utils::{
             AccessMode, DirentVisitor, FallocMode, FileRange, FlockItem, FlockList, Inode,
@@ -47,10 +48,10 @@ struct InodeHandle_ {
 impl InodeHandle_ {
     pub fn read(&self, writer: &mut VmWriter) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            return file_io.read(writer);
-        }
-
-        if !self.path.inode().is_seekable() {
+            if !file_io.is_offset_aware() {
+                return file_io.read(writer, self.status_flags());
+            }
+        } else if !self.path.inode().is_seekable() {
             return self.read_at(0, writer);
         }
 
@@ -64,10 +65,10 @@ impl InodeHandle_ {
 
     pub fn write(&self, reader: &mut VmReader) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            return file_io.write(reader);
-        }
-
-        if !self.path.inode().is_seekable() {
+            if !file_io.is_offset_aware() {
+                return file_io.write(reader, self.status_flags());
+            }
+        } else if !self.path.inode().is_seekable() {
             return self.write_at(0, reader);
         }
 
@@ -85,19 +86,24 @@ impl InodeHandle_ {
 
     pub fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            todo!("support read_at for FileIo");
+            return file_io.read_at(offset, writer, self.status_flags());
         }
 
-        if self.status_flags().contains(StatusFlags::O_DIRECT) {
-            self.path.inode().read_direct_at(offset, writer)
+        let len = if self.status_flags().contains(StatusFlags::O_DIRECT) {
+            self.path.inode().read_direct_at(offset, writer)?
         } else {
-            self.path.inode().read_at(offset, writer)
+            self.path.inode().read_at(offset, writer)?
+        };
+
+        if len > 0 {
+            self.path.notify_events(FsEvents::ACCESS);
         }
+        Ok(len)
     }
 
     pub fn write_at(&self, mut offset: usize, reader: &mut VmReader) -> Result<usize> {
         if let Some(ref file_io) = self.file_io {
-            todo!("support write_at for FileIo");
+            return file_io.write_at(offset, reader, self.status_flags());
         }
 
         let status_flags = self.status_flags();
@@ -106,11 +112,16 @@ impl InodeHandle_ {
             offset = self.path.size();
         }
 
-        if status_flags.contains(StatusFlags::O_DIRECT) {
-            self.path.inode().write_direct_at(offset, reader)
+        let len = if status_flags.contains(StatusFlags::O_DIRECT) {
+            self.path.inode().write_direct_at(offset, reader)?
         } else {
-            self.path.inode().write_at(offset, reader)
+            self.path.inode().write_at(offset, reader)?
+        };
+
+        if len > 0 {
+            self.path.notify_events(FsEvents::MODIFY);
         }
+        Ok(len)
     }
 
     pub fn seek(&self, pos: SeekFrom) -> Result<usize> {
@@ -123,7 +134,9 @@ impl InodeHandle_ {
     }
 
     pub fn resize(&self, new_size: usize) -> Result<()> {
-        do_resize_util(self.path.inode(), self.status_flags(), new_size)
+        do_resize_util(self.path.inode(), self.status_flags(), new_size)?;
+        self.path.notify_events(FsEvents::MODIFY);
+        Ok(())
     }
 
     pub fn access_mode(&self) -> AccessMode {
@@ -156,7 +169,9 @@ impl InodeHandle_ {
     }
 
     fn fallocate(&self, mode: FallocMode, offset: usize, len: usize) -> Result<()> {
-        do_fallocate_util(self.path.inode(), self.status_flags(), mode, offset, len)
+        do_fallocate_util(self.path.inode(), self.status_flags(), mode, offset, len)?;
+        self.path.notify_events(FsEvents::MODIFY);
+        Ok(())
     }
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
@@ -282,6 +297,21 @@ impl InodeHandle_ {
     pub fn set_group(&self, gid: Gid) -> Result<()>;
 }
 
+impl Drop for InodeHandle_ {
+    fn drop(&mut self) {
+        if self.status_flags().contains(StatusFlags::O_PATH) {
+            return;
+        }
+
+        let events = if self.access_mode.is_writable() {
+            FsEvents::CLOSE_WRITE
+        } else {
+            FsEvents::CLOSE_NOWRITE
+        };
+        self.path.notify_events(events);
+    }
+}
+
 impl Debug for InodeHandle_ {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         f.debug_struct("InodeHandle_")
@@ -299,6 +329,11 @@ impl<R> InodeHandle<R> {
         &self.0.path
     }
 
+    /// Returns the file I/O object that serves the opened device, if any.
+    pub fn file_io(&self) -> Option<&Arc<dyn FileIo>> {
+        self.0.file_io.as_ref()
+    }
+
     pub fn test_range_lock(&self, lock: RangeLockItem) -> Result<RangeLockItem> {
         self.0.test_range_lock(lock)
     }
@@ -331,16 +366,48 @@ impl<R> Drop for InodeHandle<R> {
     }
 }
 
-pub trait FileIo: Pollable + Send + Sync + 'static {
-    fn read(&self, writer: &mut VmWriter) -> Result<usize>;
+pub trait FileIo: Pollable + Send + Sync + Any {
+    fn read(&self, writer: &mut VmWriter, status_flags: StatusFlags) -> Result<usize>;
 
-    fn write(&self, reader: &mut VmReader) -> Result<usize>;
+    fn write(&self, reader: &mut VmReader, status_flags: StatusFlags) -> Result<usize>;
+
+    /// Returns whether the file is accessed at the file offsets.
+    ///
+    /// If so, reading or writing the file goes through [`Self::read_at`] or [`Self::write_at`]
+    /// with the file offset, instead of [`Self::read`] or [`Self::write`].
+    fn is_offset_aware(&self) -> bool {
+        false
+    }
+
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::ESPIPE, "the file cannot be read at an offset");
+    }
+
+    fn write_at(
+        &self,
+        offset: usize,
+        reader: &mut VmReader,
+        status_flags: StatusFlags,
+    ) -> Result<usize> {
+        return_errno_with_message!(Errno::ESPIPE, "the file cannot be written at an offset");
+    }
 
     fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
         return_errno_with_message!(Errno::EINVAL, "ioctl is not supported");
     }
 }
 
+impl dyn FileIo {
+    pub fn downcast_ref<T: FileIo>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+}
+
 pub fn do_seek_util(inode: &Arc<dyn Inode>, offset: &Mutex<usize>, pos: SeekFrom) -> Result<usize> {
     let mut offset = offset.lock();
     let new_offset: isize = match pos {
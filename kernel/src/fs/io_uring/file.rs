@@ -0,0 +1,546 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use core::sync::atomic::{fence, AtomicU64, Ordering};
+
+use aster_rights::Rights;
+use ostd::mm::{UFrame, VmIo, VmIoOnce};
+
+use super::{
+    request::{Completion, Opcode, Request, SqeFlags},
+    ring::{
+        Cqe, IoUringFeatures, IoUringParams, IoUringSetupFlags, RingLayout, SqRingFlags, Sqe,
+        IORING_MAX_CQ_ENTRIES, IORING_MAX_ENTRIES, IORING_OFF_CQ_RING, IORING_OFF_SQES,
+        IORING_OFF_SQ_RING,
+    },
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        file_table::FileDesc,
+        utils::{InodeMode, InodeType, Metadata},
+    },
+    prelude::*,
+    process::{
+        signal::{PollHandle, Pollable, Pollee},
+        Gid, Uid,
+    },
+    thread::work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+    time::clocks::RealTimeClock,
+    vm::vmo::{CommitFlags, Vmo, VmoFlags, VmoOptions},
+};
+
+/// A file-like object that represents an io_uring instance.
+///
+/// The SQ and the CQ live in VMOs that are mapped into user space via `mmap`. User space produces
+/// SQEs and consumes CQEs directly in the shared memory, while the kernel consumes the SQEs in
+/// `io_uring_enter` and produces the CQEs when the requests are completed, which may happen in
+/// kernel worker threads.
+///
+/// Like Linux, the heads and the tails are loaded with the acquire ordering and stored with the
+/// release ordering, so neither side accesses the SQEs or the CQEs before the other side finishes
+/// with them.
+pub struct IoUringFile {
+    layout: RingLayout,
+    /// The ring region.
+    ring_vmo: Vmo,
+    /// The first page of the ring region, which contains the heads and the tails.
+    ///
+    /// The heads and the tails are accessed with single loads and stores via the frame.
+    ring_frame: UFrame,
+    /// The SQE region.
+    sqes_vmo: Vmo,
+    /// The SQ head, which is only written by the kernel.
+    ///
+    /// The lock also serializes the submitters.
+    sq_head: Mutex<u32>,
+    cq: Mutex<CqState>,
+    /// The requests that have been submitted but not completed.
+    inflight: Mutex<BTreeMap<u64, Arc<Request>>>,
+    next_request_id: AtomicU64,
+    /// The registered files, or `None` if no files are registered.
+    fixed_files: Mutex<Option<Vec<Option<Arc<dyn FileLike>>>>>,
+    pollee: Pollee,
+    weak_self: Weak<Self>,
+}
+
+struct CqState {
+    /// The CQ tail, which is only written by the kernel.
+    tail: u32,
+    /// The number of completed requests, excluding timeout requests.
+    num_completed: u64,
+    /// The timeout requests that wait for completions, in the form of `(target, request_id)`.
+    ///
+    /// A timeout request is completed when `num_completed` reaches `target`.
+    count_timeouts: Vec<(u64, u64)>,
+    /// The CQEs that cannot be posted because the CQ is full.
+    ///
+    /// They are posted in order once user space consumes the CQEs. Like Linux, no new SQEs are
+    /// accepted until the overflowed CQEs are posted, so normally no CQEs are dropped (i.e.,
+    /// `IORING_FEAT_NODROP`). However, the number of the overflowed CQEs is still limited by
+    /// the number of CQ entries, beyond which the CQEs are dropped and counted in the ring.
+    overflow: VecDeque<Cqe>,
+}
+
+impl IoUringFile {
+    /// Creates a new io_uring instance with at least `entries` SQ entries.
+    ///
+    /// On success, the output fields of `params` are filled.
+    pub fn new(entries: u32, params: &mut IoUringParams) -> Result<Arc<Self>> {
+        let Some(flags) = IoUringSetupFlags::from_bits(params.flags) else {
+            return_errno_with_message!(Errno::EINVAL, "invalid io_uring setup flags");
+        };
+        if flags.intersects(
+            IoUringSetupFlags::IOPOLL | IoUringSetupFlags::SQPOLL | IoUringSetupFlags::SQ_AFF,
+        ) {
+            return_errno_with_message!(Errno::EINVAL, "polled io_uring is not supported");
+        }
+        if params.resv.iter().any(|resv| *resv != 0) {
+            return_errno_with_message!(Errno::EINVAL, "the reserved fields are not zero");
+        }
+
+        let is_clamped = flags.contains(IoUringSetupFlags::CLAMP);
+
+        if entries == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the number of entries cannot be zero");
+        }
+        let sq_entries = if entries <= IORING_MAX_ENTRIES {
+            entries.next_power_of_two()
+        } else if is_clamped {
+            IORING_MAX_ENTRIES
+        } else {
+            return_errno_with_message!(Errno::EINVAL, "the number of entries is too large");
+        };
+
+        let cq_entries = if flags.contains(IoUringSetupFlags::CQSIZE) {
+            let cq_entries = params.cq_entries;
+            if cq_entries == 0 {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the number of CQ entries cannot be zero"
+                );
+            }
+            let cq_entries = if cq_entries <= IORING_MAX_CQ_ENTRIES {
+                cq_entries.next_power_of_two()
+            } else if is_clamped {
+                IORING_MAX_CQ_ENTRIES
+            } else {
+                return_errno_with_message!(Errno::EINVAL, "the number of CQ entries is too large");
+            };
+            if cq_entries < sq_entries {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the number of CQ entries is less than that of SQ entries"
+                );
+            }
+            cq_entries
+        } else {
+            2 * sq_entries
+        };
+
+        let layout = RingLayout::new(sq_entries, cq_entries);
+
+        // The VMOs are contiguous so that their pages are committed all the time.
+        let ring_vmo = VmoOptions::<Rights>::new(layout.ring_size())
+            .flags(VmoFlags::CONTIGUOUS)
+            .alloc()?;
+        let sqes_vmo = VmoOptions::<Rights>::new(layout.sqes_size())
+            .flags(VmoFlags::CONTIGUOUS)
+            .alloc()?;
+        for (offset, value) in layout.initial_fields() {
+            ring_vmo.write_val(offset, &value)?;
+        }
+        let ring_frame = ring_vmo.commit_on(0, CommitFlags::empty())?;
+
+        params.sq_entries = sq_entries;
+        params.cq_entries = cq_entries;
+        params.features = (IoUringFeatures::SINGLE_MMAP
+            | IoUringFeatures::NODROP
+            | IoUringFeatures::SUBMIT_STABLE
+            | IoUringFeatures::RW_CUR_POS)
+            .bits();
+        params.sq_off = layout.sq_offsets();
+        params.cq_off = layout.cq_offsets();
+
+        Ok(Arc::new_cyclic(|weak_self| Self {
+            layout,
+            ring_vmo,
+            ring_frame,
+            sqes_vmo,
+            sq_head: Mutex::new(0),
+            cq: Mutex::new(CqState {
+                tail: 0,
+                num_completed: 0,
+                count_timeouts: Vec::new(),
+                overflow: VecDeque::new(),
+            }),
+            inflight: Mutex::new(BTreeMap::new()),
+            next_request_id: AtomicU64::new(0),
+            fixed_files: Mutex::new(None),
+            pollee: Pollee::new(),
+            weak_self: weak_self.clone(),
+        }))
+    }
+
+    /// Submits at most `to_submit` SQEs.
+    ///
+    /// This method returns the number of the submitted SQEs. Note that an SQE is still considered
+    /// submitted if the request fails to be prepared, in which case a CQE is posted with the error
+    /// code.
+    pub fn submit(&self, to_submit: u32, ctx: &Context) -> Result<u32> {
+        let mut sq_head = self.sq_head.lock();
+
+        {
+            let mut cq = self.cq.lock();
+            self.flush_overflow(&mut cq)?;
+            if !cq.overflow.is_empty() {
+                return_errno_with_message!(
+                    Errno::EBUSY,
+                    "the CQ has overflowed and the CQEs must be consumed first"
+                );
+            }
+        }
+
+        let sq_tail = self.load_acquire(self.layout.sq_tail())?;
+        let num_pending = sq_tail.wrapping_sub(*sq_head).min(self.layout.sq_entries());
+
+        let mut num_submitted = 0;
+        let mut num_dropped = 0;
+        for _ in 0..to_submit.min(num_pending) {
+            let sqe_idx: u32 = self.ring_vmo.read_val(self.layout.sq_array_at(*sq_head))?;
+            *sq_head = sq_head.wrapping_add(1);
+
+            if sqe_idx >= self.layout.sq_entries() {
+                num_dropped += 1;
+                continue;
+            }
+            let sqe: Sqe = self.sqes_vmo.read_val(self.layout.sqe_at(sqe_idx))?;
+
+            if let Err(err) = self.submit_sqe(&sqe, ctx) {
+                self.post_completion(sqe.user_data, Completion::Normal(-(err.error() as i32)));
+            }
+            num_submitted += 1;
+        }
+
+        self.store_release(self.layout.sq_head(), *sq_head)?;
+        if num_dropped > 0 {
+            let sq_dropped: u32 = self.ring_vmo.read_val(self.layout.sq_dropped())?;
+            self.ring_vmo.write_val(
+                self.layout.sq_dropped(),
+                &sq_dropped.wrapping_add(num_dropped),
+            )?;
+        }
+
+        Ok(num_submitted)
+    }
+
+    fn submit_sqe(&self, sqe: &Sqe, ctx: &Context) -> Result<()> {
+        let flags = SqeFlags::from_bits(sqe.flags)
+            .filter(|flags| (*flags - (SqeFlags::FIXED_FILE | SqeFlags::ASYNC)).is_empty())
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "unsupported SQE flags"))?;
+        if sqe.ioprio != 0 || sqe.buf_index != 0 || sqe.personality != 0 {
+            return_errno_with_message!(Errno::EINVAL, "unsupported SQE fields");
+        }
+
+        let opcode = Opcode::try_from(sqe.opcode)?;
+        let file = match opcode {
+            Opcode::Nop | Opcode::Timeout => None,
+            _ => Some(self.get_file(sqe.fd, flags, ctx)?),
+        };
+
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let work_item = {
+            let weak_self = self.weak_self.clone();
+            WorkItem::new(Box::new(move || {
+                if let Some(io_uring) = weak_self.upgrade() {
+                    io_uring.run_request(request_id);
+                }
+            }))
+        };
+        let request = Request::prepare(sqe, opcode, file, work_item.clone(), ctx)?;
+
+        // Register the timeout request before it can be completed.
+        if let Some(count) = request.timeout_count() {
+            let mut cq = self.cq.lock();
+            let target = cq.num_completed + count;
+            cq.count_timeouts.push((target, request_id));
+        }
+        // Like Linux, the requests that may block are executed in worker threads, since the
+        // submitter should not block.
+        let is_async = flags.contains(SqeFlags::ASYNC) || request.may_block();
+        self.inflight.lock().insert(request_id, request);
+
+        if is_async {
+            submit_work_item(work_item, WorkPriority::Normal);
+        } else {
+            self.run_request(request_id);
+        }
+
+        Ok(())
+    }
+
+    fn get_file(&self, fd: FileDesc, flags: SqeFlags, ctx: &Context) -> Result<Arc<dyn FileLike>> {
+        if !flags.contains(SqeFlags::FIXED_FILE) {
+            let file_table = ctx.thread_local.borrow_file_table();
+            return Ok(file_table.unwrap().read().get_file(fd)?.clone());
+        }
+
+        let fixed_files = self.fixed_files.lock();
+        fixed_files
+            .as_ref()
+            .and_then(|files| files.get(usize::try_from(fd).ok()?))
+            .and_then(Option::clone)
+            .ok_or_else(|| Error::with_message(Errno::EBADF, "the fixed file does not exist"))
+    }
+
+    fn run_request(&self, request_id: u64) {
+        let Some(request) = self.inflight.lock().get(&request_id).cloned() else {
+            return;
+        };
+
+        request.run(|request, completion| {
+            self.inflight.lock().remove(&request_id);
+
+            if matches!(completion, Completion::Timeout(_)) {
+                let mut cq = self.cq.lock();
+                cq.count_timeouts.retain(|(_, id)| *id != request_id);
+            }
+
+            self.post_completion(request.user_data(), completion);
+        });
+    }
+
+    /// Posts a CQE for the completed request.
+    fn post_completion(&self, user_data: u64, completion: Completion) {
+        let mut cq = self.cq.lock();
+
+        let res = match completion {
+            Completion::Normal(res) => {
+                cq.num_completed += 1;
+                res
+            }
+            Completion::Timeout(res) => res,
+        };
+        let cqe = Cqe {
+            user_data,
+            res,
+            flags: 0,
+        };
+        if let Err(err) = self.post_cqe(&mut cq, cqe) {
+            warn!("failed to post the CQE: {:?}", err);
+        }
+
+        // Collect the timeout requests that have enough completions.
+        let num_completed = cq.num_completed;
+        let mut reached_ids = Vec::new();
+        cq.count_timeouts.retain(|(target, id)| {
+            if *target <= num_completed {
+                reached_ids.push(*id);
+                false
+            } else {
+                true
+            }
+        });
+        drop(cq);
+
+        for id in reached_ids {
+            if let Some(request) = self.inflight.lock().get(&id).cloned() {
+                request.notify_count_reached();
+            }
+        }
+
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    /// Posts a CQE, or buffers it if the CQ is full.
+    fn post_cqe(&self, cq: &mut CqState, cqe: Cqe) -> Result<()> {
+        // The CQEs must be posted in order, so the overflowed CQEs are posted first.
+        self.flush_overflow(cq)?;
+        if cq.overflow.is_empty() && self.write_cqe(cq, &cqe)? {
+            return Ok(());
+        }
+
+        if cq.overflow.len() >= self.layout.cq_entries() as usize {
+            let cq_overflow: u32 = self.ring_frame.read_once(self.layout.cq_overflow())?;
+            self.ring_frame
+                .write_once(self.layout.cq_overflow(), &cq_overflow.wrapping_add(1))?;
+            return Ok(());
+        }
+
+        cq.overflow.push_back(cqe);
+        self.update_sq_flags(cq)
+    }
+
+    /// Posts the overflowed CQEs as many as possible.
+    fn flush_overflow(&self, cq: &mut CqState) -> Result<()> {
+        if cq.overflow.is_empty() {
+            return Ok(());
+        }
+
+        while let Some(cqe) = cq.overflow.front() {
+            if !self.write_cqe(cq, cqe)? {
+                break;
+            }
+            cq.overflow.pop_front();
+        }
+        self.update_sq_flags(cq)
+    }
+
+    /// Writes a CQE to the CQ.
+    ///
+    /// This method returns `false` if the CQ is full.
+    fn write_cqe(&self, cq: &mut CqState, cqe: &Cqe) -> Result<bool> {
+        let cq_head = self.load_acquire(self.layout.cq_head())?;
+        if cq.tail.wrapping_sub(cq_head) >= self.layout.cq_entries() {
+            return Ok(false);
+        }
+
+        self.ring_vmo.write_val(self.layout.cqe_at(cq.tail), cqe)?;
+        cq.tail = cq.tail.wrapping_add(1);
+        self.store_release(self.layout.cq_tail(), cq.tail)?;
+
+        Ok(true)
+    }
+
+    /// Tells user space whether there are overflowed CQEs.
+    fn update_sq_flags(&self, cq: &CqState) -> Result<()> {
+        let flags = if cq.overflow.is_empty() {
+            SqRingFlags::empty()
+        } else {
+            SqRingFlags::CQ_OVERFLOW
+        };
+        self.ring_frame
+            .write_once(self.layout.sq_flags(), &flags.bits())?;
+        Ok(())
+    }
+
+    /// Loads a head or a tail in the ring region with the acquire ordering.
+    fn load_acquire(&self, offset: usize) -> Result<u32> {
+        let value = self.ring_frame.read_once(offset)?;
+        fence(Ordering::Acquire);
+        Ok(value)
+    }
+
+    /// Stores a head or a tail in the ring region with the release ordering.
+    fn store_release(&self, offset: usize, value: u32) -> Result<()> {
+        fence(Ordering::Release);
+        self.ring_frame.write_once(offset, &value)?;
+        Ok(())
+    }
+
+    /// Waits until there are at least `min_complete` CQEs in the CQ.
+    pub fn wait_completions(&self, min_complete: u32) -> Result<()> {
+        let min_complete = min_complete.min(self.layout.cq_entries());
+
+        self.wait_events(IoEvents::IN, None, || {
+            if self.num_cqes()? >= min_complete {
+                Ok(())
+            } else {
+                return_errno_with_message!(Errno::EAGAIN, "there are not enough CQEs");
+            }
+        })
+    }
+
+    fn num_cqes(&self) -> Result<u32> {
+        let mut cq = self.cq.lock();
+        // User space may have consumed some CQEs, so there may be room for the overflowed ones.
+        self.flush_overflow(&mut cq)?;
+        let cq_head = self.load_acquire(self.layout.cq_head())?;
+        Ok(cq.tail.wrapping_sub(cq_head))
+    }
+
+    /// Registers the files so that they can be referred to by their indexes in the SQEs.
+    ///
+    /// The file can be `None`, which results in a sparse slot.
+    pub fn register_files(&self, files: Vec<Option<Arc<dyn FileLike>>>) -> Result<()> {
+        let mut fixed_files = self.fixed_files.lock();
+        if fixed_files.is_some() {
+            return_errno_with_message!(Errno::EBUSY, "the files are already registered");
+        }
+
+        *fixed_files = Some(files);
+        Ok(())
+    }
+
+    /// Unregisters the registered files.
+    pub fn unregister_files(&self) -> Result<()> {
+        if self.fixed_files.lock().take().is_none() {
+            return_errno_with_message!(Errno::ENXIO, "no files are registered");
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the operation is supported.
+    pub fn is_op_supported(op: u8) -> bool {
+        Opcode::try_from(op).is_ok()
+    }
+
+    /// Returns the number of the operations known by Linux.
+    pub fn num_ops() -> u8 {
+        Opcode::NUM_LINUX_OPS
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let mut events = IoEvents::empty();
+
+        if self.num_cqes().is_ok_and(|num_cqes| num_cqes > 0) {
+            events |= IoEvents::IN;
+        }
+
+        let sq_head = *self.sq_head.lock();
+        if let Ok(sq_tail) = self.load_acquire(self.layout.sq_tail()) {
+            if sq_tail.wrapping_sub(sq_head) < self.layout.sq_entries() {
+                events |= IoEvents::OUT;
+            }
+        }
+
+        events
+    }
+}
+
+impl Pollable for IoUringFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        // User space consumes the CQEs and produces the SQEs without notifying us, so the cached
+        // events can be out of date.
+        self.pollee.invalidate();
+
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl FileLike for IoUringFile {
+    fn mmap_vmo(&self, offset: usize) -> Result<Option<Vmo>> {
+        let vmo = match offset {
+            IORING_OFF_SQ_RING | IORING_OFF_CQ_RING => &self.ring_vmo,
+            IORING_OFF_SQES => &self.sqes_vmo,
+            _ => return_errno_with_message!(Errno::EINVAL, "invalid io_uring mmap offset"),
+        };
+
+        Ok(Some(vmo.dup()?))
+    }
+
+    fn metadata(&self) -> Metadata {
+        // This is a dummy implementation.
+        // TODO: Add "anonymous inode fs" and link the file to it.
+        let now = RealTimeClock::get().read_time();
+        Metadata {
+            dev: 0,
+            ino: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            type_: InodeType::NamedPipe,
+            mode: InodeMode::from_bits_truncate(0o600),
+            nlinks: 1,
+            uid: Uid::new_root(),
+            gid: Gid::new_root(),
+            rdev: 0,
+        }
+    }
+}
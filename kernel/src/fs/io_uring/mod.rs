@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The io_uring asynchronous I/O interface.
+//!
+//! An io_uring instance consists of a submission queue (SQ) and a completion queue (CQ), which
+//! are shared between user space and the kernel. User space submits I/O requests by producing SQ
+//! entries (SQEs) and calling `io_uring_enter`, and it reaps the results by consuming CQ entries
+//! (CQEs).
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/io_uring.7.html>
+
+mod file;
+mod request;
+mod ring;
+
+pub use file::IoUringFile;
+pub use ring::IoUringParams;
@@ -0,0 +1,789 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! I/O requests of io_uring instances.
+//!
+//! A request is prepared from an SQE when it is submitted. All the information that is needed to
+//! execute the request (e.g., the iovecs and the data to write) is copied into the kernel at this
+//! time, so user space can reuse the SQE and the referenced memory immediately after submission,
+//! except for the buffers that will receive data.
+//!
+//! The request is then executed without blocking, or in a kernel worker thread if it may block
+//! anyway (e.g., I/O on regular files). If it cannot be completed immediately, the request is
+//! armed, i.e., it starts to monitor the events of the file or starts its timer. The request is
+//! executed again in a kernel worker thread when the events happen. Since worker threads do not
+//! have the user space of the submitter, the user memory is accessed via [`Vmar::read_remote`]
+//! and [`Vmar::write_remote`].
+
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use aster_rights::Full;
+
+use super::ring::Sqe;
+use crate::{
+    events::{IoEvents, Observer},
+    fs::{
+        file_handle::FileLike,
+        file_table::FdFlags,
+        utils::{CreationFlags, InodeType, SeekFrom, StatusFlags},
+    },
+    net::socket::util::{MessageHeader, SendRecvFlags},
+    prelude::*,
+    process::{
+        posix_thread::{thread_table, AsPosixThread},
+        signal::PollAdaptor,
+        Process,
+    },
+    thread::{
+        work_queue::{submit_work_item, work_item::WorkItem, WorkPriority},
+        Tid,
+    },
+    time::{
+        clocks::MonotonicClock,
+        timer::{Timeout, Timer},
+        timespec_t,
+    },
+    util::net::write_socket_addr_with,
+    vm::vmar::Vmar,
+};
+
+/// The opcodes of the supported operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+#[repr(u8)]
+pub(super) enum Opcode {
+    Nop = 0,
+    Readv = 1,
+    Writev = 2,
+    Fsync = 3,
+    PollAdd = 6,
+    Timeout = 11,
+    Accept = 13,
+    Read = 22,
+    Write = 23,
+    Send = 26,
+    Recv = 27,
+}
+
+impl Opcode {
+    /// The number of the opcodes known by Linux, which is reported by `IORING_REGISTER_PROBE`.
+    pub(super) const NUM_LINUX_OPS: u8 = 58;
+}
+
+bitflags! {
+    /// The flags of SQEs.
+    pub(super) struct SqeFlags: u8 {
+        const FIXED_FILE    = 1 << 0;
+        const IO_DRAIN      = 1 << 1;
+        const IO_LINK       = 1 << 2;
+        const IO_HARDLINK   = 1 << 3;
+        const ASYNC         = 1 << 4;
+        const BUFFER_SELECT = 1 << 5;
+        const CQE_SKIP_SUCCESS = 1 << 6;
+    }
+}
+
+bitflags! {
+    /// The flags of timeout requests.
+    struct TimeoutFlags: u32 {
+        const ABS = 1 << 0;
+    }
+}
+
+/// The maximum number of iovecs in a request.
+const IOV_MAX: usize = 1024;
+
+/// The maximum number of bytes that a request reads or writes.
+///
+/// The data is copied via a kernel buffer, whose size is limited. Longer requests result in short
+/// reads or writes.
+const MAX_BUF_LEN: usize = 1 << 20;
+
+/// An I/O request.
+pub(super) struct Request {
+    user_data: u64,
+    op: Op,
+    /// The process that submits the request.
+    ///
+    /// The user memory referenced by the request belongs to this process.
+    process: Weak<Process>,
+    state: Mutex<RequestState>,
+    work_item: Arc<WorkItem>,
+}
+
+#[derive(Default)]
+struct RequestState {
+    is_completed: bool,
+    is_armed: bool,
+    poller: Option<PollAdaptor<RequestObserver>>,
+}
+
+/// The result of a request.
+pub(super) enum Completion {
+    /// The result of a normal request.
+    Normal(i32),
+    /// The result of a timeout request.
+    ///
+    /// The completions of timeout requests are not counted by other timeout requests.
+    Timeout(i32),
+}
+
+impl Request {
+    /// Prepares a request from the SQE.
+    ///
+    /// `file` is the file specified in the SQE, if the operation needs one. `work_item` should
+    /// execute the request with [`Request::run`].
+    pub(super) fn prepare(
+        sqe: &Sqe,
+        opcode: Opcode,
+        file: Option<Arc<dyn FileLike>>,
+        work_item: Arc<WorkItem>,
+        ctx: &Context,
+    ) -> Result<Arc<Self>> {
+        let op = Op::prepare(sqe, opcode, file, &work_item, ctx)?;
+
+        Ok(Arc::new(Self {
+            user_data: sqe.user_data,
+            op,
+            process: ctx.posix_thread.weak_process(),
+            state: Mutex::new(RequestState::default()),
+            work_item,
+        }))
+    }
+
+    pub(super) fn user_data(&self) -> u64 {
+        self.user_data
+    }
+
+    /// Returns the number of completions to wait for, if the request is a timeout request.
+    pub(super) fn timeout_count(&self) -> Option<u64> {
+        match &self.op {
+            Op::Timeout(timeout) if timeout.count > 0 => Some(timeout.count),
+            _ => None,
+        }
+    }
+
+    /// Returns whether executing the request may block.
+    ///
+    /// Such requests should be executed in worker threads.
+    pub(super) fn may_block(&self) -> bool {
+        self.op.may_block()
+    }
+
+    /// Notifies the timeout request that enough requests have been completed.
+    pub(super) fn notify_count_reached(&self) {
+        if let Op::Timeout(timeout) = &self.op {
+            timeout.is_count_reached.store(true, Ordering::Relaxed);
+            submit_work_item(self.work_item.clone(), WorkPriority::Normal);
+        }
+    }
+
+    /// Executes the request.
+    ///
+    /// If the request is completed, `complete` is called with the result. Otherwise, the request
+    /// will be executed again in a worker thread when it may make progress.
+    pub(super) fn run<F>(&self, complete: F)
+    where
+        F: FnOnce(&Self, Completion),
+    {
+        let mut state = self.state.lock();
+        if state.is_completed {
+            return;
+        }
+
+        let res = loop {
+            match self.op.execute(&self.process) {
+                Err(err) if err.error() == Errno::EAGAIN => (),
+                res => break res,
+            }
+
+            if state.is_armed {
+                return;
+            }
+            state.is_armed = true;
+
+            // Try again after arming the request, since the events may have happened before
+            // arming.
+            if !self.arm(&mut state) {
+                return;
+            }
+        };
+
+        state.is_completed = true;
+        state.poller = None;
+        self.op.cancel();
+        drop(state);
+
+        let res = match res {
+            Ok(len) => len as i32,
+            Err(err) => -(err.error() as i32),
+        };
+        let completion = if matches!(self.op, Op::Timeout(_)) {
+            Completion::Timeout(res)
+        } else {
+            Completion::Normal(res)
+        };
+        complete(self, completion);
+    }
+
+    /// Arms the request so that it will be executed again when it may make progress.
+    ///
+    /// This method returns whether the request should be executed again immediately.
+    fn arm(&self, state: &mut RequestState) -> bool {
+        if let Op::Timeout(timeout) = &self.op {
+            timeout.timer.set_timeout(timeout.timeout.clone());
+            return false;
+        }
+
+        let Some(file) = self.op.file() else {
+            return false;
+        };
+        let mut poller = PollAdaptor::with_observer(RequestObserver(self.work_item.clone()));
+        let events = file.poll(self.op.events(), Some(poller.as_handle_mut()));
+        state.poller = Some(poller);
+
+        !events.is_empty()
+    }
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        // Dropping an uncompleted request (e.g., because the io_uring instance is closed) cancels
+        // it.
+        self.op.cancel();
+    }
+}
+
+/// An observer that executes the request again when events happen.
+struct RequestObserver(Arc<WorkItem>);
+
+impl Observer<IoEvents> for RequestObserver {
+    fn on_events(&self, _events: &IoEvents) {
+        submit_work_item(self.0.clone(), WorkPriority::Normal);
+    }
+}
+
+enum Op {
+    Nop,
+    Read(ReadOp),
+    Write(WriteOp),
+    Fsync {
+        file: Arc<dyn FileLike>,
+        is_datasync: bool,
+    },
+    PollAdd {
+        file: Arc<dyn FileLike>,
+        events: IoEvents,
+    },
+    Accept(AcceptOp),
+    Timeout(TimeoutOp),
+}
+
+/// A read operation (`IORING_OP_READ`, `IORING_OP_READV`, or `IORING_OP_RECV`).
+struct ReadOp {
+    file: Arc<dyn FileLike>,
+    /// The file offset, or `None` to use (and update) the current file position.
+    offset: Option<usize>,
+    /// The user buffers, in the form of `(addr, len)`.
+    bufs: Vec<(Vaddr, usize)>,
+    /// The flags of `IORING_OP_RECV`.
+    recv_flags: Option<SendRecvFlags>,
+}
+
+/// A write operation (`IORING_OP_WRITE`, `IORING_OP_WRITEV`, or `IORING_OP_SEND`).
+struct WriteOp {
+    file: Arc<dyn FileLike>,
+    /// The file offset, or `None` to use (and update) the current file position.
+    offset: Option<usize>,
+    /// The data to write, which is copied from user space when the request is submitted.
+    data: Vec<u8>,
+    /// The flags of `IORING_OP_SEND`.
+    send_flags: Option<SendRecvFlags>,
+}
+
+struct AcceptOp {
+    file: Arc<dyn FileLike>,
+    addr: Vaddr,
+    addrlen_addr: Vaddr,
+    is_nonblocking: bool,
+    fd_flags: FdFlags,
+    /// The thread that submits the request.
+    ///
+    /// The accepted socket is installed into the io_uring file table of the thread, which is
+    /// looked up when the request is executed (see `PosixThread::io_uring_file_table`).
+    tid: Tid,
+}
+
+struct TimeoutOp {
+    timer: Arc<Timer>,
+    timeout: Timeout,
+    /// The number of completions to wait for, or zero if there is no such limit.
+    count: u64,
+    is_expired: Arc<AtomicBool>,
+    is_count_reached: AtomicBool,
+}
+
+impl Op {
+    fn prepare(
+        sqe: &Sqe,
+        opcode: Opcode,
+        file: Option<Arc<dyn FileLike>>,
+        work_item: &Arc<WorkItem>,
+        ctx: &Context,
+    ) -> Result<Self> {
+        let Some(file) = file else {
+            return match opcode {
+                Opcode::Nop => Ok(Self::Nop),
+                Opcode::Timeout => Ok(Self::Timeout(TimeoutOp::prepare(sqe, work_item, ctx)?)),
+                _ => unreachable!("the operation requires a file"),
+            };
+        };
+
+        // `u64::MAX` means the current file position, which is supported with
+        // `IORING_FEAT_RW_CUR_POS`.
+        let offset = if sqe.off == u64::MAX {
+            None
+        } else if sqe.off > isize::MAX as u64 {
+            return_errno_with_message!(Errno::EINVAL, "the file offset is too large");
+        } else {
+            Some(sqe.off as usize)
+        };
+
+        let op = match opcode {
+            Opcode::Read | Opcode::Recv => {
+                let recv_flags = (opcode == Opcode::Recv)
+                    .then(|| SendRecvFlags::from_bits_truncate(sqe.op_flags as i32));
+                Self::Read(ReadOp {
+                    file,
+                    offset: offset.filter(|_| opcode == Opcode::Read),
+                    bufs: vec![(sqe.addr as Vaddr, sqe.len as usize)],
+                    recv_flags,
+                })
+            }
+            Opcode::Readv => Self::Read(ReadOp {
+                file,
+                offset,
+                bufs: read_iovecs(sqe.addr as Vaddr, sqe.len as usize, ctx)?,
+                recv_flags: None,
+            }),
+            Opcode::Write | Opcode::Send => {
+                let send_flags = (opcode == Opcode::Send)
+                    .then(|| SendRecvFlags::from_bits_truncate(sqe.op_flags as i32));
+                let bufs = [(sqe.addr as Vaddr, sqe.len as usize)];
+                Self::Write(WriteOp {
+                    file,
+                    offset: offset.filter(|_| opcode == Opcode::Write),
+                    data: gather_bufs(&bufs, ctx)?,
+                    send_flags,
+                })
+            }
+            Opcode::Writev => {
+                let bufs = read_iovecs(sqe.addr as Vaddr, sqe.len as usize, ctx)?;
+                Self::Write(WriteOp {
+                    file,
+                    offset,
+                    data: gather_bufs(&bufs, ctx)?,
+                    send_flags: None,
+                })
+            }
+            Opcode::Fsync => {
+                const IORING_FSYNC_DATASYNC: u32 = 1 << 0;
+
+                if sqe.op_flags & !IORING_FSYNC_DATASYNC != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "invalid fsync flags");
+                }
+                // The file must be related to an inode.
+                file.as_inode_or_err()?;
+                Self::Fsync {
+                    file,
+                    is_datasync: sqe.op_flags & IORING_FSYNC_DATASYNC != 0,
+                }
+            }
+            Opcode::PollAdd => {
+                if sqe.len != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "multi-shot polls are not supported");
+                }
+                Self::PollAdd {
+                    file,
+                    events: IoEvents::from_bits_truncate(sqe.op_flags) | IoEvents::ALWAYS_POLL,
+                }
+            }
+            Opcode::Accept => Self::Accept(AcceptOp::prepare(sqe, file, ctx)?),
+            Opcode::Nop | Opcode::Timeout => unreachable!("the operation requires no files"),
+        };
+
+        Ok(op)
+    }
+
+    /// Returns the file that the operation is performed on.
+    fn file(&self) -> Option<&Arc<dyn FileLike>> {
+        match self {
+            Self::Nop | Self::Timeout(_) => None,
+            Self::Read(ReadOp { file, .. })
+            | Self::Write(WriteOp { file, .. })
+            | Self::Fsync { file, .. }
+            | Self::PollAdd { file, .. }
+            | Self::Accept(AcceptOp { file, .. }) => Some(file),
+        }
+    }
+
+    /// Returns the events that indicate that the operation may make progress.
+    fn events(&self) -> IoEvents {
+        match self {
+            Self::Nop | Self::Fsync { .. } | Self::Timeout(_) => IoEvents::empty(),
+            Self::Read(_) | Self::Accept(_) => IoEvents::IN,
+            Self::Write(_) => IoEvents::OUT,
+            Self::PollAdd { events, .. } => *events,
+        }
+    }
+
+    /// Returns whether executing the operation may block even if the events are ready.
+    ///
+    /// The files that are always ready (e.g., regular files) may block when they wait for the
+    /// underlying device.
+    fn may_block(&self) -> bool {
+        match self {
+            Self::Fsync { .. } => true,
+            Self::Read(ReadOp { file, .. }) | Self::Write(WriteOp { file, .. }) => {
+                is_seekable(file)
+            }
+            Self::Nop | Self::PollAdd { .. } | Self::Accept(_) | Self::Timeout(_) => false,
+        }
+    }
+
+    /// Executes the operation without blocking.
+    ///
+    /// If the operation cannot be completed immediately, this method fails with
+    /// [`Errno::EAGAIN`].
+    fn execute(&self, process: &Weak<Process>) -> Result<usize> {
+        // Check the events first because the file may block otherwise.
+        //
+        // FIXME: The events can be consumed by others after the check, so the file may still
+        // block. Introduce a non-blocking flag for each I/O operation to avoid this.
+        if let Some(file) = self.file() {
+            let events = self.events();
+            if !events.is_empty() && file.poll(events, None).is_empty() {
+                return_errno_with_message!(Errno::EAGAIN, "the file is not ready");
+            }
+        }
+
+        match self {
+            Self::Nop => Ok(0),
+            Self::Read(read_op) => read_op.execute(process),
+            Self::Write(write_op) => write_op.execute(),
+            Self::Fsync { file, is_datasync } => {
+                let path = file.as_inode_or_err()?.path();
+                if *is_datasync {
+                    path.sync_data()?;
+                } else {
+                    path.sync_all()?;
+                }
+                Ok(0)
+            }
+            Self::PollAdd { file, events } => {
+                // The events are known to be non-empty after the above check.
+                Ok(file.poll(*events, None).bits() as usize)
+            }
+            Self::Accept(accept_op) => accept_op.execute(process),
+            Self::Timeout(timeout_op) => timeout_op.execute(),
+        }
+    }
+
+    /// Cancels the pending operation.
+    fn cancel(&self) {
+        if let Self::Timeout(timeout_op) = self {
+            timeout_op.timer.cancel();
+        }
+    }
+}
+
+impl ReadOp {
+    fn execute(&self, process: &Weak<Process>) -> Result<usize> {
+        let max_len = self
+            .bufs
+            .iter()
+            .map(|(_, len)| *len)
+            .fold(0usize, |total, len| total.saturating_add(len))
+            .min(MAX_BUF_LEN);
+
+        // Limit the read to the user buffers that can be written. Otherwise, the data would be
+        // consumed from the file but lost when copying it to the user buffers.
+        let len = with_vmar(process, |vmar| {
+            let mut len = 0;
+            for (addr, buf_len) in self.bufs.iter() {
+                if len >= max_len {
+                    break;
+                }
+                let buf_len = (*buf_len).min(max_len - len);
+                let faulted_len = vmar.fault_in_writable_remote(*addr, buf_len);
+                len += faulted_len;
+                if faulted_len < buf_len {
+                    break;
+                }
+            }
+            Ok(len)
+        })?;
+        if len == 0 && max_len > 0 {
+            return_errno_with_message!(Errno::EFAULT, "the user buffers are not writable");
+        }
+
+        let mut buf = vec![0u8; len];
+        let read_len = {
+            let mut writer = VmWriter::from(buf.as_mut_slice()).to_fallible();
+            if let Some(socket) = self.file.as_socket() {
+                let flags = self.recv_flags.unwrap_or(SendRecvFlags::empty());
+                socket
+                    .recvmsg(&mut writer, flags | SendRecvFlags::MSG_DONTWAIT)?
+                    .0
+            } else {
+                match self.offset {
+                    Some(offset) if is_seekable(&self.file) => {
+                        self.file.read_at(offset, &mut writer)?
+                    }
+                    _ => self.file.read(&mut writer)?,
+                }
+            }
+        };
+
+        // Scatter the data to the user buffers. The user buffers may be unmapped concurrently,
+        // in which case the partial progress is reported.
+        let copied_len = with_vmar(process, |vmar| {
+            let mut copied_len = 0;
+            for (addr, buf_len) in self.bufs.iter() {
+                if copied_len >= read_len {
+                    break;
+                }
+                let copy_len = (read_len - copied_len).min(*buf_len);
+                let mut reader =
+                    VmReader::from(&buf[copied_len..copied_len + copy_len]).to_fallible();
+                let written_len = vmar.write_remote_from(*addr, copy_len, &mut reader, false)?;
+                copied_len += written_len;
+                if written_len < copy_len {
+                    break;
+                }
+            }
+            Ok(copied_len)
+        })
+        .unwrap_or(0);
+
+        if copied_len < read_len
+            && self.file.as_socket().is_none()
+            && self.offset.is_none()
+            && is_seekable(&self.file)
+        {
+            // Give back the data that is not copied, as if it had never been read.
+            self.file
+                .seek(SeekFrom::Current(-((read_len - copied_len) as isize)))?;
+        }
+        if copied_len == 0 && read_len > 0 {
+            return_errno_with_message!(Errno::EFAULT, "the user buffers are not writable");
+        }
+
+        Ok(copied_len)
+    }
+}
+
+impl WriteOp {
+    fn execute(&self) -> Result<usize> {
+        let mut reader = VmReader::from(self.data.as_slice()).to_fallible();
+
+        if let Some(socket) = self.file.as_socket() {
+            let flags = self.send_flags.unwrap_or(SendRecvFlags::empty());
+            return socket.sendmsg(
+                &mut reader,
+                MessageHeader::new(None, Vec::new()),
+                flags | SendRecvFlags::MSG_DONTWAIT,
+            );
+        }
+
+        match self.offset {
+            Some(offset) if is_seekable(&self.file) => self.file.write_at(offset, &mut reader),
+            _ => self.file.write(&mut reader),
+        }
+    }
+}
+
+impl AcceptOp {
+    fn prepare(sqe: &Sqe, file: Arc<dyn FileLike>, ctx: &Context) -> Result<Self> {
+        const SOCK_NONBLOCK: u32 = StatusFlags::O_NONBLOCK.bits();
+        const SOCK_CLOEXEC: u32 = CreationFlags::O_CLOEXEC.bits();
+
+        if sqe.op_flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+            return_errno_with_message!(Errno::EINVAL, "invalid accept flags");
+        }
+        file.as_socket_or_err()?;
+
+        let fd_flags = if sqe.op_flags & SOCK_CLOEXEC != 0 {
+            FdFlags::CLOEXEC
+        } else {
+            FdFlags::empty()
+        };
+
+        let file_table = ctx.thread_local.borrow_file_table().unwrap().clone();
+        *ctx.posix_thread.io_uring_file_table().lock() = Some(file_table);
+
+        Ok(Self {
+            file,
+            addr: sqe.addr as Vaddr,
+            addrlen_addr: sqe.off as Vaddr,
+            is_nonblocking: sqe.op_flags & SOCK_NONBLOCK != 0,
+            fd_flags,
+            tid: ctx.posix_thread.tid(),
+        })
+    }
+
+    fn execute(&self, process: &Weak<Process>) -> Result<usize> {
+        // Like Linux, the request is canceled if the submitter thread has exited.
+        let file_table = thread_table::get_thread(self.tid)
+            .and_then(|thread| {
+                let posix_thread = thread.as_posix_thread()?;
+                if !Weak::ptr_eq(&posix_thread.weak_process(), process) {
+                    return None;
+                }
+                posix_thread.io_uring_file_table().lock().clone()
+            })
+            .ok_or_else(|| {
+                Error::with_message(Errno::ECANCELED, "the submitter thread has exited")
+            })?;
+
+        let socket = self.file.as_socket_or_err()?;
+        let (connected_socket, socket_addr) = socket.accept()?;
+
+        if self.is_nonblocking {
+            connected_socket.set_status_flags(StatusFlags::O_NONBLOCK)?;
+        }
+
+        if self.addr != 0 {
+            with_vmar(process, |vmar| {
+                let mut max_len = 0i32;
+                vmar.read_remote(self.addrlen_addr, max_len.as_bytes_mut())?;
+                let addr_len = write_socket_addr_with(&socket_addr, max_len, |bytes| {
+                    vmar.write_remote(self.addr, bytes)
+                })?;
+                vmar.write_remote(self.addrlen_addr, addr_len.as_bytes())
+            })?;
+        }
+
+        let fd = file_table.write().insert(connected_socket, self.fd_flags);
+        Ok(fd as usize)
+    }
+}
+
+impl TimeoutOp {
+    fn prepare(sqe: &Sqe, work_item: &Arc<WorkItem>, ctx: &Context) -> Result<Self> {
+        if sqe.len != 1 {
+            return_errno_with_message!(Errno::EINVAL, "the timeout must be a single timespec");
+        }
+        let flags = TimeoutFlags::from_bits(sqe.op_flags)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid timeout flags"))?;
+
+        let timespec = ctx.user_space().read_val::<timespec_t>(sqe.addr as Vaddr)?;
+        let duration = Duration::try_from(timespec)?;
+        let timeout = if flags.contains(TimeoutFlags::ABS) {
+            Timeout::When(duration)
+        } else {
+            Timeout::After(duration)
+        };
+
+        let is_expired = Arc::new(AtomicBool::new(false));
+        let timer = {
+            let is_expired = is_expired.clone();
+            let work_item = work_item.clone();
+            MonotonicClock::timer_manager().create_timer(move || {
+                is_expired.store(true, Ordering::Relaxed);
+                submit_work_item(work_item.clone(), WorkPriority::Normal);
+            })
+        };
+
+        Ok(Self {
+            timer,
+            timeout,
+            count: sqe.off,
+            is_expired,
+            is_count_reached: AtomicBool::new(false),
+        })
+    }
+
+    fn execute(&self) -> Result<usize> {
+        if self.is_expired.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::ETIME, "the timeout expires");
+        }
+        if self.is_count_reached.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+
+        return_errno_with_message!(Errno::EAGAIN, "the timeout is pending");
+    }
+}
+
+/// Returns whether the file supports I/O at a specified offset.
+///
+/// The file offset is ignored for other files, such as pipes and sockets.
+fn is_seekable(file: &Arc<dyn FileLike>) -> bool {
+    matches!(
+        file.metadata().type_,
+        InodeType::File | InodeType::BlockDevice
+    )
+}
+
+/// Reads `count` iovecs at `addr` from the user space of the current process.
+fn read_iovecs(addr: Vaddr, count: usize, ctx: &Context) -> Result<Vec<(Vaddr, usize)>> {
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, Pod)]
+    struct UserIoVec {
+        base: Vaddr,
+        len: isize,
+    }
+
+    if count > IOV_MAX {
+        return_errno_with_message!(Errno::EINVAL, "there are too many iovecs");
+    }
+
+    let user_space = ctx.user_space();
+    (0..count)
+        .map(|idx| {
+            let iov = user_space.read_val::<UserIoVec>(addr + idx * size_of::<UserIoVec>())?;
+            if iov.len < 0 {
+                return_errno_with_message!(Errno::EINVAL, "the iovec length cannot be negative");
+            }
+            Ok((iov.base, iov.len as usize))
+        })
+        .collect()
+}
+
+/// Gathers the data in the user buffers of the current process.
+fn gather_bufs(bufs: &[(Vaddr, usize)], ctx: &Context) -> Result<Vec<u8>> {
+    let user_space = ctx.user_space();
+
+    let mut data = Vec::new();
+    for (addr, len) in bufs.iter() {
+        let len = (*len).min(MAX_BUF_LEN - data.len());
+        if len == 0 {
+            continue;
+        }
+
+        let start = data.len();
+        data.resize(start + len, 0);
+        user_space.read_bytes(*addr, &mut VmWriter::from(&mut data[start..]))?;
+    }
+
+    Ok(data)
+}
+
+/// Accesses the user memory of the process that submits the request.
+fn with_vmar<F, R>(process: &Weak<Process>, f: F) -> Result<R>
+where
+    F: FnOnce(&Vmar<Full>) -> Result<R>,
+{
+    let Some(process) = process.upgrade() else {
+        return_errno_with_message!(Errno::EFAULT, "the process has exited");
+    };
+    let root_vmar = process.lock_root_vmar();
+    let Some(vmar) = root_vmar.as_ref() else {
+        return_errno_with_message!(Errno::EFAULT, "the process has exited");
+    };
+
+    f(vmar)
+}
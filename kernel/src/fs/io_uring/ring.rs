@@ -0,0 +1,292 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The memory layout of io_uring instances.
+//!
+//! An io_uring instance has two memory regions that are shared with user space:
+//!  * The ring region, which contains the heads, the tails, and other fields of the submission
+//!    queue (SQ) and the completion queue (CQ), followed by the CQ entries (CQEs) and the SQ
+//!    array. User space maps it at [`IORING_OFF_SQ_RING`] or [`IORING_OFF_CQ_RING`].
+//!  * The SQ entry (SQE) region, which contains the SQEs. User space maps it at
+//!    [`IORING_OFF_SQES`].
+//!
+//! The offsets of the fields in the ring region are reported to user space in [`IoUringParams`],
+//! so the layout below does not have to match that of Linux.
+
+use core::mem::size_of;
+
+use align_ext::AlignExt;
+
+use crate::prelude::*;
+
+/// The mmap offset of the SQ ring.
+pub(super) const IORING_OFF_SQ_RING: usize = 0;
+/// The mmap offset of the CQ ring.
+///
+/// Since [`IoUringFeatures::SINGLE_MMAP`] is supported, this maps the same region as
+/// [`IORING_OFF_SQ_RING`].
+pub(super) const IORING_OFF_CQ_RING: usize = 0x8000000;
+/// The mmap offset of the SQEs.
+pub(super) const IORING_OFF_SQES: usize = 0x10000000;
+
+/// The maximum number of SQ entries.
+pub(super) const IORING_MAX_ENTRIES: u32 = 32768;
+/// The maximum number of CQ entries.
+pub(super) const IORING_MAX_CQ_ENTRIES: u32 = 2 * IORING_MAX_ENTRIES;
+
+bitflags! {
+    /// The flags of `io_uring_setup`.
+    pub struct IoUringSetupFlags: u32 {
+        const IOPOLL = 1 << 0;
+        const SQPOLL = 1 << 1;
+        const SQ_AFF = 1 << 2;
+        const CQSIZE = 1 << 3;
+        const CLAMP  = 1 << 4;
+    }
+}
+
+bitflags! {
+    /// The features reported by `io_uring_setup`.
+    pub struct IoUringFeatures: u32 {
+        const SINGLE_MMAP   = 1 << 0;
+        const NODROP        = 1 << 1;
+        const SUBMIT_STABLE = 1 << 2;
+        const RW_CUR_POS    = 1 << 3;
+    }
+}
+
+bitflags! {
+    /// The flags in the SQ ring, which are written by the kernel.
+    pub(super) struct SqRingFlags: u32 {
+        const NEED_WAKEUP = 1 << 0;
+        /// The CQ has overflowed, and the overflowed CQEs are buffered in the kernel.
+        const CQ_OVERFLOW = 1 << 1;
+    }
+}
+
+/// The parameters of `io_uring_setup`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct IoUringParams {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: SqRingOffsets,
+    pub cq_off: CqRingOffsets,
+}
+
+/// The offsets of the SQ fields in the ring region.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct SqRingOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// The offsets of the CQ fields in the ring region.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub struct CqRingOffsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// An SQ entry, which describes an I/O request.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct Sqe {
+    pub(super) opcode: u8,
+    pub(super) flags: u8,
+    pub(super) ioprio: u16,
+    pub(super) fd: i32,
+    /// The file offset, or `addr2` for some operations.
+    pub(super) off: u64,
+    pub(super) addr: u64,
+    pub(super) len: u32,
+    /// The operation-specific flags (e.g., `rw_flags`, `poll_events`, and `msg_flags`).
+    pub(super) op_flags: u32,
+    pub(super) user_data: u64,
+    pub(super) buf_index: u16,
+    pub(super) personality: u16,
+    pub(super) splice_fd_in: i32,
+    pub(super) addr3: u64,
+    pub(super) pad: u64,
+}
+
+/// A CQ entry, which describes the result of an I/O request.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct Cqe {
+    pub(super) user_data: u64,
+    pub(super) res: i32,
+    pub(super) flags: u32,
+}
+
+// The offsets of the fields in the ring region.
+const SQ_HEAD: usize = 0;
+const SQ_TAIL: usize = 4;
+const SQ_RING_MASK: usize = 8;
+const SQ_RING_ENTRIES: usize = 12;
+const SQ_FLAGS: usize = 16;
+const SQ_DROPPED: usize = 20;
+const CQ_HEAD: usize = 24;
+const CQ_TAIL: usize = 28;
+const CQ_RING_MASK: usize = 32;
+const CQ_RING_ENTRIES: usize = 36;
+const CQ_OVERFLOW: usize = 40;
+const CQ_FLAGS: usize = 44;
+const CQES: usize = 64;
+
+/// The layout of the ring region.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RingLayout {
+    sq_entries: u32,
+    cq_entries: u32,
+}
+
+impl RingLayout {
+    /// Creates the layout.
+    ///
+    /// The numbers of entries must be powers of two.
+    pub(super) fn new(sq_entries: u32, cq_entries: u32) -> Self {
+        debug_assert!(sq_entries.is_power_of_two());
+        debug_assert!(cq_entries.is_power_of_two());
+
+        Self {
+            sq_entries,
+            cq_entries,
+        }
+    }
+
+    pub(super) fn sq_entries(&self) -> u32 {
+        self.sq_entries
+    }
+
+    pub(super) fn cq_entries(&self) -> u32 {
+        self.cq_entries
+    }
+
+    /// Returns the offset of the SQ array.
+    fn sq_array(&self) -> usize {
+        CQES + self.cq_entries as usize * size_of::<Cqe>()
+    }
+
+    /// Returns the size of the ring region in bytes, which is page-aligned.
+    pub(super) fn ring_size(&self) -> usize {
+        (self.sq_array() + self.sq_entries as usize * size_of::<u32>()).align_up(PAGE_SIZE)
+    }
+
+    /// Returns the size of the SQE region in bytes, which is page-aligned.
+    pub(super) fn sqes_size(&self) -> usize {
+        (self.sq_entries as usize * size_of::<Sqe>()).align_up(PAGE_SIZE)
+    }
+
+    /// Returns the offset of the SQ head, which is written by the kernel.
+    pub(super) fn sq_head(&self) -> usize {
+        SQ_HEAD
+    }
+
+    /// Returns the offset of the SQ tail, which is written by user space.
+    pub(super) fn sq_tail(&self) -> usize {
+        SQ_TAIL
+    }
+
+    /// Returns the offset of the SQ flags, which are written by the kernel.
+    pub(super) fn sq_flags(&self) -> usize {
+        SQ_FLAGS
+    }
+
+    /// Returns the offset of the number of the dropped (i.e., invalid) SQEs.
+    pub(super) fn sq_dropped(&self) -> usize {
+        SQ_DROPPED
+    }
+
+    /// Returns the offset of the `idx`-th element in the SQ array.
+    pub(super) fn sq_array_at(&self, idx: u32) -> usize {
+        self.sq_array() + (idx & (self.sq_entries - 1)) as usize * size_of::<u32>()
+    }
+
+    /// Returns the offset of the CQ head, which is written by user space.
+    pub(super) fn cq_head(&self) -> usize {
+        CQ_HEAD
+    }
+
+    /// Returns the offset of the CQ tail, which is written by the kernel.
+    pub(super) fn cq_tail(&self) -> usize {
+        CQ_TAIL
+    }
+
+    /// Returns the offset of the number of the dropped CQEs, which is written by the kernel.
+    pub(super) fn cq_overflow(&self) -> usize {
+        CQ_OVERFLOW
+    }
+
+    /// Returns the offset of the `idx`-th CQE.
+    pub(super) fn cqe_at(&self, idx: u32) -> usize {
+        CQES + (idx & (self.cq_entries - 1)) as usize * size_of::<Cqe>()
+    }
+
+    /// Returns the offset of the `idx`-th SQE in the SQE region.
+    ///
+    /// The index must be less than the number of SQ entries.
+    pub(super) fn sqe_at(&self, idx: u32) -> usize {
+        debug_assert!(idx < self.sq_entries);
+        idx as usize * size_of::<Sqe>()
+    }
+
+    /// Returns the initial values of the fields that are read-only to user space.
+    pub(super) fn initial_fields(&self) -> [(usize, u32); 4] {
+        [
+            (SQ_RING_MASK, self.sq_entries - 1),
+            (SQ_RING_ENTRIES, self.sq_entries),
+            (CQ_RING_MASK, self.cq_entries - 1),
+            (CQ_RING_ENTRIES, self.cq_entries),
+        ]
+    }
+
+    pub(super) fn sq_offsets(&self) -> SqRingOffsets {
+        SqRingOffsets {
+            head: SQ_HEAD as u32,
+            tail: SQ_TAIL as u32,
+            ring_mask: SQ_RING_MASK as u32,
+            ring_entries: SQ_RING_ENTRIES as u32,
+            flags: SQ_FLAGS as u32,
+            dropped: SQ_DROPPED as u32,
+            array: self.sq_array() as u32,
+            resv1: 0,
+            user_addr: 0,
+        }
+    }
+
+    pub(super) fn cq_offsets(&self) -> CqRingOffsets {
+        CqRingOffsets {
+            head: CQ_HEAD as u32,
+            tail: CQ_TAIL as u32,
+            ring_mask: CQ_RING_MASK as u32,
+            ring_entries: CQ_RING_ENTRIES as u32,
+            overflow: CQ_OVERFLOW as u32,
+            cqes: CQES as u32,
+            flags: CQ_FLAGS as u32,
+            resv1: 0,
+            user_addr: 0,
+        }
+    }
+}
@@ -6,11 +6,16 @@ pub mod devpts;
 pub mod epoll;
 pub mod exfat;
 pub mod ext2;
+mod fat_common;
 pub mod file_handle;
 pub mod file_table;
 pub mod fs_resolver;
+pub mod fuse;
 pub mod inode_handle;
+pub mod io_uring;
+pub mod mqueue;
 pub mod named_pipe;
+pub mod notify;
 pub mod overlayfs;
 pub mod path;
 pub mod pipe;
@@ -21,6 +26,7 @@ pub mod rootfs;
 pub mod sysfs;
 pub mod thread_info;
 pub mod utils;
+pub mod vfat;
 
 use aster_block::BlockDevice;
 use aster_virtio::device::block::device::BlockDevice as VirtIoBlockDevice;
@@ -59,9 +65,12 @@ pub fn lazy_init() {
     cgroupfs::init();
     ramfs::init();
     devpts::init();
+    mqueue::init();
 
     ext2::init();
     exfat::init();
+    vfat::init();
+    fuse::init();
     overlayfs::init();
 
     //The device name is specified in qemu args as --serial={device_name}
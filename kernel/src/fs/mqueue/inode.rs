@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{sync::atomic::Ordering, time::Duration};
+
+use aster_rights::ReadOp;
+use inherit_methods_macro::inherit_methods;
+
+use super::{
+    MessageQueue, MqAttr, MqueueFS, BLOCK_SIZE, HARD_MSGMAX, HARD_MSGSIZEMAX, MSGSIZE_DEFAULT,
+    MSGSIZE_MAX, MSG_DEFAULT, MSG_MAX, QUEUES_MAX,
+};
+use crate::{
+    events::IoEvents,
+    fs::utils::{DirentVisitor, FileSystem, Inode, InodeMode, InodeType, Metadata},
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+        Credentials, Gid, Uid,
+    },
+    time::clocks::RealTimeCoarseClock,
+};
+
+struct Common {
+    metadata: RwLock<Metadata>,
+    fs: Weak<MqueueFS>,
+}
+
+impl Common {
+    fn new(metadata: Metadata, fs: Weak<MqueueFS>) -> Self {
+        Self {
+            metadata: RwLock::new(metadata),
+            fs,
+        }
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn metadata(&self) -> Metadata {
+        *self.metadata.read()
+    }
+
+    fn ino(&self) -> u64 {
+        self.metadata.read().ino
+    }
+
+    fn type_(&self) -> InodeType {
+        self.metadata.read().type_
+    }
+
+    fn size(&self) -> usize {
+        self.metadata.read().size
+    }
+
+    fn atime(&self) -> Duration {
+        self.metadata.read().atime
+    }
+
+    fn set_atime(&self, time: Duration) {
+        self.metadata.write().atime = time;
+    }
+
+    fn mtime(&self) -> Duration {
+        self.metadata.read().mtime
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        self.metadata.write().mtime = time;
+    }
+
+    fn ctime(&self) -> Duration {
+        self.metadata.read().ctime
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        self.metadata.write().ctime = time;
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(self.metadata.read().mode)
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.metadata.write().mode = mode;
+        Ok(())
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(self.metadata.read().uid)
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.metadata.write().uid = uid;
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(self.metadata.read().gid)
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        self.metadata.write().gid = gid;
+        Ok(())
+    }
+}
+
+/// The root directory of an mqueue file system, which contains all the message queues.
+pub(super) struct RootInode {
+    common: Common,
+    queues: RwLock<BTreeMap<String, Arc<MqueueInode>>>,
+}
+
+impl RootInode {
+    pub(super) fn new(ino: u64, fs: Weak<MqueueFS>) -> Arc<Self> {
+        let metadata = Metadata::new_dir(ino, InodeMode::from_bits_truncate(0o1777), BLOCK_SIZE);
+
+        Arc::new(Self {
+            common: Common::new(metadata, fs),
+            queues: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    /// Creates a message queue with the attributes.
+    ///
+    /// If `attr` is `None`, the default attributes will be used.
+    pub(super) fn create_queue(
+        &self,
+        name: &str,
+        mode: InodeMode,
+        attr: Option<&MqAttr>,
+        credentials: &Credentials<ReadOp>,
+    ) -> Result<Arc<MqueueInode>> {
+        let is_privileged = credentials
+            .effective_capset()
+            .contains(CapSet::SYS_RESOURCE);
+
+        let (max_msgs, max_msg_size) = match attr {
+            None => (MSG_DEFAULT, MSGSIZE_DEFAULT),
+            Some(attr) => {
+                if attr.mq_maxmsg <= 0 || attr.mq_msgsize <= 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the attributes are not positive");
+                }
+                let (max_msgs, max_msg_size) = (attr.mq_maxmsg as usize, attr.mq_msgsize as usize);
+
+                let (msgs_limit, msg_size_limit) = if is_privileged {
+                    (HARD_MSGMAX, HARD_MSGSIZEMAX)
+                } else {
+                    (MSG_MAX, MSGSIZE_MAX)
+                };
+                if max_msgs > msgs_limit || max_msg_size > msg_size_limit {
+                    return_errno_with_message!(Errno::EINVAL, "the attributes exceed the limits");
+                }
+
+                (max_msgs, max_msg_size)
+            }
+        };
+
+        let mut queues = self.queues.write();
+        if queues.contains_key(name) {
+            return_errno_with_message!(Errno::EEXIST, "the message queue already exists");
+        }
+
+        let fs = self.common.fs.upgrade().unwrap();
+        if fs.num_queues.load(Ordering::Relaxed) >= QUEUES_MAX && !is_privileged {
+            return_errno_with_message!(Errno::ENOSPC, "too many message queues");
+        }
+        fs.num_queues.fetch_add(1, Ordering::Relaxed);
+
+        let mut metadata = Metadata::new_file(fs.alloc_id(), mode, BLOCK_SIZE);
+        metadata.uid = credentials.fsuid();
+        metadata.gid = credentials.fsgid();
+
+        let inode = Arc::new(MqueueInode {
+            common: Common::new(metadata, self.common.fs.clone()),
+            queue: MessageQueue::new(max_msgs, max_msg_size),
+        });
+        queues.insert(name.to_string(), inode.clone());
+
+        let now = inode.common.ctime();
+        self.set_mtime(now);
+        self.set_ctime(now);
+
+        Ok(inode)
+    }
+}
+
+#[inherit_methods(from = "self.common")]
+impl Inode for RootInode {
+    fn size(&self) -> usize;
+    fn metadata(&self) -> Metadata;
+    fn ino(&self) -> u64;
+    fn type_(&self) -> InodeType;
+    fn mode(&self) -> Result<InodeMode>;
+    fn set_mode(&self, mode: InodeMode) -> Result<()>;
+    fn owner(&self) -> Result<Uid>;
+    fn set_owner(&self, uid: Uid) -> Result<()>;
+    fn group(&self) -> Result<Gid>;
+    fn set_group(&self, gid: Gid) -> Result<()>;
+    fn atime(&self) -> Duration;
+    fn set_atime(&self, time: Duration);
+    fn mtime(&self) -> Duration;
+    fn set_mtime(&self, time: Duration);
+    fn ctime(&self) -> Duration;
+    fn set_ctime(&self, time: Duration);
+    fn fs(&self) -> Arc<dyn FileSystem>;
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        Err(Error::new(Errno::EISDIR))
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        if type_ != InodeType::File {
+            return_errno_with_message!(Errno::EPERM, "only message queues can be created");
+        }
+
+        let credentials = current_thread!().as_posix_thread().unwrap().credentials();
+        let inode = self.create_queue(name, mode, None, &credentials)?;
+        Ok(inode)
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+            // Read the 2 special entries.
+            if *offset == 0 {
+                visitor.visit(".", self.ino(), self.type_(), *offset)?;
+                *offset += 1;
+            }
+            if *offset == 1 {
+                visitor.visit("..", self.ino(), self.type_(), *offset)?;
+                *offset += 1;
+            }
+
+            // Read the message queues.
+            let queues = self.queues.read();
+            for (name, inode) in queues.iter().skip(*offset - 2) {
+                visitor.visit(name, inode.ino(), inode.type_(), *offset)?;
+                *offset += 1;
+            }
+
+            Ok(())
+        };
+
+        let mut iterate_offset = offset;
+        match try_readdir(&mut iterate_offset, visitor) {
+            Err(e) if offset == iterate_offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }
+    }
+
+    fn link(&self, _old: &Arc<dyn Inode>, _name: &str) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        self.queues.write().remove(name).ok_or_else(|| {
+            Error::with_message(Errno::ENOENT, "the message queue does not exist")
+        })?;
+
+        let now = RealTimeCoarseClock::get().read_time();
+        self.set_mtime(now);
+        self.set_ctime(now);
+
+        Ok(())
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        let inode: Arc<dyn Inode> = match name {
+            "." | ".." => self.common.fs().root_inode(),
+            name => self.queues.read().get(name).cloned().ok_or_else(|| {
+                Error::with_message(Errno::ENOENT, "the message queue does not exist")
+            })?,
+        };
+        Ok(inode)
+    }
+
+    fn rename(&self, _old_name: &str, _target: &Arc<dyn Inode>, _new_name: &str) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        false
+    }
+}
+
+/// An inode of a message queue.
+pub struct MqueueInode {
+    common: Common,
+    queue: MessageQueue,
+}
+
+impl MqueueInode {
+    /// Returns the message queue.
+    pub fn queue(&self) -> &MessageQueue {
+        &self.queue
+    }
+}
+
+#[inherit_methods(from = "self.common")]
+impl Inode for MqueueInode {
+    fn size(&self) -> usize;
+    fn metadata(&self) -> Metadata;
+    fn ino(&self) -> u64;
+    fn type_(&self) -> InodeType;
+    fn mode(&self) -> Result<InodeMode>;
+    fn set_mode(&self, mode: InodeMode) -> Result<()>;
+    fn owner(&self) -> Result<Uid>;
+    fn set_owner(&self, uid: Uid) -> Result<()>;
+    fn group(&self) -> Result<Gid>;
+    fn set_group(&self, gid: Gid) -> Result<()>;
+    fn atime(&self) -> Duration;
+    fn set_atime(&self, time: Duration);
+    fn mtime(&self) -> Duration;
+    fn set_mtime(&self, time: Duration);
+    fn ctime(&self) -> Duration;
+    fn set_ctime(&self, time: Duration);
+    fn fs(&self) -> Arc<dyn FileSystem>;
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        Err(Error::new(Errno::EINVAL))
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let status = self.queue.status();
+        let data = status.as_bytes();
+
+        let start = data.len().min(offset);
+        let end = data.len().min(offset + writer.avail());
+        let len = end - start;
+        writer.write_fallible(&mut (&data[start..end]).into())?;
+        Ok(len)
+    }
+
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(&self, _offset: usize, _reader: &mut VmReader) -> Result<usize> {
+        Err(Error::with_message(
+            Errno::EINVAL,
+            "the message queue file cannot be written",
+        ))
+    }
+
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write_at(offset, reader)
+    }
+
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.queue.poll(mask, poller)
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for MqueueInode {
+    fn drop(&mut self) {
+        if let Some(fs) = self.common.fs.upgrade() {
+            fs.num_queues.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! POSIX message queues.
+//!
+//! Each message queue is a file in an mqueue file system. Every IPC namespace owns an internal
+//! instance of the file system, which is used by `mq_open` and `mq_unlink`, and the instance of
+//! the initial IPC namespace is mounted at `/dev/mqueue`.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/mq_overview.7.html>
+
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use aster_rights::ReadOp;
+
+use self::inode::RootInode;
+pub use self::{inode::MqueueInode, queue::MessageQueue};
+use crate::{
+    fs::{
+        registry::{FsProperties, FsType},
+        utils::{FileSystem, FsFlags, Inode, InodeMode, SuperBlock, NAME_MAX},
+    },
+    prelude::*,
+    process::Credentials,
+};
+
+mod inode;
+mod queue;
+
+pub(super) fn init() {
+    let mqueue_type = Arc::new(MqueueFsType);
+    super::registry::register(mqueue_type).unwrap();
+}
+
+/// Magic number.
+const MQUEUE_MAGIC: u64 = 0x1980_0202;
+/// Root Inode ID.
+const MQUEUE_ROOT_INO: u64 = 1;
+/// Block size.
+const BLOCK_SIZE: usize = PAGE_SIZE;
+
+// The following constant values are derived from the default values in Linux.
+
+/// Maximum number of message queues for unprivileged users.
+const QUEUES_MAX: usize = 256;
+/// Maximum number of messages in a queue for unprivileged users.
+const MSG_MAX: usize = 10;
+/// Maximum size of a message for unprivileged users.
+const MSGSIZE_MAX: usize = 8192;
+/// Default number of messages in a queue.
+const MSG_DEFAULT: usize = 10;
+/// Default size of a message.
+const MSGSIZE_DEFAULT: usize = 8192;
+/// Maximum number of messages in a queue for privileged users.
+const HARD_MSGMAX: usize = 65536;
+/// Maximum size of a message for privileged users.
+const HARD_MSGSIZEMAX: usize = 16 * 1024 * 1024;
+
+/// Maximum priority of a message plus one.
+pub const MQ_PRIO_MAX: u32 = 32768;
+
+/// The attributes of a message queue.
+//
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/mqueue.h
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, Pod)]
+pub struct MqAttr {
+    /// The flags, which are `O_NONBLOCK` or zero
+    pub mq_flags: i64,
+    /// Maximum number of messages
+    pub mq_maxmsg: i64,
+    /// Maximum size of a message in bytes
+    pub mq_msgsize: i64,
+    /// Number of the messages currently in the queue
+    pub mq_curmsgs: i64,
+    _reserved: [i64; 4],
+}
+
+/// An mqueue file system.
+pub struct MqueueFS {
+    sb: SuperBlock,
+    root: Arc<RootInode>,
+    inode_allocator: AtomicU64,
+    /// The number of the message queues in the file system
+    num_queues: AtomicUsize,
+}
+
+impl MqueueFS {
+    pub fn new() -> Arc<Self> {
+        Arc::new_cyclic(|weak_fs| Self {
+            sb: SuperBlock::new(MQUEUE_MAGIC, BLOCK_SIZE, NAME_MAX),
+            root: RootInode::new(MQUEUE_ROOT_INO, weak_fs.clone()),
+            inode_allocator: AtomicU64::new(MQUEUE_ROOT_INO + 1),
+            num_queues: AtomicUsize::new(0),
+        })
+    }
+
+    /// Creates a message queue with the attributes.
+    ///
+    /// If `attr` is `None`, the default attributes will be used.
+    pub fn create_queue(
+        &self,
+        name: &str,
+        mode: InodeMode,
+        attr: Option<&MqAttr>,
+        credentials: &Credentials<ReadOp>,
+    ) -> Result<Arc<MqueueInode>> {
+        self.root.create_queue(name, mode, attr, credentials)
+    }
+
+    fn alloc_id(&self) -> u64 {
+        self.inode_allocator.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl FileSystem for MqueueFS {
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        self.sb.clone()
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::empty()
+    }
+}
+
+struct MqueueFsType;
+
+impl FsType for MqueueFsType {
+    fn name(&self) -> &'static str {
+        "mqueue"
+    }
+
+    fn create(
+        &self,
+        _source: &CStr,
+        _args: Option<CString>,
+        _disk: Option<Arc<dyn aster_block::BlockDevice>>,
+        ctx: &Context,
+    ) -> Result<Arc<dyn FileSystem>> {
+        // Mounting the file system exposes the message queues of the current IPC namespace.
+        let ns_proxy = ctx.posix_thread.ns_proxy();
+        Ok(ns_proxy.ipc_ns().mqueue_root().fs())
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::empty()
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysBranchNode>> {
+        None
+    }
+}
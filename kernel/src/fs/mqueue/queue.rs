@@ -0,0 +1,351 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+use core::fmt::Debug;
+
+use ostd::sync::WaitQueue;
+
+use super::MqAttr;
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::{
+        signal::{
+            c_types::{siginfo_t, sigval_t, SigNotify},
+            constants::SI_MESGQ,
+            sig_num::SigNum,
+            signals::Signal,
+            PollHandle, Pollable, Pollee,
+        },
+        Pid, Process, Uid,
+    },
+    time::wait::ManagedTimeout,
+};
+
+/// A POSIX message queue.
+pub struct MessageQueue {
+    /// The maximum number of messages
+    max_msgs: usize,
+    /// The maximum size of a message in bytes
+    max_msg_size: usize,
+    inner: Mutex<MessageQueueInner>,
+    pollee: Pollee,
+    /// The senders waiting for free slots
+    send_wait_queue: WaitQueue,
+    /// The receivers waiting for messages
+    recv_wait_queue: WaitQueue,
+}
+
+struct MessageQueueInner {
+    /// The messages indexed by their priorities
+    ///
+    /// Messages of the same priority are received in the order they are sent.
+    messages: BTreeMap<u32, VecDeque<Vec<u8>>>,
+    num_msgs: usize,
+    /// The total number of bytes in the messages
+    num_bytes: usize,
+    /// The number of the receivers blocked because the queue is empty
+    num_blocked_receivers: usize,
+    notification: Option<Notification>,
+}
+
+/// A notification registered with `mq_notify`.
+struct Notification {
+    owner: Weak<Process>,
+    owner_pid: Pid,
+    /// The signal to send, which is `None` for `SIGEV_NONE`
+    signal: Option<(SigNum, sigval_t)>,
+}
+
+impl MessageQueue {
+    pub(super) fn new(max_msgs: usize, max_msg_size: usize) -> Self {
+        Self {
+            max_msgs,
+            max_msg_size,
+            inner: Mutex::new(MessageQueueInner {
+                messages: BTreeMap::new(),
+                num_msgs: 0,
+                num_bytes: 0,
+                num_blocked_receivers: 0,
+                notification: None,
+            }),
+            pollee: Pollee::new(),
+            send_wait_queue: WaitQueue::new(),
+            recv_wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// Returns the maximum size of a message in bytes.
+    pub fn max_msg_size(&self) -> usize {
+        self.max_msg_size
+    }
+
+    /// Returns the attributes of the queue.
+    ///
+    /// The flags are always zero since they are the status flags of the queue descriptors.
+    pub fn attr(&self) -> MqAttr {
+        MqAttr {
+            mq_maxmsg: self.max_msgs as i64,
+            mq_msgsize: self.max_msg_size as i64,
+            mq_curmsgs: self.inner.lock().num_msgs as i64,
+            ..MqAttr::default()
+        }
+    }
+
+    /// Sends a message with the priority to the queue.
+    ///
+    /// If the queue is full, this method blocks until there is a free slot or the timeout
+    /// expires, unless `is_nonblocking` is true, in which case it fails with `EAGAIN`.
+    pub fn send(
+        &self,
+        bytes: Vec<u8>,
+        priority: u32,
+        is_nonblocking: bool,
+        timeout: Option<ManagedTimeout>,
+        ctx: &Context,
+    ) -> Result<()> {
+        debug_assert!(bytes.len() <= self.max_msg_size);
+
+        let mut message = Some(bytes);
+
+        self.send_wait_queue.pause_until_or_timeout(
+            || {
+                let mut inner = self.inner.lock();
+                if inner.num_msgs >= self.max_msgs {
+                    if is_nonblocking {
+                        return Some(Err(Error::with_message(
+                            Errno::EAGAIN,
+                            "the message queue is full",
+                        )));
+                    }
+                    return None;
+                }
+
+                let bytes = message.take().unwrap();
+                inner.num_msgs += 1;
+                inner.num_bytes += bytes.len();
+                inner.messages.entry(priority).or_default().push_back(bytes);
+
+                // The notification is only sent if the queue becomes non-empty and no receivers
+                // are blocked. Otherwise, the registration remains in effect.
+                let notification = if inner.num_msgs == 1 && inner.num_blocked_receivers == 0 {
+                    inner.notification.take()
+                } else {
+                    None
+                };
+                drop(inner);
+
+                self.pollee.notify(IoEvents::IN);
+                self.recv_wait_queue.wake_all();
+                if let Some(notification) = notification {
+                    notification.notify(ctx);
+                }
+
+                Some(Ok(()))
+            },
+            timeout,
+        )?
+    }
+
+    /// Receives the oldest message of the highest priority from the queue.
+    ///
+    /// If the queue is empty, this method blocks until a message arrives or the timeout expires,
+    /// unless `is_nonblocking` is true, in which case it fails with `EAGAIN`.
+    pub fn receive(
+        &self,
+        max_len: usize,
+        is_nonblocking: bool,
+        timeout: Option<ManagedTimeout>,
+    ) -> Result<(Vec<u8>, u32)> {
+        if max_len < self.max_msg_size {
+            return_errno_with_message!(Errno::EMSGSIZE, "the buffer is too small");
+        }
+
+        let mut is_blocked = false;
+
+        let res = self.recv_wait_queue.pause_until_or_timeout(
+            || {
+                let mut inner = self.inner.lock();
+                if is_blocked {
+                    inner.num_blocked_receivers -= 1;
+                    is_blocked = false;
+                }
+
+                let Some(mut entry) = inner.messages.last_entry() else {
+                    if is_nonblocking {
+                        return Some(Err(Error::with_message(
+                            Errno::EAGAIN,
+                            "the message queue is empty",
+                        )));
+                    }
+                    inner.num_blocked_receivers += 1;
+                    is_blocked = true;
+                    return None;
+                };
+
+                let priority = *entry.key();
+                let bytes = entry.get_mut().pop_front().unwrap();
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+                inner.num_msgs -= 1;
+                inner.num_bytes -= bytes.len();
+                drop(inner);
+
+                self.pollee.notify(IoEvents::OUT);
+                self.send_wait_queue.wake_all();
+
+                Some(Ok((bytes, priority)))
+            },
+            timeout,
+        );
+
+        if is_blocked {
+            self.inner.lock().num_blocked_receivers -= 1;
+        }
+
+        res?
+    }
+
+    /// Registers the current process to be notified when a message arrives at the empty queue.
+    ///
+    /// The notification is a signal if `signal` is `Some`. Only one process can be registered at
+    /// a time, so this method fails with `EBUSY` if another process has been registered.
+    pub fn register_notification(
+        &self,
+        signal: Option<(SigNum, sigval_t)>,
+        ctx: &Context,
+    ) -> Result<()> {
+        let mut inner = self.inner.lock();
+
+        // FIXME: Linux removes the registration when the owner closes the queue descriptor. We
+        // only consider the registration of an exited process invalid.
+        if inner
+            .notification
+            .as_ref()
+            .is_some_and(|notification| notification.owner.strong_count() > 0)
+        {
+            return_errno_with_message!(Errno::EBUSY, "another process has been registered");
+        }
+
+        inner.notification = Some(Notification {
+            owner: ctx.posix_thread.weak_process(),
+            owner_pid: ctx.process.pid(),
+            signal,
+        });
+        Ok(())
+    }
+
+    /// Removes the registration of the current process, if any.
+    pub fn unregister_notification(&self, ctx: &Context) {
+        let mut inner = self.inner.lock();
+
+        if inner
+            .notification
+            .as_ref()
+            .is_some_and(|notification| notification.owner_pid == ctx.process.pid())
+        {
+            inner.notification = None;
+        }
+    }
+
+    /// Returns the status of the queue, which is the content of the queue file.
+    pub(super) fn status(&self) -> String {
+        let inner = self.inner.lock();
+
+        let (notify, signo, notify_pid) = match &inner.notification {
+            None => (0, 0, 0),
+            Some(Notification {
+                owner_pid,
+                signal: None,
+                ..
+            }) => (SigNotify::SIGEV_NONE as i32, 0, *owner_pid),
+            Some(Notification {
+                owner_pid,
+                signal: Some((signum, _)),
+                ..
+            }) => (
+                SigNotify::SIGEV_SIGNAL as i32,
+                signum.as_u8() as i32,
+                *owner_pid,
+            ),
+        };
+
+        format!(
+            "QSIZE:{:<10} NOTIFY:{:<5} SIGNO:{:<5} NOTIFY_PID:{:<6}\n",
+            inner.num_bytes, notify, signo, notify_pid
+        )
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.lock();
+
+        let mut events = IoEvents::empty();
+        if inner.num_msgs > 0 {
+            events |= IoEvents::IN;
+        }
+        if inner.num_msgs < self.max_msgs {
+            events |= IoEvents::OUT;
+        }
+        events
+    }
+}
+
+impl Pollable for MessageQueue {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl Notification {
+    /// Notifies the owner that a message is sent by the current process.
+    fn notify(self, ctx: &Context) {
+        let Some((num, value)) = self.signal else {
+            return;
+        };
+        let Some(owner) = self.owner.upgrade() else {
+            return;
+        };
+
+        let signal = MqueueSignal {
+            num,
+            value,
+            pid: ctx.process.pid(),
+            uid: ctx.posix_thread.credentials().ruid(),
+        };
+        owner.enqueue_signal(signal);
+    }
+}
+
+/// The signal sent when a message arrives at the empty queue.
+#[derive(Clone, Copy)]
+struct MqueueSignal {
+    num: SigNum,
+    value: sigval_t,
+    pid: Pid,
+    uid: Uid,
+}
+
+impl Debug for MqueueSignal {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MqueueSignal")
+            .field("num", &self.num)
+            .field("pid", &self.pid)
+            .field("uid", &self.uid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signal for MqueueSignal {
+    fn num(&self) -> SigNum {
+        self.num
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        let mut info = siginfo_t::new(self.num, SI_MESGQ);
+        info.set_pid_uid(self.pid, self.uid);
+        info.set_value(self.value);
+        info
+    }
+}
@@ -0,0 +1,525 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The inotify file.
+//!
+//! For more detailed information about inotify, refer to the man 7 inotify documentation.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use super::{FsEvent, FsEventPublisher, FsEvents};
+use crate::{
+    events::{IoEvents, Observer},
+    fs::{
+        file_handle::FileLike,
+        utils::{CreationFlags, Inode, InodeMode, IoctlCmd, Metadata, StatusFlags},
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+};
+
+/// The maximum number of watches of an inotify file.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/fs/notify/inotify/inotify_user.c#L822>.
+const MAX_WATCHES: usize = 8192;
+
+/// The maximum number of queued events of an inotify file.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/fs/notify/inotify/inotify_user.c#L820>.
+const MAX_QUEUED_EVENTS: usize = 16384;
+
+/// The event queue overflowed.
+const IN_Q_OVERFLOW: u32 = 0x0000_4000;
+/// The watch was removed.
+const IN_IGNORED: u32 = 0x0000_8000;
+
+bitflags! {
+    /// The flags of `inotify_init1`.
+    pub struct InotifyFlags: u32 {
+        const IN_NONBLOCK = StatusFlags::O_NONBLOCK.bits();
+        const IN_CLOEXEC  = CreationFlags::O_CLOEXEC.bits();
+    }
+}
+
+bitflags! {
+    /// The control bits in the mask of `inotify_add_watch`.
+    pub struct InotifyMask: u32 {
+        /// Only watch the path if it is a directory.
+        const ONLYDIR     = 0x0100_0000;
+        /// Do not follow the path if it is a symbolic link.
+        const DONT_FOLLOW = 0x0200_0000;
+        /// Do not report events on unlinked entries in a watched directory.
+        const EXCL_UNLINK = 0x0400_0000;
+        /// Fail if the path is already watched.
+        const MASK_CREATE = 0x1000_0000;
+        /// Add the events to the existing watch instead of replacing them.
+        const MASK_ADD    = 0x2000_0000;
+        /// Remove the watch after the first event.
+        const ONESHOT     = 0x8000_0000;
+    }
+}
+
+/// A file-like object that provides inotify API.
+///
+/// An inotify file maintains a set of watches. Each watch registers itself as an `Observer` to
+/// the [`FsEventPublisher`] of the watched inode, and turns the file system events into inotify
+/// events in the event queue of the file.
+pub struct InotifyFile {
+    watches: Mutex<WatchTable>,
+    // The queue is protected by a `Mutex` because user memory is accessed while reading events.
+    queue: Mutex<EventQueue>,
+    pollee: Pollee,
+    is_nonblocking: AtomicBool,
+    this: Weak<InotifyFile>,
+}
+
+impl InotifyFile {
+    /// Creates a new inotify file.
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        Arc::new_cyclic(|weak_self| Self {
+            watches: Mutex::new(WatchTable::new()),
+            queue: Mutex::new(EventQueue::new()),
+            pollee: Pollee::new(),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            this: weak_self.clone(),
+        })
+    }
+
+    /// Adds a watch of the `events` on the inode, or modifies the existing one.
+    ///
+    /// This method returns the watch descriptor.
+    pub fn add_watch(
+        &self,
+        inode: &Arc<dyn Inode>,
+        events: FsEvents,
+        mask: InotifyMask,
+    ) -> Result<i32> {
+        if mask.contains(InotifyMask::MASK_ADD | InotifyMask::MASK_CREATE) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "IN_MASK_ADD and IN_MASK_CREATE cannot be specified together"
+            );
+        }
+
+        let mut watches = self.watches.lock();
+
+        if let Some(watch) = watches.get_by_inode(inode) {
+            if mask.contains(InotifyMask::MASK_CREATE) {
+                return_errno_with_message!(Errno::EEXIST, "the inode is already watched");
+            }
+
+            let events = if mask.contains(InotifyMask::MASK_ADD) {
+                events | watch.events()
+            } else {
+                events
+            };
+            watch.update(events, mask);
+
+            return Ok(watch.wd);
+        }
+
+        if watches.len() >= MAX_WATCHES {
+            return_errno_with_message!(Errno::ENOSPC, "too many watches");
+        }
+
+        let wd = watches.alloc_wd();
+        let watch = InotifyWatch::new(wd, inode.clone(), self.this.clone());
+        watch.update(events, mask);
+        watches.insert(watch);
+
+        Ok(wd)
+    }
+
+    /// Removes a watch.
+    pub fn remove_watch(&self, wd: i32) -> Result<()> {
+        let Some(watch) = self.watches.lock().remove(wd) else {
+            return_errno_with_message!(Errno::EINVAL, "the watch descriptor does not exist");
+        };
+
+        watch.detach();
+        self.push_event(InotifyEvent::new(wd, IN_IGNORED, 0, None));
+
+        Ok(())
+    }
+
+    fn push_event(&self, event: InotifyEvent) {
+        if !self.queue.lock().push(event) {
+            return;
+        }
+
+        self.pollee.notify(IoEvents::IN);
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        if self.queue.lock().is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        }
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let mut queue = self.queue.lock();
+
+        let Some(first) = queue.front() else {
+            return_errno_with_message!(Errno::EAGAIN, "no events are available");
+        };
+        if writer.avail() < first.len() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for the event");
+        }
+
+        let mut read_len = 0;
+        while let Some(event) = queue.front() {
+            if writer.avail() < event.len() {
+                break;
+            }
+
+            event.write_to(writer)?;
+            read_len += event.len();
+            queue.pop();
+        }
+
+        self.pollee.invalidate();
+
+        Ok(read_len)
+    }
+}
+
+impl Pollable for InotifyFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl FileLike for InotifyFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        if self.is_nonblocking() {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "inotify files do not support write");
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::FIONREAD => {
+                let len = self.queue.lock().total_len() as i32;
+                current_userspace!().write_val(arg, &len)?;
+                Ok(0)
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the ioctl command is not supported"),
+        }
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking() {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        self.is_nonblocking.store(
+            new_flags.contains(StatusFlags::O_NONBLOCK),
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        // This is a dummy implementation.
+        // TODO: Add "anonymous inode fs" and link `InotifyFile` to it.
+        Metadata::new_file(
+            0,
+            InodeMode::from_bits_truncate(0o600),
+            aster_block::BLOCK_SIZE,
+        )
+    }
+}
+
+impl Drop for InotifyFile {
+    fn drop(&mut self) {
+        let watches = core::mem::take(&mut self.watches.get_mut().by_wd);
+        for watch in watches.into_values() {
+            watch.detach();
+        }
+    }
+}
+
+/// The watches of an inotify file.
+struct WatchTable {
+    by_wd: BTreeMap<i32, Arc<InotifyWatch>>,
+    // Maps the address of an inode to its watch descriptor.
+    by_inode: BTreeMap<usize, i32>,
+    next_wd: i32,
+}
+
+impl WatchTable {
+    fn new() -> Self {
+        Self {
+            by_wd: BTreeMap::new(),
+            by_inode: BTreeMap::new(),
+            next_wd: 1,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.by_wd.len()
+    }
+
+    fn get_by_inode(&self, inode: &Arc<dyn Inode>) -> Option<&Arc<InotifyWatch>> {
+        let wd = self.by_inode.get(&inode_key(inode))?;
+        self.by_wd.get(wd)
+    }
+
+    /// Allocates a watch descriptor.
+    ///
+    /// Similar to Linux, the watch descriptors are allocated cyclically, so that a removed
+    /// watch descriptor will not be reused soon.
+    fn alloc_wd(&mut self) -> i32 {
+        loop {
+            let wd = self.next_wd;
+            self.next_wd = if wd == i32::MAX { 1 } else { wd + 1 };
+
+            if !self.by_wd.contains_key(&wd) {
+                return wd;
+            }
+        }
+    }
+
+    fn insert(&mut self, watch: Arc<InotifyWatch>) {
+        self.by_inode.insert(inode_key(&watch.inode), watch.wd);
+        self.by_wd.insert(watch.wd, watch);
+    }
+
+    fn remove(&mut self, wd: i32) -> Option<Arc<InotifyWatch>> {
+        let watch = self.by_wd.remove(&wd)?;
+        self.by_inode.remove(&inode_key(&watch.inode));
+        Some(watch)
+    }
+}
+
+fn inode_key(inode: &Arc<dyn Inode>) -> usize {
+    Arc::as_ptr(inode) as *const () as usize
+}
+
+/// A watch on an inode.
+struct InotifyWatch {
+    wd: i32,
+    // The watch keeps the inode alive, so that the inode (and the publisher in its extension)
+    // will not be rebuilt by the file system while it is watched.
+    inode: Arc<dyn Inode>,
+    publisher: Option<Arc<FsEventPublisher>>,
+    events: AtomicU32,
+    is_oneshot: AtomicBool,
+    is_detached: AtomicBool,
+    owner: Weak<InotifyFile>,
+    this: Weak<InotifyWatch>,
+}
+
+impl InotifyWatch {
+    fn new(wd: i32, inode: Arc<dyn Inode>, owner: Weak<InotifyFile>) -> Arc<Self> {
+        let publisher = FsEventPublisher::get_or_create(&inode);
+        if publisher.is_none() {
+            debug!("the inode does not support extensions, so no events will be reported");
+        }
+
+        Arc::new_cyclic(|weak_self| Self {
+            wd,
+            inode,
+            publisher,
+            events: AtomicU32::new(0),
+            is_oneshot: AtomicBool::new(false),
+            is_detached: AtomicBool::new(false),
+            owner,
+            this: weak_self.clone(),
+        })
+    }
+
+    fn events(&self) -> FsEvents {
+        FsEvents::from_bits_truncate(self.events.load(Ordering::Relaxed))
+    }
+
+    /// Updates the interesting events and the control bits of the watch.
+    fn update(&self, events: FsEvents, mask: InotifyMask) {
+        self.events.store(events.bits(), Ordering::Relaxed);
+        self.is_oneshot
+            .store(mask.contains(InotifyMask::ONESHOT), Ordering::Relaxed);
+
+        let Some(publisher) = self.publisher.as_ref() else {
+            return;
+        };
+        // The watch must be removed when the inode is deleted, so `DELETE_SELF` is always
+        // interesting.
+        publisher.register_observer(self.this.clone(), events | FsEvents::DELETE_SELF);
+    }
+
+    /// Stops the watch from receiving events.
+    fn detach(&self) {
+        self.is_detached.store(true, Ordering::Relaxed);
+
+        if let Some(publisher) = self.publisher.as_ref() {
+            let observer: Weak<dyn Observer<FsEvent>> = self.this.clone();
+            publisher.unregister_observer(&observer);
+        }
+    }
+}
+
+impl Observer<FsEvent> for InotifyWatch {
+    fn on_events(&self, event: &FsEvent) {
+        if self.is_detached.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(owner) = self.owner.upgrade() else {
+            return;
+        };
+
+        if self.events().intersects(event.events() - FsEvents::ISDIR) {
+            owner.push_event(InotifyEvent::new(
+                self.wd,
+                event.events().bits(),
+                event.cookie(),
+                event.name().map(String::from),
+            ));
+
+            if self.is_oneshot.load(Ordering::Relaxed) {
+                let _ = owner.remove_watch(self.wd);
+                return;
+            }
+        }
+
+        if event.events().contains(FsEvents::DELETE_SELF) {
+            let _ = owner.remove_watch(self.wd);
+        }
+    }
+}
+
+/// An event in the event queue of an inotify file.
+#[derive(PartialEq, Eq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    name: Option<String>,
+}
+
+impl InotifyEvent {
+    fn new(wd: i32, mask: u32, cookie: u32, name: Option<String>) -> Self {
+        Self {
+            wd,
+            mask,
+            cookie,
+            name,
+        }
+    }
+
+    /// Returns the length of the name field, including the null terminator and the padding.
+    fn name_len(&self) -> usize {
+        // Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/fs/notify/inotify/inotify_user.c#L204>.
+        self.name.as_ref().map_or(0, |name| {
+            (name.len() + 1).next_multiple_of(size_of::<CInotifyEvent>())
+        })
+    }
+
+    /// Returns the number of bytes that the event occupies when it is read.
+    fn len(&self) -> usize {
+        size_of::<CInotifyEvent>() + self.name_len()
+    }
+
+    fn write_to(&self, writer: &mut VmWriter) -> Result<()> {
+        let name_len = self.name_len();
+
+        let header = CInotifyEvent {
+            wd: self.wd,
+            mask: self.mask,
+            cookie: self.cookie,
+            len: name_len as u32,
+        };
+        writer.write_val(&header)?;
+
+        if let Some(name) = self.name.as_ref() {
+            let mut buf = vec![0u8; name_len];
+            buf[..name.len()].copy_from_slice(name.as_bytes());
+            writer.write_fallible(&mut buf.as_slice().into())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The event queue of an inotify file.
+struct EventQueue {
+    events: VecDeque<InotifyEvent>,
+    total_len: usize,
+}
+
+impl EventQueue {
+    fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+            total_len: 0,
+        }
+    }
+
+    /// Pushes an event to the queue and returns whether the queue is changed.
+    fn push(&mut self, event: InotifyEvent) -> bool {
+        // Similar to Linux, merge the event with the last one if they are identical.
+        if self.events.back() == Some(&event) {
+            return false;
+        }
+
+        let event = if self.events.len() < MAX_QUEUED_EVENTS {
+            event
+        } else {
+            let overflow = InotifyEvent::new(-1, IN_Q_OVERFLOW, 0, None);
+            if self.events.back() == Some(&overflow) {
+                return false;
+            }
+            overflow
+        };
+
+        self.total_len += event.len();
+        self.events.push_back(event);
+
+        true
+    }
+
+    fn front(&self) -> Option<&InotifyEvent> {
+        self.events.front()
+    }
+
+    fn pop(&mut self) -> Option<InotifyEvent> {
+        let event = self.events.pop_front()?;
+        self.total_len -= event.len();
+        Some(event)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    fn total_len(&self) -> usize {
+        self.total_len
+    }
+}
+
+/// The header of an inotify event in the user space.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/inotify.h#L21>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct CInotifyEvent {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    len: u32,
+}
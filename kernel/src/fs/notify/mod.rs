@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! File system event notification.
+//!
+//! The VFS layer (i.e., `Dentry` and `InodeHandle`) publishes [`FsEvent`]s to the
+//! [`FsEventPublisher`] attached to the affected inodes. Notification mechanisms like inotify
+//! subscribe to the publishers as observers, so that they can report file changes uniformly
+//! regardless of the underlying file systems.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{
+    events::{Events, EventsFilter, Observer, Subject},
+    fs::utils::{Inode, NAME_MAX},
+    prelude::*,
+};
+
+mod inotify;
+
+pub use inotify::{InotifyFile, InotifyFlags, InotifyMask};
+
+bitflags! {
+    /// File system events.
+    ///
+    /// The values are the same as the inotify event masks in Linux.
+    pub struct FsEvents: u32 {
+        /// The file was accessed.
+        const ACCESS        = 0x0000_0001;
+        /// The file was modified.
+        const MODIFY        = 0x0000_0002;
+        /// The metadata of the file was changed.
+        const ATTRIB        = 0x0000_0004;
+        /// A file opened for writing was closed.
+        const CLOSE_WRITE   = 0x0000_0008;
+        /// A file not opened for writing was closed.
+        const CLOSE_NOWRITE = 0x0000_0010;
+        /// The file was opened.
+        const OPEN          = 0x0000_0020;
+        /// A file was moved out of the directory.
+        const MOVED_FROM    = 0x0000_0040;
+        /// A file was moved into the directory.
+        const MOVED_TO      = 0x0000_0080;
+        /// A file was created in the directory.
+        const CREATE        = 0x0000_0100;
+        /// A file was deleted from the directory.
+        const DELETE        = 0x0000_0200;
+        /// The file itself was deleted.
+        const DELETE_SELF   = 0x0000_0400;
+        /// The file itself was moved.
+        const MOVE_SELF     = 0x0000_0800;
+
+        /// The subject of the event is a directory.
+        const ISDIR         = 0x4000_0000;
+    }
+}
+
+/// A file system event.
+#[derive(Debug, Clone, Copy)]
+pub struct FsEvent {
+    events: FsEvents,
+    cookie: u32,
+    name: Option<FsEventName>,
+}
+
+impl FsEvent {
+    /// Creates an event that happens on the watched file itself.
+    pub fn new(events: FsEvents) -> Self {
+        Self {
+            events,
+            cookie: 0,
+            name: None,
+        }
+    }
+
+    /// Creates an event that happens on an entry named `name` in the watched directory.
+    ///
+    /// The `cookie` associates the `MOVED_FROM` and `MOVED_TO` events of the same rename. It
+    /// should be zero for other events.
+    pub fn new_with_name(events: FsEvents, cookie: u32, name: &str) -> Self {
+        Self {
+            events,
+            cookie,
+            name: Some(FsEventName::new(name)),
+        }
+    }
+
+    /// Returns the events.
+    pub fn events(&self) -> FsEvents {
+        self.events
+    }
+
+    /// Returns the cookie that associates related events.
+    pub fn cookie(&self) -> u32 {
+        self.cookie
+    }
+
+    /// Returns the name of the entry in the watched directory, if the event happens on an entry.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(FsEventName::as_str)
+    }
+}
+
+impl Events for FsEvent {}
+
+impl EventsFilter<FsEvent> for FsEvents {
+    fn filter(&self, event: &FsEvent) -> bool {
+        self.intersects(event.events - FsEvents::ISDIR)
+    }
+}
+
+/// The name carried by an [`FsEvent`].
+///
+/// Events must be `Copy`, so the name is stored inline.
+#[derive(Clone, Copy)]
+struct FsEventName {
+    len: u8,
+    bytes: [u8; NAME_MAX],
+}
+
+impl FsEventName {
+    fn new(name: &str) -> Self {
+        // Names are checked against `NAME_MAX` during path lookups. But be careful not to split a
+        // UTF-8 character if a longer name slips through.
+        let mut len = name.len().min(NAME_MAX);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut bytes = [0; NAME_MAX];
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self {
+            len: len as u8,
+            bytes,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap()
+    }
+}
+
+impl Debug for FsEventName {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// The publisher of the file system events that happen on an inode.
+///
+/// The publisher is stored in the [`Extension`] of the inode, and it is created only when the
+/// first observer comes. Inodes without an extension do not report events.
+///
+/// [`Extension`]: crate::fs::utils::Extension
+pub struct FsEventPublisher {
+    subject: Subject<FsEvent, FsEvents>,
+}
+
+impl FsEventPublisher {
+    /// Returns the publisher of the inode, creating it if it does not exist.
+    ///
+    /// This method returns `None` if the inode does not support extensions.
+    pub fn get_or_create(inode: &Arc<dyn Inode>) -> Option<Arc<Self>> {
+        inode
+            .extension()
+            .map(|extension| extension.get_or_put_default::<Self>())
+    }
+
+    /// Returns the publisher of the inode, if the inode has ever been watched.
+    pub fn get(inode: &Arc<dyn Inode>) -> Option<Arc<Self>> {
+        inode.extension()?.get::<Self>()
+    }
+
+    /// Registers an observer that is interested in the `events`.
+    ///
+    /// If the observer has already been registered, the interesting events will be updated.
+    pub fn register_observer(&self, observer: Weak<dyn Observer<FsEvent>>, events: FsEvents) {
+        self.subject.register_observer(observer, events);
+    }
+
+    /// Unregisters an observer.
+    pub fn unregister_observer(&self, observer: &Weak<dyn Observer<FsEvent>>) {
+        self.subject.unregister_observer(observer);
+    }
+
+    /// Publishes an event to the observers.
+    pub fn publish(&self, event: &FsEvent) {
+        self.subject.notify_observers(event);
+    }
+}
+
+impl Default for FsEventPublisher {
+    fn default() -> Self {
+        Self {
+            subject: Subject::new(),
+        }
+    }
+}
+
+/// Allocates a cookie that associates the `MOVED_FROM` and `MOVED_TO` events of a rename.
+pub fn alloc_rename_cookie() -> u32 {
+    static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+    loop {
+        let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+        // Zero means no cookie.
+        if cookie != 0 {
+            return cookie;
+        }
+    }
+}
@@ -22,9 +22,9 @@ use crate::{
         path::Path,
         registry::{FsProperties, FsType},
         utils::{
-            DirentCounter, DirentVisitor, FallocMode, FileSystem, FsFlags, Inode, InodeMode,
-            InodeType, IoctlCmd, Metadata, MknodType, SuperBlock, XattrName, XattrNamespace,
-            XattrSetFlags, NAME_MAX, XATTR_VALUE_MAX_LEN,
+            DirentCounter, DirentVisitor, Extension, FallocMode, FileSystem, FsFlags, Inode,
+            InodeMode, InodeType, IoctlCmd, Metadata, MknodType, SuperBlock, XattrName,
+            XattrNamespace, XattrSetFlags, NAME_MAX, XATTR_VALUE_MAX_LEN,
         },
     },
     prelude::*,
@@ -95,6 +95,8 @@ struct OverlayInode {
     lowers: Vec<Arc<dyn Inode>>,
     /// Weak fs reference.
     fs: Weak<OverlayFS>,
+    /// The extension of the overlay inode.
+    extension: Extension,
     /// Weak self reference.
     self_: Weak<OverlayInode>,
 }
@@ -171,6 +173,7 @@ impl FileSystem for OverlayFS {
                 .cloned()
                 .collect(),
             fs: self.self_.clone(),
+            extension: Extension::new(),
             self_: weak.clone(),
         })
     }
@@ -270,6 +273,7 @@ impl OverlayInode {
             upper_is_opaque,
             lowers: Vec::new(),
             fs: self.fs.clone(),
+            extension: Extension::new(),
             self_: weak.clone(),
         });
         Ok(new_child)
@@ -696,6 +700,7 @@ impl OverlayInode {
             upper_is_opaque,
             lowers: lower_children,
             fs: self.fs.clone(),
+            extension: Extension::new(),
             self_: weak.clone(),
         });
 
@@ -959,6 +964,10 @@ impl Inode for OverlayInode {
     fn get_xattr(&self, name: XattrName, value_writer: &mut VmWriter) -> Result<usize>;
     fn list_xattr(&self, namespace: XattrNamespace, list_writer: &mut VmWriter) -> Result<usize>;
     fn remove_xattr(&self, name: XattrName) -> Result<()>;
+
+    fn extension(&self) -> Option<&Extension> {
+        Some(&self.extension)
+    }
 }
 
 /// The index of the layer of an `OverlayFS`.
@@ -1115,6 +1124,7 @@ impl FsType for OverlayFsType {
 
     fn create(
         &self,
+        _source: &CStr,
         args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
         ctx: &Context,
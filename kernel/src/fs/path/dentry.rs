@@ -13,9 +13,12 @@ use ostd::sync::RwMutexWriteGuard;
 
 use super::is_dot_or_dotdot;
 use crate::{
-    fs::utils::{
-        FileSystem, Inode, InodeMode, InodeType, Metadata, MknodType, XattrName, XattrNamespace,
-        XattrSetFlags,
+    fs::{
+        notify::{alloc_rename_cookie, FsEvent, FsEventPublisher, FsEvents},
+        utils::{
+            FileSystem, Inode, InodeMode, InodeType, Metadata, MknodType, XattrName,
+            XattrNamespace, XattrSetFlags,
+        },
     },
     prelude::*,
     process::{Gid, Uid},
@@ -166,9 +169,10 @@ impl Dentry {
         let new_child = Dentry::new(new_inode, DentryOptions::Leaf((name.clone(), self.this())));
 
         if new_child.is_dentry_cacheable() {
-            children.upgrade().insert(name, new_child.clone());
+            children.upgrade().insert(name.clone(), new_child.clone());
         }
 
+        self.notify_child_events(FsEvents::CREATE, 0, &name, type_ == InodeType::Dir);
         Ok(new_child)
     }
 
@@ -217,9 +221,10 @@ impl Dentry {
         let new_child = Dentry::new(inode, DentryOptions::Leaf((name.clone(), self.this())));
 
         if new_child.is_dentry_cacheable() {
-            children.upgrade().insert(name, new_child.clone());
+            children.upgrade().insert(name.clone(), new_child.clone());
         }
 
+        self.notify_child_events(FsEvents::CREATE, 0, &name, false);
         Ok(new_child)
     }
 
@@ -243,8 +248,11 @@ impl Dentry {
         );
 
         if dentry.is_dentry_cacheable() {
-            children.upgrade().insert(name, dentry.clone());
+            children.upgrade().insert(name.clone(), dentry.clone());
         }
+
+        old.notify_self_events(FsEvents::ATTRIB);
+        self.notify_child_events(FsEvents::CREATE, 0, &name, false);
         Ok(())
     }
 
@@ -259,8 +267,12 @@ impl Dentry {
 
         self.inode.unlink(name)?;
 
-        let mut children = children.upgrade();
-        children.delete(name);
+        let victim = children.upgrade().delete(name);
+
+        self.notify_child_events(FsEvents::DELETE, 0, name, false);
+        if let Some(victim) = victim {
+            victim.notify_link_removed();
+        }
         Ok(())
     }
 
@@ -275,8 +287,12 @@ impl Dentry {
 
         self.inode.rmdir(name)?;
 
-        let mut children = children.upgrade();
-        children.delete(name);
+        let victim = children.upgrade().delete(name);
+
+        self.notify_child_events(FsEvents::DELETE, 0, name, true);
+        if let Some(victim) = victim {
+            victim.notify_link_removed();
+        }
         Ok(())
     }
 
@@ -290,7 +306,7 @@ impl Dentry {
         }
 
         // The two are the same dentry, we just modify the name
-        if Arc::ptr_eq(&self.this(), new_dir) {
+        let (old_dentry, replaced_dentry) = if Arc::ptr_eq(&self.this(), new_dir) {
             if old_name == new_name {
                 return Ok(());
             }
@@ -298,6 +314,7 @@ impl Dentry {
             let children = self.children.upread();
             let old_dentry = children.check_mountpoint_then_find(old_name)?;
             children.check_mountpoint(new_name)?;
+            let replaced_dentry = children.find(new_name).ok().flatten();
 
             self.inode.rename(old_name, &self.inode, new_name)?;
 
@@ -314,12 +331,15 @@ impl Dentry {
                     children.delete(new_name);
                 }
             }
+
+            (old_dentry, replaced_dentry)
         } else {
             // The two are different dentries
             let (mut self_children, mut new_dir_children) =
                 write_lock_children_on_two_dentries(self, new_dir);
             let old_dentry = self_children.check_mountpoint_then_find(old_name)?;
             new_dir_children.check_mountpoint(new_name)?;
+            let replaced_dentry = new_dir_children.find(new_name).ok().flatten();
 
             self.inode.rename(old_name, &new_dir.inode, new_name)?;
             match old_dentry.as_ref() {
@@ -334,9 +354,183 @@ impl Dentry {
                     new_dir_children.delete(new_name);
                 }
             }
+
+            (old_dentry, replaced_dentry)
+        };
+
+        self.notify_rename(old_name, new_dir, new_name, old_dentry.as_ref());
+        if let Some(replaced_dentry) = replaced_dentry {
+            replaced_dentry.notify_link_removed();
         }
         Ok(())
     }
+
+    /// Sets the mode of the inner inode.
+    pub(super) fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        self.inode.set_mode(mode)?;
+        self.notify_events(FsEvents::ATTRIB);
+        Ok(())
+    }
+
+    /// Sets the owner of the inner inode.
+    pub(super) fn set_owner(&self, uid: Uid) -> Result<()> {
+        self.inode.set_owner(uid)?;
+        self.notify_events(FsEvents::ATTRIB);
+        Ok(())
+    }
+
+    /// Sets the group of the inner inode.
+    pub(super) fn set_group(&self, gid: Gid) -> Result<()> {
+        self.inode.set_group(gid)?;
+        self.notify_events(FsEvents::ATTRIB);
+        Ok(())
+    }
+
+    /// Sets the change time of the inner inode.
+    pub(super) fn set_ctime(&self, time: Duration) {
+        self.inode.set_ctime(time);
+        // The change time is updated whenever the timestamps are changed by the user, so the
+        // `ATTRIB` event is reported here instead of in `set_atime` and `set_mtime`.
+        self.notify_events(FsEvents::ATTRIB);
+    }
+
+    /// Resizes the inner inode.
+    pub(super) fn resize(&self, size: usize) -> Result<()> {
+        self.inode.resize(size)?;
+        self.notify_events(FsEvents::MODIFY);
+        Ok(())
+    }
+
+    /// Sets an extended attribute of the inner inode.
+    pub(super) fn set_xattr(
+        &self,
+        name: XattrName,
+        value_reader: &mut VmReader,
+        flags: XattrSetFlags,
+    ) -> Result<()> {
+        self.inode.set_xattr(name, value_reader, flags)?;
+        self.notify_events(FsEvents::ATTRIB);
+        Ok(())
+    }
+
+    /// Removes an extended attribute of the inner inode.
+    pub(super) fn remove_xattr(&self, name: XattrName) -> Result<()> {
+        self.inode.remove_xattr(name)?;
+        self.notify_events(FsEvents::ATTRIB);
+        Ok(())
+    }
+
+    /// Notifies the watchers of the `Dentry` and the watchers of its parent directory of the
+    /// `events` that happen on the `Dentry`.
+    pub(super) fn notify_events(&self, events: FsEvents) {
+        let events = self.events_with_type(events);
+
+        if let Some(parent) = self.parent() {
+            if let Some(publisher) = FsEventPublisher::get(parent.inode()) {
+                publisher.publish(&FsEvent::new_with_name(events, 0, &self.name()));
+            }
+        }
+
+        if let Some(publisher) = FsEventPublisher::get(&self.inode) {
+            publisher.publish(&FsEvent::new(events));
+        }
+    }
+
+    /// Notifies the watchers of the `Dentry` of the `events` that happen on itself.
+    fn notify_self_events(&self, events: FsEvents) {
+        if let Some(publisher) = FsEventPublisher::get(&self.inode) {
+            publisher.publish(&FsEvent::new(self.events_with_type(events)));
+        }
+    }
+
+    /// Notifies the watchers of the directory `Dentry` of the `events` that happen on the
+    /// entry named `name`.
+    fn notify_child_events(&self, events: FsEvents, cookie: u32, name: &str, is_dir: bool) {
+        let Some(publisher) = FsEventPublisher::get(&self.inode) else {
+            return;
+        };
+
+        let events = if is_dir {
+            events | FsEvents::ISDIR
+        } else {
+            events
+        };
+        publisher.publish(&FsEvent::new_with_name(events, cookie, name));
+    }
+
+    /// Notifies the watchers of the `Dentry` that a link to its inode has been removed.
+    fn notify_link_removed(&self) {
+        let Some(publisher) = FsEventPublisher::get(&self.inode) else {
+            return;
+        };
+
+        // A removed directory is always deleted, since directories cannot have hard links.
+        if self.type_ == InodeType::Dir {
+            publisher.publish(&FsEvent::new(FsEvents::DELETE_SELF | FsEvents::ISDIR));
+            return;
+        }
+
+        publisher.publish(&FsEvent::new(FsEvents::ATTRIB));
+        if self.inode.metadata().nlinks == 0 {
+            publisher.publish(&FsEvent::new(FsEvents::DELETE_SELF));
+        }
+    }
+
+    /// Notifies the watchers of the two directories and the moved `Dentry` of a rename.
+    fn notify_rename(
+        &self,
+        old_name: &str,
+        new_dir: &Arc<Self>,
+        new_name: &str,
+        moved_dentry: Option<&Arc<Self>>,
+    ) {
+        if let Some(moved_dentry) = moved_dentry {
+            moved_dentry.notify_self_events(FsEvents::MOVE_SELF);
+        }
+
+        let old_publisher = FsEventPublisher::get(&self.inode);
+        let new_publisher = FsEventPublisher::get(&new_dir.inode);
+        if old_publisher.is_none() && new_publisher.is_none() {
+            return;
+        }
+
+        let is_dir = match moved_dentry {
+            Some(moved_dentry) => moved_dentry.type_() == InodeType::Dir,
+            None => new_dir
+                .inode
+                .lookup(new_name)
+                .is_ok_and(|inode| inode.type_() == InodeType::Dir),
+        };
+        let type_events = if is_dir {
+            FsEvents::ISDIR
+        } else {
+            FsEvents::empty()
+        };
+
+        let cookie = alloc_rename_cookie();
+        if let Some(publisher) = old_publisher {
+            publisher.publish(&FsEvent::new_with_name(
+                FsEvents::MOVED_FROM | type_events,
+                cookie,
+                old_name,
+            ));
+        }
+        if let Some(publisher) = new_publisher {
+            publisher.publish(&FsEvent::new_with_name(
+                FsEvents::MOVED_TO | type_events,
+                cookie,
+                new_name,
+            ));
+        }
+    }
+
+    fn events_with_type(&self, events: FsEvents) -> FsEvents {
+        if self.type_ == InodeType::Dir {
+            events | FsEvents::ISDIR
+        } else {
+            events
+        }
+    }
 }
 
 #[inherit_methods(from = "self.inode")]
@@ -346,33 +540,21 @@ impl Dentry {
     pub(super) fn sync_data(&self) -> Result<()>;
     pub(super) fn metadata(&self) -> Metadata;
     pub(super) fn mode(&self) -> Result<InodeMode>;
-    pub(super) fn set_mode(&self, mode: InodeMode) -> Result<()>;
     pub(super) fn size(&self) -> usize;
-    pub(super) fn resize(&self, size: usize) -> Result<()>;
     pub(super) fn owner(&self) -> Result<Uid>;
-    pub(super) fn set_owner(&self, uid: Uid) -> Result<()>;
     pub(super) fn group(&self) -> Result<Gid>;
-    pub(super) fn set_group(&self, gid: Gid) -> Result<()>;
     pub(super) fn atime(&self) -> Duration;
     pub(super) fn set_atime(&self, time: Duration);
     pub(super) fn mtime(&self) -> Duration;
     pub(super) fn set_mtime(&self, time: Duration);
     pub(super) fn ctime(&self) -> Duration;
-    pub(super) fn set_ctime(&self, time: Duration);
     pub(super) fn is_dentry_cacheable(&self) -> bool;
-    pub(super) fn set_xattr(
-        &self,
-        name: XattrName,
-        value_reader: &mut VmReader,
-        flags: XattrSetFlags,
-    ) -> Result<()>;
     pub(super) fn get_xattr(&self, name: XattrName, value_writer: &mut VmWriter) -> Result<usize>;
     pub(super) fn list_xattr(
         &self,
         namespace: XattrNamespace,
         list_writer: &mut VmWriter,
     ) -> Result<usize>;
-    pub(super) fn remove_xattr(&self, name: XattrName) -> Result<()>;
 }
 
 impl Debug for Dentry {
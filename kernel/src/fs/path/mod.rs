@@ -6,9 +6,11 @@ use core::time::Duration;
 
 use inherit_methods_macro::inherit_methods;
 pub use mount::Mount;
+pub use mount_namespace::MntNamespace;
 
 use crate::{
     fs::{
+        notify::FsEvents,
         path::dentry::{Dentry, DentryKey},
         utils::{
             FileSystem, Inode, InodeMode, InodeType, Metadata, MknodType, Permission, XattrName,
@@ -21,6 +23,7 @@ use crate::{
 
 mod dentry;
 mod mount;
+mod mount_namespace;
 
 /// A `Path` is used to represent an exact location in the VFS tree.
 ///
@@ -306,6 +309,7 @@ impl Path {
         list_writer: &mut VmWriter,
     ) -> Result<usize>;
     pub fn remove_xattr(&self, name: XattrName) -> Result<()>;
+    pub fn notify_events(&self, events: FsEvents);
 }
 
 /// Checks if the file name is ".", indicating it's the current directory.
@@ -162,6 +162,31 @@ impl Mount {
         new_root_mount
     }
 
+    /// Finds the mount node that corresponds to this mount node in a cloned mount tree.
+    ///
+    /// `old_root` is the root of the mount tree that contains this mount node, and `new_root` is
+    /// the root of the mount tree cloned from `old_root`. Returns `None` if this mount node does
+    /// not belong to the tree of `old_root`.
+    pub(super) fn find_corresponding_mount(
+        &self,
+        old_root: &Mount,
+        new_root: &Arc<Mount>,
+    ) -> Option<Arc<Self>> {
+        let mut mountpoint_keys = Vec::new();
+        let mut mount = self.this();
+        while !core::ptr::eq(Arc::as_ptr(&mount), old_root) {
+            mountpoint_keys.push(mount.mountpoint()?.key());
+            mount = mount.parent()?.upgrade()?;
+        }
+
+        let mut new_mount = new_root.clone();
+        for key in mountpoint_keys.iter().rev() {
+            let child_mount = new_mount.children.read().get(key).cloned()?;
+            new_mount = child_mount;
+        }
+        Some(new_mount)
+    }
+
     /// Detaches the mount node from the parent mount node.
     fn detach_from_parent(&self) {
         if let Some(parent) = self.parent() {
@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{Mount, Path};
+use crate::{
+    fs::{fs_resolver::FsResolver, rootfs::root_mount},
+    prelude::*,
+    process::namespace::alloc_ns_id,
+};
+
+/// A mount namespace.
+///
+/// A mount namespace owns a mount tree. Mounting or unmounting a file system in one namespace
+/// does not affect the mount trees of other namespaces.
+pub struct MntNamespace {
+    id: u64,
+    root: Arc<Mount>,
+}
+
+impl MntNamespace {
+    /// Creates the initial mount namespace, which owns the mount tree of the root file system.
+    pub fn new_init() -> Arc<Self> {
+        Arc::new(Self {
+            id: alloc_ns_id(),
+            root: root_mount().clone(),
+        })
+    }
+
+    /// Creates a new mount namespace with a copy of the mount tree of this namespace.
+    ///
+    /// The root and the current working directory of `resolver` will be moved to the
+    /// corresponding locations in the new mount tree.
+    pub fn new_copy(&self, resolver: &mut FsResolver) -> Arc<Self> {
+        let new_root = self.root.clone_mount_tree(self.root.root_dentry(), true);
+
+        let new_root_path = self.copy_path(resolver.root(), &new_root);
+        let new_cwd_path = self.copy_path(resolver.cwd(), &new_root);
+        resolver.set_root(new_root_path);
+        resolver.set_cwd(new_cwd_path);
+
+        Arc::new(Self {
+            id: alloc_ns_id(),
+            root: new_root,
+        })
+    }
+
+    /// Returns the ID of the namespace.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the root directory of the mount tree.
+    pub fn root_path(&self) -> Path {
+        Path::new_fs_root(self.root.clone())
+    }
+
+    /// Finds the path in the copied mount tree that corresponds to `path`.
+    ///
+    /// If `path` does not belong to the mount tree of this namespace, the root of the copied mount
+    /// tree will be returned.
+    fn copy_path(&self, path: &Path, new_root: &Arc<Mount>) -> Path {
+        match path.mount.find_corresponding_mount(&self.root, new_root) {
+            Some(new_mount) => Path::new(new_mount, path.dentry.clone()),
+            None => Path::new_fs_root(new_root.clone()),
+        }
+    }
+}
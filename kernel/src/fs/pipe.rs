@@ -1,6 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use ostd::mm::{io_util::HasVmReaderWriter, FrameAllocOptions, Infallible, UFrame};
 
 use super::{
     file_handle::FileLike,
@@ -14,7 +19,6 @@ use crate::{
         Gid, Uid,
     },
     time::clocks::RealTimeCoarseClock,
-    util::ring_buffer::{RbConsumer, RbProducer, RingBuffer},
 };
 
 const DEFAULT_PIPE_BUF_SIZE: usize = 65536;
@@ -37,52 +41,371 @@ pub fn new_pair() -> Result<(Arc<PipeReader>, Arc<PipeWriter>)> {
 }
 
 pub fn new_pair_with_capacity(capacity: usize) -> Result<(Arc<PipeReader>, Arc<PipeWriter>)> {
-    let (producer, consumer) = RingBuffer::new(capacity).split();
+    let buffer = Arc::new(Mutex::new(PipeBuffer::new(capacity)));
     let (producer_state, consumer_state) =
         Endpoint::new_pair(EndpointState::default(), EndpointState::default());
 
     Ok((
-        PipeReader::new(consumer, consumer_state, StatusFlags::empty())?,
-        PipeWriter::new(producer, producer_state, StatusFlags::empty())?,
+        PipeReader::new(buffer.clone(), consumer_state, StatusFlags::empty())?,
+        PipeWriter::new(buffer, producer_state, StatusFlags::empty())?,
     ))
 }
 
+/// A part of a page in a pipe.
+///
+/// A page either is allocated by the pipe to hold the written data, or refers to a page owned by
+/// others (e.g., a page in a page cache) so that the data can be moved into the pipe without
+/// being copied.
+#[derive(Clone)]
+pub struct PipePage {
+    frame: UFrame,
+    range: Range<usize>,
+    /// Whether more data can be appended to the page.
+    ///
+    /// This is only true for the last page allocated by the pipe itself. Pages owned by others
+    /// must never be modified by the pipe.
+    is_mergeable: bool,
+}
+
+impl PipePage {
+    /// Creates a page that refers to the bytes of the frame in the range.
+    pub fn new(frame: UFrame, range: Range<usize>) -> Self {
+        debug_assert!(range.start <= range.end && range.end <= PAGE_SIZE);
+
+        Self {
+            frame,
+            range,
+            is_mergeable: false,
+        }
+    }
+
+    /// Returns the number of bytes in the page.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Returns whether the page contains no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Returns a reader to read the bytes in the page.
+    pub fn reader(&self) -> VmReader<'_, Infallible> {
+        let mut reader = self.frame.reader();
+        reader.skip(self.range.start).limit(self.range.len());
+        reader
+    }
+
+    /// Returns a page that refers to the first `len` bytes of the page.
+    fn prefix(&self, len: usize) -> Self {
+        let start = self.range.start;
+        Self::new(self.frame.clone(), start..start + len.min(self.len()))
+    }
+}
+
+/// The buffer of a pipe.
+///
+/// The data is kept in a queue of [`PipePage`]s. The length and the capacity are counted in
+/// bytes. Like Linux, the number of pages is also limited, since a page may hold only a few
+/// bytes (e.g., a page spliced from a file) and cannot be merged with others.
+struct PipeBuffer {
+    pages: VecDeque<PipePage>,
+    len: usize,
+    capacity: usize,
+    /// The maximum number of pages, i.e., the number of page slots in Linux.
+    max_pages: usize,
+}
+
+impl PipeBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            pages: VecDeque::new(),
+            len: 0,
+            capacity,
+            max_pages: capacity.div_ceil(PAGE_SIZE).max(1),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes that can be written to the pipe.
+    ///
+    /// The bytes can be written to the free page slots or appended to the last page.
+    fn free_len(&self) -> usize {
+        let tail_len = self
+            .pages
+            .back()
+            .filter(|page| page.is_mergeable)
+            .map_or(0, |page| PAGE_SIZE - page.range.end);
+        let slots_len = self.free_slots() * PAGE_SIZE + tail_len;
+
+        slots_len.min(self.capacity - self.len)
+    }
+
+    /// Returns the number of pages that can be pushed to the pipe.
+    fn free_slots(&self) -> usize {
+        self.max_pages.saturating_sub(self.pages.len())
+    }
+
+    /// Returns whether a page can be pushed to the pipe.
+    fn can_push(&self) -> bool {
+        self.free_slots() > 0 && self.len < self.capacity
+    }
+
+    /// Copies bytes from the reader to the pipe.
+    fn write_fallible(&mut self, reader: &mut VmReader) -> Result<usize> {
+        let mut written_len = 0;
+
+        while reader.has_remain() && self.free_len() > 0 {
+            let free_len = self.free_len();
+            let page = self.mergeable_tail()?;
+
+            let mut writer = page.frame.writer();
+            writer.skip(page.range.end).limit(free_len);
+            // If a page fault occurs, the partially copied bytes are discarded.
+            let len = match writer.write_fallible(reader) {
+                Ok(len) => len,
+                Err(_) if written_len > 0 => break,
+                Err((err, _)) => return Err(err.into()),
+            };
+
+            page.range.end += len;
+            self.len += len;
+            written_len += len;
+        }
+
+        Ok(written_len)
+    }
+
+    /// Returns the last page if more data can be appended to it, or allocates a new page.
+    fn mergeable_tail(&mut self) -> Result<&mut PipePage> {
+        let is_mergeable = self
+            .pages
+            .back()
+            .is_some_and(|page| page.is_mergeable && page.range.end < PAGE_SIZE);
+
+        if !is_mergeable {
+            let frame = FrameAllocOptions::new().zeroed(false).alloc_frame()?;
+            self.pages.push_back(PipePage {
+                frame: frame.into(),
+                range: 0..0,
+                is_mergeable: true,
+            });
+        }
+
+        Ok(self.pages.back_mut().unwrap())
+    }
+
+    /// Copies bytes from the pipe to the writer.
+    fn read_fallible(&mut self, writer: &mut VmWriter) -> Result<usize> {
+        self.consume_with(writer.avail(), |page| {
+            page.reader()
+                .read_fallible(writer)
+                .map_err(|(err, _)| Error::from(err))
+        })
+    }
+
+    /// Consumes at most `max_len` bytes from the pipe.
+    ///
+    /// The `consume` closure is called with each page and returns the number of bytes consumed
+    /// from the beginning of the page. The consumption stops if a page is partially consumed.
+    fn consume_with<F>(&mut self, max_len: usize, mut consume: F) -> Result<usize>
+    where
+        F: FnMut(&PipePage) -> Result<usize>,
+    {
+        let mut consumed_len = 0;
+
+        while consumed_len < max_len {
+            let Some(page) = self.pages.front_mut() else {
+                break;
+            };
+
+            let page_to_consume = page.prefix(max_len - consumed_len);
+            let len = match consume(&page_to_consume) {
+                Ok(len) => len.min(page_to_consume.len()),
+                Err(_) if consumed_len > 0 => break,
+                Err(err) => return Err(err),
+            };
+
+            page.range.start += len;
+            self.len -= len;
+            consumed_len += len;
+
+            if page.range.is_empty() {
+                self.pages.pop_front();
+            }
+            if len < page_to_consume.len() {
+                break;
+            }
+        }
+
+        Ok(consumed_len)
+    }
+
+    /// Appends pages to the pipe until `max_len` bytes are appended or the pipe is full.
+    ///
+    /// The `next_page` closure is called with the maximum number of bytes that can be appended
+    /// and returns the next page, or `None` if there are no more pages.
+    ///
+    /// Returns the number of bytes appended and whether there are no more pages.
+    fn append_with<F>(&mut self, max_len: usize, mut next_page: F) -> Result<(usize, bool)>
+    where
+        F: FnMut(usize) -> Result<Option<PipePage>>,
+    {
+        let mut appended_len = 0;
+
+        while appended_len < max_len && self.can_push() {
+            let len = (max_len - appended_len).min(self.capacity - self.len);
+            let page = match next_page(len) {
+                Ok(Some(page)) => page.prefix(len),
+                Ok(None) => return Ok((appended_len, true)),
+                Err(_) if appended_len > 0 => break,
+                Err(err) => return Err(err),
+            };
+
+            appended_len += page.len();
+            self.push(page);
+        }
+
+        Ok((appended_len, false))
+    }
+
+    fn push(&mut self, page: PipePage) {
+        if page.is_empty() {
+            return;
+        }
+
+        self.len += page.len();
+        self.pages.push_back(page);
+    }
+
+    /// Moves at most `max_len` bytes to another pipe without copying the data.
+    fn move_to(&mut self, dst: &mut PipeBuffer, max_len: usize) -> usize {
+        let max_len = max_len.min(dst.capacity - dst.len);
+        self.consume_with(max_len, |page| {
+            // Stop moving if there are no page slots in the other pipe.
+            if !dst.can_push() {
+                return Ok(0);
+            }
+            dst.push(page.clone());
+            Ok(page.len())
+        })
+        .unwrap()
+    }
+
+    /// Duplicates at most `max_len` bytes to another pipe without copying the data.
+    fn duplicate_to(&self, dst: &mut PipeBuffer, max_len: usize) -> usize {
+        let max_len = max_len.min(dst.capacity - dst.len);
+        let mut duplicated_len = 0;
+
+        for page in self.pages.iter() {
+            if duplicated_len >= max_len || !dst.can_push() {
+                break;
+            }
+
+            // Both pages refer to the same frame, but the duplicated one is not mergeable. So the
+            // data appended later to the original page will not be visible in the other pipe.
+            let page = page.prefix(max_len - duplicated_len);
+            duplicated_len += page.len();
+            dst.push(page);
+        }
+
+        duplicated_len
+    }
+}
+
 pub struct PipeReader {
-    consumer: Mutex<RbConsumer<u8>>,
+    buffer: Arc<Mutex<PipeBuffer>>,
     state: Endpoint<EndpointState>,
     status_flags: AtomicU32,
 }
 
 impl PipeReader {
     fn new(
-        consumer: RbConsumer<u8>,
+        buffer: Arc<Mutex<PipeBuffer>>,
         state: Endpoint<EndpointState>,
         status_flags: StatusFlags,
     ) -> Result<Arc<Self>> {
         check_status_flags(status_flags)?;
 
         Ok(Arc::new(Self {
-            consumer: Mutex::new(consumer),
+            buffer,
             state,
             status_flags: AtomicU32::new(status_flags.bits()),
         }))
     }
 
+    /// Reads from the pipe to the writer.
+    ///
+    /// Unlike [`FileLike::read`], whether the read blocks is decided by `is_nonblocking` instead
+    /// of the status flags of the pipe.
+    pub fn read_with_nonblocking(
+        &self,
+        writer: &mut VmWriter,
+        is_nonblocking: bool,
+    ) -> Result<usize> {
+        if !writer.has_avail() {
+            // Even the peer endpoint (`PipeWriter`) has been closed, reading an empty buffer is
+            // still fine.
+            return Ok(0);
+        }
+
+        if is_nonblocking {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    /// Moves at most `max_len` bytes out of the pipe page by page.
+    ///
+    /// The `consume` closure is called with each page and returns the number of bytes consumed
+    /// from the beginning of the page. The pages can be consumed without copying the data (e.g.,
+    /// by writing them to a file or by moving them into another pipe).
+    pub fn splice_with<F>(&self, max_len: usize, is_nonblocking: bool, consume: F) -> Result<usize>
+    where
+        F: FnMut(&PipePage) -> Result<usize>,
+    {
+        if max_len == 0 {
+            return Ok(0);
+        }
+
+        if !is_nonblocking {
+            self.wait_for_data()?;
+        }
+
+        self.state
+            .read_with(|| self.buffer.lock().consume_with(max_len, consume))
+    }
+
     fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
         let read = || {
-            let mut consumer = self.consumer.lock();
-            consumer.read_fallible(writer)
+            let mut buffer = self.buffer.lock();
+            buffer.read_fallible(writer)
         };
 
         self.state.read_with(read)
     }
 
+    /// Waits until there is data in the pipe or the write end is closed.
+    fn wait_for_data(&self) -> Result<()> {
+        self.wait_events(IoEvents::IN, None, || {
+            if self.state.is_peer_shutdown() || !self.buffer.lock().is_empty() {
+                Ok(())
+            } else {
+                return_errno_with_message!(Errno::EAGAIN, "the pipe is empty");
+            }
+        })
+    }
+
     fn check_io_events(&self) -> IoEvents {
         let mut events = IoEvents::empty();
         if self.state.is_peer_shutdown() {
             events |= IoEvents::HUP;
         }
-        if !self.consumer.lock().is_empty() {
+        if !self.buffer.lock().is_empty() {
             events |= IoEvents::IN;
         }
         events
@@ -98,17 +421,8 @@ impl Pollable for PipeReader {
 
 impl FileLike for PipeReader {
     fn read(&self, writer: &mut VmWriter) -> Result<usize> {
-        if !writer.has_avail() {
-            // Even the peer endpoint (`PipeWriter`) has been closed, reading an empty buffer is
-            // still fine.
-            return Ok(0);
-        }
-
-        if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
-            self.try_read(writer)
-        } else {
-            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
-        }
+        let is_nonblocking = self.status_flags().contains(StatusFlags::O_NONBLOCK);
+        self.read_with_nonblocking(writer, is_nonblocking)
     }
 
     fn status_flags(&self) -> StatusFlags {
@@ -156,43 +470,113 @@ impl Drop for PipeReader {
 }
 
 pub struct PipeWriter {
-    producer: Mutex<RbProducer<u8>>,
+    buffer: Arc<Mutex<PipeBuffer>>,
     state: Endpoint<EndpointState>,
     status_flags: AtomicU32,
 }
 
 impl PipeWriter {
     fn new(
-        producer: RbProducer<u8>,
+        buffer: Arc<Mutex<PipeBuffer>>,
         state: Endpoint<EndpointState>,
         status_flags: StatusFlags,
     ) -> Result<Arc<Self>> {
         check_status_flags(status_flags)?;
 
         Ok(Arc::new(Self {
-            producer: Mutex::new(producer),
+            buffer,
             state,
             status_flags: AtomicU32::new(status_flags.bits()),
         }))
     }
 
+    /// Writes to the pipe from the reader.
+    ///
+    /// Unlike [`FileLike::write`], whether the write blocks is decided by `is_nonblocking`
+    /// instead of the status flags of the pipe.
+    pub fn write_with_nonblocking(
+        &self,
+        reader: &mut VmReader,
+        is_nonblocking: bool,
+    ) -> Result<usize> {
+        if !reader.has_remain() {
+            // Even the peer endpoint (`PipeReader`) has been closed, writing an empty buffer is
+            // still fine.
+            return Ok(0);
+        }
+
+        if is_nonblocking {
+            self.try_write(reader)
+        } else {
+            self.wait_events(IoEvents::OUT, None, || self.try_write(reader))
+        }
+    }
+
+    /// Moves at most `max_len` bytes into the pipe page by page.
+    ///
+    /// The `next_page` closure is called with the maximum number of bytes that the pipe can accept
+    /// and returns the next page, or `None` if there are no more pages. The pages are kept in the
+    /// pipe without copying the data.
+    ///
+    /// Unlike normal writes, the data is never written atomically.
+    pub fn splice_with<F>(
+        &self,
+        max_len: usize,
+        is_nonblocking: bool,
+        next_page: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(usize) -> Result<Option<PipePage>>,
+    {
+        if max_len == 0 {
+            return Ok(0);
+        }
+
+        if !is_nonblocking {
+            self.wait_for_space()?;
+        }
+
+        let mut is_end = false;
+        let res = self.state.write_with(|| {
+            let (len, no_more_pages) = self.buffer.lock().append_with(max_len, next_page)?;
+            is_end = no_more_pages;
+            Ok(len)
+        });
+
+        match res {
+            Err(err) if err.error() == Errno::EAGAIN && is_end => Ok(0),
+            res => res,
+        }
+    }
+
     fn try_write(&self, reader: &mut VmReader) -> Result<usize> {
         let write = || {
-            let mut producer = self.producer.lock();
-            if reader.remain() <= PIPE_BUF && producer.free_len() < reader.remain() {
+            let mut buffer = self.buffer.lock();
+            if reader.remain() <= PIPE_BUF && buffer.free_len() < reader.remain() {
                 // No sufficient space for an atomic write
                 return Ok(0);
             }
-            producer.write_fallible(reader)
+            buffer.write_fallible(reader)
         };
 
         self.state.write_with(write)
     }
 
+    /// Waits until a page can be pushed to the pipe or the read end is closed.
+    fn wait_for_space(&self) -> Result<()> {
+        self.wait_events(IoEvents::OUT, None, || {
+            if self.state.is_shutdown() || self.buffer.lock().can_push() {
+                Ok(())
+            } else {
+                return_errno_with_message!(Errno::EAGAIN, "the pipe is full");
+            }
+        })
+    }
+
     fn check_io_events(&self) -> IoEvents {
         if self.state.is_shutdown() {
             IoEvents::ERR | IoEvents::OUT
-        } else if self.producer.lock().free_len() >= PIPE_BUF {
+        } else if self.buffer.lock().free_len() >= PIPE_BUF {
             IoEvents::OUT
         } else {
             IoEvents::empty()
@@ -209,17 +593,8 @@ impl Pollable for PipeWriter {
 
 impl FileLike for PipeWriter {
     fn write(&self, reader: &mut VmReader) -> Result<usize> {
-        if !reader.has_remain() {
-            // Even the peer endpoint (`PipeReader`) has been closed, writing an empty buffer is
-            // still fine.
-            return Ok(0);
-        }
-
-        if self.status_flags().contains(StatusFlags::O_NONBLOCK) {
-            self.try_write(reader)
-        } else {
-            self.wait_events(IoEvents::OUT, None, || self.try_write(reader))
-        }
+        let is_nonblocking = self.status_flags().contains(StatusFlags::O_NONBLOCK);
+        self.write_with_nonblocking(reader, is_nonblocking)
     }
 
     fn status_flags(&self) -> StatusFlags {
@@ -260,6 +635,74 @@ impl FileLike for PipeWriter {
     }
 }
 
+/// Moves at most `max_len` bytes from one pipe to another without copying the data.
+pub fn splice_pipe(
+    reader: &PipeReader,
+    writer: &PipeWriter,
+    max_len: usize,
+    is_nonblocking: bool,
+) -> Result<usize> {
+    transfer_pipe(reader, writer, max_len, is_nonblocking, |src, dst| {
+        reader
+            .state
+            .read_with(|| writer.state.write_with(|| Ok(src.move_to(dst, max_len))))
+    })
+}
+
+/// Duplicates at most `max_len` bytes from one pipe to another without consuming or copying the
+/// data.
+pub fn tee_pipe(
+    reader: &PipeReader,
+    writer: &PipeWriter,
+    max_len: usize,
+    is_nonblocking: bool,
+) -> Result<usize> {
+    transfer_pipe(reader, writer, max_len, is_nonblocking, |src, dst| {
+        writer
+            .state
+            .write_with(|| Ok(src.duplicate_to(dst, max_len)))
+    })
+}
+
+fn transfer_pipe<F>(
+    reader: &PipeReader,
+    writer: &PipeWriter,
+    max_len: usize,
+    is_nonblocking: bool,
+    transfer: F,
+) -> Result<usize>
+where
+    F: FnOnce(&mut PipeBuffer, &mut PipeBuffer) -> Result<usize>,
+{
+    if Arc::ptr_eq(&reader.buffer, &writer.buffer) {
+        return_errno_with_message!(Errno::EINVAL, "the pipes are the same");
+    }
+    if max_len == 0 {
+        return Ok(0);
+    }
+
+    if !is_nonblocking {
+        reader.wait_for_data()?;
+        writer.wait_for_space()?;
+    }
+
+    // Lock the buffers in a fixed order to avoid deadlocks.
+    let (mut src, mut dst) = if Arc::as_ptr(&reader.buffer) < Arc::as_ptr(&writer.buffer) {
+        let src = reader.buffer.lock();
+        (src, writer.buffer.lock())
+    } else {
+        let dst = writer.buffer.lock();
+        (reader.buffer.lock(), dst)
+    };
+
+    if src.is_empty() {
+        // If the write end of the source pipe has been closed, this is the end-of-file (EOF).
+        return reader.state.read_with(|| Ok(0));
+    }
+
+    transfer(&mut *src, &mut *dst)
+}
+
 fn check_status_flags(status_flags: StatusFlags) -> Result<()> {
     if status_flags.contains(StatusFlags::O_DIRECT) {
         // "O_DIRECT .. Older kernels that do not support this flag will indicate this via an
@@ -415,6 +858,44 @@ mod test {
         );
     }
 
+    #[ktest]
+    fn test_page_slots() {
+        let (reader, writer) = new_pair().unwrap();
+        let frame: UFrame = FrameAllocOptions::new().alloc_frame().unwrap().into();
+        let next_page = |_| Ok(Some(PipePage::new(frame.clone(), 0..1)));
+
+        // Each page occupies a slot, even if it holds only one byte.
+        let num_slots = DEFAULT_PIPE_BUF_SIZE / PAGE_SIZE;
+        assert_eq!(
+            writer.splice_with(usize::MAX, true, next_page).unwrap(),
+            num_slots
+        );
+        assert_eq!(
+            writer
+                .splice_with(usize::MAX, true, next_page)
+                .unwrap_err()
+                .error(),
+            Errno::EAGAIN
+        );
+        assert_eq!(
+            writer
+                .write_with_nonblocking(&mut reader_from(&[1]), true)
+                .unwrap_err()
+                .error(),
+            Errno::EAGAIN
+        );
+
+        // Consuming a page frees its slot.
+        let mut buf = [0; 1];
+        assert_eq!(reader.read(&mut writer_from(&mut buf)).unwrap(), 1);
+        assert_eq!(
+            writer
+                .write_with_nonblocking(&mut reader_from(&[1]), true)
+                .unwrap(),
+            1
+        );
+    }
+
     fn reader_from(buf: &[u8]) -> VmReader {
         VmReader::from(buf).to_fallible()
     }
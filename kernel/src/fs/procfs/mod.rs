@@ -95,6 +95,7 @@ impl FsType for ProcFsType {
 
     fn create(
         &self,
+        _source: &CStr,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
         _ctx: &Context,
@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_rights::Full;
+
+use crate::{
+    events::IoEvents,
+    fs::{
+        inode_handle::FileIo,
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::{AccessMode, Inode, InodeMode, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable},
+    },
+    vm::vmar::Vmar,
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/mem`.
+///
+/// The file offsets are the virtual addresses in the process. Opening the file requires the same
+/// permission as `ptrace`, and the opened file accesses the memory of the process through the
+/// address space at the time of opening.
+pub struct MemFileOps(Arc<Process>);
+
+impl MemFileOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        let credentials = process_ref
+            .main_thread()
+            .as_posix_thread()
+            .unwrap()
+            .credentials();
+
+        let inode = ProcFileBuilder::new(Self(process_ref))
+            .parent(parent)
+            .build()
+            .unwrap();
+        // The file is only accessible to the owner of the process.
+        inode
+            .set_mode(InodeMode::from_bits_truncate(0o600))
+            .unwrap();
+        inode.set_owner(credentials.euid()).unwrap();
+        inode.set_group(credentials.egid()).unwrap();
+
+        inode
+    }
+}
+
+impl FileOps for MemFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        // The memory must be accessed at the offsets by `read_at` and `write_at`.
+        return_errno_with_message!(Errno::EIO, "the memory cannot be read as a whole");
+    }
+
+    fn open(
+        &self,
+        _access_mode: AccessMode,
+        _status_flags: StatusFlags,
+    ) -> Option<Result<Arc<dyn FileIo>>> {
+        Some(MemFile::open(&self.0).map(|file| Arc::new(file) as _))
+    }
+}
+
+/// An opened `/proc/[pid]/mem` file.
+struct MemFile {
+    process: Weak<Process>,
+    /// The address space of the process when the file is opened.
+    vmar: Option<Vmar<Full>>,
+}
+
+impl MemFile {
+    fn open(process: &Arc<Process>) -> Result<Self> {
+        // Like Linux, the permission is checked only once when the file is opened. Checking it
+        // on each access would be wrong, since the opened file can be passed to a privileged
+        // program (e.g., via `execve` of a set-user-ID program), which accesses the memory of
+        // itself through the file.
+        let current_thread = current_thread!();
+        let accessor = current_thread.as_posix_thread().unwrap();
+        process
+            .main_thread()
+            .as_posix_thread()
+            .unwrap()
+            .check_ptrace_access(accessor)
+            .map_err(|_| {
+                Error::with_message(Errno::EACCES, "the memory of the process is not accessible")
+            })?;
+
+        let vmar = process
+            .lock_root_vmar()
+            .as_ref()
+            .map(|vmar| vmar.dup().unwrap());
+
+        Ok(Self {
+            process: Arc::downgrade(process),
+            vmar,
+        })
+    }
+
+    fn with_vmar<F>(&self, f: F) -> Result<usize>
+    where
+        F: FnOnce(&Vmar<Full>) -> Result<usize>,
+    {
+        // Like Linux, an end of file is reported if the address space is no longer used by the
+        // process, i.e., the process has exited or has executed a new program.
+        let (Some(process), Some(vmar)) = (self.process.upgrade(), self.vmar.as_ref()) else {
+            return Ok(0);
+        };
+        if process.lock_root_vmar().as_ref() != Some(vmar) {
+            return Ok(0);
+        }
+
+        f(vmar)
+    }
+}
+
+impl Pollable for MemFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for MemFile {
+    fn read(&self, _writer: &mut VmWriter, _status_flags: StatusFlags) -> Result<usize> {
+        unreachable!("the memory is always read at the offsets")
+    }
+
+    fn write(&self, _reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        unreachable!("the memory is always written at the offsets")
+    }
+
+    fn is_offset_aware(&self) -> bool {
+        true
+    }
+
+    fn read_at(
+        &self,
+        offset: usize,
+        writer: &mut VmWriter,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let len = writer.avail();
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.with_vmar(|vmar| {
+            let read_len = vmar.read_remote_to(offset, len, writer)?;
+            if read_len == 0 {
+                return_errno_with_message!(Errno::EIO, "the memory cannot be read");
+            }
+            Ok(read_len)
+        })
+    }
+
+    fn write_at(
+        &self,
+        offset: usize,
+        reader: &mut VmReader,
+        _status_flags: StatusFlags,
+    ) -> Result<usize> {
+        let len = reader.remain();
+        if len == 0 {
+            return Ok(0);
+        }
+
+        // Like `ptrace`, the private mappings can be written even if they are not writable.
+        self.with_vmar(|vmar| {
+            let written_len = vmar.write_remote_from(offset, len, reader, true)?;
+            if written_len == 0 {
+                return_errno_with_message!(Errno::EIO, "the memory cannot be written");
+            }
+            Ok(written_len)
+        })
+    }
+}
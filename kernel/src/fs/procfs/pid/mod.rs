@@ -2,7 +2,8 @@
 
 use self::{
     cmdline::CmdlineFileOps, comm::CommFileOps, environ::EnvironFileOps, exe::ExeSymOps,
-    fd::FdDirOps, stat::StatFileOps, status::StatusFileOps, task::TaskDirOps,
+    fd::FdDirOps, mem::MemFileOps, ns::NsDirOps, stat::StatFileOps, status::StatusFileOps,
+    task::TaskDirOps,
 };
 use super::template::{DirOps, ProcDir, ProcDirBuilder};
 use crate::{
@@ -20,6 +21,8 @@ mod comm;
 mod environ;
 mod exe;
 mod fd;
+mod mem;
+mod ns;
 mod stat;
 mod status;
 mod task;
@@ -67,6 +70,8 @@ impl DirOps for PidDirOps {
             "comm" => CommFileOps::new_inode(self.0.clone(), this_ptr.clone()),
             "fd" => FdDirOps::new_inode(self.0.clone(), this_ptr.clone()),
             "cmdline" => CmdlineFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "mem" => MemFileOps::new_inode(self.0.clone(), this_ptr.clone()),
+            "ns" => NsDirOps::new_inode(self.0.clone(), this_ptr.clone()),
             "status" => {
                 StatusFileOps::new_inode(self.0.clone(), self.0.main_thread(), this_ptr.clone())
             }
@@ -100,6 +105,12 @@ impl DirOps for PidDirOps {
         cached_children.put_entry_if_not_found("cmdline", || {
             CmdlineFileOps::new_inode(self.0.clone(), this_ptr.clone())
         });
+        cached_children.put_entry_if_not_found("mem", || {
+            MemFileOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
+        cached_children.put_entry_if_not_found("ns", || {
+            NsDirOps::new_inode(self.0.clone(), this_ptr.clone())
+        });
         cached_children.put_entry_if_not_found("status", || {
             StatusFileOps::new_inode(self.0.clone(), self.0.main_thread(), this_ptr.clone())
         });
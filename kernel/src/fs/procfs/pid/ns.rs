@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::{
+        inode_handle::FileIo,
+        procfs::{
+            template::{FileOps, ProcFileBuilder},
+            DirOps, ProcDir, ProcDirBuilder, ProcSymBuilder, SymOps,
+        },
+        utils::{AccessMode, DirEntryVecExt, Inode, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        namespace::{Namespace, NsFile},
+        posix_thread::AsPosixThread,
+    },
+    Process,
+};
+
+/// Represents the inode at `/proc/[pid]/ns`.
+///
+/// Like Linux, each entry is a symbolic link whose target is `[type]:[[id]]`, e.g.,
+/// `uts:[4026531838]`. Since the target is a relative path, it is resolved in this directory,
+/// where it refers to a namespace file (see [`NsFileOps`]). The namespace files are not listed in
+/// the directory.
+pub struct NsDirOps(Arc<Process>);
+
+impl NsDirOps {
+    pub fn new_inode(process_ref: Arc<Process>, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcDirBuilder::new(Self(process_ref))
+            .parent(parent)
+            .build()
+            .unwrap()
+    }
+}
+
+/// The names of the namespace files.
+const NS_NAMES: [&str; 5] = ["ipc", "mnt", "pid", "pid_for_children", "uts"];
+
+impl DirOps for NsDirOps {
+    fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
+        if let Some(name) = NS_NAMES.into_iter().find(|ns_name| *ns_name == name) {
+            return Ok(NsSymOps::new_inode(self.0.clone(), name, this_ptr));
+        }
+
+        // Look up the target of a symbolic link.
+        let ns = NS_NAMES
+            .into_iter()
+            .map(|ns_name| ns_of_process(&self.0, ns_name))
+            .find(|ns| link_target(ns) == name)
+            .ok_or_else(|| Error::new(Errno::ENOENT))?;
+        Ok(NsFileOps::new_inode(ns, this_ptr))
+    }
+
+    fn populate_children(&self, this_ptr: Weak<dyn Inode>) {
+        let this = {
+            let this = this_ptr.upgrade().unwrap();
+            this.downcast_ref::<ProcDir<NsDirOps>>().unwrap().this()
+        };
+        let mut cached_children = this.cached_children().write();
+
+        for name in NS_NAMES {
+            cached_children.put_entry_if_not_found(name, || {
+                NsSymOps::new_inode(self.0.clone(), name, this_ptr.clone())
+            });
+        }
+    }
+
+    fn is_child_cacheable(&self, name: &str) -> bool {
+        // The namespace files may become stale once the process enters other namespaces.
+        NS_NAMES.contains(&name)
+    }
+}
+
+/// Represents the inode at `/proc/[pid]/ns/[name]`.
+struct NsSymOps {
+    process: Arc<Process>,
+    name: &'static str,
+}
+
+impl NsSymOps {
+    pub fn new_inode(
+        process_ref: Arc<Process>,
+        name: &'static str,
+        parent: Weak<dyn Inode>,
+    ) -> Arc<dyn Inode> {
+        ProcSymBuilder::new(Self {
+            process: process_ref,
+            name,
+        })
+        .parent(parent)
+        .build()
+        .unwrap()
+    }
+}
+
+impl SymOps for NsSymOps {
+    fn read_link(&self) -> Result<String> {
+        Ok(link_target(&ns_of_process(&self.process, self.name)))
+    }
+}
+
+/// Represents the namespace file that is the target of `/proc/[pid]/ns/[name]`.
+struct NsFileOps(Namespace);
+
+impl NsFileOps {
+    pub fn new_inode(ns: Namespace, parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self(ns))
+            .parent(parent)
+            .volatile()
+            .build()
+            .unwrap()
+    }
+}
+
+impl FileOps for NsFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        return_errno_with_message!(Errno::EINVAL, "namespace files cannot be read");
+    }
+
+    fn open(
+        &self,
+        _access_mode: AccessMode,
+        _status_flags: StatusFlags,
+    ) -> Option<Result<Arc<dyn FileIo>>> {
+        Some(Ok(Arc::new(NsFile::new(self.0.clone()))))
+    }
+}
+
+/// Returns the namespace of the process for the namespace file name.
+fn ns_of_process(process: &Process, name: &str) -> Namespace {
+    let main_thread = process.main_thread();
+    let posix_thread = main_thread.as_posix_thread().unwrap();
+    let ns_proxy = posix_thread.ns_proxy();
+
+    match name {
+        "ipc" => Namespace::Ipc(ns_proxy.ipc_ns().clone()),
+        "mnt" => Namespace::Mnt(ns_proxy.mnt_ns().clone()),
+        "pid" => Namespace::Pid(posix_thread.pid_ns().clone()),
+        "pid_for_children" => Namespace::Pid(ns_proxy.pid_ns_for_children().clone()),
+        "uts" => Namespace::Uts(ns_proxy.uts_ns().clone()),
+        _ => unreachable!(),
+    }
+}
+
+fn link_target(ns: &Namespace) -> String {
+    format!("{}:[{}]", ns.type_name(), ns.id())
+}
@@ -111,7 +111,12 @@ impl FileOps for StatFileOps {
         //
         // Reference: <https://github.com/torvalds/linux/blob/0ff41df1cb268fc69e703a08a57ee14ae967d0ca/fs/proc/array.c#L467-L681>
 
-        let pid = posix_thread.tid();
+        // The IDs are seen from the PID namespace of the reader. Those that are invisible in the
+        // namespace are shown as zero.
+        let current_thread = current_thread!();
+        let pid_ns = current_thread.as_posix_thread().unwrap().pid_ns();
+
+        let pid = pid_ns.to_local(posix_thread.tid());
         let comm = posix_thread
             .thread_name()
             .lock()
@@ -119,9 +124,9 @@ impl FileOps for StatFileOps {
             .and_then(|name| name.as_string())
             .unwrap_or_else(|| process.executable_path());
         let state = if thread.is_exited() { 'Z' } else { 'R' };
-        let ppid = process.parent().pid();
-        let pgrp = process.pgid();
-        let session = process.sid();
+        let ppid = pid_ns.to_local(process.parent().pid());
+        let pgrp = pid_ns.to_local(process.pgid());
+        let session = pid_ns.to_local(process.sid());
 
         let (tty_nr, tpgid) = if let Some(terminal) = process.terminal() {
             (
@@ -129,7 +134,7 @@ impl FileOps for StatFileOps {
                 terminal
                     .job_control()
                     .foreground()
-                    .map(|pgrp| pgrp.pgid() as i64)
+                    .map(|pgrp| pid_ns.to_local(pgrp.pgid()) as i64)
                     .unwrap_or(-1),
             )
         } else {
@@ -53,6 +53,7 @@ use crate::{
 /// - CapEff: Effective capabilities.
 /// - CapBnd: Bounding set.
 /// - CapAmb: Ambient capabilities.
+/// - NoNewPrivs: Whether the `no_new_privs` attribute is set.
 /// - Seccomp: Seccomp mode.
 /// - Cpus_allowed: CPUs allowed for this process.
 /// - Cpus_allowed_list: List of CPUs allowed for this process.
@@ -115,10 +116,27 @@ impl FileOps for StatusFileOps {
         };
         writeln!(status_output, "State:\t{}", state).unwrap();
 
-        writeln!(status_output, "Tgid:\t{}", process.pid()).unwrap();
-        writeln!(status_output, "Pid:\t{}", posix_thread.tid()).unwrap();
-        writeln!(status_output, "PPid:\t{}", process.parent().pid()).unwrap();
-        writeln!(status_output, "TracerPid:\t{}", 0).unwrap();
+        // The IDs are seen from the PID namespace of the reader. Those that are invisible in the
+        // namespace are shown as zero.
+        let current_thread = current_thread!();
+        let pid_ns = current_thread.as_posix_thread().unwrap().pid_ns();
+        writeln!(status_output, "Tgid:\t{}", pid_ns.to_local(process.pid())).unwrap();
+        writeln!(
+            status_output,
+            "Pid:\t{}",
+            pid_ns.to_local(posix_thread.tid())
+        )
+        .unwrap();
+        writeln!(
+            status_output,
+            "PPid:\t{}",
+            pid_ns.to_local(process.parent().pid())
+        )
+        .unwrap();
+        let tracer_pid = posix_thread
+            .tracer()
+            .map_or(0, |tracer| pid_ns.to_local(tracer.pid()));
+        writeln!(status_output, "TracerPid:\t{}", tracer_pid).unwrap();
         writeln!(
             status_output,
             "FDSize:\t{}",
@@ -133,13 +151,14 @@ impl FileOps for StatusFileOps {
 
         if let Some(vmar_ref) = process.lock_root_vmar().as_ref() {
             let vsize = vmar_ref.get_mappings_total_size();
+            let locked = vmar_ref.get_locked_size() / 1024;
             let anon = vmar_ref.get_rss_counter(RssType::RSS_ANONPAGES) * (PAGE_SIZE / 1024);
             let file = vmar_ref.get_rss_counter(RssType::RSS_FILEPAGES) * (PAGE_SIZE / 1024);
             let rss = anon + file;
             writeln!(
                 status_output,
-                "VmSize:\t{} kB\nVmRSS:\t{} kB\nRssAnon:\t{} kB\nRssFile:\t{} kB",
-                vsize, rss, anon, file
+                "VmSize:\t{} kB\nVmLck:\t{} kB\nVmRSS:\t{} kB\nRssAnon:\t{} kB\nRssFile:\t{} kB",
+                vsize, locked, rss, anon, file
             )
             .unwrap();
         }
@@ -153,6 +172,19 @@ impl FileOps for StatusFileOps {
             .unwrap();
         }
 
+        writeln!(
+            status_output,
+            "NoNewPrivs:\t{}",
+            posix_thread.no_new_privs() as u8
+        )
+        .unwrap();
+        writeln!(
+            status_output,
+            "Seccomp:\t{}",
+            posix_thread.seccomp_mode().as_u32()
+        )
+        .unwrap();
+
         Ok(status_output.into_bytes())
     }
 }
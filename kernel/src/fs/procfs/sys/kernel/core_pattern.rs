@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::{Inode, InodeMode},
+    },
+    prelude::*,
+    process::{core_pattern, set_core_pattern, MAX_CORE_PATTERN_LEN},
+};
+
+/// Represents the inode at `/proc/sys/kernel/core_pattern`.
+pub struct CorePatternFileOps;
+
+impl CorePatternFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        let inode = ProcFileBuilder::new(Self).parent(parent).build().unwrap();
+        // The pattern can be changed by the owner (i.e., the root user).
+        inode
+            .set_mode(InodeMode::from_bits_truncate(0o644))
+            .unwrap();
+
+        inode
+    }
+}
+
+impl FileOps for CorePatternFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", core_pattern());
+        Ok(output.into_bytes())
+    }
+
+    fn write_at(&self, _offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let len = reader.remain();
+
+        // Bytes beyond the maximum length are ignored, like Linux does.
+        let mut buf = vec![0u8; len.min(MAX_CORE_PATTERN_LEN)];
+        reader.read_fallible(&mut VmWriter::from(buf.as_mut_slice()))?;
+
+        // The pattern ends at the first newline or null byte.
+        let pattern_len = buf
+            .iter()
+            .position(|&byte| byte == b'\n' || byte == 0)
+            .unwrap_or(buf.len());
+        let Ok(pattern) = core::str::from_utf8(&buf[..pattern_len]) else {
+            return_errno_with_message!(Errno::EINVAL, "the core pattern is not valid UTF-8");
+        };
+        set_core_pattern(pattern);
+
+        Ok(len)
+    }
+
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        // Like Linux, truncating the file is ignored, so that the file can be opened with
+        // `O_TRUNC` (e.g., by shell redirections).
+        Ok(())
+    }
+}
@@ -3,7 +3,10 @@
 use crate::{
     fs::{
         procfs::{
-            sys::kernel::{cap_last_cap::CapLastCapFileOps, pid_max::PidMaxFileOps},
+            sys::kernel::{
+                cap_last_cap::CapLastCapFileOps, core_pattern::CorePatternFileOps,
+                msgmax::MsgMaxFileOps, msgmnb::MsgMnbFileOps, pid_max::PidMaxFileOps,
+            },
             template::{DirOps, ProcDirBuilder},
             ProcDir,
         },
@@ -13,6 +16,9 @@ use crate::{
 };
 
 mod cap_last_cap;
+mod core_pattern;
+mod msgmax;
+mod msgmnb;
 mod pid_max;
 
 /// Represents the inode at `/proc/sys/kernel`.
@@ -28,6 +34,9 @@ impl DirOps for KernelDirOps {
     fn lookup_child(&self, this_ptr: Weak<dyn Inode>, name: &str) -> Result<Arc<dyn Inode>> {
         let inode = match name {
             "cap_last_cap" => CapLastCapFileOps::new_inode(this_ptr.clone()),
+            "core_pattern" => CorePatternFileOps::new_inode(this_ptr.clone()),
+            "msgmax" => MsgMaxFileOps::new_inode(this_ptr.clone()),
+            "msgmnb" => MsgMnbFileOps::new_inode(this_ptr.clone()),
             "pid_max" => PidMaxFileOps::new_inode(this_ptr.clone()),
             _ => return_errno!(Errno::ENOENT),
         };
@@ -43,6 +52,13 @@ impl DirOps for KernelDirOps {
         cached_children.put_entry_if_not_found("cap_last_cap", || {
             CapLastCapFileOps::new_inode(this_ptr.clone())
         });
+        cached_children.put_entry_if_not_found("core_pattern", || {
+            CorePatternFileOps::new_inode(this_ptr.clone())
+        });
+        cached_children
+            .put_entry_if_not_found("msgmax", || MsgMaxFileOps::new_inode(this_ptr.clone()));
+        cached_children
+            .put_entry_if_not_found("msgmnb", || MsgMnbFileOps::new_inode(this_ptr.clone()));
         cached_children
             .put_entry_if_not_found("pid_max", || PidMaxFileOps::new_inode(this_ptr.clone()));
     }
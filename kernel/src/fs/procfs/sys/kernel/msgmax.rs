@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    ipc::msg::MSGMAX,
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/kernel/msgmax`.
+pub struct MsgMaxFileOps;
+
+impl MsgMaxFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for MsgMaxFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", MSGMAX);
+        Ok(output.into_bytes())
+    }
+}
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::format;
+
+use crate::{
+    fs::{
+        procfs::template::{FileOps, ProcFileBuilder},
+        utils::Inode,
+    },
+    ipc::msg::MSGMNB,
+    prelude::*,
+};
+
+/// Represents the inode at `/proc/sys/kernel/msgmnb`.
+pub struct MsgMnbFileOps;
+
+impl MsgMnbFileOps {
+    pub fn new_inode(parent: Weak<dyn Inode>) -> Arc<dyn Inode> {
+        ProcFileBuilder::new(Self).parent(parent).build().unwrap()
+    }
+}
+
+impl FileOps for MsgMnbFileOps {
+    fn data(&self) -> Result<Vec<u8>> {
+        let output = format!("{}\n", MSGMNB);
+        Ok(output.into_bytes())
+    }
+}
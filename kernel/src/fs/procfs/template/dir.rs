@@ -168,7 +168,9 @@ impl<D: DirOps + 'static> Inode for ProcDir<D> {
                 return Ok(inode.clone());
             }
             let inode = self.inner.lookup_child(self.this.clone(), name)?;
-            cached_children.put((String::from(name), inode.clone()));
+            if self.inner.is_child_cacheable(name) {
+                cached_children.put((String::from(name), inode.clone()));
+            }
             inode
         };
         Ok(inode)
@@ -189,4 +191,12 @@ pub trait DirOps: Sync + Send {
     }
 
     fn populate_children(&self, this_ptr: Weak<dyn Inode>) {}
+
+    /// Returns whether the child found by [`Self::lookup_child`] can be cached.
+    ///
+    /// A child that cannot be cached is looked up again each time, and it is not listed in the
+    /// directory unless it is added by [`Self::populate_children`].
+    fn is_child_cacheable(&self, _name: &str) -> bool {
+        true
+    }
 }
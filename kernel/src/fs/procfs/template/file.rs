@@ -6,7 +6,12 @@ use inherit_methods_macro::inherit_methods;
 
 use super::{Common, ProcFS};
 use crate::{
-    fs::utils::{FileSystem, Inode, InodeMode, InodeType, IoctlCmd, Metadata},
+    fs::{
+        inode_handle::FileIo,
+        utils::{
+            AccessMode, FileSystem, Inode, InodeMode, InodeType, IoctlCmd, Metadata, StatusFlags,
+        },
+    },
     prelude::*,
     process::{Gid, Uid},
 };
@@ -54,8 +59,8 @@ impl<F: FileOps + 'static> Inode for ProcFile<F> {
     fn set_ctime(&self, time: Duration);
     fn fs(&self) -> Arc<dyn FileSystem>;
 
-    fn resize(&self, _new_size: usize) -> Result<()> {
-        Err(Error::new(Errno::EPERM))
+    fn resize(&self, new_size: usize) -> Result<()> {
+        self.inner.resize(new_size)
     }
 
     fn type_(&self) -> InodeType {
@@ -63,24 +68,27 @@ impl<F: FileOps + 'static> Inode for ProcFile<F> {
     }
 
     fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
-        let data = self.inner.data()?;
-        let start = data.len().min(offset);
-        let end = data.len().min(offset + writer.avail());
-        let len = end - start;
-        writer.write_fallible(&mut (&data[start..end]).into())?;
-        Ok(len)
+        self.inner.read_at(offset, writer)
     }
 
     fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
         self.read_at(offset, writer)
     }
 
-    fn write_at(&self, _offset: usize, _reader: &mut VmReader) -> Result<usize> {
-        Err(Error::new(Errno::EPERM))
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.inner.write_at(offset, reader)
     }
 
-    fn write_direct_at(&self, _offset: usize, _reader: &mut VmReader) -> Result<usize> {
-        Err(Error::new(Errno::EPERM))
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        self.write_at(offset, reader)
+    }
+
+    fn open(
+        &self,
+        access_mode: AccessMode,
+        status_flags: StatusFlags,
+    ) -> Option<Result<Arc<dyn FileIo>>> {
+        self.inner.open(access_mode, status_flags)
     }
 
     fn read_link(&self) -> Result<String> {
@@ -102,4 +110,41 @@ impl<F: FileOps + 'static> Inode for ProcFile<F> {
 
 pub trait FileOps: Sync + Send {
     fn data(&self) -> Result<Vec<u8>>;
+
+    /// Reads the file at the offset.
+    ///
+    /// By default, the data is read from the whole content returned by [`Self::data`].
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        let data = self.data()?;
+        let start = data.len().min(offset);
+        let end = data.len().min(offset + writer.avail());
+        let len = end - start;
+        writer.write_fallible(&mut (&data[start..end]).into())?;
+        Ok(len)
+    }
+
+    /// Writes the file at the offset.
+    ///
+    /// By default, the file is not writable.
+    fn write_at(&self, _offset: usize, _reader: &mut VmReader) -> Result<usize> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    /// Resizes the file.
+    ///
+    /// By default, the file cannot be resized.
+    fn resize(&self, _new_size: usize) -> Result<()> {
+        Err(Error::new(Errno::EPERM))
+    }
+
+    /// Opens the file.
+    ///
+    /// By default, the opened file is served by [`Self::read_at`] and [`Self::write_at`].
+    fn open(
+        &self,
+        _access_mode: AccessMode,
+        _status_flags: StatusFlags,
+    ) -> Option<Result<Arc<dyn FileIo>>> {
+        None
+    }
 }
@@ -25,9 +25,10 @@ use crate::{
         path::{is_dot, is_dot_or_dotdot, is_dotdot},
         registry::{FsProperties, FsType},
         utils::{
-            CStr256, CachePage, DirentVisitor, Extension, FallocMode, FileSystem, FsFlags, Inode,
-            InodeMode, InodeType, IoctlCmd, Metadata, MknodType, PageCache, PageCacheBackend,
-            Permission, SuperBlock, XattrName, XattrNamespace, XattrSetFlags,
+            copy_from_page_cache, CStr256, CachePage, DirentVisitor, Extension, FallocMode,
+            FileSystem, FsFlags, Inode, InodeMode, InodeType, IoctlCmd, Metadata, MknodType,
+            PageCache, PageCacheBackend, Permission, SuperBlock, XattrName, XattrNamespace,
+            XattrSetFlags,
         },
     },
     prelude::*,
@@ -608,6 +609,30 @@ impl Inode for RamInode {
         self.write_at(offset, reader)
     }
 
+    fn copy_file_range(
+        &self,
+        offset: usize,
+        dst: &Arc<dyn Inode>,
+        dst_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        let Inner::File(page_cache) = &self.inner else {
+            return_errno_with_message!(Errno::EISDIR, "copy is not supported");
+        };
+
+        let copied_len = copy_from_page_cache(
+            page_cache.pages(),
+            self.size(),
+            offset,
+            dst,
+            dst_offset,
+            len,
+        )?;
+
+        self.set_atime(now());
+        Ok(copied_len)
+    }
+
     fn size(&self) -> usize {
         self.metadata.lock().size
     }
@@ -1283,6 +1308,7 @@ impl FsType for RamFsType {
 
     fn create(
         &self,
+        _source: &CStr,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
         _ctx: &Context,
@@ -20,10 +20,15 @@ pub trait FsType: Send + Sync + 'static {
 
     /// Creates an instance of this FS type.
     ///
+    /// The `source` argument is the source of the mount (e.g., `"/dev/sda1"`),
+    /// which is interpreted by the FS type. For example, it is the tag of
+    /// the device for virtio-fs, and it is ignored by pseudo FSes.
+    ///
     /// The optional `disk` argument must be provided
     /// if `self.properties()` contains `FsProperties::NEED_DISK`.
     fn create(
         &self,
+        source: &CStr,
         args: Option<CString>,
         disk: Option<Arc<dyn BlockDevice>>,
         ctx: &Context,
@@ -48,6 +53,11 @@ bitflags! {
         /// But a volatile FS such as RamFS or
         /// a pseudo FS such as SysFS does not.
         const NEED_DISK = 1 << 1;
+        /// Whether a FS can be mounted with a subtype such as `"fuse.sshfs"`.
+        ///
+        /// The subtype is a free-form name that describes the FS instance,
+        /// and it does not affect how the FS is created.
+        const HAS_SUBTYPE = 1 << 2;
     }
 }
 
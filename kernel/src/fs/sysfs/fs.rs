@@ -70,6 +70,7 @@ impl FsType for SysFsType {
 
     fn create(
         &self,
+        _source: &CStr,
         _args: Option<CString>,
         _disk: Option<Arc<dyn aster_block::BlockDevice>>,
         _ctx: &Context,
@@ -10,12 +10,15 @@ use core2::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult,
 use ostd::task::Task;
 
 use super::{
-    AccessMode, DirentVisitor, FallocMode, FileSystem, IoctlCmd, XattrName, XattrNamespace,
-    XattrSetFlags,
+    AccessMode, DirentVisitor, FallocMode, FileSystem, IoctlCmd, StatusFlags, XattrName,
+    XattrNamespace, XattrSetFlags,
 };
 use crate::{
     events::IoEvents,
-    fs::device::{Device, DeviceType},
+    fs::{
+        device::{Device, DeviceType},
+        inode_handle::FileIo,
+    },
     prelude::*,
     process::{posix_thread::AsPosixThread, signal::PollHandle, Gid, Uid},
     time::clocks::RealTimeCoarseClock,
@@ -409,6 +412,21 @@ pub trait Inode: Any + Sync + Send {
         Err(Error::new(Errno::EISDIR))
     }
 
+    /// Copies at most `len` bytes starting from `offset` of the file to `dst_offset` of the `dst`
+    /// file.
+    ///
+    /// File systems can implement this method to copy the data without an intermediate buffer.
+    /// If `EOPNOTSUPP` is returned, the caller should fall back to reading and writing the data.
+    fn copy_file_range(
+        &self,
+        offset: usize,
+        dst: &Arc<dyn Inode>,
+        dst_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        return_errno!(Errno::EOPNOTSUPP);
+    }
+
     fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
         Err(Error::new(Errno::ENOTDIR))
     }
@@ -421,6 +439,18 @@ pub trait Inode: Any + Sync + Send {
         None
     }
 
+    /// Opens the inode and returns the object that serves the I/O of the opened file, if any.
+    ///
+    /// This method is called for every open file description, so the object can keep the
+    /// states that belong to the opened file rather than the inode.
+    fn open(
+        &self,
+        access_mode: AccessMode,
+        status_flags: StatusFlags,
+    ) -> Option<Result<Arc<dyn FileIo>>> {
+        None
+    }
+
     fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
         Err(Error::new(Errno::ENOTDIR))
     }
@@ -45,4 +45,16 @@ pub enum IoctlCmd {
     KDFONTOP = 0x4B72,
     /// Get tdx report using TDCALL
     TDXGETREPORT = 0xc4405401,
+    /// Enable the userfaultfd API
+    UFFDIO_API = 0xc018aa3f,
+    /// Register a memory range with the userfaultfd
+    UFFDIO_REGISTER = 0xc020aa00,
+    /// Unregister a memory range from the userfaultfd
+    UFFDIO_UNREGISTER = 0x8010aa01,
+    /// Wake up the threads waiting for page faults in a memory range
+    UFFDIO_WAKE = 0x8010aa02,
+    /// Resolve page faults by copying data into a memory range
+    UFFDIO_COPY = 0xc028aa03,
+    /// Resolve page faults by zeroing a memory range
+    UFFDIO_ZEROPAGE = 0xc020aa04,
 }
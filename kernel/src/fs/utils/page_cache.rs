@@ -13,12 +13,13 @@ use aster_rights::Full;
 use lru::LruCache;
 use ostd::{
     impl_untyped_frame_meta_for,
-    mm::{Frame, FrameAllocOptions, UFrame, VmIoFill},
+    mm::{io_util::HasVmReaderWriter, Frame, FrameAllocOptions, UFrame, VmIoFill},
 };
 
+use super::Inode;
 use crate::{
     prelude::*,
-    vm::vmo::{get_page_idx_range, Pager, Vmo, VmoFlags, VmoOptions},
+    vm::vmo::{get_page_idx_range, CommitFlags, Pager, Vmo, VmoFlags, VmoOptions},
 };
 
 pub struct PageCache {
@@ -117,6 +118,48 @@ impl PageCache {
     }
 }
 
+/// Copies at most `len` bytes starting from `offset` of the page cache to `dst_offset` of the
+/// `dst` file.
+///
+/// The data is written to the destination directly from the pages, without an intermediate
+/// buffer. No data beyond `file_size` will be copied.
+///
+/// This is a helper for file systems to implement [`Inode::copy_file_range`].
+pub fn copy_from_page_cache(
+    pages: &Vmo<Full>,
+    file_size: usize,
+    offset: usize,
+    dst: &Arc<dyn Inode>,
+    dst_offset: usize,
+    len: usize,
+) -> Result<usize> {
+    let end = file_size.min(offset.saturating_add(len));
+    let mut copied_len = 0;
+
+    while offset + copied_len < end {
+        let pos = offset + copied_len;
+        let page_offset = pos % PAGE_SIZE;
+        let page_len = (PAGE_SIZE - page_offset).min(end - pos);
+
+        let frame = pages.commit_on(pos / PAGE_SIZE, CommitFlags::empty())?;
+        let mut reader = frame.reader().to_fallible();
+        reader.skip(page_offset).limit(page_len);
+
+        let written_len = match dst.write_at(dst_offset + copied_len, &mut reader) {
+            Ok(len) => len,
+            Err(_) if copied_len > 0 => break,
+            Err(err) => return Err(err),
+        };
+        copied_len += written_len;
+
+        if written_len < page_len {
+            break;
+        }
+    }
+
+    Ok(copied_len)
+}
+
 impl Drop for PageCache {
     fn drop(&mut self) {
         // TODO:
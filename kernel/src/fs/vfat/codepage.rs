@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::prelude::*;
+
+/// The OEM code page that encodes the non-ASCII bytes of short names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) enum Codepage {
+    /// The original IBM PC code page, which is the default of most FAT implementations.
+    #[default]
+    Cp437,
+    /// The Western European code page.
+    Cp850,
+}
+
+impl Codepage {
+    pub(super) fn from_number(number: u32) -> Result<Self> {
+        match number {
+            437 => Ok(Codepage::Cp437),
+            850 => Ok(Codepage::Cp850),
+            _ => return_errno_with_message!(Errno::EINVAL, "unsupported codepage"),
+        }
+    }
+
+    /// Decodes a byte of a short name.
+    pub(super) fn decode(&self, byte: u8) -> char {
+        if byte.is_ascii() {
+            return byte as char;
+        }
+        self.high_table()[(byte - 0x80) as usize]
+    }
+
+    /// Encodes a character into a byte of a short name, if it is representable.
+    pub(super) fn encode(&self, ch: char) -> Option<u8> {
+        if ch.is_ascii() {
+            return Some(ch as u8);
+        }
+        self.high_table()
+            .iter()
+            .position(|&c| c == ch)
+            .map(|pos| pos as u8 + 0x80)
+    }
+
+    fn high_table(&self) -> &'static [char; 128] {
+        match self {
+            Codepage::Cp437 => &CP437_HIGH,
+            Codepage::Cp850 => &CP850_HIGH,
+        }
+    }
+}
+
+#[rustfmt::skip]
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+#[rustfmt::skip]
+const CP850_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', 'ø', '£', 'Ø', '×', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '®', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', 'Á', 'Â', 'À', '©', '╣', '║', '╗', '╝', '¢', '¥', '┐',
+    '└', '┴', '┬', '├', '─', '┼', 'ã', 'Ã', '╚', '╔', '╩', '╦', '╠', '═', '╬', '¤',
+    'ð', 'Ð', 'Ê', 'Ë', 'È', 'ı', 'Í', 'Î', 'Ï', '┘', '┌', '█', '▄', '¦', 'Ì', '▀',
+    'Ó', 'ß', 'Ô', 'Ò', 'õ', 'Õ', 'µ', 'þ', 'Þ', 'Ú', 'Û', 'Ù', 'ý', 'Ý', '¯', '´',
+    '\u{ad}', '±', '‗', '¾', '¶', '§', '÷', '¸', '°', '¨', '·', '¹', '³', '²', '■', '\u{a0}',
+];
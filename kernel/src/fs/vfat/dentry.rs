@@ -0,0 +1,514 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::{format, string::String};
+
+use aster_rights::Full;
+use ostd::mm::VmIo;
+
+use super::{
+    codepage::Codepage,
+    fat::{ClusterID, FatType},
+};
+use crate::{prelude::*, vm::vmo::Vmo};
+
+pub(super) const DENTRY_SIZE: usize = 32;
+/// The maximum length of a long name in UTF-16 code units.
+pub(super) const MAX_NAME_LENGTH: usize = 255;
+
+/// The first byte of a never-used dentry, which also ends the directory.
+pub(super) const DENTRY_END: u8 = 0x00;
+/// The first byte of a deleted dentry.
+pub(super) const DENTRY_DELETED: u8 = 0xE5;
+/// A short name starting with 0xE5 is stored with 0x05 instead.
+const DENTRY_KANJI_E5: u8 = 0x05;
+
+pub(super) const SHORT_NAME_LEN: usize = 11;
+const SHORT_BASE_LEN: usize = 8;
+pub(super) const DOT_NAME: [u8; SHORT_NAME_LEN] = *b".          ";
+pub(super) const DOTDOT_NAME: [u8; SHORT_NAME_LEN] = *b"..         ";
+
+/// The short name is displayed in lower case, set by Windows NT and later.
+const CASE_LOWER_BASE: u8 = 0x08;
+const CASE_LOWER_EXT: u8 = 0x10;
+
+const LFN_CHARS_PER_DENTRY: usize = 13;
+const LFN_LAST_ENTRY: u8 = 0x40;
+const LFN_ORDER_MASK: u8 = 0x1F;
+
+bitflags! {
+    pub(super) struct VfatAttr: u8 {
+        const READONLY  = 0x01;
+        const HIDDEN    = 0x02;
+        const SYSTEM    = 0x04;
+        /// The dentry holds the volume label instead of a file.
+        const VOLUME_ID = 0x08;
+        const DIRECTORY = 0x10;
+        const ARCHIVE   = 0x20;
+        /// The combination marks a long-name dentry.
+        const LONG_NAME = Self::READONLY.bits | Self::HIDDEN.bits | Self::SYSTEM.bits
+            | Self::VOLUME_ID.bits;
+    }
+}
+
+/// A short (8.3) directory entry, which describes a file or directory.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct RawShortDentry {
+    pub(super) name: [u8; SHORT_NAME_LEN],
+    pub(super) attr: u8,
+    pub(super) case_flags: u8,
+    pub(super) create_time_cs: u8,
+    pub(super) create_time: u16,
+    pub(super) create_date: u16,
+    pub(super) access_date: u16,
+    pub(super) start_cluster_hi: u16,
+    pub(super) modify_time: u16,
+    pub(super) modify_date: u16,
+    pub(super) start_cluster_lo: u16,
+    pub(super) size: u32,
+}
+
+impl RawShortDentry {
+    pub(super) fn attr(&self) -> VfatAttr {
+        VfatAttr::from_bits_truncate(self.attr)
+    }
+
+    /// Returns the first cluster, which is 0 if no cluster is allocated.
+    ///
+    /// The high 16 bits are only used by FAT32.
+    pub(super) fn start_cluster(&self, fat_type: FatType) -> ClusterID {
+        let lo = self.start_cluster_lo as ClusterID;
+        if fat_type == FatType::Fat32 {
+            ((self.start_cluster_hi as ClusterID) << 16) | lo
+        } else {
+            lo
+        }
+    }
+
+    pub(super) fn set_start_cluster(&mut self, cluster: ClusterID) {
+        self.start_cluster_hi = (cluster >> 16) as u16;
+        self.start_cluster_lo = cluster as u16;
+    }
+
+    /// Calculates the checksum of the short name, which is stored in its long-name dentries.
+    pub(super) fn checksum(&self) -> u8 {
+        self.name
+            .iter()
+            .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+    }
+
+    /// Returns the short name in the "NAME.EXT" form.
+    pub(super) fn display_name(&self, codepage: Codepage) -> String {
+        let mut name = self.name;
+        if name[0] == DENTRY_KANJI_E5 {
+            name[0] = DENTRY_DELETED;
+        }
+        let decode = |bytes: &[u8], lower: bool| -> String {
+            let len = bytes
+                .iter()
+                .rposition(|&b| b != b' ')
+                .map_or(0, |pos| pos + 1);
+            bytes[..len]
+                .iter()
+                .map(|&b| {
+                    let ch = codepage.decode(b);
+                    if lower {
+                        ch.to_ascii_lowercase()
+                    } else {
+                        ch
+                    }
+                })
+                .collect()
+        };
+
+        let mut display = decode(
+            &name[..SHORT_BASE_LEN],
+            self.case_flags & CASE_LOWER_BASE != 0,
+        );
+        let ext = decode(
+            &name[SHORT_BASE_LEN..],
+            self.case_flags & CASE_LOWER_EXT != 0,
+        );
+        if !ext.is_empty() {
+            display.push('.');
+            display.push_str(&ext);
+        }
+        display
+    }
+}
+
+/// A long-name directory entry, which holds 13 UTF-16 code units of a long name.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, Pod)]
+pub(super) struct RawLongDentry {
+    order: u8,
+    name1: [u16; 5],
+    attr: u8,
+    type_: u8,
+    checksum: u8,
+    name2: [u16; 6],
+    start_cluster_lo: u16,
+    name3: [u16; 2],
+}
+
+impl RawLongDentry {
+    fn new(order: u8, checksum: u8, chars: &[u16; LFN_CHARS_PER_DENTRY]) -> Self {
+        let mut name1 = [0u16; 5];
+        let mut name2 = [0u16; 6];
+        let mut name3 = [0u16; 2];
+        name1.copy_from_slice(&chars[..5]);
+        name2.copy_from_slice(&chars[5..11]);
+        name3.copy_from_slice(&chars[11..]);
+        Self {
+            order,
+            name1,
+            attr: VfatAttr::LONG_NAME.bits(),
+            type_: 0,
+            checksum,
+            name2,
+            start_cluster_lo: 0,
+            name3,
+        }
+    }
+
+    fn chars(&self) -> [u16; LFN_CHARS_PER_DENTRY] {
+        let (name1, name2, name3) = (self.name1, self.name2, self.name3);
+        let mut chars = [0u16; LFN_CHARS_PER_DENTRY];
+        chars[..5].copy_from_slice(&name1);
+        chars[5..11].copy_from_slice(&name2);
+        chars[11..].copy_from_slice(&name3);
+        chars
+    }
+}
+
+/// The dentries of a file, which are the optional long-name dentries followed by
+/// the short dentry.
+#[derive(Debug, Clone)]
+pub(super) struct VfatDentrySet {
+    /// The index of the first dentry in the directory.
+    pub(super) start_entry: usize,
+    /// The index of the short dentry in the directory.
+    pub(super) short_entry: usize,
+    pub(super) short: RawShortDentry,
+    /// The long name, or the short name if there is no valid long name.
+    pub(super) name: String,
+}
+
+impl VfatDentrySet {
+    pub(super) fn num_dentries(&self) -> usize {
+        self.short_entry - self.start_entry + 1
+    }
+
+    /// Returns the offset right after the dentry set in the directory.
+    pub(super) fn end_offset(&self) -> usize {
+        (self.short_entry + 1) * DENTRY_SIZE
+    }
+
+    /// Checks whether `name` refers to this dentry set.
+    ///
+    /// Names are case-insensitive, and a file can also be referred to by its short name.
+    pub(super) fn matches(&self, name: &str, codepage: Codepage) -> bool {
+        names_equal(&self.name, name) || names_equal(&self.short.display_name(codepage), name)
+    }
+}
+
+/// Serializes the dentries that store `name` with `short`.
+///
+/// The long-name dentries are omitted if `short` is an exact representation of `name`.
+pub(super) fn build_dentries(name: &str, short: &RawShortDentry, with_long_name: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if with_long_name {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let num_long = units.len().div_ceil(LFN_CHARS_PER_DENTRY);
+        let checksum = short.checksum();
+        for order in (1..=num_long).rev() {
+            let start = (order - 1) * LFN_CHARS_PER_DENTRY;
+            let end = units.len().min(start + LFN_CHARS_PER_DENTRY);
+            // The name is terminated by a zero if there is room, and padded with 0xFFFF.
+            let mut chars = [0xFFFFu16; LFN_CHARS_PER_DENTRY];
+            chars[..end - start].copy_from_slice(&units[start..end]);
+            if end - start < LFN_CHARS_PER_DENTRY {
+                chars[end - start] = 0;
+            }
+            let flag = if order == num_long { LFN_LAST_ENTRY } else { 0 };
+            let dentry = RawLongDentry::new(order as u8 | flag, checksum, &chars);
+            bytes.extend_from_slice(dentry.as_bytes());
+        }
+    }
+    bytes.extend_from_slice(short.as_bytes());
+    bytes
+}
+
+/// Returns the number of dentries needed to store `name`.
+pub(super) fn num_dentries_for(name: &str, with_long_name: bool) -> usize {
+    if with_long_name {
+        name.encode_utf16().count().div_ceil(LFN_CHARS_PER_DENTRY) + 1
+    } else {
+        1
+    }
+}
+
+/// Checks whether a name can be stored in a directory.
+pub(super) fn check_name(name: &str) -> Result<()> {
+    if name.encode_utf16().count() > MAX_NAME_LENGTH {
+        return_errno!(Errno::ENAMETOOLONG)
+    }
+    if name.is_empty()
+        || name.chars().any(|ch| {
+            ch < ' ' || matches!(ch, '"' | '*' | '/' | ':' | '<' | '>' | '?' | '\\' | '|')
+        })
+    {
+        return_errno_with_message!(Errno::EINVAL, "invalid character in name")
+    }
+    Ok(())
+}
+
+/// Removes the trailing dots of a name, which are ignored by FAT.
+pub(super) fn strip_name(name: &str) -> &str {
+    let stripped = name.trim_end_matches('.');
+    if stripped.is_empty() {
+        name
+    } else {
+        stripped
+    }
+}
+
+/// Compares two names case-insensitively.
+pub(super) fn names_equal(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .eq(b.chars().flat_map(char::to_uppercase))
+}
+
+/// Generates the short name of `name`.
+///
+/// Returns the short name and whether long-name dentries are needed. A name that is
+/// already a valid upper-case 8.3 name is stored as is. Otherwise, a unique short name
+/// like "LONGNA~1.TXT" is generated, which must not be in `existing`.
+pub(super) fn generate_short_name(
+    name: &str,
+    codepage: Codepage,
+    existing: &[[u8; SHORT_NAME_LEN]],
+) -> Result<([u8; SHORT_NAME_LEN], bool)> {
+    if let Some(short) = exact_short_name(name, codepage) {
+        return Ok((short, false));
+    }
+
+    let (base, ext) = match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos + 1..]),
+        _ => (name, ""),
+    };
+    let to_short_chars = |part: &str, max_len: usize| -> Vec<u8> {
+        part.chars()
+            .filter(|&ch| ch != ' ' && ch != '.')
+            .flat_map(char::to_uppercase)
+            .map(|ch| match codepage.encode(ch) {
+                Some(byte) if is_short_name_byte(byte) => byte,
+                _ => b'_',
+            })
+            .take(max_len)
+            .collect()
+    };
+    let mut base = to_short_chars(base, SHORT_BASE_LEN);
+    let ext = to_short_chars(ext, SHORT_NAME_LEN - SHORT_BASE_LEN);
+    if base.is_empty() {
+        base.push(b'_');
+    }
+
+    let mut short = [b' '; SHORT_NAME_LEN];
+    short[SHORT_BASE_LEN..SHORT_BASE_LEN + ext.len()].copy_from_slice(&ext);
+    for seq in 1..1_000_000u32 {
+        let tail = format!("~{}", seq);
+        let base_len = base.len().min(SHORT_BASE_LEN - tail.len());
+        short[..SHORT_BASE_LEN].fill(b' ');
+        short[..base_len].copy_from_slice(&base[..base_len]);
+        short[base_len..base_len + tail.len()].copy_from_slice(tail.as_bytes());
+        if short[0] == DENTRY_DELETED {
+            short[0] = DENTRY_KANJI_E5;
+        }
+        if !existing.contains(&short) {
+            return Ok((short, true));
+        }
+    }
+    return_errno_with_message!(Errno::EEXIST, "no short name is available")
+}
+
+/// Converts `name` to a short name if it is a valid upper-case 8.3 name.
+fn exact_short_name(name: &str, codepage: Codepage) -> Option<[u8; SHORT_NAME_LEN]> {
+    let (base, ext) = match name.split_once('.') {
+        Some((base, ext)) => (base, ext),
+        None => (name, ""),
+    };
+    if base.is_empty()
+        || base.chars().count() > SHORT_BASE_LEN
+        || ext.chars().count() > SHORT_NAME_LEN - SHORT_BASE_LEN
+        || (name.contains('.') && ext.is_empty())
+    {
+        return None;
+    }
+
+    let mut short = [b' '; SHORT_NAME_LEN];
+    let parts = [(base, 0), (ext, SHORT_BASE_LEN)];
+    for (part, start) in parts {
+        for (i, ch) in part.chars().enumerate() {
+            if ch.is_lowercase() {
+                return None;
+            }
+            let byte = codepage.encode(ch).filter(|&b| is_short_name_byte(b))?;
+            short[start + i] = byte;
+        }
+    }
+    if short[0] == DENTRY_DELETED {
+        short[0] = DENTRY_KANJI_E5;
+    }
+    Some(short)
+}
+
+fn is_short_name_byte(byte: u8) -> bool {
+    match byte {
+        b'A'..=b'Z' | b'0'..=b'9' => true,
+        b'!' | b'#' | b'$' | b'%' | b'&' | b'\'' | b'(' | b')' | b'-' | b'@' | b'^' | b'_'
+        | b'`' | b'{' | b'}' | b'~' => true,
+        _ => !byte.is_ascii(),
+    }
+}
+
+/// An iterator over the dentry sets of a directory.
+///
+/// Deleted dentries, the volume label, and the "." and ".." dentries are skipped.
+pub(super) struct VfatDentryIterator {
+    page_cache: Vmo<Full>,
+    /// The index of the next dentry to read.
+    entry: usize,
+    /// The number of dentries in the directory.
+    num_entries: usize,
+    codepage: Codepage,
+}
+
+impl VfatDentryIterator {
+    pub(super) fn new(
+        page_cache: Vmo<Full>,
+        start_entry: usize,
+        num_entries: usize,
+        codepage: Codepage,
+    ) -> Self {
+        Self {
+            page_cache,
+            entry: start_entry,
+            num_entries,
+            codepage,
+        }
+    }
+
+    fn next_dentry_set(&mut self) -> Result<Option<VfatDentrySet>> {
+        let mut long_name = LongNameBuilder::default();
+        while self.entry < self.num_entries {
+            let index = self.entry;
+            let mut bytes = [0u8; DENTRY_SIZE];
+            self.page_cache
+                .read_bytes(index * DENTRY_SIZE, &mut bytes)?;
+            self.entry += 1;
+
+            match bytes[0] {
+                DENTRY_END => {
+                    self.entry = self.num_entries;
+                    return Ok(None);
+                }
+                DENTRY_DELETED => {
+                    long_name.reset();
+                    continue;
+                }
+                _ => {}
+            }
+
+            let short = RawShortDentry::from_bytes(&bytes);
+            if short.attr & 0x3F == VfatAttr::LONG_NAME.bits() {
+                long_name.push(index, &RawLongDentry::from_bytes(&bytes));
+                continue;
+            }
+            if short.attr().contains(VfatAttr::VOLUME_ID)
+                || short.name == DOT_NAME
+                || short.name == DOTDOT_NAME
+            {
+                long_name.reset();
+                continue;
+            }
+
+            let (start_entry, name) = match long_name.finish(short.checksum()) {
+                Some(long) => long,
+                None => (index, short.display_name(self.codepage)),
+            };
+            return Ok(Some(VfatDentrySet {
+                start_entry,
+                short_entry: index,
+                short,
+                name,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for VfatDentryIterator {
+    type Item = Result<VfatDentrySet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_dentry_set().transpose()
+    }
+}
+
+/// Collects the long-name dentries preceding a short dentry.
+#[derive(Default)]
+struct LongNameBuilder {
+    /// The index of the first long-name dentry.
+    start_entry: usize,
+    checksum: u8,
+    /// The name parts in the order of the dentries, which is the reverse of the name.
+    parts: Vec<[u16; LFN_CHARS_PER_DENTRY]>,
+    /// The order of the next expected dentry, which counts down to 1.
+    next_order: u8,
+}
+
+impl LongNameBuilder {
+    fn push(&mut self, index: usize, dentry: &RawLongDentry) {
+        if dentry.order & LFN_LAST_ENTRY != 0 {
+            self.reset();
+            self.start_entry = index;
+            self.checksum = dentry.checksum;
+            self.next_order = dentry.order & LFN_ORDER_MASK;
+        }
+        if self.next_order == 0
+            || dentry.order & LFN_ORDER_MASK != self.next_order
+            || dentry.checksum != self.checksum
+        {
+            self.reset();
+            return;
+        }
+        self.parts.push(dentry.chars());
+        self.next_order -= 1;
+    }
+
+    /// Returns the index of the first dentry and the long name, if the collected
+    /// dentries form a complete name of the short dentry with `checksum`.
+    fn finish(&mut self, checksum: u8) -> Option<(usize, String)> {
+        let is_complete =
+            !self.parts.is_empty() && self.next_order == 0 && self.checksum == checksum;
+        let parts = core::mem::take(&mut self.parts);
+        if !is_complete {
+            return None;
+        }
+        let units: Vec<u16> = parts
+            .iter()
+            .rev()
+            .flatten()
+            .copied()
+            .take_while(|&unit| unit != 0)
+            .collect();
+        Some((self.start_entry, String::from_utf16_lossy(&units)))
+    }
+
+    fn reset(&mut self) {
+        self.parts.clear();
+        self.next_order = 0;
+    }
+}
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MPL-2.0
+
+pub(super) use crate::fs::fat_common::{ClusterID, FatValue};
+
+/// Cluster 0 and 1 are reserved, the first data cluster is 2.
+pub(super) const FAT_FIRST_CLUSTER: ClusterID = 2;
+
+/// The maximum number of data clusters of a FAT12 volume.
+const FAT12_MAX_CLUSTERS: u32 = 4084;
+/// The maximum number of data clusters of a FAT16 volume.
+const FAT16_MAX_CLUSTERS: u32 = 65524;
+/// The maximum number of data clusters of a FAT32 volume.
+pub(super) const FAT32_MAX_CLUSTERS: u32 = 0x0FFF_FFF4;
+
+/// The width of a FAT entry.
+///
+/// The type of a FAT volume is determined only by its number of data clusters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+impl FatType {
+    pub(super) fn from_num_clusters(num_clusters: u32) -> Self {
+        if num_clusters <= FAT12_MAX_CLUSTERS {
+            FatType::Fat12
+        } else if num_clusters <= FAT16_MAX_CLUSTERS {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        }
+    }
+
+    /// Returns the offset in bytes of the entry of `cluster` within a FAT.
+    pub(super) fn entry_offset(&self, cluster: ClusterID) -> usize {
+        let cluster = cluster as usize;
+        match self {
+            FatType::Fat12 => cluster + cluster / 2,
+            FatType::Fat16 => cluster * 2,
+            FatType::Fat32 => cluster * 4,
+        }
+    }
+
+    /// Returns the number of bytes to access for an entry.
+    ///
+    /// A FAT12 entry takes one and a half bytes, so two bytes are accessed.
+    pub(super) fn entry_len(&self) -> usize {
+        match self {
+            FatType::Fat12 | FatType::Fat16 => 2,
+            FatType::Fat32 => 4,
+        }
+    }
+
+    /// Returns the number of bytes that a FAT with `num_entries` entries takes.
+    pub(super) fn table_size(&self, num_entries: u32) -> usize {
+        let num_entries = num_entries as usize;
+        match self {
+            FatType::Fat12 => (num_entries * 3).div_ceil(2),
+            FatType::Fat16 => num_entries * 2,
+            FatType::Fat32 => num_entries * 4,
+        }
+    }
+
+    /// Extracts the entry of `cluster` from the bytes read at its entry offset.
+    pub(super) fn decode(&self, cluster: ClusterID, raw: u32) -> FatValue {
+        let value = match self {
+            FatType::Fat12 if cluster % 2 == 1 => (raw & 0xFFFF) >> 4,
+            FatType::Fat12 => raw & 0x0FFF,
+            FatType::Fat16 => raw & 0xFFFF,
+            FatType::Fat32 => raw & 0x0FFF_FFFF,
+        };
+        let bad = self.bad_value();
+        match value {
+            0 => FatValue::Free,
+            _ if value == bad => FatValue::Bad,
+            _ if value > bad => FatValue::EndOfChain,
+            _ => FatValue::Next(value),
+        }
+    }
+
+    /// Merges the entry of `cluster` into the bytes read at its entry offset.
+    ///
+    /// The bits that do not belong to the entry are preserved, which are the adjacent
+    /// FAT12 entry or the reserved high bits of a FAT32 entry.
+    pub(super) fn encode(&self, cluster: ClusterID, raw: u32, value: FatValue) -> u32 {
+        let value = match value {
+            FatValue::Free => 0,
+            FatValue::Next(next) => next,
+            FatValue::Bad => self.bad_value(),
+            FatValue::EndOfChain => self.eoc_value(),
+        };
+        match self {
+            FatType::Fat12 if cluster % 2 == 1 => (raw & 0x000F) | (value << 4),
+            FatType::Fat12 => (raw & 0xF000) | (value & 0x0FFF),
+            FatType::Fat16 => value & 0xFFFF,
+            FatType::Fat32 => (raw & 0xF000_0000) | (value & 0x0FFF_FFFF),
+        }
+    }
+
+    fn bad_value(&self) -> u32 {
+        match self {
+            FatType::Fat12 => 0x0FF7,
+            FatType::Fat16 => 0xFFF7,
+            FatType::Fat32 => 0x0FFF_FFF7,
+        }
+    }
+
+    fn eoc_value(&self) -> u32 {
+        match self {
+            FatType::Fat12 => 0x0FFF,
+            FatType::Fat16 => 0xFFFF,
+            FatType::Fat32 => 0x0FFF_FFFF,
+        }
+    }
+}
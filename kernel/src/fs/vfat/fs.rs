@@ -0,0 +1,636 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use align_ext::AlignExt;
+use aster_block::{
+    bio::{Bio, BioDirection, BioSegment, BioType, BioWaiter},
+    id::Sid,
+    BlockDevice,
+};
+use hashbrown::HashMap;
+use ostd::mm::{io_util::HasVmReaderWriter, Segment, VmIo};
+
+use super::{
+    codepage::Codepage,
+    fat::{ClusterID, FatValue, FAT_FIRST_CLUSTER},
+    inode::{Ino, VfatInode, ROOT_INODE_HASH, VFAT_ROOT_INO},
+    super_block::{VfatBootSector, VfatFsInfo, VfatSuperBlock, FSINFO_UNKNOWN},
+};
+use crate::{
+    fs::{
+        registry::{FsProperties, FsType},
+        utils::{CachePage, FileSystem, FsFlags, Inode, PageCache, PageCacheBackend, SuperBlock},
+    },
+    prelude::*,
+};
+
+/// The magic number reported by `statfs`, which is the same as Linux's `MSDOS_SUPER_MAGIC`.
+const MSDOS_SUPER_MAGIC: u64 = 0x4d44;
+
+#[derive(Debug)]
+pub struct VfatFS {
+    block_device: Arc<dyn BlockDevice>,
+    super_block: VfatSuperBlock,
+
+    mount_option: VfatMountOptions,
+    // Used for inode allocation.
+    highest_inode_number: AtomicU64,
+
+    // Inodes are indexed by their hash_value.
+    inodes: RwMutex<HashMap<usize, Arc<VfatInode>>>,
+
+    // The cache of the FAT. All the FAT copies are updated when a page is written back.
+    fat_cache: PageCache,
+    // The allocation state, which must be held to modify the FAT.
+    alloc_state: Mutex<AllocState>,
+    // Clusters of the removed inodes that failed to be freed when the inodes were dropped,
+    // which are freed again by the next allocation or sync.
+    orphan_clusters: SpinLock<Vec<ClusterID>>,
+
+    // A global lock, we need to hold the mutex before modifying directories, otherwise
+    // there will be deadlocks.
+    mutex: Mutex<()>,
+}
+
+#[derive(Debug)]
+struct AllocState {
+    num_free_clusters: u32,
+    /// The cluster to start searching for a free cluster.
+    next_free: ClusterID,
+}
+
+/// A contiguous range on the device that backs part of a page.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct DeviceRun {
+    pub(super) page_offset: usize,
+    pub(super) device_offset: usize,
+    pub(super) len: usize,
+}
+
+impl VfatFS {
+    pub fn open(
+        block_device: Arc<dyn BlockDevice>,
+        mount_option: VfatMountOptions,
+    ) -> Result<Arc<Self>> {
+        let boot_sector = block_device.read_val::<VfatBootSector>(0)?;
+        let super_block = VfatSuperBlock::try_from(boot_sector)?;
+
+        let fat_cache_size = super_block.fat_size.align_up(PAGE_SIZE);
+        let vfat_fs = Arc::new_cyclic(|weak_self| VfatFS {
+            block_device,
+            super_block,
+            mount_option,
+            highest_inode_number: AtomicU64::new(VFAT_ROOT_INO + 1),
+            inodes: RwMutex::new(HashMap::new()),
+            fat_cache: PageCache::with_capacity(fat_cache_size, weak_self.clone() as _).unwrap(),
+            alloc_state: Mutex::new(AllocState {
+                num_free_clusters: 0,
+                next_free: FAT_FIRST_CLUSTER,
+            }),
+            orphan_clusters: SpinLock::new(Vec::new()),
+            mutex: Mutex::new(()),
+        });
+
+        vfat_fs.load_alloc_state()?;
+
+        let root = VfatInode::build_root_inode(&vfat_fs)?;
+        vfat_fs.inodes.write().insert(ROOT_INODE_HASH, root);
+
+        Ok(vfat_fs)
+    }
+
+    /// Loads the number of free clusters from the FSInfo sector, or counts them if the
+    /// FSInfo sector is missing or invalid.
+    fn load_alloc_state(&self) -> Result<()> {
+        let num_clusters = self.super_block.num_clusters;
+        let mut alloc_state = self.alloc_state.lock();
+
+        if let Some(fs_info) = self.read_fs_info()? {
+            if fs_info.free_count != FSINFO_UNKNOWN && fs_info.free_count <= num_clusters {
+                alloc_state.num_free_clusters = fs_info.free_count;
+                if self.is_valid_cluster(fs_info.next_free) {
+                    alloc_state.next_free = fs_info.next_free;
+                }
+                return Ok(());
+            }
+        }
+
+        let mut num_free_clusters = 0;
+        for cluster in FAT_FIRST_CLUSTER..FAT_FIRST_CLUSTER + num_clusters {
+            if self.read_next_fat(cluster)? == FatValue::Free {
+                num_free_clusters += 1;
+            }
+        }
+        alloc_state.num_free_clusters = num_free_clusters;
+        Ok(())
+    }
+
+    fn read_fs_info(&self) -> Result<Option<VfatFsInfo>> {
+        let Some(sector) = self.super_block.fs_info_sector else {
+            return Ok(None);
+        };
+        let offset = sector as usize * self.super_block.sector_size;
+        let fs_info = self.block_device.read_val::<VfatFsInfo>(offset)?;
+        Ok(fs_info.is_valid().then_some(fs_info))
+    }
+
+    /// Writes the allocation state back to the FSInfo sector.
+    fn write_fs_info(&self) -> Result<()> {
+        let Some(mut fs_info) = self.read_fs_info()? else {
+            return Ok(());
+        };
+        let alloc_state = self.alloc_state.lock();
+        if fs_info.free_count == alloc_state.num_free_clusters
+            && fs_info.next_free == alloc_state.next_free
+        {
+            return Ok(());
+        }
+        fs_info.free_count = alloc_state.num_free_clusters;
+        fs_info.next_free = alloc_state.next_free;
+        drop(alloc_state);
+
+        let offset = self.super_block.fs_info_sector.unwrap() as usize * self.sector_size();
+        self.block_device.write_val(offset, &fs_info)?;
+        Ok(())
+    }
+
+    pub(super) fn alloc_inode_number(&self) -> Ino {
+        self.highest_inode_number.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub(super) fn find_opened_inode(&self, hash: usize) -> Option<Arc<VfatInode>> {
+        self.inodes.read().get(&hash).cloned()
+    }
+
+    pub(super) fn remove_inode(&self, hash: usize) {
+        let _ = self.inodes.write().remove(&hash);
+    }
+
+    pub(super) fn insert_inode(&self, inode: Arc<VfatInode>) -> Option<Arc<VfatInode>> {
+        self.inodes.write().insert(inode.hash_index(), inode)
+    }
+
+    fn read_fat_raw(&self, cluster: ClusterID) -> Result<u32> {
+        let fat_type = self.super_block.fat_type;
+        let mut buf = [0u8; 4];
+        self.fat_cache.pages().read_bytes(
+            fat_type.entry_offset(cluster),
+            &mut buf[..fat_type.entry_len()],
+        )?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub(super) fn read_next_fat(&self, cluster: ClusterID) -> Result<FatValue> {
+        if !self.is_valid_cluster(cluster) {
+            return_errno_with_message!(Errno::EIO, "invalid access to FAT")
+        }
+
+        let value = self
+            .super_block
+            .fat_type
+            .decode(cluster, self.read_fat_raw(cluster)?);
+        match value {
+            FatValue::Next(next) if !self.is_valid_cluster(next) => {
+                return_errno_with_message!(Errno::EIO, "invalid cluster in FAT")
+            }
+            _ => Ok(value),
+        }
+    }
+
+    /// Writes a FAT entry. The caller must hold the lock of `alloc_state`.
+    fn write_next_fat(&self, cluster: ClusterID, value: FatValue) -> Result<()> {
+        let fat_type = self.super_block.fat_type;
+        let raw = fat_type.encode(cluster, self.read_fat_raw(cluster)?, value);
+        self.fat_cache.pages().write_bytes(
+            fat_type.entry_offset(cluster),
+            &raw.to_le_bytes()[..fat_type.entry_len()],
+        )?;
+        Ok(())
+    }
+
+    /// Reads the cluster chain starting from `start`, which is empty if `start` is 0.
+    pub(super) fn read_chain(&self, start: ClusterID) -> Result<Vec<ClusterID>> {
+        let mut clusters = Vec::new();
+        if start == 0 {
+            return Ok(clusters);
+        }
+
+        let mut current = start;
+        loop {
+            if clusters.len() >= self.super_block.num_clusters as usize {
+                return_errno_with_message!(Errno::EIO, "loop in cluster chain")
+            }
+            clusters.push(current);
+            match self.read_next_fat(current)? {
+                FatValue::Next(next) => current = next,
+                FatValue::EndOfChain => break,
+                FatValue::Free | FatValue::Bad => {
+                    return_errno_with_message!(Errno::EIO, "broken cluster chain")
+                }
+            }
+        }
+        Ok(clusters)
+    }
+
+    /// Allocates `num_clusters` clusters and appends them to the chain that ends with `tail`.
+    ///
+    /// There is no bitmap in FAT, so free clusters are found by scanning the FAT.
+    pub(super) fn alloc_clusters(
+        &self,
+        tail: Option<ClusterID>,
+        num_clusters: usize,
+    ) -> Result<Vec<ClusterID>> {
+        let mut alloc_state = self.alloc_state.lock();
+        self.free_orphan_clusters(&mut alloc_state)?;
+
+        if (alloc_state.num_free_clusters as usize) < num_clusters {
+            return_errno!(Errno::ENOSPC)
+        }
+
+        let total = self.super_block.num_clusters;
+        let mut allocated = Vec::with_capacity(num_clusters);
+        let mut prev = tail;
+        let mut cursor = alloc_state.next_free - FAT_FIRST_CLUSTER;
+        let mut scanned = 0;
+        while allocated.len() < num_clusters {
+            if scanned == total {
+                // The free count is inconsistent with the FAT, so the volume is full.
+                self.release_clusters(&mut alloc_state, &allocated, tail)?;
+                return_errno!(Errno::ENOSPC)
+            }
+            let cluster = FAT_FIRST_CLUSTER + cursor;
+            cursor = (cursor + 1) % total;
+            scanned += 1;
+
+            if self.read_next_fat(cluster)? != FatValue::Free {
+                continue;
+            }
+            self.write_next_fat(cluster, FatValue::EndOfChain)?;
+            if let Some(prev) = prev {
+                self.write_next_fat(prev, FatValue::Next(cluster))?;
+            }
+            allocated.push(cluster);
+            prev = Some(cluster);
+        }
+
+        alloc_state.num_free_clusters -= num_clusters as u32;
+        alloc_state.next_free = FAT_FIRST_CLUSTER + cursor;
+        Ok(allocated)
+    }
+
+    /// Frees `clusters`, which are the tail of the chain, and makes `new_tail` the end
+    /// of the chain.
+    pub(super) fn free_clusters(
+        &self,
+        clusters: &[ClusterID],
+        new_tail: Option<ClusterID>,
+    ) -> Result<()> {
+        let mut alloc_state = self.alloc_state.lock();
+        self.release_clusters(&mut alloc_state, clusters, new_tail)?;
+        alloc_state.num_free_clusters += clusters.len() as u32;
+        Ok(())
+    }
+
+    fn release_clusters(
+        &self,
+        alloc_state: &mut AllocState,
+        clusters: &[ClusterID],
+        new_tail: Option<ClusterID>,
+    ) -> Result<()> {
+        if let Some(tail) = new_tail {
+            self.write_next_fat(tail, FatValue::EndOfChain)?;
+        }
+        for &cluster in clusters {
+            self.write_next_fat(cluster, FatValue::Free)?;
+        }
+        if let Some(&first) = clusters.first() {
+            alloc_state.next_free = alloc_state.next_free.min(first);
+        }
+        Ok(())
+    }
+
+    /// Frees the clusters of a removed inode when the last reference to the inode is dropped.
+    ///
+    /// If the FAT fails to be updated, the clusters are kept as orphans and freed again by the
+    /// next allocation or sync.
+    pub(super) fn free_removed_clusters(&self, clusters: Vec<ClusterID>) {
+        self.orphan_clusters.lock().extend(clusters);
+        let mut alloc_state = self.alloc_state.lock();
+        if let Err(err) = self.free_orphan_clusters(&mut alloc_state) {
+            warn!("vfat: failed to free the removed clusters: {:?}", err);
+        }
+    }
+
+    fn free_orphan_clusters(&self, alloc_state: &mut AllocState) -> Result<()> {
+        let orphans = core::mem::take(&mut *self.orphan_clusters.lock());
+        if let Err(err) = self.release_clusters(alloc_state, &orphans, None) {
+            // Freeing a cluster twice is harmless, so all of them are retried later.
+            self.orphan_clusters.lock().extend(orphans);
+            return Err(err);
+        }
+        alloc_state.num_free_clusters += orphans.len() as u32;
+        Ok(())
+    }
+
+    /// Reads a page from `runs` of the device.
+    ///
+    /// A page that is backed by a single contiguous run is read asynchronously. Otherwise,
+    /// the runs are read one by one, and the part of the page not backed by the device
+    /// is filled with zeros.
+    pub(super) fn read_page_runs(
+        &self,
+        frame: &CachePage,
+        runs: &[DeviceRun],
+    ) -> Result<BioWaiter> {
+        if let [run] = runs {
+            if run.len == PAGE_SIZE {
+                return self.submit_page_bio(BioType::Read, frame, run.device_offset);
+            }
+        }
+
+        frame.writer().fill_zeros(PAGE_SIZE);
+        for run in runs {
+            let mut writer = frame.writer().to_fallible();
+            writer.skip(run.page_offset).limit(run.len);
+            self.block_device.read(run.device_offset, &mut writer)?;
+        }
+        Ok(BioWaiter::new())
+    }
+
+    /// Writes a page to `runs` of the device.
+    ///
+    /// Only the first full-page run is written asynchronously, since a frame can only be
+    /// mapped for DMA once at a time.
+    pub(super) fn write_page_runs(
+        &self,
+        frame: &CachePage,
+        runs: &[DeviceRun],
+    ) -> Result<BioWaiter> {
+        let mut waiter = BioWaiter::new();
+        for run in runs {
+            if waiter.nreqs() == 0 && run.len == PAGE_SIZE {
+                waiter.concat(self.submit_page_bio(BioType::Write, frame, run.device_offset)?);
+                continue;
+            }
+            let mut reader = frame.reader().to_fallible();
+            reader.skip(run.page_offset).limit(run.len);
+            self.block_device.write(run.device_offset, &mut reader)?;
+        }
+        Ok(waiter)
+    }
+
+    fn submit_page_bio(
+        &self,
+        type_: BioType,
+        frame: &CachePage,
+        device_offset: usize,
+    ) -> Result<BioWaiter> {
+        let direction = match type_ {
+            BioType::Write => BioDirection::ToDevice,
+            _ => BioDirection::FromDevice,
+        };
+        let bio_segment =
+            BioSegment::new_from_segment(Segment::from(frame.clone()).into(), direction);
+        let bio = Bio::new(
+            type_,
+            Sid::from_offset(device_offset),
+            vec![bio_segment],
+            None,
+        );
+        Ok(bio.submit(self.block_device())?)
+    }
+
+    /// Returns the runs of the FAT copies that back the page at `idx` of the FAT cache.
+    fn fat_runs(&self, idx: usize, all_copies: bool) -> Vec<DeviceRun> {
+        let sb = &self.super_block;
+        let offset = idx * PAGE_SIZE;
+        if offset >= sb.fat_size {
+            return Vec::new();
+        }
+        let len = (sb.fat_size - offset).min(PAGE_SIZE);
+        let copies = match sb.active_fat {
+            Some(active) => active..active + 1,
+            None if all_copies => 0..sb.num_fats,
+            None => 0..1,
+        };
+        copies
+            .map(|copy| DeviceRun {
+                page_offset: 0,
+                device_offset: sb.fat_start + copy * sb.fat_size + offset,
+                len,
+            })
+            .collect()
+    }
+
+    /// Writes back the FAT and the FSInfo sector.
+    pub(super) fn sync_fat(&self) -> Result<()> {
+        {
+            let mut alloc_state = self.alloc_state.lock();
+            self.free_orphan_clusters(&mut alloc_state)?;
+        }
+        self.fat_cache.evict_range(0..self.super_block.fat_size)?;
+        self.write_fs_info()
+    }
+
+    pub(super) fn block_device(&self) -> &dyn BlockDevice {
+        self.block_device.as_ref()
+    }
+
+    pub(super) fn super_block(&self) -> VfatSuperBlock {
+        self.super_block
+    }
+
+    pub(super) fn root_inode(&self) -> Arc<VfatInode> {
+        self.inodes.read().get(&ROOT_INODE_HASH).unwrap().clone()
+    }
+
+    pub(super) fn sector_size(&self) -> usize {
+        self.super_block.sector_size
+    }
+
+    pub(super) fn cluster_size(&self) -> usize {
+        self.super_block.cluster_size
+    }
+
+    pub(super) fn lock(&self) -> MutexGuard<()> {
+        self.mutex.lock()
+    }
+
+    pub(super) fn num_free_clusters(&self) -> u32 {
+        self.alloc_state.lock().num_free_clusters
+    }
+
+    pub(super) fn cluster_to_off(&self, cluster: ClusterID) -> usize {
+        self.super_block.data_start
+            + (cluster - FAT_FIRST_CLUSTER) as usize * self.super_block.cluster_size
+    }
+
+    pub(super) fn is_valid_cluster(&self, cluster: ClusterID) -> bool {
+        cluster >= FAT_FIRST_CLUSTER && cluster < FAT_FIRST_CLUSTER + self.super_block.num_clusters
+    }
+
+    pub fn mount_option(&self) -> VfatMountOptions {
+        self.mount_option.clone()
+    }
+}
+
+impl PageCacheBackend for VfatFS {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        if self.super_block.fat_size <= idx * PAGE_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "invalid read size")
+        }
+        self.read_page_runs(frame, &self.fat_runs(idx, false))
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        if self.super_block.fat_size <= idx * PAGE_SIZE {
+            return_errno_with_message!(Errno::EINVAL, "invalid write size")
+        }
+        self.write_page_runs(frame, &self.fat_runs(idx, true))
+    }
+
+    fn npages(&self) -> usize {
+        self.super_block.fat_size.div_ceil(PAGE_SIZE)
+    }
+}
+
+impl FileSystem for VfatFS {
+    fn sync(&self) -> Result<()> {
+        let inodes: Vec<_> = self.inodes.read().values().cloned().collect();
+        // The dentries are written to the page caches of the parents before the
+        // page caches are written back.
+        {
+            let fs_guard = self.lock();
+            for inode in inodes.iter() {
+                inode.write_inode(&fs_guard)?;
+            }
+        }
+        for inode in inodes.iter() {
+            inode.sync_pages()?;
+        }
+        self.sync_fat()?;
+        self.block_device.sync()?;
+        Ok(())
+    }
+
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root_inode()
+    }
+
+    fn sb(&self) -> SuperBlock {
+        let mut sb = SuperBlock::new(MSDOS_SUPER_MAGIC, self.cluster_size(), 255);
+        sb.blocks = self.super_block.num_clusters as usize;
+        sb.bfree = self.num_free_clusters() as usize;
+        sb.bavail = sb.bfree;
+        sb
+    }
+
+    fn flags(&self) -> FsFlags {
+        FsFlags::DENTRY_UNEVICTABLE
+    }
+}
+
+// Mount options
+#[derive(Clone, Debug)]
+pub struct VfatMountOptions {
+    pub(super) fs_uid: u32,
+    pub(super) fs_gid: u32,
+    pub(super) fs_fmask: u16,
+    pub(super) fs_dmask: u16,
+    pub(super) codepage: Codepage,
+}
+
+impl Default for VfatMountOptions {
+    fn default() -> Self {
+        Self {
+            fs_uid: 0,
+            fs_gid: 0,
+            fs_fmask: 0o022,
+            fs_dmask: 0o022,
+            codepage: Codepage::default(),
+        }
+    }
+}
+
+impl VfatMountOptions {
+    /// Parses the comma-separated mount options.
+    ///
+    /// Supported options are `uid=`, `gid=`, `umask=`, `fmask=`, `dmask=`, `codepage=`
+    /// and `iocharset=`. Unknown options are ignored.
+    pub fn parse(args: Option<CString>) -> Result<Self> {
+        let mut options = Self::default();
+        let Some(args) = args else {
+            return Ok(options);
+        };
+
+        let parse_mask = |value: &str| -> Result<u16> {
+            u16::from_str_radix(value, 8)
+                .ok()
+                .filter(|mask| *mask <= 0o777)
+                .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid mask"))
+        };
+        let parse_id = |value: &str| -> Result<u32> {
+            value
+                .parse::<u32>()
+                .map_err(|_| Error::with_message(Errno::EINVAL, "invalid id"))
+        };
+
+        for option in args.to_string_lossy().split(',') {
+            match option.split_once('=') {
+                Some(("uid", value)) => options.fs_uid = parse_id(value)?,
+                Some(("gid", value)) => options.fs_gid = parse_id(value)?,
+                Some(("umask", value)) => {
+                    let mask = parse_mask(value)?;
+                    options.fs_fmask = mask;
+                    options.fs_dmask = mask;
+                }
+                Some(("fmask", value)) => options.fs_fmask = parse_mask(value)?,
+                Some(("dmask", value)) => options.fs_dmask = parse_mask(value)?,
+                Some(("codepage", value)) => {
+                    let number = value
+                        .parse::<u32>()
+                        .map_err(|_| Error::with_message(Errno::EINVAL, "invalid codepage"))?;
+                    options.codepage = Codepage::from_number(number)?;
+                }
+                // Names are always exchanged with the VFS in UTF-8.
+                Some(("iocharset", "utf8" | "utf-8")) => (),
+                Some(("iocharset", _)) => {
+                    return_errno_with_message!(Errno::EINVAL, "unsupported iocharset")
+                }
+                _ => (),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub(super) struct VfatType;
+
+impl FsType for VfatType {
+    fn name(&self) -> &'static str {
+        "vfat"
+    }
+
+    fn create(
+        &self,
+        _source: &CStr,
+        args: Option<CString>,
+        disk: Option<Arc<dyn BlockDevice>>,
+        _ctx: &Context,
+    ) -> Result<Arc<dyn FileSystem>> {
+        let Some(disk) = disk else {
+            return_errno_with_message!(Errno::EINVAL, "vfat needs a block device")
+        };
+        let mount_option = VfatMountOptions::parse(args)?;
+        VfatFS::open(disk, mount_option).map(|fs| fs as _)
+    }
+
+    fn properties(&self) -> FsProperties {
+        FsProperties::NEED_DISK
+    }
+
+    fn sysnode(&self) -> Option<Arc<dyn aster_systree::SysBranchNode>> {
+        None
+    }
+}
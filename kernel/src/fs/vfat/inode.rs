@@ -0,0 +1,1201 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::string::String;
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use align_ext::AlignExt;
+use aster_block::bio::BioWaiter;
+use aster_rights::Full;
+use ostd::mm::VmIo;
+
+use super::{
+    dentry::{
+        build_dentries, check_name, generate_short_name, num_dentries_for, strip_name,
+        RawShortDentry, VfatAttr, VfatDentryIterator, VfatDentrySet, DENTRY_DELETED, DENTRY_END,
+        DENTRY_SIZE, DOTDOT_NAME, DOT_NAME, SHORT_NAME_LEN,
+    },
+    fat::{ClusterID, FatType},
+    fs::{DeviceRun, VfatFS},
+};
+use crate::{
+    events::IoEvents,
+    fs::{
+        fat_common::{make_hash_index, DosTimestamp},
+        path::{is_dot, is_dot_or_dotdot, is_dotdot},
+        utils::{
+            copy_from_page_cache, CachePage, DirentVisitor, Extension, FileSystem, Inode,
+            InodeMode, InodeType, IoctlCmd, Metadata, MknodType, PageCache, PageCacheBackend,
+        },
+    },
+    prelude::*,
+    process::{signal::PollHandle, Gid, Uid},
+    vm::vmo::Vmo,
+};
+
+/// Inode number.
+pub type Ino = u64;
+
+pub(super) const VFAT_ROOT_INO: Ino = 1;
+/// The hash value of the root inode, which has no dentry.
+pub(super) const ROOT_INODE_HASH: usize = usize::MAX;
+
+/// The maximum number of dentries in a directory.
+const MAX_DIR_ENTRIES: usize = 65536;
+/// The maximum size of a file, which must fit in the 32-bit size field.
+const MAX_FILE_SIZE: usize = u32::MAX as usize;
+
+#[derive(Debug)]
+pub struct VfatInode {
+    ino: Ino,
+    type_: InodeType,
+    /// Whether this is the root directory of FAT12/16, which is a fixed region
+    /// before the data region instead of a cluster chain.
+    is_fixed_root: bool,
+    /// The clusters and the size, which are used by the page cache backend.
+    layout: RwLock<VfatLayout>,
+    inner: RwMutex<VfatInodeInner>,
+    is_deleted: AtomicBool,
+    page_cache: PageCache,
+    this: Weak<VfatInode>,
+    fs: Weak<VfatFS>,
+    extension: Extension,
+}
+
+#[derive(Debug)]
+struct VfatLayout {
+    clusters: Vec<ClusterID>,
+    /// The size of a file, or the allocated size of a directory.
+    size: usize,
+}
+
+#[derive(Debug)]
+struct VfatInodeInner {
+    /// The long name, or the short name if there is no long name.
+    name: String,
+    /// The parent directory, which is dangling for the root.
+    parent: Weak<VfatInode>,
+    /// The index in the inode table of the file system.
+    hash: usize,
+    /// The index of the first dentry of the dentry set in the parent.
+    start_entry: usize,
+    /// The index of the short dentry in the parent.
+    short_entry: usize,
+    /// The short dentry, which holds the short name, the attributes and the timestamps.
+    /// The start cluster and the size are filled from the layout when it is written.
+    short: RawShortDentry,
+    /// Number of sub inodes that are directories.
+    num_sub_dirs: u32,
+}
+
+impl PageCacheBackend for VfatInode {
+    fn read_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let runs = self.device_runs(idx);
+        self.get_fs().read_page_runs(frame, &runs)
+    }
+
+    fn write_page_async(&self, idx: usize, frame: &CachePage) -> Result<BioWaiter> {
+        let runs = self.device_runs(idx);
+        self.get_fs().write_page_runs(frame, &runs)
+    }
+
+    fn npages(&self) -> usize {
+        self.layout.read().size.div_ceil(PAGE_SIZE)
+    }
+}
+
+impl VfatInode {
+    pub(super) fn build_root_inode(fs: &Arc<VfatFS>) -> Result<Arc<VfatInode>> {
+        let sb = fs.super_block();
+        let is_fixed_root = sb.fat_type != FatType::Fat32;
+        let (clusters, size) = if is_fixed_root {
+            (Vec::new(), sb.root_dir_size)
+        } else {
+            let clusters = fs.read_chain(sb.root_cluster)?;
+            let size = clusters.len() * sb.cluster_size;
+            (clusters, size)
+        };
+
+        let short = RawShortDentry {
+            attr: VfatAttr::DIRECTORY.bits(),
+            ..Default::default()
+        };
+        let inode = Self::new(
+            fs,
+            VFAT_ROOT_INO,
+            is_fixed_root,
+            VfatLayout { clusters, size },
+            VfatInodeInner {
+                name: String::new(),
+                parent: Weak::new(),
+                hash: ROOT_INODE_HASH,
+                start_entry: 0,
+                short_entry: 0,
+                short,
+                num_sub_dirs: 0,
+            },
+        );
+        inode.inner.write().num_sub_dirs = inode.count_sub_dirs()?;
+        Ok(inode)
+    }
+
+    fn build_from_dentry_set(
+        fs: &Arc<VfatFS>,
+        parent: &Arc<VfatInode>,
+        dentry_set: &VfatDentrySet,
+    ) -> Result<Arc<VfatInode>> {
+        let short = dentry_set.short;
+        let clusters = fs.read_chain(short.start_cluster(fs.super_block().fat_type))?;
+        let allocated_size = clusters.len() * fs.cluster_size();
+        let size = if short.attr().contains(VfatAttr::DIRECTORY) {
+            allocated_size
+        } else {
+            short.size as usize
+        };
+        if size > allocated_size {
+            return_errno_with_message!(Errno::EIO, "file size exceeds its clusters")
+        }
+
+        Ok(Self::new(
+            fs,
+            fs.alloc_inode_number(),
+            false,
+            VfatLayout { clusters, size },
+            VfatInodeInner {
+                name: dentry_set.name.clone(),
+                parent: Arc::downgrade(parent),
+                hash: parent.child_hash(dentry_set.short_entry),
+                start_entry: dentry_set.start_entry,
+                short_entry: dentry_set.short_entry,
+                short,
+                num_sub_dirs: 0,
+            },
+        ))
+    }
+
+    fn new(
+        fs: &Arc<VfatFS>,
+        ino: Ino,
+        is_fixed_root: bool,
+        layout: VfatLayout,
+        inner: VfatInodeInner,
+    ) -> Arc<VfatInode> {
+        let type_ = if inner.short.attr().contains(VfatAttr::DIRECTORY) {
+            InodeType::Dir
+        } else {
+            InodeType::File
+        };
+        let size = layout.size;
+        Arc::new_cyclic(|weak_self| VfatInode {
+            ino,
+            type_,
+            is_fixed_root,
+            layout: RwLock::new(layout),
+            inner: RwMutex::new(inner),
+            is_deleted: AtomicBool::new(false),
+            page_cache: PageCache::with_capacity(size, weak_self.clone() as _).unwrap(),
+            this: weak_self.clone(),
+            fs: Arc::downgrade(fs),
+            extension: Extension::new(),
+        })
+    }
+
+    pub(super) fn hash_index(&self) -> usize {
+        self.inner.read().hash
+    }
+
+    fn get_fs(&self) -> Arc<VfatFS> {
+        self.fs.upgrade().unwrap()
+    }
+
+    fn parent(&self) -> Option<Arc<VfatInode>> {
+        self.inner.read().parent.upgrade()
+    }
+
+    fn is_root(&self) -> bool {
+        self.ino == VFAT_ROOT_INO
+    }
+
+    /// Returns the first cluster, which is 0 if no cluster is allocated.
+    fn first_cluster(&self) -> ClusterID {
+        self.layout.read().clusters.first().copied().unwrap_or(0)
+    }
+
+    /// Returns the cluster stored in the ".." dentry of a sub-directory, which is 0
+    /// for the root directory.
+    fn cluster_for_dotdot(&self) -> ClusterID {
+        if self.is_root() {
+            0
+        } else {
+            self.first_cluster()
+        }
+    }
+
+    /// The hash of a child is the position of its short dentry, which is unique in the
+    /// file system since the first cluster of a directory never changes.
+    fn child_hash(&self, short_entry: usize) -> usize {
+        make_hash_index(self.first_cluster(), short_entry as u32)
+    }
+
+    fn num_entries(&self) -> usize {
+        self.layout.read().size / DENTRY_SIZE
+    }
+
+    fn dentry_iter(&self) -> VfatDentryIterator {
+        let codepage = self.get_fs().mount_option().codepage;
+        VfatDentryIterator::new(
+            self.page_cache.pages().dup(),
+            0,
+            self.num_entries(),
+            codepage,
+        )
+    }
+
+    /// Returns the runs of the device that back the page at `idx`.
+    ///
+    /// Contiguous clusters are merged into a single run.
+    fn device_runs(&self, idx: usize) -> Vec<DeviceRun> {
+        let fs = self.get_fs();
+        let sb = fs.super_block();
+        let page_start = idx * PAGE_SIZE;
+
+        if self.is_fixed_root {
+            let len = sb.root_dir_size.saturating_sub(page_start).min(PAGE_SIZE);
+            if len == 0 {
+                return Vec::new();
+            }
+            return vec![DeviceRun {
+                page_offset: 0,
+                device_offset: sb.root_dir_start + page_start,
+                len,
+            }];
+        }
+
+        let layout = self.layout.read();
+        let allocated_size = layout.clusters.len() * sb.cluster_size;
+        let page_end = allocated_size.min(page_start + PAGE_SIZE);
+        let mut runs: Vec<DeviceRun> = Vec::new();
+        let mut offset = page_start;
+        while offset < page_end {
+            let cluster = layout.clusters[offset / sb.cluster_size];
+            let offset_in_cluster = offset % sb.cluster_size;
+            let len = (sb.cluster_size - offset_in_cluster).min(page_end - offset);
+            let device_offset = fs.cluster_to_off(cluster) + offset_in_cluster;
+            match runs.last_mut() {
+                Some(last) if last.device_offset + last.len == device_offset => last.len += len,
+                _ => runs.push(DeviceRun {
+                    page_offset: offset - page_start,
+                    device_offset,
+                    len,
+                }),
+            }
+            offset += len;
+        }
+        runs
+    }
+
+    fn count_sub_dirs(&self) -> Result<u32> {
+        let mut num_sub_dirs = 0;
+        for dentry_set in self.dentry_iter() {
+            if dentry_set?.short.attr().contains(VfatAttr::DIRECTORY) {
+                num_sub_dirs += 1;
+            }
+        }
+        Ok(num_sub_dirs)
+    }
+
+    fn is_empty_dir(&self) -> Result<bool> {
+        match self.dentry_iter().next() {
+            Some(dentry_set) => dentry_set.map(|_| false),
+            None => Ok(true),
+        }
+    }
+
+    fn find_child(&self, name: &str) -> Result<Option<VfatDentrySet>> {
+        let codepage = self.get_fs().mount_option().codepage;
+        for dentry_set in self.dentry_iter() {
+            let dentry_set = dentry_set?;
+            if dentry_set.matches(name, codepage) {
+                return Ok(Some(dentry_set));
+            }
+        }
+        Ok(None)
+    }
+
+    fn lookup_child(&self, name: &str) -> Result<(VfatDentrySet, Arc<VfatInode>)> {
+        let Some(dentry_set) = self.find_child(strip_name(name))? else {
+            return_errno!(Errno::ENOENT)
+        };
+        let inode = self.get_or_load_child(&dentry_set)?;
+        Ok((dentry_set, inode))
+    }
+
+    fn get_or_load_child(&self, dentry_set: &VfatDentrySet) -> Result<Arc<VfatInode>> {
+        let fs = self.get_fs();
+        if let Some(inode) = fs.find_opened_inode(self.child_hash(dentry_set.short_entry)) {
+            return Ok(inode);
+        }
+
+        let inode = Self::build_from_dentry_set(&fs, &self.this.upgrade().unwrap(), dentry_set)?;
+        if inode.type_ == InodeType::Dir {
+            inode.inner.write().num_sub_dirs = inode.count_sub_dirs()?;
+        }
+        let _ = fs.insert_inode(inode.clone());
+        Ok(inode)
+    }
+
+    /// Finds `num_dentries` contiguous free dentries, and returns the index of the first.
+    ///
+    /// If there are not enough free dentries, the directory is extended.
+    fn find_empty_dentries(&self, num_dentries: usize) -> Result<usize> {
+        let num_entries = self.num_entries();
+        let pages = self.page_cache.pages();
+
+        let mut run_start = 0;
+        let mut run_len = 0;
+        let mut reached_end = false;
+        for entry in 0..num_entries {
+            let first_byte = if reached_end {
+                DENTRY_END
+            } else {
+                pages.read_val::<u8>(entry * DENTRY_SIZE)?
+            };
+            match first_byte {
+                DENTRY_END | DENTRY_DELETED => {
+                    reached_end |= first_byte == DENTRY_END;
+                    if run_len == 0 {
+                        run_start = entry;
+                    }
+                    run_len += 1;
+                    if run_len == num_dentries {
+                        return Ok(run_start);
+                    }
+                }
+                _ => run_len = 0,
+            }
+        }
+
+        // Extend the directory, reusing the free dentries at its end.
+        if run_len == 0 {
+            run_start = num_entries;
+        }
+        let new_num_entries = run_start + num_dentries;
+        if self.is_fixed_root || new_num_entries > MAX_DIR_ENTRIES {
+            return_errno_with_message!(Errno::ENOSPC, "directory is full")
+        }
+        let cluster_size = self.get_fs().cluster_size();
+        let new_size = (new_num_entries * DENTRY_SIZE).align_up(cluster_size);
+        self.extend_to(new_size, new_size)?;
+        Ok(run_start)
+    }
+
+    /// Allocates clusters to hold `new_size` bytes, and updates the size.
+    ///
+    /// The new content before `zero_end` is filled with zeros, and the rest is left
+    /// for the caller to overwrite.
+    fn extend_to(&self, new_size: usize, zero_end: usize) -> Result<()> {
+        let fs = self.get_fs();
+        let cluster_size = fs.cluster_size();
+        let (old_size, tail, num_clusters) = {
+            let layout = self.layout.read();
+            (
+                layout.size,
+                layout.clusters.last().copied(),
+                layout.clusters.len(),
+            )
+        };
+        let num_new_clusters = new_size.div_ceil(cluster_size).saturating_sub(num_clusters);
+        if num_new_clusters > 0 {
+            let new_clusters = fs.alloc_clusters(tail, num_new_clusters)?;
+            self.layout.write().clusters.extend(new_clusters);
+        }
+
+        self.layout.write().size = new_size;
+        self.page_cache.resize(new_size)?;
+        self.page_cache
+            .fill_zeros(old_size..zero_end.min(new_size))?;
+        Ok(())
+    }
+
+    /// Shrinks the size to `new_size`, and frees the clusters that are no longer needed.
+    fn truncate_to(&self, new_size: usize) -> Result<()> {
+        let fs = self.get_fs();
+        self.page_cache.resize(new_size)?;
+
+        let freed = {
+            let mut layout = self.layout.write();
+            layout.size = new_size;
+            let num_clusters = new_size.div_ceil(fs.cluster_size());
+            if num_clusters >= layout.clusters.len() {
+                return Ok(());
+            }
+            layout.clusters.split_off(num_clusters)
+        };
+        let new_tail = self.layout.read().clusters.last().copied();
+        fs.free_clusters(&freed, new_tail)
+    }
+
+    fn resize_locked(&self, new_size: usize, zero_end: usize) -> Result<()> {
+        if new_size > MAX_FILE_SIZE {
+            return_errno!(Errno::EFBIG)
+        }
+        let old_size = self.size();
+        match new_size.cmp(&old_size) {
+            core::cmp::Ordering::Greater => self.extend_to(new_size, zero_end),
+            core::cmp::Ordering::Less => self.truncate_to(new_size),
+            core::cmp::Ordering::Equal => Ok(()),
+        }
+    }
+
+    /// Writes the dentries of `name` to this directory, and returns the indexes of the
+    /// first dentry and the short dentry.
+    fn insert_dentry_set(
+        &self,
+        name: &str,
+        short: &RawShortDentry,
+        with_long_name: bool,
+    ) -> Result<(usize, usize)> {
+        let num_dentries = num_dentries_for(name, with_long_name);
+        let start_entry = self.find_empty_dentries(num_dentries)?;
+        let bytes = build_dentries(name, short, with_long_name);
+        self.page_cache
+            .pages()
+            .write_bytes(start_entry * DENTRY_SIZE, &bytes)?;
+        Ok((start_entry, start_entry + num_dentries - 1))
+    }
+
+    fn delete_dentries(&self, start_entry: usize, num_dentries: usize) -> Result<()> {
+        for entry in start_entry..start_entry + num_dentries {
+            self.page_cache
+                .pages()
+                .write_val(entry * DENTRY_SIZE, &DENTRY_DELETED)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the short names in this directory, which must not be reused.
+    fn short_names(&self, exclude_entry: Option<usize>) -> Result<Vec<[u8; SHORT_NAME_LEN]>> {
+        let mut names = Vec::new();
+        for dentry_set in self.dentry_iter() {
+            let dentry_set = dentry_set?;
+            if Some(dentry_set.short_entry) != exclude_entry {
+                names.push(dentry_set.short.name);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Adds a new file or directory named `name`.
+    fn add_entry(
+        &self,
+        name: &str,
+        type_: InodeType,
+        mode: InodeMode,
+        _fs_guard: &MutexGuard<()>,
+    ) -> Result<Arc<VfatInode>> {
+        let fs = self.get_fs();
+        let codepage = fs.mount_option().codepage;
+        let (short_name, with_long_name) =
+            generate_short_name(name, codepage, &self.short_names(None)?)?;
+
+        let now = DosTimestamp::now()?;
+        let mut attr = VfatAttr::ARCHIVE;
+        if type_ == InodeType::Dir {
+            attr = VfatAttr::DIRECTORY;
+        } else if !mode.is_owner_writable() {
+            attr |= VfatAttr::READONLY;
+        }
+        let mut short = RawShortDentry {
+            name: short_name,
+            attr: attr.bits(),
+            create_time_cs: now.increment_10ms,
+            create_time: now.time,
+            create_date: now.date,
+            access_date: now.date,
+            modify_time: now.time,
+            modify_date: now.date,
+            ..Default::default()
+        };
+
+        // A directory always has a cluster for the "." and ".." dentries.
+        let dir_cluster = if type_ == InodeType::Dir {
+            let cluster = fs.alloc_clusters(None, 1)?[0];
+            short.set_start_cluster(cluster);
+            Some(cluster)
+        } else {
+            None
+        };
+
+        let (start_entry, short_entry) = match self.insert_dentry_set(name, &short, with_long_name)
+        {
+            Ok(entries) => entries,
+            Err(err) => {
+                if let Some(cluster) = dir_cluster {
+                    fs.free_clusters(&[cluster], None)?;
+                }
+                return Err(err);
+            }
+        };
+
+        let dentry_set = VfatDentrySet {
+            start_entry,
+            short_entry,
+            short,
+            name: String::from(name),
+        };
+        let inode = Self::build_from_dentry_set(&fs, &self.this.upgrade().unwrap(), &dentry_set)?;
+
+        if type_ == InodeType::Dir {
+            let size = inode.size();
+            inode.page_cache.fill_zeros(0..size)?;
+
+            let mut dot = short;
+            dot.name = DOT_NAME;
+            let mut dotdot = short;
+            dotdot.name = DOTDOT_NAME;
+            dotdot.set_start_cluster(self.cluster_for_dotdot());
+            let pages = inode.page_cache.pages();
+            pages.write_val(0, &dot)?;
+            pages.write_val(DENTRY_SIZE, &dotdot)?;
+
+            self.inner.write().num_sub_dirs += 1;
+        }
+
+        let _ = fs.insert_inode(inode.clone());
+        Ok(inode)
+    }
+
+    /// Removes the dentries of a child, whose clusters are freed when it is dropped.
+    fn delete_child(
+        &self,
+        dentry_set: &VfatDentrySet,
+        inode: &VfatInode,
+        _fs_guard: &MutexGuard<()>,
+    ) -> Result<()> {
+        self.delete_dentries(dentry_set.start_entry, dentry_set.num_dentries())?;
+        self.get_fs().remove_inode(inode.hash_index());
+        inode.is_deleted.store(true, Ordering::Release);
+        if inode.type_ == InodeType::Dir {
+            self.inner.write().num_sub_dirs -= 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the short dentry to the page cache of the parent.
+    pub(super) fn write_inode(&self, _fs_guard: &MutexGuard<()>) -> Result<()> {
+        if self.is_root() || self.is_deleted.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let inner = self.inner.read();
+        let Some(parent) = inner.parent.upgrade() else {
+            return Ok(());
+        };
+        let mut short = inner.short;
+        short.set_start_cluster(self.first_cluster());
+        short.size = if self.type_ == InodeType::Dir {
+            0
+        } else {
+            self.size() as u32
+        };
+        parent
+            .page_cache
+            .pages()
+            .write_val(inner.short_entry * DENTRY_SIZE, &short)?;
+        Ok(())
+    }
+
+    /// Writes back the cached pages.
+    pub(super) fn sync_pages(&self) -> Result<()> {
+        let size = self.layout.read().size;
+        self.page_cache.evict_range(0..size)
+    }
+
+    fn sync_dentry(&self, fs_guard: &MutexGuard<()>) -> Result<()> {
+        self.write_inode(fs_guard)?;
+        let Some(parent) = self.parent() else {
+            return Ok(());
+        };
+        let inner = self.inner.read();
+        parent
+            .page_cache
+            .evict_range(inner.start_entry * DENTRY_SIZE..(inner.short_entry + 1) * DENTRY_SIZE)
+    }
+
+    /// Checks whether `self` is `ancestor` or one of its descendants.
+    fn is_descendant_of(&self, ancestor: &VfatInode) -> bool {
+        let mut current = self.parent();
+        if self.ino == ancestor.ino {
+            return true;
+        }
+        while let Some(dir) = current {
+            if dir.ino == ancestor.ino {
+                return true;
+            }
+            current = dir.parent();
+        }
+        false
+    }
+
+    fn make_mode(&self) -> InodeMode {
+        let mount_option = self.get_fs().mount_option();
+        let attr = self.inner.read().short.attr();
+        let mask = if self.type_ == InodeType::Dir {
+            mount_option.fs_dmask
+        } else {
+            mount_option.fs_fmask
+        };
+        let mut mode = InodeMode::from_bits_truncate(0o777 & !mask);
+        if attr.contains(VfatAttr::READONLY) && self.type_ != InodeType::Dir {
+            mode.remove(InodeMode::S_IWUSR | InodeMode::S_IWGRP | InodeMode::S_IWOTH);
+        }
+        mode
+    }
+
+    fn update_atime(&self) {
+        if let Ok(now) = DosTimestamp::now() {
+            self.inner.write().short.access_date = now.date;
+        }
+    }
+
+    fn update_mtime(&self) {
+        if let Ok(now) = DosTimestamp::now() {
+            let mut inner = self.inner.write();
+            inner.short.modify_time = now.time;
+            inner.short.modify_date = now.date;
+            inner.short.attr |= VfatAttr::ARCHIVE.bits();
+        }
+    }
+}
+
+impl Drop for VfatInode {
+    fn drop(&mut self) {
+        if !self.is_deleted.load(Ordering::Acquire) {
+            return;
+        }
+        let clusters = core::mem::take(&mut self.layout.get_mut().clusters);
+        if let Some(fs) = self.fs.upgrade() {
+            fs.free_removed_clusters(clusters);
+        }
+    }
+}
+
+impl Inode for VfatInode {
+    fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    fn size(&self) -> usize {
+        self.layout.read().size
+    }
+
+    fn resize(&self, new_size: usize) -> Result<()> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+
+        let fs = self.get_fs();
+        let _fs_guard = fs.lock();
+        self.resize_locked(new_size, new_size)?;
+        self.update_mtime();
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        let fs = self.get_fs();
+        let mount_option = fs.mount_option();
+        let (size, num_clusters) = {
+            let layout = self.layout.read();
+            (layout.size, layout.clusters.len())
+        };
+        let blk_size = fs.cluster_size();
+        let nlinks = if self.type_ == InodeType::Dir {
+            self.inner.read().num_sub_dirs as usize + 2
+        } else {
+            1
+        };
+
+        Metadata {
+            dev: 0,
+            ino: self.ino,
+            size,
+            blk_size,
+            blocks: if self.is_fixed_root {
+                size.div_ceil(blk_size)
+            } else {
+                num_clusters
+            },
+            atime: self.atime(),
+            mtime: self.mtime(),
+            ctime: self.ctime(),
+            type_: self.type_,
+            mode: self.make_mode(),
+            nlinks,
+            uid: Uid::new(mount_option.fs_uid),
+            gid: Gid::new(mount_option.fs_gid),
+            rdev: 0,
+        }
+    }
+
+    fn type_(&self) -> InodeType {
+        self.type_
+    }
+
+    fn mode(&self) -> Result<InodeMode> {
+        Ok(self.make_mode())
+    }
+
+    fn set_mode(&self, mode: InodeMode) -> Result<()> {
+        // Only the read-only attribute of files can be stored.
+        if self.type_ == InodeType::Dir {
+            return Ok(());
+        }
+        let mut inner = self.inner.write();
+        let mut attr = inner.short.attr();
+        attr.set(VfatAttr::READONLY, !mode.is_owner_writable());
+        inner.short.attr = attr.bits();
+        Ok(())
+    }
+
+    fn atime(&self) -> Duration {
+        let short = self.inner.read().short;
+        DosTimestamp::new(0, short.access_date, 0, 0)
+            .and_then(|time| time.as_duration())
+            .unwrap_or_default()
+    }
+
+    fn set_atime(&self, time: Duration) {
+        let time = DosTimestamp::from_duration(time).unwrap_or_default();
+        self.inner.write().short.access_date = time.date;
+    }
+
+    fn mtime(&self) -> Duration {
+        let short = self.inner.read().short;
+        DosTimestamp::new(short.modify_time, short.modify_date, 0, 0)
+            .and_then(|time| time.as_duration())
+            .unwrap_or_default()
+    }
+
+    fn set_mtime(&self, time: Duration) {
+        let time = DosTimestamp::from_duration(time).unwrap_or_default();
+        let mut inner = self.inner.write();
+        inner.short.modify_time = time.time;
+        inner.short.modify_date = time.date;
+    }
+
+    fn ctime(&self) -> Duration {
+        let short = self.inner.read().short;
+        DosTimestamp::new(
+            short.create_time,
+            short.create_date,
+            short.create_time_cs,
+            0,
+        )
+        .and_then(|time| time.as_duration())
+        .unwrap_or_default()
+    }
+
+    fn set_ctime(&self, time: Duration) {
+        let time = DosTimestamp::from_duration(time).unwrap_or_default();
+        let mut inner = self.inner.write();
+        inner.short.create_time = time.time;
+        inner.short.create_date = time.date;
+        inner.short.create_time_cs = time.increment_10ms;
+    }
+
+    fn owner(&self) -> Result<Uid> {
+        Ok(Uid::new(self.get_fs().mount_option().fs_uid))
+    }
+
+    fn set_owner(&self, uid: Uid) -> Result<()> {
+        // The owner is fixed by the mount options.
+        if uid != self.owner()? {
+            return_errno_with_message!(Errno::EPERM, "vfat does not support changing owner")
+        }
+        Ok(())
+    }
+
+    fn group(&self) -> Result<Gid> {
+        Ok(Gid::new(self.get_fs().mount_option().fs_gid))
+    }
+
+    fn set_group(&self, gid: Gid) -> Result<()> {
+        if gid != self.group()? {
+            return_errno_with_message!(Errno::EPERM, "vfat does not support changing group")
+        }
+        Ok(())
+    }
+
+    fn fs(&self) -> Arc<dyn FileSystem> {
+        self.get_fs()
+    }
+
+    fn page_cache(&self) -> Option<Vmo<Full>> {
+        Some(self.page_cache.pages().dup())
+    }
+
+    fn read_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let (read_off, read_len) = {
+            let file_size = self.size();
+            let start = file_size.min(offset);
+            let end = file_size.min(offset + writer.avail());
+            (start, end - start)
+        };
+        self.page_cache.pages().read(read_off, writer)?;
+
+        self.update_atime();
+        Ok(read_len)
+    }
+
+    // FAT has no alignment requirement on files, so direct I/O goes through the
+    // page cache and the written range is flushed immediately.
+    fn read_direct_at(&self, offset: usize, writer: &mut VmWriter) -> Result<usize> {
+        self.read_at(offset, writer)
+    }
+
+    fn write_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let write_len = reader.remain();
+        if write_len == 0 {
+            return Ok(0);
+        }
+        let new_size = offset + write_len;
+        if new_size > self.size() {
+            let fs = self.get_fs();
+            let _fs_guard = fs.lock();
+            if new_size > self.size() {
+                self.resize_locked(new_size, offset)?;
+            }
+        }
+
+        // The fs lock is released, so that file writes can be parallelized.
+        self.page_cache.pages().write(offset, reader)?;
+
+        self.update_mtime();
+        Ok(write_len)
+    }
+
+    fn write_direct_at(&self, offset: usize, reader: &mut VmReader) -> Result<usize> {
+        let write_len = self.write_at(offset, reader)?;
+        self.page_cache.evict_range(offset..offset + write_len)?;
+        Ok(write_len)
+    }
+
+    fn copy_file_range(
+        &self,
+        offset: usize,
+        dst: &Arc<dyn Inode>,
+        dst_offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        if self.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        let bytes_copied = copy_from_page_cache(
+            self.page_cache.pages(),
+            self.size(),
+            offset,
+            dst,
+            dst_offset,
+            len,
+        )?;
+        self.update_atime();
+        Ok(bytes_copied)
+    }
+
+    fn create(&self, name: &str, type_: InodeType, mode: InodeMode) -> Result<Arc<dyn Inode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+        if type_ != InodeType::File && type_ != InodeType::Dir {
+            return_errno_with_message!(Errno::EPERM, "vfat only supports files and directories")
+        }
+        let name = strip_name(name);
+        check_name(name)?;
+
+        let fs = self.get_fs();
+        let fs_guard = fs.lock();
+        if self.is_deleted.load(Ordering::Acquire) {
+            return_errno_with_message!(Errno::ENOENT, "directory removed")
+        }
+        if self.find_child(name)?.is_some() {
+            return_errno!(Errno::EEXIST)
+        }
+
+        let inode = self.add_entry(name, type_, mode, &fs_guard)?;
+        self.update_mtime();
+        Ok(inode)
+    }
+
+    fn mknod(&self, _name: &str, _mode: InodeMode, _type_: MknodType) -> Result<Arc<dyn Inode>> {
+        return_errno_with_message!(Errno::EPERM, "vfat does not support special files")
+    }
+
+    fn readdir_at(&self, offset: usize, visitor: &mut dyn DirentVisitor) -> Result<usize> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+
+        // The offsets 0 and 1 stand for "." and "..", and the offset of the dentries
+        // in the directory starts from 2.
+        const DENTRY_OFFSET_BASE: usize = 2;
+
+        let fs = self.get_fs();
+        let fs_guard = fs.lock();
+        let try_readdir = |offset: &mut usize, visitor: &mut dyn DirentVisitor| -> Result<()> {
+            if *offset == 0 {
+                visitor.visit(".", self.ino, self.type_, 1)?;
+                *offset = 1;
+            }
+            if *offset == 1 {
+                let parent_ino = self.parent().map_or(self.ino, |parent| parent.ino);
+                visitor.visit("..", parent_ino, InodeType::Dir, DENTRY_OFFSET_BASE)?;
+                *offset = DENTRY_OFFSET_BASE;
+            }
+
+            let start_entry = (*offset - DENTRY_OFFSET_BASE).div_ceil(DENTRY_SIZE);
+            let iter = VfatDentryIterator::new(
+                self.page_cache.pages().dup(),
+                start_entry,
+                self.num_entries(),
+                fs.mount_option().codepage,
+            );
+            for dentry_set in iter {
+                let dentry_set = dentry_set?;
+                let inode = self.get_or_load_child(&dentry_set)?;
+                let next_offset = dentry_set.end_offset() + DENTRY_OFFSET_BASE;
+                visitor.visit(&dentry_set.name, inode.ino, inode.type_, next_offset)?;
+                *offset = next_offset;
+            }
+            Ok(())
+        };
+
+        let mut iterate_offset = offset;
+        let offset_read = match try_readdir(&mut iterate_offset, visitor) {
+            Err(e) if iterate_offset == offset => Err(e),
+            _ => Ok(iterate_offset - offset),
+        }?;
+        drop(fs_guard);
+
+        self.update_atime();
+        Ok(offset_read)
+    }
+
+    fn link(&self, _old: &Arc<dyn Inode>, _name: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EPERM, "vfat does not support hard links")
+    }
+
+    fn unlink(&self, name: &str) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+        if is_dot_or_dotdot(name) {
+            return_errno!(Errno::EISDIR)
+        }
+
+        let fs = self.get_fs();
+        let fs_guard = fs.lock();
+        let (dentry_set, inode) = self.lookup_child(name)?;
+        if inode.type_ == InodeType::Dir {
+            return_errno!(Errno::EISDIR)
+        }
+        self.delete_child(&dentry_set, &inode, &fs_guard)?;
+        drop(fs_guard);
+
+        self.update_mtime();
+        Ok(())
+    }
+
+    fn rmdir(&self, name: &str) -> Result<()> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+        if is_dot(name) {
+            return_errno_with_message!(Errno::EINVAL, "rmdir on .")
+        }
+        if is_dotdot(name) {
+            return_errno_with_message!(Errno::ENOTEMPTY, "rmdir on ..")
+        }
+
+        let fs = self.get_fs();
+        let fs_guard = fs.lock();
+        let (dentry_set, inode) = self.lookup_child(name)?;
+        if inode.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+        if !inode.is_empty_dir()? {
+            return_errno!(Errno::ENOTEMPTY)
+        }
+        self.delete_child(&dentry_set, &inode, &fs_guard)?;
+        drop(fs_guard);
+
+        self.update_mtime();
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>> {
+        if self.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+
+        let inode = {
+            let fs = self.get_fs();
+            let _fs_guard = fs.lock();
+            self.lookup_child(name)?.1
+        };
+
+        self.update_atime();
+        Ok(inode)
+    }
+
+    fn rename(&self, old_name: &str, target: &Arc<dyn Inode>, new_name: &str) -> Result<()> {
+        if is_dot_or_dotdot(old_name) || is_dot_or_dotdot(new_name) {
+            return_errno!(Errno::EISDIR)
+        }
+        let Some(target) = target.downcast_ref::<VfatInode>() else {
+            return_errno_with_message!(Errno::EXDEV, "not same fs")
+        };
+        if self.type_ != InodeType::Dir || target.type_ != InodeType::Dir {
+            return_errno!(Errno::ENOTDIR)
+        }
+        let new_name = strip_name(new_name);
+        check_name(new_name)?;
+
+        let fs = self.get_fs();
+        let fs_guard = fs.lock();
+        let (old_set, inode) = self.lookup_child(old_name)?;
+        let is_same_dir = self.ino == target.ino;
+        if inode.type_ == InodeType::Dir && target.is_descendant_of(&inode) {
+            return_errno_with_message!(Errno::EINVAL, "cannot move a directory into itself")
+        }
+
+        let mut victim = None;
+        if let Some(exist_set) = target.find_child(new_name)? {
+            if is_same_dir && exist_set.short_entry == old_set.short_entry {
+                // Only the case of the name is changed.
+                if exist_set.name == new_name {
+                    return Ok(());
+                }
+            } else {
+                let exist_inode = target.get_or_load_child(&exist_set)?;
+                match (inode.type_, exist_inode.type_) {
+                    (InodeType::Dir, InodeType::Dir) => {
+                        if !exist_inode.is_empty_dir()? {
+                            return_errno!(Errno::ENOTEMPTY)
+                        }
+                    }
+                    (InodeType::Dir, _) => return_errno!(Errno::ENOTDIR),
+                    (_, InodeType::Dir) => return_errno!(Errno::EISDIR),
+                    _ => (),
+                }
+                victim = Some((exist_set, exist_inode));
+            }
+        }
+
+        // Write the new dentries before removing the old ones, so that the inode is never
+        // lost if the directory cannot be extended.
+        let codepage = fs.mount_option().codepage;
+        let exclude_entry = is_same_dir.then_some(old_set.short_entry);
+        let (short_name, with_long_name) =
+            generate_short_name(new_name, codepage, &target.short_names(exclude_entry)?)?;
+        let mut short = inode.inner.read().short;
+        short.name = short_name;
+        short.case_flags = 0;
+        short.set_start_cluster(inode.first_cluster());
+        short.size = if inode.type_ == InodeType::Dir {
+            0
+        } else {
+            inode.size() as u32
+        };
+        let (start_entry, short_entry) =
+            target.insert_dentry_set(new_name, &short, with_long_name)?;
+
+        if let Some((exist_set, exist_inode)) = victim {
+            target.delete_child(&exist_set, &exist_inode, &fs_guard)?;
+        }
+        self.delete_dentries(old_set.start_entry, old_set.num_dentries())?;
+
+        fs.remove_inode(inode.hash_index());
+        {
+            let mut inner = inode.inner.write();
+            inner.name = String::from(new_name);
+            inner.start_entry = start_entry;
+            inner.short_entry = short_entry;
+            inner.short = short;
+            inner.hash = target.child_hash(short_entry);
+            inner.parent = target.this.clone();
+        }
+        let _ = fs.insert_inode(inode.clone());
+
+        if inode.type_ == InodeType::Dir && !is_same_dir {
+            let pages = inode.page_cache.pages();
+            let mut dotdot = pages.read_val::<RawShortDentry>(DENTRY_SIZE)?;
+            if dotdot.name == DOTDOT_NAME {
+                dotdot.set_start_cluster(target.cluster_for_dotdot());
+                pages.write_val(DENTRY_SIZE, &dotdot)?;
+            }
+            self.inner.write().num_sub_dirs -= 1;
+            target.inner.write().num_sub_dirs += 1;
+        }
+        drop(fs_guard);
+
+        self.update_mtime();
+        if !is_same_dir {
+            target.update_mtime();
+        }
+        Ok(())
+    }
+
+    fn read_link(&self) -> Result<String> {
+        return_errno_with_message!(Errno::EINVAL, "vfat does not support symbolic links")
+    }
+
+    fn write_link(&self, _target: &str) -> Result<()> {
+        return_errno_with_message!(Errno::EINVAL, "vfat does not support symbolic links")
+    }
+
+    fn ioctl(&self, _cmd: IoctlCmd, _arg: usize) -> Result<i32> {
+        return_errno_with_message!(Errno::EINVAL, "unsupported operation")
+    }
+
+    fn sync_all(&self) -> Result<()> {
+        let fs = self.get_fs();
+        {
+            let fs_guard = fs.lock();
+            self.sync_dentry(&fs_guard)?;
+        }
+        self.sync_pages()?;
+        fs.sync_fat()?;
+        fs.block_device().sync()?;
+        Ok(())
+    }
+
+    fn sync_data(&self) -> Result<()> {
+        let fs = self.get_fs();
+        self.sync_pages()?;
+        // The FAT is needed to locate the data.
+        fs.sync_fat()?;
+        fs.block_device().sync()?;
+        Ok(())
+    }
+
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+
+    fn is_dentry_cacheable(&self) -> bool {
+        true
+    }
+
+    fn extension(&self) -> Option<&Extension> {
+        Some(&self.extension)
+    }
+}
@@ -0,0 +1,460 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The FAT file system, which supports FAT12, FAT16 and FAT32 with long names (VFAT).
+
+mod codepage;
+mod dentry;
+mod fat;
+mod fs;
+mod inode;
+mod super_block;
+
+use alloc::sync::Arc;
+
+pub use fs::{VfatFS, VfatMountOptions};
+pub use inode::VfatInode;
+
+use crate::fs::vfat::fs::VfatType;
+
+pub(super) fn init() {
+    let vfat_type = Arc::new(VfatType);
+    super::registry::register(vfat_type).unwrap();
+}
+
+#[cfg(ktest)]
+mod test {
+    use alloc::fmt::Debug;
+
+    use aster_block::{
+        bio::{BioEnqueueError, BioStatus, BioType, SubmittedBio},
+        BlockDevice, BlockDeviceMeta,
+    };
+    use ostd::{
+        mm::{io_util::HasVmReaderWriter, FrameAllocOptions, Segment, VmIo, PAGE_SIZE},
+        prelude::*,
+        Pod,
+    };
+
+    use crate::{
+        fs::{
+            utils::{FileSystem, Inode, InodeMode, InodeType},
+            vfat::{
+                dentry::MAX_NAME_LENGTH,
+                fat::FatType,
+                super_block::{VfatBootSector, VfatFsInfo, BOOT_SIGNATURE, FSINFO_UNKNOWN},
+                VfatFS, VfatMountOptions,
+            },
+        },
+        prelude::*,
+    };
+
+    const SECTOR_SIZE: usize = 512;
+    /// Every volume uses clusters of one sector, so that a few KiB span many clusters.
+    const CLUSTER_SIZE: usize = SECTOR_SIZE;
+
+    /// A block device backed by memory.
+    struct VfatMemoryDisk(Segment<()>);
+
+    impl VfatMemoryDisk {
+        fn sectors_count(&self) -> usize {
+            self.0.size() / SECTOR_SIZE
+        }
+    }
+
+    impl Debug for VfatMemoryDisk {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct("VfatMemoryDisk")
+                .field("blocks_count", &self.sectors_count())
+                .finish()
+        }
+    }
+
+    impl BlockDevice for VfatMemoryDisk {
+        fn enqueue(&self, bio: SubmittedBio) -> core::prelude::v1::Result<(), BioEnqueueError> {
+            let mut cur_device_ofs = bio.sid_range().start.to_raw() as usize * SECTOR_SIZE;
+            for seg in bio.segments() {
+                let size = match bio.type_() {
+                    BioType::Read => seg
+                        .inner_segment()
+                        .writer()
+                        .write(self.0.reader().skip(cur_device_ofs)),
+                    BioType::Write => self
+                        .0
+                        .writer()
+                        .skip(cur_device_ofs)
+                        .write(&mut seg.inner_segment().reader()),
+                    _ => 0,
+                };
+                cur_device_ofs += size;
+            }
+            bio.complete(BioStatus::Complete);
+            Ok(())
+        }
+
+        fn metadata(&self) -> BlockDeviceMeta {
+            BlockDeviceMeta {
+                max_nr_segments_per_bio: usize::MAX,
+                nr_sectors: self.sectors_count(),
+            }
+        }
+    }
+
+    /// The geometry of the volume of each FAT type.
+    ///
+    /// The numbers of sectors are chosen so that the numbers of clusters fall into the
+    /// ranges of the FAT types, i.e., 4039, 16223 and 68510 clusters, respectively.
+    struct Geometry {
+        num_sectors: u32,
+        reserved_sectors: u16,
+        fat_sectors: u32,
+        root_entries: u16,
+    }
+
+    fn geometry(fat_type: FatType) -> Geometry {
+        match fat_type {
+            FatType::Fat12 => Geometry {
+                num_sectors: 4096,
+                reserved_sectors: 1,
+                fat_sectors: 12,
+                root_entries: 512,
+            },
+            FatType::Fat16 => Geometry {
+                num_sectors: 16384,
+                reserved_sectors: 1,
+                fat_sectors: 64,
+                root_entries: 512,
+            },
+            FatType::Fat32 => Geometry {
+                num_sectors: 69632,
+                reserved_sectors: 32,
+                fat_sectors: 545,
+                root_entries: 0,
+            },
+        }
+    }
+
+    /// Formats an empty volume in memory, like `mkfs.fat -F <bits> -s 1` does.
+    fn format_disk(fat_type: FatType) -> Arc<VfatMemoryDisk> {
+        const NUM_FATS: u32 = 2;
+        const MEDIA: u8 = 0xF8;
+        const FAT32_ROOT_CLUSTER: u32 = 2;
+        const FAT32_FS_INFO_SECTOR: u16 = 1;
+
+        let geo = geometry(fat_type);
+        let segment = FrameAllocOptions::new()
+            .alloc_segment((geo.num_sectors as usize * SECTOR_SIZE).div_ceil(PAGE_SIZE))
+            .unwrap();
+
+        let mut boot = VfatBootSector::new_zeroed();
+        boot.jmp_boot = [0xEB, 0x3C, 0x90];
+        boot.oem_name = *b"MSWIN4.1";
+        boot.bytes_per_sector = SECTOR_SIZE as u16;
+        boot.sectors_per_cluster = (CLUSTER_SIZE / SECTOR_SIZE) as u8;
+        boot.reserved_sectors = geo.reserved_sectors;
+        boot.num_fats = NUM_FATS as u8;
+        boot.root_entries = geo.root_entries;
+        boot.media = MEDIA;
+        if geo.num_sectors <= u16::MAX as u32 {
+            boot.total_sectors_16 = geo.num_sectors as u16;
+        } else {
+            boot.total_sectors_32 = geo.num_sectors;
+        }
+        if fat_type == FatType::Fat32 {
+            boot.fat_size_32 = geo.fat_sectors;
+            boot.root_cluster = FAT32_ROOT_CLUSTER;
+            boot.fs_info_sector = FAT32_FS_INFO_SECTOR;
+        } else {
+            boot.fat_size_16 = geo.fat_sectors as u16;
+        }
+        boot.signature = BOOT_SIGNATURE;
+        segment.write_val(0, &boot).unwrap();
+
+        // The entries of cluster 0 and 1 hold the media type and the end-of-chain mark,
+        // and the root directory of FAT32 takes a single cluster.
+        let fat_head: &[u8] = match fat_type {
+            FatType::Fat12 => &[MEDIA, 0xFF, 0xFF],
+            FatType::Fat16 => &[MEDIA, 0xFF, 0xFF, 0xFF],
+            FatType::Fat32 => &[
+                MEDIA, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F,
+            ],
+        };
+        for copy in 0..NUM_FATS {
+            let fat_start = geo.reserved_sectors as u32 + copy * geo.fat_sectors;
+            segment
+                .write_bytes(fat_start as usize * SECTOR_SIZE, fat_head)
+                .unwrap();
+        }
+
+        if fat_type == FatType::Fat32 {
+            let mut fs_info = VfatFsInfo::new_zeroed();
+            fs_info.lead_signature = 0x41615252;
+            fs_info.struct_signature = 0x61417272;
+            fs_info.free_count = FSINFO_UNKNOWN;
+            fs_info.next_free = FSINFO_UNKNOWN;
+            fs_info.trail_signature = 0xAA550000;
+            segment
+                .write_val(FAT32_FS_INFO_SECTOR as usize * SECTOR_SIZE, &fs_info)
+                .unwrap();
+        }
+
+        Arc::new(VfatMemoryDisk(segment))
+    }
+
+    fn open_fs(disk: &Arc<VfatMemoryDisk>) -> Arc<VfatFS> {
+        let fs = VfatFS::open(disk.clone(), VfatMountOptions::default());
+        assert!(fs.is_ok(), "Fs failed to init: {:?}", fs.unwrap_err());
+        fs.unwrap()
+    }
+
+    /// Runs `f` on a newly formatted volume of each FAT type.
+    fn for_each_fat_type(f: impl Fn(FatType, &Arc<VfatMemoryDisk>, Arc<VfatFS>)) {
+        for fat_type in [FatType::Fat12, FatType::Fat16, FatType::Fat32] {
+            let disk = format_disk(fat_type);
+            let fs = open_fs(&disk);
+            f(fat_type, &disk, fs);
+        }
+    }
+
+    fn root_of(fs: &Arc<VfatFS>) -> Arc<dyn Inode> {
+        fs.root_inode() as Arc<dyn Inode>
+    }
+
+    fn create(parent: &Arc<dyn Inode>, name: &str, type_: InodeType) -> Arc<dyn Inode> {
+        let create_result = parent.create(name, type_, InodeMode::all());
+        assert!(
+            create_result.is_ok(),
+            "Fs failed to create {}: {:?}",
+            name,
+            create_result.unwrap_err()
+        );
+        create_result.unwrap()
+    }
+
+    fn list(dir: &Arc<dyn Inode>) -> Vec<String> {
+        let mut names = Vec::new();
+        dir.readdir_at(0, &mut names).unwrap();
+        names.retain(|name| name != "." && name != "..");
+        names.sort();
+        names
+    }
+
+    fn pattern(len: usize, seed: u8) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+            .collect()
+    }
+
+    fn read_all(inode: &Arc<dyn Inode>) -> Vec<u8> {
+        let mut buf = vec![0u8; inode.size()];
+        let len = inode.read_bytes_at(0, &mut buf).unwrap();
+        assert_eq!(len, buf.len());
+        buf
+    }
+
+    #[ktest]
+    fn new_vfat() {
+        for_each_fat_type(|fat_type, _, fs| {
+            let geo = geometry(fat_type);
+            let sb = fs.super_block();
+            assert_eq!(sb.fat_type, fat_type);
+            assert_eq!(
+                fs.num_free_clusters(),
+                sb.num_clusters - (sb.root_cluster != 0) as u32
+            );
+            assert_eq!(sb.root_dir_size, geo.root_entries as usize * 32);
+            assert!(list(&root_of(&fs)).is_empty());
+        });
+    }
+
+    #[ktest]
+    fn short_names() {
+        for_each_fat_type(|_, _, fs| {
+            let root = root_of(&fs);
+
+            // A valid upper-case 8.3 name is stored as is, without long-name dentries.
+            let exact = create(&root, "README.TXT", InodeType::File);
+            assert_eq!(list(&root), ["README.TXT"]);
+            assert_eq!(root.lookup("readme.txt").unwrap().ino(), exact.ino());
+
+            // Other names get numbered short aliases, which must not collide.
+            let first = create(&root, "long file name.txt", InodeType::File);
+            let second = create(&root, "Long.File.Name.txt", InodeType::File);
+            let third = create(&root, "lower.txt", InodeType::File);
+            assert_eq!(root.lookup("LONGFI~1.TXT").unwrap().ino(), first.ino());
+            assert_eq!(root.lookup("LONGFI~2.TXT").unwrap().ino(), second.ino());
+            assert_eq!(root.lookup("LOWER~1.TXT").unwrap().ino(), third.ino());
+            assert_eq!(
+                list(&root),
+                [
+                    "Long.File.Name.txt",
+                    "README.TXT",
+                    "long file name.txt",
+                    "lower.txt"
+                ]
+            );
+
+            // The alias of a removed file can be reused.
+            root.unlink("long file name.txt").unwrap();
+            let fourth = create(&root, "long file name 2.txt", InodeType::File);
+            assert_eq!(root.lookup("longfi~1.txt").unwrap().ino(), fourth.ino());
+
+            // Names are case-insensitive.
+            assert!(root
+                .create("readme.TXT", InodeType::File, InodeMode::all())
+                .is_err_and(|err| err.error() == Errno::EEXIST));
+        });
+    }
+
+    #[ktest]
+    fn long_names() {
+        for_each_fat_type(|fat_type, disk, fs| {
+            let root = root_of(&fs);
+            let dir = create(&root, "a directory with a long name", InodeType::Dir);
+
+            // The names take up to 20 long-name dentries.
+            let names: Vec<String> = (1..=MAX_NAME_LENGTH)
+                .step_by(23)
+                .map(|len| char::from(b'a' + (len % 26) as u8).to_string().repeat(len))
+                .chain([String::from("Ünïcödé名前.txt")])
+                .collect();
+            for name in names.iter() {
+                create(&dir, name, InodeType::File);
+            }
+            let too_long = "x".repeat(MAX_NAME_LENGTH + 1);
+            assert!(dir
+                .create(&too_long, InodeType::File, InodeMode::all())
+                .is_err_and(|err| err.error() == Errno::ENAMETOOLONG));
+
+            let mut expected = names.clone();
+            expected.sort();
+            assert_eq!(list(&dir), expected);
+
+            // The names are read back from the device after remounting.
+            fs.sync().unwrap();
+            drop((root, dir, fs));
+            let fs = open_fs(disk);
+            let dir = root_of(&fs).lookup("A DIRECTORY WITH A LONG NAME").unwrap();
+            assert_eq!(list(&dir), expected, "Names mismatch on {:?}", fat_type);
+        });
+    }
+
+    #[ktest]
+    fn grow_and_truncate() {
+        for_each_fat_type(|fat_type, disk, fs| {
+            const NUM_CLUSTERS: usize = 40;
+
+            let root = root_of(&fs);
+            let free = fs.num_free_clusters();
+            let file = create(&root, "file", InodeType::File);
+            assert_eq!(fs.num_free_clusters(), free);
+
+            // Grow the chain by writing one cluster at a time.
+            let data = pattern(NUM_CLUSTERS * CLUSTER_SIZE, 7);
+            for chunk in 0..NUM_CLUSTERS {
+                let range = chunk * CLUSTER_SIZE..(chunk + 1) * CLUSTER_SIZE;
+                file.write_bytes_at(range.start, &data[range]).unwrap();
+            }
+            assert_eq!(fs.num_free_clusters(), free - NUM_CLUSTERS as u32);
+            assert_eq!(read_all(&file), data);
+
+            // Truncate to a partial cluster.
+            file.resize(CLUSTER_SIZE + 1).unwrap();
+            assert_eq!(fs.num_free_clusters(), free - 2);
+            assert_eq!(read_all(&file), data[..CLUSTER_SIZE + 1]);
+
+            // Grow with a hole, which reads as zeros.
+            file.resize(4 * CLUSTER_SIZE).unwrap();
+            assert_eq!(fs.num_free_clusters(), free - 4);
+            let content = read_all(&file);
+            assert_eq!(content[..CLUSTER_SIZE + 1], data[..CLUSTER_SIZE + 1]);
+            assert!(content[CLUSTER_SIZE + 1..].iter().all(|&b| b == 0));
+
+            // The chain and the free clusters are consistent after remounting.
+            fs.sync().unwrap();
+            drop((root, file, fs));
+            let fs = open_fs(disk);
+            assert_eq!(fs.num_free_clusters(), free - 4, "Leak on {:?}", fat_type);
+            let file = root_of(&fs).lookup("file").unwrap();
+            assert_eq!(read_all(&file), content);
+
+            file.resize(0).unwrap();
+            assert_eq!(fs.num_free_clusters(), free);
+        });
+    }
+
+    #[ktest]
+    fn unlink_frees_clusters() {
+        for_each_fat_type(|_, _, fs| {
+            let root = root_of(&fs);
+            let free = fs.num_free_clusters();
+            let file = create(&root, "file", InodeType::File);
+            file.write_bytes_at(0, &pattern(8 * CLUSTER_SIZE, 1))
+                .unwrap();
+
+            // The clusters of an opened file are kept until the last reference is dropped.
+            root.unlink("file").unwrap();
+            assert!(root.lookup("file").is_err());
+            assert_eq!(fs.num_free_clusters(), free - 8);
+            assert_eq!(read_all(&file), pattern(8 * CLUSTER_SIZE, 1));
+            drop(file);
+            assert_eq!(fs.num_free_clusters(), free);
+
+            let dir = create(&root, "dir", InodeType::Dir);
+            assert_eq!(fs.num_free_clusters(), free - 1);
+            root.rmdir("dir").unwrap();
+            drop(dir);
+            assert_eq!(fs.num_free_clusters(), free);
+        });
+    }
+
+    #[ktest]
+    fn rename() {
+        for_each_fat_type(|_, disk, fs| {
+            let root = root_of(&fs);
+            let dir = create(&root, "dir", InodeType::Dir);
+            let data = pattern(3 * CLUSTER_SIZE, 3);
+            let file = create(&root, "a.txt", InodeType::File);
+            file.write_bytes_at(0, &data).unwrap();
+
+            // Rename to a long name in the same directory.
+            root.rename("a.txt", &root, "a file with a long name.txt")
+                .unwrap();
+            assert_eq!(list(&root), ["a file with a long name.txt", "dir"]);
+            let renamed = root.lookup("A FILE WITH A LONG NAME.TXT").unwrap();
+            assert_eq!(renamed.ino(), file.ino());
+
+            // Change only the case of the name.
+            root.rename(
+                "a file with a long name.txt",
+                &root,
+                "A File With A Long Name.txt",
+            )
+            .unwrap();
+            assert_eq!(list(&root), ["A File With A Long Name.txt", "dir"]);
+
+            // Move to another directory, replacing an existing file.
+            let victim = create(&dir, "B.TXT", InodeType::File);
+            victim
+                .write_bytes_at(0, &pattern(5 * CLUSTER_SIZE, 5))
+                .unwrap();
+            let free = fs.num_free_clusters();
+            root.rename("A File With A Long Name.txt", &dir, "B.TXT")
+                .unwrap();
+            assert_eq!(list(&root), ["dir"]);
+            assert_eq!(list(&dir), ["B.TXT"]);
+            drop(victim);
+            assert_eq!(fs.num_free_clusters(), free + 5);
+
+            // Move a directory into itself.
+            assert!(root
+                .rename("dir", &dir, "sub")
+                .is_err_and(|err| err.error() == Errno::EINVAL));
+
+            fs.sync().unwrap();
+            drop((root, dir, file, renamed, fs));
+            let fs = open_fs(disk);
+            let file = root_of(&fs).lookup("dir").unwrap().lookup("b.txt").unwrap();
+            assert_eq!(read_all(&file), data);
+        });
+    }
+}
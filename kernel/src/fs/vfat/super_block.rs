@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::Pod;
+
+use super::{
+    dentry::DENTRY_SIZE,
+    fat::{ClusterID, FatType, FAT32_MAX_CLUSTERS, FAT_FIRST_CLUSTER},
+};
+use crate::prelude::*;
+
+pub(super) const BOOT_SIGNATURE: u16 = 0xAA55;
+
+const FSINFO_LEAD_SIGNATURE: u32 = 0x41615252;
+const FSINFO_STRUCT_SIGNATURE: u32 = 0x61417272;
+const FSINFO_TRAIL_SIGNATURE: u32 = 0xAA550000;
+/// The value of an unknown free cluster count or next free cluster in the FSInfo sector.
+pub(super) const FSINFO_UNKNOWN: u32 = 0xFFFFFFFF;
+
+/// The FAT32 flag that only the FAT indicated by the low bits is active.
+const FAT32_MIRRORING_DISABLED: u16 = 0x0080;
+const FAT32_ACTIVE_FAT_MASK: u16 = 0x000F;
+
+/// The boot sector, which contains the BIOS Parameter Block (BPB).
+///
+/// The fields after `total_sectors_32` are only valid for FAT32. FAT12/16 place
+/// a shorter extended BPB at the same offset, which is not used here.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct VfatBootSector {
+    pub jmp_boot: [u8; 3],
+    pub oem_name: [u8; 8],
+    pub bytes_per_sector: u16,
+    pub sectors_per_cluster: u8,
+    pub reserved_sectors: u16,
+    pub num_fats: u8,
+    pub root_entries: u16,
+    pub total_sectors_16: u16,
+    pub media: u8,
+    pub fat_size_16: u16,
+    pub sectors_per_track: u16,
+    pub num_heads: u16,
+    pub hidden_sectors: u32,
+    pub total_sectors_32: u32,
+    pub fat_size_32: u32,
+    pub ext_flags: u16,
+    pub fs_version: u16,
+    pub root_cluster: u32,
+    pub fs_info_sector: u16,
+    pub backup_boot_sector: u16,
+    pub reserved: [u8; 12],
+    pub drive_number: u8,
+    pub reserved1: u8,
+    pub boot_signature: u8,
+    pub volume_id: u32,
+    pub volume_label: [u8; 11],
+    pub fs_type: [u8; 8],
+    pub boot_code: [u8; 420],
+    pub signature: u16,
+}
+
+/// The FSInfo sector of FAT32, which caches the allocation state of clusters.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Pod)]
+pub(super) struct VfatFsInfo {
+    pub lead_signature: u32,
+    pub reserved1: [u8; 480],
+    pub struct_signature: u32,
+    pub free_count: u32,
+    pub next_free: u32,
+    pub reserved2: [u8; 12],
+    pub trail_signature: u32,
+}
+
+impl VfatFsInfo {
+    pub(super) fn is_valid(&self) -> bool {
+        self.lead_signature == FSINFO_LEAD_SIGNATURE
+            && self.struct_signature == FSINFO_STRUCT_SIGNATURE
+            && self.trail_signature == FSINFO_TRAIL_SIGNATURE
+    }
+}
+
+/// The in-memory superblock info.
+///
+/// All offsets and sizes are in bytes.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct VfatSuperBlock {
+    pub fat_type: FatType,
+    pub sector_size: usize,
+    pub cluster_size: usize,
+    /// The number of data clusters, valid cluster ids are in `2..num_clusters + 2`.
+    pub num_clusters: u32,
+    /// The offset of the first FAT.
+    pub fat_start: usize,
+    /// The size of each FAT.
+    pub fat_size: usize,
+    pub num_fats: usize,
+    /// The FAT that is read and written, if mirroring is disabled on FAT32.
+    pub active_fat: Option<usize>,
+    /// The offset of the fixed root directory region of FAT12/16.
+    pub root_dir_start: usize,
+    /// The size of the fixed root directory region of FAT12/16.
+    pub root_dir_size: usize,
+    /// The first cluster of the root directory of FAT32.
+    pub root_cluster: ClusterID,
+    /// The offset of the data region, where cluster 2 starts.
+    pub data_start: usize,
+    /// The sector of the FSInfo structure of FAT32.
+    pub fs_info_sector: Option<u64>,
+}
+
+impl TryFrom<VfatBootSector> for VfatSuperBlock {
+    type Error = crate::error::Error;
+
+    fn try_from(sector: VfatBootSector) -> Result<VfatSuperBlock> {
+        if sector.signature != BOOT_SIGNATURE {
+            return_errno_with_message!(Errno::EINVAL, "invalid boot record signature");
+        }
+
+        let sector_size = sector.bytes_per_sector as usize;
+        if !sector_size.is_power_of_two() || !(512..=4096).contains(&sector_size) {
+            return_errno_with_message!(Errno::EINVAL, "bogus sector size");
+        }
+
+        let sectors_per_cluster = sector.sectors_per_cluster as usize;
+        if !sectors_per_cluster.is_power_of_two() {
+            return_errno_with_message!(Errno::EINVAL, "bogus sectors per cluster");
+        }
+        let cluster_size = sector_size * sectors_per_cluster;
+        if cluster_size > 64 * 1024 {
+            return_errno_with_message!(Errno::EINVAL, "bogus cluster size");
+        }
+
+        if sector.reserved_sectors == 0 {
+            return_errno_with_message!(Errno::EINVAL, "bogus number of reserved sectors");
+        }
+        if sector.num_fats == 0 {
+            return_errno_with_message!(Errno::EINVAL, "bogus number of FAT structure");
+        }
+
+        let num_sectors = if sector.total_sectors_16 != 0 {
+            sector.total_sectors_16 as u64
+        } else {
+            sector.total_sectors_32 as u64
+        };
+        let fat_sectors = if sector.fat_size_16 != 0 {
+            sector.fat_size_16 as u64
+        } else {
+            sector.fat_size_32 as u64
+        };
+        if fat_sectors == 0 {
+            return_errno_with_message!(Errno::EINVAL, "bogus fat length");
+        }
+
+        let root_dir_sectors =
+            (sector.root_entries as u64 * DENTRY_SIZE as u64).div_ceil(sector_size as u64);
+        let fat_start_sector = sector.reserved_sectors as u64;
+        let root_dir_start_sector = fat_start_sector + sector.num_fats as u64 * fat_sectors;
+        let data_start_sector = root_dir_start_sector + root_dir_sectors;
+        if data_start_sector >= num_sectors {
+            return_errno_with_message!(Errno::EINVAL, "bogus data start sector");
+        }
+
+        let num_clusters = ((num_sectors - data_start_sector) / sectors_per_cluster as u64)
+            .min(FAT32_MAX_CLUSTERS as u64) as u32;
+        let fat_type = FatType::from_num_clusters(num_clusters);
+
+        // The type is determined by the number of clusters, and the BPB must agree with it.
+        let is_fat32_bpb = sector.root_entries == 0 && sector.fat_size_16 == 0;
+        if (fat_type == FatType::Fat32) != is_fat32_bpb {
+            return_errno_with_message!(Errno::EINVAL, "bogus FAT type");
+        }
+
+        let fat_size = fat_sectors as usize * sector_size;
+        if fat_size < fat_type.table_size(num_clusters + FAT_FIRST_CLUSTER) {
+            return_errno_with_message!(Errno::EINVAL, "bogus fat length");
+        }
+
+        let mut active_fat = None;
+        let mut fs_info_sector = None;
+        if fat_type == FatType::Fat32 {
+            if sector.ext_flags & FAT32_MIRRORING_DISABLED != 0 {
+                let active = (sector.ext_flags & FAT32_ACTIVE_FAT_MASK) as usize;
+                if active >= sector.num_fats as usize {
+                    return_errno_with_message!(Errno::EINVAL, "bogus active FAT");
+                }
+                active_fat = Some(active);
+            }
+            if sector.root_cluster < FAT_FIRST_CLUSTER
+                || sector.root_cluster >= num_clusters + FAT_FIRST_CLUSTER
+            {
+                return_errno_with_message!(Errno::EINVAL, "bogus root cluster");
+            }
+            if sector.fs_info_sector != 0 && sector.fs_info_sector < sector.reserved_sectors {
+                fs_info_sector = Some(sector.fs_info_sector as u64);
+            }
+        }
+
+        Ok(VfatSuperBlock {
+            fat_type,
+            sector_size,
+            cluster_size,
+            num_clusters,
+            fat_start: fat_start_sector as usize * sector_size,
+            fat_size,
+            num_fats: sector.num_fats as usize,
+            active_fat,
+            root_dir_start: root_dir_start_sector as usize * sector_size,
+            root_dir_size: root_dir_sectors as usize * sector_size,
+            root_cluster: sector.root_cluster,
+            data_start: data_start_sector as usize * sector_size,
+            fs_info_sector,
+        })
+    }
+}
@@ -1,11 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use aster_rights::ReadOp;
+
 use crate::{
     prelude::*,
-    process::{Gid, Uid},
+    process::{credentials::capabilities::CapSet, Credentials, Gid, Uid},
 };
 
+pub mod msg;
+mod namespace;
 pub mod semaphore;
+pub mod shm;
+
+pub use namespace::{init_ipc_ns, IpcNamespace};
 
 #[expect(non_camel_case_types)]
 pub type key_t = i32;
@@ -85,7 +92,54 @@ impl IpcPermission {
         self.mode
     }
 
-    pub(self) fn new_sem_perm(key: key_t, uid: Uid, gid: Gid, mode: u16) -> Self {
+    /// Checks whether the credentials are granted the access mode.
+    ///
+    /// The access mode is a combination of the read (`0o4`) and write (`0o2`) bits.
+    pub fn check_access(&self, credentials: &Credentials<ReadOp>, access_mode: u16) -> Result<()> {
+        let euid = credentials.euid();
+        let granted_mode = if euid == self.uid || euid == self.cuid {
+            self.mode >> 6
+        } else if self.is_in_group(credentials) {
+            self.mode >> 3
+        } else {
+            self.mode
+        };
+
+        if access_mode & !granted_mode & 0o7 != 0
+            && !credentials.effective_capset().contains(CapSet::IPC_OWNER)
+        {
+            return_errno_with_message!(Errno::EACCES, "the IPC object cannot be accessed");
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the credentials belong to the owner or the creator.
+    ///
+    /// Only the owner, the creator, or a privileged process can change or remove an IPC object.
+    pub fn check_owner(&self, credentials: &Credentials<ReadOp>) -> Result<()> {
+        let euid = credentials.euid();
+        if euid != self.uid
+            && euid != self.cuid
+            && !credentials.effective_capset().contains(CapSet::SYS_ADMIN)
+        {
+            return_errno_with_message!(Errno::EPERM, "the IPC object is not owned by the process");
+        }
+
+        Ok(())
+    }
+
+    fn is_in_group(&self, credentials: &Credentials<ReadOp>) -> bool {
+        let egid = credentials.egid();
+        if egid == self.gid || egid == self.cguid {
+            return true;
+        }
+
+        let groups = credentials.groups();
+        groups.contains(&self.gid) || groups.contains(&self.cguid)
+    }
+
+    pub(self) fn new(key: key_t, uid: Uid, gid: Gid, mode: u16) -> Self {
         Self {
             key,
             uid,
@@ -95,8 +149,40 @@ impl IpcPermission {
             mode,
         }
     }
+
+    /// Sets the owner and the permission mode.
+    pub(self) fn set(&mut self, uid: Uid, gid: Gid, mode: u16) {
+        self.uid = uid;
+        self.gid = gid;
+        self.mode = mode;
+    }
+
+    pub(self) fn to_ipc_perm(&self) -> IpcPerm {
+        IpcPerm {
+            key: self.key as u32,
+            uid: self.uid.into(),
+            gid: self.gid.into(),
+            cuid: self.cuid.into(),
+            cgid: self.cguid.into(),
+            mode: self.mode,
+            ..IpcPerm::default()
+        }
+    }
 }
 
-pub(super) fn init() {
-    semaphore::init();
+// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/ipcbuf.h
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Pod)]
+pub struct IpcPerm {
+    key: u32,
+    uid: u32,
+    gid: u32,
+    cuid: u32,
+    cgid: u32,
+    mode: u16,
+    _pad1: u16,
+    seq: u16,
+    _pad2: u16,
+    _unused1: u64,
+    _unused2: u64,
 }
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! System V message queues.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/sysvipc.7.html>
+
+use crate::prelude::*;
+
+mod queue;
+
+pub use queue::{MsgQueue, MsgQueues, MsgSelector, MsqidDs};
+
+// The following constant values are derived from the default values in Linux.
+
+/// Maximum number of message queues.
+pub const MSGMNI: usize = 32000;
+/// Maximum size of a message in bytes.
+pub const MSGMAX: usize = 8192;
+/// Default maximum number of bytes in a message queue.
+pub const MSGMNB: usize = 16384;
+
+bitflags! {
+    /// The flags of `msgrcv`.
+    ///
+    /// `msgsnd` and `msgrcv` also accept [`IpcFlags::IPC_NOWAIT`].
+    ///
+    /// [`IpcFlags::IPC_NOWAIT`]: crate::ipc::IpcFlags::IPC_NOWAIT
+    pub struct MsgFlags: u32 {
+        /// Truncate the message if it is too long.
+        const MSG_NOERROR = 0o10000;
+        /// Receive the first message whose type is not equal to the requested type.
+        const MSG_EXCEPT  = 0o20000;
+        /// Copy the message at the requested position without removing it.
+        const MSG_COPY    = 0o40000;
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+pub enum MsgControlCmd {
+    IPC_RMID = 0,
+    IPC_SET = 1,
+    IPC_STAT = 2,
+}
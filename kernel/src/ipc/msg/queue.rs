@@ -0,0 +1,424 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_rights::ReadOp;
+use id_alloc::IdAlloc;
+use ostd::sync::WaitQueue;
+
+use super::{MsgFlags, MSGMAX, MSGMNB, MSGMNI};
+use crate::{
+    ipc::{key_t, IpcFlags, IpcPerm, IpcPermission},
+    prelude::*,
+    process::{credentials::capabilities::CapSet, Credentials, Pid},
+    time::clocks::RealTimeCoarseClock,
+};
+
+/// A System V message queue.
+pub struct MsgQueue {
+    permission: Mutex<IpcPermission>,
+    inner: Mutex<MsgQueueInner>,
+    /// The senders waiting for free space
+    send_wait_queue: WaitQueue,
+    /// The receivers waiting for messages
+    recv_wait_queue: WaitQueue,
+    /// Whether the queue is removed via `IPC_RMID`
+    is_removed: AtomicBool,
+}
+
+struct MsgQueueInner {
+    messages: VecDeque<Message>,
+    /// The total number of bytes in the messages
+    num_bytes: usize,
+    /// The maximum total number of bytes in the messages
+    ///
+    /// This is also the maximum number of the messages, so that the queue cannot be filled with
+    /// an unlimited number of empty messages.
+    max_bytes: usize,
+    /// The PID of the last `msgsnd`
+    lspid: Pid,
+    /// The PID of the last `msgrcv`
+    lrpid: Pid,
+    /// Last send time
+    stime: u64,
+    /// Last receive time
+    rtime: u64,
+    /// Creation time or last modification via `msgctl`
+    ctime: u64,
+}
+
+struct Message {
+    mtype: i64,
+    bytes: Vec<u8>,
+}
+
+/// The selector that decides which message is received by `msgrcv`.
+#[derive(Debug, Clone, Copy)]
+pub enum MsgSelector {
+    /// The first message in the queue
+    Any,
+    /// The first message of the type
+    Equal(i64),
+    /// The first message of any other type
+    NotEqual(i64),
+    /// The first message of the lowest type that is less than or equal to the type
+    LessEqual(i64),
+}
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/msgbuf.h
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Pod)]
+pub struct MsqidDs {
+    msg_perm: IpcPerm,
+    msg_stime: u64,
+    msg_rtime: u64,
+    msg_ctime: u64,
+    msg_cbytes: u64,
+    msg_qnum: u64,
+    msg_qbytes: u64,
+    msg_lspid: u32,
+    msg_lrpid: u32,
+    _unused4: u64,
+    _unused5: u64,
+}
+
+impl MsgQueue {
+    fn new(key: key_t, mode: u16, credentials: &Credentials<ReadOp>) -> Self {
+        let permission = IpcPermission::new(key, credentials.euid(), credentials.egid(), mode);
+
+        Self {
+            permission: Mutex::new(permission),
+            inner: Mutex::new(MsgQueueInner {
+                messages: VecDeque::new(),
+                num_bytes: 0,
+                max_bytes: MSGMNB,
+                lspid: 0,
+                lrpid: 0,
+                stime: 0,
+                rtime: 0,
+                ctime: now_secs(),
+            }),
+            send_wait_queue: WaitQueue::new(),
+            recv_wait_queue: WaitQueue::new(),
+            is_removed: AtomicBool::new(false),
+        }
+    }
+
+    /// Checks whether the credentials are granted the access mode.
+    pub fn check_access(&self, credentials: &Credentials<ReadOp>, access_mode: u16) -> Result<()> {
+        self.permission
+            .lock()
+            .check_access(credentials, access_mode)
+    }
+
+    /// Checks whether the credentials belong to the owner or the creator.
+    pub fn check_owner(&self, credentials: &Credentials<ReadOp>) -> Result<()> {
+        self.permission.lock().check_owner(credentials)
+    }
+
+    /// Sends a message to the queue.
+    ///
+    /// If the queue is full, this method blocks until there is enough space, unless
+    /// `is_nonblocking` is true, in which case it fails with `EAGAIN`.
+    pub fn send(&self, mtype: i64, bytes: Vec<u8>, is_nonblocking: bool, pid: Pid) -> Result<()> {
+        debug_assert!(mtype > 0);
+        debug_assert!(bytes.len() <= MSGMAX);
+
+        let mut message = Some(Message { mtype, bytes });
+
+        self.send_wait_queue.pause_until(|| {
+            if self.is_removed.load(Ordering::Relaxed) {
+                return Some(Err(Error::with_message(
+                    Errno::EIDRM,
+                    "the message queue is removed",
+                )));
+            }
+
+            let mut inner = self.inner.lock();
+            if !inner.has_space_for(message.as_ref().unwrap()) {
+                if is_nonblocking {
+                    return Some(Err(Error::with_message(
+                        Errno::EAGAIN,
+                        "the message queue is full",
+                    )));
+                }
+                return None;
+            }
+            inner.push(message.take().unwrap(), pid);
+            drop(inner);
+
+            self.recv_wait_queue.wake_all();
+            Some(Ok(()))
+        })?
+    }
+
+    /// Receives a message selected by the selector from the queue.
+    ///
+    /// The message is truncated to `max_len` bytes if [`MsgFlags::MSG_NOERROR`] is specified.
+    /// Otherwise, a message longer than `max_len` bytes is left in the queue and this method fails
+    /// with `E2BIG`.
+    ///
+    /// If there are no matching messages, this method blocks until one arrives, unless
+    /// `is_nonblocking` is true, in which case it fails with `ENOMSG`.
+    pub fn receive(
+        &self,
+        selector: MsgSelector,
+        max_len: usize,
+        flags: MsgFlags,
+        is_nonblocking: bool,
+        pid: Pid,
+    ) -> Result<(i64, Vec<u8>)> {
+        self.recv_wait_queue.pause_until(|| {
+            if self.is_removed.load(Ordering::Relaxed) {
+                return Some(Err(Error::with_message(
+                    Errno::EIDRM,
+                    "the message queue is removed",
+                )));
+            }
+
+            let mut inner = self.inner.lock();
+            let Some(index) = selector.find(&inner.messages) else {
+                if is_nonblocking {
+                    return Some(Err(Error::with_message(
+                        Errno::ENOMSG,
+                        "no messages of the requested type",
+                    )));
+                }
+                return None;
+            };
+            if inner.messages[index].bytes.len() > max_len && !flags.contains(MsgFlags::MSG_NOERROR)
+            {
+                return Some(Err(Error::with_message(
+                    Errno::E2BIG,
+                    "the message is too long",
+                )));
+            }
+            let mut message = inner.remove(index, pid);
+            drop(inner);
+
+            self.send_wait_queue.wake_all();
+            message.bytes.truncate(max_len);
+            Some(Ok((message.mtype, message.bytes)))
+        })?
+    }
+
+    /// Sets the owner, the permission mode, and the maximum size with `IPC_SET`.
+    pub fn set(&self, msqid_ds: &MsqidDs, credentials: &Credentials<ReadOp>) -> Result<()> {
+        let mut permission = self.permission.lock();
+        permission.check_owner(credentials)?;
+
+        let max_bytes = msqid_ds.msg_qbytes as usize;
+        if max_bytes > MSGMNB
+            && !credentials
+                .effective_capset()
+                .contains(CapSet::SYS_RESOURCE)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the maximum size cannot exceed the limit without privileges"
+            );
+        }
+
+        let perm = &msqid_ds.msg_perm;
+        permission.set(perm.uid.into(), perm.gid.into(), perm.mode & 0o777);
+
+        let mut inner = self.inner.lock();
+        inner.max_bytes = max_bytes;
+        inner.ctime = now_secs();
+        drop(inner);
+        drop(permission);
+
+        // The senders may be able to proceed with the new maximum size.
+        self.send_wait_queue.wake_all();
+        Ok(())
+    }
+
+    pub fn msqid_ds(&self) -> MsqidDs {
+        let msg_perm = self.permission.lock().to_ipc_perm();
+        let inner = self.inner.lock();
+
+        MsqidDs {
+            msg_perm,
+            msg_stime: inner.stime,
+            msg_rtime: inner.rtime,
+            msg_ctime: inner.ctime,
+            msg_cbytes: inner.num_bytes as u64,
+            msg_qnum: inner.messages.len() as u64,
+            msg_qbytes: inner.max_bytes as u64,
+            msg_lspid: inner.lspid,
+            msg_lrpid: inner.lrpid,
+            ..MsqidDs::default()
+        }
+    }
+}
+
+impl MsgQueueInner {
+    fn has_space_for(&self, message: &Message) -> bool {
+        self.num_bytes + message.bytes.len() <= self.max_bytes
+            && self.messages.len() < self.max_bytes
+    }
+
+    fn push(&mut self, message: Message, pid: Pid) {
+        self.num_bytes += message.bytes.len();
+        self.messages.push_back(message);
+        self.lspid = pid;
+        self.stime = now_secs();
+    }
+
+    fn remove(&mut self, index: usize, pid: Pid) -> Message {
+        let message = self.messages.remove(index).unwrap();
+        self.num_bytes -= message.bytes.len();
+        self.lrpid = pid;
+        self.rtime = now_secs();
+        message
+    }
+}
+
+impl MsgSelector {
+    /// Creates the selector from the arguments of `msgrcv`.
+    pub fn new(msgtyp: i64, flags: MsgFlags) -> Self {
+        if msgtyp == 0 {
+            Self::Any
+        } else if msgtyp < 0 {
+            Self::LessEqual(msgtyp.saturating_neg())
+        } else if flags.contains(MsgFlags::MSG_EXCEPT) {
+            Self::NotEqual(msgtyp)
+        } else {
+            Self::Equal(msgtyp)
+        }
+    }
+
+    fn find(&self, messages: &VecDeque<Message>) -> Option<usize> {
+        let mut messages = messages.iter().enumerate();
+        let (index, _) = match *self {
+            Self::Any => messages.next(),
+            Self::Equal(mtype) => messages.find(|(_, message)| message.mtype == mtype),
+            Self::NotEqual(mtype) => messages.find(|(_, message)| message.mtype != mtype),
+            Self::LessEqual(mtype) => messages
+                .filter(|(_, message)| message.mtype <= mtype)
+                .min_by_key(|(_, message)| message.mtype),
+        }?;
+        Some(index)
+    }
+}
+
+/// The message queues in an IPC namespace.
+pub struct MsgQueues {
+    inner: Mutex<MsgQueuesInner>,
+}
+
+struct MsgQueuesInner {
+    id_allocator: IdAlloc,
+    /// The queues indexed by their IDs.
+    queues: BTreeMap<i32, Arc<MsgQueue>>,
+    /// The IDs of the queues indexed by their keys.
+    ///
+    /// Queues created with `IPC_PRIVATE` are not in this table.
+    keys: BTreeMap<key_t, i32>,
+}
+
+impl MsgQueues {
+    pub(in crate::ipc) fn new() -> Self {
+        let mut id_allocator = IdAlloc::with_capacity(MSGMNI + 1);
+        // Remove the first index 0
+        id_allocator.alloc();
+
+        Self {
+            inner: Mutex::new(MsgQueuesInner {
+                id_allocator,
+                queues: BTreeMap::new(),
+                keys: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Gets the ID of the queue with the key, and creates the queue if necessary.
+    ///
+    /// This method implements the semantics of `msgget`.
+    pub fn get_or_create(
+        &self,
+        key: key_t,
+        flags: IpcFlags,
+        mode: u16,
+        credentials: &Credentials<ReadOp>,
+    ) -> Result<i32> {
+        const IPC_PRIVATE: key_t = 0;
+
+        let mut inner = self.inner.lock();
+
+        if key != IPC_PRIVATE {
+            if let Some(id) = inner.keys.get(&key) {
+                if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+                    return_errno_with_message!(Errno::EEXIST, "the message queue already exists");
+                }
+
+                // The permission bits in the mode are all treated as the requested access.
+                let access_mode = (mode >> 6) | (mode >> 3) | mode;
+                inner.queues[id].check_access(credentials, access_mode)?;
+                return Ok(*id);
+            }
+
+            if !flags.contains(IpcFlags::IPC_CREAT) {
+                return_errno_with_message!(Errno::ENOENT, "the message queue does not exist");
+            }
+        }
+
+        let id = inner
+            .id_allocator
+            .alloc()
+            .ok_or_else(|| Error::with_message(Errno::ENOSPC, "too many message queues"))?
+            as i32;
+
+        let queue = MsgQueue::new(key, mode, credentials);
+        inner.queues.insert(id, Arc::new(queue));
+        if key != IPC_PRIVATE {
+            inner.keys.insert(key, id);
+        }
+
+        Ok(id)
+    }
+
+    /// Gets the queue with the ID.
+    pub fn get(&self, id: i32) -> Result<Arc<MsgQueue>> {
+        let inner = self.inner.lock();
+        inner
+            .queues
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the message queue does not exist"))
+    }
+
+    /// Removes the queue with the ID if the queue passes the check.
+    ///
+    /// The messages in the queue are discarded, and the blocked senders and receivers fail with
+    /// `EIDRM`.
+    pub fn remove_if<F>(&self, id: i32, check: F) -> Result<()>
+    where
+        F: FnOnce(&MsgQueue) -> Result<()>,
+    {
+        let mut inner = self.inner.lock();
+        let queue = inner.queues.get(&id).ok_or_else(|| {
+            Error::with_message(Errno::EINVAL, "the message queue does not exist")
+        })?;
+        check(queue)?;
+
+        let queue = inner.queues.remove(&id).unwrap();
+        let key = queue.permission.lock().key();
+        if inner.keys.get(&key) == Some(&id) {
+            inner.keys.remove(&key);
+        }
+        inner.id_allocator.free(id as usize);
+        drop(inner);
+
+        queue.is_removed.store(true, Ordering::Relaxed);
+        queue.inner.lock().messages.clear();
+        queue.send_wait_queue.wake_all();
+        queue.recv_wait_queue.wake_all();
+
+        Ok(())
+    }
+}
+
+fn now_secs() -> u64 {
+    RealTimeCoarseClock::get().read_time().as_secs()
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use spin::Once;
+
+use super::{msg::MsgQueues, semaphore::system_v::sem_set::SemaphoreSets, shm::ShmSegments};
+use crate::{
+    fs::{
+        mqueue::MqueueFS,
+        path::{Mount, Path},
+    },
+    prelude::*,
+    process::namespace::alloc_ns_id,
+};
+
+static INIT_IPC_NS: Once<Arc<IpcNamespace>> = Once::new();
+
+/// Returns the initial IPC namespace.
+pub fn init_ipc_ns() -> &'static Arc<IpcNamespace> {
+    INIT_IPC_NS.call_once(IpcNamespace::new)
+}
+
+/// An IPC namespace.
+///
+/// An IPC namespace isolates the System V IPC objects (e.g., message queues, semaphore sets, and
+/// shared memory segments) and the POSIX message queues. The objects created in one namespace are
+/// invisible to the processes in other namespaces.
+pub struct IpcNamespace {
+    id: u64,
+    msg_queues: MsgQueues,
+    sem_sets: SemaphoreSets,
+    shm_segments: ShmSegments,
+    /// The root of the internal mqueue file system, which holds the POSIX message queues
+    mqueue_root: Path,
+}
+
+impl IpcNamespace {
+    /// Creates a new IPC namespace without any IPC objects.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            id: alloc_ns_id(),
+            msg_queues: MsgQueues::new(),
+            sem_sets: SemaphoreSets::new(),
+            shm_segments: ShmSegments::new(),
+            mqueue_root: Path::new_fs_root(Mount::new_root(MqueueFS::new())),
+        })
+    }
+
+    /// Returns the ID of the namespace.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the System V message queues in the namespace.
+    pub fn msg_queues(&self) -> &MsgQueues {
+        &self.msg_queues
+    }
+
+    /// Returns the System V semaphore sets in the namespace.
+    pub fn sem_sets(&self) -> &SemaphoreSets {
+        &self.sem_sets
+    }
+
+    /// Returns the System V shared memory segments in the namespace.
+    pub fn shm_segments(&self) -> &ShmSegments {
+        &self.shm_segments
+    }
+
+    /// Returns the root of the internal mqueue file system in the namespace.
+    pub fn mqueue_root(&self) -> &Path {
+        &self.mqueue_root
+    }
+}
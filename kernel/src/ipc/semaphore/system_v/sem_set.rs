@@ -8,15 +8,14 @@ use core::{
 
 use aster_rights::ReadOp;
 use id_alloc::IdAlloc;
-use ostd::sync::{PreemptDisabled, RwLockReadGuard, RwLockWriteGuard};
-use spin::Once;
+use ostd::sync::{PreemptDisabled, RwLockReadGuard};
 
 use super::{
     sem::{update_pending_alter, wake_const_ops, PendingOp, Status},
     PermissionMode,
 };
 use crate::{
-    ipc::{key_t, semaphore::system_v::sem::Semaphore, IpcPermission},
+    ipc::{key_t, semaphore::system_v::sem::Semaphore, IpcPerm, IpcPermission},
     prelude::*,
     process::{Credentials, Pid},
     time::clocks::RealTimeCoarseClock,
@@ -51,23 +50,6 @@ pub struct SemaphoreSet {
     sem_otime: AtomicU64,
 }
 
-// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/ipcbuf.h
-#[repr(C)]
-#[derive(Debug, Copy, Clone, Default, Pod)]
-pub struct IpcPerm {
-    key: u32,
-    uid: u32,
-    gid: u32,
-    cuid: u32,
-    cgid: u32,
-    mode: u16,
-    _pad1: u16,
-    seq: u16,
-    _pad2: u16,
-    _unused1: u64,
-    _unused2: u64,
-}
-
 // https://github.com/torvalds/linux/blob/master/arch/x86/include/uapi/asm/sembuf.h
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Default, Pod)]
@@ -212,8 +194,7 @@ impl SemaphoreSet {
             sems.push(Semaphore::new(0));
         }
 
-        let permission =
-            IpcPermission::new_sem_perm(key, credentials.euid(), credentials.egid(), mode);
+        let permission = IpcPermission::new(key, credentials.euid(), credentials.egid(), mode);
 
         Ok(Self {
             nsems,
@@ -229,18 +210,8 @@ impl SemaphoreSet {
     }
 
     pub fn semid_ds(&self) -> SemidDs {
-        let ipc_perm = IpcPerm {
-            key: self.permission.key() as u32,
-            uid: self.permission.uid().into(),
-            gid: self.permission.gid().into(),
-            cuid: self.permission.cuid().into(),
-            cgid: self.permission.cguid().into(),
-            mode: self.permission.mode(),
-            ..IpcPerm::default()
-        };
-
         SemidDs {
-            sem_perm: ipc_perm,
+            sem_perm: self.permission.to_ipc_perm(),
             sem_otime: self.sem_otime.load(Ordering::Relaxed),
             sem_ctime: self.sem_ctime.load(Ordering::Relaxed),
             sem_nsems: self.nsems as u64,
@@ -269,97 +240,114 @@ impl Drop for SemaphoreSet {
             }
         }
         pending_const.clear();
-
-        ID_ALLOCATOR
-            .get()
-            .unwrap()
-            .lock()
-            .free(self.permission.key() as usize);
     }
 }
 
-pub fn create_sem_set_with_id(
-    id: key_t,
-    nsems: usize,
-    mode: u16,
-    credentials: Credentials<ReadOp>,
-) -> Result<()> {
-    debug_assert!(nsems <= SEMMSL);
-    debug_assert!(id > 0);
-    if id as usize > SEMMNI {
-        return_errno_with_message!(Errno::ENOENT, "id larger than SEMMNI");
-    }
-
-    ID_ALLOCATOR
-        .get()
-        .unwrap()
-        .lock()
-        .alloc_specific(id as usize)
-        .ok_or(Error::new(Errno::EEXIST))?;
-
-    let mut sem_sets = SEMAPHORE_SETS.write();
-    sem_sets.insert(id, SemaphoreSet::new(id, nsems, mode, credentials)?);
-
-    Ok(())
+/// The semaphore sets in an IPC namespace.
+pub struct SemaphoreSets {
+    id_allocator: SpinLock<IdAlloc>,
+    sets: RwLock<BTreeMap<key_t, SemaphoreSet>>,
 }
 
-/// Checks the semaphore. Return Ok if the semaphore exists and pass the check.
-pub fn check_sem(id: key_t, nsems: Option<usize>, required_perm: PermissionMode) -> Result<()> {
-    debug_assert!(id > 0);
+impl SemaphoreSets {
+    pub(in crate::ipc) fn new() -> Self {
+        let mut id_allocator = IdAlloc::with_capacity(SEMMNI + 1);
+        // Remove the first index 0
+        id_allocator.alloc();
 
-    let sem_sets = SEMAPHORE_SETS.read();
-    let sem_set = sem_sets.get(&id).ok_or(Error::new(Errno::ENOENT))?;
+        Self {
+            id_allocator: SpinLock::new(id_allocator),
+            sets: RwLock::new(BTreeMap::new()),
+        }
+    }
 
-    if let Some(nsems) = nsems {
+    pub fn create_with_id(
+        &self,
+        id: key_t,
+        nsems: usize,
+        mode: u16,
+        credentials: Credentials<ReadOp>,
+    ) -> Result<()> {
         debug_assert!(nsems <= SEMMSL);
-        if nsems > sem_set.nsems() {
-            return_errno!(Errno::EINVAL);
+        debug_assert!(id > 0);
+        if id as usize > SEMMNI {
+            return_errno_with_message!(Errno::ENOENT, "id larger than SEMMNI");
         }
-    }
 
-    if !required_perm.is_empty() {
-        // TODO: Support permission check
-        warn!("Semaphore doesn't support permission check now");
+        self.id_allocator
+            .lock()
+            .alloc_specific(id as usize)
+            .ok_or(Error::new(Errno::EEXIST))?;
+
+        let mut sem_sets = self.sets.write();
+        sem_sets.insert(id, SemaphoreSet::new(id, nsems, mode, credentials)?);
+
+        Ok(())
     }
 
-    Ok(())
-}
+    /// Checks the semaphore. Return Ok if the semaphore exists and pass the check.
+    pub fn check(
+        &self,
+        id: key_t,
+        nsems: Option<usize>,
+        required_perm: PermissionMode,
+    ) -> Result<()> {
+        debug_assert!(id > 0);
+
+        let sem_sets = self.sets.read();
+        let sem_set = sem_sets.get(&id).ok_or(Error::new(Errno::ENOENT))?;
+
+        if let Some(nsems) = nsems {
+            debug_assert!(nsems <= SEMMSL);
+            if nsems > sem_set.nsems() {
+                return_errno!(Errno::EINVAL);
+            }
+        }
 
-pub fn create_sem_set(nsems: usize, mode: u16, credentials: Credentials<ReadOp>) -> Result<key_t> {
-    debug_assert!(nsems <= SEMMSL);
+        if !required_perm.is_empty() {
+            // TODO: Support permission check
+            warn!("Semaphore doesn't support permission check now");
+        }
 
-    let id = ID_ALLOCATOR
-        .get()
-        .unwrap()
-        .lock()
-        .alloc()
-        .ok_or(Error::new(Errno::ENOSPC))? as i32;
+        Ok(())
+    }
 
-    let mut sem_sets = SEMAPHORE_SETS.write();
-    sem_sets.insert(id, SemaphoreSet::new(id, nsems, mode, credentials)?);
+    pub fn create(
+        &self,
+        nsems: usize,
+        mode: u16,
+        credentials: Credentials<ReadOp>,
+    ) -> Result<key_t> {
+        debug_assert!(nsems <= SEMMSL);
 
-    Ok(id)
-}
+        let id = self
+            .id_allocator
+            .lock()
+            .alloc()
+            .ok_or(Error::new(Errno::ENOSPC))? as i32;
 
-pub fn sem_sets<'a>() -> RwLockReadGuard<'a, BTreeMap<key_t, SemaphoreSet>, PreemptDisabled> {
-    SEMAPHORE_SETS.read()
-}
+        let mut sem_sets = self.sets.write();
+        sem_sets.insert(id, SemaphoreSet::new(id, nsems, mode, credentials)?);
 
-pub fn sem_sets_mut<'a>() -> RwLockWriteGuard<'a, BTreeMap<key_t, SemaphoreSet>, PreemptDisabled> {
-    SEMAPHORE_SETS.write()
-}
+        Ok(id)
+    }
 
-static ID_ALLOCATOR: Once<SpinLock<IdAlloc>> = Once::new();
+    /// Removes the semaphore set with the ID if the semaphore set passes the check.
+    pub fn remove_if<F>(&self, id: key_t, check: F) -> Result<()>
+    where
+        F: FnOnce(&SemaphoreSet) -> Result<()>,
+    {
+        let mut sem_sets = self.sets.write();
+        let sem_set = sem_sets.get(&id).ok_or(Error::new(Errno::EINVAL))?;
+        check(sem_set)?;
 
-/// Semaphore sets in system
-static SEMAPHORE_SETS: RwLock<BTreeMap<key_t, SemaphoreSet>> = RwLock::new(BTreeMap::new());
+        sem_sets.remove(&id);
+        self.id_allocator.lock().free(id as usize);
 
-pub(super) fn init() {
-    ID_ALLOCATOR.call_once(|| {
-        let mut id_alloc = IdAlloc::with_capacity(SEMMNI + 1);
-        // Remove the first index 0
-        id_alloc.alloc();
+        Ok(())
+    }
 
-        SpinLock::new(id_alloc)
-    });
+    pub fn read(&self) -> RwLockReadGuard<'_, BTreeMap<key_t, SemaphoreSet>, PreemptDisabled> {
+        self.sets.read()
+    }
 }
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! System V shared memory.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/sysvipc.7.html>
+
+use crate::prelude::*;
+
+mod segment;
+
+pub use segment::{ShmAttachment, ShmSegment, ShmSegments, ShmidDs};
+
+// The following constant values are derived from the default values in Linux.
+
+/// Maximum number of shared memory segments.
+pub const SHMMNI: usize = 4096;
+/// Minimum size of a shared memory segment in bytes.
+pub const SHMMIN: usize = 1;
+/// Maximum size of a shared memory segment in bytes.
+pub const SHMMAX: usize = usize::MAX - (1 << 24);
+/// Alignment of the attach addresses.
+pub const SHMLBA: usize = PAGE_SIZE;
+
+bitflags! {
+    /// The flags of `shmat`.
+    pub struct ShmFlags: u32 {
+        /// Attach the segment for read-only access.
+        const SHM_RDONLY = 0o10000;
+        /// Round the attach address down to the multiple of [`SHMLBA`].
+        const SHM_RND    = 0o20000;
+        /// Replace the existing mappings in the attach range.
+        const SHM_REMAP  = 0o40000;
+        /// Allow the contents of the segment to be executed.
+        const SHM_EXEC   = 0o100000;
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+#[expect(non_camel_case_types)]
+pub enum ShmControlCmd {
+    IPC_RMID = 0,
+    IPC_SET = 1,
+    IPC_STAT = 2,
+
+    SHM_LOCK = 11,
+    SHM_UNLOCK = 12,
+}
@@ -0,0 +1,388 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use align_ext::AlignExt;
+use aster_rights::{ReadOp, Rights};
+use id_alloc::IdAlloc;
+
+use super::{SHMMAX, SHMMIN, SHMMNI};
+use crate::{
+    ipc::{key_t, IpcFlags, IpcNamespace, IpcPerm, IpcPermission},
+    prelude::*,
+    process::{credentials::capabilities::CapSet, Credentials, Pid},
+    time::clocks::RealTimeCoarseClock,
+    vm::vmo::{Vmo, VmoOptions},
+};
+
+/// The mode bit indicating that the segment will be destroyed after the last detach.
+const SHM_DEST: u16 = 0o1000;
+/// The mode bit indicating that the segment is locked in memory.
+const SHM_LOCKED: u16 = 0o2000;
+
+/// A System V shared memory segment.
+#[derive(Debug)]
+pub struct ShmSegment {
+    /// The size of the segment in bytes, which may not be page-aligned.
+    size: usize,
+    /// The VMO that holds the memory pages.
+    vmo: Vmo,
+    /// The number of the attachments (see [`ShmAttachment`]).
+    nattch: AtomicUsize,
+    permission: Mutex<IpcPermission>,
+    /// The PID of the creator
+    cpid: Pid,
+    /// The PID of the last `shmat` or `shmdt`
+    lpid: AtomicU32,
+    /// Last attach time
+    atime: AtomicU64,
+    /// Last detach time
+    dtime: AtomicU64,
+    /// Creation time or last modification via `shmctl`
+    ctime: AtomicU64,
+    /// Whether the segment is removed via `IPC_RMID`
+    is_removed: AtomicBool,
+    /// Whether the segment is locked via `SHM_LOCK`
+    is_locked: AtomicBool,
+}
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/shmbuf.h
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Pod)]
+pub struct ShmidDs {
+    shm_perm: IpcPerm,
+    shm_segsz: u64,
+    shm_atime: u64,
+    shm_dtime: u64,
+    shm_ctime: u64,
+    shm_cpid: u32,
+    shm_lpid: u32,
+    shm_nattch: u64,
+    _unused4: u64,
+    _unused5: u64,
+}
+
+impl ShmSegment {
+    fn new(
+        key: key_t,
+        size: usize,
+        mode: u16,
+        credentials: &Credentials<ReadOp>,
+        pid: Pid,
+    ) -> Result<Self> {
+        debug_assert!((SHMMIN..=SHMMAX).contains(&size));
+
+        let vmo = VmoOptions::<Rights>::new(size.align_up(PAGE_SIZE)).alloc()?;
+        let permission = IpcPermission::new(key, credentials.euid(), credentials.egid(), mode);
+
+        Ok(Self {
+            size,
+            vmo,
+            nattch: AtomicUsize::new(0),
+            permission: Mutex::new(permission),
+            cpid: pid,
+            lpid: AtomicU32::new(0),
+            atime: AtomicU64::new(0),
+            dtime: AtomicU64::new(0),
+            ctime: AtomicU64::new(now_secs()),
+            is_removed: AtomicBool::new(false),
+            is_locked: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns the size of the segment in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the VMO that holds the memory pages.
+    pub fn vmo(&self) -> &Vmo {
+        &self.vmo
+    }
+
+    /// Returns the number of the attachments.
+    pub fn nattch(&self) -> usize {
+        self.nattch.load(Ordering::Acquire)
+    }
+
+    /// Checks whether the credentials are granted the access mode.
+    pub fn check_access(&self, credentials: &Credentials<ReadOp>, access_mode: u16) -> Result<()> {
+        self.permission
+            .lock()
+            .check_access(credentials, access_mode)
+    }
+
+    /// Checks whether the credentials belong to the owner or the creator.
+    pub fn check_owner(&self, credentials: &Credentials<ReadOp>) -> Result<()> {
+        self.permission.lock().check_owner(credentials)
+    }
+
+    /// Records that the segment is attached by the process.
+    pub fn on_attach(&self, pid: Pid) {
+        self.lpid.store(pid, Ordering::Relaxed);
+        self.atime.store(now_secs(), Ordering::Relaxed);
+    }
+
+    /// Records that the segment is detached by the process.
+    pub fn on_detach(&self, pid: Pid) {
+        self.lpid.store(pid, Ordering::Relaxed);
+        self.dtime.store(now_secs(), Ordering::Relaxed);
+    }
+
+    /// Sets the owner and the permission mode with `IPC_SET`.
+    pub fn set(&self, shmid_ds: &ShmidDs, credentials: &Credentials<ReadOp>) -> Result<()> {
+        let mut permission = self.permission.lock();
+        permission.check_owner(credentials)?;
+
+        let perm = &shmid_ds.shm_perm;
+        permission.set(perm.uid.into(), perm.gid.into(), perm.mode & 0o777);
+        self.ctime.store(now_secs(), Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Locks or unlocks the segment with `SHM_LOCK` or `SHM_UNLOCK`.
+    ///
+    /// The pages are never swapped out, so this only changes the mode reported to user space.
+    pub fn set_locked(&self, is_locked: bool, credentials: &Credentials<ReadOp>) -> Result<()> {
+        if !credentials.euid().is_root()
+            && !credentials.effective_capset().contains(CapSet::IPC_LOCK)
+        {
+            self.check_owner(credentials)?;
+        }
+
+        self.is_locked.store(is_locked, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn shmid_ds(&self) -> ShmidDs {
+        let mut shm_perm = self.permission.lock().to_ipc_perm();
+        if self.is_removed.load(Ordering::Relaxed) {
+            shm_perm.key = 0;
+            shm_perm.mode |= SHM_DEST;
+        }
+        if self.is_locked.load(Ordering::Relaxed) {
+            shm_perm.mode |= SHM_LOCKED;
+        }
+
+        ShmidDs {
+            shm_perm,
+            shm_segsz: self.size as u64,
+            shm_atime: self.atime.load(Ordering::Relaxed),
+            shm_dtime: self.dtime.load(Ordering::Relaxed),
+            shm_ctime: self.ctime.load(Ordering::Relaxed),
+            shm_cpid: self.cpid,
+            shm_lpid: self.lpid.load(Ordering::Relaxed),
+            shm_nattch: self.nattch() as u64,
+            ..ShmidDs::default()
+        }
+    }
+}
+
+/// The shared memory segments in an IPC namespace.
+pub struct ShmSegments {
+    inner: Mutex<ShmSegmentsInner>,
+}
+
+struct ShmSegmentsInner {
+    id_allocator: IdAlloc,
+    /// The segments indexed by their IDs.
+    segments: BTreeMap<i32, Arc<ShmSegment>>,
+    /// The IDs of the segments indexed by their keys.
+    ///
+    /// Segments created with `IPC_PRIVATE` and removed segments are not in this table.
+    keys: BTreeMap<key_t, i32>,
+    /// The IDs of the segments that are removed but still attached.
+    removed: Vec<i32>,
+}
+
+impl ShmSegments {
+    pub(in crate::ipc) fn new() -> Self {
+        let mut id_allocator = IdAlloc::with_capacity(SHMMNI + 1);
+        // Remove the first index 0
+        id_allocator.alloc();
+
+        Self {
+            inner: Mutex::new(ShmSegmentsInner {
+                id_allocator,
+                segments: BTreeMap::new(),
+                keys: BTreeMap::new(),
+                removed: Vec::new(),
+            }),
+        }
+    }
+
+    /// Gets the ID of the segment with the key, and creates the segment if necessary.
+    ///
+    /// This method implements the semantics of `shmget`.
+    pub fn get_or_create(
+        &self,
+        key: key_t,
+        size: usize,
+        flags: IpcFlags,
+        mode: u16,
+        credentials: &Credentials<ReadOp>,
+        pid: Pid,
+    ) -> Result<i32> {
+        const IPC_PRIVATE: key_t = 0;
+
+        let mut inner = self.inner.lock();
+
+        if key != IPC_PRIVATE {
+            if let Some(id) = inner.keys.get(&key) {
+                if flags.contains(IpcFlags::IPC_CREAT | IpcFlags::IPC_EXCL) {
+                    return_errno_with_message!(Errno::EEXIST, "the segment already exists");
+                }
+
+                // The permission bits in the mode are all treated as the requested access.
+                let access_mode = (mode >> 6) | (mode >> 3) | mode;
+                let segment = &inner.segments[id];
+                segment.check_access(credentials, access_mode)?;
+                if size > segment.size() {
+                    return_errno_with_message!(Errno::EINVAL, "the segment is too small");
+                }
+                return Ok(*id);
+            }
+
+            if !flags.contains(IpcFlags::IPC_CREAT) {
+                return_errno_with_message!(Errno::ENOENT, "the segment does not exist");
+            }
+        }
+
+        if !(SHMMIN..=SHMMAX).contains(&size) {
+            return_errno_with_message!(Errno::EINVAL, "the segment size is invalid");
+        }
+
+        let id = inner
+            .id_allocator
+            .alloc()
+            .ok_or_else(|| Error::with_message(Errno::ENOSPC, "too many segments"))?
+            as i32;
+        let segment = match ShmSegment::new(key, size, mode, credentials, pid) {
+            Ok(segment) => segment,
+            Err(err) => {
+                inner.id_allocator.free(id as usize);
+                return Err(err);
+            }
+        };
+
+        inner.segments.insert(id, Arc::new(segment));
+        if key != IPC_PRIVATE {
+            inner.keys.insert(key, id);
+        }
+
+        Ok(id)
+    }
+
+    /// Gets the segment with the ID.
+    pub fn get(&self, id: i32) -> Result<Arc<ShmSegment>> {
+        let inner = self.inner.lock();
+        inner
+            .segments
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the segment does not exist"))
+    }
+
+    /// Removes the segment with the ID if the segment passes the check.
+    ///
+    /// The key of the segment can be reused immediately, but the segment is destroyed only after
+    /// it is no longer attached.
+    pub fn remove_if<F>(&self, id: i32, check: F) -> Result<()>
+    where
+        F: FnOnce(&ShmSegment) -> Result<()>,
+    {
+        let mut inner = self.inner.lock();
+        let segment = inner
+            .segments
+            .get(&id)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the segment does not exist"))?;
+        check(segment)?;
+
+        if segment.is_removed.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let key = segment.permission.lock().key();
+        if inner.keys.get(&key) == Some(&id) {
+            inner.keys.remove(&key);
+        }
+
+        inner.removed.push(id);
+        inner.reap_removed();
+
+        Ok(())
+    }
+}
+
+impl ShmSegmentsInner {
+    /// Destroys the removed segments that are no longer attached.
+    fn reap_removed(&mut self) {
+        let Self {
+            id_allocator,
+            segments,
+            removed,
+            ..
+        } = self;
+
+        removed.retain(|id| {
+            if segments[id].nattch() > 0 {
+                return true;
+            }
+            segments.remove(id);
+            id_allocator.free(*id as usize);
+            false
+        });
+    }
+}
+
+/// An attachment of a shared memory segment.
+///
+/// Each mapping of the segment holds an attachment. Like Linux, the mappings that are split from
+/// or forked from the mapping also hold their own attachments, so they are counted in the number
+/// of the attachments. A removed segment is destroyed once its last attachment is dropped, e.g.,
+/// by `shmdt`, `munmap`, or process exits.
+pub struct ShmAttachment {
+    segment: Arc<ShmSegment>,
+    ipc_ns: Arc<IpcNamespace>,
+}
+
+impl ShmAttachment {
+    /// Attaches the segment in the IPC namespace.
+    pub fn new(segment: Arc<ShmSegment>, ipc_ns: Arc<IpcNamespace>) -> Self {
+        segment.nattch.fetch_add(1, Ordering::Relaxed);
+        Self { segment, ipc_ns }
+    }
+
+    /// Returns the attached segment.
+    pub fn segment(&self) -> &Arc<ShmSegment> {
+        &self.segment
+    }
+}
+
+impl Clone for ShmAttachment {
+    fn clone(&self) -> Self {
+        Self::new(self.segment.clone(), self.ipc_ns.clone())
+    }
+}
+
+impl Drop for ShmAttachment {
+    fn drop(&mut self) {
+        if self.segment.nattch.fetch_sub(1, Ordering::Release) == 1 {
+            // The segment is checked with the lock held, so it cannot be missed by a concurrent
+            // `IPC_RMID`.
+            self.ipc_ns.shm_segments().inner.lock().reap_removed();
+        }
+    }
+}
+
+impl Debug for ShmAttachment {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ShmAttachment")
+            .field("size", &self.segment.size())
+            .finish_non_exhaustive()
+    }
+}
+
+fn now_secs() -> u64 {
+    RealTimeCoarseClock::get().read_time().as_secs()
+}
@@ -107,7 +107,6 @@ pub fn init() {
     sched::init();
     fs::rootfs::init(boot_info().initramfs.expect("No initramfs found!")).unwrap();
     device::init().unwrap();
-    syscall::init();
     #[cfg(any(target_arch = "x86_64", target_arch = "riscv64"))]
     vdso::init();
     process::init();
@@ -141,7 +140,6 @@ fn init_thread() {
     #[cfg(target_arch = "x86_64")]
     net::lazy_init();
     fs::lazy_init();
-    ipc::init();
     // driver::pci::virtio::block::block_device_test();
     let thread = ThreadOptions::new(|| {
         println!("[kernel] Hello world from kernel!");
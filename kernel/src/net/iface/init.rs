@@ -57,7 +57,7 @@ pub fn init() {
 fn new_virtio() -> Option<Arc<Iface>> {
     use aster_bigtcp::{
         iface::EtherIface,
-        wire::{EthernetAddress, Ipv4Address, Ipv4Cidr},
+        wire::{EthernetAddress, Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr},
     };
     use aster_network::AnyNetworkDevice;
     use aster_virtio::device::network::DEVICE_NAME;
@@ -65,6 +65,10 @@ fn new_virtio() -> Option<Arc<Iface>> {
     const VIRTIO_ADDRESS: Ipv4Address = Ipv4Address::new(10, 0, 2, 15);
     const VIRTIO_ADDRESS_PREFIX_LEN: u8 = 24; // mask: 255.255.255.0
     const VIRTIO_GATEWAY: Ipv4Address = Ipv4Address::new(10, 0, 2, 2);
+    // The IPv6 site-local prefix used by QEMU's user mode networking.
+    const VIRTIO_IPV6_ADDRESS: Ipv6Address = Ipv6Address::new(0xfec0, 0, 0, 0, 0, 0, 0, 0x15);
+    const VIRTIO_IPV6_ADDRESS_PREFIX_LEN: u8 = 64;
+    const VIRTIO_IPV6_GATEWAY: Ipv6Address = Ipv6Address::new(0xfec0, 0, 0, 0, 0, 0, 0, 0x2);
 
     let virtio_net = aster_network::get_device(DEVICE_NAME)?;
 
@@ -97,6 +101,8 @@ fn new_virtio() -> Option<Arc<Iface>> {
         EthernetAddress(ether_addr),
         Ipv4Cidr::new(VIRTIO_ADDRESS, VIRTIO_ADDRESS_PREFIX_LEN),
         VIRTIO_GATEWAY,
+        Ipv6Cidr::new(VIRTIO_IPV6_ADDRESS, VIRTIO_IPV6_ADDRESS_PREFIX_LEN),
+        VIRTIO_IPV6_GATEWAY,
         "eth0".to_owned(),
         PollScheduler::new(),
         flags,
@@ -107,11 +113,13 @@ fn new_loopback() -> Arc<Iface> {
     use aster_bigtcp::{
         device::{Loopback, Medium},
         iface::IpIface,
-        wire::{Ipv4Address, Ipv4Cidr},
+        wire::{Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr},
     };
 
     const LOOPBACK_ADDRESS: Ipv4Address = Ipv4Address::new(127, 0, 0, 1);
     const LOOPBACK_ADDRESS_PREFIX_LEN: u8 = 8; // mask: 255.0.0.0
+    const LOOPBACK_IPV6_ADDRESS: Ipv6Address = Ipv6Address::LOCALHOST;
+    const LOOPBACK_IPV6_ADDRESS_PREFIX_LEN: u8 = 128;
 
     struct Wrapper(Mutex<Loopback>);
 
@@ -137,6 +145,7 @@ fn new_loopback() -> Arc<Iface> {
     IpIface::new(
         Wrapper(Mutex::new(Loopback::new(Medium::Ip))),
         Ipv4Cidr::new(LOOPBACK_ADDRESS, LOOPBACK_ADDRESS_PREFIX_LEN),
+        Ipv6Cidr::new(LOOPBACK_IPV6_ADDRESS, LOOPBACK_IPV6_ADDRESS_PREFIX_LEN),
         "lo".to_owned(),
         PollScheduler::new(),
         InterfaceType::LOOPBACK,
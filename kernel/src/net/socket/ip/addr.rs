@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use aster_bigtcp::wire::{IpAddress, IpEndpoint, Ipv4Address};
+use aster_bigtcp::wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv6Address};
 
 use crate::{net::socket::util::SocketAddr, prelude::*, return_errno_with_message};
 
@@ -10,6 +10,7 @@ impl TryFrom<SocketAddr> for IpEndpoint {
     fn try_from(value: SocketAddr) -> Result<Self> {
         match value {
             SocketAddr::IPv4(addr, port) => Ok(IpEndpoint::new(addr.into(), port)),
+            SocketAddr::IPv6(addr, port) => Ok(IpEndpoint::new(addr.into(), port)),
             _ => return_errno_with_message!(
                 Errno::EAFNOSUPPORT,
                 "the address is in an unsupported address family"
@@ -23,15 +24,75 @@ impl From<IpEndpoint> for SocketAddr {
         let port = endpoint.port;
         match endpoint.addr {
             IpAddress::Ipv4(addr) => SocketAddr::IPv4(addr, port),
-            // TODO: support IPv6
+            IpAddress::Ipv6(addr) => SocketAddr::IPv6(addr, port),
         }
     }
 }
 
-/// A local endpoint, which indicates that the local endpoint is unspecified.
-///
-/// According to the Linux man pages and the Linux implementation, `getsockname()` will _not_ fail
-/// even if the socket is unbound. Instead, it will return an unspecified socket address. This
-/// unspecified endpoint helps with that.
-pub(super) const UNSPECIFIED_LOCAL_ENDPOINT: IpEndpoint =
-    IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0);
+/// The address family of an IP socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    /// `AF_INET` sockets, which only use IPv4 addresses.
+    V4,
+    /// `AF_INET6` sockets, which use IPv6 addresses and, unless `IPV6_V6ONLY` is set,
+    /// IPv4-mapped IPv6 addresses to communicate with IPv4 peers.
+    V6,
+}
+
+impl IpFamily {
+    /// Converts a socket address from the user space to an endpoint.
+    ///
+    /// For IPv6 sockets, IPv4-mapped IPv6 addresses (i.e., `::ffff:a.b.c.d`) are converted to IPv4
+    /// endpoints, so that the rest of the network stack only sees IPv4 addresses in that case.
+    pub(super) fn endpoint_from_user(
+        self,
+        socket_addr: SocketAddr,
+        is_v6only: bool,
+    ) -> Result<IpEndpoint> {
+        match (self, socket_addr) {
+            (Self::V4, SocketAddr::IPv4(addr, port)) => Ok(IpEndpoint::new(addr.into(), port)),
+            (Self::V4, SocketAddr::IPv6(..)) => return_errno_with_message!(
+                Errno::EAFNOSUPPORT,
+                "IPv6 addresses cannot be used on IPv4 sockets"
+            ),
+            (Self::V6, SocketAddr::IPv6(addr, port)) => match addr.to_ipv4_mapped() {
+                Some(_) if is_v6only => return_errno_with_message!(
+                    Errno::EINVAL,
+                    "IPv4-mapped addresses cannot be used on IPv6-only sockets"
+                ),
+                Some(ipv4_addr) => Ok(IpEndpoint::new(ipv4_addr.into(), port)),
+                None => Ok(IpEndpoint::new(addr.into(), port)),
+            },
+            (Self::V6, SocketAddr::IPv4(..)) => return_errno_with_message!(
+                Errno::EINVAL,
+                "IPv4 addresses cannot be used on IPv6 sockets"
+            ),
+            (_, socket_addr) => IpEndpoint::try_from(socket_addr),
+        }
+    }
+
+    /// Converts an endpoint to a socket address for the user space.
+    ///
+    /// This is the reverse of [`Self::endpoint_from_user`]: IPv4 endpoints are reported as
+    /// IPv4-mapped IPv6 addresses on IPv6 sockets.
+    pub(super) fn endpoint_to_user(self, endpoint: IpEndpoint) -> SocketAddr {
+        match (self, endpoint.addr) {
+            (Self::V6, IpAddress::Ipv4(ipv4_addr)) => {
+                SocketAddr::IPv6(ipv4_addr.to_ipv6_mapped(), endpoint.port)
+            }
+            _ => endpoint.into(),
+        }
+    }
+
+    /// Returns a local endpoint, which indicates that the local endpoint is unspecified.
+    ///
+    /// According to the Linux man pages and the Linux implementation, `getsockname()` will _not_
+    /// fail even if the socket is unbound. Instead, it will return an unspecified socket address.
+    /// This unspecified endpoint helps with that.
+    pub(super) const fn unspecified_local_endpoint(self) -> IpEndpoint {
+        match self {
+            Self::V4 => IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0),
+            Self::V6 => IpEndpoint::new(IpAddress::Ipv6(Ipv6Address::UNSPECIFIED), 0),
+        }
+    }
+}
@@ -11,34 +11,35 @@ use crate::{
     prelude::*,
 };
 
+/// Gets the iface to bind to the local address.
+///
+/// The unspecified address (i.e., `0.0.0.0` or `::`) means any iface, so the default iface is
+/// used. A listening socket bound to the unspecified address will then listen on all ifaces.
+//
+// FIXME: Datagram sockets bound to the unspecified address should also receive packets from all
+// ifaces, but they only receive packets from the default iface.
 pub(super) fn get_iface_to_bind(ip_addr: &IpAddress) -> Option<Arc<Iface>> {
-    let IpAddress::Ipv4(ipv4_addr) = ip_addr;
+    if ip_addr.is_unspecified() {
+        return Some(default_iface());
+    }
+
     iter_all_ifaces()
-        .find(|iface| {
-            if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-                iface_ipv4_addr == *ipv4_addr
-            } else {
-                false
-            }
-        })
+        .find(|iface| iface.has_ip_addr(ip_addr))
         .map(Clone::clone)
 }
 
 /// Get a suitable iface to deal with sendto/connect request if the socket is not bound to an iface.
 /// If the remote address is the same as that of some iface, we will use the iface.
 /// Otherwise, we will use a default interface.
-fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
-    let IpAddress::Ipv4(remote_ipv4_addr) = remote_ip_addr;
-    if let Some(iface) = iter_all_ifaces().find(|iface| {
-        if let Some(iface_ipv4_addr) = iface.ipv4_addr() {
-            iface_ipv4_addr == *remote_ipv4_addr
-        } else {
-            false
-        }
-    }) {
+pub(super) fn get_ephemeral_iface(remote_ip_addr: &IpAddress) -> Arc<Iface> {
+    if let Some(iface) = iter_all_ifaces().find(|iface| iface.has_ip_addr(remote_ip_addr)) {
         return iface.clone();
     }
 
+    default_iface()
+}
+
+fn default_iface() -> Arc<Iface> {
     // FIXME: Instead of hardcoding the rules here, we should choose the
     // default interface according to the routing table.
     if let Some(virtio_iface) = virtio_iface() {
@@ -61,7 +62,7 @@ pub(super) fn bind_port(endpoint: &IpEndpoint, can_reuse: bool) -> Result<BoundP
 
     let bind_port_config = BindPortConfig::new(endpoint.port, can_reuse);
 
-    Ok(iface.bind(bind_port_config)?)
+    Ok(iface.bind(endpoint.addr, bind_port_config)?)
 }
 
 impl From<BindError> for Error {
@@ -77,8 +78,38 @@ impl From<BindError> for Error {
     }
 }
 
-pub(super) fn get_ephemeral_endpoint(remote_endpoint: &IpEndpoint) -> IpEndpoint {
+pub(super) fn get_ephemeral_endpoint(remote_endpoint: &IpEndpoint) -> Result<IpEndpoint> {
     let iface = get_ephemeral_iface(&remote_endpoint.addr);
-    let ip_addr = iface.ipv4_addr().unwrap();
-    IpEndpoint::new(IpAddress::Ipv4(ip_addr), 0)
+
+    let ip_addr = match remote_endpoint.addr {
+        IpAddress::Ipv4(_) => iface.ipv4_addr().map(IpAddress::Ipv4),
+        IpAddress::Ipv6(_) => iface.ipv6_addr().map(IpAddress::Ipv6),
+    };
+    let Some(ip_addr) = ip_addr else {
+        return_errno_with_message!(
+            Errno::ENETUNREACH,
+            "no local address is available to reach the remote address"
+        );
+    };
+
+    Ok(IpEndpoint::new(ip_addr, 0))
+}
+
+/// Checks whether the local endpoint can be used to communicate with the remote endpoint.
+///
+/// A socket bound to an IPv4 address cannot talk to IPv6 peers, and vice versa. A socket bound to
+/// the unspecified address can talk to both, since the disallowed peers (e.g., IPv4 peers of
+/// IPv6-only sockets) have already been rejected when converting the addresses from the user
+/// space (see [`IpFamily::endpoint_from_user`]).
+///
+/// [`IpFamily::endpoint_from_user`]: super::addr::IpFamily::endpoint_from_user
+pub(super) fn check_remote_family(local: &IpEndpoint, remote: &IpEndpoint) -> Result<()> {
+    if !local.addr.is_unspecified() && local.addr.version() != remote.addr.version() {
+        return_errno_with_message!(
+            Errno::ENETUNREACH,
+            "the remote address is in a different IP version from the local address"
+        );
+    }
+
+    Ok(())
 }
@@ -6,7 +6,11 @@ use aster_bigtcp::wire::IpEndpoint;
 use bound::BoundDatagram;
 use unbound::{BindOptions, UnboundDatagram};
 
-use super::addr::UNSPECIFIED_LOCAL_ENDPOINT;
+use super::{
+    addr::IpFamily,
+    common::check_remote_family,
+    options::{Ipv6OptionSet, SetIpv6LevelOption},
+};
 use crate::{
     events::IoEvents,
     match_sock_option_mut,
@@ -32,13 +36,15 @@ mod unbound;
 #[derive(Debug, Clone)]
 struct OptionSet {
     socket: SocketOptionSet,
+    ipv6: Ipv6OptionSet,
     // TODO: UDP option set
 }
 
 impl OptionSet {
     fn new() -> Self {
         let socket = SocketOptionSet::new_udp();
-        OptionSet { socket }
+        let ipv6 = Ipv6OptionSet::new();
+        OptionSet { socket, ipv6 }
     }
 }
 
@@ -47,31 +53,40 @@ pub struct DatagramSocket {
     inner: RwMutex<Inner<UnboundDatagram, BoundDatagram>>,
     options: RwLock<OptionSet>,
 
+    family: IpFamily,
     is_nonblocking: AtomicBool,
     pollee: Pollee,
 }
 
 impl DatagramSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    pub fn new(family: IpFamily, is_nonblocking: bool) -> Arc<Self> {
         let unbound_datagram = UnboundDatagram::new();
         Arc::new(Self {
             inner: RwMutex::new(Inner::Unbound(unbound_datagram)),
             options: RwLock::new(OptionSet::new()),
+            family,
             is_nonblocking: AtomicBool::new(is_nonblocking),
             pollee: Pollee::new(),
         })
     }
 
+    fn endpoint_from_user(&self, socket_addr: SocketAddr) -> Result<IpEndpoint> {
+        let is_v6only = self.options.read().ipv6.v6only();
+        self.family.endpoint_from_user(socket_addr, is_v6only)
+    }
+
     fn try_recv(
         &self,
         writer: &mut dyn MultiWrite,
         flags: SendRecvFlags,
     ) -> Result<(usize, SocketAddr)> {
-        let recv_bytes = self
-            .inner
-            .read()
-            .try_recv(writer, flags)
-            .map(|(recv_bytes, remote_endpoint)| (recv_bytes, remote_endpoint.into()))?;
+        let recv_bytes =
+            self.inner
+                .read()
+                .try_recv(writer, flags)
+                .map(|(recv_bytes, remote_endpoint)| {
+                    (recv_bytes, self.family.endpoint_to_user(remote_endpoint))
+                })?;
         self.pollee.invalidate();
 
         Ok(recv_bytes)
@@ -98,6 +113,7 @@ impl DatagramSocket {
                     .bind_ephemeral(remote_endpoint, &self.pollee)
             },
             |bound_datagram, remote_endpoint| {
+                check_remote_family(&bound_datagram.local_endpoint(), remote_endpoint)?;
                 let sent_bytes = bound_datagram.try_send(reader, remote_endpoint, flags)?;
                 let iface_to_poll = bound_datagram.iface().clone();
                 Ok((sent_bytes, iface_to_poll))
@@ -130,7 +146,7 @@ impl SocketPrivate for DatagramSocket {
 
 impl Socket for DatagramSocket {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let endpoint = self.endpoint_from_user(socket_addr)?;
         let can_reuse = self.options.read().socket.reuse_addr();
 
         self.inner
@@ -139,9 +155,13 @@ impl Socket for DatagramSocket {
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let endpoint = self.endpoint_from_user(socket_addr)?;
 
-        self.inner.write().connect(&endpoint, &self.pollee)
+        let mut inner = self.inner.write();
+        if let Some(local_endpoint) = inner.addr() {
+            check_remote_family(&local_endpoint, &endpoint)?;
+        }
+        inner.connect(&endpoint, &self.pollee)
     }
 
     fn addr(&self) -> Result<SocketAddr> {
@@ -149,9 +169,9 @@ impl Socket for DatagramSocket {
             .inner
             .read()
             .addr()
-            .unwrap_or(UNSPECIFIED_LOCAL_ENDPOINT);
+            .unwrap_or(self.family.unspecified_local_endpoint());
 
-        Ok(endpoint.into())
+        Ok(self.family.endpoint_to_user(endpoint))
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
@@ -160,7 +180,7 @@ impl Socket for DatagramSocket {
                 Error::with_message(Errno::ENOTCONN, "the socket is not connected")
             })?;
 
-        Ok(endpoint.into())
+        Ok(self.family.endpoint_to_user(endpoint))
     }
 
     fn sendmsg(
@@ -180,7 +200,7 @@ impl Socket for DatagramSocket {
         } = message_header;
 
         let endpoint = match addr {
-            Some(addr) => Some(addr.try_into()?),
+            Some(addr) => Some(self.endpoint_from_user(addr)?),
             None => None,
         };
 
@@ -224,14 +244,35 @@ impl Socket for DatagramSocket {
         });
 
         let inner = self.inner.read();
-        self.options.read().socket.get_option(option, &*inner)
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option, &*inner) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IPv6-level options
+        if self.family == IpFamily::V6 {
+            return options.ipv6.get_option(option);
+        }
+
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
     }
 
     fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
         let inner = self.inner.read();
         let mut options = self.options.write();
 
-        match options.socket.set_option(option, &*inner) {
+        let result = match options.socket.set_option(option, &*inner) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT && self.family == IpFamily::V6 => {
+                // Deal with IPv6-level options
+                options.ipv6.set_option(option, &*inner)
+            }
+            result => result,
+        };
+
+        match result {
             Err(e) => Err(e),
             Ok(need_iface_poll) => {
                 let iface_to_poll = need_iface_poll
@@ -269,3 +310,9 @@ impl SetSocketLevelOption for Inner<UnboundDatagram, BoundDatagram> {
         bound.bound_port().set_can_reuse(reuse_addr);
     }
 }
+
+impl SetIpv6LevelOption for Inner<UnboundDatagram, BoundDatagram> {
+    fn is_bound(&self) -> bool {
+        matches!(self, Inner::Bound(_))
+    }
+}
@@ -4,9 +4,12 @@ mod addr;
 mod common;
 mod datagram;
 pub mod options;
+mod raw;
 mod stream;
 
+pub use addr::IpFamily;
 pub(in crate::net) use datagram::observer::DatagramObserver;
 pub use datagram::DatagramSocket;
+pub use raw::RawSocket;
 pub(in crate::net) use stream::observer::StreamObserver;
 pub use stream::{options as stream_options, StreamSocket};
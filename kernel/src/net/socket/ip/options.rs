@@ -31,6 +31,14 @@ impl IpOptionSet {
         }
     }
 
+    pub(super) const fn new_raw(hdrincl: bool) -> Self {
+        Self {
+            tos: 0,
+            ttl: IpTtl(None),
+            hdrincl,
+        }
+    }
+
     pub(super) fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
         match_sock_option_mut!(option, {
             ip_tos: Tos => {
@@ -86,6 +94,60 @@ impl_socket_options!(
     pub struct Hdrincl(bool);
 );
 
+/// IPv6-level socket options.
+#[derive(Debug, Clone, Copy, CopyGetters, Setters)]
+#[get_copy = "pub"]
+#[set = "pub"]
+pub(super) struct Ipv6OptionSet {
+    v6only: bool,
+}
+
+impl Ipv6OptionSet {
+    pub(super) const fn new() -> Self {
+        Self { v6only: false }
+    }
+
+    pub(super) fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            ipv6_v6only: V6Only => {
+                let v6only = self.v6only();
+                ipv6_v6only.set(v6only);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option is unknown")
+        });
+
+        Ok(())
+    }
+
+    pub(super) fn set_option(
+        &mut self,
+        option: &dyn SocketOption,
+        socket: &dyn SetIpv6LevelOption,
+    ) -> Result<NeedIfacePoll> {
+        match_sock_option_ref!(option, {
+            ipv6_v6only: V6Only => {
+                // The option only affects which addresses can be bound, so Linux refuses to change
+                // it after the socket is bound.
+                if socket.is_bound() {
+                    return_errno_with_message!(
+                        Errno::EINVAL,
+                        "IPV6_V6ONLY cannot be set after the socket is bound"
+                    );
+                }
+                let v6only = ipv6_v6only.get().unwrap();
+                self.set_v6only(*v6only);
+            },
+            _ => return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to be set is unknown")
+        });
+
+        Ok(NeedIfacePoll::FALSE)
+    }
+}
+
+impl_socket_options!(
+    pub struct V6Only(bool);
+);
+
 #[derive(Debug, Clone, Copy)]
 pub struct IpTtl(Option<NonZeroU8>);
 
@@ -106,3 +168,7 @@ impl IpTtl {
 pub(super) trait SetIpLevelOption {
     fn set_hdrincl(&self, _hdrincl: bool) -> Result<()>;
 }
+
+pub(super) trait SetIpv6LevelOption {
+    fn is_bound(&self) -> bool;
+}
@@ -0,0 +1,609 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_bigtcp::{
+    errors::raw::SendError,
+    socket::{NeedIfacePoll, RawIpConfig},
+    wire::{
+        Icmpv4Message, Icmpv4Packet, IpAddress, IpProtocol, Ipv4Address, Ipv4Packet, Ipv4Repr,
+        IPV4_HEADER_LEN,
+    },
+};
+
+use super::{
+    addr::IpFamily,
+    common::{get_ephemeral_iface, get_iface_to_bind},
+    options::{IpOptionSet, SetIpLevelOption},
+    DatagramObserver,
+};
+use crate::{
+    events::IoEvents,
+    match_sock_option_mut,
+    net::{
+        iface::{iter_all_ifaces, RawIpSocket},
+        socket::{
+            options::{Error as SocketError, SocketOption},
+            private::SocketPrivate,
+            util::{
+                options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+                MessageHeader, SendRecvFlags, SocketAddr,
+            },
+            Socket,
+        },
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    util::{net::Protocol, MultiRead, MultiWrite},
+};
+
+/// A raw IPv4 socket or an ICMP ping socket.
+///
+/// Raw sockets (`SOCK_RAW`) send and receive IP packets of a specific protocol. Ping sockets
+/// (`SOCK_DGRAM` with `IPPROTO_ICMP`) only send ICMP echo requests and receive the corresponding
+/// ICMP echo replies, so they can be created by unprivileged users.
+pub struct RawSocket {
+    // Lock order: `inner` first, `options` second
+    inner: RwMutex<Inner>,
+    options: RwLock<OptionSet>,
+
+    kind: RawSocketKind,
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RawSocketKind {
+    /// A `SOCK_RAW` socket with the specified protocol.
+    Raw(IpProtocol),
+    /// A `SOCK_DGRAM` socket with `IPPROTO_ICMP`.
+    Ping,
+}
+
+struct Inner {
+    /// The underlying sockets.
+    ///
+    /// If the socket is bound to a specific address, there is only one underlying socket on the
+    /// iface that owns the address. Otherwise, there is one underlying socket on each iface.
+    sockets: Vec<RawIpSocket>,
+    local_addr: Option<Ipv4Address>,
+    remote_addr: Option<Ipv4Address>,
+    /// The ICMP echo identifier, which is only used by ping sockets.
+    echo_ident: Option<EchoIdent>,
+}
+
+#[derive(Debug, Clone)]
+struct OptionSet {
+    socket: SocketOptionSet,
+    ip: IpOptionSet,
+}
+
+impl OptionSet {
+    fn new(kind: RawSocketKind) -> Self {
+        // "If the protocol is IPPROTO_RAW, then IP_HDRINCL is enabled by default." See
+        // <https://man7.org/linux/man-pages/man7/raw.7.html>.
+        let hdrincl = kind == RawSocketKind::Raw(IpProtocol::from(Protocol::IPPROTO_RAW as u8));
+
+        Self {
+            socket: SocketOptionSet::new_raw(),
+            ip: IpOptionSet::new_raw(hdrincl),
+        }
+    }
+}
+
+impl RawSocket {
+    /// Creates a new `SOCK_RAW` socket.
+    ///
+    /// The caller must have the `CAP_NET_RAW` capability.
+    pub fn new_raw(protocol: i32, is_nonblocking: bool) -> Result<Arc<Self>> {
+        // Raw sockets accept any protocol number, including those unknown to `Protocol`.
+        let protocol = match protocol {
+            0 => return_errno_with_message!(
+                Errno::EPROTONOSUPPORT,
+                "raw sockets cannot be created with IPPROTO_IP"
+            ),
+            1..=255 => IpProtocol::from(protocol as u8),
+            _ => return_errno_with_message!(Errno::EINVAL, "the protocol is invalid"),
+        };
+
+        let current = current_thread!();
+        let credentials = current.as_posix_thread().unwrap().credentials();
+        if !credentials.euid().is_root()
+            && !credentials.effective_capset().contains(CapSet::NET_RAW)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "creating raw sockets requires the CAP_NET_RAW capability"
+            );
+        }
+
+        let socket = Self::new(RawSocketKind::Raw(protocol), is_nonblocking);
+        // A raw socket starts receiving packets even if it is not bound.
+        let sockets = socket.new_ip_sockets(None, None)?;
+        socket.inner.write().sockets = sockets;
+
+        Ok(socket)
+    }
+
+    /// Creates a new ICMP ping socket.
+    //
+    // TODO: Linux only allows users in the groups specified by `net.ipv4.ping_group_range` to
+    // create ping sockets. Here we allow every user to create them.
+    pub fn new_ping(is_nonblocking: bool) -> Arc<Self> {
+        Self::new(RawSocketKind::Ping, is_nonblocking)
+    }
+
+    fn new(kind: RawSocketKind, is_nonblocking: bool) -> Arc<Self> {
+        Arc::new(Self {
+            inner: RwMutex::new(Inner {
+                sockets: Vec::new(),
+                local_addr: None,
+                remote_addr: None,
+                echo_ident: None,
+            }),
+            options: RwLock::new(OptionSet::new(kind)),
+            kind,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee: Pollee::new(),
+        })
+    }
+
+    fn protocol(&self) -> IpProtocol {
+        match self.kind {
+            RawSocketKind::Raw(protocol) => protocol,
+            RawSocketKind::Ping => IpProtocol::Icmp,
+        }
+    }
+
+    /// Creates the underlying sockets that receive packets destined for `local_addr`.
+    fn new_ip_sockets(
+        &self,
+        local_addr: Option<Ipv4Address>,
+        echo_ident: Option<u16>,
+    ) -> Result<Vec<RawIpSocket>> {
+        let config = RawIpConfig {
+            protocol: self.protocol(),
+            local_addr,
+            recv_header: self.kind != RawSocketKind::Ping,
+            echo_ident,
+        };
+
+        let new_socket =
+            |iface| RawIpSocket::new(iface, config, DatagramObserver::new(self.pollee.clone()));
+
+        let Some(local_addr) = local_addr else {
+            return Ok(iter_all_ifaces()
+                .map(|iface| new_socket(iface.clone()))
+                .collect());
+        };
+
+        let Some(iface) = get_iface_to_bind(&IpAddress::Ipv4(local_addr)) else {
+            return_errno_with_message!(
+                Errno::EADDRNOTAVAIL,
+                "the address is not available from the local machine"
+            );
+        };
+
+        Ok(vec![new_socket(iface)])
+    }
+
+    fn addr_from_user(&self, socket_addr: SocketAddr) -> Result<(Option<Ipv4Address>, u16)> {
+        let endpoint = IpFamily::V4.endpoint_from_user(socket_addr, false)?;
+        let IpAddress::Ipv4(addr) = endpoint.addr else {
+            unreachable!("IPv4 sockets should only accept IPv4 addresses");
+        };
+
+        let addr = if addr.is_unspecified() {
+            None
+        } else {
+            Some(addr)
+        };
+
+        Ok((addr, endpoint.port))
+    }
+
+    fn bind_inner(
+        &self,
+        inner: &mut Inner,
+        local_addr: Option<Ipv4Address>,
+        port: u16,
+    ) -> Result<()> {
+        let echo_ident = match self.kind {
+            RawSocketKind::Raw(_) => None,
+            RawSocketKind::Ping if inner.echo_ident.is_some() => {
+                return_errno_with_message!(Errno::EINVAL, "the socket is already bound")
+            }
+            RawSocketKind::Ping => Some(EchoIdent::alloc(port)?),
+        };
+
+        let sockets = self.new_ip_sockets(local_addr, echo_ident.as_ref().map(EchoIdent::ident))?;
+
+        inner.sockets = sockets;
+        inner.local_addr = local_addr;
+        inner.echo_ident = echo_ident;
+
+        Ok(())
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        _flags: SendRecvFlags,
+    ) -> Result<(usize, SocketAddr)> {
+        let inner = self.inner.read();
+
+        // TODO: Filter out the packets that do not come from the remote address if the socket is
+        // connected.
+        let Some(packet) = inner.sockets.iter().find_map(|socket| socket.recv().ok()) else {
+            return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty");
+        };
+
+        if packet.data.len() > writer.sum_lens() {
+            warn!("setting MSG_TRUNC is not supported");
+        }
+        let copied_len = writer.write(&mut VmReader::from(packet.data.as_slice()))?;
+
+        drop(inner);
+        self.pollee.invalidate();
+
+        Ok((copied_len, SocketAddr::IPv4(packet.src_addr, 0)))
+    }
+
+    fn try_send(&self, mut bytes: Vec<u8>, remote_addr: Option<Ipv4Address>) -> Result<usize> {
+        let len = bytes.len();
+
+        let mut inner = self.inner.write();
+        let options = self.options.read();
+
+        let Some(remote_addr) = remote_addr.or(inner.remote_addr) else {
+            return_errno_with_message!(
+                Errno::EDESTADDRREQ,
+                "the destination address is not specified"
+            );
+        };
+
+        if self.kind == RawSocketKind::Ping {
+            if inner.echo_ident.is_none() {
+                self.bind_inner(&mut inner, None, 0)?;
+            }
+            let ident = inner.echo_ident.as_ref().unwrap().ident();
+            fill_echo_request(&mut bytes, ident)?;
+        }
+
+        let iface = match inner.local_addr {
+            Some(_) => inner.sockets[0].iface().clone(),
+            None => get_ephemeral_iface(&IpAddress::Ipv4(remote_addr)),
+        };
+        let Some(src_addr) = inner.local_addr.or_else(|| iface.ipv4_addr()) else {
+            return_errno_with_message!(
+                Errno::ENETUNREACH,
+                "no local address is available to reach the remote address"
+            );
+        };
+
+        let (ip_repr, payload) = if options.ip.hdrincl() {
+            parse_ip_header(bytes, src_addr)?
+        } else {
+            let ip_repr = Ipv4Repr {
+                src_addr,
+                dst_addr: remote_addr,
+                next_header: self.protocol(),
+                payload_len: len,
+                hop_limit: options.ip.ttl().get(),
+            };
+            (ip_repr, bytes)
+        };
+
+        let socket = inner
+            .sockets
+            .iter()
+            .find(|socket| Arc::ptr_eq(socket.iface(), &iface))
+            .unwrap();
+        match socket.send(ip_repr, payload) {
+            Ok(()) => (),
+            Err(SendError::TooLarge) => {
+                return_errno_with_message!(Errno::EMSGSIZE, "the message is too large")
+            }
+            Err(SendError::BufferFull) => {
+                return_errno_with_message!(Errno::EAGAIN, "the send buffer is full")
+            }
+        }
+
+        drop(options);
+        drop(inner);
+
+        self.pollee.invalidate();
+        iface.poll();
+
+        Ok(len)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.read();
+
+        let mut events = IoEvents::empty();
+
+        if inner.sockets.iter().any(|socket| socket.can_recv()) {
+            events |= IoEvents::IN;
+        }
+
+        if inner.sockets.iter().all(|socket| socket.can_send()) {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+}
+
+/// Fills the identifier and the checksum of an ICMP echo request built by the user.
+fn fill_echo_request(bytes: &mut [u8], ident: u16) -> Result<()> {
+    const ICMP_ECHO_HEADER_LEN: usize = 8;
+
+    if bytes.len() < ICMP_ECHO_HEADER_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the ICMP message is too short");
+    }
+
+    let mut icmp_packet = Icmpv4Packet::new_unchecked(bytes);
+    if icmp_packet.msg_type() != Icmpv4Message::EchoRequest || icmp_packet.msg_code() != 0 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "ping sockets can only send ICMP echo requests"
+        );
+    }
+
+    icmp_packet.set_echo_ident(ident);
+    icmp_packet.fill_checksum();
+
+    Ok(())
+}
+
+/// Parses the IP header built by the user when `IP_HDRINCL` is enabled.
+///
+/// Similar to Linux, the total length is always filled in, and the source address is filled in
+/// if it is zero. See <https://man7.org/linux/man-pages/man7/raw.7.html>.
+fn parse_ip_header(mut bytes: Vec<u8>, src_addr: Ipv4Address) -> Result<(Ipv4Repr, Vec<u8>)> {
+    let len = bytes.len();
+    if len < IPV4_HEADER_LEN || len > u16::MAX as usize {
+        return_errno_with_message!(Errno::EINVAL, "the IP packet length is invalid");
+    }
+
+    let mut ip_packet = Ipv4Packet::new_unchecked(bytes.as_mut_slice());
+    ip_packet.set_total_len(len as u16);
+    if ip_packet.src_addr().is_unspecified() {
+        ip_packet.set_src_addr(src_addr);
+    }
+
+    let header_len = ip_packet.header_len() as usize;
+    if header_len < IPV4_HEADER_LEN || header_len > len {
+        return_errno_with_message!(Errno::EINVAL, "the IP header length is invalid");
+    }
+
+    // TODO: Keep the IP options and other header fields (e.g., the identification) specified by
+    // the user.
+    let ip_repr = Ipv4Repr {
+        src_addr: ip_packet.src_addr(),
+        dst_addr: ip_packet.dst_addr(),
+        next_header: ip_packet.next_header(),
+        payload_len: len - header_len,
+        hop_limit: ip_packet.hop_limit(),
+    };
+
+    Ok((ip_repr, bytes.split_off(header_len)))
+}
+
+/// An ICMP echo identifier allocated to a ping socket.
+///
+/// The identifier is released when the object is dropped.
+struct EchoIdent(u16);
+
+static USED_ECHO_IDENTS: SpinLock<BTreeSet<u16>> = SpinLock::new(BTreeSet::new());
+
+impl EchoIdent {
+    /// Allocates the identifier, or an unused identifier if `ident` is zero.
+    fn alloc(ident: u16) -> Result<Self> {
+        let mut used_idents = USED_ECHO_IDENTS.lock();
+
+        let ident = if ident != 0 {
+            ident
+        } else {
+            (1..=u16::MAX)
+                .find(|ident| !used_idents.contains(ident))
+                .ok_or_else(|| {
+                    Error::with_message(Errno::EAGAIN, "no ICMP echo identifier is available")
+                })?
+        };
+
+        if !used_idents.insert(ident) {
+            return_errno_with_message!(Errno::EADDRINUSE, "the ICMP echo identifier is in use");
+        }
+
+        Ok(Self(ident))
+    }
+
+    fn ident(&self) -> u16 {
+        self.0
+    }
+}
+
+impl Drop for EchoIdent {
+    fn drop(&mut self) {
+        USED_ECHO_IDENTS.lock().remove(&self.0);
+    }
+}
+
+impl Pollable for RawSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl SocketPrivate for RawSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for RawSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let (local_addr, port) = self.addr_from_user(socket_addr)?;
+
+        let mut inner = self.inner.write();
+        self.bind_inner(&mut inner, local_addr, port)?;
+
+        drop(inner);
+        self.pollee.invalidate();
+
+        Ok(())
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        let (remote_addr, _) = self.addr_from_user(socket_addr)?;
+        let Some(remote_addr) = remote_addr else {
+            return_errno_with_message!(Errno::EINVAL, "the remote address is unspecified");
+        };
+
+        let mut inner = self.inner.write();
+        if self.kind == RawSocketKind::Ping && inner.echo_ident.is_none() {
+            self.bind_inner(&mut inner, None, 0)?;
+        }
+        inner.remote_addr = Some(remote_addr);
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        let inner = self.inner.read();
+
+        let addr = inner.local_addr.unwrap_or(Ipv4Address::UNSPECIFIED);
+        let port = inner.echo_ident.as_ref().map_or(0, EchoIdent::ident);
+
+        Ok(SocketAddr::IPv4(addr, port))
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        let Some(remote_addr) = self.inner.read().remote_addr else {
+            return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected");
+        };
+
+        Ok(SocketAddr::IPv4(remote_addr, 0))
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+        } = message_header;
+
+        let remote_addr = match addr {
+            Some(addr) => {
+                let (remote_addr, _) = self.addr_from_user(addr)?;
+                Some(remote_addr.unwrap_or(Ipv4Address::UNSPECIFIED))
+            }
+            None => None,
+        };
+
+        if !control_messages.is_empty() {
+            // TODO: Support sending control message
+            warn!("sending control message is not supported");
+        }
+
+        let mut bytes = vec![0u8; reader.sum_lens()];
+        reader.read(&mut VmWriter::from(bytes.as_mut_slice()))?;
+
+        // TODO: Block if the send buffer is full
+        self.try_send(bytes, remote_addr)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, peer_addr) =
+            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_errors: SocketError => {
+                // TODO: Support socket errors for raw sockets
+                socket_errors.set(None);
+                return Ok(());
+            },
+            _ => ()
+        });
+
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option, self) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // Deal with IP-level options
+        options.ip.get_option(option)
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        let mut options = self.options.write();
+
+        match options.socket.set_option(option, self) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                // Deal with IP-level options
+                options.ip.set_option(option, self)
+            }
+            result => result,
+        }
+        .map(|_: NeedIfacePoll| ())
+    }
+}
+
+impl GetSocketLevelOption for RawSocket {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for RawSocket {}
+
+impl SetIpLevelOption for RawSocket {
+    fn set_hdrincl(&self, _hdrincl: bool) -> Result<()> {
+        if self.kind == RawSocketKind::Ping {
+            return_errno_with_message!(
+                Errno::ENOPROTOOPT,
+                "IP_HDRINCL cannot be set on ping sockets"
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::{
-    net::Ipv4Addr,
-    sync::atomic::{AtomicBool, Ordering},
-};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use aster_bigtcp::{socket::RawTcpOption, wire::IpEndpoint};
 
@@ -12,10 +9,7 @@ use crate::{
     events::IoEvents,
     net::{
         iface::BoundPort,
-        socket::{
-            ip::common::{bind_port, get_ephemeral_endpoint},
-            util::SocketAddr,
-        },
+        socket::ip::common::{bind_port, check_remote_family, get_ephemeral_endpoint},
     },
     prelude::*,
 };
@@ -95,14 +89,27 @@ impl InitStream {
             "`finish_last_connect()` should be called before calling `connect()`"
         );
 
-        let bound_port = if let Some(bound_port) = self.bound_port {
-            bound_port
-        } else {
-            let endpoint = get_ephemeral_endpoint(remote_endpoint);
-            match bind_port(&endpoint, can_reuse) {
+        let bound_port = match self.bound_port {
+            Some(bound_port) if bound_port.addr().is_unspecified() => {
+                // The socket is bound to the unspecified address, so we need to choose the local
+                // address now. The bound port number is kept.
+                match bind_ephemeral_addr(remote_endpoint, bound_port.port(), can_reuse) {
+                    Ok(new_bound_port) => new_bound_port,
+                    Err(err) => return Err((err, Self::new_bound(bound_port))),
+                }
+            }
+            Some(bound_port) => {
+                if let Err(err) =
+                    check_remote_family(&bound_port.endpoint().unwrap(), remote_endpoint)
+                {
+                    return Err((err, Self::new_bound(bound_port)));
+                }
+                bound_port
+            }
+            None => match bind_ephemeral_addr(remote_endpoint, 0, can_reuse) {
                 Ok(bound_port) => bound_port,
                 Err(err) => return Err((err, self)),
-            }
+            },
         };
 
         ConnectingStream::new(bound_port, *remote_endpoint, option, observer).map_err(
@@ -139,6 +146,9 @@ impl InitStream {
         self,
         backlog: usize,
         option: &RawTcpOption,
+        unspecified_endpoint: &IpEndpoint,
+        can_reuse: bool,
+        is_v6only: bool,
         observer: StreamObserver,
     ) -> core::result::Result<ListenStream, (Error, Self)> {
         if !self.is_connect_done {
@@ -153,28 +163,23 @@ impl InitStream {
             ));
         }
 
-        let Some(bound_port) = self.bound_port else {
-            // FIXME: The socket should be bound to INADDR_ANY (i.e., 0.0.0.0) with an ephemeral
-            // port. However, INADDR_ANY is not yet supported, so we need to return an error first.
-            warn!("listen() without bind() is not implemented");
-            return Err((
-                Error::with_message(Errno::EINVAL, "listen() without bind() is not implemented"),
-                self,
-            ));
+        let bound_port = if let Some(bound_port) = self.bound_port {
+            bound_port
+        } else {
+            // Like Linux, the socket is bound to the unspecified address with an ephemeral port.
+            match bind_port(unspecified_endpoint, can_reuse) {
+                Ok(bound_port) => bound_port,
+                Err(err) => return Err((err, self)),
+            }
         };
 
-        match ListenStream::new(bound_port, backlog, option, observer) {
+        match ListenStream::new(bound_port, backlog, option, is_v6only, observer) {
             Ok(listen_stream) => Ok(listen_stream),
             Err((bound_port, error)) => Err((error, Self::new_bound(bound_port))),
         }
     }
 
-    pub(super) fn try_recv(&self) -> Result<(usize, SocketAddr)> {
-        // FIXME: Linux does not return addresses for `recvfrom` on connection-oriented sockets.
-        // This is a placeholder that has no Linux equivalent. (Note also that in this case
-        // `getpeeraddr` will simply fail with `ENOTCONN`).
-        const UNSPECIFIED_SOCKET_ADDR: SocketAddr = SocketAddr::IPv4(Ipv4Addr::UNSPECIFIED, 0);
-
+    pub(super) fn try_recv(&self) -> Result<usize> {
         // Below are some magic checks to make our behavior identical to Linux.
 
         if self.is_connect_done {
@@ -185,7 +190,7 @@ impl InitStream {
             return Err(err);
         }
 
-        Ok((0, UNSPECIFIED_SOCKET_ADDR))
+        Ok(0)
     }
 
     pub(super) fn try_send(&self) -> Result<usize> {
@@ -224,3 +229,13 @@ impl InitStream {
         }
     }
 }
+
+/// Binds a port at the local address that is used to communicate with the remote endpoint.
+fn bind_ephemeral_addr(
+    remote_endpoint: &IpEndpoint,
+    port: u16,
+    can_reuse: bool,
+) -> Result<BoundPort> {
+    let ephemeral_endpoint = get_ephemeral_endpoint(remote_endpoint)?;
+    bind_port(&IpEndpoint::new(ephemeral_endpoint.addr, port), can_reuse)
+}
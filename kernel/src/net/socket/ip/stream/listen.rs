@@ -2,19 +2,25 @@
 
 use aster_bigtcp::{
     errors::tcp::ListenError,
+    iface::BindPortConfig,
     socket::{RawTcpOption, RawTcpSetOption},
-    wire::IpEndpoint,
+    wire::{IpAddress, IpEndpoint, Ipv4Address},
 };
 
 use super::{connected::ConnectedStream, observer::StreamObserver};
 use crate::{
     events::IoEvents,
-    net::iface::{BoundPort, Iface, TcpListener},
+    net::iface::{iter_all_ifaces, BoundPort, Iface, TcpListener},
     prelude::*,
 };
 
 pub(super) struct ListenStream {
-    tcp_listener: TcpListener,
+    /// The TCP listeners.
+    ///
+    /// The first listener uses the port bound by the user. If the socket is bound to the
+    /// unspecified address, there are also listeners on the other ifaces (see
+    /// [`bind_other_ports`]).
+    tcp_listeners: Vec<TcpListener>,
 }
 
 impl ListenStream {
@@ -22,41 +28,65 @@ impl ListenStream {
         bound_port: BoundPort,
         backlog: usize,
         option: &RawTcpOption,
+        is_v6only: bool,
         observer: StreamObserver,
     ) -> core::result::Result<Self, (BoundPort, Error)> {
         const SOMAXCONN: usize = 4096;
         let max_conn = SOMAXCONN.min(backlog);
 
-        match TcpListener::new_listen(bound_port, max_conn, option, observer) {
-            Ok(tcp_listener) => Ok(Self { tcp_listener }),
-            Err((bound_port, ListenError::AddressInUse)) => Err((
-                bound_port,
-                Error::with_message(Errno::EADDRINUSE, "listener key conflicts"),
-            )),
-            Err((_, err)) => {
-                unreachable!("`new_listen` fails with {:?}, which should not happen", err)
+        let other_bound_ports = match bind_other_ports(&bound_port, is_v6only) {
+            Ok(other_bound_ports) => other_bound_ports,
+            Err(err) => return Err((bound_port, err)),
+        };
+
+        let mut tcp_listeners = Vec::with_capacity(other_bound_ports.len() + 1);
+
+        for other_bound_port in other_bound_ports {
+            match new_listener(other_bound_port, max_conn, option, observer.clone()) {
+                Ok(tcp_listener) => tcp_listeners.push(tcp_listener),
+                Err((_, err)) => {
+                    close_listeners(&tcp_listeners);
+                    return Err((bound_port, err));
+                }
             }
         }
+
+        match new_listener(bound_port, max_conn, option, observer) {
+            Ok(tcp_listener) => tcp_listeners.insert(0, tcp_listener),
+            Err((bound_port, err)) => {
+                close_listeners(&tcp_listeners);
+                return Err((bound_port, err));
+            }
+        }
+
+        Ok(Self { tcp_listeners })
     }
 
     pub(super) fn try_accept(&self) -> Result<ConnectedStream> {
-        let (new_conn, remote_endpoint) = self.tcp_listener.accept().ok_or_else(|| {
-            Error::with_message(Errno::EAGAIN, "no pending connection is available")
-        })?;
+        let Some((new_conn, remote_endpoint)) =
+            self.tcp_listeners.iter().find_map(TcpListener::accept)
+        else {
+            return_errno_with_message!(Errno::EAGAIN, "no pending connection is available");
+        };
 
         Ok(ConnectedStream::new(new_conn, remote_endpoint, false))
     }
 
     pub(super) fn local_endpoint(&self) -> IpEndpoint {
-        self.tcp_listener.local_endpoint().unwrap()
+        self.tcp_listeners[0].local_endpoint().unwrap()
     }
 
     pub(super) fn iface(&self) -> &Arc<Iface> {
-        self.tcp_listener.iface()
+        self.tcp_listeners[0].iface()
+    }
+
+    /// Returns an iterator over the ifaces that the socket listens on.
+    pub(super) fn ifaces(&self) -> impl Iterator<Item = &Arc<Iface>> {
+        self.tcp_listeners.iter().map(TcpListener::iface)
     }
 
     pub(super) fn check_io_events(&self) -> IoEvents {
-        let can_accept = self.tcp_listener.can_accept();
+        let can_accept = self.tcp_listeners.iter().any(TcpListener::can_accept);
 
         // If network packets come in simultaneously, the socket state may change in the middle.
         // However, the current pollee implementation should be able to handle this race condition.
@@ -67,14 +97,71 @@ impl ListenStream {
         }
     }
 
-    pub(super) fn set_raw_option<R>(
-        &self,
-        set_option: impl FnOnce(&dyn RawTcpSetOption) -> R,
-    ) -> R {
-        set_option(&self.tcp_listener)
+    pub(super) fn set_raw_option<R>(&self, set_option: impl Fn(&dyn RawTcpSetOption) -> R) -> R {
+        let (first_listener, other_listeners) = self.tcp_listeners.split_first().unwrap();
+        for tcp_listener in other_listeners {
+            set_option(tcp_listener);
+        }
+        set_option(first_listener)
+    }
+
+    pub(super) fn into_listeners(self) -> Vec<TcpListener> {
+        self.tcp_listeners
     }
+}
+
+fn new_listener(
+    bound_port: BoundPort,
+    max_conn: usize,
+    option: &RawTcpOption,
+    observer: StreamObserver,
+) -> core::result::Result<TcpListener, (BoundPort, Error)> {
+    match TcpListener::new_listen(bound_port, max_conn, option, observer) {
+        Ok(tcp_listener) => Ok(tcp_listener),
+        Err((bound_port, ListenError::AddressInUse)) => Err((
+            bound_port,
+            Error::with_message(Errno::EADDRINUSE, "listener key conflicts"),
+        )),
+        Err((_, err)) => {
+            unreachable!("`new_listen` fails with {:?}, which should not happen", err)
+        }
+    }
+}
+
+/// Binds the other ports that the socket should listen on.
+///
+/// Like Linux, a socket bound to the unspecified address listens on all ifaces. In addition, an
+/// IPv6 socket bound to the unspecified address listens on the IPv4 unspecified address as well,
+/// unless it is IPv6-only.
+fn bind_other_ports(bound_port: &BoundPort, is_v6only: bool) -> Result<Vec<BoundPort>> {
+    let bound_addr = bound_port.addr();
+    if !bound_addr.is_unspecified() {
+        return Ok(Vec::new());
+    }
+
+    let mut addrs = vec![bound_addr];
+    if matches!(bound_addr, IpAddress::Ipv6(_)) && !is_v6only {
+        addrs.push(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED));
+    }
+
+    let mut other_bound_ports = Vec::new();
+    for iface in iter_all_ifaces() {
+        for &addr in addrs.iter() {
+            if iface.index() == bound_port.iface().index() && addr == bound_addr {
+                continue;
+            }
+
+            let config = BindPortConfig::new(bound_port.port(), bound_port.can_reuse());
+            other_bound_ports.push(iface.bind(addr, config)?);
+        }
+    }
+
+    Ok(other_bound_ports)
+}
 
-    pub(super) fn into_listener(self) -> TcpListener {
-        self.tcp_listener
+fn close_listeners(tcp_listeners: &[TcpListener]) {
+    for tcp_listener in tcp_listeners {
+        tcp_listener.close();
+        tcp_listener.iface().poll();
     }
 }
@@ -20,8 +20,8 @@ use takeable::Takeable;
 use util::{Retrans, TcpOptionSet};
 
 use super::{
-    addr::UNSPECIFIED_LOCAL_ENDPOINT,
-    options::{IpOptionSet, SetIpLevelOption},
+    addr::IpFamily,
+    options::{IpOptionSet, Ipv6OptionSet, SetIpLevelOption, SetIpv6LevelOption},
 };
 use crate::{
     events::IoEvents,
@@ -57,6 +57,7 @@ pub struct StreamSocket {
     state: RwLock<Takeable<State>, PreemptDisabled>,
     options: RwLock<OptionSet>,
 
+    family: IpFamily,
     is_nonblocking: AtomicBool,
     pollee: Pollee,
 }
@@ -76,6 +77,7 @@ enum State {
 struct OptionSet {
     socket: SocketOptionSet,
     ip: IpOptionSet,
+    ipv6: Ipv6OptionSet,
     tcp: TcpOptionSet,
 }
 
@@ -83,8 +85,14 @@ impl OptionSet {
     fn new() -> Self {
         let socket = SocketOptionSet::new_tcp();
         let ip = IpOptionSet::new_tcp();
+        let ipv6 = Ipv6OptionSet::new();
         let tcp = TcpOptionSet::new();
-        OptionSet { socket, ip, tcp }
+        OptionSet {
+            socket,
+            ip,
+            ipv6,
+            tcp,
+        }
     }
 
     fn raw(&self) -> RawTcpOption {
@@ -96,20 +104,27 @@ impl OptionSet {
 }
 
 impl StreamSocket {
-    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+    pub fn new(family: IpFamily, is_nonblocking: bool) -> Arc<Self> {
         let init_stream = InitStream::new();
         Arc::new(Self {
             state: RwLock::new(Takeable::new(State::Init(init_stream))),
             options: RwLock::new(OptionSet::new()),
+            family,
             is_nonblocking: AtomicBool::new(is_nonblocking),
             pollee: Pollee::new(),
         })
     }
 
-    fn new_accepted(connected_stream: ConnectedStream) -> Arc<Self> {
+    fn new_accepted(
+        connected_stream: ConnectedStream,
+        family: IpFamily,
+        is_v6only: bool,
+    ) -> Arc<Self> {
         let options = connected_stream.raw_with(|raw_tcp_socket| {
             let mut options = OptionSet::new();
 
+            options.ipv6.set_v6only(is_v6only);
+
             if raw_tcp_socket.keep_alive().is_some() {
                 options.socket.set_keep_alive(true);
             }
@@ -129,11 +144,17 @@ impl StreamSocket {
         Arc::new(Self {
             options: RwLock::new(options),
             state: RwLock::new(Takeable::new(State::Connected(connected_stream))),
+            family,
             is_nonblocking: AtomicBool::new(false),
             pollee,
         })
     }
 
+    fn endpoint_from_user(&self, socket_addr: SocketAddr) -> Result<IpEndpoint> {
+        let is_v6only = self.options.read().ipv6.v6only();
+        self.family.endpoint_from_user(socket_addr, is_v6only)
+    }
+
     /// Ensures that the socket state is up to date and obtains a read lock on it.
     ///
     /// For a description of what "up-to-date" means, see [`Self::write_updated_state`].
@@ -293,16 +314,20 @@ impl StreamSocket {
             return_errno_with_message!(Errno::EINVAL, "the socket is not listening");
         };
 
+        let is_v6only = self.options.read().ipv6.v6only();
         let accepted = listen_stream.try_accept().map(|connected_stream| {
             let remote_endpoint = connected_stream.remote_endpoint();
-            let accepted_socket = Self::new_accepted(connected_stream);
-            (accepted_socket as _, remote_endpoint.into())
+            let accepted_socket = Self::new_accepted(connected_stream, self.family, is_v6only);
+            (
+                accepted_socket as _,
+                self.family.endpoint_to_user(remote_endpoint),
+            )
         });
-        let iface_to_poll = listen_stream.iface().clone();
+        let ifaces_to_poll = listen_stream.ifaces().cloned().collect::<Vec<_>>();
 
         drop(state);
         self.pollee.invalidate();
-        iface_to_poll.poll();
+        ifaces_to_poll.iter().for_each(|iface| iface.poll());
 
         accepted
     }
@@ -319,7 +344,14 @@ impl StreamSocket {
             State::Init(init_stream) => {
                 let result = init_stream.try_recv();
                 self.pollee.invalidate();
-                return result;
+
+                // FIXME: Linux does not return addresses for `recvfrom` on connection-oriented
+                // sockets. This is a placeholder that has no Linux equivalent. (Note also that in
+                // this case `getpeeraddr` will simply fail with `ENOTCONN`).
+                let unspecified_addr = self
+                    .family
+                    .endpoint_to_user(self.family.unspecified_local_endpoint());
+                return result.map(|recv_bytes| (recv_bytes, unspecified_addr));
             }
             State::Listen(_) => {
                 return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected")
@@ -341,7 +373,7 @@ impl StreamSocket {
             iface.poll();
         }
 
-        Ok((recv_bytes, remote_endpoint.into()))
+        Ok((recv_bytes, self.family.endpoint_to_user(remote_endpoint)))
     }
 
     fn try_send(&self, reader: &mut dyn MultiRead, flags: SendRecvFlags) -> Result<usize> {
@@ -421,7 +453,7 @@ impl SocketPrivate for StreamSocket {
 
 impl Socket for StreamSocket {
     fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
-        let endpoint = socket_addr.try_into()?;
+        let endpoint = self.endpoint_from_user(socket_addr)?;
 
         let mut state = self.write_updated_state();
         let State::Init(init_stream) = state.as_mut() else {
@@ -433,7 +465,7 @@ impl Socket for StreamSocket {
     }
 
     fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
-        let remote_endpoint = socket_addr.try_into()?;
+        let remote_endpoint = self.endpoint_from_user(socket_addr)?;
 
         if let Some(result) = self.start_connect(&remote_endpoint) {
             return result;
@@ -468,6 +500,9 @@ impl Socket for StreamSocket {
             let listen_stream = match init_stream.listen(
                 backlog,
                 &raw_option,
+                &self.family.unspecified_local_endpoint(),
+                options.socket.reuse_addr(),
+                options.ipv6.v6only(),
                 StreamObserver::new(self.pollee.clone()),
             ) {
                 Ok(listen_stream) => listen_stream,
@@ -510,12 +545,12 @@ impl Socket for StreamSocket {
         let local_endpoint = match state.as_ref() {
             State::Init(init_stream) => init_stream
                 .local_endpoint()
-                .unwrap_or(UNSPECIFIED_LOCAL_ENDPOINT),
+                .unwrap_or(self.family.unspecified_local_endpoint()),
             State::Connecting(connecting_stream) => connecting_stream.local_endpoint(),
             State::Listen(listen_stream) => listen_stream.local_endpoint(),
             State::Connected(connected_stream) => connected_stream.local_endpoint(),
         };
-        Ok(local_endpoint.into())
+        Ok(self.family.endpoint_to_user(local_endpoint))
     }
 
     fn peer_addr(&self) -> Result<SocketAddr> {
@@ -527,7 +562,7 @@ impl Socket for StreamSocket {
             State::Connecting(connecting_stream) => connecting_stream.remote_endpoint(),
             State::Connected(connected_stream) => connected_stream.remote_endpoint(),
         };
-        Ok(remote_endpoint.into())
+        Ok(self.family.endpoint_to_user(remote_endpoint))
     }
 
     fn sendmsg(
@@ -604,6 +639,14 @@ impl Socket for StreamSocket {
             res => return res,
         }
 
+        // Deal with IPv6-level options
+        if self.family == IpFamily::V6 {
+            match options.ipv6.get_option(option) {
+                Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+                res => return res,
+            }
+        }
+
         // Deal with TCP-level options
         // FIXME: Here we only return the previously set values, without actually
         // asking the underlying sockets for the real, effective values.
@@ -672,8 +715,24 @@ impl Socket for StreamSocket {
                 // Deal with IP-level options
                 match options.ip.set_option(option, state.as_ref()) {
                     Err(err) if err.error() == Errno::ENOPROTOOPT => {
-                        // Deal with TCP-level options
-                        do_tcp_setsockopt(option, &mut options, state.as_mut())?
+                        // Deal with IPv6-level options
+                        let result = if self.family == IpFamily::V6 {
+                            options.ipv6.set_option(option, state.as_ref())
+                        } else {
+                            Err(Error::with_message(
+                                Errno::ENOPROTOOPT,
+                                "IPv6-level options are not available for IPv4 sockets",
+                            ))
+                        };
+
+                        match result {
+                            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                                // Deal with TCP-level options
+                                do_tcp_setsockopt(option, &mut options, state.as_mut())?
+                            }
+                            Err(err) => return Err(err),
+                            Ok(need_iface_poll) => need_iface_poll,
+                        }
                     }
                     Err(err) => return Err(err),
                     Ok(need_iface_poll) => need_iface_poll,
@@ -781,7 +840,7 @@ impl State {
     ///
     /// For listening sockets, socket options are inherited by new connections. However, they are
     /// not updated for connections in the backlog queue.
-    fn set_raw_option<R>(&self, set_option: impl FnOnce(&dyn RawTcpSetOption) -> R) -> Option<R> {
+    fn set_raw_option<R>(&self, set_option: impl Fn(&dyn RawTcpSetOption) -> R) -> Option<R> {
         match self {
             State::Init(_) => None,
             State::Connecting(connecting_stream) => {
@@ -851,6 +910,15 @@ impl SetIpLevelOption for State {
     }
 }
 
+impl SetIpv6LevelOption for State {
+    fn is_bound(&self) -> bool {
+        match self {
+            State::Init(init_stream) => init_stream.bound_port().is_some(),
+            State::Connecting(_) | State::Connected(_) | State::Listen(_) => true,
+        }
+    }
+}
+
 impl Drop for StreamSocket {
     fn drop(&mut self) {
         let state = self.state.get_mut().take();
@@ -860,9 +928,10 @@ impl Drop for StreamSocket {
             State::Connecting(connecting_stream) => connecting_stream.into_connection(),
             State::Connected(connected_stream) => connected_stream.into_connection(),
             State::Listen(listen_stream) => {
-                let listener = listen_stream.into_listener();
-                listener.close();
-                listener.iface().poll();
+                for listener in listen_stream.into_listeners() {
+                    listener.close();
+                    listener.iface().poll();
+                }
                 return;
             }
         };
@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{net::socket::util::SocketAddr, prelude::*};
+
+/// The maximum length of a hardware address in [`PacketSocketAddr`].
+pub const MAX_HW_ADDR_LEN: usize = 8;
+
+/// A packet socket address.
+///
+/// This corresponds to `struct sockaddr_ll` in Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketSocketAddr {
+    /// The link-layer protocol in host byte order.
+    pub protocol: u16,
+    /// The interface index, where zero matches any interface.
+    pub ifindex: u32,
+    /// The hardware type (i.e., the ARP hardware identifier).
+    pub hatype: u16,
+    /// The type of the frame.
+    pub pkt_type: u8,
+    /// The length of the hardware address.
+    pub halen: u8,
+    /// The hardware address.
+    pub addr: [u8; MAX_HW_ADDR_LEN],
+}
+
+impl PacketSocketAddr {
+    /// Returns the valid part of the hardware address.
+    pub fn hw_addr(&self) -> &[u8] {
+        &self.addr[..(self.halen as usize).min(MAX_HW_ADDR_LEN)]
+    }
+}
+
+impl TryFrom<SocketAddr> for PacketSocketAddr {
+    type Error = Error;
+
+    fn try_from(value: SocketAddr) -> Result<Self> {
+        match value {
+            SocketAddr::Packet(addr) => Ok(addr),
+            _ => return_errno_with_message!(
+                Errno::EINVAL,
+                "the address is in an unsupported address family"
+            ),
+        }
+    }
+}
+
+impl From<PacketSocketAddr> for SocketAddr {
+    fn from(value: PacketSocketAddr) -> Self {
+        SocketAddr::Packet(value)
+    }
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Packet sockets (`AF_PACKET`).
+//!
+//! Packet sockets work at the link layer. They receive a copy of every frame that an iface sends
+//! or receives, and they can send frames to an iface directly, bypassing the network stack.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man7/packet.7.html>.
+
+mod addr;
+mod socket;
+
+pub use addr::{PacketSocketAddr, MAX_HW_ADDR_LEN};
+pub use socket::PacketSocket;
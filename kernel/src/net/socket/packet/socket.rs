@@ -0,0 +1,480 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use aster_bigtcp::{
+    errors::packet::SendError,
+    socket::{PacketConfig, PacketProtocol},
+    wire::{EthernetAddress, EthernetFrame, EthernetProtocol, ETHERNET_HEADER_LEN},
+};
+
+use super::addr::{PacketSocketAddr, MAX_HW_ADDR_LEN};
+use crate::{
+    events::IoEvents,
+    match_sock_option_mut,
+    net::{
+        iface::{iter_all_ifaces, Iface, PacketSocket as BigtcpPacketSocket},
+        socket::{
+            ip::DatagramObserver,
+            options::{Error as SocketError, SocketOption},
+            private::SocketPrivate,
+            util::{
+                options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+                MessageHeader, SendRecvFlags, SocketAddr,
+            },
+            Socket,
+        },
+    },
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        posix_thread::AsPosixThread,
+        signal::{PollHandle, Pollable, Pollee},
+    },
+    util::{MultiRead, MultiWrite},
+};
+
+/// A packet socket.
+pub struct PacketSocket {
+    // Lock order: `inner` first, `options` second
+    inner: RwMutex<Inner>,
+    options: RwLock<OptionSet>,
+
+    /// Whether the link-layer header is included (`SOCK_RAW`) or not (`SOCK_DGRAM`).
+    is_raw: bool,
+    is_nonblocking: AtomicBool,
+    pollee: Pollee,
+}
+
+struct Inner {
+    /// The underlying sockets.
+    ///
+    /// If the socket is bound to a specific iface, there is only one underlying socket on that
+    /// iface. Otherwise, there is one underlying socket on each iface.
+    sockets: Vec<BigtcpPacketSocket>,
+    /// The link-layer protocol in host byte order.
+    protocol: u16,
+    /// The index of the bound iface.
+    ifindex: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+struct OptionSet {
+    socket: SocketOptionSet,
+}
+
+impl OptionSet {
+    fn new() -> Self {
+        Self {
+            socket: SocketOptionSet::new_packet(),
+        }
+    }
+}
+
+/// The protocol that matches all link-layer protocols.
+const ETH_P_ALL: u16 = 0x0003;
+
+impl PacketSocket {
+    /// Creates a new packet socket.
+    ///
+    /// The protocol is in host byte order. The caller must have the `CAP_NET_RAW` capability.
+    pub fn new(is_raw: bool, protocol: u16, is_nonblocking: bool) -> Result<Arc<Self>> {
+        let current = current_thread!();
+        let credentials = current.as_posix_thread().unwrap().credentials();
+        if !credentials.euid().is_root()
+            && !credentials.effective_capset().contains(CapSet::NET_RAW)
+        {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "creating packet sockets requires the CAP_NET_RAW capability"
+            );
+        }
+
+        let socket = Arc::new(Self {
+            inner: RwMutex::new(Inner {
+                sockets: Vec::new(),
+                protocol,
+                ifindex: None,
+            }),
+            options: RwLock::new(OptionSet::new()),
+            is_raw,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            pollee: Pollee::new(),
+        });
+
+        // A packet socket starts receiving frames even if it is not bound.
+        let sockets = socket.new_packet_sockets(protocol, None)?;
+        socket.inner.write().sockets = sockets;
+
+        Ok(socket)
+    }
+
+    /// Creates the underlying sockets that receive frames from the iface with `ifindex`.
+    fn new_packet_sockets(
+        &self,
+        protocol: u16,
+        ifindex: Option<u32>,
+    ) -> Result<Vec<BigtcpPacketSocket>> {
+        let config = PacketConfig {
+            protocol: match protocol {
+                0 => PacketProtocol::Disabled,
+                ETH_P_ALL => PacketProtocol::All,
+                protocol => PacketProtocol::Only(protocol),
+            },
+        };
+
+        let new_socket = |iface: &Arc<Iface>| {
+            BigtcpPacketSocket::new(
+                iface.clone(),
+                config,
+                DatagramObserver::new(self.pollee.clone()),
+            )
+        };
+
+        let Some(ifindex) = ifindex else {
+            return Ok(iter_all_ifaces().map(new_socket).collect());
+        };
+
+        Ok(vec![new_socket(get_iface_by_index(ifindex)?)])
+    }
+
+    fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, SocketAddr)> {
+        if flags.contains(SendRecvFlags::MSG_PEEK) {
+            // TODO: Support peeking at frames
+            warn!("MSG_PEEK is not supported for packet sockets");
+        }
+
+        let inner = self.inner.read();
+
+        let Some((frame, iface)) = inner.sockets.iter().find_map(|socket| {
+            socket
+                .recv()
+                .ok()
+                .map(|frame| (frame, socket.iface().clone()))
+        }) else {
+            return_errno_with_message!(Errno::EAGAIN, "the receive buffer is empty");
+        };
+
+        drop(inner);
+        self.pollee.invalidate();
+
+        let data = if self.is_raw {
+            frame.data.as_slice()
+        } else {
+            &frame.data[frame.header_len..]
+        };
+
+        if data.len() > writer.sum_lens() {
+            warn!("setting MSG_TRUNC is not supported");
+        }
+        let copied_len = writer.write(&mut VmReader::from(data))?;
+
+        let mut addr = [0; MAX_HW_ADDR_LEN];
+        let halen = if let Some(src_hw_addr) = frame.src_hw_addr {
+            let src_hw_addr = src_hw_addr.as_bytes();
+            addr[..src_hw_addr.len()].copy_from_slice(src_hw_addr);
+            src_hw_addr.len() as u8
+        } else {
+            0
+        };
+        let src_addr = PacketSocketAddr {
+            protocol: frame.protocol,
+            ifindex: iface.index(),
+            hatype: iface.type_() as u16,
+            pkt_type: frame.pkt_type as u8,
+            halen,
+            addr,
+        };
+
+        Ok((copied_len, src_addr.into()))
+    }
+
+    fn try_send(&self, bytes: Vec<u8>, remote_addr: Option<PacketSocketAddr>) -> Result<usize> {
+        let len = bytes.len();
+
+        let inner = self.inner.read();
+
+        let ifindex = match remote_addr {
+            Some(remote_addr) if remote_addr.ifindex != 0 => remote_addr.ifindex,
+            _ => match inner.ifindex {
+                Some(ifindex) => ifindex,
+                None => return_errno_with_message!(
+                    Errno::ENXIO,
+                    "the interface to send the frame is not specified"
+                ),
+            },
+        };
+        let Some(socket) = inner
+            .sockets
+            .iter()
+            .find(|socket| socket.iface().index() == ifindex)
+        else {
+            return_errno_with_message!(Errno::ENODEV, "the interface does not exist");
+        };
+        let iface = socket.iface();
+
+        let frame = if self.is_raw {
+            bytes
+        } else if let Some(hw_addr) = iface.hw_addr() {
+            let Some(remote_addr) = remote_addr else {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the destination hardware address is not specified"
+                );
+            };
+            let protocol = if remote_addr.protocol != 0 {
+                remote_addr.protocol
+            } else {
+                inner.protocol
+            };
+            build_ether_frame(&remote_addr, hw_addr, protocol, &bytes)?
+        } else {
+            bytes
+        };
+
+        let header_len = if iface.hw_addr().is_some() {
+            ETHERNET_HEADER_LEN
+        } else {
+            0
+        };
+        if frame.len() < header_len {
+            return_errno_with_message!(Errno::EINVAL, "the frame is too short");
+        }
+        if frame.len() > iface.mtu() + header_len {
+            return_errno_with_message!(Errno::EMSGSIZE, "the frame is too large");
+        }
+
+        match socket.send(frame) {
+            Ok(()) => (),
+            Err(SendError::TooLarge) => {
+                return_errno_with_message!(Errno::EMSGSIZE, "the frame is too large")
+            }
+            Err(SendError::BufferFull) => {
+                return_errno_with_message!(Errno::EAGAIN, "the send buffer is full")
+            }
+        }
+
+        let iface = iface.clone();
+        drop(inner);
+
+        self.pollee.invalidate();
+        iface.poll();
+
+        Ok(len)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let inner = self.inner.read();
+
+        let mut events = IoEvents::empty();
+
+        if inner.sockets.iter().any(|socket| socket.can_recv()) {
+            events |= IoEvents::IN;
+        }
+
+        if inner.sockets.iter().all(|socket| socket.can_send()) {
+            events |= IoEvents::OUT;
+        }
+
+        events
+    }
+}
+
+fn get_iface_by_index(ifindex: u32) -> Result<&'static Arc<Iface>> {
+    iter_all_ifaces()
+        .find(|iface| iface.index() == ifindex)
+        .ok_or_else(|| Error::with_message(Errno::ENODEV, "the interface does not exist"))
+}
+
+/// Builds an Ethernet frame for a `SOCK_DGRAM` packet socket.
+fn build_ether_frame(
+    remote_addr: &PacketSocketAddr,
+    src_addr: EthernetAddress,
+    protocol: u16,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    let dst_addr = remote_addr.hw_addr();
+    if dst_addr.len() < 6 {
+        return_errno_with_message!(Errno::EINVAL, "the hardware address is too short");
+    }
+
+    let mut frame = vec![0; ETHERNET_HEADER_LEN + payload.len()];
+
+    let mut ether_frame = EthernetFrame::new_unchecked(frame.as_mut_slice());
+    ether_frame.set_dst_addr(EthernetAddress::from_bytes(&dst_addr[..6]));
+    ether_frame.set_src_addr(src_addr);
+    ether_frame.set_ethertype(EthernetProtocol::from(protocol));
+    ether_frame.payload_mut().copy_from_slice(payload);
+
+    Ok(frame)
+}
+
+impl Pollable for PacketSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl SocketPrivate for PacketSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, is_nonblocking: bool) {
+        self.is_nonblocking.store(is_nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for PacketSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let addr = PacketSocketAddr::try_from(socket_addr)?;
+
+        let mut inner = self.inner.write();
+
+        // A zero protocol keeps the current protocol, and a zero interface index matches all
+        // interfaces. See <https://man7.org/linux/man-pages/man7/packet.7.html>.
+        let protocol = if addr.protocol != 0 {
+            addr.protocol
+        } else {
+            inner.protocol
+        };
+        let ifindex = if addr.ifindex != 0 {
+            Some(addr.ifindex)
+        } else {
+            None
+        };
+
+        let sockets = self.new_packet_sockets(protocol, ifindex)?;
+
+        inner.sockets = sockets;
+        inner.protocol = protocol;
+        inner.ifindex = ifindex;
+
+        drop(inner);
+        self.pollee.invalidate();
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        let inner = self.inner.read();
+
+        let (hatype, halen, addr) = match inner.ifindex {
+            Some(ifindex) => {
+                let iface = get_iface_by_index(ifindex)?;
+
+                let mut addr = [0; MAX_HW_ADDR_LEN];
+                let halen = if let Some(hw_addr) = iface.hw_addr() {
+                    addr[..6].copy_from_slice(hw_addr.as_bytes());
+                    6
+                } else {
+                    0
+                };
+
+                (iface.type_() as u16, halen, addr)
+            }
+            None => (0, 0, [0; MAX_HW_ADDR_LEN]),
+        };
+
+        let addr = PacketSocketAddr {
+            protocol: inner.protocol,
+            ifindex: inner.ifindex.unwrap_or(0),
+            hatype,
+            pkt_type: 0,
+            halen,
+            addr,
+        };
+
+        Ok(addr.into())
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            addr,
+            control_messages,
+        } = message_header;
+
+        let remote_addr = addr.map(PacketSocketAddr::try_from).transpose()?;
+
+        if !control_messages.is_empty() {
+            // TODO: Support sending control message
+            warn!("sending control message is not supported");
+        }
+
+        let mut bytes = vec![0u8; reader.sum_lens()];
+        reader.read(&mut VmWriter::from(bytes.as_mut_slice()))?;
+
+        // TODO: Block if the send buffer is full
+        self.try_send(bytes, remote_addr)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with other flags. Only MSG_PEEK is checked here.
+        if !flags.sub(SendRecvFlags::MSG_PEEK).is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, peer_addr) =
+            self.block_on(IoEvents::IN, || self.try_recv(writer, flags))?;
+
+        let message_header = MessageHeader::new(Some(peer_addr), Vec::new());
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        match_sock_option_mut!(option, {
+            socket_errors: SocketError => {
+                // TODO: Support socket errors for packet sockets
+                socket_errors.set(None);
+                return Ok(());
+            },
+            _ => ()
+        });
+
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option, self) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // TODO: Deal with packet-level options (e.g., `PACKET_ADD_MEMBERSHIP`)
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        let mut options = self.options.write();
+
+        // TODO: Deal with packet-level options (e.g., `PACKET_ADD_MEMBERSHIP`)
+        options.socket.set_option(option, self).map(|_| ())
+    }
+}
+
+impl GetSocketLevelOption for PacketSocket {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for PacketSocket {}
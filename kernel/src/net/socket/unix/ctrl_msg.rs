@@ -5,7 +5,7 @@ use core::fmt;
 use aster_rights::ReadOp;
 use ostd::task::Task;
 
-use super::{cred::SocketCred, CUserCred, UnixStreamSocket};
+use super::{cred::SocketCred, CUserCred, UnixDatagramSocket, UnixStreamSocket};
 use crate::{
     fs::{
         file_handle::FileLike,
@@ -247,10 +247,10 @@ impl AuxiliaryData {
         // FIXME: Sending UNIX sockets over UNIX sockets can easily lead to circular references and
         // memory leaks. Linux uses a complex garbage collection algorithm to address these issues.
         // See also <https://elixir.bootlin.com/linux/v6.15/source/net/unix/garbage.c#L592>.
-        if files
-            .iter()
-            .any(|file| (&**file as &dyn Any).is::<UnixStreamSocket>())
-        {
+        if files.iter().any(|file| {
+            let file = &**file as &dyn Any;
+            file.is::<UnixStreamSocket>() || file.is::<UnixDatagramSocket>()
+        }) {
             warn!("UNIX sockets in SCM_RIGHTS messages can leak kernel resource");
 
             let credentials = current_thread!().as_posix_thread().unwrap().credentials();
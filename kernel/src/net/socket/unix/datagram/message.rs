@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ostd::sync::WaitQueue;
+
+use crate::{
+    events::IoEvents,
+    net::socket::{
+        unix::{
+            addr::{UnixSocketAddrBound, UnixSocketAddrKey},
+            ctrl_msg::AuxiliaryData,
+            UnixSocketAddr,
+        },
+        util::{ControlMessage, SendRecvFlags},
+    },
+    prelude::*,
+    process::signal::Pollee,
+    util::MultiWrite,
+};
+
+/// The default capacity of the receive queue of UNIX datagram sockets.
+pub(in crate::net) const UNIX_DATAGRAM_DEFAULT_BUF_SIZE: usize = 65536;
+
+/// A message in the receive queue of a UNIX datagram socket.
+pub(super) struct Message {
+    bytes: Vec<u8>,
+    src_addr: Option<UnixSocketAddrBound>,
+    aux_data: AuxiliaryData,
+}
+
+impl Message {
+    pub(super) fn new(
+        bytes: Vec<u8>,
+        src_addr: Option<UnixSocketAddrBound>,
+        aux_data: AuxiliaryData,
+    ) -> Self {
+        Self {
+            bytes,
+            src_addr,
+            aux_data,
+        }
+    }
+}
+
+/// The receiving end of a UNIX datagram socket.
+///
+/// Every UNIX datagram socket owns a receiver. Once the socket is bound, the receiver is
+/// registered in a global table so that other sockets can look it up by the address and send
+/// messages to it.
+pub(super) struct Receiver {
+    addr: Mutex<Option<UnixSocketAddrBound>>,
+    queue: Mutex<MessageQueue>,
+    pollee: Pollee,
+    wait_queue: WaitQueue,
+    is_pass_cred: AtomicBool,
+    is_read_shutdown: AtomicBool,
+}
+
+struct MessageQueue {
+    messages: VecDeque<Message>,
+    total_len: usize,
+}
+
+impl Receiver {
+    pub(super) fn new(pollee: Pollee) -> Arc<Self> {
+        Arc::new(Self {
+            addr: Mutex::new(None),
+            queue: Mutex::new(MessageQueue {
+                messages: VecDeque::new(),
+                total_len: 0,
+            }),
+            pollee,
+            wait_queue: WaitQueue::new(),
+            is_pass_cred: AtomicBool::new(false),
+            is_read_shutdown: AtomicBool::new(false),
+        })
+    }
+
+    pub(super) fn addr(&self) -> Option<UnixSocketAddrBound> {
+        self.addr.lock().clone()
+    }
+
+    pub(super) fn bind(self: &Arc<Self>, addr_to_bind: UnixSocketAddr) -> Result<()> {
+        let mut addr = self.addr.lock();
+
+        if addr.is_some() {
+            return addr_to_bind.bind_unnamed();
+        }
+
+        let bound_addr = addr_to_bind.bind()?;
+        RECEIVER_TABLE.add_receiver(bound_addr.to_key(), Arc::downgrade(self));
+        *addr = Some(bound_addr);
+
+        Ok(())
+    }
+
+    pub(super) fn try_push(&self, message: &mut Option<Message>) -> Result<()> {
+        if self.is_read_shutdown.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EPIPE, "the remote socket is shut down for reading");
+        }
+
+        let mut queue = self.queue.lock();
+
+        let len = message.as_ref().unwrap().bytes.len();
+        // A message that is larger than the capacity can still be queued if the queue is empty.
+        // Otherwise, the message can never be sent.
+        if !queue.messages.is_empty() && queue.total_len + len > UNIX_DATAGRAM_DEFAULT_BUF_SIZE {
+            return_errno_with_message!(Errno::EAGAIN, "the receive queue is full");
+        }
+
+        queue.messages.push_back(message.take().unwrap());
+        queue.total_len += len;
+
+        drop(queue);
+        self.pollee.notify(IoEvents::IN);
+
+        Ok(())
+    }
+
+    pub(super) fn try_recv(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, Option<UnixSocketAddrBound>, Vec<ControlMessage>)> {
+        let mut queue = self.queue.lock();
+
+        let Some(message) = queue.messages.front_mut() else {
+            if self.is_read_shutdown.load(Ordering::Relaxed) {
+                return Ok((0, None, Vec::new()));
+            }
+            return_errno_with_message!(Errno::EAGAIN, "the receive queue is empty");
+        };
+
+        if message.bytes.len() > writer.sum_lens() {
+            warn!("setting MSG_TRUNC is not supported");
+        }
+        let copied_len = writer.write(&mut VmReader::from(message.bytes.as_slice()))?;
+        let src_addr = message.src_addr.clone();
+
+        if flags.contains(SendRecvFlags::MSG_PEEK) {
+            // TODO: Report the credentials and duplicate the files when peeking at a message
+            // with auxiliary data.
+            return Ok((copied_len, src_addr, Vec::new()));
+        }
+
+        let mut message = queue.messages.pop_front().unwrap();
+        queue.total_len -= message.bytes.len();
+        drop(queue);
+
+        self.pollee.invalidate();
+        self.wait_queue.wake_all();
+
+        let is_pass_cred = self.is_pass_cred.load(Ordering::Relaxed);
+        let ctrl_msgs = message.aux_data.generate_control(is_pass_cred);
+
+        Ok((copied_len, src_addr, ctrl_msgs))
+    }
+
+    pub(super) fn shutdown_read(&self) {
+        self.is_read_shutdown.store(true, Ordering::Relaxed);
+        self.wait_queue.wake_all();
+    }
+
+    pub(super) fn is_read_shutdown(&self) -> bool {
+        self.is_read_shutdown.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn is_pass_cred(&self) -> bool {
+        self.is_pass_cred.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn set_pass_cred(&self, is_pass_cred: bool) {
+        self.is_pass_cred.store(is_pass_cred, Ordering::Relaxed);
+    }
+
+    pub(super) fn check_io_events(&self) -> IoEvents {
+        if self.queue.lock().messages.is_empty() {
+            IoEvents::empty()
+        } else {
+            IoEvents::IN
+        }
+    }
+
+    pub(super) fn pause_until<F>(&self, mut cond: F) -> Result<()>
+    where
+        F: FnMut() -> Result<()>,
+    {
+        self.wait_queue.pause_until(|| match cond() {
+            Err(err) if err.error() == Errno::EAGAIN => None,
+            result => Some(result),
+        })?
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        if let Some(addr) = self.addr.get_mut() {
+            RECEIVER_TABLE.remove_receiver(&addr.to_key());
+        }
+    }
+}
+
+static RECEIVER_TABLE: ReceiverTable = ReceiverTable::new();
+
+struct ReceiverTable {
+    receivers: RwLock<BTreeMap<UnixSocketAddrKey, Weak<Receiver>>>,
+}
+
+impl ReceiverTable {
+    const fn new() -> Self {
+        Self {
+            receivers: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    fn add_receiver(&self, addr_key: UnixSocketAddrKey, receiver: Weak<Receiver>) {
+        self.receivers.write().insert(addr_key, receiver);
+    }
+
+    fn get_receiver(&self, addr_key: &UnixSocketAddrKey) -> Option<Arc<Receiver>> {
+        self.receivers.read().get(addr_key).and_then(Weak::upgrade)
+    }
+
+    fn remove_receiver(&self, addr_key: &UnixSocketAddrKey) {
+        self.receivers.write().remove(addr_key);
+    }
+}
+
+pub(super) fn get_receiver(addr_key: &UnixSocketAddrKey) -> Result<Arc<Receiver>> {
+    RECEIVER_TABLE.get_receiver(addr_key).ok_or_else(|| {
+        Error::with_message(
+            Errno::ECONNREFUSED,
+            "no UNIX datagram socket is bound to the remote address",
+        )
+    })
+}
@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::message::{get_receiver, Message, Receiver};
+use crate::{
+    events::IoEvents,
+    net::socket::{
+        options::SocketOption,
+        private::SocketPrivate,
+        unix::{addr::UnixSocketAddrBound, ctrl_msg::AuxiliaryData, UnixSocketAddr},
+        util::{
+            options::{GetSocketLevelOption, SetSocketLevelOption, SocketOptionSet},
+            MessageHeader, SendRecvFlags, SockShutdownCmd, SocketAddr,
+        },
+        Socket,
+    },
+    prelude::*,
+    process::signal::{PollHandle, Pollable, Pollee},
+    util::{MultiRead, MultiWrite},
+};
+
+pub struct UnixDatagramSocket {
+    // Lock order: `peer` first, `options` second
+    peer: RwMutex<Option<Peer>>,
+    options: RwMutex<OptionSet>,
+
+    receiver: Arc<Receiver>,
+    pollee: Pollee,
+    is_nonblocking: AtomicBool,
+    is_write_shutdown: AtomicBool,
+}
+
+/// The default destination of a connected UNIX datagram socket.
+struct Peer {
+    addr: Option<UnixSocketAddrBound>,
+    receiver: Weak<Receiver>,
+}
+
+impl Peer {
+    fn new(receiver: &Arc<Receiver>) -> Self {
+        Self {
+            addr: receiver.addr(),
+            receiver: Arc::downgrade(receiver),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct OptionSet {
+    socket: SocketOptionSet,
+}
+
+impl OptionSet {
+    fn new() -> Self {
+        Self {
+            socket: SocketOptionSet::new_unix_datagram(),
+        }
+    }
+}
+
+impl UnixDatagramSocket {
+    pub fn new(is_nonblocking: bool) -> Arc<Self> {
+        let pollee = Pollee::new();
+
+        Arc::new(Self {
+            peer: RwMutex::new(None),
+            options: RwMutex::new(OptionSet::new()),
+            receiver: Receiver::new(pollee.clone()),
+            pollee,
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+            is_write_shutdown: AtomicBool::new(false),
+        })
+    }
+
+    pub fn new_pair(is_nonblocking: bool) -> (Arc<Self>, Arc<Self>) {
+        let socket_a = Self::new(is_nonblocking);
+        let socket_b = Self::new(is_nonblocking);
+
+        *socket_a.peer.write() = Some(Peer::new(&socket_b.receiver));
+        *socket_b.peer.write() = Some(Peer::new(&socket_a.receiver));
+
+        (socket_a, socket_b)
+    }
+
+    fn remote_receiver(&self, addr: Option<SocketAddr>) -> Result<Arc<Receiver>> {
+        if let Some(addr) = addr {
+            let remote_addr = UnixSocketAddr::try_from(addr)?.connect()?;
+            return get_receiver(&remote_addr);
+        }
+
+        match self.peer.read().as_ref() {
+            Some(peer) => peer.receiver.upgrade().ok_or_else(|| {
+                Error::with_message(Errno::ECONNREFUSED, "the remote socket has been closed")
+            }),
+            None => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        let mut events = self.receiver.check_io_events();
+
+        let is_read_shutdown = self.receiver.is_read_shutdown();
+        let is_write_shutdown = self.is_write_shutdown.load(Ordering::Relaxed);
+
+        if is_read_shutdown {
+            events |= IoEvents::RDHUP | IoEvents::IN;
+
+            if is_write_shutdown {
+                events |= IoEvents::HUP;
+            }
+        }
+
+        // TODO: Report `IoEvents::OUT` only if the receive queue of the peer is not full.
+        events |= IoEvents::OUT;
+
+        events
+    }
+}
+
+impl Pollable for UnixDatagramSocket {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl SocketPrivate for UnixDatagramSocket {
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) {
+        self.is_nonblocking.store(nonblocking, Ordering::Relaxed);
+    }
+}
+
+impl Socket for UnixDatagramSocket {
+    fn bind(&self, socket_addr: SocketAddr) -> Result<()> {
+        let addr = UnixSocketAddr::try_from(socket_addr)?;
+
+        self.receiver.bind(addr)
+    }
+
+    fn connect(&self, socket_addr: SocketAddr) -> Result<()> {
+        let remote_addr = UnixSocketAddr::try_from(socket_addr)?.connect()?;
+        let receiver = get_receiver(&remote_addr)?;
+
+        *self.peer.write() = Some(Peer::new(&receiver));
+
+        Ok(())
+    }
+
+    fn shutdown(&self, cmd: SockShutdownCmd) -> Result<()> {
+        if cmd.shut_read() {
+            self.receiver.shutdown_read();
+        }
+
+        if cmd.shut_write() {
+            self.is_write_shutdown.store(true, Ordering::Relaxed);
+        }
+
+        self.pollee.invalidate();
+
+        Ok(())
+    }
+
+    fn addr(&self) -> Result<SocketAddr> {
+        Ok(self.receiver.addr().into())
+    }
+
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        match self.peer.read().as_ref() {
+            Some(peer) => Ok(peer.addr.clone().into()),
+            None => return_errno_with_message!(Errno::ENOTCONN, "the socket is not connected"),
+        }
+    }
+
+    fn sendmsg(
+        &self,
+        reader: &mut dyn MultiRead,
+        message_header: MessageHeader,
+        flags: SendRecvFlags,
+    ) -> Result<usize> {
+        // TODO: Deal with flags
+        if !flags.is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let MessageHeader {
+            control_messages,
+            addr,
+        } = message_header;
+
+        if self.is_write_shutdown.load(Ordering::Relaxed) {
+            return_errno_with_message!(Errno::EPIPE, "the socket is shut down for writing");
+        }
+
+        let receiver = self.remote_receiver(addr)?;
+
+        let len = reader.sum_lens();
+        if len > self.options.read().socket.send_buf() as usize {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too large");
+        }
+
+        let mut aux_data = AuxiliaryData::from_control(control_messages)?;
+        if self.receiver.is_pass_cred() || receiver.is_pass_cred() {
+            aux_data.fill_cred();
+        }
+
+        let mut bytes = vec![0u8; len];
+        reader.read(&mut VmWriter::from(bytes.as_mut_slice()))?;
+
+        // TODO: According to the Linux man pages, an unbound socket should be bound to an
+        // autogenerated address if `SO_PASSCRED` is enabled.
+        let mut message = Some(Message::new(bytes, self.receiver.addr(), aux_data));
+
+        if self.is_nonblocking() {
+            receiver.try_push(&mut message)?;
+        } else {
+            receiver.pause_until(|| receiver.try_push(&mut message))?;
+        }
+
+        Ok(len)
+    }
+
+    fn recvmsg(
+        &self,
+        writer: &mut dyn MultiWrite,
+        flags: SendRecvFlags,
+    ) -> Result<(usize, MessageHeader)> {
+        // TODO: Deal with other flags. Only MSG_PEEK is handled here.
+        if !flags.sub(SendRecvFlags::MSG_PEEK).is_all_supported() {
+            warn!("unsupported flags: {:?}", flags);
+        }
+
+        let (received_bytes, src_addr, control_messages) =
+            self.block_on(IoEvents::IN, || self.receiver.try_recv(writer, flags))?;
+
+        let message_header = MessageHeader::new(Some(src_addr.into()), control_messages);
+
+        Ok((received_bytes, message_header))
+    }
+
+    fn get_option(&self, option: &mut dyn SocketOption) -> Result<()> {
+        let options = self.options.read();
+
+        // Deal with socket-level options
+        match options.socket.get_option(option, self) {
+            Err(err) if err.error() == Errno::ENOPROTOOPT => (),
+            res => return res,
+        }
+
+        // TODO: Deal with socket options from other levels
+        warn!("only socket-level options are supported");
+
+        return_errno_with_message!(Errno::ENOPROTOOPT, "the socket option to get is unknown")
+    }
+
+    fn set_option(&self, option: &dyn SocketOption) -> Result<()> {
+        let mut options = self.options.write();
+
+        match options.socket.set_option(option, self) {
+            Ok(_) => Ok(()),
+            Err(err) if err.error() == Errno::ENOPROTOOPT => {
+                // TODO: Deal with socket options from other levels
+                warn!("only socket-level options are supported");
+                return_errno_with_message!(
+                    Errno::ENOPROTOOPT,
+                    "the socket option to set is unknown"
+                )
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl GetSocketLevelOption for UnixDatagramSocket {
+    fn is_listening(&self) -> bool {
+        false
+    }
+}
+
+impl SetSocketLevelOption for UnixDatagramSocket {
+    fn set_pass_cred(&self, pass_cred: bool) {
+        self.receiver.set_pass_cred(pass_cred);
+    }
+}
@@ -3,11 +3,14 @@
 mod addr;
 mod cred;
 mod ctrl_msg;
+mod datagram;
 mod ns;
 mod stream;
 
 pub use addr::UnixSocketAddr;
 pub use cred::CUserCred;
 pub(super) use ctrl_msg::UnixControlMessage;
+pub use datagram::UnixDatagramSocket;
+pub(super) use datagram::UNIX_DATAGRAM_DEFAULT_BUF_SIZE;
 pub use stream::UnixStreamSocket;
 pub(super) use stream::UNIX_STREAM_DEFAULT_BUF_SIZE;
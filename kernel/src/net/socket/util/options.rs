@@ -3,7 +3,8 @@
 use core::ops::RangeInclusive;
 
 use aster_bigtcp::socket::{
-    NeedIfacePoll, TCP_RECV_BUF_LEN, TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
+    NeedIfacePoll, PACKET_RECV_BUF_LEN, PACKET_SEND_BUF_LEN, RAW_RECV_BUF_LEN, RAW_SEND_BUF_LEN,
+    TCP_RECV_BUF_LEN, TCP_SEND_BUF_LEN, UDP_RECV_PAYLOAD_LEN, UDP_SEND_PAYLOAD_LEN,
 };
 
 use super::LingerOption;
@@ -14,7 +15,7 @@ use crate::{
             AcceptConn, KeepAlive, Linger, PassCred, PeerCred, PeerGroups, Priority, RecvBuf,
             RecvBufForce, ReuseAddr, ReusePort, SendBuf, SendBufForce, SocketOption,
         },
-        unix::{CUserCred, UNIX_STREAM_DEFAULT_BUF_SIZE},
+        unix::{CUserCred, UNIX_DATAGRAM_DEFAULT_BUF_SIZE, UNIX_STREAM_DEFAULT_BUF_SIZE},
     },
     prelude::*,
     process::{credentials::capabilities::CapSet, posix_thread::AsPosixThread},
@@ -68,6 +69,24 @@ impl SocketOptionSet {
         }
     }
 
+    /// Returns the default socket level options for raw socket.
+    pub(in crate::net) fn new_raw() -> Self {
+        Self {
+            send_buf: RAW_SEND_BUF_LEN as u32,
+            recv_buf: RAW_RECV_BUF_LEN as u32,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the default socket level options for packet socket.
+    pub(in crate::net) fn new_packet() -> Self {
+        Self {
+            send_buf: PACKET_SEND_BUF_LEN as u32,
+            recv_buf: PACKET_RECV_BUF_LEN as u32,
+            ..Default::default()
+        }
+    }
+
     /// Returns the default socket level options for unix stream socket.
     pub(in crate::net) fn new_unix_stream() -> Self {
         Self {
@@ -77,6 +96,15 @@ impl SocketOptionSet {
         }
     }
 
+    /// Returns the default socket level options for unix datagram socket.
+    pub(in crate::net) fn new_unix_datagram() -> Self {
+        Self {
+            send_buf: UNIX_DATAGRAM_DEFAULT_BUF_SIZE as u32,
+            recv_buf: UNIX_DATAGRAM_DEFAULT_BUF_SIZE as u32,
+            ..Default::default()
+        }
+    }
+
     /// Gets socket-level options.
     ///
     /// Note that the socket error has to be handled separately, because it is automatically
@@ -14,13 +14,13 @@ use super::{
 };
 use crate::{
     cpu::LinuxAbi,
-    current_userspace,
     fs::{
-        file_table::{FdFlags, FileTable},
+        cgroupfs::{self, CgroupNode},
+        file_table::{FdFlags, FileDesc, FileTable},
         thread_info::ThreadFsInfo,
     },
     prelude::*,
-    process::{pid_file::PidFile, posix_thread::allocate_posix_tid},
+    process::{namespace::NsProxy, pid_file::PidFile, posix_thread::allocate_posix_tid},
     sched::Nice,
     thread::{AsThread, Tid},
 };
@@ -91,7 +91,8 @@ pub struct CloneArgs {
     pub tls: u64,
     pub _set_tid: Option<u64>,
     pub _set_tid_size: Option<u64>,
-    pub _cgroup: Option<u64>,
+    /// The file descriptor of the cgroup that the child is placed in (`CLONE_INTO_CGROUP`).
+    pub cgroup: Option<u64>,
 }
 
 impl CloneArgs {
@@ -173,13 +174,40 @@ impl CloneFlags {
             | CloneFlags::CLONE_PARENT_SETTID
             | CloneFlags::CLONE_CHILD_SETTID
             | CloneFlags::CLONE_CHILD_CLEARTID
-            | CloneFlags::CLONE_VFORK;
+            | CloneFlags::CLONE_VFORK
+            | NsProxy::SUPPORTED_NS_FLAGS;
         let unsupported_flags = *self - supported_flags;
         if !unsupported_flags.is_empty() {
             warn!("contains unsupported clone flags: {:?}", unsupported_flags);
         }
         Ok(())
     }
+
+    fn check_invalid_ns_flags(&self) -> Result<()> {
+        // These combinations are not valid, according to the Linux man pages. See
+        // <https://www.man7.org/linux/man-pages/man2/clone.2.html>.
+        if self.contains(CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_FS) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "`CLONE_NEWNS` cannot be used together with `CLONE_FS`"
+            );
+        }
+        if self.contains(CloneFlags::CLONE_NEWIPC | CloneFlags::CLONE_SYSVSEM) {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "`CLONE_NEWIPC` cannot be used together with `CLONE_SYSVSEM`"
+            );
+        }
+        if self.contains(CloneFlags::CLONE_NEWPID)
+            && self.intersects(CloneFlags::CLONE_THREAD | CloneFlags::CLONE_PARENT)
+        {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "`CLONE_NEWPID` cannot be used together with `CLONE_THREAD` or `CLONE_PARENT`"
+            );
+        }
+        Ok(())
+    }
 }
 
 /// Clone a child thread or child process.
@@ -192,13 +220,18 @@ pub fn clone_child(
     clone_args: CloneArgs,
 ) -> Result<Tid> {
     clone_args.flags.check_unsupported_flags()?;
+    clone_args.flags.check_invalid_ns_flags()?;
+
+    // The returned ID is the ID of the child in the PID namespace of the current thread.
+    let pid_ns = ctx.posix_thread.pid_ns();
+
     if clone_args.flags.contains(CloneFlags::CLONE_THREAD) {
         let child_task = clone_child_task(ctx, parent_context, clone_args)?;
         let child_thread = child_task.as_thread().unwrap();
         child_thread.run();
 
         let child_tid = child_thread.as_posix_thread().unwrap().tid();
-        Ok(child_tid)
+        Ok(pid_ns.to_local(child_tid))
     } else {
         let child_process = clone_child_process(ctx, parent_context, clone_args)?;
         if clone_args.flags.contains(CloneFlags::CLONE_VFORK) {
@@ -214,7 +247,7 @@ pub fn clone_child(
         }
 
         let child_pid = child_process.pid();
-        Ok(child_pid)
+        Ok(pid_ns.to_local(child_pid))
     }
 }
 
@@ -241,6 +274,14 @@ fn clone_child_task(
         );
     }
 
+    // All threads in a process must be in the same cgroup.
+    if clone_args.cgroup.is_some() {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "`CLONE_THREAD` cannot be used together with `CLONE_INTO_CGROUP`"
+        );
+    }
+
     let Context {
         process,
         thread_local,
@@ -257,6 +298,18 @@ fn clone_child_task(
     // Clone fs
     let child_fs = clone_fs(&thread_local.borrow_fs(), clone_flags);
 
+    // Clone namespaces
+    let child_ns_proxy = clone_ns_proxy(ctx, &child_fs, clone_flags)?;
+
+    // All threads in a process must be in the same PID namespace.
+    let child_pid_ns = posix_thread.pid_ns().clone();
+    if !Arc::ptr_eq(&child_pid_ns, child_ns_proxy.pid_ns_for_children()) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "`CLONE_THREAD` cannot be used after the PID namespace for children is changed"
+        );
+    }
+
     // Clone FPU context
     let child_fpu_context = thread_local.fpu().clone_context();
 
@@ -274,6 +327,10 @@ fn clone_child_task(
     // Inherit the thread name.
     let thread_name = posix_thread.thread_name().lock().as_ref().cloned();
 
+    // Inherit the seccomp mode and the `no_new_privs` attribute
+    let seccomp_mode = posix_thread.seccomp_mode();
+    let no_new_privs = posix_thread.no_new_privs();
+
     let child_tid = allocate_posix_tid();
     let child_task = {
         let credentials = {
@@ -285,23 +342,40 @@ fn clone_child_task(
             .process(posix_thread.weak_process())
             .thread_name(thread_name)
             .sig_mask(sig_mask)
+            .seccomp_mode(seccomp_mode)
+            .no_new_privs(no_new_privs)
+            .ns_proxy(child_ns_proxy)
+            .pid_ns(child_pid_ns)
             .file_table(child_file_table)
             .fs(child_fs)
             .fpu_context(child_fpu_context);
 
-        // Deal with SETTID/CLEARTID flags
-        clone_parent_settid(child_tid, clone_args.parent_tid, clone_flags)?;
+        // Deal with CLEARTID/SETTID flags
         thread_builder = clone_child_cleartid(thread_builder, clone_args.child_tid, clone_flags);
         thread_builder = clone_child_settid(thread_builder, clone_args.child_tid, clone_flags);
 
         thread_builder.build()
     };
 
-    process
-        .tasks()
-        .lock()
-        .insert(child_task.clone())
-        .map_err(|_| Error::with_message(Errno::EINTR, "the process has exited"))?;
+    // Deal with PARENT_SETTID flag after the child has an ID in our PID namespace
+    clone_parent_settid(ctx, child_tid, clone_args.parent_tid, clone_flags)?;
+
+    // Lock order: cgroup of process -> tasks of process
+    let cgroup = process.cgroup().lock();
+    if let Some(cgroup) = cgroup.as_ref() {
+        cgroup.try_charge_pids(1)?;
+    }
+    if process.tasks().lock().insert(child_task.clone()).is_err() {
+        if let Some(cgroup) = cgroup.as_ref() {
+            cgroup.uncharge_pids(1);
+        }
+        return_errno_with_message!(Errno::EINTR, "the process has exited");
+    }
+    child_task
+        .as_thread()
+        .unwrap()
+        .sched_attr()
+        .set_group(cgroup.as_ref().map(|cgroup| cgroup.fair_group().clone()));
 
     Ok(child_task)
 }
@@ -341,6 +415,10 @@ fn clone_child_process(
     // Clone the filesystem information
     let child_fs = clone_fs(&thread_local.borrow_fs(), clone_flags);
 
+    // Clone the namespaces
+    let child_ns_proxy = clone_ns_proxy(ctx, &child_fs, clone_flags)?;
+    let child_pid_ns = child_ns_proxy.pid_ns_for_children().clone();
+
     // Clone signal dispositions
     let child_sig_dispositions = clone_sighand(process.sig_dispositions(), clone_flags);
 
@@ -353,12 +431,19 @@ fn clone_child_process(
     // Inherit the parent's signal mask
     let child_sig_mask = posix_thread.sig_mask().load(Ordering::Relaxed).into();
 
+    // Inherit the parent's seccomp mode and `no_new_privs` attribute
+    let child_seccomp_mode = posix_thread.seccomp_mode();
+    let child_no_new_privs = posix_thread.no_new_privs();
+
     // Inherit the parent's resource limits
     let child_resource_limits = process.resource_limits().clone();
 
     // Inherit the parent's nice value
     let child_nice = process.nice().load(Ordering::Relaxed);
 
+    // Inherit the parent's cgroup, unless `CLONE_INTO_CGROUP` is specified
+    let child_cgroup = clone_cgroup(ctx, clone_args.cgroup)?;
+
     let child_tid = allocate_posix_tid();
 
     let child = {
@@ -374,18 +459,25 @@ fn clone_child_process(
             PosixThreadBuilder::new(child_tid, child_user_ctx, credentials)
                 .thread_name(Some(child_thread_name))
                 .sig_mask(child_sig_mask)
+                .seccomp_mode(child_seccomp_mode)
+                .no_new_privs(child_no_new_privs)
+                .ns_proxy(child_ns_proxy)
+                .pid_ns(child_pid_ns)
                 .file_table(child_file_table)
                 .fs(child_fs)
                 .fpu_context(child_fpu_context)
         };
 
-        // Deal with SETTID/CLEARTID flags
-        clone_parent_settid(child_tid, clone_args.parent_tid, clone_flags)?;
+        // Deal with CLEARTID/SETTID flags
         child_thread_builder =
             clone_child_cleartid(child_thread_builder, clone_args.child_tid, clone_flags);
         child_thread_builder =
             clone_child_settid(child_thread_builder, clone_args.child_tid, clone_flags);
 
+        if let Some(cgroup) = child_cgroup.as_ref() {
+            cgroup.try_charge_pids(1)?;
+        }
+
         create_child_process(
             child_tid,
             posix_thread.weak_process(),
@@ -394,15 +486,26 @@ fn clone_child_process(
             child_resource_limits,
             child_nice,
             child_sig_dispositions,
+            child_cgroup,
             child_thread_builder,
         )
     };
 
-    clone_pidfd(ctx, &child, clone_flags, clone_args.pidfd)?;
+    // Deal with PARENT_SETTID flag after the child has an ID in our PID namespace
+    let res = clone_parent_settid(ctx, child_tid, clone_args.parent_tid, clone_flags)
+        .and_then(|_| clone_pidfd(ctx, &child, clone_flags, clone_args.pidfd));
+    if let Err(err) = res {
+        // The child will never run, so its thread should no longer be counted.
+        if let Some(cgroup) = child.cgroup().lock().as_ref() {
+            cgroup.uncharge_pids(1);
+        }
+        return Err(err);
+    }
 
     if let Some(sig) = clone_args.exit_signal {
         child.set_exit_signal(sig);
     };
+    child.set_dumpable(process.dumpable());
 
     // Sets parent process and group for child process.
     set_parent_and_group(process, &child);
@@ -442,6 +545,7 @@ fn clone_child_settid(
 }
 
 fn clone_parent_settid(
+    ctx: &Context,
     child_tid: Tid,
     parent_tidptr: Option<Vaddr>,
     clone_flags: CloneFlags,
@@ -449,7 +553,8 @@ fn clone_parent_settid(
     if let Some(addr) =
         parent_tidptr.filter(|_| clone_flags.contains(CloneFlags::CLONE_PARENT_SETTID))
     {
-        current_userspace!().write_val(addr, &child_tid)?;
+        let child_tid = ctx.posix_thread.pid_ns().to_local(child_tid);
+        ctx.user_space().write_val(addr, &child_tid)?;
     }
     Ok(())
 }
@@ -517,6 +622,18 @@ fn clone_files(parent_file_table: &RwArc<FileTable>, clone_flags: CloneFlags) ->
     }
 }
 
+fn clone_ns_proxy(
+    ctx: &Context,
+    child_fs: &ThreadFsInfo,
+    clone_flags: CloneFlags,
+) -> Result<Arc<NsProxy>> {
+    // If new namespaces are requested, the child has its own copy. Otherwise, the child shares
+    // the namespaces with the parent.
+    ctx.posix_thread
+        .ns_proxy()
+        .new_copy(clone_flags, child_fs, ctx)
+}
+
 fn clone_sighand(
     parent_sig_dispositions: &Arc<Mutex<SigDispositions>>,
     clone_flags: CloneFlags,
@@ -529,6 +646,22 @@ fn clone_sighand(
     }
 }
 
+fn clone_cgroup(ctx: &Context, cgroup_fd: Option<u64>) -> Result<Option<Arc<CgroupNode>>> {
+    let Some(cgroup_fd) = cgroup_fd else {
+        return Ok(ctx.process.cgroup().lock().clone());
+    };
+
+    let cgroup_fd = FileDesc::try_from(cgroup_fd)
+        .map_err(|_| Error::with_message(Errno::EBADF, "the cgroup file descriptor is invalid"))?;
+    let file = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let file_table_locked = file_table.unwrap().read();
+        file_table_locked.get_file(cgroup_fd)?.clone()
+    };
+
+    cgroupfs::cgroup_of_file(file.as_ref())
+}
+
 fn clone_sysvsem(clone_flags: CloneFlags) -> Result<()> {
     if clone_flags.contains(CloneFlags::CLONE_SYSVSEM) {
         warn!("CLONE_SYSVSEM is not supported now");
@@ -577,8 +710,11 @@ fn create_child_process(
     resource_limits: ResourceLimits,
     nice: Nice,
     sig_dispositions: Arc<Mutex<SigDispositions>>,
+    cgroup: Option<Arc<CgroupNode>>,
     thread_builder: PosixThreadBuilder,
 ) -> Arc<Process> {
+    let fair_group = cgroup.as_ref().map(|cgroup| cgroup.fair_group().clone());
+
     let child_proc = Process::new(
         pid,
         parent,
@@ -587,9 +723,15 @@ fn create_child_process(
         resource_limits,
         nice,
         sig_dispositions,
+        cgroup,
     );
 
     let child_task = thread_builder.process(Arc::downgrade(&child_proc)).build();
+    child_task
+        .as_thread()
+        .unwrap()
+        .sched_attr()
+        .set_group(fair_group);
     child_proc.tasks().lock().insert(child_task).unwrap();
 
     child_proc
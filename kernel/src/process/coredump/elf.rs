@@ -0,0 +1,337 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The ELF format of core files.
+//!
+//! A core file consists of the ELF header, the program headers, a `PT_NOTE` segment that
+//! describes the process and its threads, and `PT_LOAD` segments that contain the memory of the
+//! process.
+//!
+//! Reference: <https://www.man7.org/linux/man-pages/man5/elf.5.html>
+
+use core::ops::Range;
+
+use align_ext::AlignExt;
+use aster_rights::Full;
+use ostd::cpu::context::UserContext;
+
+use super::note::{self, FileMapping};
+use crate::{
+    fs::file_handle::FileLike,
+    prelude::*,
+    process::signal::sig_num::SigNum,
+    vm::{
+        perms::VmPerms,
+        vmar::{vm_mapping::VmMapping, Vmar},
+    },
+};
+
+/// Writes the core file of the current process.
+pub(super) fn write_core_file(
+    file: &dyn FileLike,
+    limit: usize,
+    sig_num: SigNum,
+    user_ctx: &UserContext,
+    ctx: &Context,
+) -> Result<()> {
+    let user_space = ctx.user_space();
+    let vmar = user_space.root_vmar();
+
+    let (mut segments, file_mappings) = collect_segments(vmar);
+    let mut page = vec![0u8; PAGE_SIZE].into_boxed_slice();
+    for segment in segments.iter_mut() {
+        segment.resolve_elf_header(vmar, &mut page);
+    }
+
+    let notes = note::build_notes(sig_num, user_ctx, &file_mappings, ctx);
+
+    let phnum = segments.len() + 1;
+    let notes_offset = size_of::<Elf64Ehdr>() + size_of::<Elf64Phdr>() * phnum;
+    let data_offset = (notes_offset + notes.len()).align_up(PAGE_SIZE);
+
+    let mut writer = CoreFileWriter::new(file, limit);
+
+    writer.write(Elf64Ehdr::new_core(phnum)?.as_bytes())?;
+    writer.write(Elf64Phdr::new_note(notes_offset, notes.len()).as_bytes())?;
+    let mut segment_offset = data_offset;
+    for segment in segments.iter() {
+        writer.write(Elf64Phdr::new_load(segment, segment_offset).as_bytes())?;
+        segment_offset += segment.dump_size;
+    }
+    writer.write(&notes)?;
+
+    writer.skip(data_offset - writer.pos())?;
+    for segment in segments.iter() {
+        let dump_range = segment.range.start..segment.range.start + segment.dump_size;
+        for page_addr in dump_range.step_by(PAGE_SIZE) {
+            // Pages that have never been touched are left as holes, which are read as zeros.
+            match vmar.read_dump_page(page_addr, &mut page) {
+                Ok(true) => writer.write(&page)?,
+                Ok(false) | Err(_) => writer.skip(PAGE_SIZE)?,
+            }
+        }
+    }
+
+    writer.finish()
+}
+
+/// Collects the memory segments to dump and the file mappings to report.
+fn collect_segments(vmar: &Vmar<Full>) -> (Vec<Segment>, Vec<FileMapping>) {
+    let query_guard = vmar.query(vmar.base()..vmar.base() + vmar.size());
+
+    let mut segments = Vec::new();
+    let mut file_mappings = Vec::new();
+    for vm_mapping in query_guard.iter() {
+        segments.push(Segment::new(vm_mapping));
+
+        if let Some(path) = vm_mapping.path() {
+            file_mappings.push(FileMapping {
+                range: vm_mapping.map_to_addr()..vm_mapping.map_end(),
+                offset: vm_mapping.vmo_offset().unwrap_or(0),
+                path: path.abs_path(),
+            });
+        }
+    }
+
+    (segments, file_mappings)
+}
+
+/// A memory segment, which is dumped as a `PT_LOAD` segment.
+struct Segment {
+    range: Range<Vaddr>,
+    perms: VmPerms,
+    /// The number of bytes to dump, starting from the beginning of the segment.
+    dump_size: usize,
+    /// Whether only the ELF header should be dumped.
+    ///
+    /// The mapping may contain an ELF header, so the first page should be dumped if it starts
+    /// with the ELF magic number. This helps debuggers to identify the mapped ELF files.
+    maybe_elf_header: bool,
+}
+
+impl Segment {
+    /// Creates a segment from the mapping.
+    ///
+    /// The segment is dumped following the default `coredump_filter` in Linux. Private and
+    /// shared anonymous mappings are dumped, while file-backed mappings are not dumped unless
+    /// they are private and writable (and thus may have been modified).
+    fn new(vm_mapping: &VmMapping) -> Self {
+        let range = vm_mapping.map_to_addr()..vm_mapping.map_end();
+        let perms = vm_mapping.perms();
+
+        let mut maybe_elf_header = false;
+        let dump_size = if vm_mapping.dont_dump() || !perms.contains(VmPerms::READ) {
+            0
+        } else if vm_mapping.inode().is_none() {
+            range.len()
+        } else if vm_mapping.is_shared() {
+            0
+        } else if perms.contains(VmPerms::WRITE) {
+            range.len()
+        } else {
+            maybe_elf_header = vm_mapping.vmo_offset() == Some(0);
+            0
+        };
+
+        Self {
+            range,
+            perms,
+            dump_size,
+            maybe_elf_header,
+        }
+    }
+
+    /// Determines whether the ELF header should be dumped.
+    fn resolve_elf_header(&mut self, vmar: &Vmar<Full>, page: &mut [u8]) {
+        const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+        if !self.maybe_elf_header {
+            return;
+        }
+        self.maybe_elf_header = false;
+
+        if let Ok(true) = vmar.read_dump_page(self.range.start, page)
+            && page.starts_with(ELF_MAGIC)
+        {
+            self.dump_size = PAGE_SIZE;
+        }
+    }
+}
+
+/// A writer that writes the core file sequentially, within the size limit.
+///
+/// Like Linux, the core file is truncated if it exceeds the size limit, i.e., the bytes beyond the
+/// limit are silently discarded.
+struct CoreFileWriter<'a> {
+    file: &'a dyn FileLike,
+    pos: usize,
+    limit: usize,
+}
+
+impl<'a> CoreFileWriter<'a> {
+    fn new(file: &'a dyn FileLike, limit: usize) -> Self {
+        Self {
+            file,
+            pos: 0,
+            limit,
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<()> {
+        let buf = &buf[..buf.len().min(self.limit.saturating_sub(self.pos))];
+
+        let mut written_len = 0;
+        while written_len < buf.len() {
+            let len = self
+                .file
+                .write_bytes_at(self.pos + written_len, &buf[written_len..])?;
+            if len == 0 {
+                return_errno_with_message!(Errno::EIO, "the core file cannot be written");
+            }
+            written_len += len;
+        }
+        self.pos += written_len;
+
+        Ok(())
+    }
+
+    /// Skips the bytes, which leaves a hole in the core file.
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.pos = self.pos.saturating_add(len).min(self.limit);
+
+        Ok(())
+    }
+
+    /// Finishes writing the core file.
+    ///
+    /// The file is extended to include the trailing hole, if any.
+    fn finish(self) -> Result<()> {
+        self.file.resize(self.pos)
+    }
+}
+
+/// The ELF header of 64-bit ELF files.
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct Elf64Ehdr {
+    e_ident: [u8; 16],
+    e_type: u16,
+    e_machine: u16,
+    e_version: u32,
+    e_entry: u64,
+    e_phoff: u64,
+    e_shoff: u64,
+    e_flags: u32,
+    e_ehsize: u16,
+    e_phentsize: u16,
+    e_phnum: u16,
+    e_shentsize: u16,
+    e_shnum: u16,
+    e_shstrndx: u16,
+}
+
+const ET_CORE: u16 = 4;
+const EV_CURRENT: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+#[cfg(target_arch = "x86_64")]
+const ELF_MACHINE: u16 = 62; // EM_X86_64
+#[cfg(target_arch = "riscv64")]
+const ELF_MACHINE: u16 = 243; // EM_RISCV
+#[cfg(target_arch = "loongarch64")]
+const ELF_MACHINE: u16 = 258; // EM_LOONGARCH
+
+impl Elf64Ehdr {
+    fn new_core(phnum: usize) -> Result<Self> {
+        let Ok(e_phnum) = u16::try_from(phnum) else {
+            return_errno_with_message!(Errno::EFBIG, "there are too many mappings to dump");
+        };
+
+        let mut e_ident = [0u8; 16];
+        e_ident[..4].copy_from_slice(b"\x7fELF");
+        e_ident[4] = ELFCLASS64;
+        e_ident[5] = ELFDATA2LSB;
+        e_ident[6] = EV_CURRENT;
+
+        Ok(Self {
+            e_ident,
+            e_type: ET_CORE,
+            e_machine: ELF_MACHINE,
+            e_version: EV_CURRENT as u32,
+            e_entry: 0,
+            e_phoff: size_of::<Self>() as u64,
+            e_shoff: 0,
+            e_flags: 0,
+            e_ehsize: size_of::<Self>() as u16,
+            e_phentsize: size_of::<Elf64Phdr>() as u16,
+            e_phnum,
+            e_shentsize: 0,
+            e_shnum: 0,
+            e_shstrndx: 0,
+        })
+    }
+}
+
+/// The program header of 64-bit ELF files.
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct Elf64Phdr {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+impl Elf64Phdr {
+    fn new_note(offset: usize, size: usize) -> Self {
+        Self {
+            p_type: PT_NOTE,
+            p_flags: 0,
+            p_offset: offset as u64,
+            p_vaddr: 0,
+            p_paddr: 0,
+            p_filesz: size as u64,
+            p_memsz: 0,
+            p_align: 0,
+        }
+    }
+
+    fn new_load(segment: &Segment, offset: usize) -> Self {
+        let mut p_flags = 0;
+        if segment.perms.contains(VmPerms::READ) {
+            p_flags |= PF_R;
+        }
+        if segment.perms.contains(VmPerms::WRITE) {
+            p_flags |= PF_W;
+        }
+        if segment.perms.contains(VmPerms::EXEC) {
+            p_flags |= PF_X;
+        }
+
+        Self {
+            p_type: PT_LOAD,
+            p_flags,
+            p_offset: offset as u64,
+            p_vaddr: segment.range.start as u64,
+            p_paddr: 0,
+            p_filesz: segment.dump_size as u64,
+            p_memsz: segment.range.len() as u64,
+            p_align: PAGE_SIZE as u64,
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Core dumps.
+//!
+//! When a process is terminated by a signal whose default action is to dump core, an ELF core
+//! file is generated so that the process can be inspected with debuggers (e.g., GDB) later.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man5/core.5.html>
+
+use core::sync::atomic::AtomicU64;
+
+use aster_rights::WriteOp;
+use atomic_integer_wrapper::define_atomic_version_of_integer_like_type;
+use ostd::cpu::context::UserContext;
+
+pub use self::pattern::{core_pattern, set_core_pattern, MAX_CORE_PATTERN_LEN};
+use super::{signal::sig_num::SigNum, Credentials, ResourceType};
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        fs_resolver::{FsPath, AT_FDCWD},
+        utils::{AccessMode, CreationFlags, InodeType},
+    },
+    prelude::*,
+};
+
+mod elf;
+mod note;
+mod pattern;
+
+/// Whether a core dump can be generated for a process.
+///
+/// This is the value returned by `prctl(PR_GET_DUMPABLE)`.
+#[repr(u64)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
+pub enum Dumpable {
+    Disable = 0, /* No setuid dumping */
+    User = 1,    /* Dump as user of process */
+    Root = 2,    /* Dump as root */
+}
+
+define_atomic_version_of_integer_like_type!(Dumpable, try_from = true, {
+    #[derive(Debug)]
+    pub struct AtomicDumpable(AtomicU64);
+});
+
+impl From<Dumpable> for u64 {
+    fn from(value: Dumpable) -> Self {
+        value as _
+    }
+}
+
+/// Changes the credentials of the current thread with `f`.
+///
+/// Like Linux, the current process is no longer dumpable if its effective or file system user or
+/// group IDs are changed, since its memory may contain data that the new IDs cannot access.
+pub fn change_credentials<T>(
+    ctx: &Context,
+    f: impl FnOnce(&Credentials<WriteOp>) -> Result<T>,
+) -> Result<T> {
+    let credentials = ctx.posix_thread.credentials_mut();
+    let dump_ids = |credentials: &Credentials<WriteOp>| {
+        (
+            credentials.euid(),
+            credentials.fsuid(),
+            credentials.egid(),
+            credentials.fsgid(),
+        )
+    };
+
+    let old_ids = dump_ids(&credentials);
+    let res = f(&credentials);
+    if dump_ids(&credentials) != old_ids {
+        ctx.process.set_dumpable(Dumpable::Disable);
+    }
+
+    res
+}
+
+/// Generates a core dump for the current process, which is being terminated by `sig_num`.
+///
+/// Returns whether the core dump has been generated successfully.
+pub(super) fn do_coredump(sig_num: SigNum, user_ctx: &UserContext, ctx: &Context) -> bool {
+    let process = ctx.process;
+
+    if process.dumpable() == Dumpable::Disable {
+        return false;
+    }
+
+    // Linux does not generate core dumps that cannot even hold the ELF headers.
+    let limit = process
+        .resource_limits()
+        .get_rlimit(ResourceType::RLIMIT_CORE)
+        .get_cur();
+    if limit < PAGE_SIZE as u64 {
+        return false;
+    }
+
+    // Another thread is already terminating the process (and may be dumping it).
+    if process.tasks().lock().has_exited_group() {
+        return false;
+    }
+
+    let pattern = core_pattern();
+    if pattern.starts_with('|') {
+        warn!("piping core dumps to a program is not supported");
+        return false;
+    }
+    let file_name = pattern::expand(&pattern, sig_num, ctx);
+    if file_name.is_empty() {
+        return false;
+    }
+
+    // TODO: Other threads in the process should be stopped before the memory is dumped, and
+    // their registers should be dumped in separate `NT_PRSTATUS` notes.
+    let res = open_core_file(&file_name, ctx).and_then(|file| {
+        elf::write_core_file(file.as_ref(), limit as usize, sig_num, user_ctx, ctx)
+    });
+    if let Err(err) = res {
+        warn!("failed to dump core to {:?}: {:?}", file_name, err);
+        return false;
+    }
+
+    true
+}
+
+/// Creates the core file, whose path is relative to the current working directory.
+fn open_core_file(file_name: &str, ctx: &Context) -> Result<Arc<dyn FileLike>> {
+    let fs_ref = ctx.thread_local.borrow_fs();
+    let fs_resolver = fs_ref.resolver().read();
+
+    // Like Linux, remove the old core file first so that the new one will be created with the
+    // correct owner and permissions.
+    let fs_path = FsPath::new(AT_FDCWD, file_name)?;
+    if let Ok((dir_path, name)) = fs_resolver.lookup_dir_and_base_name(&fs_path) {
+        let _ = dir_path.unlink(&name);
+    }
+
+    let flags = AccessMode::O_WRONLY as u32
+        | (CreationFlags::O_CREAT | CreationFlags::O_EXCL | CreationFlags::O_NOFOLLOW).bits();
+    let mode = 0o600 & !fs_ref.umask().read().get();
+    let inode_handle = fs_resolver.open(&fs_path, flags, mode)?;
+
+    if inode_handle.path().type_() != InodeType::File {
+        return_errno_with_message!(Errno::EINVAL, "the core file is not a regular file");
+    }
+
+    Ok(Arc::new(inode_handle))
+}
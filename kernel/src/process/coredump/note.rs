@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The notes in the `PT_NOTE` segment of core files.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/linux/elfcore.h>
+
+use core::{ops::Range, sync::atomic::Ordering};
+
+use align_ext::AlignExt;
+use ostd::cpu::context::UserContext;
+
+use crate::{
+    prelude::*,
+    process::{posix_thread::MAX_THREAD_NAME_LEN, signal::sig_num::SigNum},
+    time::timeval_t,
+};
+
+/// A file mapping, which is reported in the `NT_FILE` note.
+pub(super) struct FileMapping {
+    pub(super) range: Range<Vaddr>,
+    /// The offset in the file.
+    pub(super) offset: usize,
+    /// The absolute path of the file.
+    pub(super) path: String,
+}
+
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x46494c45;
+
+/// Builds the notes of the current process.
+pub(super) fn build_notes(
+    sig_num: SigNum,
+    user_ctx: &UserContext,
+    file_mappings: &[FileMapping],
+    ctx: &Context,
+) -> Vec<u8> {
+    let mut notes = Vec::new();
+
+    push_note(
+        &mut notes,
+        NT_PRSTATUS,
+        ElfPrStatus::new(sig_num, user_ctx, ctx).as_bytes(),
+    );
+    push_note(&mut notes, NT_PRPSINFO, ElfPrPsInfo::new(ctx).as_bytes());
+    // The auxiliary vector is not available if the init stack has been corrupted.
+    if let Ok(auxv) = ctx.process.init_stack_reader().auxv() {
+        let auxv_desc: Vec<u8> = auxv
+            .iter()
+            .flat_map(|(key, value)| [*key, *value])
+            .flat_map(u64::to_ne_bytes)
+            .collect();
+        push_note(&mut notes, NT_AUXV, &auxv_desc);
+    }
+    push_note(&mut notes, NT_FILE, &build_file_desc(file_mappings));
+
+    notes
+}
+
+/// The header of a note.
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct Elf64Nhdr {
+    n_namesz: u32,
+    n_descsz: u32,
+    n_type: u32,
+}
+
+fn push_note(notes: &mut Vec<u8>, note_type: u32, desc: &[u8]) {
+    const NOTE_NAME: &[u8] = b"CORE\0";
+    const NOTE_ALIGN: usize = 4;
+
+    let header = Elf64Nhdr {
+        n_namesz: NOTE_NAME.len() as u32,
+        n_descsz: desc.len() as u32,
+        n_type: note_type,
+    };
+    notes.extend_from_slice(header.as_bytes());
+
+    notes.extend_from_slice(NOTE_NAME);
+    notes.resize(notes.len().align_up(NOTE_ALIGN), 0);
+
+    notes.extend_from_slice(desc);
+    notes.resize(notes.len().align_up(NOTE_ALIGN), 0);
+}
+
+/// Builds the descriptor of the `NT_FILE` note.
+///
+/// The descriptor contains the number of file mappings, the page size, the address ranges and
+/// the file offsets (in pages) of the mappings, and finally the null-terminated file names.
+fn build_file_desc(file_mappings: &[FileMapping]) -> Vec<u8> {
+    let mut desc = Vec::new();
+
+    desc.extend_from_slice(&(file_mappings.len() as u64).to_ne_bytes());
+    desc.extend_from_slice(&(PAGE_SIZE as u64).to_ne_bytes());
+    for file_mapping in file_mappings.iter() {
+        desc.extend_from_slice(&(file_mapping.range.start as u64).to_ne_bytes());
+        desc.extend_from_slice(&(file_mapping.range.end as u64).to_ne_bytes());
+        desc.extend_from_slice(&((file_mapping.offset / PAGE_SIZE) as u64).to_ne_bytes());
+    }
+    for file_mapping in file_mappings.iter() {
+        desc.extend_from_slice(file_mapping.path.as_bytes());
+        desc.push(0);
+    }
+
+    desc
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        use crate::arch::cpu::UserRegs as ElfGregs;
+
+        fn elf_gregs(user_ctx: &UserContext) -> ElfGregs {
+            ElfGregs::from_user_ctx(user_ctx)
+        }
+    } else if #[cfg(target_arch = "riscv64")] {
+        type ElfGregs = [u64; 32];
+
+        // TODO: Dump the user registers on RISC-V.
+        fn elf_gregs(_user_ctx: &UserContext) -> ElfGregs {
+            [0; 32]
+        }
+    } else if #[cfg(target_arch = "loongarch64")] {
+        type ElfGregs = [u64; 45];
+
+        // TODO: Dump the user registers on LoongArch.
+        fn elf_gregs(_user_ctx: &UserContext) -> ElfGregs {
+            [0; 45]
+        }
+    }
+}
+
+/// The status of a thread, which is reported in the `NT_PRSTATUS` note.
+///
+/// This has the same layout as `struct elf_prstatus` in Linux.
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct ElfPrStatus {
+    si_signo: i32,
+    si_code: i32,
+    si_errno: i32,
+    cursig: u16,
+    _pad0: u16,
+    sigpend: u64,
+    sighold: u64,
+    pid: u32,
+    ppid: u32,
+    pgrp: u32,
+    sid: u32,
+    utime: timeval_t,
+    stime: timeval_t,
+    cutime: timeval_t,
+    cstime: timeval_t,
+    reg: ElfGregs,
+    fpvalid: i32,
+    _pad1: u32,
+}
+
+impl ElfPrStatus {
+    fn new(sig_num: SigNum, user_ctx: &UserContext, ctx: &Context) -> Self {
+        let process = ctx.process;
+        let posix_thread = ctx.posix_thread;
+        let pid_ns = posix_thread.pid_ns();
+        let prof_clock = posix_thread.prof_clock();
+
+        let mut prstatus = Self::new_zeroed();
+        prstatus.si_signo = sig_num.as_u8() as i32;
+        prstatus.cursig = sig_num.as_u8() as u16;
+        prstatus.sigpend = u64::from(posix_thread.sig_pending());
+        prstatus.sighold = u64::from(posix_thread.sig_mask().load(Ordering::Relaxed));
+        prstatus.pid = pid_ns.to_local(posix_thread.tid());
+        prstatus.ppid = pid_ns.to_local(process.parent().pid());
+        prstatus.pgrp = pid_ns.to_local(process.pgid());
+        prstatus.sid = pid_ns.to_local(process.sid());
+        prstatus.utime = prof_clock.user_clock().read_time().into();
+        prstatus.stime = prof_clock.kernel_clock().read_time().into();
+        // TODO: Report the CPU time of the waited-for children.
+        prstatus.reg = elf_gregs(user_ctx);
+        // TODO: Dump the FPU registers in the `NT_PRFPREG` note.
+
+        prstatus
+    }
+}
+
+/// The information of a process, which is reported in the `NT_PRPSINFO` note.
+///
+/// This has the same layout as `struct elf_prpsinfo` in Linux.
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct ElfPrPsInfo {
+    state: u8,
+    sname: u8,
+    zomb: u8,
+    nice: i8,
+    _pad0: u32,
+    flag: u64,
+    uid: u32,
+    gid: u32,
+    pid: u32,
+    ppid: u32,
+    pgrp: u32,
+    sid: u32,
+    fname: [u8; MAX_THREAD_NAME_LEN],
+    psargs: [u8; ELF_PRARGSZ],
+}
+
+/// The maximum length of the arguments in `ElfPrPsInfo`, including the trailing null byte.
+const ELF_PRARGSZ: usize = 80;
+
+impl ElfPrPsInfo {
+    fn new(ctx: &Context) -> Self {
+        let process = ctx.process;
+        let posix_thread = ctx.posix_thread;
+        let pid_ns = posix_thread.pid_ns();
+        let credentials = posix_thread.credentials();
+
+        let mut prpsinfo = Self::new_zeroed();
+        // The dumping thread is running.
+        prpsinfo.sname = b'R';
+        prpsinfo.nice = process.nice().load(Ordering::Relaxed).value().get();
+        prpsinfo.uid = u32::from(credentials.ruid());
+        prpsinfo.gid = u32::from(credentials.rgid());
+        prpsinfo.pid = pid_ns.to_local(process.pid());
+        prpsinfo.ppid = pid_ns.to_local(process.parent().pid());
+        prpsinfo.pgrp = pid_ns.to_local(process.pgid());
+        prpsinfo.sid = pid_ns.to_local(process.sid());
+
+        if let Some(thread_name) = &*posix_thread.thread_name().lock()
+            && let Some(name) = thread_name.name()
+        {
+            let name = name.to_bytes();
+            let len = name.len().min(MAX_THREAD_NAME_LEN - 1);
+            prpsinfo.fname[..len].copy_from_slice(&name[..len]);
+        }
+
+        // The arguments are separated by spaces and truncated to fit in the buffer.
+        let argv = process.init_stack_reader().argv().unwrap_or_default();
+        let args = argv
+            .iter()
+            .map(|arg| arg.to_bytes())
+            .collect::<Vec<_>>()
+            .join(&b' ');
+        let len = args.len().min(ELF_PRARGSZ - 1);
+        prpsinfo.psargs[..len].copy_from_slice(&args[..len]);
+
+        prpsinfo
+    }
+}
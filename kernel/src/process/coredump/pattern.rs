@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use alloc::borrow::Cow;
+use core::fmt::Write;
+
+use crate::{
+    prelude::*,
+    process::{signal::sig_num::SigNum, ResourceType},
+    time::clocks::RealTimeClock,
+};
+
+/// The maximum length of the core pattern in bytes.
+///
+/// Linux limits the pattern to `CORENAME_MAX_SIZE` (128) bytes, including the trailing null byte.
+pub const MAX_CORE_PATTERN_LEN: usize = 127;
+
+/// The pattern to name the core files, which is exposed at `/proc/sys/kernel/core_pattern`.
+static CORE_PATTERN: RwLock<Cow<'static, str>> = RwLock::new(Cow::Borrowed("core"));
+
+/// Returns the pattern to name the core files.
+pub fn core_pattern() -> String {
+    CORE_PATTERN.read().to_string()
+}
+
+/// Sets the pattern to name the core files.
+///
+/// The pattern will be truncated if it is longer than [`MAX_CORE_PATTERN_LEN`] bytes.
+pub fn set_core_pattern(pattern: &str) {
+    let mut len = pattern.len().min(MAX_CORE_PATTERN_LEN);
+    while !pattern.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    *CORE_PATTERN.write() = Cow::Owned(pattern[..len].to_string());
+}
+
+/// Expands the `%` specifiers in the core pattern to get the name of the core file.
+///
+/// Unknown specifiers are dropped, like Linux does.
+pub(super) fn expand(pattern: &str, sig_num: SigNum, ctx: &Context) -> String {
+    let process = ctx.process;
+    let posix_thread = ctx.posix_thread;
+    let pid_ns = posix_thread.pid_ns();
+
+    let mut file_name = String::new();
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            file_name.push(ch);
+            continue;
+        }
+
+        let Some(specifier) = chars.next() else {
+            break;
+        };
+        match specifier {
+            '%' => file_name.push('%'),
+            'p' => write_num(&mut file_name, pid_ns.to_local(process.pid())),
+            'P' => write_num(&mut file_name, process.pid()),
+            'i' => write_num(&mut file_name, pid_ns.to_local(posix_thread.tid())),
+            'I' => write_num(&mut file_name, posix_thread.tid()),
+            'u' => write_num(&mut file_name, u32::from(posix_thread.credentials().ruid())),
+            'g' => write_num(&mut file_name, u32::from(posix_thread.credentials().rgid())),
+            'd' => write_num(&mut file_name, process.dumpable() as u64),
+            's' => write_num(&mut file_name, sig_num.as_u8()),
+            't' => write_num(&mut file_name, RealTimeClock::get().read_time().as_secs()),
+            'c' => write_num(
+                &mut file_name,
+                process
+                    .resource_limits()
+                    .get_rlimit(ResourceType::RLIMIT_CORE)
+                    .get_cur(),
+            ),
+            'h' => {
+                let uts_name = posix_thread.ns_proxy().uts_ns().uts_name();
+                push_escaped(
+                    &mut file_name,
+                    &String::from_utf8_lossy(uts_name.nodename()),
+                );
+            }
+            'e' => {
+                let name = match &*posix_thread.thread_name().lock() {
+                    Some(thread_name) => thread_name.as_string().unwrap_or_default(),
+                    None => String::new(),
+                };
+                push_escaped(&mut file_name, &name);
+            }
+            'E' => push_escaped(&mut file_name, &process.executable_path()),
+            _ => (),
+        }
+    }
+
+    file_name
+}
+
+fn write_num(file_name: &mut String, num: impl core::fmt::Display) {
+    // Writing to a `String` never fails.
+    let _ = write!(file_name, "{}", num);
+}
+
+/// Pushes the name with `/` replaced by `!`, so that the name does not create subdirectories.
+fn push_escaped(file_name: &mut String, name: &str) {
+    file_name.extend(name.chars().map(|ch| if ch == '/' { '!' } else { ch }));
+}
@@ -2,8 +2,15 @@
 
 use core::sync::atomic::Ordering;
 
-use super::{process_table, Pid, Process};
-use crate::{events::IoEvents, prelude::*, process::signal::signals::kernel::KernelSignal};
+use super::{
+    posix_thread::{exit_ptrace_tracer, AsPosixThread},
+    process_table, Pid, Process,
+};
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::signal::signals::{child::ChildSignal, kernel::KernelSignal},
+};
 
 /// Exits the current POSIX process.
 ///
@@ -21,6 +28,8 @@ pub(super) fn exit_process(current_process: &Process) {
 
     current_process.pidfile_pollee.notify(IoEvents::IN);
 
+    exit_ptrace_tracer(current_process);
+
     send_parent_death_signal(current_process);
 
     move_children_to_reaper_process(current_process);
@@ -121,7 +130,20 @@ fn send_child_death_signal(current_process: &Process) {
         return;
     };
 
-    if let Some(signal) = current_process.exit_signal().map(KernelSignal::new) {
+    if let Some(signum) = current_process.exit_signal() {
+        // The PID of the child is seen from the PID namespace of the parent.
+        let pid = {
+            let main_thread = parent.main_thread();
+            let pid_ns = main_thread.as_posix_thread().unwrap().pid_ns();
+            pid_ns.to_local(current_process.pid())
+        };
+        let uid = {
+            let main_thread = current_process.main_thread();
+            main_thread.as_posix_thread().unwrap().credentials().ruid()
+        };
+        let exit_code = current_process.status().exit_code();
+
+        let signal = ChildSignal::new_exited(pid, uid, exit_code).with_num(signum);
         parent.enqueue_signal(signal);
     };
     parent.children_wait_queue().wake_all();
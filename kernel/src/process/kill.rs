@@ -1,14 +1,14 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::{
-    posix_thread::{thread_table, AsPosixThread},
+    posix_thread::AsPosixThread,
     process_table,
     signal::{
         constants::SIGCONT,
         sig_num::SigNum,
         signals::{user::UserSignal, Signal},
     },
-    Pgid, Pid, Process, Sid, Uid,
+    Pgid, Pid, PidFile, Process, Sid, Uid,
 };
 use crate::{prelude::*, thread::Tid};
 
@@ -20,6 +20,12 @@ use crate::{prelude::*, thread::Tid};
 /// If `signal` is `None`, this method will only check permission without sending
 /// any signal.
 pub fn kill(pid: Pid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
+    // The process ID is in the PID namespace of the current thread.
+    let pid =
+        ctx.posix_thread.pid_ns().to_global(pid).ok_or_else(|| {
+            Error::with_message(Errno::ESRCH, "the target process does not exist")
+        })?;
+
     // Fast path: If the signal is sent to self, we can skip most check.
     if pid == ctx.process.pid() {
         let Some(signal) = signal else {
@@ -43,6 +49,27 @@ pub fn kill(pid: Pid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
     kill_process(&process, signal, ctx)
 }
 
+/// Sends a signal to the process referred to by a PID file, using the current
+/// process as the sender.
+///
+/// Unlike [`kill`], the target process is not looked up by its PID, so the
+/// signal will never be sent to another process that reuses the PID.
+///
+/// If `signal` is `None`, this method will only check permission without sending
+/// any signal.
+pub fn kill_pidfd(pid_file: &PidFile, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
+    let process = pid_file.process();
+
+    // A zombie process can still be signaled, but a reaped process cannot.
+    let is_reaped = process_table::get_process(process.pid())
+        .is_none_or(|table_process| !Arc::ptr_eq(&table_process, process));
+    if is_reaped {
+        return_errno_with_message!(Errno::ESRCH, "the target process has been reaped");
+    }
+
+    kill_process(process, signal, ctx)
+}
+
 /// Sends a signal to all processes in a group, using the current process
 /// as the sender.
 ///
@@ -52,7 +79,12 @@ pub fn kill(pid: Pid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
 /// If `signal` is `None`, this method will only check permission without sending
 /// any signal.
 pub fn kill_group(pgid: Pgid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
-    let process_group = process_table::get_process_group(&pgid)
+    // The process group ID is in the PID namespace of the current thread.
+    let process_group = ctx
+        .posix_thread
+        .pid_ns()
+        .to_global(pgid)
+        .and_then(|pgid| process_table::get_process_group(&pgid))
         .ok_or_else(|| Error::with_message(Errno::ESRCH, "target group does not exist"))?;
 
     let inner = process_group.lock();
@@ -69,7 +101,10 @@ pub fn kill_group(pgid: Pgid, signal: Option<UserSignal>, ctx: &Context) -> Resu
 /// If `signal` is `None`, this method will only check permission without sending
 /// any signal.
 pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
-    let thread = thread_table::get_thread(tid)
+    // The IDs are in the PID namespace of the current thread.
+    let pid_ns = ctx.posix_thread.pid_ns();
+    let thread = pid_ns
+        .get_thread(tid)
         .ok_or_else(|| Error::with_message(Errno::ESRCH, "target thread does not exist"))?;
 
     if thread.is_exited() {
@@ -79,7 +114,7 @@ pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>, ctx: &Context) ->
     let posix_thread = thread.as_posix_thread().unwrap();
 
     // Check tgid
-    let pid = posix_thread.process().pid();
+    let pid = pid_ns.to_local(posix_thread.process().pid());
     if pid != tgid {
         return_errno_with_message!(
             Errno::EINVAL,
@@ -95,6 +130,7 @@ pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>, ctx: &Context) ->
     if let Some(signal) = signal {
         // We've checked the permission issues above.
         // FIXME: We should take some lock while checking the permission to avoid race conditions.
+        let signal = signal.to_receiver_pid_ns(posix_thread.pid_ns());
         posix_thread.enqueue_signal(Box::new(signal));
     }
 
@@ -104,12 +140,24 @@ pub fn tgkill(tid: Tid, tgid: Pid, signal: Option<UserSignal>, ctx: &Context) ->
 /// Sends a signal to all processes except current process and init process, using
 /// the current process as the sender.
 ///
+/// Only the processes in the PID namespace of the current thread are affected, and the
+/// init process refers to the init process of the namespace.
+///
 /// The credentials of the current process will be checked to determine
 /// if it is authorized to send the signal to the target group.
 pub fn kill_all(signal: Option<UserSignal>, ctx: &Context) -> Result<()> {
+    const NS_INIT_PID: Pid = 1;
+
     let current = current!();
+    let pid_ns = ctx.posix_thread.pid_ns();
     for process in process_table::process_table_mut().iter() {
-        if Arc::ptr_eq(&current, process) || process.is_init_process() {
+        if Arc::ptr_eq(&current, process) {
+            continue;
+        }
+        // Skip the processes that are invisible in the namespace and the init process of the
+        // namespace.
+        let local_pid = pid_ns.to_local(process.pid());
+        if local_pid == 0 || local_pid == NS_INIT_PID {
             continue;
         }
 
@@ -156,7 +204,9 @@ fn kill_process(process: &Process, signal: Option<UserSignal>, ctx: &Context) ->
     };
 
     // Since `permitted_thread` has been set, `signal` cannot be `None`.
-    let signal = signal.unwrap();
+    let signal = signal
+        .unwrap()
+        .to_receiver_pid_ns(permitted_thread.pid_ns());
 
     // Drop the signal if it's ignored. See explanation at `enqueue_signal_locked`.
     let signum = signal.num();
@@ -1,9 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
 mod clone;
+mod coredump;
 pub mod credentials;
 mod exit;
 mod kill;
+pub mod namespace;
 mod pid_file;
 pub mod posix_thread;
 #[expect(clippy::module_inception)]
@@ -13,6 +15,7 @@ pub mod process_table;
 mod process_vm;
 mod program_loader;
 pub mod rlimit;
+pub mod seccomp;
 pub mod signal;
 mod status;
 pub mod sync;
@@ -21,8 +24,11 @@ mod term_status;
 mod wait;
 
 pub use clone::{clone_child, CloneArgs, CloneFlags};
+pub use coredump::{
+    change_credentials, core_pattern, set_core_pattern, Dumpable, MAX_CORE_PATTERN_LEN,
+};
 pub use credentials::{Credentials, Gid, Uid};
-pub use kill::{kill, kill_all, kill_group, tgkill};
+pub use kill::{kill, kill_all, kill_group, kill_pidfd, tgkill};
 pub use pid_file::PidFile;
 pub use process::{
     broadcast_signal_async, enqueue_signal_async, spawn_init_process, ExitCode, JobControl, Pgid,
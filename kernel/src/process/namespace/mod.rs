@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Linux namespaces.
+//!
+//! A namespace wraps a global system resource so that the processes within the namespace have
+//! their own isolated instance of the resource. The supported namespaces are:
+//!  - the mount namespace ([`MntNamespace`]), which isolates the mount tree;
+//!  - the UTS namespace ([`UtsNamespace`]), which isolates the host name and the domain name;
+//!  - the IPC namespace ([`IpcNamespace`]), which isolates the System V IPC objects;
+//!  - the PID namespace ([`PidNamespace`]), which isolates the process ID number space.
+//!
+//! Except for the PID namespace, the namespaces of a thread are kept in its [`NsProxy`]. The PID
+//! namespace of a thread is fixed once the thread is created, so the proxy only records the PID
+//! namespace for the children of the thread.
+//!
+//! [`MntNamespace`]: crate::fs::path::MntNamespace
+//! [`IpcNamespace`]: crate::ipc::IpcNamespace
+//! A namespace can be referred to by a [`NsFile`], which is opened from `/proc/[pid]/ns` and can
+//! be passed to `setns` to enter the namespace.
+//!
+//! [`PidNamespace`]: super::process_table::PidNamespace
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::{process_table::PidNamespace, CloneFlags};
+use crate::{fs::path::MntNamespace, ipc::IpcNamespace, prelude::*};
+
+mod ns_file;
+mod ns_proxy;
+mod uts;
+
+pub use ns_file::NsFile;
+pub use ns_proxy::NsProxy;
+pub use uts::{UtsName, UtsNamespace};
+
+/// A namespace of one of the supported types.
+#[derive(Clone)]
+pub enum Namespace {
+    Mnt(Arc<MntNamespace>),
+    Uts(Arc<UtsNamespace>),
+    Ipc(Arc<IpcNamespace>),
+    Pid(Arc<PidNamespace>),
+}
+
+impl Namespace {
+    /// Returns the `CLONE_NEW*` flag of the namespace type.
+    pub fn clone_flag(&self) -> CloneFlags {
+        match self {
+            Self::Mnt(_) => CloneFlags::CLONE_NEWNS,
+            Self::Uts(_) => CloneFlags::CLONE_NEWUTS,
+            Self::Ipc(_) => CloneFlags::CLONE_NEWIPC,
+            Self::Pid(_) => CloneFlags::CLONE_NEWPID,
+        }
+    }
+
+    /// Returns the name of the namespace type, e.g., `uts`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Mnt(_) => "mnt",
+            Self::Uts(_) => "uts",
+            Self::Ipc(_) => "ipc",
+            Self::Pid(_) => "pid",
+        }
+    }
+
+    /// Returns the unique ID of the namespace.
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Mnt(ns) => ns.id(),
+            Self::Uts(ns) => ns.id(),
+            Self::Ipc(ns) => ns.id(),
+            Self::Pid(ns) => ns.id(),
+        }
+    }
+}
+
+/// Allocates a unique ID for a namespace.
+///
+/// The ID is reported as the inode number of the namespace files in `/proc/[pid]/ns`.
+pub fn alloc_ns_id() -> u64 {
+    // Linux allocates the inode numbers of namespaces from `0xF0000000`.
+    static NEXT_NS_ID: AtomicU64 = AtomicU64::new(0xF000_0000);
+
+    NEXT_NS_ID.fetch_add(1, Ordering::Relaxed)
+}
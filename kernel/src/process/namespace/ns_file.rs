@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::Namespace;
+use crate::{
+    events::IoEvents,
+    fs::{inode_handle::FileIo, utils::StatusFlags},
+    prelude::*,
+    process::signal::{PollHandle, Pollable},
+};
+
+/// An opened namespace file in `/proc/[pid]/ns`.
+///
+/// The file refers to the namespace at the time of opening, even if the process later moves to
+/// another namespace. Like Linux, the file itself cannot be read or written.
+pub struct NsFile(Namespace);
+
+impl NsFile {
+    pub fn new(ns: Namespace) -> Self {
+        Self(ns)
+    }
+
+    /// Returns the namespace that the file refers to.
+    pub fn ns(&self) -> &Namespace {
+        &self.0
+    }
+}
+
+impl Pollable for NsFile {
+    fn poll(&self, mask: IoEvents, _poller: Option<&mut PollHandle>) -> IoEvents {
+        let events = IoEvents::IN | IoEvents::OUT;
+        events & mask
+    }
+}
+
+impl FileIo for NsFile {
+    fn read(&self, _writer: &mut VmWriter, _status_flags: StatusFlags) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "namespace files cannot be read");
+    }
+
+    fn write(&self, _reader: &mut VmReader, _status_flags: StatusFlags) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "namespace files cannot be written");
+    }
+}
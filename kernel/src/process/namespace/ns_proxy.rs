@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{Namespace, UtsNamespace};
+use crate::{
+    fs::{path::MntNamespace, thread_info::ThreadFsInfo},
+    ipc::{init_ipc_ns, IpcNamespace},
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        process_table::{init_pid_ns, PidNamespace},
+        CloneFlags,
+    },
+};
+
+/// The namespaces of a thread.
+///
+/// Threads that share all their namespaces share the same proxy. A new proxy is created when a
+/// thread creates or enters namespaces with `clone`, `unshare`, or `setns`.
+#[derive(Clone)]
+pub struct NsProxy {
+    uts_ns: Arc<UtsNamespace>,
+    ipc_ns: Arc<IpcNamespace>,
+    mnt_ns: Arc<MntNamespace>,
+    pid_ns_for_children: Arc<PidNamespace>,
+}
+
+impl NsProxy {
+    /// The `CLONE_NEW*` flags of the supported namespaces.
+    pub const SUPPORTED_NS_FLAGS: CloneFlags = CloneFlags::CLONE_NEWNS
+        .union(CloneFlags::CLONE_NEWUTS)
+        .union(CloneFlags::CLONE_NEWIPC)
+        .union(CloneFlags::CLONE_NEWPID);
+
+    /// Creates the proxy of the initial namespaces.
+    pub(in crate::process) fn new_init() -> Arc<Self> {
+        Arc::new(Self {
+            uts_ns: UtsNamespace::new_init(),
+            ipc_ns: init_ipc_ns().clone(),
+            mnt_ns: MntNamespace::new_init(),
+            pid_ns_for_children: init_pid_ns().clone(),
+        })
+    }
+
+    /// Returns the UTS namespace.
+    pub fn uts_ns(&self) -> &Arc<UtsNamespace> {
+        &self.uts_ns
+    }
+
+    /// Returns the IPC namespace.
+    pub fn ipc_ns(&self) -> &Arc<IpcNamespace> {
+        &self.ipc_ns
+    }
+
+    /// Returns the mount namespace.
+    pub fn mnt_ns(&self) -> &Arc<MntNamespace> {
+        &self.mnt_ns
+    }
+
+    /// Returns the PID namespace for the children created by the thread.
+    pub fn pid_ns_for_children(&self) -> &Arc<PidNamespace> {
+        &self.pid_ns_for_children
+    }
+
+    /// Creates a proxy with new namespaces for the `CLONE_NEW*` flags in `flags`.
+    ///
+    /// The namespaces without the corresponding flags are shared with this proxy. If a new mount
+    /// namespace is created, the root and the current working directory in `fs` will be moved to
+    /// the new mount tree, so `fs` must not be shared with other threads.
+    pub fn new_copy(
+        self: &Arc<Self>,
+        flags: CloneFlags,
+        fs: &ThreadFsInfo,
+        ctx: &Context,
+    ) -> Result<Arc<Self>> {
+        let flags = flags & Self::SUPPORTED_NS_FLAGS;
+        if flags.is_empty() {
+            return Ok(self.clone());
+        }
+        check_sys_admin(ctx)?;
+
+        let mut new_proxy = self.as_ref().clone();
+        if flags.contains(CloneFlags::CLONE_NEWUTS) {
+            new_proxy.uts_ns = self.uts_ns.new_copy();
+        }
+        if flags.contains(CloneFlags::CLONE_NEWIPC) {
+            new_proxy.ipc_ns = IpcNamespace::new();
+        }
+        if flags.contains(CloneFlags::CLONE_NEWPID) {
+            new_proxy.pid_ns_for_children = self.pid_ns_for_children.new_child()?;
+        }
+        if flags.contains(CloneFlags::CLONE_NEWNS) {
+            new_proxy.mnt_ns = self.mnt_ns.new_copy(&mut fs.resolver().write());
+        }
+
+        Ok(Arc::new(new_proxy))
+    }
+
+    /// Creates a proxy that enters the namespaces in `namespaces`.
+    ///
+    /// The namespaces of the other types are shared with this proxy. If a mount namespace is
+    /// entered, the root and the current working directory in `fs` will be set to the root of the
+    /// target mount tree, so `fs` must not be shared with other threads.
+    pub fn new_enter(
+        self: &Arc<Self>,
+        namespaces: &[Namespace],
+        fs: &ThreadFsInfo,
+        ctx: &Context,
+    ) -> Result<Arc<Self>> {
+        if namespaces.is_empty() {
+            return Ok(self.clone());
+        }
+        check_sys_admin(ctx)?;
+
+        let mut new_proxy = self.as_ref().clone();
+        let mut new_root_path = None;
+        for ns in namespaces {
+            match ns {
+                Namespace::Uts(uts_ns) => new_proxy.uts_ns = uts_ns.clone(),
+                Namespace::Ipc(ipc_ns) => new_proxy.ipc_ns = ipc_ns.clone(),
+                Namespace::Pid(pid_ns) => {
+                    // A thread can only enter its own PID namespace or a descendant namespace.
+                    if !ctx.posix_thread.pid_ns().is_ancestor_of(pid_ns) {
+                        return_errno_with_message!(
+                            Errno::EINVAL,
+                            "the target PID namespace is not a descendant namespace"
+                        );
+                    }
+                    new_proxy.pid_ns_for_children = pid_ns.clone();
+                }
+                Namespace::Mnt(mnt_ns) => {
+                    new_root_path = Some(mnt_ns.root_path());
+                    new_proxy.mnt_ns = mnt_ns.clone();
+                }
+            }
+        }
+
+        // Move to the new mount tree only after all the checks have passed.
+        if let Some(root_path) = new_root_path {
+            let mut resolver = fs.resolver().write();
+            resolver.set_root(root_path.clone());
+            resolver.set_cwd(root_path);
+        }
+
+        Ok(Arc::new(new_proxy))
+    }
+}
+
+/// Checks whether the current thread is allowed to create or enter namespaces.
+fn check_sys_admin(ctx: &Context) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    if !credentials.euid().is_root() && !credentials.effective_capset().contains(CapSet::SYS_ADMIN)
+    {
+        return_errno_with_message!(
+            Errno::EPERM,
+            "creating or entering namespaces requires CAP_SYS_ADMIN"
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::alloc_ns_id;
+use crate::prelude::*;
+
+/// A UTS namespace.
+///
+/// A UTS namespace isolates the host name and the NIS domain name, which are returned by `uname`
+/// and can be changed by `sethostname` and `setdomainname`.
+pub struct UtsNamespace {
+    id: u64,
+    uts_name: RwLock<UtsName>,
+}
+
+impl UtsNamespace {
+    /// Creates the initial UTS namespace.
+    pub(super) fn new_init() -> Arc<Self> {
+        // We don't use the real name and version of our os here. Instead, we pick up fake values
+        // witch is the same as the ones of linux. The values are used to fool glibc since glibc
+        // will check the version and os name.
+        let mut uts_name = UtsName::new_zeroed();
+        copy_field(b"Linux", &mut uts_name.sysname);
+        copy_field(b"WHITLEY", &mut uts_name.nodename);
+        copy_field(b"5.13.0", &mut uts_name.release);
+        copy_field(b"5.13.0", &mut uts_name.version);
+        copy_field(b"x86_64", &mut uts_name.machine);
+        copy_field(b"", &mut uts_name.domainname);
+
+        Arc::new(Self {
+            id: alloc_ns_id(),
+            uts_name: RwLock::new(uts_name),
+        })
+    }
+
+    /// Creates a new UTS namespace with the names copied from this namespace.
+    pub(super) fn new_copy(&self) -> Arc<Self> {
+        Arc::new(Self {
+            id: alloc_ns_id(),
+            uts_name: RwLock::new(*self.uts_name.read()),
+        })
+    }
+
+    /// Returns the ID of the namespace.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the UTS names.
+    pub fn uts_name(&self) -> UtsName {
+        *self.uts_name.read()
+    }
+
+    /// Sets the host name.
+    pub fn set_hostname(&self, hostname: &[u8]) -> Result<()> {
+        check_name_len(hostname)?;
+        copy_field(hostname, &mut self.uts_name.write().nodename);
+        Ok(())
+    }
+
+    /// Sets the NIS domain name.
+    pub fn set_domainname(&self, domainname: &[u8]) -> Result<()> {
+        check_name_len(domainname)?;
+        copy_field(domainname, &mut self.uts_name.write().domainname);
+        Ok(())
+    }
+}
+
+const UTS_FIELD_LEN: usize = 65;
+
+/// The UTS names, which have the same layout as `struct new_utsname` in Linux.
+#[derive(Debug, Clone, Copy, Pod)]
+#[repr(C)]
+pub struct UtsName {
+    sysname: [u8; UTS_FIELD_LEN],
+    nodename: [u8; UTS_FIELD_LEN],
+    release: [u8; UTS_FIELD_LEN],
+    version: [u8; UTS_FIELD_LEN],
+    machine: [u8; UTS_FIELD_LEN],
+    domainname: [u8; UTS_FIELD_LEN],
+}
+
+impl UtsName {
+    /// Returns the host name without the trailing null bytes.
+    pub fn nodename(&self) -> &[u8] {
+        let len = self
+            .nodename
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(UTS_FIELD_LEN);
+        &self.nodename[..len]
+    }
+}
+
+/// Checks that the name can be stored with a trailing null byte.
+fn check_name_len(name: &[u8]) -> Result<()> {
+    if name.len() >= UTS_FIELD_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the name is too long");
+    }
+    Ok(())
+}
+
+/// Copies the name to the field and fills the rest of the field with null bytes.
+fn copy_field(src: &[u8], dst: &mut [u8; UTS_FIELD_LEN]) {
+    let len = src.len().min(UTS_FIELD_LEN - 1);
+    dst[..len].copy_from_slice(&src[..len]);
+    dst[len..].fill(0);
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::AtomicU32;
+use core::sync::atomic::{AtomicBool, AtomicU32};
 
 use ostd::{
     cpu::{
@@ -11,12 +11,15 @@ use ostd::{
     task::Task,
 };
 
-use super::{thread_table, PosixThread, ThreadLocal};
+use super::{ptrace::PtraceState, thread_table, PosixThread, ThreadLocal};
 use crate::{
     fs::{file_table::FileTable, thread_info::ThreadFsInfo},
     prelude::*,
     process::{
+        namespace::NsProxy,
         posix_thread::name::ThreadName,
+        process_table::{init_pid_ns, PidNamespace},
+        seccomp::SeccompMode,
         signal::{sig_mask::AtomicSigMask, sig_queues::SigQueues},
         Credentials, Process,
     },
@@ -41,6 +44,10 @@ pub struct PosixThreadBuilder {
     fs: Option<Arc<ThreadFsInfo>>,
     sig_mask: AtomicSigMask,
     sig_queues: SigQueues,
+    seccomp_mode: SeccompMode,
+    no_new_privs: bool,
+    ns_proxy: Option<Arc<NsProxy>>,
+    pid_ns: Option<Arc<PidNamespace>>,
     sched_policy: SchedPolicy,
     fpu_context: FpuContext,
 }
@@ -59,6 +66,10 @@ impl PosixThreadBuilder {
             fs: None,
             sig_mask: AtomicSigMask::new_empty(),
             sig_queues: SigQueues::new(),
+            seccomp_mode: SeccompMode::Disabled,
+            no_new_privs: false,
+            ns_proxy: None,
+            pid_ns: None,
             sched_policy: SchedPolicy::Fair(Nice::default()),
             fpu_context: FpuContext::new(),
         }
@@ -99,6 +110,26 @@ impl PosixThreadBuilder {
         self
     }
 
+    pub fn seccomp_mode(mut self, seccomp_mode: SeccompMode) -> Self {
+        self.seccomp_mode = seccomp_mode;
+        self
+    }
+
+    pub fn no_new_privs(mut self, no_new_privs: bool) -> Self {
+        self.no_new_privs = no_new_privs;
+        self
+    }
+
+    pub fn ns_proxy(mut self, ns_proxy: Arc<NsProxy>) -> Self {
+        self.ns_proxy = Some(ns_proxy);
+        self
+    }
+
+    pub fn pid_ns(mut self, pid_ns: Arc<PidNamespace>) -> Self {
+        self.pid_ns = Some(pid_ns);
+        self
+    }
+
     pub fn fpu_context(mut self, fpu_context: FpuContext) -> Self {
         self.fpu_context = fpu_context;
         self
@@ -117,6 +148,10 @@ impl PosixThreadBuilder {
             fs,
             sig_mask,
             sig_queues,
+            seccomp_mode,
+            no_new_privs,
+            ns_proxy,
+            pid_ns,
             sched_policy,
             fpu_context,
         } = self;
@@ -125,6 +160,11 @@ impl PosixThreadBuilder {
 
         let fs = fs.unwrap_or_else(|| Arc::new(ThreadFsInfo::default()));
 
+        let ns_proxy = ns_proxy.unwrap_or_else(NsProxy::new_init);
+
+        let pid_ns = pid_ns.unwrap_or_else(|| init_pid_ns().clone());
+        pid_ns.alloc_ids(tid);
+
         let root_vmar = process
             .upgrade()
             .unwrap()
@@ -145,9 +185,19 @@ impl PosixThreadBuilder {
                     name: Mutex::new(thread_name),
                     credentials,
                     file_table: Mutex::new(Some(file_table.clone_ro())),
+                    io_uring_file_table: Mutex::new(None),
                     sig_mask,
                     sig_queues,
                     signalled_waker: SpinLock::new(None),
+                    ptrace: SpinLock::new(PtraceState::new()),
+                    is_seccomp_enabled: AtomicBool::new(!matches!(
+                        seccomp_mode,
+                        SeccompMode::Disabled
+                    )),
+                    seccomp: SpinLock::new(seccomp_mode),
+                    no_new_privs: AtomicBool::new(no_new_privs),
+                    ns_proxy: Mutex::new(ns_proxy),
+                    pid_ns,
                     prof_clock,
                     virtual_timer_manager,
                     prof_timer_manager,
@@ -45,6 +45,8 @@ fn exit_internal(term_status: TermStatus, is_exiting_group: bool) {
     let posix_process = posix_thread.process();
 
     let is_last_thread = {
+        // Lock order: cgroup of process -> tasks of process
+        let cgroup = posix_process.cgroup().lock();
         let mut tasks = posix_process.tasks().lock();
         let has_exited_group = tasks.has_exited_group();
 
@@ -66,6 +68,11 @@ fn exit_internal(term_status: TermStatus, is_exiting_group: bool) {
         }
         current_thread.exit();
 
+        // The exited thread is no longer counted by the `pids` controller.
+        if let Some(cgroup) = cgroup.as_ref() {
+            cgroup.uncharge_pids(1);
+        }
+
         tasks.remove_exited(&current_task)
     };
 
@@ -73,6 +80,9 @@ fn exit_internal(term_status: TermStatus, is_exiting_group: bool) {
 
     wake_robust_list(thread_local, posix_thread.tid());
 
+    // The exit code of the process has been updated to the one of the current thread.
+    posix_thread.exit_ptrace(posix_process.status().exit_code());
+
     // According to Linux behavior, the main thread shouldn't be removed from the table until the
     // process is reaped by its parent.
     if posix_thread.tid() != posix_process.pid() {
@@ -81,6 +91,7 @@ fn exit_internal(term_status: TermStatus, is_exiting_group: bool) {
 
     // Drop fields in `PosixThread`.
     *posix_thread.file_table().lock() = None;
+    *posix_thread.io_uring_file_table().lock() = None;
 
     // Drop fields in `ThreadLocal`.
     *thread_local.root_vmar().borrow_mut() = None;
@@ -82,6 +82,7 @@ pub fn futex_wait_bitset(
             .handle_page_fault(&PageFaultInfo {
                 address: futex_addr,
                 required_perms: VmPerms::READ,
+                is_user: false,
             })
             .map_err(|_| {
                 Error::with_message(
@@ -311,6 +312,7 @@ pub fn futex_wake_op(
             .handle_page_fault(&PageFaultInfo {
                 address: futex_addr_2,
                 required_perms: VmPerms::READ | VmPerms::WRITE,
+                is_user: false,
             })
             .map_err(|_| {
                 Error::with_message(
@@ -1,13 +1,18 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use aster_rights::{ReadDupOp, ReadOp, WriteOp};
-use ostd::sync::{RoArc, Waker};
+use ostd::sync::{RoArc, RwArc, Waker};
 
+use self::ptrace::PtraceState;
 use super::{
     kill::SignalSenderIds,
+    namespace::NsProxy,
+    process_table::PidNamespace,
+    seccomp::SeccompMode,
     signal::{
+        sig_action::SigAction,
         sig_disposition::SigDispositions,
         sig_mask::{AtomicSigMask, SigMask, SigSet},
         sig_num::SigNum,
@@ -31,6 +36,7 @@ mod exit;
 pub mod futex;
 mod name;
 mod posix_thread_ext;
+mod ptrace;
 mod robust_list;
 mod thread_local;
 pub mod thread_table;
@@ -39,6 +45,8 @@ pub use builder::PosixThreadBuilder;
 pub use exit::{do_exit, do_exit_group};
 pub use name::{ThreadName, MAX_THREAD_NAME_LEN};
 pub use posix_thread_ext::AsPosixThread;
+pub(super) use ptrace::exit_ptrace_tracer;
+pub use ptrace::{ptrace_attach, PtraceOptions, PtraceResumeMode, PtraceStop, PtraceStopKind};
 pub use robust_list::RobustListHead;
 pub use thread_local::{AsThreadLocal, FileTableRefMut, ThreadLocal};
 
@@ -56,6 +64,8 @@ pub struct PosixThread {
     // Files
     /// File table
     file_table: Mutex<Option<RoArc<FileTable>>>,
+    /// File table into which the io_uring requests submitted by the thread install files
+    io_uring_file_table: Mutex<Option<RwArc<FileTable>>>,
 
     // Signal
     /// Blocked signals
@@ -66,6 +76,27 @@ pub struct PosixThread {
     /// when enqueuing a signal.
     signalled_waker: SpinLock<Option<Arc<Waker>>>,
 
+    // Ptrace
+    /// The tracer and the ptrace-stop of the thread
+    ptrace: SpinLock<PtraceState>,
+
+    // Security
+    /// The seccomp mode and filters
+    seccomp: SpinLock<SeccompMode>,
+    /// Whether the seccomp mode is not [`SeccompMode::Disabled`]
+    ///
+    /// This allows the syscalls to be checked without locking `seccomp` in the common case where
+    /// seccomp is disabled. Once seccomp is enabled, it cannot be disabled.
+    is_seccomp_enabled: AtomicBool,
+    /// Whether `execve` is prevented from granting new privileges
+    no_new_privs: AtomicBool,
+
+    // Namespaces
+    /// The namespaces of the thread
+    ns_proxy: Mutex<Arc<NsProxy>>,
+    /// The PID namespace of the thread, which cannot be changed
+    pid_ns: Arc<PidNamespace>,
+
     /// A profiling clock measures the user CPU time and kernel CPU time in the thread.
     prof_clock: Arc<ProfClock>,
 
@@ -101,6 +132,17 @@ impl PosixThread {
         &self.file_table
     }
 
+    /// Returns the file table into which the io_uring requests submitted by the thread install
+    /// files (e.g., the sockets accepted by `IORING_OP_ACCEPT`).
+    ///
+    /// The requests look up the file table here when they are executed. They cannot keep the
+    /// file table alive by themselves, since the file table may contain the io_uring instance,
+    /// which keeps the requests alive. The file table is set when such a request is submitted
+    /// and is dropped when the thread exits.
+    pub fn io_uring_file_table(&self) -> &Mutex<Option<RwArc<FileTable>>> {
+        &self.io_uring_file_table
+    }
+
     /// Get the reference to the signal mask of the thread.
     ///
     /// Note that while this function offers mutable access to the signal mask,
@@ -220,6 +262,29 @@ impl PosixThread {
         self.enqueue_signal_locked(signal, sig_dispositions);
     }
 
+    /// Enqueues a thread-directed signal that cannot be blocked or ignored.
+    ///
+    /// Like Linux's `force_sig_info`, if the signal is blocked or ignored, it is unblocked and its
+    /// disposition is reset to the default one. Since this may modify the signal mask, this
+    /// method should only be called by the current thread.
+    pub(in crate::process) fn enqueue_forced_signal(&self, signal: Box<dyn Signal>) {
+        let process = self.process();
+        let mut sig_dispositions = process.sig_dispositions().lock();
+
+        let signum = signal.num();
+        let is_blocked = self.has_signal_blocked(signum);
+        let is_ignored = matches!(sig_dispositions.get(signum), SigAction::Ign);
+        if is_blocked || is_ignored {
+            sig_dispositions.set_default(signum);
+        }
+        if is_blocked {
+            let sig_mask = self.sig_mask.load(Ordering::Relaxed);
+            self.sig_mask.store(sig_mask - signum, Ordering::Relaxed);
+        }
+
+        self.enqueue_signal_locked(signal, sig_dispositions);
+    }
+
     /// Enqueues a thread-directed signal with locked dispositions.
     ///
     /// By locking dispositions, the caller should have already checked the signal is not to be
@@ -306,12 +371,62 @@ impl PosixThread {
         self.credentials.dup().restrict()
     }
 
+    /// Returns the seccomp mode of the thread.
+    pub fn seccomp_mode(&self) -> SeccompMode {
+        if !self.is_seccomp_enabled.load(Ordering::Acquire) {
+            return SeccompMode::Disabled;
+        }
+        self.seccomp.lock().clone()
+    }
+
+    pub(in crate::process) fn seccomp(&self) -> &SpinLock<SeccompMode> {
+        &self.seccomp
+    }
+
+    /// Marks seccomp as enabled after the seccomp mode is changed via [`Self::seccomp`].
+    pub(in crate::process) fn set_seccomp_enabled(&self) {
+        self.is_seccomp_enabled.store(true, Ordering::Release);
+    }
+
+    /// Returns whether the `no_new_privs` attribute of the thread is set.
+    pub fn no_new_privs(&self) -> bool {
+        self.no_new_privs.load(Ordering::Relaxed)
+    }
+
+    /// Sets the `no_new_privs` attribute of the thread.
+    ///
+    /// Once set, the attribute cannot be unset.
+    pub fn set_no_new_privs(&self) {
+        self.no_new_privs.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the namespaces of the thread.
+    pub fn ns_proxy(&self) -> Arc<NsProxy> {
+        self.ns_proxy.lock().clone()
+    }
+
+    /// Sets the namespaces of the thread.
+    pub fn set_ns_proxy(&self, ns_proxy: Arc<NsProxy>) {
+        *self.ns_proxy.lock() = ns_proxy;
+    }
+
+    /// Returns the PID namespace of the thread.
+    pub fn pid_ns(&self) -> &Arc<PidNamespace> {
+        &self.pid_ns
+    }
+
     /// Returns the I/O priority value of the thread.
     pub fn io_priority(&self) -> &AtomicU32 {
         &self.io_priority
     }
 }
 
+impl Drop for PosixThread {
+    fn drop(&mut self) {
+        self.pid_ns.free_ids(self.tid);
+    }
+}
+
 static POSIX_TID_ALLOCATOR: AtomicU32 = AtomicU32::new(1);
 
 /// Allocates a new tid for the new posix thread
@@ -0,0 +1,663 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Process tracing (`ptrace`).
+//!
+//! A thread (the tracee) can be traced by a process (the tracer). When the tracee is about to
+//! handle a signal, or when it enters or exits a syscall if requested, it enters a _ptrace-stop_
+//! and waits for the tracer to resume it. In the meantime, the tracer can observe the stop via
+//! the `wait` family syscalls and inspect or modify the registers and memory of the tracee.
+//!
+//! Unlike Linux, where the tracer is a thread, the tracer is a process here. So any thread in
+//! the tracer process can operate on the tracees.
+
+use core::ptr;
+
+use ostd::{
+    cpu::context::UserContext,
+    sync::{Waiter, Waker},
+};
+
+use super::{AsPosixThread, PosixThread};
+use crate::{
+    cpu::LinuxAbi,
+    prelude::*,
+    process::{
+        credentials::capabilities::CapSet,
+        signal::{
+            c_types::siginfo_t,
+            constants::{CLD_TRAPPED, SIGKILL, SIGTRAP},
+            sig_num::SigNum,
+            signals::{child::ChildSignal, kernel::KernelSignal, Signal},
+        },
+        Dumpable, ExitCode, Process, WaitOptions,
+    },
+    thread::{Thread, Tid},
+};
+
+bitflags! {
+    /// The options of a tracee.
+    ///
+    /// The options can be set by `PTRACE_SETOPTIONS` or `PTRACE_SEIZE`.
+    pub struct PtraceOptions: u32 {
+        /// Sets bit 7 of the signal number when reporting syscall-stops.
+        const TRACESYSGOOD    = 1 << 0;
+        const TRACEFORK       = 1 << 1;
+        const TRACEVFORK      = 1 << 2;
+        const TRACECLONE      = 1 << 3;
+        /// Stops the tracee with `PTRACE_EVENT_EXEC` after `execve`.
+        const TRACEEXEC       = 1 << 4;
+        const TRACEVFORKDONE  = 1 << 5;
+        const TRACEEXIT       = 1 << 6;
+        const TRACESECCOMP    = 1 << 7;
+        /// Kills the tracee when the tracer exits.
+        const EXITKILL        = 1 << 20;
+        const SUSPEND_SECCOMP = 1 << 21;
+    }
+}
+
+impl PtraceOptions {
+    /// Checks whether the options are supported.
+    ///
+    /// The options that stop the tracee at new children are rejected, because the tracer would
+    /// otherwise wait for the events that never happen.
+    pub fn check(&self) -> Result<()> {
+        // FIXME: Support tracing the children created by `fork`, `vfork` and `clone`.
+        let unsupported_events = PtraceOptions::TRACEFORK
+            | PtraceOptions::TRACEVFORK
+            | PtraceOptions::TRACECLONE
+            | PtraceOptions::TRACEVFORKDONE;
+        if self.intersects(unsupported_events) {
+            return_errno_with_message!(Errno::EINVAL, "tracing the new children is not supported");
+        }
+
+        let supported_options =
+            PtraceOptions::TRACESYSGOOD | PtraceOptions::TRACEEXEC | PtraceOptions::EXITKILL;
+        if !supported_options.contains(*self) {
+            warn!(
+                "unsupported ptrace options are found: {:?}",
+                *self - supported_options
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The way to resume a tracee from a ptrace-stop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PtraceResumeMode {
+    /// Runs until the next ptrace-stop caused by signals (`PTRACE_CONT`).
+    #[default]
+    Continue,
+    /// Also stops at the next syscall entry or exit (`PTRACE_SYSCALL`).
+    Syscall,
+    /// Also stops after executing a single instruction (`PTRACE_SINGLESTEP`).
+    SingleStep,
+}
+
+/// The kind of a ptrace-stop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PtraceStopKind {
+    /// The tracee is about to handle the signal (signal-delivery-stop).
+    Signal(SigNum),
+    /// The tracee is entering the syscall with the syscall number (syscall-enter-stop).
+    SyscallEnter(usize),
+    /// The tracee is exiting the syscall with the syscall number (syscall-exit-stop).
+    SyscallExit(usize),
+    /// The tracee has executed a new program (`PTRACE_EVENT_EXEC` stop).
+    Exec,
+}
+
+impl PtraceStopKind {
+    /// Returns the stop code that is reported to the tracer via the `wait` family syscalls.
+    fn code(&self, options: PtraceOptions) -> u32 {
+        // Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/ptrace.h#L155>
+        const PTRACE_EVENT_EXEC: u32 = 4;
+
+        let sigtrap = SIGTRAP.as_u8() as u32;
+        match self {
+            Self::Signal(sig_num) => sig_num.as_u8() as u32,
+            Self::SyscallEnter(_) | Self::SyscallExit(_) => {
+                if options.contains(PtraceOptions::TRACESYSGOOD) {
+                    sigtrap | 0x80
+                } else {
+                    sigtrap
+                }
+            }
+            Self::Exec => sigtrap | (PTRACE_EVENT_EXEC << 8),
+        }
+    }
+
+    /// Returns the signal that should be delivered if the tracee is not traced.
+    fn signal(&self) -> Option<SigNum> {
+        match self {
+            Self::Signal(sig_num) => Some(*sig_num),
+            Self::SyscallEnter(_) | Self::SyscallExit(_) | Self::Exec => None,
+        }
+    }
+}
+
+/// A ptrace-stop of a tracee.
+pub struct PtraceStop {
+    kind: PtraceStopKind,
+    /// The stop code reported via the `wait` family syscalls.
+    code: u32,
+    siginfo: siginfo_t,
+    /// A snapshot of the user context, which will be restored when the tracee is resumed.
+    user_ctx: UserContext,
+    waker: Arc<Waker>,
+    /// Whether the stop has been reported via the `wait` family syscalls.
+    is_reported: bool,
+    is_resumed: bool,
+    /// The signal to deliver after the tracee is resumed, which is chosen by the tracer.
+    resume_signal: Option<SigNum>,
+}
+
+impl PtraceStop {
+    /// Returns the kind of the stop.
+    pub fn kind(&self) -> PtraceStopKind {
+        self.kind
+    }
+
+    /// Returns the signal information of the stop.
+    ///
+    /// For stops that are not caused by signals, the information describes a `SIGTRAP` with the
+    /// stop code as `si_code`, which is the same as Linux.
+    pub fn siginfo(&self) -> &siginfo_t {
+        &self.siginfo
+    }
+
+    /// Returns the user context of the tracee.
+    pub fn user_ctx(&self) -> &UserContext {
+        &self.user_ctx
+    }
+
+    /// Returns the mutable user context of the tracee.
+    pub fn user_ctx_mut(&mut self) -> &mut UserContext {
+        &mut self.user_ctx
+    }
+
+    /// Changes the syscall that the tracee is about to execute in a syscall-enter-stop.
+    pub fn set_syscall_num(&mut self, syscall_num: usize) {
+        if let PtraceStopKind::SyscallEnter(num) = &mut self.kind {
+            *num = syscall_num;
+        }
+    }
+}
+
+/// The ptrace state of a thread.
+pub(super) struct PtraceState {
+    tracer: Option<Weak<Process>>,
+    options: PtraceOptions,
+    /// Whether the tracee is attached by `PTRACE_SEIZE`.
+    is_seized: bool,
+    resume_mode: PtraceResumeMode,
+    /// Whether single-stepping is enabled in the user context.
+    is_single_stepping: bool,
+    stop: Option<PtraceStop>,
+    /// The exit code of the thread, which is reported to the tracer after the thread exits.
+    exit_code: Option<ExitCode>,
+}
+
+impl PtraceState {
+    pub(super) const fn new() -> Self {
+        Self {
+            tracer: None,
+            options: PtraceOptions::empty(),
+            is_seized: false,
+            resume_mode: PtraceResumeMode::Continue,
+            is_single_stepping: false,
+            stop: None,
+            exit_code: None,
+        }
+    }
+
+    fn is_traced_by(&self, tracer: &Process) -> bool {
+        self.tracer
+            .as_ref()
+            .is_some_and(|weak_tracer| ptr::eq(weak_tracer.as_ptr(), tracer))
+    }
+
+    /// Returns the ptrace-stop that has not been resumed.
+    fn active_stop(&mut self, tracer: &Process) -> Result<&mut PtraceStop> {
+        if !self.is_traced_by(tracer) {
+            return_errno_with_message!(Errno::ESRCH, "the thread is not traced by the process");
+        }
+
+        self.stop
+            .as_mut()
+            .filter(|stop| !stop.is_resumed)
+            .ok_or_else(|| Error::with_message(Errno::ESRCH, "the tracee is not stopped"))
+    }
+
+    /// Stops tracing and resumes the tracee if it is stopped.
+    fn reset(&mut self, resume_signal: Option<SigNum>) {
+        self.tracer = None;
+        self.options = PtraceOptions::empty();
+        self.is_seized = false;
+        self.resume_mode = PtraceResumeMode::Continue;
+
+        if let Some(stop) = self.stop.as_mut()
+            && !stop.is_resumed
+        {
+            stop.is_resumed = true;
+            stop.resume_signal = resume_signal;
+            stop.waker.wake_up();
+        }
+    }
+}
+
+/// Attaches a tracer to a thread.
+pub fn ptrace_attach(
+    tracee: &Arc<Thread>,
+    tracer: &Arc<Process>,
+    options: PtraceOptions,
+    is_seized: bool,
+) -> Result<()> {
+    let posix_thread = tracee.as_posix_thread().unwrap();
+
+    // Lock order: tracees of tracer -> ptrace state of tracee
+    let mut tracees = tracer.tracees().lock();
+    if tracer.status().is_zombie() {
+        return_errno_with_message!(Errno::EPERM, "the tracer has exited");
+    }
+
+    let mut state = posix_thread.ptrace.lock();
+    if state.tracer.is_some() {
+        return_errno_with_message!(Errno::EPERM, "the thread is already traced");
+    }
+    // Check this while holding the lock to avoid racing with `exit_ptrace`.
+    if tracee.is_exited() {
+        return_errno_with_message!(Errno::ESRCH, "the thread has exited");
+    }
+    state.tracer = Some(Arc::downgrade(tracer));
+    state.options = options;
+    state.is_seized = is_seized;
+    drop(state);
+
+    tracees.insert(posix_thread.tid(), tracee.clone());
+
+    Ok(())
+}
+
+impl PosixThread {
+    /// Returns the tracer of the thread, if the thread is traced.
+    pub fn tracer(&self) -> Option<Arc<Process>> {
+        self.ptrace.lock().tracer.as_ref()?.upgrade()
+    }
+
+    /// Returns whether the thread is traced by the process.
+    pub fn is_traced_by(&self, tracer: &Process) -> bool {
+        self.ptrace.lock().is_traced_by(tracer)
+    }
+
+    /// Checks whether the `accessor` thread can trace the thread or access its memory.
+    ///
+    /// A thread can always access the threads in the same process. Otherwise, the accessor must
+    /// be privileged, or its real user and group IDs must match all the user and group IDs of
+    /// the thread and the process of the thread must be dumpable.
+    ///
+    /// Reference: <https://man7.org/linux/man-pages/man2/ptrace.2.html> ("Ptrace access mode
+    /// checking").
+    pub fn check_ptrace_access(&self, accessor: &PosixThread) -> Result<()> {
+        if Arc::ptr_eq(&self.process(), &accessor.process()) {
+            return Ok(());
+        }
+
+        let credentials = accessor.credentials();
+        if credentials.euid().is_root()
+            || credentials.effective_capset().contains(CapSet::SYS_PTRACE)
+        {
+            return Ok(());
+        }
+
+        let tracee_credentials = self.credentials();
+        let uid = credentials.ruid();
+        let gid = credentials.rgid();
+        let is_same_uid = tracee_credentials.ruid() == uid
+            && tracee_credentials.euid() == uid
+            && tracee_credentials.suid() == uid;
+        let is_same_gid = tracee_credentials.rgid() == gid
+            && tracee_credentials.egid() == gid
+            && tracee_credentials.sgid() == gid;
+        if !is_same_uid || !is_same_gid {
+            return_errno_with_message!(
+                Errno::EPERM,
+                "the current thread does not have permission to access the thread"
+            );
+        }
+
+        // Like Linux, a process that is not dumpable (e.g., a process that has executed a
+        // set-user-ID program) can only be accessed by privileged threads.
+        if self.process().dumpable() != Dumpable::User {
+            return_errno_with_message!(Errno::EPERM, "the process of the thread is not dumpable");
+        }
+
+        Ok(())
+    }
+
+    /// Calls `f` with the ptrace-stop of the thread.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error with `ESRCH` if the thread is not traced by `tracer` or
+    /// is not in a ptrace-stop.
+    pub fn with_ptrace_stop<F, R>(&self, tracer: &Process, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut PtraceStop) -> R,
+    {
+        let mut state = self.ptrace.lock();
+        let stop = state.active_stop(tracer)?;
+        Ok(f(stop))
+    }
+
+    /// Sets the ptrace options of the stopped thread.
+    pub fn set_ptrace_options(&self, tracer: &Process, options: PtraceOptions) -> Result<()> {
+        let mut state = self.ptrace.lock();
+        state.active_stop(tracer)?;
+        state.options = options;
+        Ok(())
+    }
+
+    /// Resumes the thread from the ptrace-stop.
+    ///
+    /// If `signal` is not `None`, the signal will be delivered to the thread after resuming.
+    pub fn ptrace_resume(
+        &self,
+        tracer: &Process,
+        mode: PtraceResumeMode,
+        signal: Option<SigNum>,
+    ) -> Result<()> {
+        let mut state = self.ptrace.lock();
+
+        let stop = state.active_stop(tracer)?;
+        stop.is_resumed = true;
+        stop.resume_signal = signal;
+        stop.waker.wake_up();
+
+        state.resume_mode = mode;
+        Ok(())
+    }
+
+    /// Detaches the stopped thread from its tracer and resumes it.
+    pub fn ptrace_detach(&self, tracer: &Process, signal: Option<SigNum>) -> Result<()> {
+        // Lock order: tracees of tracer -> ptrace state of tracee
+        let mut tracees = tracer.tracees().lock();
+
+        let mut state = self.ptrace.lock();
+        state.active_stop(tracer)?;
+        state.reset(signal);
+        drop(state);
+
+        tracees.remove(&self.tid);
+        Ok(())
+    }
+
+    /// Gets and marks the ptrace-stop as reported for the `wait` syscall.
+    ///
+    /// Returns the stop code if the thread is in a ptrace-stop that has not been reported.
+    pub(in crate::process) fn wait_ptrace_stopped(&self, options: WaitOptions) -> Option<u32> {
+        let mut state = self.ptrace.lock();
+
+        let stop = state
+            .stop
+            .as_mut()
+            .filter(|stop| !stop.is_resumed && !stop.is_reported)?;
+        if !options.contains(WaitOptions::WNOWAIT) {
+            stop.is_reported = true;
+        }
+
+        Some(stop.code)
+    }
+
+    /// Gets the exit code of the thread if the exit should be reported to the tracer.
+    ///
+    /// The exit is reported only once, so the tracer should remove the thread from its tracees
+    /// unless `WNOWAIT` is specified.
+    pub(in crate::process) fn wait_ptrace_exited(&self) -> Option<ExitCode> {
+        self.ptrace.lock().exit_code
+    }
+
+    /// Reports the signal to the tracer before the current thread handles it.
+    ///
+    /// This method returns the signal that should be handled, which may be changed or suppressed
+    /// by the tracer.
+    pub(in crate::process) fn ptrace_report_signal(
+        &self,
+        signal: Box<dyn Signal>,
+        user_ctx: &mut UserContext,
+    ) -> Option<Box<dyn Signal>> {
+        let sig_num = signal.num();
+        // `SIGKILL` always kills the tracee without notifying the tracer.
+        if sig_num == SIGKILL {
+            return Some(signal);
+        }
+
+        let siginfo = signal.to_info();
+        let resume_signal =
+            self.ptrace_stop(PtraceStopKind::Signal(sig_num), Some(siginfo), user_ctx)?;
+        if resume_signal == sig_num {
+            Some(signal)
+        } else {
+            Some(Box::new(KernelSignal::new(resume_signal)))
+        }
+    }
+
+    /// Reports the syscall entry to the tracer if the current thread is resumed by
+    /// `PTRACE_SYSCALL`.
+    ///
+    /// The tracer may change the syscall number and the arguments in `user_ctx`.
+    pub fn ptrace_report_syscall_entry(&self, user_ctx: &mut UserContext) {
+        let syscall_num = user_ctx.syscall_num();
+        self.ptrace_report_syscall(PtraceStopKind::SyscallEnter(syscall_num), user_ctx);
+    }
+
+    /// Reports the syscall exit to the tracer if the current thread is resumed by
+    /// `PTRACE_SYSCALL`.
+    pub fn ptrace_report_syscall_exit(&self, syscall_num: usize, user_ctx: &mut UserContext) {
+        self.ptrace_report_syscall(PtraceStopKind::SyscallExit(syscall_num), user_ctx);
+    }
+
+    fn ptrace_report_syscall(&self, kind: PtraceStopKind, user_ctx: &mut UserContext) {
+        if self.ptrace.lock().resume_mode != PtraceResumeMode::Syscall {
+            return;
+        }
+
+        if let Some(sig_num) = self.ptrace_stop(kind, None, user_ctx) {
+            self.enqueue_signal(Box::new(KernelSignal::new(sig_num)));
+        }
+    }
+
+    /// Reports to the tracer that the current thread has executed a new program.
+    pub fn ptrace_report_exec(&self, user_ctx: &mut UserContext) {
+        let (options, is_seized) = {
+            let state = self.ptrace.lock();
+            if state.tracer.is_none() {
+                return;
+            }
+            (state.options, state.is_seized)
+        };
+
+        if options.contains(PtraceOptions::TRACEEXEC) {
+            if let Some(sig_num) = self.ptrace_stop(PtraceStopKind::Exec, None, user_ctx) {
+                self.enqueue_signal(Box::new(KernelSignal::new(sig_num)));
+            }
+        } else if !is_seized {
+            // For compatibility, a legacy `SIGTRAP` is sent if the tracee is not attached by
+            // `PTRACE_SEIZE`.
+            self.enqueue_signal(Box::new(KernelSignal::new(SIGTRAP)));
+        }
+    }
+
+    /// Enters a ptrace-stop and waits until the tracer resumes the current thread.
+    ///
+    /// This method returns the signal that the tracer wants to deliver. If the current thread is
+    /// not traced, the signal of the stop (if any) is returned without stopping.
+    fn ptrace_stop(
+        &self,
+        kind: PtraceStopKind,
+        siginfo: Option<siginfo_t>,
+        user_ctx: &mut UserContext,
+    ) -> Option<SigNum> {
+        let (waiter, waker) = Waiter::new_pair();
+
+        let (tracer, code) = {
+            let mut state = self.ptrace.lock();
+            let Some(tracer) = state.tracer.as_ref().and_then(Weak::upgrade) else {
+                return kind.signal();
+            };
+
+            let code = kind.code(state.options);
+            let siginfo = siginfo.unwrap_or_else(|| siginfo_t::new(SIGTRAP, code as i32));
+            state.stop = Some(PtraceStop {
+                kind,
+                code,
+                siginfo,
+                user_ctx: user_ctx.clone(),
+                waker: waker.clone(),
+                is_reported: false,
+                is_resumed: false,
+                resume_signal: None,
+            });
+
+            (tracer, code)
+        };
+
+        // Like Linux, `si_status` only contains the signal number of the stop code.
+        let signal = ChildSignal::new(
+            CLD_TRAPPED,
+            self.tid_in_ns_of(&tracer),
+            self.credentials().ruid(),
+            (code & 0x7f) as i32,
+        );
+        tracer.enqueue_signal(signal);
+        tracer.children_wait_queue().wake_all();
+
+        // Use the waker as the signalled waker so that `SIGKILL` can wake up the thread.
+        self.set_signalled_waker(waker);
+        loop {
+            let is_resumed = self
+                .ptrace
+                .lock()
+                .stop
+                .as_ref()
+                .is_none_or(|stop| stop.is_resumed);
+            if is_resumed || self.sig_pending().contains(SIGKILL) {
+                break;
+            }
+            waiter.wait();
+        }
+        self.clear_signalled_waker();
+
+        let mut state = self.ptrace.lock();
+        let stop = state.stop.take()?;
+        if !stop.is_resumed {
+            // The thread is killed. The tracer should not see the stop anymore.
+            return None;
+        }
+
+        *user_ctx = stop.user_ctx;
+        if let PtraceStopKind::SyscallEnter(syscall_num) = stop.kind {
+            user_ctx.set_syscall_num(syscall_num);
+        }
+
+        let is_single_stepping = state.resume_mode == PtraceResumeMode::SingleStep;
+        if is_single_stepping || state.is_single_stepping {
+            set_single_step(user_ctx, is_single_stepping);
+        }
+        state.is_single_stepping = is_single_stepping;
+
+        stop.resume_signal
+    }
+
+    /// Detaches the current thread from its tracer when the thread exits.
+    ///
+    /// The exit is reported to the tracer, which can then wait for the thread, unless the tracer
+    /// is the parent and the thread is the main thread. In that case, the parent waits for the
+    /// process as usual.
+    pub(in crate::process) fn exit_ptrace(&self, exit_code: ExitCode) {
+        let tracer = {
+            let mut state = self.ptrace.lock();
+            let tracer = state.tracer.take();
+            state.reset(None);
+            tracer
+        };
+
+        let Some(tracer) = tracer.as_ref().and_then(Weak::upgrade) else {
+            return;
+        };
+
+        let process = self.process();
+        let is_parent = ptr::eq(
+            process.parent().lock().process().as_ptr(),
+            Arc::as_ptr(&tracer),
+        );
+
+        // Lock order: tracees of tracer -> ptrace state of tracee
+        let mut tracees = tracer.tracees().lock();
+        if is_parent && self.tid == process.pid() {
+            tracees.remove(&self.tid);
+            drop(tracees);
+        } else {
+            self.ptrace.lock().exit_code = Some(exit_code);
+            drop(tracees);
+
+            let signal = ChildSignal::new_exited(
+                self.tid_in_ns_of(&tracer),
+                self.credentials().ruid(),
+                exit_code,
+            );
+            tracer.enqueue_signal(signal);
+        }
+
+        tracer.children_wait_queue().wake_all();
+    }
+
+    /// Returns the thread ID in the PID namespace of the process.
+    fn tid_in_ns_of(&self, process: &Process) -> Tid {
+        let main_thread = process.main_thread();
+        let pid_ns = main_thread.as_posix_thread().unwrap().pid_ns();
+        pid_ns.to_local(self.tid)
+    }
+}
+
+/// Detaches all the tracees of the process when the process exits.
+pub(in crate::process) fn exit_ptrace_tracer(tracer: &Process) {
+    let tracees = core::mem::take(&mut *tracer.tracees().lock());
+
+    for tracee in tracees.values() {
+        let posix_thread = tracee.as_posix_thread().unwrap();
+
+        let mut state = posix_thread.ptrace.lock();
+        if !state.is_traced_by(tracer) {
+            continue;
+        }
+        let options = state.options;
+        state.reset(None);
+        drop(state);
+
+        if options.contains(PtraceOptions::EXITKILL) {
+            posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGKILL)));
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        /// Sets or clears the trap flag in the user context.
+        fn set_single_step(user_ctx: &mut UserContext, is_enabled: bool) {
+            // Bit 8 is the TF flag.
+            const X86_RFLAGS_TF: usize = 1 << 8;
+
+            if is_enabled {
+                user_ctx.general_regs_mut().rflags |= X86_RFLAGS_TF;
+            } else {
+                user_ctx.general_regs_mut().rflags &= !X86_RFLAGS_TF;
+            }
+        }
+    } else {
+        // Single-stepping is not supported on this architecture. `sys_ptrace` rejects
+        // `PTRACE_SINGLESTEP`, so there is nothing to do.
+        fn set_single_step(_user_ctx: &mut UserContext, _is_enabled: bool) {}
+    }
+}
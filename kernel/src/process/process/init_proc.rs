@@ -64,6 +64,7 @@ fn create_init_process(
         resource_limits,
         nice,
         sig_dispositions,
+        None,
     );
 
     let init_task = create_init_task(
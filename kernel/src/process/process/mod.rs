@@ -4,6 +4,7 @@ use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 use self::timer_manager::PosixTimerManager;
 use super::{
+    coredump::{AtomicDumpable, Dumpable},
     posix_thread::AsPosixThread,
     process_table,
     process_vm::{Heap, InitStackReader, ProcessVm, ProcessVmarGuard},
@@ -17,10 +18,11 @@ use super::{
     task_set::TaskSet,
 };
 use crate::{
+    fs::cgroupfs::CgroupNode,
     prelude::*,
     process::{signal::Pollee, status::StopWaitStatus, WaitOptions},
     sched::{AtomicNice, Nice},
-    thread::{AsThread, Thread},
+    thread::{AsThread, Thread, Tid},
     time::clocks::ProfClock,
 };
 
@@ -77,6 +79,8 @@ pub struct Process {
     pub(super) parent: ParentProcess,
     /// Children processes
     children: Mutex<BTreeMap<Pid, Arc<Process>>>,
+    /// Threads traced by this process
+    tracees: Mutex<BTreeMap<Tid, Arc<Thread>>>,
     /// Process group
     pub(super) process_group: Mutex<Weak<ProcessGroup>>,
     /// resource limits
@@ -85,6 +89,8 @@ pub struct Process {
     /// According to POSIX.1, the nice value is a per-process attribute,
     /// the threads in a process should share a nice value.
     nice: AtomicNice,
+    /// The cgroup of the process, or `None` if the process is in the root cgroup.
+    cgroup: Mutex<Option<Arc<CgroupNode>>>,
 
     // Child reaper attribute
     /// Whether the process is a child subreaper.
@@ -110,6 +116,9 @@ pub struct Process {
     /// The signal that should be sent to the parent when this process exits.
     exit_signal: AtomicSigNum,
 
+    /// Whether a core dump can be generated for the process.
+    dumpable: AtomicDumpable,
+
     /// A profiling clock measures the user CPU time and kernel CPU time of the current process.
     prof_clock: Arc<ProfClock>,
 
@@ -193,6 +202,7 @@ impl Process {
         resource_limits: ResourceLimits,
         nice: Nice,
         sig_dispositions: Arc<Mutex<SigDispositions>>,
+        cgroup: Option<Arc<CgroupNode>>,
     ) -> Arc<Self> {
         // SIGCHID does not interrupt pauser. Child process will
         // resume paused parent when doing exit.
@@ -210,14 +220,17 @@ impl Process {
             status: ProcessStatus::default(),
             parent: ParentProcess::new(parent),
             children: Mutex::new(BTreeMap::new()),
+            tracees: Mutex::new(BTreeMap::new()),
             process_group: Mutex::new(Weak::new()),
             is_child_subreaper: AtomicBool::new(false),
             has_child_subreaper: AtomicBool::new(false),
             sig_dispositions,
             parent_death_signal: AtomicSigNum::new_empty(),
             exit_signal: AtomicSigNum::new_empty(),
+            dumpable: AtomicDumpable::new(Dumpable::User),
             resource_limits,
             nice: AtomicNice::new(nice),
+            cgroup: Mutex::new(cgroup),
             timer_manager: PosixTimerManager::new(&prof_clock, process_ref),
             prof_clock,
         })
@@ -275,6 +288,13 @@ impl Process {
         &self.nice
     }
 
+    /// Returns the cgroup of the process, or `None` if the process is in the root cgroup.
+    ///
+    /// To avoid deadlocks, the lock should be acquired before locking [`Self::tasks`].
+    pub fn cgroup(&self) -> &Mutex<Option<Arc<CgroupNode>>> {
+        &self.cgroup
+    }
+
     pub fn main_thread(&self) -> Arc<Thread> {
         self.tasks.lock().main().as_thread().unwrap().clone()
     }
@@ -297,6 +317,14 @@ impl Process {
         &self.children_wait_queue
     }
 
+    /// Returns the threads traced by this process.
+    ///
+    /// To avoid deadlocks, the lock should be acquired after locking [`Self::children`] and
+    /// before locking the ptrace state of any tracee.
+    pub(in crate::process) fn tracees(&self) -> &Mutex<BTreeMap<Tid, Arc<Thread>>> {
+        &self.tracees
+    }
+
     // *********** Process group & Session ***********
 
     /// Returns the process group ID of the process.
@@ -671,6 +699,16 @@ impl Process {
         self.exit_signal.as_sig_num()
     }
 
+    /// Returns whether a core dump can be generated for the process.
+    pub fn dumpable(&self) -> Dumpable {
+        self.dumpable.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether a core dump can be generated for the process.
+    pub fn set_dumpable(&self, dumpable: Dumpable) {
+        self.dumpable.store(dumpable, Ordering::Relaxed);
+    }
+
     // ******************* Status ********************
 
     /// Returns a reference to the process status.
@@ -678,10 +716,11 @@ impl Process {
         &self.status
     }
 
-    /// Stops the process.
-    //
-    // FIXME: `ptrace` is another reason that can cause a process to stop.
-    // Consider extending the method signature to support `ptrace` if necessary.
+    /// Stops the process due to job control.
+    ///
+    /// Note that ptrace-stops are per-thread, so they are managed by [`PosixThread`] instead.
+    ///
+    /// [`PosixThread`]: crate::process::posix_thread::PosixThread
     pub fn stop(&self, sig_num: SigNum) {
         if self.status.stop_status().stop(sig_num) {
             self.wake_up_parent();
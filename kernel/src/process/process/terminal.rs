@@ -4,10 +4,13 @@ use alloc::sync::Arc;
 
 use super::{session::SessionGuard, JobControl, Pgid, Process, Session, Sid};
 use crate::{
-    current_userspace,
+    current_thread, current_userspace,
     fs::{device::Device, inode_handle::FileIo, utils::IoctlCmd},
     prelude::{current, return_errno_with_message, warn, Errno, Error, Result},
-    process::process_table,
+    process::{
+        posix_thread::AsPosixThread,
+        process_table::{self, PidNamespace},
+    },
 };
 
 /// A terminal.
@@ -28,6 +31,12 @@ impl dyn Terminal {
                 if pgid.cast_signed() < 0 {
                     return_errno_with_message!(Errno::EINVAL, "negative PGIDs are not valid");
                 }
+                let pgid = current_pid_ns().to_global(pgid).ok_or_else(|| {
+                    Error::with_message(
+                        Errno::ESRCH,
+                        "the process group to be foreground does not exist",
+                    )
+                })?;
 
                 self.set_foreground(pgid, &current!())
             }
@@ -43,6 +52,7 @@ impl dyn Terminal {
                 } else {
                     self.is_control_and(&current!(), |_, _| Ok(operate()))?
                 };
+                let pgid = current_pid_ns().to_local(pgid);
 
                 current_userspace!().write_val::<Pgid>(arg, &pgid)
             }
@@ -79,6 +89,7 @@ impl dyn Terminal {
                 } else {
                     self.is_control_and(&current!(), |session, _| Ok(session.sid()))?
                 };
+                let sid = current_pid_ns().to_local(sid);
 
                 current_userspace!().write_val::<Sid>(arg, &sid)
             }
@@ -206,3 +217,11 @@ impl dyn Terminal {
         op(&session, &mut session_inner)
     }
 }
+
+/// Returns the PID namespace of the current thread.
+///
+/// The process group IDs and the session IDs in the `ioctl` arguments are in this namespace.
+fn current_pid_ns() -> Arc<PidNamespace> {
+    let current_thread = current_thread!();
+    current_thread.as_posix_thread().unwrap().pid_ns().clone()
+}
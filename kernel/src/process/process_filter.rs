@@ -3,6 +3,9 @@
 use super::{Pgid, Pid};
 use crate::{fs::file_table::get_file_fast, prelude::*, process::PidFile};
 
+/// A filter of processes.
+///
+/// The process IDs and the process group IDs are in the PID namespace of the current thread.
 #[derive(Debug, Clone)]
 pub enum ProcessFilter {
     Any,
@@ -40,7 +43,7 @@ impl ProcessFilter {
     }
 
     // For `wait4` and `kill`.
-    pub fn from_id(wait_pid: i32) -> Self {
+    pub fn from_id(wait_pid: i32, ctx: &Context) -> Self {
         // Reference:
         // <https://man7.org/linux/man-pages/man2/waitpid.2.html>
         // <https://man7.org/linux/man-pages/man2/kill.2.html>
@@ -54,7 +57,7 @@ impl ProcessFilter {
         } else if wait_pid == 0 {
             // "wait for any child process whose process group ID is equal to that of the calling
             // process at the time of the call to `waitpid()`"
-            let pgid = current!().pgid();
+            let pgid = ctx.posix_thread.pid_ns().to_local(ctx.process.pgid());
             ProcessFilter::WithPgid(pgid)
         } else {
             // "wait for the child whose process ID is equal to the value of `pid`"
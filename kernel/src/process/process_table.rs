@@ -5,13 +5,22 @@
 //! A global table stores the pid to process mapping.
 //! This table can be used to get process with pid.
 //! TODO: progress group, thread all need similar mapping
+//!
+//! The pids in the global table are the pids in the initial PID namespace. The pids seen by
+//! processes in other PID namespaces are translated by [`PidNamespace`].
 
 use alloc::collections::btree_map::Values;
 
-use super::{Pgid, Pid, Process, ProcessGroup, Session, Sid};
+use spin::Once;
+
+use super::{
+    namespace::alloc_ns_id, posix_thread::thread_table, Pgid, Pid, Process, ProcessGroup, Session,
+    Sid,
+};
 use crate::{
     events::{Events, Observer, Subject},
     prelude::*,
+    thread::{Thread, Tid},
 };
 
 static PROCESS_TABLE: Mutex<ProcessTable> = Mutex::new(ProcessTable::new());
@@ -113,6 +122,148 @@ pub(super) fn session_table_mut() -> MutexGuard<'static, BTreeMap<Sid, Arc<Sessi
     SESSION_TABLE.lock()
 }
 
+// ************ PID namespace *************
+
+/// A PID namespace.
+///
+/// Each thread has one ID in its own PID namespace and one ID in every ancestor namespace. The ID
+/// in the initial namespace is the global ID that is used as the key of the global tables.
+///
+/// FIXME: The directories in procfs are still named by global IDs, which may be inaccurate in
+/// non-initial namespaces. The IDs of a thread are freed once the thread is dropped, so a process
+/// group ID or a session ID cannot be translated after its leader has been reaped. The init
+/// process of a non-initial namespace does not reap the orphaned processes either.
+pub struct PidNamespace {
+    id: u64,
+    level: usize,
+    parent: Option<Arc<PidNamespace>>,
+    ids: SpinLock<PidNsIds>,
+}
+
+/// The ID mappings of a non-initial PID namespace.
+struct PidNsIds {
+    next_id: Tid,
+    global_ids: BTreeMap<Tid, Tid>,
+    local_ids: BTreeMap<Tid, Tid>,
+}
+
+/// The maximum nesting depth of PID namespaces.
+const MAX_PID_NS_LEVEL: usize = 32;
+
+static INIT_PID_NS: Once<Arc<PidNamespace>> = Once::new();
+
+/// Returns the initial PID namespace.
+pub fn init_pid_ns() -> &'static Arc<PidNamespace> {
+    INIT_PID_NS.call_once(|| Arc::new(PidNamespace::new(0, None)))
+}
+
+impl PidNamespace {
+    fn new(level: usize, parent: Option<Arc<PidNamespace>>) -> Self {
+        Self {
+            id: alloc_ns_id(),
+            level,
+            parent,
+            ids: SpinLock::new(PidNsIds {
+                next_id: 1,
+                global_ids: BTreeMap::new(),
+                local_ids: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Creates a child PID namespace.
+    pub fn new_child(self: &Arc<Self>) -> Result<Arc<Self>> {
+        if self.level >= MAX_PID_NS_LEVEL {
+            return_errno_with_message!(Errno::ENOSPC, "the PID namespaces are nested too deeply");
+        }
+
+        Ok(Arc::new(Self::new(self.level + 1, Some(self.clone()))))
+    }
+
+    /// Returns the ID of the namespace.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns whether this namespace is the initial PID namespace.
+    pub fn is_init(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    /// Returns whether this namespace is `other` or one of the ancestors of `other`.
+    pub fn is_ancestor_of(&self, other: &Arc<PidNamespace>) -> bool {
+        let mut ns = Some(other);
+        while let Some(current) = ns {
+            if core::ptr::eq(self, Arc::as_ptr(current)) {
+                return true;
+            }
+            ns = current.parent.as_ref();
+        }
+        false
+    }
+
+    /// Allocates the IDs in this namespace and its ancestors for the thread with the global ID.
+    pub(super) fn alloc_ids(&self, global_tid: Tid) {
+        let mut ns = Some(self);
+        while let Some(current) = ns.filter(|ns| !ns.is_init()) {
+            let mut ids = current.ids.lock();
+            let local_tid = ids.next_id;
+            ids.next_id += 1;
+            ids.global_ids.insert(local_tid, global_tid);
+            ids.local_ids.insert(global_tid, local_tid);
+            ns = current.parent.as_deref();
+        }
+    }
+
+    /// Frees the IDs in this namespace and its ancestors for the thread with the global ID.
+    pub(super) fn free_ids(&self, global_tid: Tid) {
+        let mut ns = Some(self);
+        while let Some(current) = ns.filter(|ns| !ns.is_init()) {
+            let mut ids = current.ids.lock();
+            if let Some(local_tid) = ids.local_ids.remove(&global_tid) {
+                ids.global_ids.remove(&local_tid);
+            }
+            ns = current.parent.as_deref();
+        }
+    }
+
+    /// Translates an ID in this namespace to the global ID.
+    ///
+    /// Returns `None` if no thread has the ID in this namespace.
+    pub fn to_global(&self, local_tid: Tid) -> Option<Tid> {
+        if self.is_init() {
+            return Some(local_tid);
+        }
+        self.ids.lock().global_ids.get(&local_tid).copied()
+    }
+
+    /// Translates a global ID to the ID in this namespace.
+    ///
+    /// Returns zero if the thread is invisible in this namespace, which is also what Linux
+    /// reports to the user space in this case.
+    pub fn to_local(&self, global_tid: Tid) -> Tid {
+        if self.is_init() {
+            return global_tid;
+        }
+        self.ids
+            .lock()
+            .local_ids
+            .get(&global_tid)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Gets a thread with the ID in this namespace.
+    pub fn get_thread(&self, local_tid: Tid) -> Option<Arc<Thread>> {
+        thread_table::get_thread(self.to_global(local_tid)?)
+    }
+
+    /// Gets a process with the ID in this namespace.
+    pub fn get_process(&self, local_pid: Pid) -> Option<Arc<Process>> {
+        get_process(self.to_global(local_pid)?)
+    }
+}
+
 // ************ Observer *************
 
 /// Registers an observer which watches `PidEvent`.
@@ -496,6 +496,53 @@ impl InitStackReader<'_> {
         Ok(envp)
     }
 
+    /// Reads the auxiliary vector from the process init stack.
+    ///
+    /// The returned key-value pairs include the terminating `AT_NULL` entry.
+    pub fn auxv(&self) -> Result<Vec<(u64, u64)>> {
+        // The init stack can be modified by the user program, so the number of entries is
+        // bounded to avoid reading forever.
+        const MAX_NR_AUX_ENTRIES: usize = 64;
+
+        let argc = self.argc()? as usize;
+        // The auxiliary vector is located after the envp pointers, which start after the
+        // argv pointers.
+        let read_offset = self.init_stack_bottom()
+            + size_of::<usize>()
+            + size_of::<usize>() * argc
+            + size_of::<usize>();
+
+        let vmar = self.vmar.unwrap();
+        let read_u64 = |addr: Vaddr| -> Result<u64> {
+            let mut val = 0u64;
+            vmar.read_remote(addr, val.as_bytes_mut())?;
+            Ok(val)
+        };
+
+        let mut ptr_addr = read_offset;
+        for _ in 0..MAX_NR_STRING_ARGS {
+            let envp_ptr = read_u64(ptr_addr)?;
+            ptr_addr += size_of::<u64>();
+            if envp_ptr == 0 {
+                break;
+            }
+        }
+
+        let mut auxv = Vec::new();
+        for _ in 0..MAX_NR_AUX_ENTRIES {
+            let key = read_u64(ptr_addr)?;
+            let value = read_u64(ptr_addr + size_of::<u64>())?;
+            ptr_addr += size_of::<u64>() * 2;
+
+            auxv.push((key, value));
+            if key == AuxKey::AT_NULL as u64 {
+                return Ok(auxv);
+            }
+        }
+
+        return_errno_with_message!(Errno::EINVAL, "the auxiliary vector is corrupted");
+    }
+
     /// Returns the bottom address of the init stack (lowest address).
     pub const fn init_stack_bottom(&self) -> Vaddr {
         self.base
@@ -373,7 +373,7 @@ fn map_segment_vmo(
     if segment_size != 0 {
         let mut vm_map_options = root_vmar
             .new_map(segment_size, perms)?
-            .vmo(segment_vmo.dup()?)
+            .path(elf_file.clone())
             .vmo_offset(segment_offset)
             .can_overwrite(true);
         vm_map_options = vm_map_options.offset(offset).handle_page_faults_around();
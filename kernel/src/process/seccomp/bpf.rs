@@ -0,0 +1,591 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The classic BPF (cBPF) programs used by seccomp filters.
+//!
+//! Only the subset of cBPF that is allowed by seccomp is supported. In particular, the only way
+//! to access the input data is loading an aligned 32-bit word at a constant offset.
+//!
+//! Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/kernel/seccomp.c#L278>
+
+use crate::prelude::*;
+
+/// The maximum number of instructions in a program.
+pub const BPF_MAXINSNS: usize = 4096;
+
+/// The number of words in the scratch memory.
+const BPF_MEMWORDS: usize = 16;
+
+/// A raw cBPF instruction.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/filter.h#L24>
+#[expect(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+pub(super) struct sock_filter {
+    pub(super) code: u16,
+    pub(super) jt: u8,
+    pub(super) jf: u8,
+    pub(super) k: u32,
+}
+
+/// A raw cBPF program in the user space.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/filter.h#L31>
+#[expect(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct sock_fprog {
+    len: u16,
+    _padding: [u8; 6],
+    filter: Vaddr,
+}
+
+/// A validated cBPF program.
+#[derive(Debug)]
+pub struct BpfProgram {
+    insns: Box<[BpfInsn]>,
+}
+
+impl BpfProgram {
+    /// Reads and validates a cBPF program from the user space.
+    pub fn read_from_user(fprog_addr: Vaddr, ctx: &Context) -> Result<Self> {
+        let user_space = ctx.user_space();
+
+        let fprog = user_space.read_val::<sock_fprog>(fprog_addr)?;
+        let len = fprog.len as usize;
+        if len == 0 || len > BPF_MAXINSNS {
+            return_errno_with_message!(Errno::EINVAL, "the program length is invalid");
+        }
+
+        let mut raw_insns = Vec::with_capacity(len);
+        for i in 0..len {
+            let raw_insn =
+                user_space.read_val::<sock_filter>(fprog.filter + i * size_of::<sock_filter>())?;
+            raw_insns.push(raw_insn);
+        }
+
+        Self::new(&raw_insns, size_of::<super::seccomp_data>())
+    }
+
+    /// Validates the raw instructions and creates a program.
+    ///
+    /// The program can only load data within `data_len` bytes.
+    pub(super) fn new(raw_insns: &[sock_filter], data_len: usize) -> Result<Self> {
+        let len = raw_insns.len();
+
+        let insns = raw_insns
+            .iter()
+            .enumerate()
+            .map(|(pc, raw_insn)| {
+                let insn = BpfInsn::decode(raw_insn, data_len)?;
+                // Jumps can only go forward and must not go beyond the end of the program.
+                let max_offset = insn.max_jump_offset();
+                if pc + 1 + max_offset >= len {
+                    return_errno_with_message!(Errno::EINVAL, "the jump target is out of range");
+                }
+                Ok(insn)
+            })
+            .collect::<Result<Box<[_]>>>()?;
+
+        if !matches!(insns.last(), Some(BpfInsn::RetK(_) | BpfInsn::RetA)) {
+            return_errno_with_message!(Errno::EINVAL, "the program does not end with a return");
+        }
+
+        Ok(Self { insns })
+    }
+
+    /// Returns the number of instructions.
+    pub fn num_insns(&self) -> usize {
+        self.insns.len()
+    }
+
+    /// Runs the program with the input data and returns the result.
+    pub fn run(&self, data: &[u8]) -> u32 {
+        let mut a: u32 = 0;
+        let mut x: u32 = 0;
+        let mut mem = [0u32; BPF_MEMWORDS];
+
+        let mut pc = 0;
+        loop {
+            let insn = &self.insns[pc];
+            pc += 1;
+
+            match *insn {
+                BpfInsn::LdAbs(offset) => {
+                    let bytes = &data[offset as usize..offset as usize + size_of::<u32>()];
+                    a = u32::from_ne_bytes(bytes.try_into().unwrap());
+                }
+                BpfInsn::LdImm(k) => a = k,
+                BpfInsn::LdxImm(k) => x = k,
+                BpfInsn::LdMem(index) => a = mem[index],
+                BpfInsn::LdxMem(index) => x = mem[index],
+                BpfInsn::St(index) => mem[index] = a,
+                BpfInsn::Stx(index) => mem[index] = x,
+                BpfInsn::Alu(op, src) => {
+                    let operand = src.value(x);
+                    a = match op {
+                        AluOp::Add => a.wrapping_add(operand),
+                        AluOp::Sub => a.wrapping_sub(operand),
+                        AluOp::Mul => a.wrapping_mul(operand),
+                        AluOp::Div => {
+                            // Division by a zero constant is rejected during validation, so this
+                            // can only happen if `X` is zero.
+                            if operand == 0 {
+                                return 0;
+                            }
+                            a / operand
+                        }
+                        AluOp::Or => a | operand,
+                        AluOp::And => a & operand,
+                        AluOp::Lsh => a.wrapping_shl(operand),
+                        AluOp::Rsh => a.wrapping_shr(operand),
+                        AluOp::Xor => a ^ operand,
+                    };
+                }
+                BpfInsn::Neg => a = a.wrapping_neg(),
+                BpfInsn::Ja(offset) => pc += offset as usize,
+                BpfInsn::Jmp(op, src, jt, jf) => {
+                    let operand = src.value(x);
+                    let is_true = match op {
+                        JmpOp::Jeq => a == operand,
+                        JmpOp::Jgt => a > operand,
+                        JmpOp::Jge => a >= operand,
+                        JmpOp::Jset => a & operand != 0,
+                    };
+                    pc += if is_true { jt } else { jf } as usize;
+                }
+                BpfInsn::RetK(k) => return k,
+                BpfInsn::RetA => return a,
+                BpfInsn::Tax => x = a,
+                BpfInsn::Txa => a = x,
+            }
+        }
+    }
+}
+
+/// A decoded cBPF instruction.
+#[derive(Clone, Copy, Debug)]
+enum BpfInsn {
+    /// `A = data[k]`, where `k` is the byte offset of a 32-bit word
+    LdAbs(u32),
+    /// `A = k`
+    LdImm(u32),
+    /// `X = k`
+    LdxImm(u32),
+    /// `A = M[k]`
+    LdMem(usize),
+    /// `X = M[k]`
+    LdxMem(usize),
+    /// `M[k] = A`
+    St(usize),
+    /// `M[k] = X`
+    Stx(usize),
+    /// `A = A <op> src`
+    Alu(AluOp, Src),
+    /// `A = -A`
+    Neg,
+    /// `pc += k`
+    Ja(u32),
+    /// `pc += (A <op> src) ? jt : jf`
+    Jmp(JmpOp, Src, u8, u8),
+    /// `return k`
+    RetK(u32),
+    /// `return A`
+    RetA,
+    /// `X = A`
+    Tax,
+    /// `A = X`
+    Txa,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum AluOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Or,
+    And,
+    Lsh,
+    Rsh,
+    Xor,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum JmpOp {
+    Jeq,
+    Jgt,
+    Jge,
+    Jset,
+}
+
+/// The source operand of ALU and jump instructions.
+#[derive(Clone, Copy, Debug)]
+enum Src {
+    K(u32),
+    X,
+}
+
+impl Src {
+    fn value(&self, x: u32) -> u32 {
+        match self {
+            Self::K(k) => *k,
+            Self::X => x,
+        }
+    }
+}
+
+// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/bpf_common.h>
+pub(super) mod code {
+    // Instruction classes
+    pub const LD: u16 = 0x00;
+    pub const LDX: u16 = 0x01;
+    pub const ST: u16 = 0x02;
+    pub const STX: u16 = 0x03;
+    pub const ALU: u16 = 0x04;
+    pub const JMP: u16 = 0x05;
+    pub const RET: u16 = 0x06;
+    pub const MISC: u16 = 0x07;
+
+    // Modes of `LD` and `LDX`
+    pub const IMM: u16 = 0x00;
+    pub const ABS: u16 = 0x20;
+    pub const MEM: u16 = 0x60;
+    pub const LEN: u16 = 0x80;
+
+    // Operations of `ALU` and `JMP`
+    pub const ADD: u16 = 0x00;
+    pub const SUB: u16 = 0x10;
+    pub const MUL: u16 = 0x20;
+    pub const DIV: u16 = 0x30;
+    pub const OR: u16 = 0x40;
+    pub const AND: u16 = 0x50;
+    pub const LSH: u16 = 0x60;
+    pub const RSH: u16 = 0x70;
+    pub const NEG: u16 = 0x80;
+    pub const XOR: u16 = 0xa0;
+    pub const JA: u16 = 0x00;
+    pub const JEQ: u16 = 0x10;
+    pub const JGT: u16 = 0x20;
+    pub const JGE: u16 = 0x30;
+    pub const JSET: u16 = 0x40;
+
+    // Sources of `ALU`, `JMP` and `RET`
+    pub const K: u16 = 0x00;
+    pub const X: u16 = 0x08;
+    pub const A: u16 = 0x10;
+
+    // Operations of `MISC`
+    pub const TAX: u16 = 0x00;
+    pub const TXA: u16 = 0x80;
+}
+
+impl BpfInsn {
+    fn decode(raw_insn: &sock_filter, data_len: usize) -> Result<Self> {
+        use code::*;
+
+        let sock_filter { code, jt, jf, k } = *raw_insn;
+        if code > 0xff {
+            return_errno_with_message!(Errno::EINVAL, "the instruction code is invalid");
+        }
+
+        let mem_index = || {
+            if (k as usize) < BPF_MEMWORDS {
+                Ok(k as usize)
+            } else {
+                Err(Error::with_message(
+                    Errno::EINVAL,
+                    "the memory index is out of range",
+                ))
+            }
+        };
+        let src = || match code & 0x08 {
+            K => Src::K(k),
+            _ => Src::X,
+        };
+
+        // The instruction code consists of the class (bits 0-2) and the class-specific fields
+        // (bits 3-7). Note that the size `W` is zero, so it is implied in the modes below.
+        let insn = match (code & 0x07, code & 0xf8) {
+            (LD, ABS) => {
+                if k % 4 != 0 || k as usize >= data_len {
+                    return_errno_with_message!(Errno::EINVAL, "the load offset is invalid");
+                }
+                Self::LdAbs(k)
+            }
+            // The length of the data is fixed.
+            (LD, LEN) => Self::LdImm(data_len as u32),
+            (LDX, LEN) => Self::LdxImm(data_len as u32),
+            (LD, IMM) => Self::LdImm(k),
+            (LDX, IMM) => Self::LdxImm(k),
+            (LD, MEM) => Self::LdMem(mem_index()?),
+            (LDX, MEM) => Self::LdxMem(mem_index()?),
+            (ST, 0) => Self::St(mem_index()?),
+            (STX, 0) => Self::Stx(mem_index()?),
+            (ALU, NEG) => Self::Neg,
+            (ALU, _) => {
+                let op = match code & 0xf0 {
+                    ADD => AluOp::Add,
+                    SUB => AluOp::Sub,
+                    MUL => AluOp::Mul,
+                    DIV => AluOp::Div,
+                    OR => AluOp::Or,
+                    AND => AluOp::And,
+                    LSH => AluOp::Lsh,
+                    RSH => AluOp::Rsh,
+                    XOR => AluOp::Xor,
+                    _ => return_errno_with_message!(Errno::EINVAL, "the ALU operation is invalid"),
+                };
+                let src = src();
+                if let Src::K(k) = src {
+                    if matches!(op, AluOp::Div) && k == 0 {
+                        return_errno_with_message!(Errno::EINVAL, "the divisor is zero");
+                    }
+                    if matches!(op, AluOp::Lsh | AluOp::Rsh) && k >= 32 {
+                        return_errno_with_message!(Errno::EINVAL, "the shift is too large");
+                    }
+                }
+                Self::Alu(op, src)
+            }
+            (JMP, JA) => Self::Ja(k),
+            (JMP, _) => {
+                let op = match code & 0xf0 {
+                    JEQ => JmpOp::Jeq,
+                    JGT => JmpOp::Jgt,
+                    JGE => JmpOp::Jge,
+                    JSET => JmpOp::Jset,
+                    _ => return_errno_with_message!(Errno::EINVAL, "the jump operation is invalid"),
+                };
+                Self::Jmp(op, src(), jt, jf)
+            }
+            (RET, K) => Self::RetK(k),
+            (RET, A) => Self::RetA,
+            (MISC, TAX) => Self::Tax,
+            (MISC, TXA) => Self::Txa,
+            _ => return_errno_with_message!(Errno::EINVAL, "the instruction is not allowed"),
+        };
+
+        Ok(insn)
+    }
+
+    /// Returns the maximum offset that the instruction may jump.
+    fn max_jump_offset(&self) -> usize {
+        match self {
+            Self::Ja(offset) => *offset as usize,
+            Self::Jmp(_, _, jt, jf) => (*jt).max(*jf) as usize,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::{code::*, *};
+
+    const DATA_LEN: usize = 64;
+
+    fn insn(code: u16, jt: u8, jf: u8, k: u32) -> sock_filter {
+        sock_filter { code, jt, jf, k }
+    }
+
+    fn stmt(code: u16, k: u32) -> sock_filter {
+        insn(code, 0, 0, k)
+    }
+
+    fn run_with(raw_insns: &[sock_filter], data: &[u8; DATA_LEN]) -> u32 {
+        BpfProgram::new(raw_insns, DATA_LEN).unwrap().run(data)
+    }
+
+    fn run(raw_insns: &[sock_filter]) -> u32 {
+        run_with(raw_insns, &[0; DATA_LEN])
+    }
+
+    fn new_err(raw_insns: &[sock_filter]) -> Errno {
+        BpfProgram::new(raw_insns, DATA_LEN).unwrap_err().error()
+    }
+
+    /// Computes `a <op> operand`, taking the operand from `K` and `X`, respectively.
+    fn alu(op: u16, a: u32, operand: u32) -> [u32; 2] {
+        let with_k = run(&[
+            stmt(LD | IMM, a),
+            stmt(ALU | op | K, operand),
+            stmt(RET | A, 0),
+        ]);
+        let with_x = run(&[
+            stmt(LD | IMM, a),
+            stmt(LDX | IMM, operand),
+            stmt(ALU | op | X, 0),
+            stmt(RET | A, 0),
+        ]);
+        [with_k, with_x]
+    }
+
+    /// Returns whether `a <op> operand` jumps to the true branch, taking the operand from `K`
+    /// and `X`, respectively.
+    fn jmp(op: u16, a: u32, operand: u32) -> [bool; 2] {
+        let with_k = run(&[
+            stmt(LD | IMM, a),
+            insn(JMP | op | K, 1, 0, operand),
+            stmt(RET | K, 0),
+            stmt(RET | K, 1),
+        ]);
+        let with_x = run(&[
+            stmt(LD | IMM, a),
+            stmt(LDX | IMM, operand),
+            insn(JMP | op | X, 1, 0, 0),
+            stmt(RET | K, 0),
+            stmt(RET | K, 1),
+        ]);
+        [with_k == 1, with_x == 1]
+    }
+
+    #[ktest]
+    fn alu_ops() {
+        let cases = [
+            (ADD, 5, 3, 8),
+            (SUB, 3, 5, u32::MAX - 1),
+            (MUL, 0x1_0000, 0x1_0001, 0x1_0000),
+            (DIV, 7, 2, 3),
+            (OR, 0b1100, 0b1010, 0b1110),
+            (AND, 0b1100, 0b1010, 0b1000),
+            (LSH, 1, 31, 0x8000_0000),
+            (RSH, 0x8000_0000, 31, 1),
+            (XOR, 0b1100, 0b1010, 0b0110),
+        ];
+        for (op, a, operand, result) in cases {
+            assert_eq!(alu(op, a, operand), [result; 2], "op {:#x}", op);
+        }
+
+        let neg = [stmt(LD | IMM, 1), stmt(ALU | NEG, 0), stmt(RET | A, 0)];
+        assert_eq!(run(&neg), u32::MAX);
+    }
+
+    #[ktest]
+    fn alu_invalid() {
+        // Division by a zero `X` terminates the program with zero.
+        let div_by_x = [
+            stmt(LD | IMM, 7),
+            stmt(LDX | IMM, 0),
+            stmt(ALU | DIV | X, 0),
+            stmt(RET | K, 1),
+        ];
+        assert_eq!(run(&div_by_x), 0);
+
+        // Division by a zero `K` and large shifts are rejected.
+        let div_by_k = [stmt(ALU | DIV | K, 0), stmt(RET | A, 0)];
+        assert_eq!(new_err(&div_by_k), Errno::EINVAL);
+        let lsh = [stmt(ALU | LSH | K, 32), stmt(RET | A, 0)];
+        assert_eq!(new_err(&lsh), Errno::EINVAL);
+        let rsh = [stmt(ALU | RSH | K, 32), stmt(RET | A, 0)];
+        assert_eq!(new_err(&rsh), Errno::EINVAL);
+        let mod_op = [stmt(ALU | 0x90 | K, 1), stmt(RET | A, 0)];
+        assert_eq!(new_err(&mod_op), Errno::EINVAL);
+    }
+
+    #[ktest]
+    fn mem_and_misc() {
+        let mem = [
+            stmt(LD | IMM, 1),
+            stmt(ST, 0),
+            stmt(LDX | IMM, 2),
+            stmt(STX, 15),
+            stmt(LD | MEM, 15),
+            stmt(LDX | MEM, 0),
+            stmt(ALU | LSH | K, 4),
+            stmt(ALU | OR | X, 0),
+            stmt(RET | A, 0),
+        ];
+        assert_eq!(run(&mem), 0x21);
+
+        let tax_txa = [
+            stmt(LD | IMM, 3),
+            stmt(MISC | TAX, 0),
+            stmt(LD | IMM, 0),
+            stmt(MISC | TXA, 0),
+            stmt(RET | A, 0),
+        ];
+        assert_eq!(run(&tax_txa), 3);
+
+        let st = [stmt(ST, 16), stmt(RET | A, 0)];
+        assert_eq!(new_err(&st), Errno::EINVAL);
+        let ldx_mem = [stmt(LDX | MEM, 16), stmt(RET | A, 0)];
+        assert_eq!(new_err(&ldx_mem), Errno::EINVAL);
+    }
+
+    #[ktest]
+    fn jmp_ops() {
+        let cases = [
+            (JEQ, 5, 5, true),
+            (JEQ, 5, 6, false),
+            (JGT, 6, 5, true),
+            (JGT, 5, 5, false),
+            (JGE, 5, 5, true),
+            (JGE, 4, 5, false),
+            (JSET, 0b1100, 0b0100, true),
+            (JSET, 0b1100, 0b0011, false),
+        ];
+        for (op, a, operand, is_true) in cases {
+            assert_eq!(jmp(op, a, operand), [is_true; 2], "op {:#x}", op);
+        }
+
+        let ja = [stmt(JMP | JA, 1), stmt(RET | K, 0), stmt(RET | K, 1)];
+        assert_eq!(run(&ja), 1);
+    }
+
+    #[ktest]
+    fn jmp_out_of_range() {
+        let ja = [stmt(JMP | JA, 1), stmt(RET | K, 0)];
+        assert_eq!(new_err(&ja), Errno::EINVAL);
+        let jt = [insn(JMP | JEQ | K, 1, 0, 0), stmt(RET | K, 0)];
+        assert_eq!(new_err(&jt), Errno::EINVAL);
+        let jf = [insn(JMP | JEQ | K, 0, 1, 0), stmt(RET | K, 0)];
+        assert_eq!(new_err(&jf), Errno::EINVAL);
+    }
+
+    #[ktest]
+    fn ret() {
+        assert_eq!(run(&[stmt(RET | K, 0x7fff_0000)]), 0x7fff_0000);
+        assert_eq!(run(&[stmt(LD | IMM, 42), stmt(RET | A, 0)]), 42);
+        assert_eq!(run(&[stmt(LD | LEN, 0), stmt(RET | A, 0)]), DATA_LEN as u32);
+
+        // The program must not be empty and must end with a return.
+        assert_eq!(new_err(&[]), Errno::EINVAL);
+        assert_eq!(new_err(&[stmt(LD | IMM, 0)]), Errno::EINVAL);
+        let no_ret = [
+            insn(JMP | JEQ | K, 0, 1, 0),
+            stmt(RET | K, 0),
+            stmt(LD | IMM, 0),
+        ];
+        assert_eq!(new_err(&no_ret), Errno::EINVAL);
+    }
+
+    #[ktest]
+    fn load() {
+        let mut data = [0u8; DATA_LEN];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        for offset in [0, 4, 16, 60] {
+            let expected = u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+            let prog = [stmt(LD | ABS, offset as u32), stmt(RET | A, 0)];
+            assert_eq!(run_with(&prog, &data), expected, "offset {}", offset);
+        }
+
+        // The offset must be aligned and within the data.
+        for offset in [2, 63, 64, u32::MAX - 3] {
+            let prog = [stmt(LD | ABS, offset), stmt(RET | A, 0)];
+            assert_eq!(new_err(&prog), Errno::EINVAL, "offset {}", offset);
+        }
+
+        // Other modes to access the data are not allowed.
+        const IND: u16 = 0x40;
+        const B: u16 = 0x10;
+        let ind = [stmt(LD | IND, 0), stmt(RET | A, 0)];
+        assert_eq!(new_err(&ind), Errno::EINVAL);
+        let byte = [stmt(LD | B | ABS, 0), stmt(RET | A, 0)];
+        assert_eq!(new_err(&byte), Errno::EINVAL);
+    }
+}
@@ -0,0 +1,503 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Secure computing (seccomp).
+//!
+//! A thread can restrict the syscalls that it can make in one of the two modes:
+//!  - In the strict mode, only `read`, `write`, `exit` and `rt_sigreturn` are allowed. Other
+//!    syscalls will kill the thread.
+//!  - In the filter mode, each syscall is checked by a chain of classic BPF programs
+//!    (i.e., [`SeccompFilter`]s), which decide the action taken for the syscall.
+//!
+//! The mode is inherited by the child threads and preserved across `execve`.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/seccomp.2.html>
+
+use ostd::{cpu::context::UserContext, user::UserContextApi};
+
+use self::bpf::BpfProgram;
+use super::{
+    credentials::capabilities::CapSet,
+    posix_thread::{do_exit, do_exit_group, AsPosixThread},
+    signal::{
+        c_types::siginfo_t,
+        constants::{SIGKILL, SIGSYS},
+        sig_num::SigNum,
+        signals::Signal,
+    },
+    TermStatus,
+};
+use crate::{cpu::LinuxAbi, prelude::*, thread::Tid};
+
+mod bpf;
+
+/// The seccomp mode of a thread.
+#[derive(Clone, Debug, Default)]
+pub enum SeccompMode {
+    #[default]
+    Disabled,
+    Strict,
+    Filter(Arc<SeccompFilter>),
+}
+
+impl SeccompMode {
+    /// Returns the mode number, which is used by `prctl(PR_GET_SECCOMP)` and
+    /// `/proc/[pid]/status`.
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            Self::Disabled => 0,
+            Self::Strict => 1,
+            Self::Filter(_) => 2,
+        }
+    }
+}
+
+/// A seccomp filter.
+///
+/// The filters installed by a thread form a chain. The newest filter points to the previously
+/// installed one, so the filters can be shared by threads that have installed the same filters
+/// (e.g., the filters are inherited from the parent).
+#[derive(Debug)]
+pub struct SeccompFilter {
+    prog: BpfProgram,
+    /// Whether the actions other than `SECCOMP_RET_ALLOW` should be logged.
+    is_logged: bool,
+    prev: Option<Arc<SeccompFilter>>,
+}
+
+impl SeccompFilter {
+    /// Runs all the filters in the chain and returns the action with the highest precedence.
+    fn run(&self, data: &seccomp_data) -> SeccompRet {
+        let mut result = SeccompRet(SECCOMP_RET_ALLOW);
+        let mut is_logged = false;
+
+        let mut filter = Some(self);
+        while let Some(current) = filter {
+            let ret = SeccompRet(current.prog.run(data.as_bytes()));
+            if ret.precedence() < result.precedence() {
+                result = ret;
+                is_logged = current.is_logged;
+            }
+            filter = current.prev.as_deref();
+        }
+
+        // `SECCOMP_RET_LOG` is always logged when the action is taken.
+        if is_logged && result.action() != SECCOMP_RET_LOG {
+            info!(
+                "seccomp: syscall {} is filtered with {:#x}",
+                data.nr, result.0
+            );
+        }
+
+        result
+    }
+
+    /// Returns the number of instructions that should be counted against the maximum limit.
+    fn path_len(&self) -> usize {
+        // Linux adds a penalty of 4 instructions for each filter.
+        const FILTER_PENALTY: usize = 4;
+
+        let mut len = 0;
+        let mut filter = Some(self);
+        while let Some(current) = filter {
+            len += current.prog.num_insns() + FILTER_PENALTY;
+            filter = current.prev.as_deref();
+        }
+        len
+    }
+
+    /// Returns whether `self` is `other` or one of its predecessors.
+    fn is_ancestor_of(self: &Arc<Self>, other: &Arc<Self>) -> bool {
+        let mut filter = Some(other);
+        while let Some(current) = filter {
+            if Arc::ptr_eq(self, current) {
+                return true;
+            }
+            filter = current.prev.as_ref();
+        }
+        false
+    }
+}
+
+impl Drop for SeccompFilter {
+    fn drop(&mut self) {
+        // Drop the chain iteratively to avoid stack overflows with long chains.
+        let mut prev = self.prev.take();
+        while let Some(filter) = prev {
+            prev = Arc::into_inner(filter).and_then(|mut filter| filter.prev.take());
+        }
+    }
+}
+
+bitflags! {
+    /// The flags of `SECCOMP_SET_MODE_FILTER`.
+    pub struct SeccompFilterFlags: u32 {
+        const TSYNC              = 1 << 0;
+        const LOG                = 1 << 1;
+        const SPEC_ALLOW         = 1 << 2;
+        const NEW_LISTENER       = 1 << 3;
+        const TSYNC_ESRCH        = 1 << 4;
+        const WAIT_KILLABLE_RECV = 1 << 5;
+    }
+}
+
+// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/seccomp.h#L38>
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+const SECCOMP_RET_DATA: u32 = 0x0000_ffff;
+
+/// The return value of a seccomp filter.
+#[derive(Clone, Copy, Debug)]
+struct SeccompRet(u32);
+
+impl SeccompRet {
+    fn action(&self) -> u32 {
+        self.0 & SECCOMP_RET_ACTION_FULL
+    }
+
+    fn data(&self) -> u16 {
+        (self.0 & SECCOMP_RET_DATA) as u16
+    }
+
+    /// Returns the precedence of the action. A smaller value means a higher precedence.
+    fn precedence(&self) -> i32 {
+        self.action() as i32
+    }
+}
+
+/// Returns whether the seccomp action is supported.
+///
+/// This is used by `SECCOMP_GET_ACTION_AVAIL`.
+pub fn is_action_available(action: u32) -> bool {
+    matches!(
+        action,
+        SECCOMP_RET_KILL_PROCESS
+            | SECCOMP_RET_KILL_THREAD
+            | SECCOMP_RET_TRAP
+            | SECCOMP_RET_ERRNO
+            | SECCOMP_RET_LOG
+            | SECCOMP_RET_ALLOW
+    )
+}
+
+/// The input data of seccomp filters.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/seccomp.h#L62>
+#[expect(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Pod)]
+#[repr(C)]
+struct seccomp_data {
+    nr: i32,
+    arch: u32,
+    instruction_pointer: u64,
+    args: [u64; 6],
+}
+
+impl seccomp_data {
+    fn new(user_ctx: &UserContext) -> Self {
+        Self {
+            nr: user_ctx.syscall_num() as i32,
+            arch: AUDIT_ARCH,
+            instruction_pointer: user_ctx.instruction_pointer() as u64,
+            args: user_ctx.syscall_args().map(|arg| arg as u64),
+        }
+    }
+}
+
+// Reference: <https://elixir.bootlin.com/linux/v6.15.7/source/include/uapi/linux/audit.h#L383>
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        const AUDIT_ARCH: u32 = 0xc000_003e;
+        /// The syscalls allowed in the strict mode: `read`, `write`, `exit` and `rt_sigreturn`.
+        const STRICT_SYSCALLS: [usize; 4] = [0, 1, 60, 15];
+    } else if #[cfg(target_arch = "riscv64")] {
+        const AUDIT_ARCH: u32 = 0xc000_00f3;
+        const STRICT_SYSCALLS: [usize; 4] = [63, 64, 93, 139];
+    } else if #[cfg(target_arch = "loongarch64")] {
+        const AUDIT_ARCH: u32 = 0xc000_0102;
+        const STRICT_SYSCALLS: [usize; 4] = [63, 64, 93, 139];
+    } else {
+        compile_error!("unsupported target");
+    }
+}
+
+/// Sets the seccomp mode of the current thread to the strict mode.
+pub fn set_mode_strict(ctx: &Context) -> Result<()> {
+    let mut mode = ctx.posix_thread.seccomp().lock();
+    if !matches!(*mode, SeccompMode::Disabled | SeccompMode::Strict) {
+        return_errno_with_message!(Errno::EINVAL, "the seccomp mode cannot be changed");
+    }
+
+    *mode = SeccompMode::Strict;
+    ctx.posix_thread.set_seccomp_enabled();
+    Ok(())
+}
+
+/// Installs a seccomp filter to the current thread.
+///
+/// If `SECCOMP_FILTER_FLAG_TSYNC` is specified but some thread cannot be synchronized, this
+/// method returns the TID of the thread instead of installing the filter.
+pub fn set_mode_filter(
+    fprog_addr: Vaddr,
+    flags: SeccompFilterFlags,
+    ctx: &Context,
+) -> Result<Option<Tid>> {
+    if flags.intersects(SeccompFilterFlags::NEW_LISTENER | SeccompFilterFlags::WAIT_KILLABLE_RECV) {
+        return_errno_with_message!(Errno::EINVAL, "seccomp user notification is not supported");
+    }
+    if flags.contains(SeccompFilterFlags::TSYNC_ESRCH) && !flags.contains(SeccompFilterFlags::TSYNC)
+    {
+        return_errno_with_message!(Errno::EINVAL, "TSYNC_ESRCH requires TSYNC");
+    }
+
+    // Unprivileged threads must set `no_new_privs`. Otherwise, they may fool a privileged
+    // (e.g., set-user-ID) program into a dangerous state.
+    let posix_thread = ctx.posix_thread;
+    if !posix_thread.no_new_privs() {
+        let credentials = posix_thread.credentials();
+        if !credentials.euid().is_root()
+            && !credentials.effective_capset().contains(CapSet::SYS_ADMIN)
+        {
+            return_errno_with_message!(
+                Errno::EACCES,
+                "installing seccomp filters requires no_new_privs or CAP_SYS_ADMIN"
+            );
+        }
+    }
+
+    let prog = BpfProgram::read_from_user(fprog_addr, ctx)?;
+
+    // Lock the tasks to synchronize with other threads in the process.
+    let tasks = ctx.process.tasks().lock();
+
+    let mut mode = posix_thread.seccomp().lock();
+    let prev = match &*mode {
+        SeccompMode::Disabled => None,
+        SeccompMode::Filter(filter) => Some(filter.clone()),
+        SeccompMode::Strict => {
+            return_errno_with_message!(Errno::EINVAL, "the seccomp mode cannot be changed")
+        }
+    };
+
+    let new_filter = Arc::new(SeccompFilter {
+        prog,
+        is_logged: flags.contains(SeccompFilterFlags::LOG),
+        prev: prev.clone(),
+    });
+    const MAX_INSNS_PER_PATH: usize = 32768;
+    if new_filter.path_len() > MAX_INSNS_PER_PATH {
+        return_errno_with_message!(Errno::ENOMEM, "too many seccomp filter instructions");
+    }
+
+    if !flags.contains(SeccompFilterFlags::TSYNC) {
+        *mode = SeccompMode::Filter(new_filter);
+        posix_thread.set_seccomp_enabled();
+        return Ok(None);
+    }
+    drop(mode);
+
+    // Other threads can be synchronized only if their filters are the predecessors of the filter
+    // of the current thread.
+    let other_threads = tasks
+        .as_slice()
+        .iter()
+        .map(|task| task.as_posix_thread().unwrap())
+        .filter(|thread| !core::ptr::eq(*thread, posix_thread));
+    for thread in other_threads.clone() {
+        let can_sync = match (&*thread.seccomp().lock(), &prev) {
+            (SeccompMode::Disabled, _) => true,
+            (SeccompMode::Filter(filter), Some(prev)) => filter.is_ancestor_of(prev),
+            _ => false,
+        };
+        if can_sync {
+            continue;
+        }
+
+        if flags.contains(SeccompFilterFlags::TSYNC_ESRCH) {
+            return_errno_with_message!(Errno::ESRCH, "some thread cannot be synchronized");
+        }
+        return Ok(Some(thread.tid()));
+    }
+
+    let no_new_privs = posix_thread.no_new_privs();
+    for thread in other_threads.chain(core::iter::once(posix_thread)) {
+        *thread.seccomp().lock() = SeccompMode::Filter(new_filter.clone());
+        thread.set_seccomp_enabled();
+        if no_new_privs {
+            thread.set_no_new_privs();
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks whether the current thread is allowed to make the syscall.
+///
+/// If the syscall is not allowed, this method takes the action that is decided by the seccomp
+/// mode (e.g., setting the return value or killing the thread) and returns `false`. In this
+/// case, the syscall should be skipped.
+pub fn secure_computing(ctx: &Context, user_ctx: &mut UserContext) -> bool {
+    let filter = match ctx.posix_thread.seccomp_mode() {
+        SeccompMode::Disabled => return true,
+        SeccompMode::Strict => {
+            if STRICT_SYSCALLS.contains(&user_ctx.syscall_num()) {
+                return true;
+            }
+            do_exit(TermStatus::Killed(SIGKILL));
+            return false;
+        }
+        SeccompMode::Filter(filter) => filter,
+    };
+
+    let data = seccomp_data::new(user_ctx);
+    let ret = filter.run(&data);
+
+    match ret.action() {
+        SECCOMP_RET_ALLOW => return true,
+        SECCOMP_RET_LOG => {
+            info!("seccomp: syscall {} is allowed with logging", data.nr);
+            return true;
+        }
+        SECCOMP_RET_ERRNO => {
+            // Linux caps the error number to `MAX_ERRNO`.
+            const MAX_ERRNO: u16 = 4095;
+            let errno = ret.data().min(MAX_ERRNO) as i32;
+            user_ctx.set_syscall_ret(-errno as usize);
+        }
+        SECCOMP_RET_TRAP => {
+            let signal = SeccompSignal {
+                data,
+                errno: ret.data(),
+            };
+            // Like Linux, the signal is delivered even if it is blocked or ignored.
+            ctx.posix_thread.enqueue_forced_signal(Box::new(signal));
+        }
+        // FIXME: Support `PTRACE_O_TRACESECCOMP` and seccomp user notification. Without a tracer
+        // or a listener, Linux fails the syscall with `ENOSYS`.
+        SECCOMP_RET_TRACE | SECCOMP_RET_USER_NOTIF => {
+            user_ctx.set_syscall_ret(-(Errno::ENOSYS as i32) as usize);
+        }
+        SECCOMP_RET_KILL_THREAD => do_exit(TermStatus::Killed(SIGSYS)),
+        // Unknown actions are treated as `SECCOMP_RET_KILL_PROCESS`.
+        _ => do_exit_group(TermStatus::Killed(SIGSYS)),
+    }
+
+    false
+}
+
+/// The `SIGSYS` signal sent by `SECCOMP_RET_TRAP`.
+#[derive(Clone, Copy, Debug)]
+struct SeccompSignal {
+    data: seccomp_data,
+    errno: u16,
+}
+
+impl Signal for SeccompSignal {
+    fn num(&self) -> SigNum {
+        SIGSYS
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        const SYS_SECCOMP: i32 = 1;
+
+        let mut info = siginfo_t::new(SIGSYS, SYS_SECCOMP);
+        info.si_errno = self.errno as i32;
+        info.set_sigsys(
+            self.data.instruction_pointer as Vaddr,
+            self.data.nr,
+            self.data.arch,
+        );
+        info
+    }
+}
+
+#[cfg(ktest)]
+mod test {
+    use ostd::prelude::*;
+
+    use super::{
+        bpf::{code::*, sock_filter},
+        *,
+    };
+
+    /// Creates a filter that returns `ret` if the syscall number is `nr`, or allows the syscall
+    /// otherwise.
+    fn new_filter(nr: i32, ret: u32, prev: Option<Arc<SeccompFilter>>) -> Arc<SeccompFilter> {
+        let insn = |code, jf, k| sock_filter { code, jt: 0, jf, k };
+        let raw_insns = [
+            insn(LD | ABS, 0, 0),
+            insn(JMP | JEQ | K, 1, nr as u32),
+            insn(RET | K, 0, ret),
+            insn(RET | K, 0, SECCOMP_RET_ALLOW),
+        ];
+        let prog = BpfProgram::new(&raw_insns, size_of::<seccomp_data>()).unwrap();
+        Arc::new(SeccompFilter {
+            prog,
+            is_logged: false,
+            prev,
+        })
+    }
+
+    /// Creates a filter chain from the oldest filter to the newest filter.
+    fn new_chain(filters: &[(i32, u32)]) -> Arc<SeccompFilter> {
+        filters
+            .iter()
+            .fold(None, |prev, &(nr, ret)| Some(new_filter(nr, ret, prev)))
+            .unwrap()
+    }
+
+    fn run(filter: &SeccompFilter, nr: i32) -> u32 {
+        let data = seccomp_data {
+            nr,
+            arch: AUDIT_ARCH,
+            instruction_pointer: 0,
+            args: [0; 6],
+        };
+        filter.run(&data).0
+    }
+
+    #[ktest]
+    fn precedence() {
+        let chain = new_chain(&[
+            (1, SECCOMP_RET_ERRNO | 1),
+            (1, SECCOMP_RET_TRAP | 2),
+            (1, SECCOMP_RET_LOG),
+        ]);
+        assert_eq!(run(&chain, 1), SECCOMP_RET_TRAP | 2);
+        assert_eq!(run(&chain, 2), SECCOMP_RET_ALLOW);
+
+        let chain = new_chain(&[
+            (1, SECCOMP_RET_KILL_THREAD),
+            (1, SECCOMP_RET_KILL_PROCESS),
+            (2, SECCOMP_RET_LOG),
+        ]);
+        assert_eq!(run(&chain, 1), SECCOMP_RET_KILL_PROCESS);
+        assert_eq!(run(&chain, 2), SECCOMP_RET_LOG);
+
+        // Like Linux, unknown actions are ordered by their values as signed integers. So they
+        // may have lower or higher precedences than the known actions.
+        let chain = new_chain(&[(1, SECCOMP_RET_KILL_THREAD), (1, 0x1000_0000)]);
+        assert_eq!(run(&chain, 1), SECCOMP_RET_KILL_THREAD);
+        let chain = new_chain(&[(1, SECCOMP_RET_ERRNO), (1, 0xf000_0000)]);
+        assert_eq!(run(&chain, 1), 0xf000_0000);
+    }
+
+    #[ktest]
+    fn precedence_same_action() {
+        // The data of the newest filter is used if the actions are the same.
+        let chain = new_chain(&[
+            (1, SECCOMP_RET_ERRNO | 1),
+            (1, SECCOMP_RET_ERRNO | 2),
+            (2, SECCOMP_RET_ERRNO | 3),
+        ]);
+        assert_eq!(run(&chain, 1), SECCOMP_RET_ERRNO | 2);
+        assert_eq!(run(&chain, 2), SECCOMP_RET_ERRNO | 3);
+    }
+}
@@ -65,21 +65,48 @@ impl siginfo_t {
         self.siginfo_fields.common.first = pid_uid;
     }
 
+    pub fn set_pid(&mut self, pid: Pid) {
+        self.siginfo_fields.common.first.piduid.pid = pid;
+    }
+
     pub fn set_status(&mut self, status: i32) {
         self.siginfo_fields.common.second.sigchild.status = status;
     }
 
+    pub fn set_value(&mut self, value: sigval_t) {
+        self.siginfo_fields.common.second.value = value;
+    }
+
+    pub fn set_sigsys(&mut self, call_addr: Vaddr, syscall: i32, arch: u32) {
+        self.siginfo_fields.sigsys = siginfo_sigsys_t {
+            call_addr,
+            syscall,
+            arch,
+        };
+    }
+
     pub fn si_addr(&self) -> Vaddr {
         read_union_field!(self, Self, siginfo_fields.sigfault.addr)
     }
 }
 
+impl Debug for siginfo_t {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("siginfo_t")
+            .field("si_signo", &self.si_signo)
+            .field("si_errno", &self.si_errno)
+            .field("si_code", &self.si_code)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Clone, Copy, Pod)]
 #[repr(C)]
 union siginfo_fields_t {
     bytes: [u8; 128 - mem::size_of::<i32>() * 4],
     common: siginfo_common_t,
     sigfault: siginfo_sigfault_t,
+    sigsys: siginfo_sigsys_t,
 }
 
 impl siginfo_fields_t {
@@ -158,6 +185,14 @@ struct siginfo_sigfault_t {
     first: siginfo_sigfault_first_t,
 }
 
+#[derive(Clone, Copy, Pod)]
+#[repr(C)]
+struct siginfo_sigsys_t {
+    call_addr: Vaddr, // *const c_void
+    syscall: i32,
+    arch: u32,
+}
+
 #[derive(Clone, Copy, Pod)]
 #[repr(C)]
 union siginfo_sigfault_first_t {
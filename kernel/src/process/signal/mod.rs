@@ -35,7 +35,9 @@ use crate::{
     cpu::LinuxAbi,
     current_userspace,
     prelude::*,
-    process::{posix_thread::do_exit_group, signal::c_types::stack_t, TermStatus},
+    process::{
+        coredump::do_coredump, posix_thread::do_exit_group, signal::c_types::stack_t, TermStatus,
+    },
 };
 
 pub trait SignalContext {
@@ -72,6 +74,10 @@ pub fn handle_pending_signal(
             return;
         }
     };
+    // The tracer can change or suppress the signal.
+    let Some(signal) = posix_thread.ptrace_report_signal(signal, user_ctx) else {
+        return;
+    };
     let sig_num = signal.num();
     trace!("sig_num = {:?}, sig_name = {}", sig_num, sig_num.sig_name());
 
@@ -141,8 +147,15 @@ pub fn handle_pending_signal(
                         current.executable_path(),
                         sig_num.sig_name()
                     );
+                    let is_core_dumped = sig_default_action == SigDefaultAction::Core
+                        && do_coredump(sig_num, user_ctx, ctx);
+                    let term_status = if is_core_dumped {
+                        TermStatus::Dumped(sig_num)
+                    } else {
+                        TermStatus::Killed(sig_num)
+                    };
                     // We should exit current here, since we cannot restore a valid status from trap now.
-                    do_exit_group(TermStatus::Killed(sig_num));
+                    do_exit_group(term_status);
                 }
                 SigDefaultAction::Ign => {}
                 SigDefaultAction::Stop => ctx.process.stop(sig_num),
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::Signal;
+use crate::process::{
+    signal::{
+        c_types::siginfo_t,
+        constants::{CLD_DUMPED, CLD_EXITED, CLD_KILLED, SIGCHLD},
+        sig_num::SigNum,
+    },
+    ExitCode, Pid, Uid,
+};
+
+/// A signal that notifies a process of a state change of its child or tracee.
+///
+/// The signal is `SIGCHLD`, unless the child is created with another exit signal (e.g., by
+/// `clone`) and the signal reports its exit.
+#[derive(Debug, Clone, Copy)]
+pub struct ChildSignal {
+    num: SigNum,
+    /// The `si_code` field, e.g., `CLD_TRAPPED`.
+    code: i32,
+    /// The ID of the child, which is in the PID namespace of the receiver.
+    pid: Pid,
+    uid: Uid,
+    status: i32,
+}
+
+impl ChildSignal {
+    pub fn new(code: i32, pid: Pid, uid: Uid, status: i32) -> Self {
+        Self {
+            num: SIGCHLD,
+            code,
+            pid,
+            uid,
+            status,
+        }
+    }
+
+    /// Creates a `SIGCHLD` signal that reports the exit of the child.
+    pub fn new_exited(pid: Pid, uid: Uid, exit_code: ExitCode) -> Self {
+        let (code, status) = exit_code_to_code_and_status(exit_code);
+        Self::new(code, pid, uid, status)
+    }
+
+    /// Sets the signal number.
+    pub fn with_num(mut self, num: SigNum) -> Self {
+        self.num = num;
+        self
+    }
+}
+
+/// Converts the exit code (i.e., the status reported by `wait4`) to `si_code` and `si_status`.
+pub fn exit_code_to_code_and_status(exit_code: ExitCode) -> (i32, i32) {
+    const NORMAL_EXIT_MASK: u32 = 0xff;
+    const CORE_DUMP_FLAG: u32 = 0x80;
+
+    // If the process exits normally, the lowest 8 bits of `status_code`
+    // will be zero. In this case, we return the actual exit code by
+    // shifting the `status_code` right by 8 bits.
+    if (exit_code & NORMAL_EXIT_MASK) == 0 {
+        (CLD_EXITED, (exit_code >> 8) as i32)
+    } else if (exit_code & CORE_DUMP_FLAG) != 0 {
+        (CLD_DUMPED, (exit_code & !CORE_DUMP_FLAG) as i32)
+    } else {
+        (CLD_KILLED, exit_code as i32)
+    }
+}
+
+impl Signal for ChildSignal {
+    fn num(&self) -> SigNum {
+        self.num
+    }
+
+    fn to_info(&self) -> siginfo_t {
+        let mut info = siginfo_t::new(self.num, self.code);
+        info.set_pid_uid(self.pid, self.uid);
+        info.set_status(self.status);
+        info
+    }
+}
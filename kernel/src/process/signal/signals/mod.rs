@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
+pub mod child;
 pub mod fault;
 pub mod kernel;
 pub mod user;
@@ -4,9 +4,10 @@
 
 use super::Signal;
 use crate::process::{
+    process_table::PidNamespace,
     signal::{
         c_types::siginfo_t,
-        constants::{SI_QUEUE, SI_TKILL, SI_USER},
+        constants::{SI_TKILL, SI_USER},
         sig_num::SigNum,
     },
     Pid, Uid,
@@ -24,7 +25,8 @@ pub struct UserSignal {
 pub enum UserSignalKind {
     Kill,
     Tkill,
-    Sigqueue,
+    /// The signal information is provided by the sender (e.g., via `pidfd_send_signal`).
+    Sigqueue(siginfo_t),
 }
 
 impl UserSignal {
@@ -44,6 +46,21 @@ impl UserSignal {
     pub fn kind(&self) -> UserSignalKind {
         self.kind
     }
+
+    /// Translates the PID of the sender, which is a global PID, into the PID namespace of the
+    /// receiver.
+    ///
+    /// The PID becomes zero if the sender is invisible in the namespace. For the signal
+    /// information provided by the sender, only this case is handled, as in Linux.
+    pub(in crate::process) fn to_receiver_pid_ns(mut self, pid_ns: &PidNamespace) -> Self {
+        self.pid = pid_ns.to_local(self.pid);
+        if let UserSignalKind::Sigqueue(ref mut info) = self.kind {
+            if self.pid == 0 {
+                info.set_pid(0);
+            }
+        }
+        self
+    }
 }
 
 impl Signal for UserSignal {
@@ -55,14 +72,11 @@ impl Signal for UserSignal {
         let code = match self.kind {
             UserSignalKind::Kill => SI_USER,
             UserSignalKind::Tkill => SI_TKILL,
-            UserSignalKind::Sigqueue => SI_QUEUE,
+            UserSignalKind::Sigqueue(info) => return info,
         };
 
-        siginfo_t::new(self.num, code)
-        // info.set_si_pid(self.pid);
-        // info.set_si_uid(self.uid);
-        // if let UserSignalKind::Sigqueue(val) = self.kind {
-        //     info.set_si_value(val);
-        // }
+        let mut info = siginfo_t::new(self.num, code);
+        info.set_pid_uid(self.pid, self.uid);
+        info
     }
 }
@@ -14,6 +14,7 @@ use crate::{
         status::StopWaitStatus,
         Uid,
     },
+    thread::Thread,
     time::clocks::ProfClock,
 };
 
@@ -72,6 +73,9 @@ pub fn do_wait(
         false
     };
 
+    // The process IDs in the filter are in the PID namespace of the current thread.
+    let pid_ns = ctx.posix_thread.pid_ns();
+
     let zombie_child = with_sigmask_changed(
         ctx,
         |sigmask| sigmask + SIGCHLD,
@@ -86,15 +90,37 @@ pub fn do_wait(
                     .values()
                     .filter(|child| match &child_filter {
                         ProcessFilter::Any => true,
-                        ProcessFilter::WithPid(pid) => child.pid() == *pid,
-                        ProcessFilter::WithPgid(pgid) => child.pgid() == *pgid,
+                        ProcessFilter::WithPid(pid) => pid_ns.to_local(child.pid()) == *pid,
+                        ProcessFilter::WithPgid(pgid) => pid_ns.to_local(child.pgid()) == *pgid,
                         ProcessFilter::WithPidfd(pid_file) => {
                             Arc::ptr_eq(pid_file.process(), *child)
                         }
                     })
                     .collect::<Box<_>>();
 
-                if unwaited_children.is_empty() {
+                // Lock order: children of process -> tracees of process
+                let mut tracees_lock = ctx.process.tracees().lock();
+
+                let unwaited_tracees = tracees_lock
+                    .values()
+                    .filter(|tracee| {
+                        let posix_thread = tracee.as_posix_thread().unwrap();
+                        match &child_filter {
+                            ProcessFilter::Any => true,
+                            ProcessFilter::WithPid(pid) => {
+                                pid_ns.to_local(posix_thread.tid()) == *pid
+                            }
+                            ProcessFilter::WithPgid(pgid) => {
+                                pid_ns.to_local(posix_thread.process().pgid()) == *pgid
+                            }
+                            ProcessFilter::WithPidfd(pid_file) => {
+                                Arc::ptr_eq(pid_file.process(), &posix_thread.process())
+                            }
+                        }
+                    })
+                    .collect::<Box<_>>();
+
+                if unwaited_children.is_empty() && unwaited_tracees.is_empty() {
                     return Some(Err(Error::with_message(
                         Errno::ECHILD,
                         "the process has no child to wait",
@@ -102,12 +128,24 @@ pub fn do_wait(
                 }
 
                 if let Some(status) = wait_zombie(&unwaited_children) {
+                    drop(tracees_lock);
                     if !wait_options.contains(WaitOptions::WNOWAIT) {
                         reap_zombie_child(status.pid(), &mut children_lock);
                     }
                     return Some(Ok(Some(status)));
                 }
 
+                if let Some(status) = wait_ptrace_exited(&unwaited_tracees) {
+                    if !wait_options.contains(WaitOptions::WNOWAIT) {
+                        tracees_lock.remove(&status.pid());
+                    }
+                    return Some(Ok(Some(status)));
+                }
+
+                if let Some(status) = wait_ptrace_stopped(&unwaited_tracees, wait_options) {
+                    return Some(Ok(Some(status)));
+                }
+
                 if let Some(status) = wait_stopped_or_continued(&unwaited_children, wait_options) {
                     return Some(Ok(Some(status)));
                 }
@@ -136,31 +174,45 @@ pub enum WaitStatus {
     Zombie(Arc<Process>),
     Stop(Arc<Process>, SigNum),
     Continue(Arc<Process>),
+    /// A traced thread is in a ptrace-stop with the stop code.
+    PtraceStop(Arc<Thread>, u32),
+    /// A traced thread has exited with the exit code.
+    PtraceExit(Arc<Thread>, ExitCode),
 }
 
 impl WaitStatus {
     pub fn pid(&self) -> Pid {
-        self.process().pid()
+        match self {
+            WaitStatus::PtraceStop(thread, _) | WaitStatus::PtraceExit(thread, _) => {
+                thread.as_posix_thread().unwrap().tid()
+            }
+            _ => self.process().unwrap().pid(),
+        }
     }
 
     pub fn uid(&self) -> Uid {
-        self.process()
-            .main_thread()
-            .as_posix_thread()
-            .unwrap()
-            .credentials()
-            .ruid()
+        let thread = match self {
+            WaitStatus::PtraceStop(thread, _) | WaitStatus::PtraceExit(thread, _) => thread.clone(),
+            _ => self.process().unwrap().main_thread(),
+        };
+        thread.as_posix_thread().unwrap().credentials().ruid()
     }
 
     pub fn prof_clock(&self) -> &Arc<ProfClock> {
-        self.process().prof_clock()
+        match self {
+            WaitStatus::PtraceStop(thread, _) | WaitStatus::PtraceExit(thread, _) => {
+                thread.as_posix_thread().unwrap().prof_clock()
+            }
+            _ => self.process().unwrap().prof_clock(),
+        }
     }
 
-    fn process(&self) -> &Arc<Process> {
+    fn process(&self) -> Option<&Arc<Process>> {
         match self {
             WaitStatus::Zombie(process)
             | WaitStatus::Stop(process, _)
-            | WaitStatus::Continue(process) => process,
+            | WaitStatus::Continue(process) => Some(process),
+            WaitStatus::PtraceStop(..) | WaitStatus::PtraceExit(..) => None,
         }
     }
 }
@@ -172,6 +224,30 @@ fn wait_zombie(unwaited_children: &[&Arc<Process>]) -> Option<WaitStatus> {
         .map(|child| WaitStatus::Zombie((*child).clone()))
 }
 
+fn wait_ptrace_exited(unwaited_tracees: &[&Arc<Thread>]) -> Option<WaitStatus> {
+    unwaited_tracees.iter().find_map(|tracee| {
+        let exit_code = tracee.as_posix_thread().unwrap().wait_ptrace_exited()?;
+        Some(WaitStatus::PtraceExit((*tracee).clone(), exit_code))
+    })
+}
+
+fn wait_ptrace_stopped(
+    unwaited_tracees: &[&Arc<Thread>],
+    wait_options: WaitOptions,
+) -> Option<WaitStatus> {
+    // Ptrace-stops are always reported to the tracer, regardless of `WSTOPPED`.
+    for tracee in unwaited_tracees.iter() {
+        let posix_thread = tracee.as_posix_thread().unwrap();
+        let Some(code) = posix_thread.wait_ptrace_stopped(wait_options) else {
+            continue;
+        };
+
+        return Some(WaitStatus::PtraceStop((*tracee).clone(), code));
+    }
+
+    None
+}
+
 fn wait_stopped_or_continued(
     unwaited_children: &[&Arc<Process>],
     wait_options: WaitOptions,
@@ -1,13 +1,15 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use alloc::{collections::BinaryHeap, sync::Arc};
+use alloc::{collections::BinaryHeap, sync::Arc, vec::Vec};
 use core::{
     cmp::{self, Reverse},
+    mem,
     sync::atomic::{AtomicU64, Ordering},
 };
 
 use ostd::{
     cpu::{num_cpus, CpuId},
+    sync::{LocalIrqDisabled, SpinLock},
     task::{
         scheduler::{EnqueueFlags, UpdateFlags},
         Task,
@@ -15,7 +17,8 @@ use ostd::{
 };
 
 use super::{
-    time::{base_slice_clocks, min_period_clocks},
+    sched_clock,
+    time::{base_slice_clocks, min_period_clocks, ns_to_clocks},
     CurrentRuntime, SchedAttr, SchedClassRq,
 };
 use crate::{
@@ -67,6 +70,163 @@ pub const fn nice_to_weight(nice: Nice) -> u64 {
     NICE_TO_WEIGHT[(nice.value().get() + 20) as usize]
 }
 
+/// A group of threads in the FAIR scheduling class.
+///
+/// A group scales the weights of its threads by the group weight, and limits the CPU time that
+/// the threads in the group and its descendant groups can consume in each period. Groups are used
+/// to implement the CPU controller of cgroups, so the weights and the bandwidth limits follow
+/// the semantics of `cpu.weight` and `cpu.max`.
+///
+/// FIXME: The group weight is applied to each thread in the group, rather than the group as a
+/// whole. So a group with more runnable threads still gets more CPU time than its siblings with
+/// the same weight.
+#[derive(Debug)]
+pub struct FairGroup {
+    parent: Option<Arc<FairGroup>>,
+    weight: AtomicU64,
+    bandwidth: SpinLock<Bandwidth, LocalIrqDisabled>,
+}
+
+#[derive(Debug)]
+struct Bandwidth {
+    /// The CPU time that can be consumed in each period, in nanoseconds.
+    quota_ns: Option<u64>,
+    /// The length of a period, in nanoseconds.
+    period_ns: u64,
+    /// The quota, measured in sched clocks.
+    quota: Option<u64>,
+    /// The length of a period, measured in sched clocks.
+    period: u64,
+    /// The start of the current period, measured in sched clocks.
+    period_start: u64,
+    /// The CPU time consumed in the current period, measured in sched clocks.
+    runtime: u64,
+}
+
+impl FairGroup {
+    /// The default weight of a group.
+    pub const DEFAULT_WEIGHT: u64 = 100;
+    /// The default length of a period, in nanoseconds.
+    pub const DEFAULT_PERIOD_NS: u64 = 100_000_000;
+
+    /// Creates a new group without a bandwidth limit.
+    pub fn new(parent: Option<Arc<FairGroup>>) -> Arc<Self> {
+        let bandwidth = Bandwidth {
+            quota_ns: None,
+            period_ns: Self::DEFAULT_PERIOD_NS,
+            quota: None,
+            period: ns_to_clocks(Self::DEFAULT_PERIOD_NS),
+            period_start: sched_clock(),
+            runtime: 0,
+        };
+
+        Arc::new(Self {
+            parent,
+            weight: AtomicU64::new(Self::DEFAULT_WEIGHT),
+            bandwidth: SpinLock::new(bandwidth),
+        })
+    }
+
+    /// Returns the weight of the group.
+    pub fn weight(&self) -> u64 {
+        self.weight.load(Ordering::Relaxed)
+    }
+
+    /// Sets the weight of the group.
+    ///
+    /// The new weight takes effect on a thread after [`SchedAttr::set_group`] is called again
+    /// for the thread.
+    pub fn set_weight(&self, weight: u64) {
+        self.weight.store(weight, Ordering::Relaxed);
+    }
+
+    /// Returns the quota and the period of the bandwidth limit, in nanoseconds.
+    ///
+    /// The quota is `None` if the bandwidth is unlimited.
+    pub fn bandwidth(&self) -> (Option<u64>, u64) {
+        let bandwidth = self.bandwidth.lock();
+        (bandwidth.quota_ns, bandwidth.period_ns)
+    }
+
+    /// Sets the quota and the period of the bandwidth limit, in nanoseconds.
+    pub fn set_bandwidth(&self, quota_ns: Option<u64>, period_ns: u64) {
+        let mut bandwidth = self.bandwidth.lock();
+        bandwidth.quota_ns = quota_ns;
+        bandwidth.period_ns = period_ns;
+        bandwidth.quota = quota_ns.map(ns_to_clocks);
+        bandwidth.period = ns_to_clocks(period_ns);
+        bandwidth.period_start = sched_clock();
+        bandwidth.runtime = 0;
+    }
+
+    /// Scales a thread weight by the weights of the group and its ancestors.
+    fn scale_weight(&self, mut weight: u64) -> u64 {
+        // The scaled weight is capped so that it never overlaps with `HAS_PENDING` and the total
+        // weight of a run queue never overflows.
+        const MAX_SCALED_WEIGHT: u64 = u32::MAX as u64;
+
+        let mut group = Some(self);
+        while let Some(current) = group {
+            weight = (weight.saturating_mul(current.weight()) / Self::DEFAULT_WEIGHT)
+                .clamp(1, MAX_SCALED_WEIGHT);
+            group = current.parent.as_deref();
+        }
+        weight
+    }
+
+    /// Charges the CPU time to the group and its ancestors.
+    ///
+    /// This method returns whether the group is throttled after charging.
+    fn charge(&self, delta: u64) -> bool {
+        let now = sched_clock();
+        let mut is_throttled = false;
+
+        let mut group = Some(self);
+        while let Some(current) = group {
+            let mut bandwidth = current.bandwidth.lock();
+            bandwidth.refresh(now);
+            bandwidth.runtime += delta;
+            is_throttled |= bandwidth.is_exhausted();
+            group = current.parent.as_deref();
+        }
+
+        is_throttled
+    }
+
+    /// Returns whether the group or any of its ancestors has run out of its quota in the current
+    /// period.
+    fn is_throttled(&self) -> bool {
+        let now = sched_clock();
+
+        let mut group = Some(self);
+        while let Some(current) = group {
+            let mut bandwidth = current.bandwidth.lock();
+            bandwidth.refresh(now);
+            if bandwidth.is_exhausted() {
+                return true;
+            }
+            group = current.parent.as_deref();
+        }
+
+        false
+    }
+}
+
+impl Bandwidth {
+    /// Starts a new period if the current period has ended.
+    fn refresh(&mut self, now: u64) {
+        let elapsed = now.saturating_sub(self.period_start);
+        if elapsed >= self.period {
+            self.period_start = now - elapsed % self.period.max(1);
+            self.runtime = 0;
+        }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.quota.is_some_and(|quota| self.runtime >= quota)
+    }
+}
+
 /// The scheduling entity for the FAIR scheduling class.
 ///
 /// The structure contains a significant indicator: `vruntime`.
@@ -125,11 +285,16 @@ pub const fn nice_to_weight(nice: Nice) -> u64 {
 ///
 /// This method allows the access to the weight lock-free and ensures only 1 load
 /// is needed most of the time.
+///
+/// The weight of a thread is determined by its nice value and its [`FairGroup`],
+/// so changing the group goes through the same process.
 #[derive(Debug)]
 pub struct FairAttr {
     weight: AtomicU64,
     pending_weight: AtomicU64,
     vruntime: AtomicU64,
+    nice_weight: AtomicU64,
+    group: SpinLock<Option<Arc<FairGroup>>, LocalIrqDisabled>,
 }
 
 impl FairAttr {
@@ -138,15 +303,52 @@ impl FairAttr {
             weight: nice_to_weight(nice).into(),
             pending_weight: Default::default(),
             vruntime: Default::default(),
+            nice_weight: nice_to_weight(nice).into(),
+            group: SpinLock::new(None),
         }
     }
 
     pub fn update(&self, nice: Nice) {
-        self.pending_weight
+        let group = self.group.lock();
+        self.nice_weight
             .store(nice_to_weight(nice), Ordering::Relaxed);
+        self.update_weight(group.as_deref());
+    }
+
+    pub fn set_group(&self, new_group: Option<Arc<FairGroup>>) {
+        let mut group = self.group.lock();
+        *group = new_group;
+        self.update_weight(group.as_deref());
+    }
+
+    fn update_weight(&self, group: Option<&FairGroup>) {
+        let nice_weight = self.nice_weight.load(Ordering::Relaxed);
+        let weight = match group {
+            Some(group) => group.scale_weight(nice_weight),
+            None => nice_weight,
+        };
+
+        self.pending_weight.store(weight, Ordering::Relaxed);
         self.weight.fetch_or(HAS_PENDING, Ordering::Release);
     }
 
+    /// Charges the CPU time to the group of the thread.
+    ///
+    /// This method returns whether the thread should be throttled.
+    fn charge_runtime(&self, delta: u64) -> bool {
+        self.group
+            .lock()
+            .as_ref()
+            .is_some_and(|group| group.charge(delta))
+    }
+
+    fn is_throttled(&self) -> bool {
+        self.group
+            .lock()
+            .as_ref()
+            .is_some_and(|group| group.is_throttled())
+    }
+
     fn update_vruntime(&self, delta: u64, weight: u64) -> u64 {
         let delta = delta * WEIGHT_0 / weight;
         self.vruntime.fetch_add(delta, Ordering::Relaxed) + delta
@@ -229,12 +431,17 @@ impl Ord for FairQueueItem {
 ///
 /// The structure contains a `BTreeSet` to store the threads in the run queue to
 /// ensure the efficiency for finding next-to-run threads.
+///
+/// The threads whose [`FairGroup`]s have run out of their quotas are moved out of
+/// the run queue when they are picked, and are put back once a new period starts.
 #[derive(Debug)]
 pub(super) struct FairClassRq {
     #[expect(unused)]
     cpu: CpuId,
     /// The ready-to-run threads.
     entities: BinaryHeap<Reverse<FairQueueItem>>,
+    /// The threads that are runnable but throttled.
+    throttled: Vec<Arc<Task>>,
     /// The minimum of vruntime in the run queue. Serves as the initial
     /// value of newly-enqueued threads.
     min_vruntime: u64,
@@ -246,6 +453,7 @@ impl FairClassRq {
         Self {
             cpu,
             entities: BinaryHeap::new(),
+            throttled: Vec::new(),
             min_vruntime: 0,
             total_weight: 0,
         }
@@ -283,6 +491,26 @@ impl FairClassRq {
     fn time_slice(&self, cur_weight: u64) -> u64 {
         self.period() * cur_weight / (self.total_weight + cur_weight)
     }
+
+    /// Puts the throttled threads that can run again back to the run queue.
+    fn unthrottle(&mut self) {
+        if self.throttled.is_empty() {
+            return;
+        }
+
+        let (runnable, throttled): (Vec<_>, Vec<_>) = mem::take(&mut self.throttled)
+            .into_iter()
+            .partition(|task| !is_throttled(task));
+        self.throttled = throttled;
+
+        for task in runnable {
+            self.enqueue(task, None);
+        }
+    }
+}
+
+fn is_throttled(task: &Task) -> bool {
+    task.as_thread().unwrap().sched_attr().fair.is_throttled()
 }
 
 impl SchedClassRq for FairClassRq {
@@ -304,25 +532,34 @@ impl SchedClassRq for FairClassRq {
     }
 
     fn len(&self) -> usize {
-        self.entities.len()
+        self.entities.len() + self.throttled.len()
     }
 
     fn is_empty(&self) -> bool {
-        self.entities.is_empty()
+        self.entities.is_empty() && self.throttled.iter().all(|task| is_throttled(task))
     }
 
     fn pick_next(&mut self) -> Option<Arc<Task>> {
-        let Reverse(FairQueueItem(entity, _)) = self.entities.pop()?;
+        self.unthrottle();
+
+        while let Some(Reverse(FairQueueItem(entity, _))) = self.entities.pop() {
+            let sched_attr = entity.as_thread().unwrap().sched_attr();
+            let (old_weight, _weight) = sched_attr.fair.fetch_weight();
+            // Equals to:
+            //
+            // self.total_weight = self.total_weight + weight - old_weight;
+            // self.total_weight -= weight;
+            self.total_weight -= old_weight;
+
+            if sched_attr.fair.is_throttled() {
+                self.throttled.push(entity);
+                continue;
+            }
 
-        let sched_attr = entity.as_thread().unwrap().sched_attr();
-        let (old_weight, _weight) = sched_attr.fair.fetch_weight();
-        // Equals to:
-        //
-        // self.total_weight = self.total_weight + weight - old_weight;
-        // self.total_weight -= weight;
-        self.total_weight -= old_weight;
+            return Some(entity);
+        }
 
-        Some(entity)
+        None
     }
 
     fn update_current(
@@ -333,18 +570,24 @@ impl SchedClassRq for FairClassRq {
     ) -> bool {
         match flags {
             UpdateFlags::Tick | UpdateFlags::Yield | UpdateFlags::Wait => {
+                self.unthrottle();
+
                 let (_old_weight, weight) = attr.fair.fetch_weight();
                 let vruntime = attr.fair.update_vruntime(rt.delta, weight);
+                let is_throttled = attr.fair.charge_runtime(rt.delta);
                 let leftmost = self.entities.peek();
                 self.min_vruntime = match leftmost {
                     Some(Reverse(leftmost)) => vruntime.min(leftmost.key()),
                     None => vruntime,
                 };
                 if leftmost.is_none() {
-                    return false;
+                    // A throttled thread should give up the CPU even if there are no other
+                    // threads in this run queue.
+                    return is_throttled;
                 }
 
-                matches!(flags, UpdateFlags::Wait)
+                is_throttled
+                    || matches!(flags, UpdateFlags::Wait)
                     || rt.period_delta > self.time_slice(weight)
                     || vruntime > self.min_vruntime + self.vtime_slice()
             }
@@ -37,6 +37,7 @@ mod stop;
 
 use self::policy::{SchedPolicyKind, SchedPolicyState};
 pub use self::{
+    fair::FairGroup,
     policy::SchedPolicy,
     real_time::{RealTimePolicy, RealTimePriority},
 };
@@ -197,6 +198,14 @@ impl SchedAttr {
         })
     }
 
+    /// Sets the group of the thread in the FAIR scheduling class.
+    ///
+    /// This method should also be called after the weight of the group (or its ancestors) is
+    /// changed, so that the weight of the thread can be updated.
+    pub fn set_group(&self, group: Option<Arc<FairGroup>>) {
+        self.fair.set_group(group);
+    }
+
     fn last_cpu(&self) -> Option<CpuId> {
         self.last_cpu.get()
     }
@@ -51,3 +51,9 @@ pub fn base_slice_clocks() -> u64 {
 pub fn min_period_clocks() -> u64 {
     consts().1
 }
+
+/// Converts a duration in nanoseconds to TSC clock units.
+pub fn ns_to_clocks(ns: u64) -> u64 {
+    let (a, b) = tsc_factors();
+    (u128::from(ns) * u128::from(b) / u128::from(a)) as u64
+}
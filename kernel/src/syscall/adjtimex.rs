@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{ClockId, SyscallReturn};
+use crate::{
+    prelude::*,
+    time::{
+        clockid_t,
+        ntp::{adjust_system_clock, timex_t},
+    },
+};
+
+pub fn sys_adjtimex(timex_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    do_adjtimex(timex_addr, ctx)
+}
+
+pub fn sys_clock_adjtime(
+    clockid: clockid_t,
+    timex_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("clockid = {:?}", clockid);
+
+    match ClockId::try_from(clockid) {
+        Ok(ClockId::CLOCK_REALTIME) => do_adjtimex(timex_addr, ctx),
+        Ok(_) => return_errno_with_message!(Errno::EOPNOTSUPP, "the clock cannot be adjusted"),
+        // TODO: Support adjusting dynamic clocks (e.g., PTP clocks).
+        Err(_) => return_errno_with_message!(Errno::EINVAL, "invalid clock ID"),
+    }
+}
+
+fn do_adjtimex(timex_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let user_space = ctx.user_space();
+    let mut timex = user_space.read_val::<timex_t>(timex_addr)?;
+    debug!("timex = {:?}", timex);
+
+    let clock_state = adjust_system_clock(&mut timex, ctx)?;
+    user_space.write_val(timex_addr, &timex)?;
+
+    Ok(SyscallReturn::Return(clock_state as _))
+}
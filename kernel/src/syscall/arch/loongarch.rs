@@ -5,6 +5,7 @@
 use super::{
     accept::{sys_accept, sys_accept4},
     access::{sys_faccessat, sys_faccessat2},
+    adjtimex::{sys_adjtimex, sys_clock_adjtime},
     bind::sys_bind,
     brk::sys_brk,
     capget::sys_capget,
@@ -14,9 +15,11 @@ use super::{
     chown::{sys_fchown, sys_fchownat},
     chroot::sys_chroot,
     clock_gettime::sys_clock_gettime,
+    clock_settime::sys_clock_settime,
     clone::{sys_clone, sys_clone3},
     close::{sys_close, sys_close_range},
     connect::sys_connect,
+    copy_file_range::sys_copy_file_range,
     dup::{sys_dup, sys_dup3},
     epoll::{sys_epoll_create1, sys_epoll_ctl, sys_epoll_pwait, sys_epoll_pwait2},
     eventfd::sys_eventfd2,
@@ -54,6 +57,8 @@ use super::{
     getuid::sys_getuid,
     getxattr::{sys_fgetxattr, sys_getxattr, sys_lgetxattr},
     impl_syscall_nums_and_dispatch_fn,
+    inotify::{sys_inotify_add_watch, sys_inotify_init1, sys_inotify_rm_watch},
+    io_uring::{sys_io_uring_enter, sys_io_uring_register, sys_io_uring_setup},
     ioctl::sys_ioctl,
     kill::sys_kill,
     link::sys_linkat,
@@ -62,24 +67,40 @@ use super::{
     lseek::sys_lseek,
     madvise::sys_madvise,
     memfd_create::sys_memfd_create,
+    mincore::sys_mincore,
     mkdir::sys_mkdirat,
     mknod::sys_mknodat,
+    mlock::{sys_mlock, sys_mlock2, sys_mlockall, sys_munlock, sys_munlockall},
     mmap::sys_mmap,
     mount::sys_mount,
     mprotect::sys_mprotect,
+    mq_getsetattr::sys_mq_getsetattr,
+    mq_notify::sys_mq_notify,
+    mq_open::sys_mq_open,
+    mq_timedreceive::sys_mq_timedreceive,
+    mq_timedsend::sys_mq_timedsend,
+    mq_unlink::sys_mq_unlink,
     mremap::sys_mremap,
+    msgctl::sys_msgctl,
+    msgget::sys_msgget,
+    msgrcv::sys_msgrcv,
+    msgsnd::sys_msgsnd,
     msync::sys_msync,
     munmap::sys_munmap,
     nanosleep::{sys_clock_nanosleep, sys_nanosleep},
     open::sys_openat,
+    pidfd_getfd::sys_pidfd_getfd,
     pidfd_open::sys_pidfd_open,
+    pidfd_send_signal::sys_pidfd_send_signal,
     pipe::sys_pipe2,
     ppoll::sys_ppoll,
     prctl::sys_prctl,
     pread64::sys_pread64,
     preadv::{sys_preadv, sys_preadv2, sys_readv},
     prlimit64::sys_prlimit64,
+    process_vm::{sys_process_vm_readv, sys_process_vm_writev},
     pselect6::sys_pselect6,
+    ptrace::sys_ptrace,
     pwrite64::sys_pwrite64,
     pwritev::{sys_pwritev, sys_pwritev2, sys_writev},
     read::sys_read,
@@ -102,6 +123,7 @@ use super::{
     sched_setparam::sys_sched_setparam,
     sched_setscheduler::sys_sched_setscheduler,
     sched_yield::sys_sched_yield,
+    seccomp::sys_seccomp,
     semctl::sys_semctl,
     semget::sys_semget,
     semop::{sys_semop, sys_semtimedop},
@@ -116,7 +138,9 @@ use super::{
     setfsuid::sys_setfsuid,
     setgid::sys_setgid,
     setgroups::sys_setgroups,
+    sethostname::{sys_setdomainname, sys_sethostname},
     setitimer::{sys_getitimer, sys_setitimer},
+    setns::sys_setns,
     setpgid::sys_setpgid,
     setregid::sys_setregid,
     setresgid::sys_setresgid,
@@ -124,19 +148,26 @@ use super::{
     setreuid::sys_setreuid,
     setsid::sys_setsid,
     setsockopt::sys_setsockopt,
+    settimeofday::sys_settimeofday,
     setuid::sys_setuid,
     setxattr::{sys_fsetxattr, sys_lsetxattr, sys_setxattr},
+    shmat::sys_shmat,
+    shmctl::sys_shmctl,
+    shmdt::sys_shmdt,
+    shmget::sys_shmget,
     shutdown::sys_shutdown,
     sigaltstack::sys_sigaltstack,
     signalfd::sys_signalfd4,
     socket::sys_socket,
     socketpair::sys_socketpair,
+    splice::sys_splice,
     stat::{sys_fstat, sys_fstatat},
     statfs::{sys_fstatfs, sys_statfs},
     statx::sys_statx,
     symlink::sys_symlinkat,
     sync::sys_sync,
     sysinfo::sys_sysinfo,
+    tee::sys_tee,
     tgkill::sys_tgkill,
     timer_create::{sys_timer_create, sys_timer_delete},
     timer_settime::{sys_timer_gettime, sys_timer_settime},
@@ -148,7 +179,10 @@ use super::{
     umount::sys_umount,
     uname::sys_uname,
     unlink::sys_unlinkat,
+    unshare::sys_unshare,
+    userfaultfd::sys_userfaultfd,
     utimens::sys_utimensat,
+    vmsplice::sys_vmsplice,
     wait4::sys_wait4,
     waitid::sys_waitid,
     write::sys_write,
@@ -175,6 +209,9 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_DUP = 23                     => sys_dup(args[..1]);
     SYS_DUP3 = 24                    => sys_dup3(args[..3]);
     SYS_FCNTL = 25                   => sys_fcntl(args[..3]);
+    SYS_INOTIFY_INIT1 = 26           => sys_inotify_init1(args[..1]);
+    SYS_INOTIFY_ADD_WATCH = 27       => sys_inotify_add_watch(args[..3]);
+    SYS_INOTIFY_RM_WATCH = 28        => sys_inotify_rm_watch(args[..2]);
     SYS_IOCTL = 29                   => sys_ioctl(args[..3]);
     SYS_IOPRIO_SET = 30              => sys_ioprio_set(args[..3]);
     SYS_IOPRIO_GET = 31              => sys_ioprio_get(args[..2]);
@@ -216,6 +253,9 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_PSELECT6 = 72                => sys_pselect6(args[..6]);
     SYS_PPOLL = 73                   => sys_ppoll(args[..5]);
     SYS_SIGNALFD4 = 74               => sys_signalfd4(args[..4]);
+    SYS_VMSPLICE = 75                => sys_vmsplice(args[..4]);
+    SYS_SPLICE = 76                  => sys_splice(args[..6]);
+    SYS_TEE = 77                     => sys_tee(args[..4]);
     SYS_READLINKAT = 78              => sys_readlinkat(args[..4]);
     SYS_NEWFSTATAT = 79              => sys_fstatat(args[..4]);
     SYS_NEWFSTAT = 80                => sys_fstat(args[..2]);
@@ -232,6 +272,7 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_EXIT_GROUP = 94              => sys_exit_group(args[..1]);
     SYS_WAITID = 95                  => sys_waitid(args[..5]);
     SYS_SET_TID_ADDRESS = 96         => sys_set_tid_address(args[..1]);
+    SYS_UNSHARE = 97                 => sys_unshare(args[..1]);
     SYS_FUTEX = 98                   => sys_futex(args[..6]);
     SYS_SET_ROBUST_LIST = 99         => sys_set_robust_list(args[..2]);
     SYS_NANOSLEEP = 101              => sys_nanosleep(args[..2]);
@@ -241,8 +282,10 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_TIMER_GETTIME = 108          => sys_timer_gettime(args[..2]);
     SYS_TIMER_SETTIME = 110          => sys_timer_settime(args[..4]);
     SYS_TIMER_DELETE = 111           => sys_timer_delete(args[..1]);
+    SYS_CLOCK_SETTIME = 112          => sys_clock_settime(args[..2]);
     SYS_CLOCK_GETTIME = 113          => sys_clock_gettime(args[..2]);
     SYS_CLOCK_NANOSLEEP = 115        => sys_clock_nanosleep(args[..4]);
+    SYS_PTRACE = 117                 => sys_ptrace(args[..4]);
     SYS_SCHED_SETPARAM = 118         => sys_sched_setparam(args[..2]);
     SYS_SCHED_SETSCHEDULER = 119     => sys_sched_setscheduler(args[..3]);
     SYS_SCHED_GETSCHEDULER = 120     => sys_sched_getscheduler(args[..1]);
@@ -279,11 +322,15 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_GETGROUPS = 158              => sys_getgroups(args[..2]);
     SYS_SETGROUPS = 159              => sys_setgroups(args[..2]);
     SYS_NEWUNAME = 160               => sys_uname(args[..1]);
+    SYS_SETHOSTNAME = 161            => sys_sethostname(args[..2]);
+    SYS_SETDOMAINNAME = 162          => sys_setdomainname(args[..2]);
     SYS_GETRUSAGE = 165              => sys_getrusage(args[..2]);
     SYS_UMASK = 166                  => sys_umask(args[..1]);
     SYS_PRCTL = 167                  => sys_prctl(args[..5]);
     SYS_GETCPU = 168                 => sys_getcpu(args[..3]);
     SYS_GETTIMEOFDAY = 169           => sys_gettimeofday(args[..1]);
+    SYS_SETTIMEOFDAY = 170           => sys_settimeofday(args[..2]);
+    SYS_ADJTIMEX = 171               => sys_adjtimex(args[..1]);
     SYS_GETPID = 172                 => sys_getpid(args[..0]);
     SYS_GETPPID = 173                => sys_getppid(args[..0]);
     SYS_GETUID = 174                 => sys_getuid(args[..0]);
@@ -292,10 +339,24 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_GETEGID = 177                => sys_getegid(args[..0]);
     SYS_GETTID = 178                 => sys_gettid(args[..0]);
     SYS_SYSINFO = 179                => sys_sysinfo(args[..1]);
+    SYS_MQ_OPEN = 180                => sys_mq_open(args[..4]);
+    SYS_MQ_UNLINK = 181              => sys_mq_unlink(args[..1]);
+    SYS_MQ_TIMEDSEND = 182           => sys_mq_timedsend(args[..5]);
+    SYS_MQ_TIMEDRECEIVE = 183        => sys_mq_timedreceive(args[..5]);
+    SYS_MQ_NOTIFY = 184              => sys_mq_notify(args[..2]);
+    SYS_MQ_GETSETATTR = 185          => sys_mq_getsetattr(args[..3]);
+    SYS_MSGGET = 186                 => sys_msgget(args[..2]);
+    SYS_MSGCTL = 187                 => sys_msgctl(args[..3]);
+    SYS_MSGRCV = 188                 => sys_msgrcv(args[..5]);
+    SYS_MSGSND = 189                 => sys_msgsnd(args[..4]);
     SYS_SEMGET = 190                 => sys_semget(args[..3]);
     SYS_SEMCTL = 191                 => sys_semctl(args[..4]);
     SYS_SEMTIMEDOP = 192             => sys_semtimedop(args[..4]);
     SYS_SEMOP = 193                  => sys_semop(args[..3]);
+    SYS_SHMGET = 194                 => sys_shmget(args[..3]);
+    SYS_SHMCTL = 195                 => sys_shmctl(args[..3]);
+    SYS_SHMAT = 196                  => sys_shmat(args[..3]);
+    SYS_SHMDT = 197                  => sys_shmdt(args[..1]);
     SYS_SOCKET = 198                 => sys_socket(args[..3]);
     SYS_SOCKETPAIR = 199             => sys_socketpair(args[..4]);
     SYS_BIND = 200                   => sys_bind(args[..3]);
@@ -320,21 +381,39 @@ impl_syscall_nums_and_dispatch_fn! {
     SYS_FADVISE64 = 223              => sys_fadvise64(args[..4]);
     SYS_MPROTECT = 226               => sys_mprotect(args[..3]);
     SYS_MSYNC = 227                  => sys_msync(args[..3]);
+    SYS_MLOCK = 228                  => sys_mlock(args[..2]);
+    SYS_MUNLOCK = 229                => sys_munlock(args[..2]);
+    SYS_MLOCKALL = 230               => sys_mlockall(args[..1]);
+    SYS_MUNLOCKALL = 231             => sys_munlockall(args[..0]);
+    SYS_MINCORE = 232                => sys_mincore(args[..3]);
     SYS_MADVISE = 233                => sys_madvise(args[..3]);
     SYS_ACCEPT4 = 242                => sys_accept4(args[..4]);
     SYS_WAIT4 = 260                  => sys_wait4(args[..4]);
     SYS_PRLIMIT64 = 261              => sys_prlimit64(args[..4]);
+    SYS_CLOCK_ADJTIME = 266          => sys_clock_adjtime(args[..2]);
+    SYS_SETNS = 268                  => sys_setns(args[..2]);
+    SYS_PROCESS_VM_READV = 270       => sys_process_vm_readv(args[..6]);
+    SYS_PROCESS_VM_WRITEV = 271      => sys_process_vm_writev(args[..6]);
     SYS_SCHED_SETATTR = 274          => sys_sched_setattr(args[..3]);
     SYS_SCHED_GETATTR = 275          => sys_sched_getattr(args[..4]);
+    SYS_SECCOMP = 277                => sys_seccomp(args[..3]);
     SYS_GETRANDOM = 278              => sys_getrandom(args[..3]);
     SYS_MEMFD_CREATE = 279           => sys_memfd_create(args[..2]);
     SYS_EXECVEAT = 281               => sys_execveat(args[..5], &mut user_ctx);
+    SYS_USERFAULTFD = 282            => sys_userfaultfd(args[..1]);
+    SYS_MLOCK2 = 284                 => sys_mlock2(args[..3]);
+    SYS_COPY_FILE_RANGE = 285        => sys_copy_file_range(args[..6]);
     SYS_PREADV2 = 286                => sys_preadv2(args[..5]);
     SYS_PWRITEV2 = 287               => sys_pwritev2(args[..5]);
     SYS_STATX = 291                  => sys_statx(args[..5]);
+    SYS_PIDFD_SEND_SIGNAL = 424      => sys_pidfd_send_signal(args[..4]);
+    SYS_IO_URING_SETUP = 425         => sys_io_uring_setup(args[..2]);
+    SYS_IO_URING_ENTER = 426         => sys_io_uring_enter(args[..6]);
+    SYS_IO_URING_REGISTER = 427      => sys_io_uring_register(args[..4]);
     SYS_PIDFD_OPEN = 434             => sys_pidfd_open(args[..2]);
     SYS_CLONE3 = 435                 => sys_clone3(args[..2], &user_ctx);
     SYS_CLOSE_RANGE = 436            => sys_close_range(args[..3]);
+    SYS_PIDFD_GETFD = 438            => sys_pidfd_getfd(args[..3]);
     SYS_FACCESSAT2 = 439             => sys_faccessat2(args[..4]);
     SYS_EPOLL_PWAIT2 = 441           => sys_epoll_pwait2(args[..5]);
 }
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{ClockId, SyscallReturn};
+use crate::{
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    time::{clockid_t, clocks::RealTimeClock, timespec_t},
+};
+
+pub fn sys_clock_settime(
+    clockid: clockid_t,
+    timespec_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let timespec = ctx.user_space().read_val::<timespec_t>(timespec_addr)?;
+    debug!("clockid = {:?}, timespec = {:?}", clockid, timespec);
+
+    match ClockId::try_from(clockid) {
+        Ok(ClockId::CLOCK_REALTIME) => (),
+        Ok(ClockId::CLOCK_PROCESS_CPUTIME_ID | ClockId::CLOCK_THREAD_CPUTIME_ID) => {
+            return_errno_with_message!(Errno::EPERM, "the CPU-time clocks cannot be set")
+        }
+        _ => return_errno_with_message!(Errno::EINVAL, "the clock cannot be set"),
+    }
+
+    let time = Duration::try_from(timespec)?;
+    set_realtime(time, ctx)?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Sets the real-time clock, which requires the `CAP_SYS_TIME` capability.
+pub(super) fn set_realtime(time: Duration, ctx: &Context) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    if !credentials.euid().is_root() && !credentials.effective_capset().contains(CapSet::SYS_TIME) {
+        return_errno_with_message!(Errno::EPERM, "setting the clock is not allowed");
+    }
+
+    RealTimeClock::get().set_time(time)
+}
@@ -81,17 +81,18 @@ struct Clone3Args {
     cgroup: u64,
 }
 
+/// Places the child in the cgroup referred to by `Clone3Args::cgroup`.
+///
+/// The flag does not fit in `CloneFlags`, so it can only be specified with `clone3`.
+const CLONE_INTO_CGROUP: u64 = 0x2_0000_0000;
+
 impl From<Clone3Args> for CloneArgs {
     fn from(value: Clone3Args) -> Self {
-        // TODO: Deal with set_tid, set_tid_size, cgroup
+        // TODO: Deal with set_tid, set_tid_size
         if value.set_tid != 0 || value.set_tid_size != 0 {
             warn!("set_tid is not supported");
         }
 
-        if value.cgroup != 0 {
-            warn!("cgroup is not supported");
-        }
-
         Self {
             flags: CloneFlags::from_bits_truncate(value.flags as u32),
             pidfd: Some(value.pidfd as Vaddr),
@@ -103,7 +104,7 @@ impl From<Clone3Args> for CloneArgs {
             tls: value.tls,
             _set_tid: Some(value.set_tid),
             _set_tid_size: Some(value.set_tid_size),
-            _cgroup: Some(value.cgroup),
+            cgroup: (value.flags & CLONE_INTO_CGROUP != 0).then_some(value.cgroup),
         }
     }
 }
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    splice::{get_file_pair, read_offset},
+    SyscallReturn,
+};
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::FileDesc,
+        utils::{InodeType, SeekFrom, StatusFlags},
+    },
+    prelude::*,
+};
+
+pub fn sys_copy_file_range(
+    fd_in: FileDesc,
+    off_in_addr: Vaddr,
+    fd_out: FileDesc,
+    off_out_addr: Vaddr,
+    len: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd_in = {}, off_in = {:#x}, fd_out = {}, off_out = {:#x}, len = {}, flags = {:#x}",
+        fd_in, off_in_addr, fd_out, off_out_addr, len, flags
+    );
+
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags must be zero");
+    }
+
+    let (file_in, file_out) = get_file_pair(fd_in, fd_out, ctx)?;
+    let off_in = read_offset(off_in_addr, ctx)?;
+    let off_out = read_offset(off_out_addr, ctx)?;
+
+    if file_out.status_flags().contains(StatusFlags::O_APPEND) {
+        return_errno_with_message!(Errno::EBADF, "the output file is opened in append mode");
+    }
+
+    let (Some(inode_in), Some(inode_out)) = (file_in.inode(), file_out.inode()) else {
+        return_errno_with_message!(Errno::EINVAL, "the files are not regular files");
+    };
+    for inode in [inode_in, inode_out] {
+        match inode.type_() {
+            InodeType::File => (),
+            InodeType::Dir => {
+                return_errno_with_message!(Errno::EISDIR, "the file is a directory")
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the file is not a regular file"),
+        }
+    }
+
+    let pos_in = match off_in {
+        Some(offset) => offset,
+        None => file_in.seek(SeekFrom::Current(0))?,
+    };
+    let pos_out = match off_out {
+        Some(offset) => offset,
+        None => file_out.seek(SeekFrom::Current(0))?,
+    };
+
+    let exceeds_limit = |pos: usize| {
+        pos.checked_add(len)
+            .is_none_or(|end| end > isize::MAX as usize)
+    };
+    if exceeds_limit(pos_in) || exceeds_limit(pos_out) {
+        return_errno_with_message!(Errno::EOVERFLOW, "the range is too large");
+    }
+    if Arc::ptr_eq(inode_in, inode_out) && pos_in < pos_out + len && pos_out < pos_in + len {
+        return_errno_with_message!(Errno::EINVAL, "the ranges overlap in the same file");
+    }
+    if len == 0 {
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    // Let the file system copy the data without an intermediate buffer if it supports to do so.
+    let copied_len = match inode_in.copy_file_range(pos_in, inode_out, pos_out, len) {
+        Err(err) if err.error() == Errno::EOPNOTSUPP => {
+            copy_with_buffer(file_in.as_ref(), pos_in, file_out.as_ref(), pos_out, len)?
+        }
+        res => res?,
+    };
+
+    match off_in {
+        Some(_) => ctx
+            .user_space()
+            .write_val(off_in_addr, &((pos_in + copied_len) as i64))?,
+        None => {
+            file_in.seek(SeekFrom::Current(copied_len as isize))?;
+        }
+    }
+    match off_out {
+        Some(_) => ctx
+            .user_space()
+            .write_val(off_out_addr, &((pos_out + copied_len) as i64))?,
+        None => {
+            file_out.seek(SeekFrom::Current(copied_len as isize))?;
+        }
+    }
+
+    Ok(SyscallReturn::Return(copied_len as _))
+}
+
+fn copy_with_buffer(
+    file_in: &dyn FileLike,
+    pos_in: usize,
+    file_out: &dyn FileLike,
+    pos_out: usize,
+    len: usize,
+) -> Result<usize> {
+    const BUFFER_SIZE: usize = PAGE_SIZE;
+    let mut buffer = vec![0u8; BUFFER_SIZE].into_boxed_slice();
+    let mut copied_len = 0;
+
+    while copied_len < len {
+        let max_read_len = BUFFER_SIZE.min(len - copied_len);
+        let read_res = file_in.read_bytes_at(pos_in + copied_len, &mut buffer[..max_read_len]);
+        let read_len = match read_res {
+            Ok(0) => break,
+            Ok(read_len) => read_len,
+            Err(_) if copied_len > 0 => break,
+            Err(err) => return Err(err),
+        };
+
+        let write_res = file_out.write_bytes_at(pos_out + copied_len, &buffer[..read_len]);
+        let written_len = match write_res {
+            Ok(written_len) => written_len,
+            Err(_) if copied_len > 0 => break,
+            Err(err) => return Err(err),
+        };
+        copied_len += written_len;
+
+        if written_len < read_len {
+            break;
+        }
+    }
+
+    Ok(copied_len)
+}
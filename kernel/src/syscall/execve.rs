@@ -15,8 +15,8 @@ use crate::{
     },
     prelude::*,
     process::{
-        check_executable_file, posix_thread::ThreadName, renew_vm_and_map, Credentials, Process,
-        ProgramToLoad, MAX_LEN_STRING_ARG, MAX_NR_STRING_ARGS,
+        check_executable_file, posix_thread::ThreadName, renew_vm_and_map, Credentials, Dumpable,
+        Process, ProgramToLoad, MAX_LEN_STRING_ARG, MAX_NR_STRING_ARGS,
     },
 };
 
@@ -149,9 +149,14 @@ fn do_execve(
     // Reset FPU context
     thread_local.fpu().set_context(FpuContext::new());
 
+    // With `no_new_privs`, the set-user-ID and set-group-ID bits are ignored.
+    let no_new_privs = posix_thread.no_new_privs();
     let credentials = posix_thread.credentials_mut();
-    set_uid_from_elf(process, &credentials, &elf_file)?;
-    set_gid_from_elf(process, &credentials, &elf_file)?;
+    // The process becomes dumpable again, unless it executes a set-user-ID or set-group-ID
+    // program.
+    process.set_dumpable(Dumpable::User);
+    set_uid_from_elf(process, &credentials, &elf_file, no_new_privs)?;
+    set_gid_from_elf(process, &credentials, &elf_file, no_new_privs)?;
     credentials.set_keep_capabilities(false);
 
     // set executable path
@@ -167,6 +172,8 @@ fn do_execve(
     // set new user stack top
     user_context.set_stack_pointer(elf_load_info.user_stack_top as _);
     debug!("user stack top: 0x{:x}", elf_load_info.user_stack_top);
+
+    posix_thread.ptrace_report_exec(user_context);
     Ok(())
 }
 
@@ -220,12 +227,14 @@ fn set_uid_from_elf(
     current: &Process,
     credentials: &Credentials<WriteOp>,
     elf_file: &Path,
+    no_new_privs: bool,
 ) -> Result<()> {
-    if elf_file.mode()?.has_set_uid() {
+    if elf_file.mode()?.has_set_uid() && !no_new_privs {
         let uid = elf_file.owner()?;
         credentials.set_euid(uid);
 
         current.clear_parent_death_signal();
+        current.set_dumpable(Dumpable::Disable);
     }
 
     // No matter whether the elf_file has `set_uid` bit, suid should be reset.
@@ -238,12 +247,14 @@ fn set_gid_from_elf(
     current: &Process,
     credentials: &Credentials<WriteOp>,
     elf_file: &Path,
+    no_new_privs: bool,
 ) -> Result<()> {
-    if elf_file.mode()?.has_set_gid() {
+    if elf_file.mode()?.has_set_gid() && !no_new_privs {
         let gid = elf_file.group()?;
         credentials.set_egid(gid);
 
         current.clear_parent_death_signal();
+        current.set_dumpable(Dumpable::Disable);
     }
 
     // No matter whether the the elf file has `set_gid` bit, sgid should be reset.
@@ -77,12 +77,20 @@ impl PriorityTarget {
     pub(super) fn new(which: i32, who: u32, ctx: &Context) -> Result<Self> {
         let which = Which::try_from(which)
             .map_err(|_| Error::with_message(Errno::EINVAL, "invalid which value"))?;
+
+        // The process IDs are in the PID namespace of the current thread.
+        let to_global = |id: u32| {
+            ctx.posix_thread.pid_ns().to_global(id).ok_or_else(|| {
+                Error::with_message(Errno::ESRCH, "the target process does not exist")
+            })
+        };
+
         Ok(match which {
             Which::PRIO_PROCESS => {
                 let pid = if who == 0 {
                     ctx.process.pid()
                 } else {
-                    who as Pid
+                    to_global(who)?
                 };
                 Self::Process(pid)
             }
@@ -90,7 +98,7 @@ impl PriorityTarget {
                 let pgid = if who == 0 {
                     ctx.process.pgid()
                 } else {
-                    who as Pgid
+                    to_global(who)?
                 };
                 Self::ProcessGroup(pgid)
             }
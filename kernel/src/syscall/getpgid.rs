@@ -1,24 +1,25 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{
-    prelude::*,
-    process::{process_table, Pid},
-};
+use crate::{prelude::*, process::Pid};
 
 pub fn sys_getpgid(pid: Pid, ctx: &Context) -> Result<SyscallReturn> {
     debug!("pid = {}", pid);
 
+    // The IDs are in the PID namespace of the current thread.
+    let pid_ns = ctx.posix_thread.pid_ns();
+
     // The documentation quoted below is from
     // <https://www.man7.org/linux/man-pages/man2/getpgid.2.html>.
 
     // "If `pid` is equal to 0, getpgid() shall return the process group ID of the calling
     // process."
     if pid == 0 {
-        return Ok(SyscallReturn::Return(ctx.process.pgid() as _));
+        let pgid = pid_ns.to_local(ctx.process.pgid());
+        return Ok(SyscallReturn::Return(pgid as _));
     }
 
-    let process = process_table::get_process(pid).ok_or(Error::with_message(
+    let process = pid_ns.get_process(pid).ok_or(Error::with_message(
         Errno::ESRCH,
         "the process to get the PGID does not exist",
     ))?;
@@ -27,5 +28,5 @@ pub fn sys_getpgid(pid: Pid, ctx: &Context) -> Result<SyscallReturn> {
     // session than the current process. Linux does not perform this check by default, but some
     // strict security policies (e.g. SELinux) may do so.
 
-    Ok(SyscallReturn::Return(process.pgid() as _))
+    Ok(SyscallReturn::Return(pid_ns.to_local(process.pgid()) as _))
 }
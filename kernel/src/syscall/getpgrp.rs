@@ -4,5 +4,6 @@ use super::SyscallReturn;
 use crate::prelude::*;
 
 pub fn sys_getpgrp(ctx: &Context) -> Result<SyscallReturn> {
-    Ok(SyscallReturn::Return(ctx.process.pgid() as _))
+    let pgid = ctx.posix_thread.pid_ns().to_local(ctx.process.pgid());
+    Ok(SyscallReturn::Return(pgid as _))
 }
@@ -4,5 +4,10 @@ use super::SyscallReturn;
 use crate::prelude::*;
 
 pub fn sys_getppid(ctx: &Context) -> Result<SyscallReturn> {
-    Ok(SyscallReturn::Return(ctx.process.parent().pid() as _))
+    // If the parent is outside the PID namespace, the parent ID is zero.
+    let ppid = ctx
+        .posix_thread
+        .pid_ns()
+        .to_local(ctx.process.parent().pid());
+    Ok(SyscallReturn::Return(ppid as _))
 }
@@ -1,23 +1,24 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{
-    prelude::*,
-    process::{process_table, Pid},
-};
+use crate::{prelude::*, process::Pid};
 
 pub fn sys_getsid(pid: Pid, ctx: &Context) -> Result<SyscallReturn> {
     debug!("pid = {}", pid);
 
+    // The IDs are in the PID namespace of the current thread.
+    let pid_ns = ctx.posix_thread.pid_ns();
+
     // The documentation quoted below is from
     // <https://www.man7.org/linux/man-pages/man2/getsid.2.html>.
 
     // "If `pid` is 0, getsid() returns the session ID of the calling process."
     if pid == 0 {
-        return Ok(SyscallReturn::Return(ctx.process.sid() as _));
+        let sid = pid_ns.to_local(ctx.process.sid());
+        return Ok(SyscallReturn::Return(sid as _));
     }
 
-    let process = process_table::get_process(pid).ok_or(Error::with_message(
+    let process = pid_ns.get_process(pid).ok_or(Error::with_message(
         Errno::ESRCH,
         "the process to get the SID does not exist",
     ))?;
@@ -26,5 +27,5 @@ pub fn sys_getsid(pid: Pid, ctx: &Context) -> Result<SyscallReturn> {
     // session than the current process. Linux does not perform this check by default, but some
     // strict security policies (e.g. SELinux) may do so.
 
-    Ok(SyscallReturn::Return(process.sid() as _))
+    Ok(SyscallReturn::Return(pid_ns.to_local(process.sid()) as _))
 }
@@ -4,6 +4,6 @@ use super::SyscallReturn;
 use crate::prelude::*;
 
 pub fn sys_gettid(ctx: &Context) -> Result<SyscallReturn> {
-    let tid = ctx.posix_thread.tid();
+    let tid = ctx.posix_thread.pid_ns().to_local(ctx.posix_thread.tid());
     Ok(SyscallReturn::Return(tid as _))
 }
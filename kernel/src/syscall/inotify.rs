@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_table::{get_file_fast, FdFlags, FileDesc},
+        fs_resolver::{FsPath, AT_FDCWD},
+        notify::{FsEvents, InotifyFile, InotifyFlags, InotifyMask},
+        utils::{InodeType, Permission, PATH_MAX},
+    },
+    prelude::*,
+};
+
+pub fn sys_inotify_init(ctx: &Context) -> Result<SyscallReturn> {
+    sys_inotify_init1(0, ctx)
+}
+
+pub fn sys_inotify_init1(flags: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = InotifyFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid flags"))?;
+    debug!("flags = {:?}", flags);
+
+    let inotify_file = InotifyFile::new(flags.contains(InotifyFlags::IN_NONBLOCK));
+    let fd_flags = if flags.contains(InotifyFlags::IN_CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
+    };
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table.unwrap().write().insert(inotify_file, fd_flags);
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+pub fn sys_inotify_add_watch(
+    fd: FileDesc,
+    path_ptr: Vaddr,
+    mask: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let path_name = ctx.user_space().read_cstring(path_ptr, PATH_MAX)?;
+    let events = FsEvents::from_bits_truncate(mask) - FsEvents::ISDIR;
+    let inotify_mask = InotifyMask::from_bits_truncate(mask);
+    debug!(
+        "fd = {}, path_name = {:?}, events = {:?}, mask = {:?}",
+        fd, path_name, events, inotify_mask
+    );
+
+    if events.is_empty() {
+        return_errno_with_message!(Errno::EINVAL, "no events are specified");
+    }
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd).into_owned();
+    drop(file_table);
+
+    let inotify_file = file
+        .downcast_ref::<InotifyFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the file is not an inotify file"))?;
+
+    let path = {
+        let path_name = path_name.to_string_lossy();
+        if path_name.is_empty() {
+            return_errno_with_message!(Errno::ENOENT, "path is empty");
+        }
+        let fs_path = FsPath::new(AT_FDCWD, path_name.as_ref())?;
+        let fs_ref = ctx.thread_local.borrow_fs();
+        let fs = fs_ref.resolver().read();
+        if inotify_mask.contains(InotifyMask::DONT_FOLLOW) {
+            fs.lookup_no_follow(&fs_path)?
+        } else {
+            fs.lookup(&fs_path)?
+        }
+    };
+
+    if inotify_mask.contains(InotifyMask::ONLYDIR) && path.type_() != InodeType::Dir {
+        return_errno_with_message!(Errno::ENOTDIR, "the path is not a directory");
+    }
+    path.inode().check_permission(Permission::MAY_READ)?;
+
+    let wd = inotify_file.add_watch(path.inode(), events, inotify_mask)?;
+    Ok(SyscallReturn::Return(wd as _))
+}
+
+pub fn sys_inotify_rm_watch(fd: FileDesc, wd: i32, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("fd = {}, wd = {}", fd, wd);
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd).into_owned();
+    drop(file_table);
+
+    let inotify_file = file
+        .downcast_ref::<InotifyFile>()
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the file is not an inotify file"))?;
+    inotify_file.remove_watch(wd)?;
+
+    Ok(SyscallReturn::Return(0))
+}
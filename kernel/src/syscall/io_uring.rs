@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::size_of;
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_table::{get_file_fast, FdFlags, FileDesc},
+        io_uring::{IoUringFile, IoUringParams},
+    },
+    prelude::*,
+};
+
+pub fn sys_io_uring_setup(
+    entries: u32,
+    params_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("entries = {}, params_addr = 0x{:x}", entries, params_addr);
+
+    let user_space = ctx.user_space();
+    let mut params: IoUringParams = user_space.read_val(params_addr)?;
+    let io_uring_file = IoUringFile::new(entries, &mut params)?;
+    user_space.write_val(params_addr, &params)?;
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let fd = file_table
+        .unwrap()
+        .write()
+        .insert(io_uring_file, FdFlags::CLOEXEC);
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+pub fn sys_io_uring_enter(
+    fd: FileDesc,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    argp: Vaddr,
+    argsz: usize,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = EnterFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid io_uring_enter flags"))?;
+    debug!(
+        "fd = {}, to_submit = {}, min_complete = {}, flags = {:?}, argp = 0x{:x}, argsz = {}",
+        fd, to_submit, min_complete, flags, argp, argsz
+    );
+
+    if flags.contains(EnterFlags::EXT_ARG) {
+        return_errno_with_message!(Errno::EINVAL, "extended arguments are not supported");
+    }
+
+    // Submitting requests and registering files need to access the file table.
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd).into_owned();
+    drop(file_table);
+    let Some(io_uring_file) = file.downcast_ref::<IoUringFile>() else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the file is not an io_uring file");
+    };
+
+    let num_submitted = if to_submit > 0 {
+        io_uring_file.submit(to_submit, ctx)?
+    } else {
+        0
+    };
+
+    if flags.contains(EnterFlags::GETEVENTS) {
+        if argp != 0 {
+            // TODO: Support setting the signal mask during the wait.
+            warn!("the signal mask of io_uring_enter is not supported");
+        }
+
+        if let Err(err) = io_uring_file.wait_completions(min_complete) {
+            // Linux reports the number of the submitted SQEs, if any, instead of the error.
+            if num_submitted == 0 {
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(SyscallReturn::Return(num_submitted as _))
+}
+
+pub fn sys_io_uring_register(
+    fd: FileDesc,
+    opcode: u32,
+    arg: Vaddr,
+    nr_args: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "fd = {}, opcode = {}, arg = 0x{:x}, nr_args = {}",
+        fd, opcode, arg, nr_args
+    );
+
+    // Submitting requests and registering files need to access the file table.
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd).into_owned();
+    drop(file_table);
+    let Some(io_uring_file) = file.downcast_ref::<IoUringFile>() else {
+        return_errno_with_message!(Errno::EOPNOTSUPP, "the file is not an io_uring file");
+    };
+
+    match opcode {
+        IORING_REGISTER_FILES => register_files(io_uring_file, arg, nr_args, ctx)?,
+        IORING_UNREGISTER_FILES => {
+            if arg != 0 || nr_args != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the arguments must be empty");
+            }
+            io_uring_file.unregister_files()?;
+        }
+        IORING_REGISTER_PROBE => register_probe(arg, nr_args, ctx)?,
+        _ => return_errno_with_message!(Errno::EINVAL, "unsupported io_uring_register opcode"),
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+fn register_files(
+    io_uring_file: &IoUringFile,
+    arg: Vaddr,
+    nr_args: u32,
+    ctx: &Context,
+) -> Result<()> {
+    if nr_args == 0 || nr_args > IORING_MAX_FIXED_FILES {
+        return_errno_with_message!(Errno::EINVAL, "invalid number of files");
+    }
+
+    let user_space = ctx.user_space();
+    let fds = (0..nr_args as usize)
+        .map(|idx| user_space.read_val::<FileDesc>(arg + idx * size_of::<FileDesc>()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let file_table = ctx.thread_local.borrow_file_table();
+    let file_table_locked = file_table.unwrap().read();
+
+    let mut files = Vec::with_capacity(fds.len());
+    for fd in fds {
+        // A file descriptor of -1 creates a sparse slot.
+        if fd == -1 {
+            files.push(None);
+            continue;
+        }
+
+        let file = file_table_locked.get_file(fd)?;
+        if file.downcast_ref::<IoUringFile>().is_some() {
+            return_errno_with_message!(Errno::EBADF, "io_uring files cannot be registered");
+        }
+        files.push(Some(file.clone()));
+    }
+    drop(file_table_locked);
+
+    io_uring_file.register_files(files)
+}
+
+fn register_probe(arg: Vaddr, nr_args: u32, ctx: &Context) -> Result<()> {
+    const IO_URING_OP_SUPPORTED: u16 = 1 << 0;
+
+    let num_ops = IoUringFile::num_ops();
+    let ops_len = nr_args.min(num_ops as u32) as u8;
+
+    let user_space = ctx.user_space();
+
+    // The probe must be zeroed by user space.
+    let mut probe_bytes = vec![0u8; probe_op_offset(ops_len as usize)];
+    user_space.read_bytes(arg, &mut VmWriter::from(probe_bytes.as_mut_slice()))?;
+    if probe_bytes.iter().any(|byte| *byte != 0) {
+        return_errno_with_message!(Errno::EINVAL, "the probe is not zeroed");
+    }
+
+    let probe = Probe {
+        last_op: num_ops - 1,
+        ops_len,
+        ..Probe::new_zeroed()
+    };
+    user_space.write_val(arg, &probe)?;
+
+    for op in 0..ops_len {
+        let flags = if IoUringFile::is_op_supported(op) {
+            IO_URING_OP_SUPPORTED
+        } else {
+            0
+        };
+        let probe_op = ProbeOp {
+            op,
+            flags,
+            ..ProbeOp::new_zeroed()
+        };
+        user_space.write_val(arg + probe_op_offset(op as usize), &probe_op)?;
+    }
+
+    Ok(())
+}
+
+fn probe_op_offset(idx: usize) -> usize {
+    size_of::<Probe>() + idx * size_of::<ProbeOp>()
+}
+
+bitflags! {
+    struct EnterFlags: u32 {
+        const GETEVENTS = 1 << 0;
+        const SQ_WAKEUP = 1 << 1;
+        const SQ_WAIT   = 1 << 2;
+        const EXT_ARG   = 1 << 3;
+    }
+}
+
+const IORING_REGISTER_FILES: u32 = 2;
+const IORING_UNREGISTER_FILES: u32 = 3;
+const IORING_REGISTER_PROBE: u32 = 8;
+
+/// The maximum number of registered files.
+const IORING_MAX_FIXED_FILES: u32 = 1 << 15;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct Probe {
+    last_op: u8,
+    ops_len: u8,
+    resv: u16,
+    resv2: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct ProbeOp {
+    op: u8,
+    resv: u8,
+    flags: u16,
+    resv2: u32,
+}
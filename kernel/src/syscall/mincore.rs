@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::{prelude::*, vm::vmar::is_userspace_vaddr};
+
+pub fn sys_mincore(start: Vaddr, len: usize, vec: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    debug!(
+        "start = 0x{:x}, len = 0x{:x}, vec = 0x{:x}",
+        start, len, vec
+    );
+
+    if start % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the start address should be page aligned");
+    }
+    if len == 0 {
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let end = start
+        .checked_add(len)
+        .filter(|end| is_userspace_vaddr(start) && is_userspace_vaddr(end - 1))
+        .ok_or_else(|| Error::with_message(Errno::ENOMEM, "the range is not in user space"))?
+        .align_up(PAGE_SIZE);
+
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+
+    // Query the residency in chunks to bound the size of the kernel buffer.
+    const MAX_PAGES_PER_CHUNK: usize = PAGE_SIZE;
+    let mut residency = vec![0u8; MAX_PAGES_PER_CHUNK];
+
+    let mut chunk_start = start;
+    let mut vec_addr = vec;
+    while chunk_start < end {
+        let chunk_end = end.min(chunk_start + MAX_PAGES_PER_CHUNK * PAGE_SIZE);
+        let nr_pages = (chunk_end - chunk_start) / PAGE_SIZE;
+
+        root_vmar.query_residency(chunk_start..chunk_end, &mut residency[..nr_pages])?;
+        user_space.write_bytes(vec_addr, &mut VmReader::from(&residency[..nr_pages]))?;
+
+        chunk_start = chunk_end;
+        vec_addr += nr_pages;
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, ResourceType},
+    vm::vmar::LockMode,
+};
+
+pub fn sys_mlock(start: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("start = 0x{:x}, len = 0x{:x}", start, len);
+
+    do_mlock(start, len, LockMode::Populate, ctx)
+}
+
+pub fn sys_mlock2(start: Vaddr, len: usize, flags: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = Mlock2Flags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid mlock2 flags"))?;
+    debug!(
+        "start = 0x{:x}, len = 0x{:x}, flags = {:?}",
+        start, len, flags
+    );
+
+    let mode = if flags.contains(Mlock2Flags::MLOCK_ONFAULT) {
+        LockMode::OnFault
+    } else {
+        LockMode::Populate
+    };
+    do_mlock(start, len, mode, ctx)
+}
+
+pub fn sys_munlock(start: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("start = 0x{:x}, len = 0x{:x}", start, len);
+
+    let Some(range) = page_range(start, len)? else {
+        return Ok(SyscallReturn::Return(0));
+    };
+
+    ctx.user_space().root_vmar().unlock(range)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_mlockall(flags: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = MlockallFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid mlockall flags"))?;
+    debug!("flags = {:?}", flags);
+
+    if !flags.intersects(MlockallFlags::MCL_CURRENT | MlockallFlags::MCL_FUTURE) {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "either MCL_CURRENT or MCL_FUTURE must be specified"
+        );
+    }
+    check_can_mlock(ctx)?;
+
+    let mode = if flags.contains(MlockallFlags::MCL_ONFAULT) {
+        LockMode::OnFault
+    } else {
+        LockMode::Populate
+    };
+    let current = flags.contains(MlockallFlags::MCL_CURRENT).then_some(mode);
+    let future = flags.contains(MlockallFlags::MCL_FUTURE).then_some(mode);
+
+    ctx.user_space().root_vmar().lock_all(current, future)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_munlockall(ctx: &Context) -> Result<SyscallReturn> {
+    ctx.user_space().root_vmar().unlock_all();
+    Ok(SyscallReturn::Return(0))
+}
+
+fn do_mlock(start: Vaddr, len: usize, mode: LockMode, ctx: &Context) -> Result<SyscallReturn> {
+    check_can_mlock(ctx)?;
+
+    let Some(range) = page_range(start, len)? else {
+        return Ok(SyscallReturn::Return(0));
+    };
+
+    ctx.user_space().root_vmar().lock(range, mode)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Returns the range of the pages that contain `start..start + len`.
+///
+/// Unlike most memory syscalls, the start address does not need to be page-aligned.
+fn page_range(start: Vaddr, len: usize) -> Result<Option<Range<Vaddr>>> {
+    let end = start
+        .checked_add(len)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "integer overflow when (start + len)"))?;
+    if end > isize::MAX as usize {
+        return_errno_with_message!(Errno::ENOMEM, "the range is not in user space");
+    }
+
+    let start = start.align_down(PAGE_SIZE);
+    let end = end.align_up(PAGE_SIZE);
+
+    if start == end {
+        return Ok(None);
+    }
+    Ok(Some(start..end))
+}
+
+/// Checks whether the current thread can lock memory.
+///
+/// Like Linux, memory cannot be locked if `RLIMIT_MEMLOCK` is zero, unless the thread has the
+/// `CAP_IPC_LOCK` capability.
+pub(super) fn check_can_mlock(ctx: &Context) -> Result<()> {
+    let rlimit_memlock = ctx
+        .process
+        .resource_limits()
+        .get_rlimit(ResourceType::RLIMIT_MEMLOCK)
+        .get_cur();
+    if rlimit_memlock != 0 {
+        return Ok(());
+    }
+
+    let credentials = ctx.posix_thread.credentials();
+    if credentials.euid().is_root() || credentials.effective_capset().contains(CapSet::IPC_LOCK) {
+        return Ok(());
+    }
+
+    return_errno_with_message!(Errno::EPERM, "locking memory is not allowed")
+}
+
+bitflags! {
+    /// Flags for `mlock2`.
+    struct Mlock2Flags: u32 {
+        /// Locks the pages when they are faulted in, instead of faulting in all of them.
+        const MLOCK_ONFAULT = 0x01;
+    }
+}
+
+bitflags! {
+    /// Flags for `mlockall`.
+    struct MlockallFlags: u32 {
+        /// Locks the pages that are currently mapped.
+        const MCL_CURRENT = 1;
+        /// Locks the pages that will be mapped in the future.
+        const MCL_FUTURE  = 2;
+        /// Locks the pages when they are faulted in, instead of faulting in all of them.
+        const MCL_ONFAULT = 4;
+    }
+}
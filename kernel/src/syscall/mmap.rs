@@ -5,11 +5,15 @@
 use align_ext::AlignExt;
 use aster_rights::Rights;
 
-use super::SyscallReturn;
+use super::{mlock::check_can_mlock, SyscallReturn};
 use crate::{
     fs::file_table::{get_file_fast, FileDesc},
     prelude::*,
-    vm::{perms::VmPerms, vmar::is_userspace_vaddr, vmo::VmoOptions},
+    vm::{
+        perms::VmPerms,
+        vmar::{is_userspace_vaddr, LockMode},
+        vmo::VmoOptions,
+    },
 };
 
 pub fn sys_mmap(
@@ -54,6 +58,9 @@ fn do_sys_mmap(
     }
 
     check_option(addr, len, &option)?;
+    if option.flags.contains(MMapFlags::MAP_LOCKED) {
+        check_can_mlock(ctx)?;
+    }
 
     if len == 0 {
         return_errno_with_message!(Errno::EINVAL, "mmap len cannot be zero");
@@ -100,6 +107,10 @@ fn do_sys_mmap(
             options = options.is_shared(true);
         }
 
+        if flags.contains(MMapFlags::MAP_LOCKED) {
+            options = options.lock(LockMode::Populate);
+        }
+
         if option.flags.contains(MMapFlags::MAP_ANONYMOUS) {
             if offset != 0 {
                 return_errno_with_message!(
@@ -131,17 +142,29 @@ fn do_sys_mmap(
                 return_errno!(Errno::EACCES);
             }
 
-            let Some(inode) = file.inode() else {
-                return_errno_with_message!(Errno::EINVAL, "the file has no associated inode");
-            };
-            if inode.page_cache().is_none() {
-                return_errno_with_message!(Errno::EBADF, "File does not have page cache");
+            if let Some(vmo) = file.mmap_vmo(offset)? {
+                if len > vmo.size() {
+                    return_errno_with_message!(Errno::EINVAL, "mmap len exceeds the VMO size");
+                }
+                // The VMO is shared with the kernel. A private mapping would copy the pages on
+                // write and then miss the updates made by the kernel, so it is always shared.
+                options = options.vmo(vmo).is_shared(true);
+            } else {
+                let Some(inode) = file.inode() else {
+                    return_errno_with_message!(Errno::EINVAL, "the file has no associated inode");
+                };
+                if inode.page_cache().is_none() {
+                    return_errno_with_message!(Errno::EBADF, "File does not have page cache");
+                }
+
+                // Record the path if possible, so that the mapping can be reported with the
+                // file name.
+                options = match file.as_inode_or_err() {
+                    Ok(inode_handle) => options.path(inode_handle.path().clone()),
+                    Err(_) => options.inode(inode.clone()),
+                };
+                options = options.vmo_offset(offset).handle_page_faults_around();
             }
-
-            options = options
-                .inode(inode.clone())
-                .vmo_offset(offset)
-                .handle_page_faults_around();
         }
 
         options
@@ -11,10 +11,11 @@ pub use clock_gettime::ClockId;
 use ostd::cpu::context::UserContext;
 pub use timer_create::create_timer;
 
-use crate::{context::Context, cpu::LinuxAbi, prelude::*};
+use crate::{context::Context, cpu::LinuxAbi, prelude::*, process::seccomp::secure_computing};
 
 mod accept;
 mod access;
+mod adjtimex;
 mod alarm;
 #[cfg(target_arch = "x86_64")]
 #[path = "arch/x86.rs"]
@@ -35,10 +36,12 @@ mod chmod;
 mod chown;
 mod chroot;
 mod clock_gettime;
+mod clock_settime;
 mod clone;
 mod close;
 mod connect;
 mod constants;
+mod copy_file_range;
 mod dup;
 mod epoll;
 mod eventfd;
@@ -77,6 +80,8 @@ mod gettid;
 mod gettimeofday;
 mod getuid;
 mod getxattr;
+mod inotify;
+mod io_uring;
 mod ioctl;
 mod kill;
 mod link;
@@ -85,18 +90,32 @@ mod listxattr;
 mod lseek;
 mod madvise;
 mod memfd_create;
+mod mincore;
 mod mkdir;
 mod mknod;
+mod mlock;
 mod mmap;
 mod mount;
 mod mprotect;
+mod mq_getsetattr;
+mod mq_notify;
+mod mq_open;
+mod mq_timedreceive;
+mod mq_timedsend;
+mod mq_unlink;
 mod mremap;
+mod msgctl;
+mod msgget;
+mod msgrcv;
+mod msgsnd;
 mod msync;
 mod munmap;
 mod nanosleep;
 mod open;
 mod pause;
+mod pidfd_getfd;
 mod pidfd_open;
+mod pidfd_send_signal;
 mod pipe;
 mod poll;
 mod ppoll;
@@ -104,7 +123,9 @@ mod prctl;
 mod pread64;
 mod preadv;
 mod prlimit64;
+mod process_vm;
 mod pselect6;
+mod ptrace;
 mod pwrite64;
 mod pwritev;
 mod read;
@@ -129,6 +150,7 @@ mod sched_setattr;
 mod sched_setparam;
 mod sched_setscheduler;
 mod sched_yield;
+mod seccomp;
 mod select;
 mod semctl;
 mod semget;
@@ -144,7 +166,9 @@ mod setfsgid;
 mod setfsuid;
 mod setgid;
 mod setgroups;
+mod sethostname;
 mod setitimer;
+mod setns;
 mod setpgid;
 mod setregid;
 mod setresgid;
@@ -152,19 +176,26 @@ mod setresuid;
 mod setreuid;
 mod setsid;
 mod setsockopt;
+mod settimeofday;
 mod setuid;
 mod setxattr;
+mod shmat;
+mod shmctl;
+mod shmdt;
+mod shmget;
 mod shutdown;
 mod sigaltstack;
 mod signalfd;
 mod socket;
 mod socketpair;
+mod splice;
 mod stat;
 mod statfs;
 mod statx;
 mod symlink;
 mod sync;
 mod sysinfo;
+mod tee;
 mod tgkill;
 mod time;
 mod timer_create;
@@ -177,7 +208,10 @@ mod umask;
 mod umount;
 mod uname;
 mod unlink;
+mod unshare;
+mod userfaultfd;
 mod utimens;
+mod vmsplice;
 mod wait4;
 mod waitid;
 mod write;
@@ -359,26 +393,40 @@ impl SyscallArgument {
 }
 
 pub fn handle_syscall(ctx: &Context, user_ctx: &mut UserContext) {
+    // The tracer may change the syscall number and the arguments.
+    ctx.posix_thread.ptrace_report_syscall_entry(user_ctx);
+
     let syscall_frame = SyscallArgument::new_from_context(user_ctx);
-    let syscall_return = arch::syscall_dispatch(
-        syscall_frame.syscall_number,
-        syscall_frame.args,
-        ctx,
-        user_ctx,
-    );
 
-    match syscall_return {
-        Ok(return_value) => {
-            if let SyscallReturn::Return(return_value) = return_value {
-                user_ctx.set_syscall_ret(return_value as usize);
+    // The syscall is skipped if it is rejected by seccomp, in which case the return value (if
+    // any) has been set.
+    if secure_computing(ctx, user_ctx) {
+        let syscall_return = arch::syscall_dispatch(
+            syscall_frame.syscall_number,
+            syscall_frame.args,
+            ctx,
+            user_ctx,
+        );
+
+        match syscall_return {
+            Ok(return_value) => {
+                if let SyscallReturn::Return(return_value) = return_value {
+                    user_ctx.set_syscall_ret(return_value as usize);
+                }
+            }
+            Err(err) => {
+                debug!("syscall return error: {:?}", err);
+                let errno = err.error() as i32;
+                user_ctx.set_syscall_ret((-errno) as usize)
             }
-        }
-        Err(err) => {
-            debug!("syscall return error: {:?}", err);
-            let errno = err.error() as i32;
-            user_ctx.set_syscall_ret((-errno) as usize)
         }
     }
+
+    // Syscalls like `exit` never return to the user space.
+    if !ctx.thread.is_exited() {
+        ctx.posix_thread
+            .ptrace_report_syscall_exit(syscall_frame.syscall_number as usize, user_ctx);
+    }
 }
 
 #[macro_export]
@@ -401,7 +449,3 @@ macro_rules! log_syscall_entry {
         }
     };
 }
-
-pub(super) fn init() {
-    uname::init();
-}
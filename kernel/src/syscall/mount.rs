@@ -5,7 +5,7 @@ use crate::{
     fs::{
         fs_resolver::{FsPath, AT_FDCWD},
         path::Path,
-        registry::FsProperties,
+        registry::{FsProperties, FsType},
         utils::{FileSystem, InodeType},
     },
     prelude::*,
@@ -168,8 +168,8 @@ fn get_fs(
     let fs_type = fs_type
         .to_str()
         .map_err(|_| Error::with_message(Errno::ENODEV, "Invalid file system type"))?;
-    let fs_type = crate::fs::registry::look_up(fs_type)
-        .ok_or(Error::with_message(Errno::EINVAL, "Invalid fs type"))?;
+    let fs_type =
+        look_up_fs_type(fs_type).ok_or(Error::with_message(Errno::EINVAL, "Invalid fs type"))?;
 
     let disk = if fs_type.properties().contains(FsProperties::NEED_DISK) {
         Some(
@@ -180,7 +180,18 @@ fn get_fs(
         None
     };
 
-    fs_type.create(data, disk, ctx)
+    fs_type.create(&devname, data, disk, ctx)
+}
+
+/// Looks up a FS type by the name, which may be in the form of `"<type>.<subtype>"`.
+fn look_up_fs_type(name: &str) -> Option<Arc<dyn FsType>> {
+    if let Some(fs_type) = crate::fs::registry::look_up(name) {
+        return Some(fs_type);
+    }
+
+    let (name, _subtype) = name.split_once('.')?;
+    crate::fs::registry::look_up(name)
+        .filter(|fs_type| fs_type.properties().contains(FsProperties::HAS_SUBTYPE))
 }
 
 bitflags! {
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{mq_open::access_mqueue_with, SyscallReturn};
+use crate::{
+    fs::{file_table::FileDesc, mqueue::MqAttr, utils::StatusFlags},
+    prelude::*,
+};
+
+pub fn sys_mq_getsetattr(
+    mqdes: FileDesc,
+    new_attr_addr: Vaddr,
+    old_attr_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "mqdes = {}, new_attr_addr = {:#x}, old_attr_addr = {:#x}",
+        mqdes, new_attr_addr, old_attr_addr
+    );
+
+    let user_space = ctx.user_space();
+
+    let new_attr = if new_attr_addr == 0 {
+        None
+    } else {
+        let new_attr = user_space.read_val::<MqAttr>(new_attr_addr)?;
+        // Only `O_NONBLOCK` can be changed.
+        if new_attr.mq_flags & !(StatusFlags::O_NONBLOCK.bits() as i64) != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the message queue flags are invalid");
+        }
+        Some(new_attr)
+    };
+
+    let old_attr = access_mqueue_with(mqdes, ctx, |file, queue| {
+        let status_flags = file.status_flags();
+
+        let mut old_attr = queue.attr();
+        old_attr.mq_flags = (status_flags & StatusFlags::O_NONBLOCK).bits() as i64;
+
+        if let Some(new_attr) = new_attr {
+            let new_flags = if new_attr.mq_flags != 0 {
+                status_flags | StatusFlags::O_NONBLOCK
+            } else {
+                status_flags - StatusFlags::O_NONBLOCK
+            };
+            file.set_status_flags(new_flags)?;
+        }
+
+        Ok(old_attr)
+    })?;
+
+    if old_attr_addr != 0 {
+        user_space.write_val(old_attr_addr, &old_attr)?;
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
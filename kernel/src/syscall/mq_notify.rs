@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{mq_open::access_mqueue_with, SyscallReturn};
+use crate::{
+    fs::file_table::FileDesc,
+    prelude::*,
+    process::signal::{
+        c_types::{sigevent_t, SigNotify},
+        sig_num::SigNum,
+    },
+};
+
+pub fn sys_mq_notify(
+    mqdes: FileDesc,
+    sigevent_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!("mqdes = {}, sigevent_addr = {:#x}", mqdes, sigevent_addr);
+
+    // A null pointer removes the registration.
+    if sigevent_addr == 0 {
+        access_mqueue_with(mqdes, ctx, |_, queue| {
+            queue.unregister_notification(ctx);
+            Ok(())
+        })?;
+        return Ok(SyscallReturn::Return(0));
+    }
+
+    let sig_event = ctx.user_space().read_val::<sigevent_t>(sigevent_addr)?;
+    let signal = match SigNotify::try_from(sig_event.sigev_notify)? {
+        // Register without sending any notifications.
+        SigNotify::SIGEV_NONE => None,
+        // Send a signal to the current process when a message arrives.
+        SigNotify::SIGEV_SIGNAL => {
+            let signo = u8::try_from(sig_event.sigev_signo)
+                .map_err(|_| Error::with_message(Errno::EINVAL, "the signal number is invalid"))?;
+            // Linux accepts the null signal, which is never sent.
+            if signo == 0 {
+                None
+            } else {
+                Some((SigNum::try_from(signo)?, sig_event.sigev_value))
+            }
+        }
+        // TODO: Support `SIGEV_THREAD`, which requires a netlink socket to notify the C library.
+        SigNotify::SIGEV_THREAD => {
+            return_errno_with_message!(Errno::EINVAL, "SIGEV_THREAD is not supported");
+        }
+        SigNotify::SIGEV_THREAD_ID => {
+            return_errno_with_message!(Errno::EINVAL, "SIGEV_THREAD_ID is invalid for mq_notify");
+        }
+    };
+
+    access_mqueue_with(mqdes, ctx, |_, queue| {
+        queue.register_notification(signal, ctx)
+    })?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::{get_file_fast, FdFlags, FileDesc},
+        inode_handle::InodeHandle,
+        mqueue::{MessageQueue, MqAttr, MqueueFS, MqueueInode},
+        utils::{AccessMode, CreationFlags, InodeMode, Permission, StatusFlags, NAME_MAX},
+    },
+    prelude::*,
+    syscall::constants::MAX_FILENAME_LEN,
+};
+
+pub fn sys_mq_open(
+    name_addr: Vaddr,
+    flags: u32,
+    mode: u16,
+    attr_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let name = ctx.user_space().read_cstring(name_addr, MAX_FILENAME_LEN)?;
+    debug!(
+        "name = {:?}, flags = {:#o}, mode = {:#o}, attr_addr = {:#x}",
+        name, flags, mode, attr_addr
+    );
+
+    let name = name.to_string_lossy();
+    check_mqueue_name(&name)?;
+
+    let creation_flags = CreationFlags::from_bits_truncate(flags);
+    let status_flags = StatusFlags::from_bits_truncate(flags);
+    let access_mode = AccessMode::from_u32(flags)?;
+
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let mqueue_root = ns_proxy.ipc_ns().mqueue_root();
+
+    let inode_handle = match mqueue_root.lookup(&name) {
+        Ok(path) => {
+            if creation_flags.contains(CreationFlags::O_CREAT | CreationFlags::O_EXCL) {
+                return_errno_with_message!(Errno::EEXIST, "the message queue already exists");
+            }
+            InodeHandle::new(path, access_mode, status_flags)?
+        }
+        Err(err) if err.error() == Errno::ENOENT => {
+            if !creation_flags.contains(CreationFlags::O_CREAT) {
+                return_errno_with_message!(Errno::ENOENT, "the message queue does not exist");
+            }
+
+            let attr = if attr_addr == 0 {
+                None
+            } else {
+                Some(ctx.user_space().read_val::<MqAttr>(attr_addr)?)
+            };
+
+            mqueue_root
+                .inode()
+                .check_permission(Permission::MAY_WRITE | Permission::MAY_EXEC)?;
+
+            let mask_mode = mode & !ctx.thread_local.borrow_fs().umask().read().get();
+            let mqueue_fs = mqueue_root.fs();
+            mqueue_fs.downcast_ref::<MqueueFS>().unwrap().create_queue(
+                &name,
+                InodeMode::from_bits_truncate(mask_mode),
+                attr.as_ref(),
+                &ctx.posix_thread.credentials(),
+            )?;
+
+            // Don't check access mode for newly created message queue
+            let path = mqueue_root.lookup(&name)?;
+            InodeHandle::new_unchecked_access(path, access_mode, status_flags)?
+        }
+        Err(err) => return Err(err),
+    };
+
+    let fd = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let mut file_table_locked = file_table.unwrap().write();
+        file_table_locked.insert(Arc::new(inode_handle), FdFlags::CLOEXEC)
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
+
+/// Checks whether the name of a message queue is valid.
+///
+/// The leading slash of the name is removed by the C library, so the name here must not contain
+/// any slashes.
+pub(super) fn check_mqueue_name(name: &str) -> Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return_errno_with_message!(Errno::EACCES, "the message queue name is invalid");
+    }
+    if name.len() > NAME_MAX {
+        return_errno_with_message!(Errno::ENAMETOOLONG, "the message queue name is too long");
+    }
+
+    Ok(())
+}
+
+/// Accesses the message queue of the descriptor with the function.
+pub(super) fn access_mqueue_with<T>(
+    mqdes: FileDesc,
+    ctx: &Context,
+    f: impl FnOnce(&dyn FileLike, &MessageQueue) -> Result<T>,
+) -> Result<T> {
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, mqdes).into_owned();
+    drop(file_table);
+
+    let Some(mqueue_inode) = file
+        .inode()
+        .and_then(|inode| inode.downcast_ref::<MqueueInode>())
+    else {
+        return_errno_with_message!(Errno::EBADF, "the file is not a message queue");
+    };
+
+    f(file.as_ref(), mqueue_inode.queue())
+}
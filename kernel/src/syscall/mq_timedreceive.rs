@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    mq_open::access_mqueue_with,
+    mq_timedsend::{map_mqueue_wait_error, read_mqueue_timeout},
+    SyscallReturn,
+};
+use crate::{
+    fs::{file_table::FileDesc, utils::StatusFlags},
+    prelude::*,
+};
+
+pub fn sys_mq_timedreceive(
+    mqdes: FileDesc,
+    msg_ptr: Vaddr,
+    msg_len: usize,
+    msg_prio_addr: Vaddr,
+    abs_timeout_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "mqdes = {}, msg_ptr = {:#x}, msg_len = {}, msg_prio_addr = {:#x}, timeout_addr = {:#x}",
+        mqdes, msg_ptr, msg_len, msg_prio_addr, abs_timeout_addr
+    );
+
+    let timeout = read_mqueue_timeout(abs_timeout_addr, ctx)?;
+
+    let (bytes, priority) = access_mqueue_with(mqdes, ctx, |file, queue| {
+        if !file.access_mode().is_readable() {
+            return_errno_with_message!(Errno::EBADF, "the message queue is not opened for reading");
+        }
+
+        let is_nonblocking = file.status_flags().contains(StatusFlags::O_NONBLOCK);
+        queue
+            .receive(msg_len, is_nonblocking, timeout)
+            .map_err(map_mqueue_wait_error)
+    })?;
+
+    let user_space = ctx.user_space();
+    user_space.write_bytes(msg_ptr, &mut VmReader::from(bytes.as_slice()))?;
+    if msg_prio_addr != 0 {
+        user_space.write_val(msg_prio_addr, &priority)?;
+    }
+
+    Ok(SyscallReturn::Return(bytes.len() as _))
+}
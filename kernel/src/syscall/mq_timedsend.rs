@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{mq_open::access_mqueue_with, SyscallReturn};
+use crate::{
+    fs::{file_table::FileDesc, mqueue::MQ_PRIO_MAX, utils::StatusFlags},
+    prelude::*,
+    time::{clocks::RealTimeClock, timer::Timeout, timespec_t, wait::ManagedTimeout},
+};
+
+pub fn sys_mq_timedsend(
+    mqdes: FileDesc,
+    msg_ptr: Vaddr,
+    msg_len: usize,
+    msg_prio: u32,
+    abs_timeout_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "mqdes = {}, msg_ptr = {:#x}, msg_len = {}, msg_prio = {}, timeout_addr = {:#x}",
+        mqdes, msg_ptr, msg_len, msg_prio, abs_timeout_addr
+    );
+
+    if msg_prio >= MQ_PRIO_MAX {
+        return_errno_with_message!(Errno::EINVAL, "the message priority is too large");
+    }
+    let timeout = read_mqueue_timeout(abs_timeout_addr, ctx)?;
+
+    access_mqueue_with(mqdes, ctx, |file, queue| {
+        if !file.access_mode().is_writable() {
+            return_errno_with_message!(Errno::EBADF, "the message queue is not opened for writing");
+        }
+        if msg_len > queue.max_msg_size() {
+            return_errno_with_message!(Errno::EMSGSIZE, "the message is too long");
+        }
+
+        let mut bytes = vec![0u8; msg_len];
+        ctx.user_space()
+            .read_bytes(msg_ptr, &mut VmWriter::from(bytes.as_mut_slice()))?;
+
+        let is_nonblocking = file.status_flags().contains(StatusFlags::O_NONBLOCK);
+        queue
+            .send(bytes, msg_prio, is_nonblocking, timeout, ctx)
+            .map_err(map_mqueue_wait_error)
+    })?;
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Reads the absolute timeout of `mq_timedsend` or `mq_timedreceive`, which is based on
+/// `CLOCK_REALTIME`.
+pub(super) fn read_mqueue_timeout(
+    abs_timeout_addr: Vaddr,
+    ctx: &Context,
+) -> Result<Option<ManagedTimeout<'static>>> {
+    if abs_timeout_addr == 0 {
+        return Ok(None);
+    }
+
+    let timespec = ctx.user_space().read_val::<timespec_t>(abs_timeout_addr)?;
+    let timeout = Duration::try_from(timespec)?;
+
+    Ok(Some(ManagedTimeout::new_with_manager(
+        Timeout::When(timeout),
+        RealTimeClock::timer_manager(),
+    )))
+}
+
+/// Maps the errors of waiting for a message queue to the errors reported to user space.
+pub(super) fn map_mqueue_wait_error(err: Error) -> Error {
+    match err.error() {
+        Errno::ETIME => Error::with_message(Errno::ETIMEDOUT, "the timeout expires"),
+        Errno::EINTR => Error::new(Errno::ERESTARTSYS),
+        _ => err,
+    }
+}
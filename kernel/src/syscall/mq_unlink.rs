@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{mq_open::check_mqueue_name, SyscallReturn};
+use crate::{fs::utils::Permission, prelude::*, syscall::constants::MAX_FILENAME_LEN};
+
+pub fn sys_mq_unlink(name_addr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let name = ctx.user_space().read_cstring(name_addr, MAX_FILENAME_LEN)?;
+    debug!("name = {:?}", name);
+
+    let name = name.to_string_lossy();
+    check_mqueue_name(&name)?;
+
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let mqueue_root = ns_proxy.ipc_ns().mqueue_root();
+    mqueue_root
+        .inode()
+        .check_permission(Permission::MAY_WRITE | Permission::MAY_EXEC)?;
+    mqueue_root.unlink(&name)?;
+
+    Ok(SyscallReturn::Return(0))
+}
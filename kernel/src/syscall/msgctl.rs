@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::msg::{MsgControlCmd, MsqidDs},
+    prelude::*,
+};
+
+pub fn sys_msgctl(msqid: i32, cmd: i32, buf: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    // The flag that indicates the new version of the structures, which is always used.
+    const IPC_64: i32 = 0x100;
+
+    if msqid < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the message queue ID is invalid");
+    }
+
+    let cmd = MsgControlCmd::try_from(cmd & !IPC_64)?;
+    debug!(
+        "[sys_msgctl] msqid = {}, cmd = {:?}, buf = 0x{:x}",
+        msqid, cmd, buf
+    );
+
+    let credentials = ctx.posix_thread.credentials();
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let msg_queues = ns_proxy.ipc_ns().msg_queues();
+
+    match cmd {
+        MsgControlCmd::IPC_RMID => {
+            msg_queues.remove_if(msqid, |queue| queue.check_owner(&credentials))?;
+        }
+        MsgControlCmd::IPC_SET => {
+            let msqid_ds: MsqidDs = ctx.user_space().read_val(buf)?;
+            msg_queues.get(msqid)?.set(&msqid_ds, &credentials)?;
+        }
+        MsgControlCmd::IPC_STAT => {
+            let queue = msg_queues.get(msqid)?;
+            queue.check_access(&credentials, 0o4)?;
+            ctx.user_space().write_val(buf, &queue.msqid_ds())?;
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::size_of;
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{
+        msg::{MsgFlags, MsgSelector},
+        IpcFlags,
+    },
+    prelude::*,
+};
+
+pub fn sys_msgrcv(
+    msqid: i32,
+    msgp: Vaddr,
+    msgsz: usize,
+    msgtyp: i64,
+    msgflg: i32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let ipc_flags = IpcFlags::from_bits_truncate(msgflg as u32);
+    let msg_flags = MsgFlags::from_bits_truncate(msgflg as u32);
+    debug!(
+        "[sys_msgrcv] msqid = {}, msgp = 0x{:x}, msgsz = {}, msgtyp = {}, flags = {:?} {:?}",
+        msqid, msgp, msgsz, msgtyp, ipc_flags, msg_flags
+    );
+
+    if msqid < 0 || (msgsz as isize) < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the message queue ID or size is invalid");
+    }
+    if msg_flags.contains(MsgFlags::MSG_COPY) {
+        // TODO: Support `MSG_COPY`, which is only used for checkpoint/restore in Linux.
+        return_errno_with_message!(Errno::ENOSYS, "MSG_COPY is not supported");
+    }
+
+    let credentials = ctx.posix_thread.credentials();
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let queue = ns_proxy.ipc_ns().msg_queues().get(msqid)?;
+    queue.check_access(&credentials, 0o4)?;
+
+    let (mtype, bytes) = queue.receive(
+        MsgSelector::new(msgtyp, msg_flags),
+        msgsz,
+        msg_flags,
+        ipc_flags.contains(IpcFlags::IPC_NOWAIT),
+        ctx.process.pid(),
+    )?;
+
+    // The message buffer starts with a `long` type, which is followed by the message text.
+    let user_space = ctx.user_space();
+    user_space.write_val(msgp, &mtype)?;
+    user_space.write_bytes(
+        msgp + size_of::<i64>(),
+        &mut VmReader::from(bytes.as_slice()),
+    )?;
+
+    Ok(SyscallReturn::Return(bytes.len() as _))
+}
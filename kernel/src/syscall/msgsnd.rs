@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::mem::size_of;
+
+use super::SyscallReturn;
+use crate::{
+    ipc::{msg::MSGMAX, IpcFlags},
+    prelude::*,
+};
+
+pub fn sys_msgsnd(
+    msqid: i32,
+    msgp: Vaddr,
+    msgsz: usize,
+    msgflg: i32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = IpcFlags::from_bits_truncate(msgflg as u32);
+    debug!(
+        "[sys_msgsnd] msqid = {}, msgp = 0x{:x}, msgsz = {}, flags = {:?}",
+        msqid, msgp, msgsz, flags
+    );
+
+    if msqid < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the message queue ID is invalid");
+    }
+    if msgsz > MSGMAX {
+        return_errno_with_message!(Errno::EINVAL, "the message is too long");
+    }
+
+    // The message buffer starts with a `long` type, which is followed by the message text.
+    let user_space = ctx.user_space();
+    let mtype: i64 = user_space.read_val(msgp)?;
+    if mtype <= 0 {
+        return_errno_with_message!(Errno::EINVAL, "the message type must be positive");
+    }
+    let mut bytes = vec![0u8; msgsz];
+    user_space.read_bytes(
+        msgp + size_of::<i64>(),
+        &mut VmWriter::from(bytes.as_mut_slice()),
+    )?;
+
+    let credentials = ctx.posix_thread.credentials();
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let queue = ns_proxy.ipc_ns().msg_queues().get(msqid)?;
+    queue.check_access(&credentials, 0o2)?;
+
+    queue.send(
+        mtype,
+        bytes,
+        flags.contains(IpcFlags::IPC_NOWAIT),
+        ctx.process.pid(),
+    )?;
+
+    Ok(SyscallReturn::Return(0))
+}
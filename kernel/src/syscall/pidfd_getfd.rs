@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::file_table::{get_file_fast, FdFlags, FileDesc},
+    prelude::*,
+    process::{posix_thread::AsPosixThread, PidFile},
+    syscall::SyscallReturn,
+};
+
+pub fn sys_pidfd_getfd(
+    pidfd: FileDesc,
+    target_fd: FileDesc,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "pidfd = {}, target_fd = {}, flags = {}",
+        pidfd, target_fd, flags
+    );
+
+    // "The flags argument is reserved for future use. Currently, it must be specified as 0."
+    // Reference: <https://man7.org/linux/man-pages/man2/pidfd_getfd.2.html>.
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "invalid flags");
+    }
+
+    let file = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        get_file_fast!(&mut file_table, pidfd).into_owned()
+    };
+    let pid_file = Arc::downcast::<PidFile>(file)
+        .map_err(|_| Error::with_message(Errno::EBADF, "the file is not a PID file"))?;
+
+    let target_process = pid_file.process();
+    if target_process.status().is_zombie() {
+        return_errno_with_message!(Errno::ESRCH, "the target process has exited");
+    }
+    let target_thread = target_process.main_thread();
+    let target = target_thread.as_posix_thread().unwrap();
+
+    // Like Linux, duplicating a file from the target process requires the same permission as
+    // attaching to it with `ptrace`.
+    target.check_ptrace_access(ctx.posix_thread)?;
+
+    let target_file = {
+        let file_table = target.file_table().lock();
+        let file_table = file_table
+            .as_ref()
+            .ok_or_else(|| Error::with_message(Errno::ESRCH, "the target process has exited"))?;
+        file_table.read().get_file(target_fd)?.clone()
+    };
+
+    let fd = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let mut file_table_locked = file_table.unwrap().write();
+        // "The close-on-exec flag (FD_CLOEXEC; see fcntl(2)) is set on the file descriptor
+        // returned by pidfd_getfd()."
+        // Reference: <https://man7.org/linux/man-pages/man2/pidfd_getfd.2.html>.
+        file_table_locked.insert(target_file, FdFlags::CLOEXEC)
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
@@ -3,7 +3,7 @@
 use crate::{
     fs::{file_table::FdFlags, utils::StatusFlags},
     prelude::*,
-    process::{process_table, Pid, PidFile},
+    process::{Pid, PidFile},
     syscall::SyscallReturn,
 };
 
@@ -19,7 +19,10 @@ pub fn sys_pidfd_open(pid: Pid, flags: u32, ctx: &Context) -> Result<SyscallRetu
         return_errno_with_message!(Errno::EINVAL, "all negative PIDs are not valid");
     }
 
-    let process = process_table::get_process(pid)
+    let process = ctx
+        .posix_thread
+        .pid_ns()
+        .get_process(pid)
         .ok_or_else(|| Error::with_message(Errno::ESRCH, "the process does not exist"))?;
 
     let pid_fd = {
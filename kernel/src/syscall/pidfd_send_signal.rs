@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    fs::file_table::{get_file_fast, FileDesc},
+    prelude::*,
+    process::{
+        kill_pidfd,
+        signal::{
+            c_types::siginfo_t,
+            constants::SI_TKILL,
+            sig_num::SigNum,
+            signals::user::{UserSignal, UserSignalKind},
+        },
+        PidFile,
+    },
+    syscall::SyscallReturn,
+};
+
+pub fn sys_pidfd_send_signal(
+    pidfd: FileDesc,
+    sig_num: u64,
+    info_addr: Vaddr,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let sig_num = if sig_num == 0 {
+        None
+    } else {
+        let sig_num = u8::try_from(sig_num)
+            .map_err(|_| Error::with_message(Errno::EINVAL, "invalid signal number"))?;
+        Some(SigNum::try_from(sig_num)?)
+    };
+    debug!(
+        "pidfd = {}, sig_num = {:?}, info_addr = 0x{:x}, flags = {}",
+        pidfd, sig_num, info_addr, flags
+    );
+
+    // FIXME: Support the `PIDFD_SIGNAL_*` flags added in Linux 6.9.
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "invalid flags");
+    }
+
+    let file = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        get_file_fast!(&mut file_table, pidfd).into_owned()
+    };
+    let pid_file = Arc::downcast::<PidFile>(file)
+        .map_err(|_| Error::with_message(Errno::EBADF, "the file is not a PID file"))?;
+
+    let target_process = pid_file.process();
+    if ctx.posix_thread.pid_ns().to_local(target_process.pid()) == 0 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the target process is not in the PID namespace of the current thread"
+        );
+    }
+
+    let kind = if info_addr == 0 {
+        UserSignalKind::Kill
+    } else {
+        let info: siginfo_t = ctx.user_space().read_val(info_addr)?;
+        let sig_num_raw = sig_num.map_or(0, |sig_num| sig_num.as_u8() as i32);
+        if info.si_signo != sig_num_raw {
+            return_errno_with_message!(Errno::EINVAL, "the signal numbers do not match");
+        }
+        // Like `rt_sigqueueinfo`, a process cannot pretend to be the kernel or `tgkill` when
+        // sending signals to other processes.
+        if (info.si_code >= 0 || info.si_code == SI_TKILL)
+            && !core::ptr::eq(target_process.as_ref(), ctx.process)
+        {
+            return_errno_with_message!(Errno::EPERM, "the signal code is not allowed");
+        }
+        // Like `rt_sigqueueinfo`, the signal information is delivered as is. Only the PID of
+        // the sender may be cleared, which happens when the signal is sent.
+        UserSignalKind::Sigqueue(info)
+    };
+
+    let signal = sig_num.map(|sig_num| {
+        // The PID will be translated into the PID namespace of the target process when the
+        // signal is sent.
+        let pid = ctx.process.pid();
+        let uid = ctx.posix_thread.credentials().ruid();
+        UserSignal::new(sig_num, kind, pid, uid)
+    });
+    kill_pidfd(&pid_file, signal, ctx)?;
+
+    Ok(SyscallReturn::Return(0))
+}
@@ -3,7 +3,12 @@
 use super::SyscallReturn;
 use crate::{
     prelude::*,
-    process::{posix_thread::MAX_THREAD_NAME_LEN, signal::sig_num::SigNum},
+    process::{
+        posix_thread::MAX_THREAD_NAME_LEN,
+        seccomp::{set_mode_filter, set_mode_strict, SeccompFilterFlags},
+        signal::sig_num::SigNum,
+        Dumpable,
+    },
 };
 
 pub fn sys_prctl(
@@ -31,15 +36,14 @@ pub fn sys_prctl(
             ctx.user_space().write_val(write_to_addr, &write_val)?;
         }
         PrctlCmd::PR_GET_DUMPABLE => {
-            // TODO: when coredump is supported, return the actual value
-            return Ok(SyscallReturn::Return(Dumpable::Disable as _));
+            return Ok(SyscallReturn::Return(ctx.process.dumpable() as _));
         }
         PrctlCmd::PR_SET_DUMPABLE(dumpable) => {
             if dumpable != Dumpable::Disable && dumpable != Dumpable::User {
                 return_errno!(Errno::EINVAL)
             }
 
-            // TODO: implement coredump
+            ctx.process.set_dumpable(dumpable);
         }
         PrctlCmd::PR_GET_KEEPCAPS => {
             let keep_cap = {
@@ -93,6 +97,24 @@ pub fn sys_prctl(
             ctx.user_space()
                 .write_val(write_addr, &(process.is_child_subreaper() as u32))?;
         }
+        PrctlCmd::PR_GET_SECCOMP => {
+            let mode = ctx.posix_thread.seccomp_mode();
+            return Ok(SyscallReturn::Return(mode.as_u32() as _));
+        }
+        PrctlCmd::PR_SET_SECCOMP(mode, fprog_addr) => match mode {
+            SECCOMP_MODE_STRICT => set_mode_strict(ctx)?,
+            SECCOMP_MODE_FILTER => {
+                set_mode_filter(fprog_addr, SeccompFilterFlags::empty(), ctx)?;
+            }
+            _ => return_errno_with_message!(Errno::EINVAL, "the seccomp mode is invalid"),
+        },
+        PrctlCmd::PR_SET_NO_NEW_PRIVS => {
+            ctx.posix_thread.set_no_new_privs();
+        }
+        PrctlCmd::PR_GET_NO_NEW_PRIVS => {
+            let no_new_privs = ctx.posix_thread.no_new_privs();
+            return Ok(SyscallReturn::Return(no_new_privs as _));
+        }
         _ => todo!(),
     }
     Ok(SyscallReturn::Return(0))
@@ -106,10 +128,17 @@ const PR_GET_KEEPCAPS: i32 = 7;
 const PR_SET_KEEPCAPS: i32 = 8;
 const PR_SET_NAME: i32 = 15;
 const PR_GET_NAME: i32 = 16;
+const PR_GET_SECCOMP: i32 = 21;
+const PR_SET_SECCOMP: i32 = 22;
 const PR_SET_TIMERSLACK: i32 = 29;
 const PR_GET_TIMERSLACK: i32 = 30;
 const PR_SET_CHILD_SUBREAPER: i32 = 36;
 const PR_GET_CHILD_SUBREAPER: i32 = 37;
+const PR_SET_NO_NEW_PRIVS: i32 = 38;
+const PR_GET_NO_NEW_PRIVS: i32 = 39;
+
+const SECCOMP_MODE_STRICT: u64 = 1;
+const SECCOMP_MODE_FILTER: u64 = 2;
 
 #[expect(non_camel_case_types)]
 #[derive(Debug, Clone, Copy)]
@@ -128,18 +157,14 @@ pub enum PrctlCmd {
     PR_GET_DUMPABLE,
     PR_SET_CHILD_SUBREAPER(bool),
     PR_GET_CHILD_SUBREAPER(Vaddr),
-}
-
-#[repr(u64)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, TryFromInt)]
-pub enum Dumpable {
-    Disable = 0, /* No setuid dumping */
-    User = 1,    /* Dump as user of process */
-    Root = 2,    /* Dump as root */
+    PR_GET_SECCOMP,
+    PR_SET_SECCOMP(u64, Vaddr),
+    PR_SET_NO_NEW_PRIVS,
+    PR_GET_NO_NEW_PRIVS,
 }
 
 impl PrctlCmd {
-    fn from_args(option: i32, arg2: u64, _arg3: u64, _arg4: u64, _arg5: u64) -> Result<PrctlCmd> {
+    fn from_args(option: i32, arg2: u64, arg3: u64, arg4: u64, arg5: u64) -> Result<PrctlCmd> {
         match option {
             PR_SET_PDEATHSIG => {
                 let signum = SigNum::try_from(arg2 as u8)?;
@@ -156,6 +181,20 @@ impl PrctlCmd {
             PR_SET_KEEPCAPS => Ok(PrctlCmd::PR_SET_KEEPCAPS(arg2 as _)),
             PR_SET_CHILD_SUBREAPER => Ok(PrctlCmd::PR_SET_CHILD_SUBREAPER(arg2 > 0)),
             PR_GET_CHILD_SUBREAPER => Ok(PrctlCmd::PR_GET_CHILD_SUBREAPER(arg2 as _)),
+            PR_GET_SECCOMP => Ok(PrctlCmd::PR_GET_SECCOMP),
+            PR_SET_SECCOMP => Ok(PrctlCmd::PR_SET_SECCOMP(arg2, arg3 as _)),
+            PR_SET_NO_NEW_PRIVS => {
+                if arg2 != 1 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "no_new_privs can only be set");
+                }
+                Ok(PrctlCmd::PR_SET_NO_NEW_PRIVS)
+            }
+            PR_GET_NO_NEW_PRIVS => {
+                if arg2 != 0 || arg3 != 0 || arg4 != 0 || arg5 != 0 {
+                    return_errno_with_message!(Errno::EINVAL, "the arguments must be zero");
+                }
+                Ok(PrctlCmd::PR_GET_NO_NEW_PRIVS)
+            }
             _ => {
                 debug!("prctl cmd number: {}", option);
                 return_errno_with_message!(Errno::EINVAL, "unsupported prctl command");
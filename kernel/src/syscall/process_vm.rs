@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::ops::Range;
+
+use aster_rights::Full;
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{posix_thread::AsPosixThread, Process},
+    thread::Tid,
+    util::{read_user_io_vecs, MultiRead, MultiWrite, VmReaderArray, VmWriterArray},
+    vm::vmar::Vmar,
+};
+
+pub fn sys_process_vm_readv(
+    pid: Tid,
+    local_iov_addr: Vaddr,
+    local_iov_count: usize,
+    remote_iov_addr: Vaddr,
+    remote_iov_count: usize,
+    flags: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "pid = {}, local_iov = {:#x}, local_iov_count = {}, remote_iov = {:#x}, \
+         remote_iov_count = {}, flags = {:#x}",
+        pid, local_iov_addr, local_iov_count, remote_iov_addr, remote_iov_count, flags
+    );
+
+    check_args(local_iov_count, remote_iov_count, flags)?;
+
+    let user_space = ctx.user_space();
+    let remote_ranges = read_user_io_vecs(&user_space, remote_iov_addr, remote_iov_count)?;
+    let mut writers =
+        VmWriterArray::from_user_io_vecs(&user_space, local_iov_addr, local_iov_count)?;
+
+    let process = get_target_process(pid, ctx)?;
+    let read_len = transfer(&process, &remote_ranges, &mut writers)?;
+
+    Ok(SyscallReturn::Return(read_len as _))
+}
+
+pub fn sys_process_vm_writev(
+    pid: Tid,
+    local_iov_addr: Vaddr,
+    local_iov_count: usize,
+    remote_iov_addr: Vaddr,
+    remote_iov_count: usize,
+    flags: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    debug!(
+        "pid = {}, local_iov = {:#x}, local_iov_count = {}, remote_iov = {:#x}, \
+         remote_iov_count = {}, flags = {:#x}",
+        pid, local_iov_addr, local_iov_count, remote_iov_addr, remote_iov_count, flags
+    );
+
+    check_args(local_iov_count, remote_iov_count, flags)?;
+
+    let user_space = ctx.user_space();
+    let remote_ranges = read_user_io_vecs(&user_space, remote_iov_addr, remote_iov_count)?;
+    let mut readers =
+        VmReaderArray::from_user_io_vecs(&user_space, local_iov_addr, local_iov_count)?;
+
+    let process = get_target_process(pid, ctx)?;
+    let written_len = transfer(&process, &remote_ranges, &mut readers)?;
+
+    Ok(SyscallReturn::Return(written_len as _))
+}
+
+/// The maximum number of IO vectors.
+const IOV_MAX: usize = 1024;
+
+fn check_args(local_iov_count: usize, remote_iov_count: usize, flags: u64) -> Result<()> {
+    if flags != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the flags must be zero");
+    }
+    if local_iov_count > IOV_MAX || remote_iov_count > IOV_MAX {
+        return_errno_with_message!(Errno::EINVAL, "too many IO vectors");
+    }
+
+    Ok(())
+}
+
+/// Gets the process of the thread, if the current thread is allowed to access its memory.
+fn get_target_process(tid: Tid, ctx: &Context) -> Result<Arc<Process>> {
+    let thread = ctx
+        .posix_thread
+        .pid_ns()
+        .get_thread(tid)
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the thread does not exist"))?;
+    let posix_thread = thread.as_posix_thread().unwrap();
+
+    posix_thread.check_ptrace_access(ctx.posix_thread)?;
+
+    Ok(posix_thread.process())
+}
+
+/// Transfers data between the local IO vectors and the remote memory ranges.
+///
+/// The transfer stops at the first remote page that cannot be accessed. An error is returned
+/// only if no data can be transferred.
+fn transfer(
+    process: &Process,
+    remote_ranges: &[Range<Vaddr>],
+    local: &mut dyn LocalIoVecs,
+) -> Result<usize> {
+    let root_vmar = process.lock_root_vmar();
+    let Some(vmar) = root_vmar.as_ref() else {
+        return_errno_with_message!(Errno::ESRCH, "the process has exited");
+    };
+
+    let mut total_len = 0;
+    for range in remote_ranges.iter() {
+        let len = range.len().min(local.remaining_len());
+        if len == 0 {
+            break;
+        }
+
+        let transferred_len = match local.transfer(vmar, range.start, len) {
+            Ok(transferred_len) => transferred_len,
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        };
+        if transferred_len == 0 && total_len == 0 {
+            return_errno_with_message!(Errno::EFAULT, "the remote memory cannot be accessed");
+        }
+
+        total_len += transferred_len;
+        if transferred_len < len {
+            break;
+        }
+    }
+
+    Ok(total_len)
+}
+
+/// The local IO vectors of `process_vm_readv` or `process_vm_writev`.
+trait LocalIoVecs {
+    /// Returns the number of bytes remaining to transfer.
+    fn remaining_len(&self) -> usize;
+
+    /// Transfers at most `len` bytes between the local IO vectors and the remote memory.
+    fn transfer(&mut self, vmar: &Vmar<Full>, remote_addr: Vaddr, len: usize) -> Result<usize>;
+}
+
+impl LocalIoVecs for VmWriterArray<'_> {
+    fn remaining_len(&self) -> usize {
+        self.sum_lens()
+    }
+
+    fn transfer(&mut self, vmar: &Vmar<Full>, remote_addr: Vaddr, len: usize) -> Result<usize> {
+        vmar.read_remote_to(remote_addr, len, self)
+    }
+}
+
+impl LocalIoVecs for VmReaderArray<'_> {
+    fn remaining_len(&self) -> usize {
+        self.sum_lens()
+    }
+
+    fn transfer(&mut self, vmar: &Vmar<Full>, remote_addr: Vaddr, len: usize) -> Result<usize> {
+        // Unlike `ptrace` and `/proc/[pid]/mem`, the memory protection is respected.
+        vmar.write_remote_from(remote_addr, len, self, false)
+    }
+}
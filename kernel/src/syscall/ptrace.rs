@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use aster_rights::Full;
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{
+        posix_thread::{
+            ptrace_attach, AsPosixThread, PosixThread, PtraceOptions, PtraceResumeMode,
+        },
+        signal::{
+            constants::{SIGKILL, SIGSTOP},
+            sig_num::SigNum,
+            signals::kernel::KernelSignal,
+        },
+    },
+    thread::{Thread, Tid},
+    vm::vmar::Vmar,
+};
+
+pub fn sys_ptrace(
+    request: u32,
+    pid: Tid,
+    addr: Vaddr,
+    data: u64,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let request = PtraceRequest::try_from(request)
+        .map_err(|_| Error::with_message(Errno::EIO, "the ptrace request is not supported"))?;
+    debug!(
+        "request = {:?}, pid = {}, addr = 0x{:x}, data = 0x{:x}",
+        request, pid, addr, data
+    );
+
+    match request {
+        PtraceRequest::TraceMe => do_trace_me(ctx)?,
+        PtraceRequest::Attach => do_attach(pid, PtraceOptions::empty(), false, ctx)?,
+        PtraceRequest::Seize => {
+            if addr != 0 {
+                return_errno_with_message!(Errno::EIO, "the address must be zero");
+            }
+            let options = parse_options(data)?;
+            do_attach(pid, options, true, ctx)?;
+        }
+        _ => {
+            let tracee =
+                ctx.posix_thread.pid_ns().get_thread(pid).ok_or_else(|| {
+                    Error::with_message(Errno::ESRCH, "the thread does not exist")
+                })?;
+            do_traced_request(request, &tracee, addr, data, ctx)?;
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Handles the requests that operate on an existing tracee.
+fn do_traced_request(
+    request: PtraceRequest,
+    tracee: &Arc<Thread>,
+    addr: Vaddr,
+    data: u64,
+    ctx: &Context,
+) -> Result<()> {
+    let posix_thread = tracee.as_posix_thread().unwrap();
+    let tracer = ctx.process;
+
+    match request {
+        PtraceRequest::PeekText | PtraceRequest::PeekData => {
+            posix_thread.with_ptrace_stop(tracer, |_| ())?;
+
+            let mut word = [0u8; size_of::<usize>()];
+            access_tracee_memory(posix_thread, |vmar| vmar.read_remote(addr, &mut word))?;
+            ctx.user_space()
+                .write_val(data as Vaddr, &usize::from_ne_bytes(word))?;
+        }
+        PtraceRequest::PokeText | PtraceRequest::PokeData => {
+            posix_thread.with_ptrace_stop(tracer, |_| ())?;
+
+            let word = (data as usize).to_ne_bytes();
+            access_tracee_memory(posix_thread, |vmar| vmar.write_remote(addr, &word))?;
+        }
+        PtraceRequest::GetRegs => do_get_regs(posix_thread, data as Vaddr, ctx)?,
+        PtraceRequest::SetRegs => do_set_regs(posix_thread, data as Vaddr, ctx)?,
+        PtraceRequest::GetSigInfo => {
+            let siginfo = posix_thread.with_ptrace_stop(tracer, |stop| *stop.siginfo())?;
+            ctx.user_space().write_val(data as Vaddr, &siginfo)?;
+        }
+        PtraceRequest::SetOptions => {
+            let options = parse_options(data)?;
+            posix_thread.set_ptrace_options(tracer, options)?;
+        }
+        PtraceRequest::Cont => {
+            let signal = parse_signal(data)?;
+            posix_thread.ptrace_resume(tracer, PtraceResumeMode::Continue, signal)?;
+        }
+        PtraceRequest::Syscall => {
+            let signal = parse_signal(data)?;
+            posix_thread.ptrace_resume(tracer, PtraceResumeMode::Syscall, signal)?;
+        }
+        PtraceRequest::SingleStep => {
+            if !cfg!(target_arch = "x86_64") {
+                return_errno_with_message!(Errno::EIO, "single-stepping is not supported");
+            }
+            let signal = parse_signal(data)?;
+            posix_thread.ptrace_resume(tracer, PtraceResumeMode::SingleStep, signal)?;
+        }
+        PtraceRequest::Kill => {
+            if !posix_thread.is_traced_by(tracer) {
+                return_errno_with_message!(Errno::ESRCH, "the thread is not traced by the process");
+            }
+            posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGKILL)));
+        }
+        PtraceRequest::Detach => {
+            let signal = parse_signal(data)?;
+            posix_thread.ptrace_detach(tracer, signal)?;
+        }
+        PtraceRequest::TraceMe | PtraceRequest::Attach | PtraceRequest::Seize => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn do_trace_me(ctx: &Context) -> Result<()> {
+    let parent = ctx
+        .process
+        .parent()
+        .lock()
+        .process()
+        .upgrade()
+        .ok_or_else(|| Error::with_message(Errno::EPERM, "the process has no parent"))?;
+
+    ptrace_attach(&current_thread!(), &parent, PtraceOptions::empty(), false)
+}
+
+fn do_attach(pid: Tid, options: PtraceOptions, is_seized: bool, ctx: &Context) -> Result<()> {
+    let tracee = ctx
+        .posix_thread
+        .pid_ns()
+        .get_thread(pid)
+        .ok_or_else(|| Error::with_message(Errno::ESRCH, "the thread does not exist"))?;
+    let posix_thread = tracee.as_posix_thread().unwrap();
+
+    let tracer = current!();
+    if Arc::ptr_eq(&posix_thread.process(), &tracer) {
+        return_errno_with_message!(Errno::EPERM, "a process cannot trace itself");
+    }
+    posix_thread.check_ptrace_access(ctx.posix_thread)?;
+
+    ptrace_attach(&tracee, &tracer, options, is_seized)?;
+
+    if !is_seized {
+        posix_thread.enqueue_signal(Box::new(KernelSignal::new(SIGSTOP)));
+    }
+
+    Ok(())
+}
+
+fn access_tracee_memory<F>(tracee: &PosixThread, f: F) -> Result<()>
+where
+    F: FnOnce(&Vmar<Full>) -> Result<()>,
+{
+    let process = tracee.process();
+    let root_vmar = process.lock_root_vmar();
+    let Some(vmar) = root_vmar.as_ref() else {
+        return_errno_with_message!(Errno::ESRCH, "the process of the tracee has exited");
+    };
+
+    // Linux reports `EIO` if the memory cannot be accessed.
+    f(vmar).map_err(|_| Error::with_message(Errno::EIO, "the memory cannot be accessed"))
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        use crate::arch::cpu::UserRegs;
+
+        fn do_get_regs(tracee: &PosixThread, regs_addr: Vaddr, ctx: &Context) -> Result<()> {
+            let regs =
+                tracee.with_ptrace_stop(ctx.process, |stop| UserRegs::from_ptrace_stop(stop))?;
+            ctx.user_space().write_val(regs_addr, &regs)?;
+            Ok(())
+        }
+
+        fn do_set_regs(tracee: &PosixThread, regs_addr: Vaddr, ctx: &Context) -> Result<()> {
+            let regs: UserRegs = ctx.user_space().read_val(regs_addr)?;
+            tracee.with_ptrace_stop(ctx.process, |stop| regs.write_to_ptrace_stop(stop))
+        }
+    } else {
+        // Other architectures only support `PTRACE_GETREGSET` and `PTRACE_SETREGSET`, which are
+        // not implemented yet.
+
+        fn do_get_regs(_tracee: &PosixThread, _regs_addr: Vaddr, _ctx: &Context) -> Result<()> {
+            return_errno_with_message!(Errno::EIO, "PTRACE_GETREGS is not supported");
+        }
+
+        fn do_set_regs(_tracee: &PosixThread, _regs_addr: Vaddr, _ctx: &Context) -> Result<()> {
+            return_errno_with_message!(Errno::EIO, "PTRACE_SETREGS is not supported");
+        }
+    }
+}
+
+fn parse_options(data: u64) -> Result<PtraceOptions> {
+    let options = u32::try_from(data)
+        .ok()
+        .and_then(PtraceOptions::from_bits)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "the ptrace options are invalid"))?;
+    options.check()?;
+    Ok(options)
+}
+
+/// Parses the signal to deliver when the tracee is resumed.
+fn parse_signal(data: u64) -> Result<Option<SigNum>> {
+    if data == 0 {
+        return Ok(None);
+    }
+
+    u8::try_from(data)
+        .ok()
+        .and_then(|sig_num| SigNum::try_from(sig_num).ok())
+        .map(Some)
+        .ok_or_else(|| Error::with_message(Errno::EIO, "the signal is invalid"))
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+enum PtraceRequest {
+    TraceMe = 0,
+    PeekText = 1,
+    PeekData = 2,
+    PokeText = 4,
+    PokeData = 5,
+    Cont = 7,
+    Kill = 8,
+    SingleStep = 9,
+    GetRegs = 12,
+    SetRegs = 13,
+    Attach = 16,
+    Detach = 17,
+    Syscall = 24,
+    SetOptions = 0x4200,
+    GetSigInfo = 0x4202,
+    Seize = 0x4206,
+}
@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::seccomp::{is_action_available, set_mode_filter, set_mode_strict, SeccompFilterFlags},
+};
+
+pub fn sys_seccomp(op: u32, flags: u32, args: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    let op = SeccompOp::try_from(op)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the seccomp operation is invalid"))?;
+    debug!("op = {:?}, flags = {:#x}, args = {:#x}", op, flags, args);
+
+    match op {
+        SeccompOp::SetModeStrict => {
+            if flags != 0 || args != 0 {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the flags and the arguments of the strict mode must be zero"
+                );
+            }
+            set_mode_strict(ctx)?;
+        }
+        SeccompOp::SetModeFilter => {
+            let flags = SeccompFilterFlags::from_bits(flags).ok_or_else(|| {
+                Error::with_message(Errno::EINVAL, "the seccomp filter flags are invalid")
+            })?;
+            // With `SECCOMP_FILTER_FLAG_TSYNC`, the TID of the thread that cannot be synchronized
+            // is returned on failure.
+            if let Some(tid) = set_mode_filter(args, flags, ctx)? {
+                return Ok(SyscallReturn::Return(tid as _));
+            }
+        }
+        SeccompOp::GetActionAvail => {
+            if flags != 0 {
+                return_errno_with_message!(Errno::EINVAL, "the flags must be zero");
+            }
+            let action = ctx.user_space().read_val::<u32>(args)?;
+            if !is_action_available(action) {
+                return_errno_with_message!(Errno::EOPNOTSUPP, "the action is not supported");
+            }
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, TryFromInt)]
+enum SeccompOp {
+    SetModeStrict = 0,
+    SetModeFilter = 1,
+    GetActionAvail = 2,
+}
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Gid},
+};
 
 pub fn sys_setfsgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("gid = {}", gid);
@@ -12,10 +15,7 @@ pub fn sys_setfsgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
         Some(Gid::new(gid as u32))
     };
 
-    let old_fsgid = {
-        let credentials = ctx.posix_thread.credentials_mut();
-        credentials.set_fsgid(fsgid)?
-    };
+    let old_fsgid = change_credentials(ctx, |credentials| credentials.set_fsgid(fsgid))?;
 
     Ok(SyscallReturn::Return(
         <Gid as Into<u32>>::into(old_fsgid) as _
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Uid},
+};
 
 pub fn sys_setfsuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("uid = {}", uid);
@@ -12,10 +15,7 @@ pub fn sys_setfsuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
         Some(Uid::new(uid as u32))
     };
 
-    let old_fsuid = {
-        let credentials = ctx.posix_thread.credentials_mut();
-        credentials.set_fsuid(fsuid)?
-    };
+    let old_fsuid = change_credentials(ctx, |credentials| credentials.set_fsuid(fsuid))?;
 
     Ok(SyscallReturn::Return(
         <Uid as Into<u32>>::into(old_fsuid) as _
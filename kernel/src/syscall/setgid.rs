@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Gid},
+};
 
 pub fn sys_setgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("gid = {}", gid);
@@ -12,8 +15,10 @@ pub fn sys_setgid(gid: i32, ctx: &Context) -> Result<SyscallReturn> {
 
     let gid = Gid::new(gid as u32);
 
-    let credentials = ctx.posix_thread.credentials_mut();
-    credentials.set_gid(gid);
+    change_credentials(ctx, |credentials| {
+        credentials.set_gid(gid);
+        Ok(())
+    })?;
 
     Ok(SyscallReturn::Return(0))
 }
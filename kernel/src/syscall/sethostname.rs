@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{credentials::capabilities::CapSet, namespace::UtsNamespace},
+};
+
+pub fn sys_sethostname(addr: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("addr = 0x{:x}, len = {}", addr, len);
+
+    set_uts_name(addr, len, UtsNamespace::set_hostname, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+pub fn sys_setdomainname(addr: Vaddr, len: usize, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("addr = 0x{:x}, len = {}", addr, len);
+
+    set_uts_name(addr, len, UtsNamespace::set_domainname, ctx)?;
+    Ok(SyscallReturn::Return(0))
+}
+
+/// The maximum length of the host name and the domain name.
+const MAX_NAME_LEN: usize = 64;
+
+fn set_uts_name(
+    addr: Vaddr,
+    len: usize,
+    set_name: fn(&UtsNamespace, &[u8]) -> Result<()>,
+    ctx: &Context,
+) -> Result<()> {
+    let credentials = ctx.posix_thread.credentials();
+    if !credentials.euid().is_root() && !credentials.effective_capset().contains(CapSet::SYS_ADMIN)
+    {
+        return_errno_with_message!(Errno::EPERM, "changing UTS names requires CAP_SYS_ADMIN");
+    }
+
+    if len > MAX_NAME_LEN {
+        return_errno_with_message!(Errno::EINVAL, "the name is too long");
+    }
+
+    let mut name = vec![0u8; len];
+    ctx.user_space()
+        .read_bytes(addr, &mut VmWriter::from(name.as_mut_slice()))?;
+
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    set_name(ns_proxy.uts_ns(), &name)
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::file_table::{get_file_fast, FileDesc},
+    prelude::*,
+    process::{
+        namespace::{Namespace, NsFile, NsProxy},
+        posix_thread::{AsPosixThread, PosixThread},
+        CloneFlags, PidFile,
+    },
+};
+
+pub fn sys_setns(fd: FileDesc, nstype: u32, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("fd = {}, nstype = 0x{:x}", fd, nstype);
+
+    let file = {
+        let mut file_table = ctx.thread_local.borrow_file_table_mut();
+        get_file_fast!(&mut file_table, fd).into_owned()
+    };
+
+    let ns_file = file
+        .as_inode_or_err()
+        .ok()
+        .and_then(|inode_handle| inode_handle.file_io())
+        .and_then(|file_io| file_io.downcast_ref::<NsFile>());
+    let namespaces = if let Some(ns_file) = ns_file {
+        // "nstype == 0: Allow any type of namespace to be joined."
+        let ns = ns_file.ns();
+        if nstype != 0 && nstype != ns.clone_flag().bits() {
+            return_errno_with_message!(
+                Errno::EINVAL,
+                "the namespace type does not match the namespace file"
+            );
+        }
+        vec![ns.clone()]
+    } else if let Ok(pid_file) = Arc::downcast::<PidFile>(file) {
+        let flags = CloneFlags::from_bits(nstype)
+            .filter(|flags| !flags.is_empty() && NsProxy::SUPPORTED_NS_FLAGS.contains(*flags))
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the namespace types are invalid"))?;
+
+        let target_process = pid_file.process();
+        if target_process.status().is_zombie() {
+            return_errno_with_message!(Errno::ESRCH, "the target process has exited");
+        }
+        let target_thread = target_process.main_thread();
+        namespaces_of(target_thread.as_posix_thread().unwrap(), flags)
+    } else {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the file is not a PID file or a namespace file"
+        );
+    };
+
+    let Context {
+        thread_local,
+        posix_thread,
+        ..
+    } = ctx;
+
+    let fs = thread_local.borrow_fs();
+    let enters_mnt_ns = namespaces.iter().any(|ns| matches!(ns, Namespace::Mnt(_)));
+    if enters_mnt_ns && Arc::strong_count(&*fs) > 1 {
+        return_errno_with_message!(
+            Errno::EINVAL,
+            "the file system information is shared with other threads"
+        );
+    }
+
+    let ns_proxy = posix_thread.ns_proxy();
+    let new_ns_proxy = ns_proxy.new_enter(&namespaces, &fs, ctx)?;
+    posix_thread.set_ns_proxy(new_ns_proxy);
+
+    Ok(SyscallReturn::Return(0))
+}
+
+/// Returns the namespaces of the target thread for the `CLONE_NEW*` flags in `flags`.
+fn namespaces_of(target: &PosixThread, flags: CloneFlags) -> Vec<Namespace> {
+    let ns_proxy = target.ns_proxy();
+
+    let mut namespaces = Vec::new();
+    if flags.contains(CloneFlags::CLONE_NEWNS) {
+        namespaces.push(Namespace::Mnt(ns_proxy.mnt_ns().clone()));
+    }
+    if flags.contains(CloneFlags::CLONE_NEWUTS) {
+        namespaces.push(Namespace::Uts(ns_proxy.uts_ns().clone()));
+    }
+    if flags.contains(CloneFlags::CLONE_NEWIPC) {
+        namespaces.push(Namespace::Ipc(ns_proxy.ipc_ns().clone()));
+    }
+    if flags.contains(CloneFlags::CLONE_NEWPID) {
+        namespaces.push(Namespace::Pid(target.pid_ns().clone()));
+    }
+    namespaces
+}
@@ -16,11 +16,26 @@ pub fn sys_setpgid(pid: Pid, pgid: Pgid, ctx: &Context) -> Result<SyscallReturn>
         return_errno_with_message!(Errno::EINVAL, "negative PIDs or PGIDs are not valid");
     }
 
+    // The IDs are in the PID namespace of the current thread.
+    let pid_ns = ctx.posix_thread.pid_ns();
+
     // "If `pid` is zero, then the process ID of the calling process is used."
-    let pid = if pid == 0 { current.pid() } else { pid };
+    let pid = if pid == 0 {
+        current.pid()
+    } else {
+        pid_ns.to_global(pid).ok_or_else(|| {
+            Error::with_message(Errno::ESRCH, "the process to set the PGID does not exist")
+        })?
+    };
     // "If `pgid` is zero, then the PGID of the process specified by `pid` is made the same as its
     // process ID."
-    let pgid = if pgid == 0 { pid } else { pgid };
+    let pgid = if pgid == 0 {
+        pid
+    } else {
+        pid_ns.to_global(pgid).ok_or_else(|| {
+            Error::with_message(Errno::EPERM, "the new process group does not exist")
+        })?
+    };
 
     debug!("pid = {}, pgid = {}", pid, pgid);
 
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Gid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Gid},
+};
 
 pub fn sys_setregid(rgid: i32, egid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("rgid = {}, egid = {}", rgid, egid);
@@ -18,8 +21,7 @@ pub fn sys_setregid(rgid: i32, egid: i32, ctx: &Context) -> Result<SyscallReturn
         None
     };
 
-    let credentials = ctx.posix_thread.credentials_mut();
-    credentials.set_regid(rgid, egid)?;
+    change_credentials(ctx, |credentials| credentials.set_regid(rgid, egid))?;
 
     Ok(SyscallReturn::Return(0))
 }
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Uid},
+};
 
 pub fn sys_setresuid(ruid: i32, euid: i32, suid: i32, ctx: &Context) -> Result<SyscallReturn> {
     let ruid = if ruid > 0 {
@@ -24,9 +27,7 @@ pub fn sys_setresuid(ruid: i32, euid: i32, suid: i32, ctx: &Context) -> Result<S
 
     debug!("ruid = {:?}, euid = {:?}, suid = {:?}", ruid, euid, suid);
 
-    let credentials = ctx.posix_thread.credentials_mut();
-
-    credentials.set_resuid(ruid, euid, suid)?;
+    change_credentials(ctx, |credentials| credentials.set_resuid(ruid, euid, suid))?;
 
     Ok(SyscallReturn::Return(0))
 }
@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Uid},
+};
 
 pub fn sys_setreuid(ruid: i32, euid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("ruid = {}, euid = {}", ruid, euid);
@@ -18,8 +21,7 @@ pub fn sys_setreuid(ruid: i32, euid: i32, ctx: &Context) -> Result<SyscallReturn
         None
     };
 
-    let credentials = ctx.posix_thread.credentials_mut();
-    credentials.set_reuid(ruid, euid)?;
+    change_credentials(ctx, |credentials| credentials.set_reuid(ruid, euid))?;
 
     Ok(SyscallReturn::Return(0))
 }
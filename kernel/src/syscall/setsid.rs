@@ -3,8 +3,10 @@
 use super::SyscallReturn;
 use crate::prelude::*;
 
-pub fn sys_setsid(_ctx: &Context) -> Result<SyscallReturn> {
+pub fn sys_setsid(ctx: &Context) -> Result<SyscallReturn> {
     let sid = current!().to_new_session()?;
 
+    // The session ID is in the PID namespace of the current thread.
+    let sid = ctx.posix_thread.pid_ns().to_local(sid);
     Ok(SyscallReturn::Return(sid as _))
 }
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::time::Duration;
+
+use super::{clock_settime::set_realtime, SyscallReturn};
+use crate::{prelude::*, process::credentials::capabilities::CapSet, time::timeval_t};
+
+/// The `timezone` struct in Linux.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod)]
+struct timezone_t {
+    minuteswest: i32,
+    dsttime: i32,
+}
+
+pub fn sys_settimeofday(
+    timeval_addr: Vaddr,
+    timezone_addr: Vaddr,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let user_space = ctx.user_space();
+
+    let time = if timeval_addr != 0 {
+        let timeval = user_space.read_val::<timeval_t>(timeval_addr)?;
+        debug!("timeval = {:?}", timeval);
+        if timeval.usec >= 1_000_000 {
+            return_errno_with_message!(Errno::EINVAL, "the microseconds are not normalized");
+        }
+        Some(Duration::try_from(timeval)?)
+    } else {
+        None
+    };
+
+    // The use of the timezone structure is obsolete, so it is validated but ignored.
+    if timezone_addr != 0 {
+        let timezone = user_space.read_val::<timezone_t>(timezone_addr)?;
+        debug!("timezone = {:?}", timezone);
+        if !(-15 * 60..=15 * 60).contains(&timezone.minuteswest) {
+            return_errno_with_message!(Errno::EINVAL, "the timezone is out of range");
+        }
+
+        let credentials = ctx.posix_thread.credentials();
+        if !credentials.euid().is_root()
+            && !credentials.effective_capset().contains(CapSet::SYS_TIME)
+        {
+            return_errno_with_message!(Errno::EPERM, "setting the timezone is not allowed");
+        }
+    }
+
+    if let Some(time) = time {
+        set_realtime(time, ctx)?;
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
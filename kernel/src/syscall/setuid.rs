@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use super::SyscallReturn;
-use crate::{prelude::*, process::Uid};
+use crate::{
+    prelude::*,
+    process::{change_credentials, Uid},
+};
 
 pub fn sys_setuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
     debug!("uid = {}", uid);
@@ -12,8 +15,10 @@ pub fn sys_setuid(uid: i32, ctx: &Context) -> Result<SyscallReturn> {
 
     let uid = Uid::new(uid as u32);
 
-    let credentials = ctx.posix_thread.credentials_mut();
-    credentials.set_uid(uid);
+    change_credentials(ctx, |credentials| {
+        credentials.set_uid(uid);
+        Ok(())
+    })?;
 
     Ok(SyscallReturn::Return(0))
 }
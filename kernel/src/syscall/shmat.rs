@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::{
+    ipc::shm::{ShmAttachment, ShmFlags, SHMLBA},
+    prelude::*,
+    vm::{perms::VmPerms, vmar::is_userspace_vaddr},
+};
+
+pub fn sys_shmat(shmid: i32, shmaddr: Vaddr, shmflg: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = ShmFlags::from_bits_truncate(shmflg as u32);
+    debug!(
+        "[sys_shmat] shmid = {}, shmaddr = 0x{:x}, flags = {:?}",
+        shmid, shmaddr, flags
+    );
+
+    if shmid < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the segment ID is invalid");
+    }
+
+    let addr = if shmaddr % SHMLBA == 0 {
+        shmaddr
+    } else if flags.contains(ShmFlags::SHM_RND) {
+        shmaddr.align_down(SHMLBA)
+    } else {
+        return_errno_with_message!(Errno::EINVAL, "the attach address is not aligned");
+    };
+    if addr == 0 && flags.contains(ShmFlags::SHM_REMAP) {
+        return_errno_with_message!(Errno::EINVAL, "SHM_REMAP requires an attach address");
+    }
+
+    let (vm_perms, access_mode) = {
+        let (mut vm_perms, mut access_mode) = if flags.contains(ShmFlags::SHM_RDONLY) {
+            (VmPerms::READ, 0o4)
+        } else {
+            (VmPerms::READ | VmPerms::WRITE, 0o6)
+        };
+        if flags.contains(ShmFlags::SHM_EXEC) {
+            vm_perms |= VmPerms::EXEC;
+            access_mode |= 0o1;
+        }
+        (vm_perms, access_mode)
+    };
+
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let ipc_ns = ns_proxy.ipc_ns();
+    let segment = ipc_ns.shm_segments().get(shmid)?;
+    segment.check_access(&ctx.posix_thread.credentials(), access_mode)?;
+
+    let size = segment.size().align_up(PAGE_SIZE);
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+
+    let mut options = root_vmar
+        .new_map(size, vm_perms)?
+        .vmo(segment.vmo().dup()?)
+        .is_shared(true)
+        .shm_attachment(ShmAttachment::new(segment.clone(), ipc_ns.clone()));
+    if addr != 0 {
+        let end = addr
+            .checked_add(size)
+            .filter(|end| is_userspace_vaddr(addr) && is_userspace_vaddr(end - 1))
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the attach range is invalid"))?;
+        if !flags.contains(ShmFlags::SHM_REMAP)
+            && root_vmar.query(addr..end).iter().next().is_some()
+        {
+            return_errno_with_message!(Errno::EINVAL, "the attach range is already mapped");
+        }
+        options = options.offset(addr).can_overwrite(true);
+    }
+    let addr = options.build()?;
+
+    segment.on_attach(ctx.process.pid());
+
+    Ok(SyscallReturn::Return(addr as isize))
+}
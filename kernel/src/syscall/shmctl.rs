@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    ipc::shm::{ShmControlCmd, ShmidDs},
+    prelude::*,
+};
+
+pub fn sys_shmctl(shmid: i32, cmd: i32, buf: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    // The flag that indicates the new version of the structures, which is always used.
+    const IPC_64: i32 = 0x100;
+
+    if shmid < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the segment ID is invalid");
+    }
+
+    let cmd = ShmControlCmd::try_from(cmd & !IPC_64)?;
+    debug!(
+        "[sys_shmctl] shmid = {}, cmd = {:?}, buf = 0x{:x}",
+        shmid, cmd, buf
+    );
+
+    let credentials = ctx.posix_thread.credentials();
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let shm_segments = ns_proxy.ipc_ns().shm_segments();
+
+    match cmd {
+        ShmControlCmd::IPC_RMID => {
+            shm_segments.remove_if(shmid, |segment| segment.check_owner(&credentials))?;
+        }
+        ShmControlCmd::IPC_SET => {
+            let shmid_ds: ShmidDs = ctx.user_space().read_val(buf)?;
+            shm_segments.get(shmid)?.set(&shmid_ds, &credentials)?;
+        }
+        ShmControlCmd::IPC_STAT => {
+            let segment = shm_segments.get(shmid)?;
+            segment.check_access(&credentials, 0o4)?;
+            ctx.user_space().write_val(buf, &segment.shmid_ds())?;
+        }
+        ShmControlCmd::SHM_LOCK => {
+            shm_segments.get(shmid)?.set_locked(true, &credentials)?;
+        }
+        ShmControlCmd::SHM_UNLOCK => {
+            shm_segments.get(shmid)?.set_locked(false, &credentials)?;
+        }
+    }
+
+    Ok(SyscallReturn::Return(0))
+}
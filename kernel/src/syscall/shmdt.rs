@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use align_ext::AlignExt;
+
+use super::SyscallReturn;
+use crate::prelude::*;
+
+pub fn sys_shmdt(shmaddr: Vaddr, ctx: &Context) -> Result<SyscallReturn> {
+    debug!("[sys_shmdt] shmaddr = 0x{:x}", shmaddr);
+
+    if shmaddr % PAGE_SIZE != 0 {
+        return_errno_with_message!(Errno::EINVAL, "the address is not aligned");
+    }
+
+    let user_space = ctx.user_space();
+    let root_vmar = user_space.root_vmar();
+
+    let segment = root_vmar
+        .query(shmaddr..shmaddr + 1)
+        .iter()
+        .find(|vm_mapping| vm_mapping.map_to_addr() == shmaddr)
+        .and_then(|vm_mapping| {
+            let segment = vm_mapping.shm_attachment()?.segment();
+            (vm_mapping.offset_in_vmo(segment.vmo()) == Some(0)).then(|| segment.clone())
+        })
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "no segment is attached"))?;
+
+    // The segment may have been partially unmapped or remapped, so only the mappings that still
+    // map the segment at the corresponding offsets are removed.
+    let end = shmaddr.saturating_add(segment.size().align_up(PAGE_SIZE));
+    let ranges = root_vmar
+        .query(shmaddr..end)
+        .iter()
+        .filter(|vm_mapping| {
+            vm_mapping
+                .offset_in_vmo(segment.vmo())
+                .is_some_and(|offset| shmaddr + offset == vm_mapping.map_to_addr())
+        })
+        .map(|vm_mapping| vm_mapping.map_to_addr()..vm_mapping.map_end().min(end))
+        .collect::<Vec<_>>();
+
+    for range in ranges {
+        root_vmar.remove_mapping(range)?;
+    }
+
+    // The segment is destroyed when the last attachment is dropped with the mapping, if it has
+    // been removed.
+    segment.on_detach(ctx.process.pid());
+
+    Ok(SyscallReturn::Return(0))
+}
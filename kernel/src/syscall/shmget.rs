@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{ipc::IpcFlags, prelude::*};
+
+pub fn sys_shmget(key: i32, size: usize, shmflg: i32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = IpcFlags::from_bits_truncate(shmflg as u32);
+    let mode = (shmflg as u32 & 0o777) as u16;
+    debug!(
+        "[sys_shmget] key = {}, size = {}, flags = {:?}, mode = {:o}",
+        key, size, flags, mode
+    );
+
+    let credentials = ctx.posix_thread.credentials();
+    let ns_proxy = ctx.posix_thread.ns_proxy();
+    let shm_segments = ns_proxy.ipc_ns().shm_segments();
+
+    let id = shm_segments.get_or_create(key, size, flags, mode, &credentials, ctx.process.pid())?;
+
+    Ok(SyscallReturn::Return(id as isize))
+}
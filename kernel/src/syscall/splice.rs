@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::mm::{io_util::HasVmReaderWriter, FrameAllocOptions, UFrame};
+
+use super::SyscallReturn;
+use crate::{
+    fs::{
+        file_handle::FileLike,
+        file_table::{FileDesc, WithFileTable},
+        pipe::{self, PipePage, PipeReader, PipeWriter},
+        utils::{InodeType, SeekFrom, StatusFlags},
+    },
+    prelude::*,
+    vm::vmo::CommitFlags,
+};
+
+pub fn sys_splice(
+    fd_in: FileDesc,
+    off_in_addr: Vaddr,
+    fd_out: FileDesc,
+    off_out_addr: Vaddr,
+    len: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = SpliceFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid splice flags"))?;
+    debug!(
+        "fd_in = {}, off_in = {:#x}, fd_out = {}, off_out = {:#x}, len = {}, flags = {:?}",
+        fd_in, off_in_addr, fd_out, off_out_addr, len, flags
+    );
+
+    let (file_in, file_out) = get_file_pair(fd_in, fd_out, ctx)?;
+    let off_in = read_offset(off_in_addr, ctx)?;
+    let off_out = read_offset(off_out_addr, ctx)?;
+
+    let is_nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK);
+
+    let (spliced_len, off_in, off_out) = match (
+        file_in.downcast_ref::<PipeReader>(),
+        file_out.downcast_ref::<PipeWriter>(),
+    ) {
+        (Some(reader), Some(writer)) => {
+            if off_in.is_some() || off_out.is_some() {
+                return_errno_with_message!(Errno::ESPIPE, "pipes cannot have offsets");
+            }
+            let is_nonblocking =
+                is_nonblocking || is_pipe_nonblocking(reader) || is_pipe_nonblocking(writer);
+            let len = pipe::splice_pipe(reader, writer, len, is_nonblocking)?;
+            (len, None, None)
+        }
+        (Some(reader), None) => {
+            if off_in.is_some() {
+                return_errno_with_message!(Errno::ESPIPE, "pipes cannot have offsets");
+            }
+            let is_nonblocking = is_nonblocking || is_pipe_nonblocking(reader);
+            let len = splice_pipe_to_file(reader, file_out.as_ref(), off_out, len, is_nonblocking)?;
+            (len, None, off_out.map(|offset| offset + len))
+        }
+        (None, Some(writer)) => {
+            if off_out.is_some() {
+                return_errno_with_message!(Errno::ESPIPE, "pipes cannot have offsets");
+            }
+            let is_nonblocking = is_nonblocking || is_pipe_nonblocking(writer);
+            let len = splice_file_to_pipe(file_in.as_ref(), off_in, writer, len, is_nonblocking)?;
+            (len, off_in.map(|offset| offset + len), None)
+        }
+        (None, None) => {
+            return_errno_with_message!(Errno::EINVAL, "neither of the files is a pipe");
+        }
+    };
+
+    if let Some(offset) = off_in {
+        ctx.user_space().write_val(off_in_addr, &(offset as i64))?;
+    }
+    if let Some(offset) = off_out {
+        ctx.user_space().write_val(off_out_addr, &(offset as i64))?;
+    }
+
+    Ok(SyscallReturn::Return(spliced_len as _))
+}
+
+bitflags! {
+    /// Flags used by `splice`, `tee` and `vmsplice`.
+    pub(super) struct SpliceFlags: u32 {
+        /// Attempts to move pages instead of copying.
+        const SPLICE_F_MOVE = 1 << 0;
+        /// Does not block on I/O.
+        const SPLICE_F_NONBLOCK = 1 << 1;
+        /// Expects more data in a subsequent splice.
+        const SPLICE_F_MORE = 1 << 2;
+        /// Gifts the user pages to the kernel (only for `vmsplice`).
+        const SPLICE_F_GIFT = 1 << 3;
+    }
+}
+
+/// Gets the files of the two descriptors.
+///
+/// The input file must be readable and the output file must be writable.
+pub(super) fn get_file_pair(
+    fd_in: FileDesc,
+    fd_out: FileDesc,
+    ctx: &Context,
+) -> Result<(Arc<dyn FileLike>, Arc<dyn FileLike>)> {
+    let (file_in, file_out) = ctx
+        .thread_local
+        .borrow_file_table_mut()
+        .read_with(|inner| {
+            let file_in = inner.get_file(fd_in)?.clone();
+            let file_out = inner.get_file(fd_out)?.clone();
+            Ok::<_, Error>((file_in, file_out))
+        })?;
+
+    if !file_in.access_mode().is_readable() {
+        return_errno_with_message!(Errno::EBADF, "the input file is not opened for reading");
+    }
+    if !file_out.access_mode().is_writable() {
+        return_errno_with_message!(Errno::EBADF, "the output file is not opened for writing");
+    }
+
+    Ok((file_in, file_out))
+}
+
+/// Reads the file offset at the user address, or returns `None` if the address is null.
+pub(super) fn read_offset(offset_addr: Vaddr, ctx: &Context) -> Result<Option<usize>> {
+    if offset_addr == 0 {
+        return Ok(None);
+    }
+
+    let offset: i64 = ctx.user_space().read_val(offset_addr)?;
+    if offset < 0 {
+        return_errno_with_message!(Errno::EINVAL, "the offset is negative");
+    }
+
+    Ok(Some(offset as usize))
+}
+
+pub(super) fn is_pipe_nonblocking(pipe: &dyn FileLike) -> bool {
+    pipe.status_flags().contains(StatusFlags::O_NONBLOCK)
+}
+
+fn splice_pipe_to_file(
+    reader: &PipeReader,
+    file: &dyn FileLike,
+    offset: Option<usize>,
+    len: usize,
+    is_nonblocking: bool,
+) -> Result<usize> {
+    if file.status_flags().contains(StatusFlags::O_APPEND) {
+        return_errno_with_message!(Errno::EINVAL, "the file is opened in append mode");
+    }
+
+    // The pages are written to the file directly, without an intermediate buffer.
+    let mut offset = offset;
+    reader.splice_with(len, is_nonblocking, |page| {
+        let mut page_reader = page.reader().to_fallible();
+        if let Some(offset) = offset.as_mut() {
+            let written_len = file.write_at(*offset, &mut page_reader)?;
+            *offset += written_len;
+            Ok(written_len)
+        } else {
+            file.write(&mut page_reader)
+        }
+    })
+}
+
+fn splice_file_to_pipe(
+    file: &dyn FileLike,
+    offset: Option<usize>,
+    writer: &PipeWriter,
+    len: usize,
+    is_nonblocking: bool,
+) -> Result<usize> {
+    let Some(inode) = file
+        .inode()
+        .filter(|inode| inode.type_() == InodeType::File)
+    else {
+        return splice_file_to_pipe_by_copying(file, offset, writer, len, is_nonblocking);
+    };
+    let Some(pages) = inode.page_cache() else {
+        return splice_file_to_pipe_by_copying(file, offset, writer, len, is_nonblocking);
+    };
+
+    // The pages in the page cache are moved into the pipe without copying.
+    let start = match offset {
+        Some(offset) => offset,
+        None => file.seek(SeekFrom::Current(0))?,
+    };
+    let file_size = inode.size();
+
+    let mut pos = start;
+    let spliced_len = writer.splice_with(len, is_nonblocking, |max_len| {
+        let end = file_size.min(pos + max_len);
+        if pos >= end {
+            return Ok(None);
+        }
+
+        let page_offset = pos % PAGE_SIZE;
+        let page_len = (PAGE_SIZE - page_offset).min(end - pos);
+        let frame = pages.commit_on(pos / PAGE_SIZE, CommitFlags::empty())?;
+        pos += page_len;
+
+        Ok(Some(PipePage::new(
+            frame,
+            page_offset..page_offset + page_len,
+        )))
+    })?;
+
+    if offset.is_none() {
+        file.seek(SeekFrom::Current(spliced_len as isize))?;
+    }
+
+    Ok(spliced_len)
+}
+
+fn splice_file_to_pipe_by_copying(
+    file: &dyn FileLike,
+    offset: Option<usize>,
+    writer: &PipeWriter,
+    len: usize,
+    is_nonblocking: bool,
+) -> Result<usize> {
+    let mut offset = offset;
+    writer.splice_with(len, is_nonblocking, |max_len| {
+        let frame: UFrame = FrameAllocOptions::new().zeroed(false).alloc_frame()?.into();
+
+        let read_len = {
+            let mut frame_writer = frame.writer().to_fallible();
+            frame_writer.limit(max_len);
+            if let Some(offset) = offset.as_mut() {
+                let read_len = file.read_at(*offset, &mut frame_writer)?;
+                *offset += read_len;
+                read_len
+            } else {
+                file.read(&mut frame_writer)?
+            }
+        };
+        if read_len == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(PipePage::new(frame, 0..read_len)))
+    })
+}
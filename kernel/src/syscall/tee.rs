@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    splice::{get_file_pair, is_pipe_nonblocking, SpliceFlags},
+    SyscallReturn,
+};
+use crate::{
+    fs::{
+        file_table::FileDesc,
+        pipe::{self, PipeReader, PipeWriter},
+    },
+    prelude::*,
+};
+
+pub fn sys_tee(
+    fd_in: FileDesc,
+    fd_out: FileDesc,
+    len: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = SpliceFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid splice flags"))?;
+    debug!(
+        "fd_in = {}, fd_out = {}, len = {}, flags = {:?}",
+        fd_in, fd_out, len, flags
+    );
+
+    let (file_in, file_out) = get_file_pair(fd_in, fd_out, ctx)?;
+    let (Some(reader), Some(writer)) = (
+        file_in.downcast_ref::<PipeReader>(),
+        file_out.downcast_ref::<PipeWriter>(),
+    ) else {
+        return_errno_with_message!(Errno::EINVAL, "the files are not pipes");
+    };
+
+    let is_nonblocking = flags.contains(SpliceFlags::SPLICE_F_NONBLOCK)
+        || is_pipe_nonblocking(reader)
+        || is_pipe_nonblocking(writer);
+    let len = pipe::tee_pipe(reader, writer, len, is_nonblocking)?;
+
+    Ok(SyscallReturn::Return(len as _))
+}
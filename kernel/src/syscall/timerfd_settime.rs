@@ -52,11 +52,14 @@ pub fn sys_timerfd_settime(
     // when the timer is rearmed.
     timerfd_file.clear_ticks();
 
-    if expire_time != Duration::ZERO {
-        if flags.contains(TFDSetTimeFlags::TFD_TIMER_CANCEL_ON_SET) {
-            unimplemented!()
-        }
+    // `TFD_TIMER_CANCEL_ON_SET` is ignored if the timer is not an absolute timer.
+    timerfd_file.set_cancel_on_set(
+        flags.contains(
+            TFDSetTimeFlags::TFD_TIMER_ABSTIME | TFDSetTimeFlags::TFD_TIMER_CANCEL_ON_SET,
+        ),
+    );
 
+    if expire_time != Duration::ZERO {
         let timeout = if flags.contains(TFDSetTimeFlags::TFD_TIMER_ABSTIME) {
             Timeout::When(expire_time)
         } else {
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use ostd::sync::RwArc;
+
+use super::SyscallReturn;
+use crate::{
+    prelude::*,
+    process::{namespace::NsProxy, CloneFlags},
+};
+
+pub fn sys_unshare(flags: u64, ctx: &Context) -> Result<SyscallReturn> {
+    let mut flags = CloneFlags::from_bits(flags as u32)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown unshare flags"))?;
+    debug!("flags = {:?}", flags);
+
+    let supported_flags = CloneFlags::CLONE_FS
+        | CloneFlags::CLONE_FILES
+        | CloneFlags::CLONE_SYSVSEM
+        | NsProxy::SUPPORTED_NS_FLAGS;
+    if !supported_flags.contains(flags) {
+        return_errno_with_message!(Errno::EINVAL, "the unshare flags contain unsupported flags");
+    }
+
+    // A new mount namespace requires a private copy of the root and the working directory.
+    if flags.contains(CloneFlags::CLONE_NEWNS) {
+        flags |= CloneFlags::CLONE_FS;
+    }
+
+    let Context {
+        thread_local,
+        posix_thread,
+        ..
+    } = ctx;
+
+    if flags.contains(CloneFlags::CLONE_FS) {
+        let mut fs = thread_local.borrow_fs_mut();
+        if Arc::strong_count(&*fs) > 1 {
+            *fs = Arc::new(fs.as_ref().clone());
+        }
+    }
+
+    if flags.contains(CloneFlags::CLONE_FILES) {
+        let new_table = RwArc::new(thread_local.borrow_file_table().unwrap().read().clone());
+        *posix_thread.file_table().lock() = Some(new_table.clone_ro());
+        if let Some(io_uring_file_table) = posix_thread.io_uring_file_table().lock().as_mut() {
+            *io_uring_file_table = new_table.clone();
+        }
+        let _ = thread_local
+            .borrow_file_table_mut()
+            .replace(Some(new_table));
+    }
+
+    // FIXME: We should detach the System V semaphore undo list if `CLONE_SYSVSEM` is specified.
+    // Currently, the undo operations of semaphores are not supported.
+
+    let ns_proxy = posix_thread.ns_proxy();
+    let new_ns_proxy = ns_proxy.new_copy(flags, &thread_local.borrow_fs(), ctx)?;
+    posix_thread.set_ns_proxy(new_ns_proxy);
+
+    Ok(SyscallReturn::Return(0))
+}
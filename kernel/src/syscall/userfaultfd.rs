@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::SyscallReturn;
+use crate::{
+    fs::file_table::FdFlags,
+    prelude::*,
+    process::credentials::capabilities::CapSet,
+    vm::userfaultfd::{UserfaultfdFile, UserfaultfdFlags},
+};
+
+pub fn sys_userfaultfd(flags: u32, ctx: &Context) -> Result<SyscallReturn> {
+    let flags = UserfaultfdFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "unknown flags"))?;
+    debug!("flags = {:?}", flags);
+
+    // Like Linux with `vm.unprivileged_userfaultfd` being zero, which is the default value,
+    // creating a userfaultfd that also handles the page faults caused by the kernel requires the
+    // `CAP_SYS_PTRACE` capability.
+    let is_user_mode_only = flags.contains(UserfaultfdFlags::UFFD_USER_MODE_ONLY);
+    let credentials = ctx.posix_thread.credentials();
+    if !is_user_mode_only
+        && !credentials.euid().is_root()
+        && !credentials.effective_capset().contains(CapSet::SYS_PTRACE)
+    {
+        return_errno_with_message!(Errno::EPERM, "creating a userfaultfd is not allowed");
+    }
+
+    let userfaultfd_file = UserfaultfdFile::new(
+        ctx.user_space().root_vmar(),
+        is_user_mode_only,
+        flags.contains(UserfaultfdFlags::UFFD_NONBLOCK),
+    )?;
+
+    let fd = {
+        let file_table = ctx.thread_local.borrow_file_table();
+        let mut file_table_locked = file_table.unwrap().write();
+        let fd_flags = if flags.contains(UserfaultfdFlags::UFFD_CLOEXEC) {
+            FdFlags::CLOEXEC
+        } else {
+            FdFlags::empty()
+        };
+        file_table_locked.insert(Arc::new(userfaultfd_file), fd_flags)
+    };
+
+    Ok(SyscallReturn::Return(fd as _))
+}
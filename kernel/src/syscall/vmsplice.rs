@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::{
+    splice::{is_pipe_nonblocking, SpliceFlags},
+    SyscallReturn,
+};
+use crate::{
+    fs::{
+        file_table::{get_file_fast, FileDesc},
+        pipe::{PipeReader, PipeWriter},
+    },
+    prelude::*,
+    util::{VmReaderArray, VmWriterArray},
+};
+
+pub fn sys_vmsplice(
+    fd: FileDesc,
+    io_vec_ptr: Vaddr,
+    io_vec_count: usize,
+    flags: u32,
+    ctx: &Context,
+) -> Result<SyscallReturn> {
+    let flags = SpliceFlags::from_bits(flags)
+        .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid splice flags"))?;
+    debug!(
+        "fd = {}, io_vec_ptr = {:#x}, io_vec_count = {}, flags = {:?}",
+        fd, io_vec_ptr, io_vec_count, flags
+    );
+
+    let mut file_table = ctx.thread_local.borrow_file_table_mut();
+    let file = get_file_fast!(&mut file_table, fd).into_owned();
+    drop(file_table);
+
+    let is_nonblocking =
+        flags.contains(SpliceFlags::SPLICE_F_NONBLOCK) || is_pipe_nonblocking(file.as_ref());
+
+    // TODO: Support `SPLICE_F_GIFT` by mapping the user pages into the pipe.
+    let len = if let Some(writer) = file.downcast_ref::<PipeWriter>() {
+        vmsplice_to_pipe(writer, io_vec_ptr, io_vec_count, is_nonblocking, ctx)?
+    } else if let Some(reader) = file.downcast_ref::<PipeReader>() {
+        vmsplice_from_pipe(reader, io_vec_ptr, io_vec_count, is_nonblocking, ctx)?
+    } else {
+        return_errno_with_message!(Errno::EBADF, "the file is not a pipe");
+    };
+
+    Ok(SyscallReturn::Return(len as _))
+}
+
+fn vmsplice_to_pipe(
+    writer: &PipeWriter,
+    io_vec_ptr: Vaddr,
+    io_vec_count: usize,
+    is_nonblocking: bool,
+    ctx: &Context,
+) -> Result<usize> {
+    let user_space = ctx.user_space();
+    let mut reader_array = VmReaderArray::from_user_io_vecs(&user_space, io_vec_ptr, io_vec_count)?;
+
+    let mut total_len = 0;
+    for reader in reader_array.readers_mut() {
+        if !reader.has_remain() {
+            continue;
+        }
+
+        match writer.write_with_nonblocking(reader, is_nonblocking) {
+            Ok(len) => total_len += len,
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        }
+        if reader.has_remain() {
+            break;
+        }
+    }
+
+    Ok(total_len)
+}
+
+fn vmsplice_from_pipe(
+    reader: &PipeReader,
+    io_vec_ptr: Vaddr,
+    io_vec_count: usize,
+    is_nonblocking: bool,
+    ctx: &Context,
+) -> Result<usize> {
+    let user_space = ctx.user_space();
+    let mut writer_array = VmWriterArray::from_user_io_vecs(&user_space, io_vec_ptr, io_vec_count)?;
+
+    let mut total_len = 0;
+    for writer in writer_array.writers_mut() {
+        if !writer.has_avail() {
+            continue;
+        }
+
+        // Once some data has been read, do not wait for more data to come.
+        match reader.read_with_nonblocking(writer, is_nonblocking || total_len > 0) {
+            Ok(len) => total_len += len,
+            Err(_) if total_len > 0 => break,
+            Err(err) => return Err(err),
+        }
+        if writer.has_avail() {
+            break;
+        }
+    }
+
+    Ok(total_len)
+}
@@ -7,7 +7,8 @@ use crate::{
         do_wait,
         signal::{
             c_types::siginfo_t,
-            constants::{CLD_CONTINUED, CLD_EXITED, CLD_KILLED, CLD_STOPPED, SIGCHLD, SIGCONT},
+            constants::{CLD_CONTINUED, CLD_STOPPED, CLD_TRAPPED, SIGCHLD, SIGCONT},
+            signals::child::exit_code_to_code_and_status,
         },
         ProcessFilter, WaitOptions, WaitStatus,
     },
@@ -49,7 +50,7 @@ pub fn sys_waitid(
     if infoq_addr != 0 {
         let siginfo = {
             let (si_code, si_status) = calculate_si_code_and_si_status(&wait_status);
-            let pid = wait_status.pid();
+            let pid = ctx.posix_thread.pid_ns().to_local(wait_status.pid());
             let uid = wait_status.uid();
 
             let mut siginfo = siginfo_t::new(SIGCHLD, si_code);
@@ -66,22 +67,11 @@ pub fn sys_waitid(
 }
 
 fn calculate_si_code_and_si_status(wait_status: &WaitStatus) -> (i32, i32) {
-    // TODO: Add supports for `CLD_DUMPED` and `CLD_TRAPPED`.
     match wait_status {
-        WaitStatus::Zombie(process) => {
-            const NORMAL_EXIT_MASK: u32 = 0xff;
-
-            let exit_code = process.status().exit_code();
-            // If the process exits normally, the lowest 8 bits of `status_code`
-            // will be zero. In this case, we return the actual exit code by
-            // shifting the `status_code` right by 8 bits.
-            if (exit_code & NORMAL_EXIT_MASK) == 0 {
-                (CLD_EXITED, (exit_code >> 8) as i32)
-            } else {
-                (CLD_KILLED, exit_code as i32)
-            }
-        }
+        WaitStatus::Zombie(process) => exit_code_to_code_and_status(process.status().exit_code()),
+        WaitStatus::PtraceExit(_, exit_code) => exit_code_to_code_and_status(*exit_code),
         WaitStatus::Stop(_process, signum) => (CLD_STOPPED, signum.as_u8() as i32),
         WaitStatus::Continue(_) => (CLD_CONTINUED, SIGCONT.as_u8() as i32),
+        WaitStatus::PtraceStop(_, code) => (CLD_TRAPPED, *code as i32),
     }
 }
@@ -29,6 +29,12 @@ pub struct PageFaultInfo {
     /// The [`VmPerms`] required by the memory operation that causes page fault.
     /// For example, a "store" operation may require `VmPerms::WRITE`.
     pub required_perms: VmPerms,
+
+    /// Whether the page fault is caused by the user-mode code.
+    ///
+    /// This is false if the page fault is caused by the kernel when accessing the user space
+    /// (e.g., in system calls).
+    pub is_user: bool,
 }
 
 /// We can't handle most exceptions, just send self a fault signal before return to user space.
@@ -38,8 +44,12 @@ pub fn handle_exception(ctx: &Context, context: &UserContext, exception: CpuExce
     if let Ok(page_fault_info) = PageFaultInfo::try_from(&exception) {
         let user_space = ctx.user_space();
         let root_vmar = user_space.root_vmar();
-        if handle_page_fault_from_vmar(root_vmar, &page_fault_info).is_ok() {
-            return;
+        match handle_page_fault_from_vmar(root_vmar, &page_fault_info) {
+            Ok(()) => return,
+            // The page fault was waiting for a userfaultfd and is interrupted by a signal. The
+            // signal will be handled and the faulting instruction will be executed again.
+            Err(err) if err.error() == Errno::EINTR => return,
+            Err(_) => (),
         }
     }
 
@@ -50,15 +60,17 @@ pub fn handle_exception(ctx: &Context, context: &UserContext, exception: CpuExce
 fn handle_page_fault_from_vmar(
     root_vmar: &Vmar<Full>,
     page_fault_info: &PageFaultInfo,
-) -> core::result::Result<(), ()> {
-    if let Err(e) = root_vmar.handle_page_fault(page_fault_info) {
-        warn!(
-            "page fault handler failed: addr: 0x{:x}, err: {:?}",
-            page_fault_info.address, e
-        );
-        return Err(());
-    }
-    Ok(())
+) -> Result<()> {
+    root_vmar
+        .handle_page_fault(page_fault_info)
+        .inspect_err(|e| {
+            if e.error() != Errno::EINTR {
+                warn!(
+                    "page fault handler failed: addr: 0x{:x}, err: {:?}",
+                    page_fault_info.address, e
+                );
+            }
+        })
 }
 
 /// generate a fault signal for current process.
@@ -78,5 +90,9 @@ pub(super) fn page_fault_handler(info: &CpuException) -> core::result::Result<()
     }
 
     let user_space = CurrentUserSpace::new(thread_local);
-    handle_page_fault_from_vmar(user_space.root_vmar(), &info.try_into().unwrap())
+    let page_fault_info = PageFaultInfo {
+        is_user: false,
+        ..info.try_into().unwrap()
+    };
+    handle_page_fault_from_vmar(user_space.root_vmar(), &page_fault_info).map_err(|_| ())
 }
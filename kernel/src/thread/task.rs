@@ -55,8 +55,11 @@ pub fn create_new_user_task(
         // in the child process.
         let child_tid_ptr = current_thread_local.set_child_tid().get();
         if is_userspace_vaddr(child_tid_ptr) {
+            let child_tid = current_posix_thread
+                .pid_ns()
+                .to_local(current_posix_thread.tid());
             current_userspace!()
-                .write_val(child_tid_ptr, &current_posix_thread.tid())
+                .write_val(child_tid_ptr, &child_tid)
                 .unwrap();
         }
 
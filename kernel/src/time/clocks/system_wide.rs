@@ -3,13 +3,20 @@
 use alloc::sync::Arc;
 use core::time::Duration;
 
-use aster_time::read_monotonic_time;
+use aster_time::{read_monotonic_raw_time, read_monotonic_time};
 use ostd::{cpu::PinCurrentCpu, cpu_local, sync::SpinLock, task::disable_preempt, timer::Jiffies};
 use paste::paste;
 use spin::Once;
 
-use crate::time::{
-    self, system_time::START_TIME_AS_DURATION, timer::TimerManager, Clock, SystemTime,
+use crate::{
+    prelude::*,
+    time::{
+        self,
+        system_time::{realtime_offset, set_realtime_offset},
+        timer::TimerManager,
+        timerfd, Clock, SystemTime,
+    },
+    vdso,
 };
 
 /// The Clock that reads the jiffies, and turn the counter into `Duration`.
@@ -36,6 +43,30 @@ impl RealTimeClock {
             .get()
             .unwrap()
     }
+
+    /// Sets the current time of this clock.
+    ///
+    /// The clock jumps to the new time, so the timers based on this clock are re-armed, and
+    /// the timerfds that should be canceled on clock jumps are canceled.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EINVAL`] if the new time is earlier than the monotonic time,
+    /// i.e., the boot time would be earlier than the Unix epoch.
+    pub fn set_time(&self, time: Duration) -> Result<()> {
+        let now = read_monotonic_time();
+        let Some(offset) = time.checked_sub(now) else {
+            return_errno_with_message!(Errno::EINVAL, "the new time is earlier than the boot time");
+        };
+        let old_offset = set_realtime_offset(offset);
+
+        // All CPUs share the same timer manager, so re-arming the timers once is enough.
+        Self::timer_manager().handle_clock_jump(now + old_offset, time);
+        vdso::update_vdso_realtime();
+        timerfd::cancel_on_clock_set();
+
+        Ok(())
+    }
 }
 
 /// `MonotonicClock` represents a clock that measures time in a way that is
@@ -62,10 +93,10 @@ impl MonotonicClock {
 
 /// `RealTimeCoarseClock` is a coarse-grained version of a real-time clock.
 ///
-/// This clock will maintain a record to `RealTimeClock`. This record
-/// will be updated during each system timer interruption. Reading this clock
-/// will directly reads the value of the record instead of calculating the time
-/// based on the clocksource. Hence it is faster but less accurate.
+/// This clock is based on [`MonotonicCoarseClock`]. Reading this clock will add the
+/// offset of the real time to the value of the record maintained by `MonotonicCoarseClock`,
+/// instead of calculating the time based on the clocksource. Hence it is faster but less
+/// accurate.
 ///
 /// Usually it will not be used to create a timer.
 pub struct RealTimeCoarseClock {
@@ -73,13 +104,6 @@ pub struct RealTimeCoarseClock {
 }
 
 impl RealTimeCoarseClock {
-    /// A reference to the current value of this clock.
-    fn current_ref() -> &'static Once<SpinLock<Duration>> {
-        static CURRENT: Once<SpinLock<Duration>> = Once::new();
-
-        &CURRENT
-    }
-
     /// Get the singleton of this clock.
     pub fn get() -> &'static Arc<RealTimeCoarseClock> {
         CLOCK_REALTIME_COARSE_INSTANCE.get().unwrap()
@@ -88,7 +112,10 @@ impl RealTimeCoarseClock {
 
 /// `MonotonicCoarseClock` is a coarse-grained version of the monotonic clock.
 ///
-/// This clock is based on [`RealTimeCoarseClock`].
+/// This clock will maintain a record to `MonotonicClock`. This record
+/// will be updated during each system timer interruption. Reading this clock
+/// will directly reads the value of the record instead of calculating the time
+/// based on the clocksource. Hence it is faster but less accurate.
 ///
 /// Usually it will not be used to create a timer.
 pub struct MonotonicCoarseClock {
@@ -96,6 +123,13 @@ pub struct MonotonicCoarseClock {
 }
 
 impl MonotonicCoarseClock {
+    /// A reference to the current value of this clock.
+    fn current_ref() -> &'static Once<SpinLock<Duration>> {
+        static CURRENT: Once<SpinLock<Duration>> = Once::new();
+
+        &CURRENT
+    }
+
     /// Get the singleton of this clock.
     pub fn get() -> &'static Arc<MonotonicCoarseClock> {
         CLOCK_MONOTONIC_COARSE_INSTANCE.get().unwrap()
@@ -103,10 +137,7 @@ impl MonotonicCoarseClock {
 }
 
 /// `MonotonicRawClock` provides raw monotonic time that is not influenced by
-/// NTP corrections.
-///
-/// Note: Currently we have not implement NTP corrections so we treat this clock
-/// as the [`MonotonicClock`].
+/// NTP corrections, i.e., neither the frequency adjustment nor the slewing.
 pub struct MonotonicRawClock {
     _private: (),
 }
@@ -165,19 +196,19 @@ impl Clock for MonotonicClock {
 
 impl Clock for RealTimeCoarseClock {
     fn read_time(&self) -> Duration {
-        *Self::current_ref().get().unwrap().disable_irq().lock()
+        MonotonicCoarseClock::get().read_time() + realtime_offset()
     }
 }
 
 impl Clock for MonotonicCoarseClock {
     fn read_time(&self) -> Duration {
-        RealTimeCoarseClock::get().read_time() - *START_TIME_AS_DURATION.get().unwrap()
+        *Self::current_ref().get().unwrap().disable_irq().lock()
     }
 }
 
 impl Clock for MonotonicRawClock {
     fn read_time(&self) -> Duration {
-        read_monotonic_time()
+        read_monotonic_raw_time()
     }
 }
 
@@ -283,14 +314,14 @@ fn init_jiffies_clock_manager() {
 }
 
 fn update_coarse_clock() {
-    let real_time = RealTimeClock::get().read_time();
-    let current = RealTimeCoarseClock::current_ref().get().unwrap();
-    *current.disable_irq().lock() = real_time;
+    let monotonic_time = MonotonicClock::get().read_time();
+    let current = MonotonicCoarseClock::current_ref().get().unwrap();
+    *current.disable_irq().lock() = monotonic_time;
 }
 
 fn init_coarse_clock() {
-    let real_time = RealTimeClock::get().read_time();
-    RealTimeCoarseClock::current_ref().call_once(|| SpinLock::new(real_time));
+    let monotonic_time = MonotonicClock::get().read_time();
+    MonotonicCoarseClock::current_ref().call_once(|| SpinLock::new(monotonic_time));
     time::softirq::register_callback(update_coarse_clock);
 }
 
@@ -315,7 +346,8 @@ pub fn init_for_ktest() {
         });
     }
     CLOCK_REALTIME_COARSE_INSTANCE.call_once(|| Arc::new(RealTimeCoarseClock { _private: () }));
-    RealTimeCoarseClock::current_ref().call_once(|| SpinLock::new(Duration::from_secs(0)));
+    CLOCK_MONOTONIC_COARSE_INSTANCE.call_once(|| Arc::new(MonotonicCoarseClock { _private: () }));
+    MonotonicCoarseClock::current_ref().call_once(|| SpinLock::new(Duration::from_secs(0)));
     JIFFIES_TIMER_MANAGER.call_once(|| {
         let clock = JiffiesClock { _private: () };
         TimerManager::new(Arc::new(clock))
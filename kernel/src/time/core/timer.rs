@@ -7,7 +7,7 @@ use alloc::{
     vec::Vec,
 };
 use core::{
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     time::Duration,
 };
 
@@ -75,17 +75,18 @@ impl Timer {
     /// when reaching timeout. If the timer has a valid interval, this timer
     /// will be set again with the interval when reaching timeout.
     pub fn set_timeout(self: &Arc<Self>, timeout: Timeout) {
-        let expired_time = match timeout {
+        let (expired_time, is_relative) = match timeout {
             Timeout::After(timeout) => {
                 let now = self.timer_manager.clock.read_time();
-                now + timeout
+                (now + timeout, true)
             }
-            Timeout::When(timeout) => timeout,
+            Timeout::When(timeout) => (timeout, false),
         };
 
         let timer_weak = Arc::downgrade(self);
         let new_timer_callback = Arc::new(TimerCallback::new(
             expired_time,
+            is_relative,
             Box::new(move || interval_timer_callback(&timer_weak)),
         ));
 
@@ -100,7 +101,9 @@ impl Timer {
     /// Return the current expired time of this timer.
     pub fn expired_time(&self) -> Duration {
         let timer_callback = self.timer_callback.disable_irq().lock().upgrade();
-        timer_callback.map_or(Duration::ZERO, |timer_callback| timer_callback.expired_time)
+        timer_callback.map_or(Duration::ZERO, |timer_callback| {
+            timer_callback.expired_time()
+        })
     }
 
     /// Return the remain time to expiration of this timer.
@@ -198,7 +201,7 @@ impl TimerManager {
                 if t.is_cancelled() {
                     // Just ignore the cancelled callback
                     timeout_list.pop();
-                } else if t.expired_time <= current_time {
+                } else if t.expired_time() <= current_time {
                     callbacks.push(timeout_list.pop().unwrap());
                 } else {
                     break;
@@ -212,6 +215,31 @@ impl TimerManager {
         }
     }
 
+    /// Re-arms the managed timers after the clock jumps from `old_time` to `new_time`.
+    ///
+    /// A timer set with a relative timeout (i.e., [`Timeout::After`]) should expire after the
+    /// specified duration elapses regardless of the clock jumps, so its expired time is shifted
+    /// with the clock. A timer set with an absolute timeout (i.e., [`Timeout::When`]) should
+    /// expire when the clock reaches the specified time, so its expired time is kept.
+    pub fn handle_clock_jump(&self, old_time: Duration, new_time: Duration) {
+        let mut timeout_list = self.timer_callbacks.disable_irq().lock();
+
+        let mut callbacks = core::mem::take(&mut *timeout_list).into_vec();
+        callbacks.retain(|t| !t.is_cancelled());
+        for t in callbacks.iter().filter(|t| t.is_relative) {
+            let expired_time = t.expired_time();
+            let expired_time = if new_time >= old_time {
+                expired_time.saturating_add(new_time - old_time)
+            } else {
+                expired_time.saturating_sub(old_time - new_time)
+            };
+            t.set_expired_time(expired_time);
+        }
+
+        // The order of the expired times has changed, so the heap must be rebuilt.
+        *timeout_list = BinaryHeap::from(callbacks);
+    }
+
     /// Create an [`Timer`], which will be managed by this `TimerManager`.
     pub fn create_timer<F>(self: &Arc<Self>, function: F) -> Arc<Timer>
     where
@@ -223,21 +251,39 @@ impl TimerManager {
 
 /// A `TimerCallback` can be used to execute a timer callback function.
 struct TimerCallback {
-    expired_time: Duration,
+    /// The expired time in nanoseconds.
+    ///
+    /// This is only changed with the lock of [`TimerManager::timer_callbacks`] held, since the
+    /// order of the `TimerCallback`s in the heap relies on it.
+    expired_nanos: AtomicU64,
+    /// Whether the timeout is relative, so the expired time should be shifted when the clock
+    /// jumps.
+    is_relative: bool,
     callback: Box<dyn Fn() + Send + Sync>,
     is_cancelled: AtomicBool,
 }
 
 impl TimerCallback {
     /// Create an instance of `TimerCallback`.
-    fn new(timeout: Duration, callback: Box<dyn Fn() + Send + Sync>) -> Self {
+    fn new(timeout: Duration, is_relative: bool, callback: Box<dyn Fn() + Send + Sync>) -> Self {
         Self {
-            expired_time: timeout,
+            expired_nanos: AtomicU64::new(duration_to_nanos(timeout)),
+            is_relative,
             callback,
             is_cancelled: AtomicBool::new(false),
         }
     }
 
+    /// Returns the expired time of the `TimerCallback`.
+    fn expired_time(&self) -> Duration {
+        Duration::from_nanos(self.expired_nanos.load(Ordering::Relaxed))
+    }
+
+    fn set_expired_time(&self, expired_time: Duration) {
+        self.expired_nanos
+            .store(duration_to_nanos(expired_time), Ordering::Relaxed);
+    }
+
     /// Cancel a `TimerCallback`. If the callback function has not been called,
     /// it will never be called again.
     fn cancel(&self) {
@@ -250,9 +296,14 @@ impl TimerCallback {
     }
 }
 
+/// Converts a `Duration` into nanoseconds, saturating at `u64::MAX` (i.e., more than 584 years).
+fn duration_to_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
 impl PartialEq for TimerCallback {
     fn eq(&self, other: &Self) -> bool {
-        self.expired_time == other.expired_time
+        self.expired_time() == other.expired_time()
     }
 }
 
@@ -269,6 +320,6 @@ impl Ord for TimerCallback {
         // We want `TimerCallback`s to be processed in ascending order of `expired_time`,
         // and the in-order management of `TimerCallback`s currently relies on a maximum heap,
         // so we need the reverse instruction here.
-        self.expired_time.cmp(&other.expired_time).reverse()
+        self.expired_time().cmp(&other.expired_time()).reverse()
     }
 }
@@ -0,0 +1,372 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! The adjustments of the system clock, which are made by NTP clients via `adjtimex`.
+//!
+//! The frequency adjustment and the time offset are applied to the clocksource, which slews the
+//! time gradually instead of making it jump (see [`aster_time::set_slew_offset`]).
+//!
+//! The phase-locked loop (PLL) and the frequency-locked loop (FLL) of the NTP kernel model are
+//! not implemented. Instead, a time offset set with `ADJ_OFFSET` is slewed directly, as if it is
+//! set with `ADJ_OFFSET_SINGLESHOT`.
+//!
+//! Reference: <https://man7.org/linux/man-pages/man2/adjtimex.2.html>
+
+use core::time::Duration;
+
+use super::{clocks::RealTimeClock, Clock, NSEC_PER_SEC, NSEC_PER_USEC, USEC_PER_SEC};
+use crate::{prelude::*, process::credentials::capabilities::CapSet};
+
+/// The `timex` struct in Linux.
+///
+/// Reference: <https://elixir.bootlin.com/linux/v6.16/source/include/uapi/linux/timex.h#L99>
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod)]
+pub struct timex_t {
+    pub modes: u32,
+    __pad0: u32,
+    /// The time offset, in microseconds (or nanoseconds if `STA_NANO` is set).
+    pub offset: i64,
+    /// The frequency offset, in scaled parts per million.
+    pub freq: i64,
+    /// The maximum error, in microseconds.
+    pub maxerror: i64,
+    /// The estimated error, in microseconds.
+    pub esterror: i64,
+    pub status: i32,
+    __pad1: u32,
+    /// The PLL time constant.
+    pub constant: i64,
+    /// The clock precision, in microseconds.
+    pub precision: i64,
+    /// The clock frequency tolerance, in scaled parts per million.
+    pub tolerance: i64,
+    /// The current time, with microseconds (or nanoseconds if `STA_NANO` is set).
+    pub time: timex_timeval_t,
+    /// The microseconds between clock ticks.
+    pub tick: i64,
+    pub ppsfreq: i64,
+    pub jitter: i64,
+    pub shift: i32,
+    __pad2: u32,
+    pub stabil: i64,
+    pub jitcnt: i64,
+    pub calcnt: i64,
+    pub errcnt: i64,
+    pub stbcnt: i64,
+    /// The offset between the TAI and the UTC, in seconds.
+    pub tai: i32,
+    __pad3: [u32; 11],
+}
+
+/// The time in [`timex_t`], where `usec` may be nanoseconds if `STA_NANO` is set.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod)]
+pub struct timex_timeval_t {
+    pub sec: i64,
+    pub usec: i64,
+}
+
+bitflags! {
+    /// The modes of `adjtimex`.
+    pub struct TimexModes: u32 {
+        const ADJ_OFFSET = 0x0001;
+        const ADJ_FREQUENCY = 0x0002;
+        const ADJ_MAXERROR = 0x0004;
+        const ADJ_ESTERROR = 0x0008;
+        const ADJ_STATUS = 0x0010;
+        const ADJ_TIMECONST = 0x0020;
+        const ADJ_TAI = 0x0080;
+        const ADJ_SETOFFSET = 0x0100;
+        const ADJ_MICRO = 0x1000;
+        const ADJ_NANO = 0x2000;
+        const ADJ_TICK = 0x4000;
+        // The following two flags are used together with `ADJ_OFFSET`.
+        const ADJ_OFFSET_SS = 0x8000;
+        const ADJ_OFFSET_READONLY = 0x2000;
+    }
+}
+
+impl TimexModes {
+    /// `ADJ_OFFSET_SINGLESHOT`, which is used by `adjtime`.
+    pub const ADJ_OFFSET_SINGLESHOT: Self = Self::ADJ_OFFSET.union(Self::ADJ_OFFSET_SS);
+    /// `ADJ_OFFSET_SS_READ`, which reads the remaining offset of `adjtime`.
+    pub const ADJ_OFFSET_SS_READ: Self =
+        Self::ADJ_OFFSET_SINGLESHOT.union(Self::ADJ_OFFSET_READONLY);
+}
+
+bitflags! {
+    /// The clock status of NTP.
+    struct TimexStatus: i32 {
+        const STA_PLL = 0x0001;
+        const STA_PPSFREQ = 0x0002;
+        const STA_PPSTIME = 0x0004;
+        const STA_FLL = 0x0008;
+        const STA_INS = 0x0010;
+        const STA_DEL = 0x0020;
+        const STA_UNSYNC = 0x0040;
+        const STA_FREQHOLD = 0x0080;
+        // The following flags are read-only.
+        const STA_PPSSIGNAL = 0x0100;
+        const STA_PPSJITTER = 0x0200;
+        const STA_PPSWANDER = 0x0400;
+        const STA_PPSERROR = 0x0800;
+        const STA_CLOCKERR = 0x1000;
+        const STA_NANO = 0x2000;
+        const STA_MODE = 0x4000;
+        const STA_CLK = 0x8000;
+    }
+}
+
+impl TimexStatus {
+    const STA_RONLY: Self = Self::STA_PPSSIGNAL
+        .union(Self::STA_PPSJITTER)
+        .union(Self::STA_PPSWANDER)
+        .union(Self::STA_PPSERROR)
+        .union(Self::STA_CLOCKERR)
+        .union(Self::STA_NANO)
+        .union(Self::STA_MODE)
+        .union(Self::STA_CLK);
+}
+
+/// The clock is synchronized.
+const TIME_OK: i32 = 0;
+/// The clock is not synchronized.
+const TIME_ERROR: i32 = 5;
+
+/// The scale of the frequencies in [`timex_t`], i.e., parts per million shifted left by 16.
+const PPM_SHIFT: u32 = 16;
+/// The maximum frequency offset, in scaled parts per million.
+const MAX_FREQ_SCALED: i64 = 500 << PPM_SHIFT;
+/// The maximum time offset of `ADJ_OFFSET`, in nanoseconds.
+const MAX_PHASE: i64 = 500_000_000;
+/// The maximum error, in microseconds.
+const MAX_ERROR: i64 = (MAX_PHASE / NSEC_PER_USEC) << 5;
+/// The maximum PLL time constant.
+const MAX_TIME_CONSTANT: i64 = 10;
+/// The nominal microseconds between clock ticks, where the tick rate is `USER_HZ` (i.e., 100).
+const NOMINAL_TICK: i64 = USEC_PER_SEC / 100;
+
+/// The NTP state that is not applied to the clocksource.
+struct NtpState {
+    status: TimexStatus,
+    /// The frequency offset in scaled parts per million.
+    ///
+    /// This keeps the value set by users, which may not be exactly represented in parts per
+    /// billion by the clocksource.
+    freq: i64,
+    maxerror: i64,
+    esterror: i64,
+    constant: i64,
+    tick: i64,
+    tai: i32,
+}
+
+static NTP_STATE: Mutex<NtpState> = Mutex::new(NtpState {
+    status: TimexStatus::STA_UNSYNC,
+    freq: 0,
+    maxerror: MAX_ERROR,
+    esterror: MAX_ERROR,
+    constant: 2,
+    tick: NOMINAL_TICK,
+    tai: 0,
+});
+
+/// Adjusts the system clock as specified by `timex`.
+///
+/// When this function succeeds, `timex` will be filled with the current state of the system
+/// clock, and the clock state (e.g., `TIME_OK`) will be returned.
+///
+/// Adjusting the system clock requires the `CAP_SYS_TIME` capability, while reading the state
+/// of the system clock (i.e., with no modes or `ADJ_OFFSET_SS_READ`) does not.
+pub fn adjust_system_clock(timex: &mut timex_t, ctx: &Context) -> Result<i32> {
+    let modes = TimexModes::from_bits_truncate(timex.modes);
+    validate_timex(modes, timex, ctx)?;
+
+    let mut state = NTP_STATE.lock();
+
+    if modes.contains(TimexModes::ADJ_SETOFFSET) {
+        set_offset(modes, &timex.time)?;
+    }
+
+    if modes.contains(TimexModes::ADJ_OFFSET_SS) {
+        // The offset of `adjtime` is always in microseconds.
+        let old_offset = if modes.contains(TimexModes::ADJ_OFFSET_READONLY) {
+            aster_time::slew_offset()
+        } else {
+            aster_time::set_slew_offset(timex.offset.saturating_mul(NSEC_PER_USEC))
+        };
+        timex.offset = old_offset / NSEC_PER_USEC;
+    } else {
+        state.apply(modes, timex);
+
+        let offset = aster_time::slew_offset();
+        timex.offset = if state.status.contains(TimexStatus::STA_NANO) {
+            offset
+        } else {
+            offset / NSEC_PER_USEC
+        };
+    }
+
+    state.fill(timex);
+
+    if state.status.contains(TimexStatus::STA_UNSYNC) {
+        Ok(TIME_ERROR)
+    } else {
+        Ok(TIME_OK)
+    }
+}
+
+impl NtpState {
+    fn apply(&mut self, modes: TimexModes, timex: &timex_t) {
+        if modes.contains(TimexModes::ADJ_STATUS) {
+            let status = TimexStatus::from_bits_truncate(timex.status);
+            self.status =
+                (self.status & TimexStatus::STA_RONLY) | (status - TimexStatus::STA_RONLY);
+        }
+
+        if modes.contains(TimexModes::ADJ_NANO) {
+            self.status |= TimexStatus::STA_NANO;
+        }
+        if modes.contains(TimexModes::ADJ_MICRO) {
+            self.status -= TimexStatus::STA_NANO;
+        }
+
+        if modes.contains(TimexModes::ADJ_FREQUENCY) {
+            self.freq = timex.freq.clamp(-MAX_FREQ_SCALED, MAX_FREQ_SCALED);
+        }
+        if modes.contains(TimexModes::ADJ_TICK) {
+            self.tick = timex.tick;
+        }
+        if modes.intersects(TimexModes::ADJ_FREQUENCY | TimexModes::ADJ_TICK) {
+            aster_time::set_freq_adjustment(self.freq_adjustment());
+        }
+
+        if modes.contains(TimexModes::ADJ_MAXERROR) {
+            self.maxerror = timex.maxerror.clamp(0, MAX_ERROR);
+        }
+        if modes.contains(TimexModes::ADJ_ESTERROR) {
+            self.esterror = timex.esterror.clamp(0, MAX_ERROR);
+        }
+
+        if modes.contains(TimexModes::ADJ_TIMECONST) {
+            self.constant = timex.constant.clamp(0, MAX_TIME_CONSTANT);
+        }
+
+        if modes.contains(TimexModes::ADJ_TAI) && timex.constant >= 0 {
+            self.tai = timex.constant.min(i32::MAX as i64) as i32;
+        }
+
+        // The time offset is only used when the PLL mode is enabled.
+        if modes.contains(TimexModes::ADJ_OFFSET) && self.status.contains(TimexStatus::STA_PLL) {
+            let offset = if self.status.contains(TimexStatus::STA_NANO) {
+                timex.offset
+            } else {
+                timex.offset.saturating_mul(NSEC_PER_USEC)
+            };
+            aster_time::set_slew_offset(offset.clamp(-MAX_PHASE, MAX_PHASE));
+        }
+    }
+
+    /// Returns the frequency adjustment of the clocksource, in parts per billion.
+    ///
+    /// A tick length longer than [`NOMINAL_TICK`] makes the clock run faster, as each tick
+    /// advances the clock by more microseconds, and vice versa.
+    fn freq_adjustment(&self) -> i64 {
+        // These cannot overflow since the frequency offset and the tick length have been
+        // validated or clamped.
+        let tick_adjustment = (self.tick - NOMINAL_TICK) * NSEC_PER_SEC / NOMINAL_TICK;
+        let freq_adjustment = (self.freq * 1000) >> PPM_SHIFT;
+        tick_adjustment + freq_adjustment
+    }
+
+    fn fill(&self, timex: &mut timex_t) {
+        let now = RealTimeClock::get().read_time();
+        let usec = if self.status.contains(TimexStatus::STA_NANO) {
+            now.subsec_nanos() as i64
+        } else {
+            now.subsec_micros() as i64
+        };
+
+        *timex = timex_t {
+            modes: timex.modes,
+            offset: timex.offset,
+            freq: self.freq,
+            maxerror: self.maxerror,
+            esterror: self.esterror,
+            status: self.status.bits(),
+            constant: self.constant,
+            precision: 1,
+            tolerance: MAX_FREQ_SCALED,
+            time: timex_timeval_t {
+                sec: now.as_secs() as i64,
+                usec,
+            },
+            tick: self.tick,
+            tai: self.tai,
+            ..Default::default()
+        };
+    }
+}
+
+fn validate_timex(modes: TimexModes, timex: &timex_t, ctx: &Context) -> Result<()> {
+    let has_cap_sys_time = || {
+        let credentials = ctx.posix_thread.credentials();
+        credentials.euid().is_root() || credentials.effective_capset().contains(CapSet::SYS_TIME)
+    };
+
+    if modes.contains(TimexModes::ADJ_OFFSET_SS) {
+        if !modes.contains(TimexModes::ADJ_OFFSET_SINGLESHOT) {
+            return_errno_with_message!(Errno::EINVAL, "ADJ_OFFSET_SS is used without ADJ_OFFSET");
+        }
+        if !modes.contains(TimexModes::ADJ_OFFSET_READONLY) && !has_cap_sys_time() {
+            return_errno_with_message!(Errno::EPERM, "adjusting the clock is not allowed");
+        }
+    } else {
+        if !modes.is_empty() && !has_cap_sys_time() {
+            return_errno_with_message!(Errno::EPERM, "adjusting the clock is not allowed");
+        }
+        if modes.contains(TimexModes::ADJ_TICK)
+            && !(NOMINAL_TICK * 9 / 10..=NOMINAL_TICK * 11 / 10).contains(&timex.tick)
+        {
+            return_errno_with_message!(Errno::EINVAL, "the tick is out of range");
+        }
+    }
+
+    // The frequency offset is in scaled parts per million, and it must be able to be converted
+    // to scaled parts per billion without overflow, though it will be clamped later.
+    if modes.contains(TimexModes::ADJ_FREQUENCY)
+        && timex.freq.checked_mul(1000 << PPM_SHIFT).is_none()
+    {
+        return_errno_with_message!(Errno::EINVAL, "the frequency offset is out of range");
+    }
+
+    if modes.contains(TimexModes::ADJ_SETOFFSET) {
+        let max_usec = if modes.contains(TimexModes::ADJ_NANO) {
+            NSEC_PER_SEC
+        } else {
+            USEC_PER_SEC
+        };
+        if !(0..max_usec).contains(&timex.time.usec) {
+            return_errno_with_message!(Errno::EINVAL, "the time offset is not normalized");
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes the real-time clock jump by the offset specified with `ADJ_SETOFFSET`.
+fn set_offset(modes: TimexModes, offset: &timex_timeval_t) -> Result<()> {
+    let offset_nanos = if modes.contains(TimexModes::ADJ_NANO) {
+        offset.usec as i128
+    } else {
+        offset.usec as i128 * NSEC_PER_USEC as i128
+    };
+    let offset_nanos = offset.sec as i128 * NSEC_PER_SEC as i128 + offset_nanos;
+
+    let realtime_clock = RealTimeClock::get();
+    let now_nanos = realtime_clock.read_time().as_nanos() as i128;
+    let time = u64::try_from(now_nanos + offset_nanos)
+        .map_err(|_| Error::with_message(Errno::EINVAL, "the new time is out of range"))?;
+
+    realtime_clock.set_time(Duration::from_nanos(time))
+}
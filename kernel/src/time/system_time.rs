@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::time::Duration;
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use aster_time::{read_monotonic_time, read_start_time};
 use spin::Once;
@@ -13,15 +16,32 @@ use crate::prelude::*;
 pub struct SystemTime(PrimitiveDateTime);
 
 pub static START_TIME: Once<SystemTime> = Once::new();
-pub(super) static START_TIME_AS_DURATION: Once<Duration> = Once::new();
+
+/// The offset of the real time from the monotonic time, in nanoseconds.
+///
+/// The offset is initialized to the start time, and it changes when the real time is set.
+static REALTIME_OFFSET: AtomicU64 = AtomicU64::new(0);
 
 pub(super) fn init() {
     let start_time = convert_system_time(read_start_time()).unwrap();
-    START_TIME_AS_DURATION
-        .call_once(|| start_time.duration_since(&SystemTime::UNIX_EPOCH).unwrap());
+    let start_time_as_duration = start_time.duration_since(&SystemTime::UNIX_EPOCH).unwrap();
+    set_realtime_offset(start_time_as_duration);
     START_TIME.call_once(|| start_time);
 }
 
+/// Returns the offset of the real time from the monotonic time.
+///
+/// In other words, the real time is the monotonic time plus the offset.
+pub fn realtime_offset() -> Duration {
+    Duration::from_nanos(REALTIME_OFFSET.load(Ordering::Relaxed))
+}
+
+/// Sets the offset of the real time from the monotonic time, and returns the old offset.
+pub(super) fn set_realtime_offset(offset: Duration) -> Duration {
+    let old_offset = REALTIME_OFFSET.swap(offset.as_nanos() as u64, Ordering::Relaxed);
+    Duration::from_nanos(old_offset)
+}
+
 impl SystemTime {
     /// The unix epoch, which represents 1970-01-01 00:00:00
     pub const UNIX_EPOCH: SystemTime = SystemTime::unix_epoch();
@@ -41,10 +61,8 @@ impl SystemTime {
     /// Returns the current system time
     pub fn now() -> Self {
         // The get real time result should always be valid
-        START_TIME
-            .get()
-            .unwrap()
-            .checked_add(read_monotonic_time())
+        Self::UNIX_EPOCH
+            .checked_add(read_monotonic_time() + realtime_offset())
             .unwrap()
     }
 
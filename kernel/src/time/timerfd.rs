@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use super::clockid_t;
 use crate::{
@@ -14,7 +14,7 @@ use crate::{
         signal::{PollHandle, Pollable, Pollee},
         Gid, Uid,
     },
-    syscall::create_timer,
+    syscall::{create_timer, ClockId},
     time::{clocks::RealTimeClock, Timer},
 };
 
@@ -24,6 +24,39 @@ pub struct TimerfdFile {
     ticks: Arc<AtomicU64>,
     pollee: Pollee,
     flags: SpinLock<TFDFlags>,
+    cancel_state: Arc<CancelState>,
+}
+
+/// The state of a timerfd that can be canceled when the real-time clock is set.
+struct CancelState {
+    /// Whether the timerfd should be canceled when the clock is set.
+    ///
+    /// This is set by `TFD_TIMER_CANCEL_ON_SET`.
+    is_cancel_on_set: AtomicBool,
+    /// Whether the timerfd has been canceled and the cancellation has not been read.
+    is_canceled: AtomicBool,
+    pollee: Pollee,
+}
+
+/// The timerfds based on the real-time clock.
+static REALTIME_TIMERFDS: SpinLock<Vec<Weak<CancelState>>> = SpinLock::new(Vec::new());
+
+/// Cancels the timerfds set with `TFD_TIMER_CANCEL_ON_SET` since the real-time clock is set.
+///
+/// Reading a canceled timerfd fails with [`Errno::ECANCELED`].
+pub(super) fn cancel_on_clock_set() {
+    REALTIME_TIMERFDS.lock().retain(|cancel_state| {
+        let Some(cancel_state) = cancel_state.upgrade() else {
+            return false;
+        };
+
+        if cancel_state.is_cancel_on_set.load(Ordering::Acquire) {
+            cancel_state.is_canceled.store(true, Ordering::Release);
+            cancel_state.pollee.notify(IoEvents::IN);
+        }
+
+        true
+    });
 }
 
 bitflags! {
@@ -59,11 +92,21 @@ impl TimerfdFile {
             create_timer(clockid, expired_fn, ctx)
         }?;
 
+        let cancel_state = Arc::new(CancelState {
+            is_cancel_on_set: AtomicBool::new(false),
+            is_canceled: AtomicBool::new(false),
+            pollee: pollee.clone(),
+        });
+        if clockid == ClockId::CLOCK_REALTIME as clockid_t {
+            REALTIME_TIMERFDS.lock().push(Arc::downgrade(&cancel_state));
+        }
+
         Ok(TimerfdFile {
             timer,
             ticks,
             pollee,
             flags: SpinLock::new(flags),
+            cancel_state,
         })
     }
 
@@ -77,11 +120,28 @@ impl TimerfdFile {
         self.ticks.store(0, Ordering::Release);
     }
 
+    /// Sets whether the timerfd should be canceled when the real-time clock is set.
+    ///
+    /// This has no effect if the timerfd is not based on the real-time clock.
+    pub fn set_cancel_on_set(&self, is_cancel_on_set: bool) {
+        self.cancel_state
+            .is_cancel_on_set
+            .store(is_cancel_on_set, Ordering::Release);
+        self.cancel_state
+            .is_canceled
+            .store(false, Ordering::Release);
+    }
+
     fn is_nonblocking(&self) -> bool {
         self.flags.lock().contains(TFDFlags::TFD_NONBLOCK)
     }
 
     fn try_read(&self, writer: &mut VmWriter) -> Result<()> {
+        if self.cancel_state.is_canceled.swap(false, Ordering::AcqRel) {
+            self.clear_ticks();
+            return_errno_with_message!(Errno::ECANCELED, "the real-time clock has been set");
+        }
+
         let ticks = self.ticks.fetch_and(0, Ordering::AcqRel);
 
         if ticks == 0 {
@@ -96,7 +156,9 @@ impl TimerfdFile {
     fn check_io_events(&self) -> IoEvents {
         let mut events = IoEvents::empty();
 
-        if self.ticks.load(Ordering::Acquire) != 0 {
+        if self.ticks.load(Ordering::Acquire) != 0
+            || self.cancel_state.is_canceled.load(Ordering::Acquire)
+        {
             events |= IoEvents::IN;
         }
 
@@ -174,3 +236,17 @@ impl FileLike for TimerfdFile {
         }
     }
 }
+
+impl Drop for TimerfdFile {
+    fn drop(&mut self) {
+        // Only the timerfds based on the real-time clock are registered.
+        if Arc::weak_count(&self.cancel_state) == 0 {
+            return;
+        }
+
+        let cancel_state = Arc::as_ptr(&self.cancel_state);
+        REALTIME_TIMERFDS
+            .lock()
+            .retain(|weak| weak.as_ptr() != cancel_state);
+    }
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use core::ops::Range;
+
 use ostd::mm::{Infallible, VmSpace};
 
 use crate::prelude::*;
@@ -83,6 +85,25 @@ fn copy_iovs_and_convert<'a, T: 'a>(
     Ok(v.into_boxed_slice())
 }
 
+/// Reads the user-provided IO vectors as address ranges.
+///
+/// Unlike [`VmReaderArray`] and [`VmWriterArray`], the ranges are not required to be in the
+/// current address space, which is useful if the IO vectors describe the memory of another
+/// process. Empty IO vectors are skipped.
+pub fn read_user_io_vecs<'a>(
+    user_space: &'a CurrentUserSpace<'a>,
+    start_addr: Vaddr,
+    count: usize,
+) -> Result<Box<[Range<Vaddr>]>> {
+    copy_iovs_and_convert(user_space, start_addr, count, |iov, _| {
+        let end = iov
+            .base
+            .checked_add(iov.len)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the IO vector range overflows"))?;
+        Ok(iov.base..end)
+    })
+}
+
 /// A collection of [`VmReader`]s.
 ///
 /// Such readers are built from user-provided buffer, so it's always fallible.
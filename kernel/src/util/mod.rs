@@ -6,4 +6,4 @@ pub mod per_cpu_counter;
 pub mod random;
 pub mod ring_buffer;
 
-pub use iovec::{MultiRead, MultiWrite, VmReaderArray, VmWriterArray};
+pub use iovec::{read_user_io_vecs, MultiRead, MultiWrite, VmReaderArray, VmWriterArray};
@@ -4,7 +4,13 @@ use core::cmp::min;
 
 use ostd::task::Task;
 
-use super::{ip::CSocketAddrInet, netlink::CSocketAddrNetlink, unix, vsock::CSocketAddrVm};
+use super::{
+    ip::{CSocketAddrInet, CSocketAddrInet6},
+    netlink::CSocketAddrNetlink,
+    packet::CSocketAddrLl,
+    unix,
+    vsock::CSocketAddrVm,
+};
 use crate::{current_userspace, net::socket::util::SocketAddr, prelude::*};
 
 /// Address family.
@@ -158,6 +164,13 @@ pub fn read_socket_addr_from_user(addr: Vaddr, addr_len: usize) -> Result<Socket
             let (addr, port) = CSocketAddrInet::from_bytes(storage.as_bytes()).into();
             SocketAddr::IPv4(addr, port)
         }
+        Ok(CSocketAddrFamily::AF_INET6) => {
+            if addr_len < size_of::<CSocketAddrInet6>() {
+                return_errno_with_message!(Errno::EINVAL, "the socket address length is too small");
+            }
+            let (addr, port) = CSocketAddrInet6::from_bytes(storage.as_bytes()).into();
+            SocketAddr::IPv6(addr, port)
+        }
         Ok(CSocketAddrFamily::AF_UNIX) => {
             let addr = unix::from_c_bytes(&storage.as_bytes()[..addr_len])?;
             SocketAddr::Unix(addr)
@@ -176,6 +189,13 @@ pub fn read_socket_addr_from_user(addr: Vaddr, addr_len: usize) -> Result<Socket
             let addr = CSocketAddrVm::from_bytes(storage.as_bytes());
             SocketAddr::Vsock(addr.into())
         }
+        Ok(CSocketAddrFamily::AF_PACKET) => {
+            if addr_len < CSocketAddrLl::MIN_LEN {
+                return_errno_with_message!(Errno::EINVAL, "the socket address length is too small");
+            }
+            let addr = CSocketAddrLl::from_bytes(storage.as_bytes());
+            SocketAddr::Packet(addr.into())
+        }
         _ => {
             return_errno_with_message!(
                 Errno::EAFNOSUPPORT,
@@ -238,6 +258,26 @@ pub fn write_socket_addr_with_max_len(
     dest: Vaddr,
     max_len: i32,
 ) -> Result<i32> {
+    write_socket_addr_with(socket_addr, max_len, |bytes| {
+        current_userspace!().write_bytes(dest, &mut VmReader::from(bytes))?;
+        Ok(())
+    })
+}
+
+/// Writes a socket address with the specified function.
+///
+/// This method is the same as [`write_socket_addr_with_max_len`], except that the (possibly
+/// truncated) bytes of the C structure are passed to `write_bytes` instead of being written to
+/// the user space of the current task. This is useful when the socket address is written on
+/// behalf of another task.
+pub fn write_socket_addr_with<F>(
+    socket_addr: &SocketAddr,
+    max_len: i32,
+    write_bytes: F,
+) -> Result<i32>
+where
+    F: FnOnce(&[u8]) -> Result<()>,
+{
     if max_len < 0 {
         return_errno_with_message!(
             Errno::EINVAL,
@@ -246,44 +286,56 @@ pub fn write_socket_addr_with_max_len(
     }
 
     let actual_len = match socket_addr {
-        SocketAddr::IPv4(addr, port) => write_c_socket_address_util::<CSocketAddrInet, _>(
+        SocketAddr::IPv4(addr, port) => write_c_socket_address_util::<CSocketAddrInet, _, _>(
             (*addr, *port),
-            dest,
             max_len as usize,
+            write_bytes,
+        )?,
+        SocketAddr::IPv6(addr, port) => write_c_socket_address_util::<CSocketAddrInet6, _, _>(
+            (*addr, *port),
+            max_len as usize,
+            write_bytes,
         )?,
         SocketAddr::Unix(addr) => unix::into_c_bytes_and(addr, |bytes| {
             let written_len = min(bytes.len(), max_len as _);
-            current_userspace!().write_bytes(dest, &mut VmReader::from(&bytes[..written_len]))?;
+            write_bytes(&bytes[..written_len])?;
             Ok::<usize, Error>(bytes.len())
         })?,
-        SocketAddr::Netlink(addr) => {
-            write_c_socket_address_util::<CSocketAddrNetlink, _>(*addr, dest, max_len as usize)?
-        }
-        SocketAddr::Vsock(addr) => {
-            write_c_socket_address_util::<CSocketAddrVm, _>(*addr, dest, max_len as usize)?
-        }
+        SocketAddr::Netlink(addr) => write_c_socket_address_util::<CSocketAddrNetlink, _, _>(
+            *addr,
+            max_len as usize,
+            write_bytes,
+        )?,
+        SocketAddr::Vsock(addr) => write_c_socket_address_util::<CSocketAddrVm, _, _>(
+            *addr,
+            max_len as usize,
+            write_bytes,
+        )?,
+        SocketAddr::Packet(addr) => write_c_socket_address_util::<CSocketAddrLl, _, _>(
+            *addr,
+            max_len as usize,
+            write_bytes,
+        )?,
     };
 
     Ok(actual_len as i32)
 }
 
-// Utility function to write a C socket address to user space.
-fn write_c_socket_address_util<TCSockAddr, TSockAddr>(
+// Utility function to write a C socket address with the specified function.
+fn write_c_socket_address_util<TCSockAddr, TSockAddr, F>(
     addr: TSockAddr,
-    dest: Vaddr,
     max_len: usize,
+    write_bytes: F,
 ) -> Result<usize>
 where
     TCSockAddr: Pod + From<TSockAddr>,
+    F: FnOnce(&[u8]) -> Result<()>,
 {
     let c_socket_addr = TCSockAddr::from(addr);
     let actual_len = size_of::<TCSockAddr>();
     let written_len = min(actual_len, max_len);
 
-    current_userspace!().write_bytes(
-        dest,
-        &mut VmReader::from(&c_socket_addr.as_bytes()[..written_len]),
-    )?;
+    write_bytes(&c_socket_addr.as_bytes()[..written_len])?;
 
     Ok(actual_len)
 }
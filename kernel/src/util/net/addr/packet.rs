@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use super::family::CSocketAddrFamily;
+use crate::{
+    net::socket::packet::{PacketSocketAddr, MAX_HW_ADDR_LEN},
+    prelude::*,
+};
+
+/// Packet socket address.
+///
+/// See <https://elixir.bootlin.com/linux/v6.0.9/source/include/uapi/linux/if_packet.h#L14>.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+pub(super) struct CSocketAddrLl {
+    /// Address family (AF_PACKET).
+    sll_family: u16,
+    /// Physical-layer protocol in network byte order.
+    sll_protocol: u16,
+    /// Interface number.
+    sll_ifindex: i32,
+    /// ARP hardware type.
+    sll_hatype: u16,
+    /// Packet type.
+    sll_pkttype: u8,
+    /// Length of address.
+    sll_halen: u8,
+    /// Physical-layer address.
+    sll_addr: [u8; MAX_HW_ADDR_LEN],
+}
+
+impl CSocketAddrLl {
+    /// The minimum length of a valid packet socket address.
+    ///
+    /// The hardware address is optional, so it is not counted here.
+    pub(super) const MIN_LEN: usize = core::mem::offset_of!(CSocketAddrLl, sll_addr);
+}
+
+impl From<PacketSocketAddr> for CSocketAddrLl {
+    fn from(value: PacketSocketAddr) -> Self {
+        Self {
+            sll_family: CSocketAddrFamily::AF_PACKET as u16,
+            sll_protocol: value.protocol.to_be(),
+            sll_ifindex: value.ifindex as i32,
+            sll_hatype: value.hatype,
+            sll_pkttype: value.pkt_type,
+            sll_halen: value.halen,
+            sll_addr: value.addr,
+        }
+    }
+}
+
+impl From<CSocketAddrLl> for PacketSocketAddr {
+    fn from(value: CSocketAddrLl) -> Self {
+        debug_assert_eq!(value.sll_family, CSocketAddrFamily::AF_PACKET as u16);
+        Self {
+            protocol: u16::from_be(value.sll_protocol),
+            ifindex: value.sll_ifindex as u32,
+            hatype: value.sll_hatype,
+            pkt_type: value.sll_pkttype,
+            halen: value.sll_halen,
+            addr: value.sll_addr,
+        }
+    }
+}
@@ -5,8 +5,8 @@ mod options;
 mod socket;
 
 pub use addr::{
-    read_socket_addr_from_user, write_socket_addr_to_user, write_socket_addr_with_max_len,
-    CSocketAddrFamily,
+    read_socket_addr_from_user, write_socket_addr_to_user, write_socket_addr_with,
+    write_socket_addr_with_max_len, CSocketAddrFamily,
 };
 pub use options::{new_raw_socket_option, CSocketOptionLevel};
 pub use socket::{CUserMsgHdr, Protocol, SockFlags, SockType, SOCK_TYPE_MASK};
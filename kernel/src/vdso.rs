@@ -28,7 +28,7 @@ use spin::Once;
 
 use crate::{
     syscall::ClockId,
-    time::{clocks::MonotonicClock, timer::Timeout, SystemTime, START_TIME},
+    time::{clocks::MonotonicClock, realtime_offset, timer::Timeout},
     vm::vmo::{Vmo, VmoOptions},
 };
 
@@ -36,7 +36,6 @@ const CLOCK_TAI: usize = 11;
 const VDSO_BASES: usize = CLOCK_TAI + 1;
 const DEFAULT_CLOCK_MODE: VdsoClockMode = VdsoClockMode::Tsc;
 
-static START_SECS_COUNT: Once<u64> = Once::new();
 static VDSO: Once<Arc<Vdso>> = Once::new();
 
 #[derive(Debug, Copy, Clone)]
@@ -134,11 +133,10 @@ impl VdsoData {
     /// Initializes vDSO data based on the default clock source.
     fn init(&mut self) {
         let clocksource = aster_time::default_clocksource();
-        let coeff = clocksource.coeff();
+        let (last_instant, last_cycles, coeff) = clocksource.last_record_with_coeff();
         self.set_clock_mode(DEFAULT_CLOCK_MODE);
-        self.set_coeff(coeff);
+        self.set_coeff(&coeff);
 
-        let (last_instant, last_cycles) = clocksource.last_record();
         self.update_high_res_instant(last_instant, last_cycles);
         self.update_coarse_res_instant(last_instant);
     }
@@ -160,15 +158,15 @@ impl VdsoData {
     fn update_high_res_instant(&mut self, instant: Instant, instant_cycles: u64) {
         self.last_cycles = instant_cycles;
         for clock_id in HIGH_RES_CLOCK_IDS {
-            let secs = if clock_id == ClockId::CLOCK_REALTIME {
-                instant.secs() + START_SECS_COUNT.get().unwrap()
+            let instant = if clock_id == ClockId::CLOCK_REALTIME {
+                instant + realtime_offset()
             } else {
-                instant.secs()
+                instant
             };
 
             self.update_clock_instant(
                 clock_id as usize,
-                secs,
+                instant.secs(),
                 (instant.nanos() as u64) << self.shift as u64,
             );
         }
@@ -176,12 +174,12 @@ impl VdsoData {
 
     fn update_coarse_res_instant(&mut self, instant: Instant) {
         for clock_id in COARSE_RES_CLOCK_IDS {
-            let secs = if clock_id == ClockId::CLOCK_REALTIME_COARSE {
-                instant.secs() + START_SECS_COUNT.get().unwrap()
+            let instant = if clock_id == ClockId::CLOCK_REALTIME_COARSE {
+                instant + realtime_offset()
             } else {
-                instant.secs()
+                instant
             };
-            self.update_clock_instant(clock_id as usize, secs, instant.nanos() as u64);
+            self.update_clock_instant(clock_id as usize, instant.secs(), instant.nanos() as u64);
         }
     }
 }
@@ -257,9 +255,13 @@ impl Vdso {
         }
     }
 
-    fn update_high_res_instant(&self, instant: Instant, instant_cycles: u64) {
+    fn update_high_res_instant(&self, instant: Instant, instant_cycles: u64, coeff: &Coeff) {
         let mut data = self.data.lock();
 
+        // The coeff changes if the frequency of the clock source is adjusted or a time offset
+        // is being slewed. It must be paired with the instant, so that the time calculated in
+        // the vDSO is the same as that in the kernel.
+        data.set_coeff(coeff);
         data.update_high_res_instant(instant, instant_cycles);
 
         // Update begins.
@@ -267,6 +269,12 @@ impl Vdso {
             .write_once(vdso_data_field_offset!(seq), &1)
             .unwrap();
 
+        self.data_frame
+            .write_val(vdso_data_field_offset!(mult), &data.mult)
+            .unwrap();
+        self.data_frame
+            .write_val(vdso_data_field_offset!(shift), &data.shift)
+            .unwrap();
         self.data_frame
             .write_val(vdso_data_field_offset!(last_cycles), &instant_cycles)
             .unwrap();
@@ -323,10 +331,10 @@ impl Vdso {
 }
 
 /// Updates instants with respect to high-resolution clocks in vDSO data.
-fn update_vdso_high_res_instant(instant: Instant, instant_cycles: u64) {
+fn update_vdso_high_res_instant(instant: Instant, instant_cycles: u64, coeff: Coeff) {
     VDSO.get()
         .unwrap()
-        .update_high_res_instant(instant, instant_cycles);
+        .update_high_res_instant(instant, instant_cycles, &coeff);
 }
 
 /// Updates instants with respect to coarse-resolution clocks in vDSO data.
@@ -335,14 +343,18 @@ fn update_vdso_coarse_res_instant() {
     VDSO.get().unwrap().update_coarse_res_instant(instant);
 }
 
-/// Initializes the time duration from 1970-01-01 00:00:00 to the start time.
-fn init_start_secs_count() {
-    let time_duration = START_TIME
-        .get()
-        .unwrap()
-        .duration_since(&SystemTime::UNIX_EPOCH)
-        .unwrap();
-    START_SECS_COUNT.call_once(|| time_duration.as_secs());
+/// Updates instants with respect to the real-time clocks in vDSO data.
+///
+/// This should be called after the real time is set.
+pub(crate) fn update_vdso_realtime() {
+    let Some(vdso) = VDSO.get() else {
+        return;
+    };
+
+    let (instant, instant_cycles, coeff) =
+        aster_time::default_clocksource().last_record_with_coeff();
+    vdso.update_high_res_instant(instant, instant_cycles, &coeff);
+    vdso.update_coarse_res_instant(Instant::from(read_monotonic_time()));
 }
 
 /// Initializes the vDSO singleton.
@@ -352,7 +364,6 @@ fn init_vdso() {
 }
 
 pub(super) fn init() {
-    init_start_secs_count();
     init_vdso();
 
     aster_time::VDSO_DATA_HIGH_RES_UPDATE_FN.call_once(|| Arc::new(update_vdso_high_res_instant));
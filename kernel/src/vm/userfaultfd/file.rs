@@ -0,0 +1,551 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use core::{
+    mem::{offset_of, size_of},
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use align_ext::AlignExt;
+use aster_rights::Full;
+use ostd::mm::{io_util::HasVmReaderWriter, FrameAllocOptions, UFrame};
+
+use super::{Fault, Userfaultfd};
+use crate::{
+    events::IoEvents,
+    fs::{
+        file_handle::FileLike,
+        utils::{CreationFlags, InodeMode, InodeType, IoctlCmd, Metadata, StatusFlags},
+    },
+    prelude::*,
+    process::{
+        signal::{PollHandle, Pollable},
+        Gid, Uid,
+    },
+    time::clocks::RealTimeClock,
+    vm::vmar::{is_userspace_vaddr, Vmar},
+};
+
+bitflags! {
+    /// The flags of `userfaultfd`.
+    pub struct UserfaultfdFlags: u32 {
+        const UFFD_USER_MODE_ONLY = 1;
+        const UFFD_NONBLOCK = StatusFlags::O_NONBLOCK.bits();
+        const UFFD_CLOEXEC  = CreationFlags::O_CLOEXEC.bits();
+    }
+}
+
+/// A file-like object that provides the userfaultfd API.
+///
+/// The file monitors the address space where it is created. Page faults in the mappings
+/// registered with `UFFDIO_REGISTER` are read from the file as messages, and they are resolved
+/// with other ioctls (e.g., `UFFDIO_COPY`).
+pub struct UserfaultfdFile {
+    userfaultfd: Arc<Userfaultfd>,
+    /// The monitored address space.
+    vmar: Vmar<Full>,
+    /// The features enabled by `UFFDIO_API`, or `None` if the API handshake has not been done.
+    features: SpinLock<Option<UffdFeatures>>,
+    is_nonblocking: AtomicBool,
+}
+
+impl UserfaultfdFile {
+    /// Creates a new userfaultfd file that monitors the address space.
+    ///
+    /// If `is_user_mode_only` is true, only the page faults caused by the user-mode code are
+    /// reported.
+    pub fn new(vmar: &Vmar<Full>, is_user_mode_only: bool, is_nonblocking: bool) -> Result<Self> {
+        Ok(Self {
+            userfaultfd: Arc::new(Userfaultfd::new(is_user_mode_only)),
+            vmar: vmar.dup()?,
+            features: SpinLock::new(None),
+            is_nonblocking: AtomicBool::new(is_nonblocking),
+        })
+    }
+
+    fn features(&self) -> Result<UffdFeatures> {
+        self.features.lock().ok_or_else(|| {
+            Error::with_message(Errno::EINVAL, "the userfaultfd API has not been enabled")
+        })
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.is_nonblocking.load(Ordering::Relaxed)
+    }
+
+    fn check_io_events(&self) -> IoEvents {
+        if self.features.lock().is_none() {
+            IoEvents::ERR
+        } else if self.userfaultfd.has_pending_faults() {
+            IoEvents::IN
+        } else {
+            IoEvents::empty()
+        }
+    }
+
+    fn try_read(&self, writer: &mut VmWriter) -> Result<usize> {
+        let features = self.features()?;
+
+        let mut read_len = 0;
+        while writer.avail() >= size_of::<UffdMsg>() {
+            let Some(fault) = self.userfaultfd.take_pending_fault() else {
+                break;
+            };
+
+            // Like Linux, the fault is not returned to the pending queue if the message cannot
+            // be written.
+            let msg = UffdMsg::new_pagefault(&fault, features);
+            match writer.write_val(&msg) {
+                Ok(()) => read_len += size_of::<UffdMsg>(),
+                Err(_) if read_len > 0 => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if read_len == 0 {
+            return_errno_with_message!(Errno::EAGAIN, "no page faults are pending");
+        }
+
+        Ok(read_len)
+    }
+
+    fn handle_api(&self, arg: Vaddr) -> Result<i32> {
+        let user_space = current_userspace!();
+        let mut uffdio_api: UffdioApi = user_space.read_val(arg)?;
+
+        let res = self.enable_api(&uffdio_api);
+        match res {
+            Ok(()) => {
+                uffdio_api.features = UffdFeatures::all().bits();
+                uffdio_api.ioctls = UFFD_API_IOCTLS;
+                user_space.write_val(arg, &uffdio_api)?;
+            }
+            // Like Linux, the structure is zeroed on failures.
+            Err(_) => user_space.write_val(arg, &UffdioApi::new_zeroed())?,
+        }
+
+        res.map(|()| 0)
+    }
+
+    fn enable_api(&self, uffdio_api: &UffdioApi) -> Result<()> {
+        if uffdio_api.api != UFFD_API {
+            return_errno_with_message!(Errno::EINVAL, "the API version is not supported");
+        }
+        let features = UffdFeatures::from_bits(uffdio_api.features)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the features are not supported"))?;
+
+        let mut enabled_features = self.features.lock();
+        if enabled_features.is_some() {
+            return_errno_with_message!(Errno::EINVAL, "the userfaultfd API has been enabled");
+        }
+        *enabled_features = Some(features);
+        drop(enabled_features);
+
+        self.userfaultfd.pollee.invalidate();
+
+        Ok(())
+    }
+
+    fn handle_register(&self, arg: Vaddr) -> Result<i32> {
+        self.features()?;
+
+        let user_space = current_userspace!();
+        let uffdio_register: UffdioRegister = user_space.read_val(arg)?;
+
+        let mode = RegisterMode::from_bits(uffdio_register.mode)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid register mode"))?;
+        if mode != RegisterMode::MISSING {
+            return_errno_with_message!(Errno::EINVAL, "only the missing mode is supported");
+        }
+        let range = uffdio_register.range.to_page_range()?;
+
+        self.vmar.register_userfaultfd(range, &self.userfaultfd)?;
+
+        user_space.write_val(
+            arg + offset_of!(UffdioRegister, ioctls),
+            &UFFD_API_RANGE_IOCTLS,
+        )?;
+
+        Ok(0)
+    }
+
+    fn handle_unregister(&self, arg: Vaddr) -> Result<i32> {
+        self.features()?;
+
+        let uffdio_range: UffdioRange = current_userspace!().read_val(arg)?;
+        let range = uffdio_range.to_page_range()?;
+
+        self.vmar
+            .unregister_userfaultfd(range.clone(), &self.userfaultfd)?;
+
+        // Wake up the faults so that they will be handled as usual.
+        self.userfaultfd.wake(range);
+
+        Ok(0)
+    }
+
+    fn handle_wake(&self, arg: Vaddr) -> Result<i32> {
+        self.features()?;
+
+        let uffdio_range: UffdioRange = current_userspace!().read_val(arg)?;
+        let range = uffdio_range.to_page_range()?;
+
+        self.userfaultfd.wake(range);
+
+        Ok(0)
+    }
+
+    fn handle_copy(&self, arg: Vaddr) -> Result<i32> {
+        self.features()?;
+
+        let user_space = current_userspace!();
+        let uffdio_copy: UffdioCopy = user_space.read_val(arg)?;
+
+        let mode = CopyMode::from_bits(uffdio_copy.mode)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid copy mode"))?;
+        let range = UffdioRange {
+            start: uffdio_copy.dst,
+            len: uffdio_copy.len,
+        }
+        .to_page_range()?;
+        let src = uffdio_copy.src as Vaddr;
+        if src.checked_add(range.len()).is_none() {
+            return_errno_with_message!(Errno::EINVAL, "the source range overflows");
+        }
+
+        let res = self.fill_pages(range.clone(), |page_addr| {
+            let frame = FrameAllocOptions::new().zeroed(false).alloc_frame()?;
+            user_space.read_bytes(src + (page_addr - range.start), &mut frame.writer())?;
+            Ok(frame.into())
+        });
+
+        self.complete_fill(
+            res,
+            range,
+            arg + offset_of!(UffdioCopy, copy),
+            !mode.contains(CopyMode::DONTWAKE),
+        )
+    }
+
+    fn handle_zeropage(&self, arg: Vaddr) -> Result<i32> {
+        self.features()?;
+
+        let uffdio_zeropage: UffdioZeropage = current_userspace!().read_val(arg)?;
+
+        let mode = ZeropageMode::from_bits(uffdio_zeropage.mode)
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "invalid zeropage mode"))?;
+        let range = uffdio_zeropage.range.to_page_range()?;
+
+        let res = self.fill_pages(range.clone(), |_| {
+            Ok(FrameAllocOptions::new().alloc_frame()?.into())
+        });
+
+        self.complete_fill(
+            res,
+            range,
+            arg + offset_of!(UffdioZeropage, zeropage),
+            !mode.contains(ZeropageMode::DONTWAKE),
+        )
+    }
+
+    /// Fills the missing pages in the range with the frames prepared by `prepare_frame`.
+    ///
+    /// The pages are filled one by one, and the filling stops at the first page that cannot be
+    /// filled. So the number of bytes filled is returned. An error is returned only if the first
+    /// page cannot be filled.
+    fn fill_pages<F>(&self, range: Range<Vaddr>, mut prepare_frame: F) -> Result<usize>
+    where
+        F: FnMut(Vaddr) -> Result<UFrame>,
+    {
+        let mut filled_len = 0;
+        for page_addr in range.step_by(PAGE_SIZE) {
+            let res = prepare_frame(page_addr)
+                .and_then(|frame| self.vmar.fill_missing_page(page_addr, frame));
+            match res {
+                Ok(()) => filled_len += PAGE_SIZE,
+                Err(_) if filled_len > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(filled_len)
+    }
+
+    /// Reports the result of filling the pages to user space, and wakes up the faults on the
+    /// filled pages if `should_wake` is true.
+    ///
+    /// Like Linux, the number of bytes filled (or the negative error number) is written to
+    /// `result_addr`. [`Errno::EAGAIN`] is returned if only some of the pages are filled.
+    fn complete_fill(
+        &self,
+        res: Result<usize>,
+        range: Range<Vaddr>,
+        result_addr: Vaddr,
+        should_wake: bool,
+    ) -> Result<i32> {
+        let result = match &res {
+            Ok(filled_len) => *filled_len as i64,
+            Err(err) => -(err.error() as i64),
+        };
+        current_userspace!().write_val(result_addr, &result)?;
+
+        let filled_len = res?;
+        if should_wake {
+            self.userfaultfd.wake(range.start..range.start + filled_len);
+        }
+
+        if filled_len < range.len() {
+            return_errno_with_message!(Errno::EAGAIN, "only some of the pages are filled");
+        }
+
+        Ok(0)
+    }
+}
+
+impl Drop for UserfaultfdFile {
+    fn drop(&mut self) {
+        // Like Linux, the mappings are unregistered when the file is closed, and the faults are
+        // woken up so that they will be handled as usual.
+        self.vmar.unregister_userfaultfd_all(&self.userfaultfd);
+        self.userfaultfd.release();
+    }
+}
+
+impl Pollable for UserfaultfdFile {
+    fn poll(&self, mask: IoEvents, poller: Option<&mut PollHandle>) -> IoEvents {
+        self.userfaultfd
+            .pollee
+            .poll_with(mask, poller, || self.check_io_events())
+    }
+}
+
+impl FileLike for UserfaultfdFile {
+    fn read(&self, writer: &mut VmWriter) -> Result<usize> {
+        if writer.avail() < size_of::<UffdMsg>() {
+            return_errno_with_message!(Errno::EINVAL, "the buffer is too small for a message");
+        }
+
+        if self.is_nonblocking() {
+            self.try_read(writer)
+        } else {
+            self.wait_events(IoEvents::IN, None, || self.try_read(writer))
+        }
+    }
+
+    fn write(&self, _reader: &mut VmReader) -> Result<usize> {
+        return_errno_with_message!(Errno::EINVAL, "userfaultfd files do not support write");
+    }
+
+    fn ioctl(&self, cmd: IoctlCmd, arg: usize) -> Result<i32> {
+        match cmd {
+            IoctlCmd::UFFDIO_API => self.handle_api(arg),
+            IoctlCmd::UFFDIO_REGISTER => self.handle_register(arg),
+            IoctlCmd::UFFDIO_UNREGISTER => self.handle_unregister(arg),
+            IoctlCmd::UFFDIO_WAKE => self.handle_wake(arg),
+            IoctlCmd::UFFDIO_COPY => self.handle_copy(arg),
+            IoctlCmd::UFFDIO_ZEROPAGE => self.handle_zeropage(arg),
+            _ => return_errno_with_message!(Errno::EINVAL, "the ioctl command is not supported"),
+        }
+    }
+
+    fn status_flags(&self) -> StatusFlags {
+        if self.is_nonblocking() {
+            StatusFlags::O_NONBLOCK
+        } else {
+            StatusFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, new_flags: StatusFlags) -> Result<()> {
+        self.is_nonblocking.store(
+            new_flags.contains(StatusFlags::O_NONBLOCK),
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn metadata(&self) -> Metadata {
+        // This is a dummy implementation.
+        // TODO: Add "anonymous inode fs" and link the file to it.
+        let now = RealTimeClock::get().read_time();
+        Metadata {
+            dev: 0,
+            ino: 0,
+            size: 0,
+            blk_size: 0,
+            blocks: 0,
+            atime: now,
+            mtime: now,
+            ctime: now,
+            type_: InodeType::NamedPipe,
+            mode: InodeMode::from_bits_truncate(0o600),
+            nlinks: 1,
+            uid: Uid::new_root(),
+            gid: Gid::new_root(),
+            rdev: 0,
+        }
+    }
+}
+
+/// The version of the userfaultfd API.
+const UFFD_API: u64 = 0xAA;
+
+/// The ioctls that are available after `UFFDIO_API`.
+const UFFD_API_IOCTLS: u64 = 1 << 0x00 /* UFFDIO_REGISTER */
+    | 1 << 0x01 /* UFFDIO_UNREGISTER */
+    | 1 << 0x3F /* UFFDIO_API */;
+
+/// The ioctls that are available on the registered ranges.
+const UFFD_API_RANGE_IOCTLS: u64 = 1 << 0x02 /* UFFDIO_WAKE */
+    | 1 << 0x03 /* UFFDIO_COPY */
+    | 1 << 0x04 /* UFFDIO_ZEROPAGE */;
+
+bitflags! {
+    /// The features of the userfaultfd API.
+    struct UffdFeatures: u64 {
+        /// Reports the thread ID of the faulting thread.
+        const THREAD_ID     = 1 << 8;
+        /// Reports the exact faulting address instead of the page-aligned one.
+        const EXACT_ADDRESS = 1 << 11;
+    }
+}
+
+bitflags! {
+    /// The modes of `UFFDIO_REGISTER`.
+    struct RegisterMode: u64 {
+        const MISSING = 1 << 0;
+        const WP      = 1 << 1;
+        const MINOR   = 1 << 2;
+    }
+}
+
+bitflags! {
+    /// The modes of `UFFDIO_COPY`.
+    struct CopyMode: u64 {
+        /// Does not wake up the faults on the filled pages.
+        const DONTWAKE = 1 << 0;
+    }
+}
+
+bitflags! {
+    /// The modes of `UFFDIO_ZEROPAGE`.
+    struct ZeropageMode: u64 {
+        /// Does not wake up the faults on the filled pages.
+        const DONTWAKE = 1 << 0;
+    }
+}
+
+/// The page fault event.
+const UFFD_EVENT_PAGEFAULT: u8 = 0x12;
+/// The page fault is caused by a write access.
+const UFFD_PAGEFAULT_FLAG_WRITE: u64 = 1 << 0;
+
+/// The message read from a userfaultfd file (i.e., `struct uffd_msg` in Linux).
+///
+/// Only the page fault event is supported, so the union in the Linux structure is flattened.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct UffdMsg {
+    event: u8,
+    reserved1: u8,
+    reserved2: u16,
+    reserved3: u32,
+    flags: u64,
+    address: u64,
+    ptid: u32,
+    reserved4: u32,
+}
+
+impl UffdMsg {
+    fn new_pagefault(fault: &Fault, features: UffdFeatures) -> Self {
+        let flags = if fault.is_write {
+            UFFD_PAGEFAULT_FLAG_WRITE
+        } else {
+            0
+        };
+        let address = if features.contains(UffdFeatures::EXACT_ADDRESS) {
+            fault.address
+        } else {
+            fault.address.align_down(PAGE_SIZE)
+        };
+        let ptid = if features.contains(UffdFeatures::THREAD_ID) {
+            fault.tid
+        } else {
+            0
+        };
+
+        Self {
+            event: UFFD_EVENT_PAGEFAULT,
+            reserved1: 0,
+            reserved2: 0,
+            reserved3: 0,
+            flags,
+            address: address as u64,
+            ptid,
+            reserved4: 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct UffdioApi {
+    api: u64,
+    features: u64,
+    ioctls: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct UffdioRange {
+    start: u64,
+    len: u64,
+}
+
+impl UffdioRange {
+    /// Converts the range to a non-empty range of user space pages.
+    fn to_page_range(self) -> Result<Range<Vaddr>> {
+        let start = self.start as Vaddr;
+        let len = self.len as usize;
+
+        if start % PAGE_SIZE != 0 || len % PAGE_SIZE != 0 {
+            return_errno_with_message!(Errno::EINVAL, "the range is not page-aligned");
+        }
+        if len == 0 {
+            return_errno_with_message!(Errno::EINVAL, "the range is empty");
+        }
+
+        let end = start
+            .checked_add(len)
+            .filter(|end| is_userspace_vaddr(start) && is_userspace_vaddr(end - 1))
+            .ok_or_else(|| Error::with_message(Errno::EINVAL, "the range is not in user space"))?;
+
+        Ok(start..end)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct UffdioRegister {
+    range: UffdioRange,
+    mode: u64,
+    ioctls: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct UffdioCopy {
+    dst: u64,
+    src: u64,
+    len: u64,
+    mode: u64,
+    copy: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod)]
+struct UffdioZeropage {
+    range: UffdioRange,
+    mode: u64,
+    zeropage: i64,
+}
@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Userfaultfd, which allows page faults to be handled in user space.
+//!
+//! Memory mappings can be registered with a userfaultfd. When a thread faults on a missing page
+//! in a registered mapping, the page fault is reported to the userfaultfd as a message, and the
+//! thread waits until the fault is resolved. A handler thread reads the message from the
+//! userfaultfd file, fills the page (e.g., with `UFFDIO_COPY`), and wakes up the faulting thread.
+//!
+//! Only the missing mode (i.e., `UFFDIO_REGISTER_MODE_MISSING`) on private anonymous mappings is
+//! supported for now.
+//!
+//! Reference: <https://docs.kernel.org/admin-guide/mm/userfaultfd.html>
+
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use align_ext::AlignExt;
+use ostd::{sync::WaitQueue, task::Task};
+
+pub use self::file::{UserfaultfdFile, UserfaultfdFlags};
+use crate::{
+    events::IoEvents,
+    prelude::*,
+    process::{posix_thread::AsPosixThread, signal::Pollee},
+    thread::Tid,
+};
+
+mod file;
+
+/// A userfaultfd context, with which memory mappings can be registered.
+///
+/// The page faults on the missing pages in the registered mappings are reported to the context.
+/// The faults wait in the context until they are woken up by the handler, or the userfaultfd
+/// file is closed.
+pub struct Userfaultfd {
+    faults: SpinLock<FaultQueue>,
+    wait_queue: WaitQueue,
+    pollee: Pollee,
+    /// Whether only the page faults caused by the user-mode code are reported.
+    ///
+    /// If so, the page faults caused by the kernel (e.g., in system calls) on the missing pages
+    /// fail instead.
+    is_user_mode_only: bool,
+}
+
+impl Debug for Userfaultfd {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Userfaultfd").finish_non_exhaustive()
+    }
+}
+
+struct FaultQueue {
+    /// The faults that have not been read by the handler.
+    pending: VecDeque<Arc<Fault>>,
+    /// The faults that have been read by the handler but have not been woken up.
+    waiting: Vec<Arc<Fault>>,
+    /// Whether the userfaultfd file has been closed.
+    ///
+    /// If so, no more faults will be reported.
+    is_released: bool,
+}
+
+/// A page fault reported to a [`Userfaultfd`].
+struct Fault {
+    /// The faulting address.
+    address: Vaddr,
+    /// Whether the fault is caused by a write access.
+    is_write: bool,
+    /// The thread ID of the faulting thread.
+    tid: Tid,
+    is_woken: AtomicBool,
+}
+
+impl Userfaultfd {
+    fn new(is_user_mode_only: bool) -> Self {
+        Self {
+            faults: SpinLock::new(FaultQueue {
+                pending: VecDeque::new(),
+                waiting: Vec::new(),
+                is_released: false,
+            }),
+            wait_queue: WaitQueue::new(),
+            pollee: Pollee::new(),
+            is_user_mode_only,
+        }
+    }
+
+    /// Returns whether only the page faults caused by the user-mode code are reported.
+    pub(super) fn is_user_mode_only(&self) -> bool {
+        self.is_user_mode_only
+    }
+
+    /// Reports a page fault on a missing page at `address`.
+    ///
+    /// The returned [`ReportedFault`] can be used to wait for the fault to be woken up. `None`
+    /// is returned if the userfaultfd file has been closed, in which case the page fault should
+    /// be handled as usual.
+    pub(super) fn report_fault(
+        self: &Arc<Self>,
+        address: Vaddr,
+        is_write: bool,
+    ) -> Option<ReportedFault> {
+        let tid = Task::current()
+            .and_then(|task| {
+                task.as_posix_thread()
+                    .map(|posix_thread| posix_thread.tid())
+            })
+            .unwrap_or(0);
+        let fault = Arc::new(Fault {
+            address,
+            is_write,
+            tid,
+            is_woken: AtomicBool::new(false),
+        });
+
+        let mut faults = self.faults.lock();
+        if faults.is_released {
+            return None;
+        }
+        faults.pending.push_back(fault.clone());
+        drop(faults);
+
+        self.pollee.notify(IoEvents::IN);
+
+        Some(ReportedFault {
+            userfaultfd: self.clone(),
+            fault,
+        })
+    }
+
+    /// Takes a pending fault so that it can be read by the handler.
+    fn take_pending_fault(&self) -> Option<Arc<Fault>> {
+        let mut faults = self.faults.lock();
+
+        let fault = faults.pending.pop_front()?;
+        faults.waiting.push(fault.clone());
+        drop(faults);
+
+        self.pollee.invalidate();
+
+        Some(fault)
+    }
+
+    fn has_pending_faults(&self) -> bool {
+        !self.faults.lock().pending.is_empty()
+    }
+
+    /// Wakes up the faults whose faulting pages are in the range.
+    fn wake(&self, range: Range<Vaddr>) {
+        let is_in_range = |fault: &Arc<Fault>| range.contains(&fault.address.align_down(PAGE_SIZE));
+
+        let mut faults = self.faults.lock();
+        let FaultQueue {
+            pending, waiting, ..
+        } = &mut *faults;
+        for fault in pending.iter().chain(waiting.iter()) {
+            if is_in_range(fault) {
+                fault.is_woken.store(true, Ordering::Release);
+            }
+        }
+        pending.retain(|fault| !is_in_range(fault));
+        waiting.retain(|fault| !is_in_range(fault));
+        drop(faults);
+
+        self.pollee.invalidate();
+        self.wait_queue.wake_all();
+    }
+
+    /// Wakes up all the faults and stops reporting new faults.
+    ///
+    /// This is called when the userfaultfd file is closed.
+    fn release(&self) {
+        let mut faults = self.faults.lock();
+        let FaultQueue {
+            pending,
+            waiting,
+            is_released,
+        } = &mut *faults;
+        *is_released = true;
+        for fault in pending.drain(..).chain(waiting.drain(..)) {
+            fault.is_woken.store(true, Ordering::Release);
+        }
+        drop(faults);
+
+        self.wait_queue.wake_all();
+    }
+
+    fn remove_fault(&self, fault: &Arc<Fault>) {
+        let mut faults = self.faults.lock();
+        if let Some(pos) = faults.pending.iter().position(|f| Arc::ptr_eq(f, fault)) {
+            faults.pending.remove(pos);
+            drop(faults);
+            self.pollee.invalidate();
+        } else if let Some(pos) = faults.waiting.iter().position(|f| Arc::ptr_eq(f, fault)) {
+            faults.waiting.swap_remove(pos);
+        }
+    }
+}
+
+/// A page fault that has been reported to a [`Userfaultfd`].
+///
+/// The fault is withdrawn from the userfaultfd when this object is dropped.
+pub struct ReportedFault {
+    userfaultfd: Arc<Userfaultfd>,
+    fault: Arc<Fault>,
+}
+
+impl ReportedFault {
+    /// Waits until the fault is woken up.
+    ///
+    /// After this method returns, the page fault should be handled again, since the faulting
+    /// page may or may not have been filled.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EINTR`] if the waiting is interrupted by a signal.
+    pub fn wait(self) -> Result<()> {
+        self.userfaultfd
+            .wait_queue
+            .pause_until(|| self.fault.is_woken.load(Ordering::Acquire).then_some(()))
+    }
+}
+
+impl Drop for ReportedFault {
+    fn drop(&mut self) {
+        if !self.fault.is_woken.load(Ordering::Acquire) {
+            self.userfaultfd.remove_fault(&self.fault);
+        }
+    }
+}
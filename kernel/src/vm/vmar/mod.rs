@@ -14,24 +14,29 @@ use aster_rights::Rights;
 use ostd::{
     cpu::CpuId,
     mm::{
-        tlb::TlbFlushOp, vm_space::CursorMut, PageFlags, PageProperty, VmSpace, MAX_USERSPACE_VADDR,
+        tlb::TlbFlushOp, vm_space::CursorMut, PageFlags, PageProperty, UFrame, VmIo, VmSpace,
+        MAX_USERSPACE_VADDR,
     },
     sync::RwMutexReadGuard,
-    task::disable_preempt,
+    task::{disable_preempt, Task},
 };
 
 use self::{
     interval_set::{Interval, IntervalSet},
-    vm_mapping::{MappedVmo, VmMapping},
+    vm_mapping::{MappedVmo, RemoteAccess, VmMapping},
 };
 use crate::{
-    fs::utils::Inode,
+    fs::{path::Path, utils::Inode},
+    ipc::shm::ShmAttachment,
     prelude::*,
-    process::{Process, ResourceType},
+    process::{
+        credentials::capabilities::CapSet, posix_thread::AsPosixThread, Process, ResourceType,
+    },
     thread::exception::PageFaultInfo,
-    util::per_cpu_counter::PerCpuCounter,
+    util::{per_cpu_counter::PerCpuCounter, MultiRead, MultiWrite},
     vm::{
         perms::VmPerms,
+        userfaultfd::Userfaultfd,
         vmo::{Vmo, VmoRightsOp},
     },
 };
@@ -129,6 +134,327 @@ impl<R> Vmar<R> {
     ) -> Result<Vaddr> {
         self.0.remap(old_addr, old_size, new_addr, new_size)
     }
+
+    /// Reads bytes from the VMAR on behalf of another process.
+    ///
+    /// Unlike accessing the memory via the activated [`VmSpace`], this method works on VMARs of
+    /// any processes. Pages that have not been mapped will be faulted in.
+    pub fn read_remote(&self, vaddr: Vaddr, buf: &mut [u8]) -> Result<()> {
+        self.0.access_remote(
+            vaddr,
+            buf.len(),
+            RemoteAccess::Read,
+            |frame, offset, buf_range| {
+                frame.read_bytes(offset, &mut buf[buf_range])?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Writes bytes to the VMAR on behalf of another process.
+    ///
+    /// Pages in private mappings can be written even if the mappings are not writable, so that
+    /// debuggers can insert breakpoints into read-only code.
+    pub fn write_remote(&self, vaddr: Vaddr, buf: &[u8]) -> Result<()> {
+        self.0.access_remote(
+            vaddr,
+            buf.len(),
+            RemoteAccess::ForcedWrite,
+            |frame, offset, buf_range| {
+                frame.write_bytes(offset, &buf[buf_range])?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Reads at most `len` bytes from the VMAR to the writer on behalf of another process.
+    ///
+    /// The bytes are copied page by page, and the reading stops at the first page that cannot
+    /// be accessed. So the number of bytes read is returned, which will be zero if the first
+    /// page cannot be accessed.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EFAULT`] if no bytes can be written to the writer.
+    pub fn read_remote_to(
+        &self,
+        vaddr: Vaddr,
+        len: usize,
+        writer: &mut dyn MultiWrite,
+    ) -> Result<usize> {
+        let len = len.min(writer.sum_lens());
+        let mut buf = vec![0u8; PAGE_SIZE.min(len)];
+
+        let mut read_len = 0;
+        while read_len < len {
+            let Some(addr) = vaddr.checked_add(read_len) else {
+                break;
+            };
+            let chunk = &mut buf[..(PAGE_SIZE - addr % PAGE_SIZE).min(len - read_len)];
+            if self.read_remote(addr, chunk).is_err() {
+                break;
+            }
+
+            let chunk_len = chunk.len();
+            let written_len = match writer.write(&mut VmReader::from(&*chunk)) {
+                Ok(written_len) => written_len,
+                Err(_) if read_len > 0 => break,
+                Err(err) => return Err(err),
+            };
+            read_len += written_len;
+            if written_len < chunk_len {
+                break;
+            }
+        }
+
+        Ok(read_len)
+    }
+
+    /// Writes at most `len` bytes from the reader to the VMAR on behalf of another process.
+    ///
+    /// The bytes are copied page by page, and the writing stops at the first page that cannot
+    /// be accessed. So the number of bytes written is returned, which will be zero if the first
+    /// page cannot be accessed.
+    ///
+    /// If `is_forced` is true, pages in private mappings can be written even if the mappings are
+    /// not writable, similar to [`Self::write_remote`].
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EFAULT`] if no bytes can be read from the reader.
+    pub fn write_remote_from(
+        &self,
+        vaddr: Vaddr,
+        len: usize,
+        reader: &mut dyn MultiRead,
+        is_forced: bool,
+    ) -> Result<usize> {
+        let len = len.min(reader.sum_lens());
+        let mut buf = vec![0u8; PAGE_SIZE.min(len)];
+        let access = if is_forced {
+            RemoteAccess::ForcedWrite
+        } else {
+            RemoteAccess::Write
+        };
+
+        let mut written_len = 0;
+        while written_len < len {
+            let Some(addr) = vaddr.checked_add(written_len) else {
+                break;
+            };
+            let chunk = &mut buf[..(PAGE_SIZE - addr % PAGE_SIZE).min(len - written_len)];
+            let chunk_len = match reader.read(&mut VmWriter::from(&mut *chunk)) {
+                Ok(0) => break,
+                Ok(chunk_len) => chunk_len,
+                Err(_) if written_len > 0 => break,
+                Err(err) => return Err(err),
+            };
+
+            let chunk = &chunk[..chunk_len];
+            let res = self
+                .0
+                .access_remote(addr, chunk_len, access, |frame, offset, buf_range| {
+                    frame.write_bytes(offset, &chunk[buf_range])?;
+                    Ok(())
+                });
+            if res.is_err() {
+                break;
+            }
+            written_len += chunk_len;
+        }
+
+        Ok(written_len)
+    }
+
+    /// Faults in at most `len` bytes of the VMAR for writing on behalf of another process.
+    ///
+    /// The pages are faulted in page by page, and the faulting stops at the first page that
+    /// cannot be written. So the number of bytes that can be written is returned, which will be
+    /// zero if the first page cannot be written. Unlike [`Self::write_remote`], pages in
+    /// mappings that are not writable cannot be written.
+    pub fn fault_in_writable_remote(&self, vaddr: Vaddr, len: usize) -> usize {
+        let mut faulted_len = 0;
+        while faulted_len < len {
+            let Some(addr) = vaddr.checked_add(faulted_len) else {
+                break;
+            };
+            let chunk_len = (PAGE_SIZE - addr % PAGE_SIZE).min(len - faulted_len);
+            let res = self
+                .0
+                .access_remote(addr, chunk_len, RemoteAccess::Write, |_, _, _| Ok(()));
+            if res.is_err() {
+                break;
+            }
+            faulted_len += chunk_len;
+        }
+
+        faulted_len
+    }
+
+    /// Reads a page from the VMAR to write it to a core dump.
+    ///
+    /// Unlike [`Self::read_remote`], this method does not fault in pages that have never been
+    /// touched. For such pages, `false` is returned and `buf` is left unchanged.
+    pub fn read_dump_page(&self, page_addr: Vaddr, buf: &mut [u8]) -> Result<bool> {
+        debug_assert!(page_addr % PAGE_SIZE == 0);
+        debug_assert!(buf.len() == PAGE_SIZE);
+
+        let inner = self.0.inner.read();
+        let Some(vm_mapping) = inner.vm_mappings.find_one(&page_addr) else {
+            return_errno_with_message!(Errno::EFAULT, "the address is not mapped");
+        };
+        let Some(frame) = vm_mapping.get_frame_for_dump(&self.0.vm_space, page_addr)? else {
+            return Ok(false);
+        };
+        frame.read_bytes(0, buf)?;
+
+        Ok(true)
+    }
+
+    /// Changes whether the mappings in the specified range are excluded from core dumps.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOMEM`] if the range is not fully mapped, but the
+    /// mappings within the range are still changed.
+    pub fn set_dont_dump(&self, range: Range<usize>, dont_dump: bool) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        self.0.set_dont_dump(range, dont_dump)
+    }
+
+    /// Locks the mappings in the specified range in memory.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOMEM`] if the locked memory would exceed
+    /// `RLIMIT_MEMLOCK`, or if the range is not fully mapped. In the latter case, the
+    /// mappings within the range are still locked.
+    pub fn lock(&self, range: Range<usize>, mode: LockMode) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        self.0.lock(range, mode)
+    }
+
+    /// Unlocks the mappings in the specified range.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOMEM`] if the range is not fully mapped, but the
+    /// mappings within the range are still unlocked.
+    pub fn unlock(&self, range: Range<usize>) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        self.0.unlock(range)
+    }
+
+    /// Locks all the mappings in memory.
+    ///
+    /// If `current` is `Some`, the existing mappings are locked with the mode. If `future` is
+    /// `Some`, the mappings created later are locked with the mode; otherwise, they are not
+    /// locked, even if they were requested to be locked by an earlier call.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOMEM`] if the existing mappings need to be locked but the
+    /// total size of them exceeds `RLIMIT_MEMLOCK`.
+    pub fn lock_all(&self, current: Option<LockMode>, future: Option<LockMode>) -> Result<()> {
+        self.0.lock_all(current, future)
+    }
+
+    /// Unlocks all the mappings, and stops locking the mappings created later.
+    pub fn unlock_all(&self) {
+        self.0.unlock_all()
+    }
+
+    /// Queries whether the pages in the specified range are resident in memory.
+    ///
+    /// Each byte in `residency` corresponds to a page in the range. The byte is set to one if
+    /// the page is mapped in the page table, and zero otherwise.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOMEM`] if the range is not fully mapped.
+    pub fn query_residency(&self, range: Range<usize>, residency: &mut [u8]) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        assert_eq!(range.len() / PAGE_SIZE, residency.len());
+        self.0.query_residency(range, residency)
+    }
+
+    /// Registers the mappings in the specified range with a userfaultfd.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EINVAL`] if there are no mappings in the range or some
+    /// mappings in the range cannot be registered, or [`Errno::EBUSY`] if some mappings have
+    /// been registered with another userfaultfd. In these cases, no mappings are registered.
+    pub fn register_userfaultfd(
+        &self,
+        range: Range<usize>,
+        userfaultfd: &Arc<Userfaultfd>,
+    ) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        self.0.register_userfaultfd(range, userfaultfd)
+    }
+
+    /// Unregisters the mappings in the specified range from a userfaultfd.
+    ///
+    /// The mappings that are not registered with the userfaultfd are left unchanged.
+    ///
+    /// The range's start and end addresses must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EINVAL`] if there are no mappings in the range or some
+    /// mappings in the range cannot be registered. In these cases, no mappings are unregistered.
+    pub fn unregister_userfaultfd(
+        &self,
+        range: Range<usize>,
+        userfaultfd: &Arc<Userfaultfd>,
+    ) -> Result<()> {
+        assert!(range.start % PAGE_SIZE == 0);
+        assert!(range.end % PAGE_SIZE == 0);
+        self.0.unregister_userfaultfd(range, userfaultfd)
+    }
+
+    /// Unregisters all the mappings from a userfaultfd.
+    pub fn unregister_userfaultfd_all(&self, userfaultfd: &Arc<Userfaultfd>) {
+        self.0.unregister_userfaultfd_all(userfaultfd)
+    }
+
+    /// Fills a missing page with the frame to resolve the page faults reported to a userfaultfd.
+    ///
+    /// The address must be page-aligned.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::ENOENT`] if the page is not in a mapping registered with a
+    /// userfaultfd, or [`Errno::EEXIST`] if the page has already been mapped.
+    pub fn fill_missing_page(&self, page_addr: Vaddr, frame: UFrame) -> Result<()> {
+        assert!(page_addr % PAGE_SIZE == 0);
+        self.0.fill_missing_page(page_addr, frame)
+    }
+}
+
+/// The mode of locking mappings in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// The pages are faulted in when the mappings are locked.
+    Populate,
+    /// The pages are faulted in when they are accessed (i.e., `MLOCK_ONFAULT`).
+    OnFault,
 }
 
 pub(super) struct Vmar_ {
@@ -153,6 +479,12 @@ struct VmarInner {
     vm_mappings: IntervalSet<Vaddr, VmMapping>,
     /// The total mapped memory in bytes.
     total_vm: usize,
+    /// The total locked memory in bytes.
+    locked_vm: usize,
+    /// The mode of locking the new mappings, if they should be locked.
+    ///
+    /// This is set by `mlockall(MCL_FUTURE)`.
+    lock_future: Option<LockMode>,
 }
 
 impl VmarInner {
@@ -160,6 +492,8 @@ impl VmarInner {
         Self {
             vm_mappings: IntervalSet::new(),
             total_vm: 0,
+            locked_vm: 0,
+            lock_future: None,
         }
     }
 
@@ -187,6 +521,14 @@ impl VmarInner {
         Ok(())
     }
 
+    /// Returns whether the calling process may lock the passed size of extra
+    /// memory.
+    fn may_lock_extra_size(&self, extra_size: usize) -> bool {
+        self.locked_vm
+            .checked_add(extra_size)
+            .is_some_and(may_lock_memory)
+    }
+
     /// Checks whether `addr..addr + size` is covered by a single `VmMapping`,
     /// and returns the address of the single `VmMapping` if successful.
     fn check_lies_in_single_mapping(&self, addr: Vaddr, size: usize) -> Result<Vaddr> {
@@ -210,6 +552,9 @@ impl VmarInner {
     /// Make sure the insertion doesn't exceed address space limit.
     fn insert_without_try_merge(&mut self, vm_mapping: VmMapping) {
         self.total_vm += vm_mapping.map_size();
+        if vm_mapping.is_locked() {
+            self.locked_vm += vm_mapping.map_size();
+        }
         self.vm_mappings.insert(vm_mapping);
     }
 
@@ -222,6 +567,9 @@ impl VmarInner {
     /// Make sure the insertion doesn't exceed address space limit.
     fn insert_try_merge(&mut self, vm_mapping: VmMapping) {
         self.total_vm += vm_mapping.map_size();
+        if vm_mapping.is_locked() {
+            self.locked_vm += vm_mapping.map_size();
+        }
         let mut vm_mapping = vm_mapping;
         let addr = vm_mapping.map_to_addr();
 
@@ -248,9 +596,52 @@ impl VmarInner {
     fn remove(&mut self, key: &Vaddr) -> Option<VmMapping> {
         let vm_mapping = self.vm_mappings.remove(key)?;
         self.total_vm -= vm_mapping.map_size();
+        if vm_mapping.is_locked() {
+            self.locked_vm -= vm_mapping.map_size();
+        }
         Some(vm_mapping)
     }
 
+    /// Updates the mappings that intersect with the provided range.
+    ///
+    /// Only the mappings that satisfy `needs_update` are updated. They are
+    /// split first, so the parts outside the range are left unchanged.
+    ///
+    /// Returns the total size of the mapped pages within the range.
+    fn update_mappings(
+        &mut self,
+        range: &Range<Vaddr>,
+        needs_update: impl Fn(&VmMapping) -> bool,
+        update: impl Fn(VmMapping) -> VmMapping,
+    ) -> usize {
+        let mut mapped_size = 0;
+        let mut mappings_to_update = Vec::new();
+
+        for vm_mapping in self.vm_mappings.find(range) {
+            mapped_size += get_intersected_range(range, &vm_mapping.range()).len();
+            if needs_update(vm_mapping) {
+                mappings_to_update.push(vm_mapping.map_to_addr());
+            }
+        }
+
+        for vm_mapping_addr in mappings_to_update {
+            let vm_mapping = self.remove(&vm_mapping_addr).unwrap();
+            let intersected_range = get_intersected_range(range, &vm_mapping.range());
+
+            let (left, taken, right) = vm_mapping.split_range(&intersected_range);
+            if let Some(left) = left {
+                self.insert_without_try_merge(left);
+            }
+            if let Some(right) = right {
+                self.insert_without_try_merge(right);
+            }
+
+            self.insert_try_merge(update(taken));
+        }
+
+        mapped_size
+    }
+
     /// Finds a set of [`VmMapping`]s that intersect with the provided range.
     fn query(&self, range: &Range<Vaddr>) -> impl Iterator<Item = &VmMapping> {
         self.vm_mappings.find(range)
@@ -408,6 +799,9 @@ impl VmarInner {
         debug_assert_eq!(last_mapping.map_end(), old_map_end);
 
         self.check_extra_size_fits_rlimit(new_map_end - old_map_end)?;
+        if last_mapping.is_locked() && !self.may_lock_extra_size(new_map_end - old_map_end) {
+            return_errno_with_message!(Errno::EAGAIN, "locked memory limit overflow");
+        }
         let last_mapping = self.remove(&last_mapping_addr).unwrap();
         let last_mapping = last_mapping.enlarge(new_map_end - old_map_end);
         self.insert_try_merge(last_mapping);
@@ -423,6 +817,43 @@ pub fn is_userspace_vaddr(vaddr: Vaddr) -> bool {
     (ROOT_VMAR_LOWEST_ADDR..ROOT_VMAR_CAP_ADDR).contains(&vaddr)
 }
 
+/// Returns whether the mapping is registered with the userfaultfd.
+fn is_registered_with(vm_mapping: &VmMapping, userfaultfd: &Arc<Userfaultfd>) -> bool {
+    vm_mapping
+        .userfaultfd()
+        .is_some_and(|registered| Arc::ptr_eq(registered, userfaultfd))
+}
+
+/// Returns whether the calling process may lock the passed size of memory in
+/// total.
+///
+/// The size is limited by `RLIMIT_MEMLOCK` unless the calling thread has the
+/// `CAP_IPC_LOCK` capability.
+fn may_lock_memory(locked_size: usize) -> bool {
+    let Some(process) = Process::current() else {
+        return true;
+    };
+
+    let has_ipc_lock = Task::current()
+        .and_then(|task| {
+            task.as_posix_thread().map(|posix_thread| {
+                let credentials = posix_thread.credentials();
+                credentials.euid().is_root()
+                    || credentials.effective_capset().contains(CapSet::IPC_LOCK)
+            })
+        })
+        .unwrap_or(false);
+    if has_ipc_lock {
+        return true;
+    }
+
+    let rlimit_memlock = process
+        .resource_limits()
+        .get_rlimit(ResourceType::RLIMIT_MEMLOCK)
+        .get_cur();
+    locked_size as u64 <= rlimit_memlock
+}
+
 impl Interval<usize> for Arc<Vmar_> {
     fn range(&self) -> Range<usize> {
         self.base..(self.base + self.size)
@@ -510,6 +941,146 @@ impl Vmar_ {
         Ok(())
     }
 
+    fn set_dont_dump(&self, range: Range<usize>, dont_dump: bool) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        let mapped_size = inner.update_mappings(
+            &range,
+            |vm_mapping| vm_mapping.dont_dump() != dont_dump,
+            |vm_mapping| vm_mapping.set_dont_dump(dont_dump),
+        );
+        if mapped_size < range.len() {
+            return_errno_with_message!(Errno::ENOMEM, "the range is not fully mapped");
+        }
+
+        Ok(())
+    }
+
+    fn lock(&self, range: Range<usize>, mode: LockMode) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        let extra_size: usize = inner
+            .vm_mappings
+            .find(&range)
+            .filter(|vm_mapping| !vm_mapping.is_locked())
+            .map(|vm_mapping| get_intersected_range(&range, &vm_mapping.range()).len())
+            .sum();
+        if !inner.may_lock_extra_size(extra_size) {
+            return_errno_with_message!(Errno::ENOMEM, "locked memory limit overflow");
+        }
+
+        let mapped_size = inner.update_mappings(
+            &range,
+            |vm_mapping| !vm_mapping.is_locked(),
+            |vm_mapping| vm_mapping.set_locked(true),
+        );
+        if mapped_size < range.len() {
+            return_errno_with_message!(Errno::ENOMEM, "the range is not fully mapped");
+        }
+
+        drop(inner);
+        if mode == LockMode::Populate {
+            self.populate(range)?;
+        }
+
+        Ok(())
+    }
+
+    fn unlock(&self, range: Range<usize>) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        let mapped_size = inner.update_mappings(
+            &range,
+            |vm_mapping| vm_mapping.is_locked(),
+            |vm_mapping| vm_mapping.set_locked(false),
+        );
+        if mapped_size < range.len() {
+            return_errno_with_message!(Errno::ENOMEM, "the range is not fully mapped");
+        }
+
+        Ok(())
+    }
+
+    fn lock_all(&self, current: Option<LockMode>, future: Option<LockMode>) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        // Like Linux, the limit is checked against the total size of the mappings.
+        if current.is_some() && !may_lock_memory(inner.total_vm) {
+            return_errno_with_message!(Errno::ENOMEM, "locked memory limit overflow");
+        }
+
+        inner.lock_future = future;
+        let Some(mode) = current else {
+            return Ok(());
+        };
+
+        let full_range = self.base..self.base + self.size;
+        inner.update_mappings(
+            &full_range,
+            |vm_mapping| !vm_mapping.is_locked(),
+            |vm_mapping| vm_mapping.set_locked(true),
+        );
+
+        drop(inner);
+        if mode == LockMode::Populate {
+            // Like Linux, errors are ignored when populating all the mappings.
+            let _ = self.populate(full_range);
+        }
+
+        Ok(())
+    }
+
+    fn unlock_all(&self) {
+        let mut inner = self.inner.write();
+
+        inner.lock_future = None;
+
+        let full_range = self.base..self.base + self.size;
+        inner.update_mappings(
+            &full_range,
+            |vm_mapping| vm_mapping.is_locked(),
+            |vm_mapping| vm_mapping.set_locked(false),
+        );
+    }
+
+    /// Faults in the pages of the mappings in the range.
+    fn populate(&self, range: Range<usize>) -> Result<()> {
+        let inner = self.inner.read();
+        let mut rss_delta = RssDelta::new(self);
+
+        for vm_mapping in inner.vm_mappings.find(&range) {
+            let intersected_range = get_intersected_range(&range, &vm_mapping.range());
+            vm_mapping.populate(&self.vm_space, intersected_range, &mut rss_delta)?;
+        }
+
+        Ok(())
+    }
+
+    fn query_residency(&self, range: Range<usize>, residency: &mut [u8]) -> Result<()> {
+        let inner = self.inner.read();
+
+        if inner.count_overlap_size(range.clone()) < range.len() {
+            return_errno_with_message!(Errno::ENOMEM, "the range is not fully mapped");
+        }
+
+        residency.fill(0);
+
+        // Keep `inner` locked so that the mappings cannot be changed.
+        let preempt_guard = disable_preempt();
+        let mut cursor = self.vm_space.cursor(&preempt_guard, &range)?;
+        while let Some(mapped_va) = cursor.find_next(range.end - cursor.virt_addr()) {
+            residency[(mapped_va - range.start) / PAGE_SIZE] = 1;
+
+            let next_va = mapped_va + PAGE_SIZE;
+            if next_va >= range.end {
+                break;
+            }
+            cursor.jump(next_va)?;
+        }
+
+        Ok(())
+    }
+
     /// Handles user space page fault, if the page fault is successfully handled, return Ok(()).
     pub fn handle_page_fault(&self, page_fault_info: &PageFaultInfo) -> Result<()> {
         let address = page_fault_info.address;
@@ -517,22 +1088,176 @@ impl Vmar_ {
             return_errno_with_message!(Errno::EACCES, "page fault addr is not in current vmar");
         }
 
-        let inner = self.inner.read();
+        loop {
+            let inner = self.inner.read();
 
-        if let Some(vm_mapping) = inner.vm_mappings.find_one(&address) {
+            let Some(vm_mapping) = inner.vm_mappings.find_one(&address) else {
+                return_errno_with_message!(Errno::EACCES, "page fault addr is not in current vmar");
+            };
             debug_assert!(vm_mapping.range().contains(&address));
 
+            // If the fault is reported to the userfaultfd, wait for it to be resolved without
+            // holding the lock, and then handle the fault again.
+            if let Some(fault) = vm_mapping.report_userfault(&self.vm_space, page_fault_info)? {
+                drop(inner);
+                fault.wait()?;
+                continue;
+            }
+
             let mut rss_delta = RssDelta::new(self);
             return vm_mapping.handle_page_fault(&self.vm_space, page_fault_info, &mut rss_delta);
         }
+    }
+
+    fn register_userfaultfd(
+        &self,
+        range: Range<usize>,
+        userfaultfd: &Arc<Userfaultfd>,
+    ) -> Result<()> {
+        let mut inner = self.inner.write();
 
-        return_errno_with_message!(Errno::EACCES, "page fault addr is not in current vmar");
+        let mut is_mapped = false;
+        for vm_mapping in inner.vm_mappings.find(&range) {
+            is_mapped = true;
+            if !vm_mapping.can_userfault() {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the mapping cannot be registered with a userfaultfd"
+                );
+            }
+            if vm_mapping
+                .userfaultfd()
+                .is_some_and(|registered| !Arc::ptr_eq(registered, userfaultfd))
+            {
+                return_errno_with_message!(
+                    Errno::EBUSY,
+                    "the mapping has been registered with another userfaultfd"
+                );
+            }
+        }
+        if !is_mapped {
+            return_errno_with_message!(Errno::EINVAL, "the range is not mapped");
+        }
+
+        inner.update_mappings(
+            &range,
+            |vm_mapping| vm_mapping.userfaultfd().is_none(),
+            |vm_mapping| vm_mapping.set_userfaultfd(Some(userfaultfd.clone())),
+        );
+
+        Ok(())
+    }
+
+    fn unregister_userfaultfd(
+        &self,
+        range: Range<usize>,
+        userfaultfd: &Arc<Userfaultfd>,
+    ) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        let mut is_mapped = false;
+        for vm_mapping in inner.vm_mappings.find(&range) {
+            is_mapped = true;
+            if !vm_mapping.can_userfault() {
+                return_errno_with_message!(
+                    Errno::EINVAL,
+                    "the mapping cannot be registered with a userfaultfd"
+                );
+            }
+        }
+        if !is_mapped {
+            return_errno_with_message!(Errno::EINVAL, "the range is not mapped");
+        }
+
+        inner.update_mappings(
+            &range,
+            |vm_mapping| is_registered_with(vm_mapping, userfaultfd),
+            |vm_mapping| vm_mapping.set_userfaultfd(None),
+        );
+
+        Ok(())
+    }
+
+    fn unregister_userfaultfd_all(&self, userfaultfd: &Arc<Userfaultfd>) {
+        let mut inner = self.inner.write();
+
+        let full_range = self.base..self.base + self.size;
+        inner.update_mappings(
+            &full_range,
+            |vm_mapping| is_registered_with(vm_mapping, userfaultfd),
+            |vm_mapping| vm_mapping.set_userfaultfd(None),
+        );
+    }
+
+    fn fill_missing_page(&self, page_addr: Vaddr, frame: UFrame) -> Result<()> {
+        let inner = self.inner.read();
+
+        let Some(vm_mapping) = inner
+            .vm_mappings
+            .find_one(&page_addr)
+            .filter(|vm_mapping| vm_mapping.userfaultfd().is_some())
+        else {
+            return_errno_with_message!(
+                Errno::ENOENT,
+                "the page is not in a mapping registered with a userfaultfd"
+            );
+        };
+
+        let mut rss_delta = RssDelta::new(self);
+        vm_mapping.fill_missing_page(&self.vm_space, page_addr, frame, &mut rss_delta)
+    }
+
+    /// Accesses the memory in `vaddr..vaddr + len` page by page.
+    ///
+    /// For each page, `access` is called with the frame, the offset in the frame, and the
+    /// corresponding range relative to `vaddr`.
+    fn access_remote<F>(
+        &self,
+        vaddr: Vaddr,
+        len: usize,
+        kind: RemoteAccess,
+        mut access: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&UFrame, usize, Range<usize>) -> Result<()>,
+    {
+        let end = vaddr
+            .checked_add(len)
+            .ok_or_else(|| Error::with_message(Errno::EFAULT, "the range overflows"))?;
+
+        let inner = self.inner.read();
+        let mut rss_delta = RssDelta::new(self);
+
+        let mut addr = vaddr;
+        while addr < end {
+            let Some(vm_mapping) = inner.vm_mappings.find_one(&addr) else {
+                return_errno_with_message!(Errno::EFAULT, "the address is not mapped");
+            };
+
+            let page_addr = addr.align_down(PAGE_SIZE);
+            let frame = vm_mapping.get_frame_for_remote_access(
+                &self.vm_space,
+                page_addr,
+                kind,
+                &mut rss_delta,
+            )?;
+
+            let chunk_end = (page_addr + PAGE_SIZE).min(end);
+            access(
+                &frame,
+                addr - page_addr,
+                (addr - vaddr)..(chunk_end - vaddr),
+            )?;
+            addr = chunk_end;
+        }
+
+        Ok(())
     }
 
     /// Clears all content of the root VMAR.
     fn clear_root_vmar(&self) -> Result<()> {
         let mut inner = self.inner.write();
-        inner.vm_mappings.clear();
+        *inner = VmarInner::new();
 
         // Keep `inner` locked to avoid race conditions.
         let preempt_guard = disable_preempt();
@@ -725,8 +1450,13 @@ impl Vmar_ {
             for vm_mapping in inner.vm_mappings.iter() {
                 let base = vm_mapping.map_to_addr();
 
-                // Clone the `VmMapping` to the new VMAR.
-                let new_mapping = vm_mapping.new_fork()?;
+                // Clone the `VmMapping` to the new VMAR. Like Linux, memory locks
+                // are not inherited by the child. The userfaultfd registrations are
+                // not inherited either, as if `UFFD_FEATURE_EVENT_FORK` is not enabled.
+                let new_mapping = vm_mapping
+                    .new_fork()?
+                    .set_locked(false)
+                    .set_userfaultfd(None);
                 new_inner.insert_without_try_merge(new_mapping);
 
                 // Protect the mapping and copy to the new page table for COW.
@@ -818,6 +1548,11 @@ impl<R> Vmar<R> {
     pub fn get_mappings_total_size(&self) -> usize {
         self.0.inner.read().total_vm
     }
+
+    /// Returns the total size of the locked mappings in bytes.
+    pub fn get_locked_size(&self) -> usize {
+        self.0.inner.read().locked_vm
+    }
 }
 
 /// Options for creating a new mapping. The mapping is not allowed to overlap
@@ -827,6 +1562,7 @@ pub struct VmarMapOptions<'a, R1, R2> {
     parent: &'a Vmar<R1>,
     vmo: Option<Vmo<R2>>,
     inode: Option<Arc<dyn Inode>>,
+    path: Option<Path>,
     perms: VmPerms,
     vmo_offset: usize,
     size: usize,
@@ -837,6 +1573,10 @@ pub struct VmarMapOptions<'a, R1, R2> {
     is_shared: bool,
     // Whether the mapping needs to handle surrounding pages when handling page fault.
     handle_page_faults_around: bool,
+    // The mode of locking the mapping in memory, if it should be locked.
+    lock_mode: Option<LockMode>,
+    // The attachment of the System V shared memory segment that is mapped.
+    shm_attachment: Option<ShmAttachment>,
 }
 
 impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
@@ -851,6 +1591,7 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
             parent,
             vmo: None,
             inode: None,
+            path: None,
             perms,
             vmo_offset: 0,
             size,
@@ -859,6 +1600,8 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
             can_overwrite: false,
             is_shared: false,
             handle_page_faults_around: false,
+            lock_mode: None,
+            shm_attachment: None,
         }
     }
 
@@ -891,6 +1634,14 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
         self
     }
 
+    /// Sets the attachment of the System V shared memory segment.
+    ///
+    /// The segment must be the one whose VMO is bound to the mapping.
+    pub fn shm_attachment(mut self, shm_attachment: ShmAttachment) -> Self {
+        self.shm_attachment = Some(shm_attachment);
+        self
+    }
+
     /// Sets the offset of the first memory page in the VMO that is to be
     /// mapped into the VMAR.
     ///
@@ -952,6 +1703,15 @@ impl<'a, R1, R2> VmarMapOptions<'a, R1, R2> {
         self.handle_page_faults_around = true;
         self
     }
+
+    /// Sets the mapping to be locked in memory with the specified mode.
+    ///
+    /// If not set, the mapping is locked only if it is requested for all new
+    /// mappings (see [`Vmar::lock_all`]).
+    pub fn lock(mut self, mode: LockMode) -> Self {
+        self.lock_mode = Some(mode);
+        self
+    }
 }
 
 impl<R1> VmarMapOptions<'_, R1, Rights> {
@@ -982,6 +1742,20 @@ impl<R1> VmarMapOptions<'_, R1, Rights> {
 
         self
     }
+
+    /// Binds the file at the [`Path`] to the mapping.
+    ///
+    /// This is the same as [`Self::inode`] with the inode of the path, except that the path is
+    /// also recorded in the mapping, so it can be reported (e.g., in core dumps).
+    ///
+    /// # Panics
+    ///
+    /// This function panics for the same reasons as [`Self::inode`].
+    pub fn path(self, path: Path) -> Self {
+        let mut this = self.inode(path.inode().clone());
+        this.path = Some(path);
+        this
+    }
 }
 
 impl<R1, R2> VmarMapOptions<'_, R1, R2>
@@ -999,6 +1773,7 @@ where
             parent,
             vmo,
             inode,
+            path,
             perms,
             vmo_offset,
             size: map_size,
@@ -1007,10 +1782,17 @@ where
             can_overwrite,
             is_shared,
             handle_page_faults_around,
+            lock_mode,
+            shm_attachment,
         } = self;
 
         let mut inner = parent.0.inner.write();
 
+        let lock_mode = lock_mode.or(inner.lock_future);
+        if lock_mode.is_some() && !inner.may_lock_extra_size(map_size) {
+            return_errno_with_message!(Errno::EAGAIN, "locked memory limit overflow");
+        }
+
         inner.check_extra_size_fits_rlimit(map_size).or_else(|e| {
             if can_overwrite {
                 let offset = offset.ok_or(Error::with_message(
@@ -1056,14 +1838,23 @@ where
             map_to_addr,
             vmo,
             inode,
+            path,
             is_shared,
             handle_page_faults_around,
             perms,
+            lock_mode.is_some(),
+            shm_attachment,
         );
 
         // Add the mapping to the VMAR.
         inner.insert_try_merge(vm_mapping);
 
+        drop(inner);
+        if lock_mode == Some(LockMode::Populate) {
+            // Like Linux, errors are ignored when populating the new mapping.
+            let _ = parent.0.populate(map_to_addr..map_to_addr + map_size);
+        }
+
         Ok(map_to_addr)
     }
 
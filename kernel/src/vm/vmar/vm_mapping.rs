@@ -16,11 +16,13 @@ use ostd::{
 
 use super::{interval_set::Interval, RssDelta, RssType};
 use crate::{
-    fs::utils::Inode,
+    fs::{path::Path, utils::Inode},
+    ipc::shm::ShmAttachment,
     prelude::*,
     thread::exception::PageFaultInfo,
     vm::{
         perms::VmPerms,
+        userfaultfd::{ReportedFault, Userfaultfd},
         util::duplicate_frame,
         vmar::is_intersected,
         vmo::{CommitFlags, Vmo, VmoCommitError},
@@ -64,6 +66,11 @@ pub struct VmMapping {
     /// If the inode is `Some`, it means that the mapping is file-backed.
     /// And the `vmo` field must be the page cache of the inode.
     inode: Option<Arc<dyn Inode>>,
+    /// The path of the file that backs the mapping.
+    ///
+    /// This is `None` if the mapping is not file-backed, or if the file is not
+    /// opened via a path (e.g., a memfd file).
+    path: Option<Path>,
     /// Whether the mapping is shared.
     ///
     /// The updates to a shared mapping are visible among processes, or carried
@@ -76,6 +83,23 @@ pub struct VmMapping {
     ///
     /// All pages within the same `VmMapping` have the same permissions.
     perms: VmPerms,
+    /// Whether the mapping is excluded from core dumps.
+    ///
+    /// This is set by `madvise(MADV_DONTDUMP)` and cleared by `madvise(MADV_DODUMP)`.
+    dont_dump: bool,
+    /// Whether the mapping is locked in memory (i.e., `VM_LOCKED` in Linux).
+    ///
+    /// This is set by `mlock` or `mlockall` and cleared by `munlock` or `munlockall`.
+    is_locked: bool,
+    /// The userfaultfd that the mapping is registered with.
+    ///
+    /// If this is `Some`, the page faults on the missing pages in the mapping are reported to
+    /// the userfaultfd instead of being handled by the kernel.
+    userfaultfd: Option<Arc<Userfaultfd>>,
+    /// The attachment of the System V shared memory segment that is mapped.
+    ///
+    /// If this is `Some`, the `vmo` field must be the VMO of the segment.
+    shm_attachment: Option<ShmAttachment>,
 }
 
 impl Interval<Vaddr> for VmMapping {
@@ -87,23 +111,32 @@ impl Interval<Vaddr> for VmMapping {
 /***************************** Basic methods *********************************/
 
 impl VmMapping {
+    #[expect(clippy::too_many_arguments)]
     pub(super) fn new(
         map_size: NonZeroUsize,
         map_to_addr: Vaddr,
         vmo: Option<MappedVmo>,
         inode: Option<Arc<dyn Inode>>,
+        path: Option<Path>,
         is_shared: bool,
         handle_page_faults_around: bool,
         perms: VmPerms,
+        is_locked: bool,
+        shm_attachment: Option<ShmAttachment>,
     ) -> Self {
         Self {
             map_size,
             map_to_addr,
             vmo,
             inode,
+            path,
             is_shared,
             handle_page_faults_around,
             perms,
+            dont_dump: false,
+            is_locked,
+            userfaultfd: None,
+            shm_attachment,
         }
     }
 
@@ -111,6 +144,9 @@ impl VmMapping {
         Ok(VmMapping {
             vmo: self.vmo.as_ref().map(|vmo| vmo.dup()).transpose()?,
             inode: self.inode.clone(),
+            path: self.path.clone(),
+            userfaultfd: self.userfaultfd.clone(),
+            shm_attachment: self.shm_attachment.clone(),
             ..*self
         })
     }
@@ -146,6 +182,56 @@ impl VmMapping {
         self.inode.as_ref()
     }
 
+    /// Returns the path of the file that backs the mapping.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref()
+    }
+
+    /// Returns the offset in the backing VMO where the mapping starts.
+    ///
+    /// For file-backed mappings, this is the offset in the file.
+    pub fn vmo_offset(&self) -> Option<usize> {
+        self.vmo.as_ref().map(|vmo| vmo.offset)
+    }
+
+    /// Returns whether the mapping is shared.
+    pub fn is_shared(&self) -> bool {
+        self.is_shared
+    }
+
+    /// Returns whether the mapping is excluded from core dumps.
+    pub fn dont_dump(&self) -> bool {
+        self.dont_dump
+    }
+
+    /// Returns whether the mapping is locked in memory.
+    pub fn is_locked(&self) -> bool {
+        self.is_locked
+    }
+
+    /// Returns the userfaultfd that the mapping is registered with.
+    pub fn userfaultfd(&self) -> Option<&Arc<Userfaultfd>> {
+        self.userfaultfd.as_ref()
+    }
+
+    /// Returns the attachment of the System V shared memory segment that is mapped.
+    pub fn shm_attachment(&self) -> Option<&ShmAttachment> {
+        self.shm_attachment.as_ref()
+    }
+
+    /// Returns whether the mapping can be registered with a userfaultfd.
+    ///
+    /// Only private anonymous mappings are supported for now.
+    pub(super) fn can_userfault(&self) -> bool {
+        self.vmo.is_none()
+    }
+
+    /// Returns the offset in `vmo` where the mapping starts if the mapping is backed by `vmo`.
+    pub fn offset_in_vmo(&self, vmo: &Vmo) -> Option<usize> {
+        let mapped_vmo = self.vmo.as_ref()?;
+        Arc::ptr_eq(&mapped_vmo.vmo.0, &vmo.0).then_some(mapped_vmo.offset)
+    }
+
     /// Returns the mapping's RSS type.
     pub fn rss_type(&self) -> RssType {
         if self.vmo.is_none() {
@@ -412,6 +498,269 @@ impl VmMapping {
     }
 }
 
+impl VmMapping {
+    /// Faults in the pages in the range, as if they were accessed by the user.
+    ///
+    /// For private writable mappings, the pages are faulted in for writing so that the
+    /// copy-on-write is performed in advance. Pages in inaccessible mappings are not faulted in.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EFAULT`] if a missing page should be filled by the userfaultfd
+    /// that the mapping is registered with.
+    pub(super) fn populate(
+        &self,
+        vm_space: &VmSpace,
+        range: Range<Vaddr>,
+        rss_delta: &mut RssDelta,
+    ) -> Result<()> {
+        debug_assert!(self.map_to_addr <= range.start && range.end <= self.map_end());
+
+        if !self.perms.contains(VmPerms::READ) {
+            return Ok(());
+        }
+
+        let mut required_perms = VmPerms::READ;
+        if !self.is_shared && self.perms.contains(VmPerms::WRITE) {
+            required_perms |= VmPerms::WRITE;
+        }
+
+        // Pages beyond the end of the VMO cannot be accessed in shared mappings.
+        let end = match &self.vmo {
+            Some(vmo) if self.is_shared => range.end.min(self.map_to_addr + vmo.valid_size()),
+            _ => range.end,
+        };
+
+        for page_addr in (range.start..end).step_by(PAGE_SIZE) {
+            self.check_userfault_missing_page(vm_space, page_addr)?;
+            self.handle_single_page_fault(vm_space, page_addr, required_perms, rss_delta)?;
+        }
+
+        Ok(())
+    }
+}
+
+/******************************* Userfaultfd *********************************/
+
+impl VmMapping {
+    /// Reports a page fault to the userfaultfd that the mapping is registered with.
+    ///
+    /// The page fault is reported only if it is on a missing page. Otherwise, or if the mapping
+    /// is not registered, `None` is returned and the page fault should be handled as usual.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EFAULT`] if the page fault is caused by the kernel on a
+    /// missing page, but the userfaultfd only handles the page faults caused by the user-mode
+    /// code.
+    pub(super) fn report_userfault(
+        &self,
+        vm_space: &VmSpace,
+        page_fault_info: &PageFaultInfo,
+    ) -> Result<Option<ReportedFault>> {
+        let Some(userfaultfd) = self.userfaultfd.as_ref() else {
+            return Ok(None);
+        };
+        // Faults that violate the permissions are not reported.
+        if !self.perms.contains(page_fault_info.required_perms) {
+            return Ok(None);
+        }
+
+        let page_aligned_addr = page_fault_info.address.align_down(PAGE_SIZE);
+        if self.is_page_mapped(vm_space, page_aligned_addr)? {
+            return Ok(None);
+        }
+
+        if !page_fault_info.is_user && userfaultfd.is_user_mode_only() {
+            return_errno_with_message!(
+                Errno::EFAULT,
+                "the page fault caused by the kernel cannot be reported to the userfaultfd"
+            );
+        }
+
+        let is_write = page_fault_info.required_perms.contains(VmPerms::WRITE);
+        let Some(fault) = userfaultfd.report_fault(page_fault_info.address, is_write) else {
+            return Ok(None);
+        };
+
+        // The page may have been filled before the fault is reported. If so, the fault will
+        // never be woken up, so it should be withdrawn.
+        if self.is_page_mapped(vm_space, page_aligned_addr)? {
+            return Ok(None);
+        }
+
+        Ok(Some(fault))
+    }
+
+    /// Fills a missing page in the mapping with the frame.
+    ///
+    /// This is used to resolve the page faults reported to the userfaultfd.
+    ///
+    /// # Errors
+    ///
+    /// This method returns [`Errno::EEXIST`] if the page has already been mapped.
+    pub(super) fn fill_missing_page(
+        &self,
+        vm_space: &VmSpace,
+        page_aligned_addr: Vaddr,
+        frame: UFrame,
+        rss_delta: &mut RssDelta,
+    ) -> Result<()> {
+        let preempt_guard = disable_preempt();
+        let mut cursor = vm_space.cursor_mut(
+            &preempt_guard,
+            &(page_aligned_addr..page_aligned_addr + PAGE_SIZE),
+        )?;
+        if let (_, Some(_)) = cursor.query()? {
+            return_errno_with_message!(Errno::EEXIST, "the page has already been mapped");
+        }
+
+        let page_flags = PageFlags::from(self.perms) | PageFlags::ACCESSED | PageFlags::DIRTY;
+        cursor.map(
+            frame,
+            PageProperty::new_user(page_flags, CachePolicy::Writeback),
+        );
+        rss_delta.add(self.rss_type(), 1);
+
+        Ok(())
+    }
+
+    /// Checks that the page is not a missing page that should be filled by a userfaultfd.
+    ///
+    /// Like Linux, the missing pages in a mapping registered with a userfaultfd are not filled
+    /// with zeros when they are faulted in by the kernel on behalf of the user (e.g., for remote
+    /// accesses or `mlock`). Otherwise, the handler would fail to fill the pages later.
+    fn check_userfault_missing_page(
+        &self,
+        vm_space: &VmSpace,
+        page_aligned_addr: Vaddr,
+    ) -> Result<()> {
+        if self.userfaultfd.is_some() && !self.is_page_mapped(vm_space, page_aligned_addr)? {
+            return_errno_with_message!(
+                Errno::EFAULT,
+                "the missing page should be filled by the userfaultfd"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn is_page_mapped(&self, vm_space: &VmSpace, page_aligned_addr: Vaddr) -> Result<bool> {
+        let preempt_guard = disable_preempt();
+        let mut cursor = vm_space.cursor(
+            &preempt_guard,
+            &(page_aligned_addr..page_aligned_addr + PAGE_SIZE),
+        )?;
+        let (_, item) = cursor.query()?;
+        Ok(item.is_some())
+    }
+}
+
+/****************************** Remote access ********************************/
+
+/// The kind of an access from another process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RemoteAccess {
+    Read,
+    /// Writes to the mapping only if it is writable.
+    Write,
+    /// Writes to the mapping even if it is a private mapping that is not writable.
+    ForcedWrite,
+}
+
+impl VmMapping {
+    /// Gets the frame mapped at the page for an access from another process.
+    ///
+    /// The page will be faulted in if it has not been mapped yet. For forced write accesses to a
+    /// private mapping, the page will be copied on write even if the mapping is not writable, so
+    /// that debuggers can insert breakpoints into read-only code. This is similar to `FOLL_FORCE`
+    /// in Linux.
+    pub(super) fn get_frame_for_remote_access(
+        &self,
+        vm_space: &VmSpace,
+        page_aligned_addr: Vaddr,
+        kind: RemoteAccess,
+        rss_delta: &mut RssDelta,
+    ) -> Result<UFrame> {
+        if !self.perms.contains(VmPerms::READ) {
+            return_errno_with_message!(Errno::EFAULT, "the mapping is not readable");
+        }
+        let is_write = kind != RemoteAccess::Read;
+        if is_write
+            && (self.is_shared || kind == RemoteAccess::Write)
+            && !self.perms.contains(VmPerms::WRITE)
+        {
+            return_errno_with_message!(Errno::EFAULT, "the mapping is not writable");
+        }
+
+        let mut required_perms = VmPerms::READ;
+        if is_write && self.perms.contains(VmPerms::WRITE) {
+            required_perms |= VmPerms::WRITE;
+        }
+        self.check_userfault_missing_page(vm_space, page_aligned_addr)?;
+        self.handle_single_page_fault(vm_space, page_aligned_addr, required_perms, rss_delta)?;
+
+        let preempt_guard = disable_preempt();
+        let mut cursor = vm_space.cursor_mut(
+            &preempt_guard,
+            &(page_aligned_addr..page_aligned_addr + PAGE_SIZE),
+        )?;
+        let (_, Some((frame, prop))) = cursor.query()? else {
+            return_errno_with_message!(Errno::EFAULT, "the page is not mapped");
+        };
+
+        if !is_write || prop.flags.contains(PageFlags::W) {
+            return Ok(frame);
+        }
+
+        // This is a write access to a private mapping that is not writable. If we are the only
+        // reference to the frame (see `handle_single_page_fault`), we can write to it directly.
+        // Otherwise, we need to perform COW while keeping the page read-only.
+        if frame.reference_count() == 2 {
+            return Ok(frame);
+        }
+        let new_frame: UFrame = duplicate_frame(&frame)?.into();
+        cursor.map(new_frame.clone(), prop);
+        cursor.flusher().sync_tlb_flush();
+
+        Ok(new_frame)
+    }
+
+    /// Gets the frame of a page to write it to a core dump.
+    ///
+    /// Unlike [`Self::get_frame_for_remote_access`], this method does not fault in the page.
+    /// `None` will be returned if the page has never been touched, in which case the page
+    /// contains only zeros.
+    pub(super) fn get_frame_for_dump(
+        &self,
+        vm_space: &VmSpace,
+        page_aligned_addr: Vaddr,
+    ) -> Result<Option<UFrame>> {
+        let preempt_guard = disable_preempt();
+        let mut cursor = vm_space.cursor(
+            &preempt_guard,
+            &(page_aligned_addr..page_aligned_addr + PAGE_SIZE),
+        )?;
+        if let (_, Some((frame, _))) = cursor.query()? {
+            return Ok(Some(frame));
+        }
+        drop(cursor);
+        drop(preempt_guard);
+
+        // The page may still exist in the VMO (e.g., in the page cache of the file).
+        let Some(vmo) = self.vmo.as_ref() else {
+            return Ok(None);
+        };
+        let page_offset = page_aligned_addr - self.map_to_addr;
+        if page_offset >= vmo.valid_size() {
+            return Ok(None);
+        }
+        let frame = vmo.commit_on((vmo.offset + page_offset) / PAGE_SIZE, CommitFlags::empty())?;
+
+        Ok(Some(frame))
+    }
+}
+
 /**************************** Transformations ********************************/
 
 impl VmMapping {
@@ -447,6 +796,9 @@ impl VmMapping {
             map_size: NonZeroUsize::new(left_size).unwrap(),
             vmo: l_vmo,
             inode: self.inode.clone(),
+            path: self.path.clone(),
+            userfaultfd: self.userfaultfd.clone(),
+            shm_attachment: self.shm_attachment.clone(),
             ..self
         };
         let right = Self {
@@ -454,6 +806,7 @@ impl VmMapping {
             map_size: NonZeroUsize::new(right_size).unwrap(),
             vmo: r_vmo,
             inode: self.inode,
+            path: self.path,
             ..self
         };
 
@@ -531,6 +884,24 @@ impl VmMapping {
             (self, None)
         }
     }
+
+    /// Changes whether the mapping is excluded from core dumps.
+    pub(super) fn set_dont_dump(self, dont_dump: bool) -> Self {
+        Self { dont_dump, ..self }
+    }
+
+    /// Changes whether the mapping is locked in memory.
+    pub(super) fn set_locked(self, is_locked: bool) -> Self {
+        Self { is_locked, ..self }
+    }
+
+    /// Changes the userfaultfd that the mapping is registered with.
+    pub(super) fn set_userfaultfd(self, userfaultfd: Option<Arc<Userfaultfd>>) -> Self {
+        Self {
+            userfaultfd,
+            ..self
+        }
+    }
 }
 
 /************************** VM Space operations ******************************/
@@ -656,7 +1027,14 @@ fn try_merge(left: &VmMapping, right: &VmMapping) -> Option<VmMapping> {
     let is_adjacent = left.map_end() == right.map_to_addr();
     let is_type_equal = left.is_shared == right.is_shared
         && left.handle_page_faults_around == right.handle_page_faults_around
-        && left.perms == right.perms;
+        && left.perms == right.perms
+        && left.dont_dump == right.dont_dump
+        && left.is_locked == right.is_locked
+        && match (&left.userfaultfd, &right.userfaultfd) {
+            (None, None) => true,
+            (Some(l_uffd), Some(r_uffd)) => Arc::ptr_eq(l_uffd, r_uffd),
+            _ => false,
+        };
 
     if !is_adjacent || !is_type_equal {
         return None;
@@ -680,6 +1058,9 @@ fn try_merge(left: &VmMapping, right: &VmMapping) -> Option<VmMapping> {
         map_size,
         vmo,
         inode: left.inode.clone(),
+        path: left.path.clone(),
+        userfaultfd: left.userfaultfd.clone(),
+        shm_attachment: left.shm_attachment.clone(),
         ..*left
     })
 }
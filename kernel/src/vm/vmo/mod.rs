@@ -18,7 +18,7 @@ use ostd::{
 };
 use xarray::{Cursor, LockedXArray, XArray};
 
-use crate::prelude::*;
+use crate::{fs::cgroupfs::MemoryCharge, prelude::*};
 
 mod dyn_cap;
 mod options;
@@ -155,6 +155,10 @@ pub(super) struct Vmo_ {
     /// the [`XArray`] in the `pages` field. Therefore, the size read after locking the
     /// `pages` will be the latest size.
     size: AtomicUsize,
+    /// The memory charged to the cgroup that the VMO is created in.
+    ///
+    /// Only anonymous VMOs (i.e., those without pagers) are charged.
+    memory_charge: Option<MemoryCharge>,
 }
 
 impl Debug for Vmo_ {
@@ -215,6 +219,11 @@ impl Vmo_ {
             return Ok(page.clone());
         }
 
+        // FIXME: Try to reclaim memory or trigger the OOM handling before failing.
+        if let Some(memory_charge) = &self.memory_charge {
+            memory_charge.try_charge(PAGE_SIZE)?;
+        }
+
         cursor.store(new_page.clone());
         Ok(new_page)
     }
@@ -445,7 +454,10 @@ impl Vmo_ {
 
         let Some(pager) = &self.pager else {
             for _ in page_idx_range {
-                cursor.remove();
+                let removed_page = cursor.remove();
+                if let (Some(_), Some(memory_charge)) = (removed_page, &self.memory_charge) {
+                    memory_charge.uncharge(PAGE_SIZE);
+                }
                 cursor.next();
             }
             return Ok(());
@@ -12,7 +12,7 @@ use ostd::mm::{FrameAllocOptions, UFrame, USegment};
 use xarray::XArray;
 
 use super::{Pager, Vmo, VmoFlags};
-use crate::{prelude::*, vm::vmo::Vmo_};
+use crate::{fs::cgroupfs::MemoryCharge, prelude::*, vm::vmo::Vmo_};
 
 /// Options for allocating a root VMO.
 ///
@@ -122,12 +122,26 @@ impl<R: TRights> VmoOptions<TRightSet<R>> {
 
 fn alloc_vmo_(size: usize, flags: VmoFlags, pager: Option<Arc<dyn Pager>>) -> Result<Vmo_> {
     let size = size.align_up(PAGE_SIZE);
+
+    // The pages of anonymous VMOs are charged to the cgroup of the current process.
+    let memory_charge = if pager.is_none() {
+        MemoryCharge::new_for_current()
+    } else {
+        None
+    };
+    if let Some(memory_charge) = &memory_charge {
+        if flags.contains(VmoFlags::CONTIGUOUS) {
+            memory_charge.try_charge(size)?;
+        }
+    }
+
     let pages = committed_pages_if_continuous(flags, size)?;
     Ok(Vmo_ {
         pager,
         flags,
         pages,
         size: AtomicUsize::new(size),
+        memory_charge,
     })
 }
 
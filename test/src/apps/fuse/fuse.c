@@ -0,0 +1,357 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdint.h>
+#include <unistd.h>
+#include <linux/fuse.h>
+#include <sys/mman.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/uio.h>
+#include <sys/wait.h>
+
+#include "../test.h"
+
+#define MNT_DIR "/tmp/fuse_test"
+#define FILE_NAME "hello"
+#define FILE_PATH MNT_DIR "/" FILE_NAME
+#define FILE_CONTENT "Hello, FUSE!\n"
+#define FILE_NODEID 2
+
+static int fuse_fd;
+static pid_t daemon_pid;
+
+// The file handles seen by the daemon, which are shared with the test.
+static struct {
+	uint64_t next_fh;
+	uint64_t last_flushed_fh;
+	uint64_t last_released_fh;
+	int nr_flushes;
+	int nr_releases;
+} *handles;
+
+static char req_buf[FUSE_MIN_READ_BUFFER + 65536];
+static char reply_buf[65536];
+
+static void fill_attr(struct fuse_attr *attr, uint64_t nodeid)
+{
+	memset(attr, 0, sizeof(*attr));
+	attr->ino = nodeid;
+	if (nodeid == FUSE_ROOT_ID) {
+		attr->mode = S_IFDIR | 0755;
+		attr->nlink = 2;
+	} else {
+		attr->mode = S_IFREG | 0444;
+		attr->nlink = 1;
+		attr->size = strlen(FILE_CONTENT);
+	}
+	attr->blksize = 4096;
+}
+
+static size_t add_dirent(char *buf, uint64_t ino, uint64_t off, uint32_t type,
+			 const char *name)
+{
+	struct fuse_dirent *dirent = (struct fuse_dirent *)buf;
+	size_t len = FUSE_NAME_OFFSET + strlen(name);
+
+	memset(buf, 0, FUSE_DIRENT_ALIGN(len));
+	dirent->ino = ino;
+	dirent->off = off;
+	dirent->namelen = strlen(name);
+	dirent->type = type;
+	memcpy(dirent->name, name, dirent->namelen);
+	return FUSE_DIRENT_ALIGN(len);
+}
+
+// Handles a request and returns the length of the reply payload, or a negated
+// errno to reply with.
+static ssize_t handle_request(struct fuse_in_header *in, void *arg)
+{
+	switch (in->opcode) {
+	case FUSE_INIT: {
+		struct fuse_init_in *init_in = arg;
+		struct fuse_init_out *init_out = (void *)reply_buf;
+
+		if (init_in->major != FUSE_KERNEL_VERSION)
+			return -EPROTO;
+		memset(init_out, 0, sizeof(*init_out));
+		init_out->major = FUSE_KERNEL_VERSION;
+		init_out->minor = 31;
+		init_out->max_readahead = init_in->max_readahead;
+		init_out->max_write = 65536;
+		return sizeof(*init_out);
+	}
+	case FUSE_LOOKUP: {
+		struct fuse_entry_out *entry_out = (void *)reply_buf;
+
+		if (in->nodeid != FUSE_ROOT_ID || strcmp(arg, FILE_NAME) != 0)
+			return -ENOENT;
+		memset(entry_out, 0, sizeof(*entry_out));
+		entry_out->nodeid = FILE_NODEID;
+		entry_out->entry_valid = 1;
+		entry_out->attr_valid = 1;
+		fill_attr(&entry_out->attr, FILE_NODEID);
+		return sizeof(*entry_out);
+	}
+	case FUSE_GETATTR: {
+		struct fuse_attr_out *attr_out = (void *)reply_buf;
+
+		memset(attr_out, 0, sizeof(*attr_out));
+		attr_out->attr_valid = 1;
+		fill_attr(&attr_out->attr, in->nodeid);
+		return sizeof(*attr_out);
+	}
+	case FUSE_OPEN:
+	case FUSE_OPENDIR: {
+		struct fuse_open_out *open_out = (void *)reply_buf;
+
+		memset(open_out, 0, sizeof(*open_out));
+		open_out->fh = ++handles->next_fh;
+		return sizeof(*open_out);
+	}
+	case FUSE_READ: {
+		struct fuse_read_in *read_in = arg;
+		size_t size = strlen(FILE_CONTENT);
+
+		if (in->nodeid != FILE_NODEID)
+			return -EISDIR;
+		if (read_in->offset >= size)
+			return 0;
+		size -= read_in->offset;
+		if (size > read_in->size)
+			size = read_in->size;
+		memcpy(reply_buf, FILE_CONTENT + read_in->offset, size);
+		return size;
+	}
+	case FUSE_READDIR: {
+		struct fuse_read_in *read_in = arg;
+		size_t len = 0;
+
+		// Each entry carries the offset of the next one.
+		if (read_in->offset < 1)
+			len += add_dirent(reply_buf + len, FUSE_ROOT_ID, 1,
+					  DT_DIR, ".");
+		if (read_in->offset < 2)
+			len += add_dirent(reply_buf + len, FUSE_ROOT_ID, 2,
+					  DT_DIR, "..");
+		if (read_in->offset < 3)
+			len += add_dirent(reply_buf + len, FILE_NODEID, 3,
+					  DT_REG, FILE_NAME);
+		return len <= read_in->size ? (ssize_t)len : -EINVAL;
+	}
+	case FUSE_STATFS:
+		memset(reply_buf, 0, sizeof(struct fuse_statfs_out));
+		return sizeof(struct fuse_statfs_out);
+	case FUSE_FLUSH:
+		handles->last_flushed_fh = ((struct fuse_flush_in *)arg)->fh;
+		handles->nr_flushes++;
+		return 0;
+	case FUSE_RELEASE:
+		handles->last_released_fh = ((struct fuse_release_in *)arg)->fh;
+		handles->nr_releases++;
+		return 0;
+	case FUSE_RELEASEDIR:
+	case FUSE_DESTROY:
+		return 0;
+	default:
+		return -ENOSYS;
+	}
+}
+
+// Serves the requests until the connection is aborted.
+//
+// The device is non-blocking, so the daemon waits for the requests with poll().
+static int serve(void)
+{
+	for (;;) {
+		struct fuse_in_header *in = (void *)req_buf;
+		struct fuse_out_header out;
+		struct iovec iov[2];
+		struct pollfd pfd = { .fd = fuse_fd, .events = POLLIN };
+		ssize_t len;
+
+		len = read(fuse_fd, req_buf, sizeof(req_buf));
+		if (len < 0 && errno == EAGAIN) {
+			if (poll(&pfd, 1, -1) < 0)
+				return EXIT_FAILURE;
+			continue;
+		}
+		if (len < 0 && errno == ENODEV)
+			return EXIT_SUCCESS;
+		if (len < (ssize_t)sizeof(*in) || in->len != len)
+			return EXIT_FAILURE;
+
+		if (in->opcode == FUSE_FORGET || in->opcode == FUSE_BATCH_FORGET)
+			continue;
+
+		len = handle_request(in, in + 1);
+		out.unique = in->unique;
+		out.error = len < 0 ? len : 0;
+		out.len = sizeof(out) + (len < 0 ? 0 : len);
+		iov[0].iov_base = &out;
+		iov[0].iov_len = sizeof(out);
+		iov[1].iov_base = reply_buf;
+		iov[1].iov_len = len < 0 ? 0 : len;
+		if (writev(fuse_fd, iov, 2) != out.len)
+			return EXIT_FAILURE;
+	}
+}
+
+FN_SETUP(mount)
+{
+	char options[128];
+
+	handles = mmap(NULL, sizeof(*handles), PROT_READ | PROT_WRITE,
+		       MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+	CHECK_WITH(handles, _ret != MAP_FAILED);
+
+	CHECK(mkdir(MNT_DIR, 0755));
+	fuse_fd = CHECK(open("/dev/fuse", O_RDWR | O_NONBLOCK));
+
+	// The mount does not wait for the reply of FUSE_INIT, so the daemon can be
+	// started after the mount.
+	snprintf(options, sizeof(options),
+		 "fd=%d,rootmode=40000,user_id=0,group_id=0", fuse_fd);
+	CHECK(mount("fuse_test", MNT_DIR, "fuse", 0, options));
+
+	daemon_pid = CHECK(fork());
+	if (daemon_pid == 0)
+		exit(serve());
+}
+END_SETUP()
+
+FN_TEST(lookup)
+{
+	struct stat st;
+
+	TEST_RES(stat(FILE_PATH, &st),
+		 S_ISREG(st.st_mode) && st.st_ino == FILE_NODEID &&
+			 st.st_size == strlen(FILE_CONTENT));
+	TEST_ERRNO(stat(MNT_DIR "/missing", &st), ENOENT);
+	TEST_RES(stat(MNT_DIR, &st), S_ISDIR(st.st_mode));
+}
+END_TEST()
+
+FN_TEST(read)
+{
+	char buf[64];
+	int fd;
+
+	fd = TEST_SUCC(open(FILE_PATH, O_RDONLY));
+	TEST_RES(read(fd, buf, sizeof(buf)),
+		 _ret == strlen(FILE_CONTENT) &&
+			 memcmp(buf, FILE_CONTENT, _ret) == 0);
+	TEST_RES(read(fd, buf, sizeof(buf)), _ret == 0);
+	TEST_RES(pread(fd, buf, 5, 7),
+		 _ret == 5 && memcmp(buf, FILE_CONTENT + 7, 5) == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+// Waits for the daemon to handle the requests sent in the background.
+static void wait_for_releases(int nr_releases)
+{
+	int i;
+
+	for (i = 0; i < 100 && handles->nr_releases < nr_releases; i++)
+		usleep(10 * 1000);
+}
+
+FN_TEST(handles)
+{
+	int nr_flushes = handles->nr_flushes;
+	int nr_releases = handles->nr_releases;
+	uint64_t fh1, fh2;
+	int fd1, fd2, fd3;
+
+	// Each open file description has its own handle.
+	fd1 = TEST_SUCC(open(FILE_PATH, O_RDONLY));
+	fh1 = handles->next_fh;
+	fd2 = TEST_SUCC(open(FILE_PATH, O_RDONLY));
+	fh2 = handles->next_fh;
+	TEST_RES(fh2, _ret != fh1);
+
+	// The handle is flushed and released when the last file descriptor is closed.
+	fd3 = TEST_SUCC(dup(fd1));
+	TEST_SUCC(close(fd1));
+	TEST_SUCC(close(fd2));
+	wait_for_releases(nr_releases + 1);
+	TEST_RES(handles->nr_releases,
+		 _ret == nr_releases + 1 &&
+			 handles->nr_flushes == nr_flushes + 1 &&
+			 handles->last_flushed_fh == fh2 &&
+			 handles->last_released_fh == fh2);
+
+	TEST_SUCC(close(fd3));
+	wait_for_releases(nr_releases + 2);
+	TEST_RES(handles->nr_releases,
+		 _ret == nr_releases + 2 &&
+			 handles->nr_flushes == nr_flushes + 2 &&
+			 handles->last_flushed_fh == fh1 &&
+			 handles->last_released_fh == fh1);
+}
+END_TEST()
+
+FN_TEST(readdir)
+{
+	int found_dot = 0, found_dotdot = 0, found_file = 0, others = 0;
+	struct dirent *dirent;
+	DIR *dir;
+
+	dir = TEST_RES(opendir(MNT_DIR), _ret != NULL);
+	while ((dirent = readdir(dir)) != NULL) {
+		if (strcmp(dirent->d_name, ".") == 0)
+			found_dot++;
+		else if (strcmp(dirent->d_name, "..") == 0)
+			found_dotdot++;
+		else if (strcmp(dirent->d_name, FILE_NAME) == 0)
+			found_file++;
+		else
+			others++;
+	}
+	TEST_RES(found_dot + found_dotdot + found_file + others,
+		 found_dot == 1 && found_dotdot == 1 && found_file == 1 &&
+			 others == 0);
+	TEST_SUCC(closedir(dir));
+}
+END_TEST()
+
+FN_TEST(notify)
+{
+	struct {
+		struct fuse_out_header out;
+		struct fuse_notify_inval_inode_out inval;
+	} notify = {
+		.out = { .len = sizeof(notify),
+			 .error = FUSE_NOTIFY_INVAL_INODE,
+			 .unique = 0 },
+		.inval = { .ino = FUSE_ROOT_ID, .off = 0, .len = 0 },
+	};
+
+	TEST_RES(write(fuse_fd, &notify, sizeof(notify)),
+		 _ret == sizeof(notify));
+
+	notify.out.error = 1000;
+	TEST_ERRNO(write(fuse_fd, &notify, sizeof(notify)), EINVAL);
+}
+END_TEST()
+
+FN_TEST(umount)
+{
+	int status;
+
+	TEST_SUCC(umount(MNT_DIR));
+	TEST_RES(waitpid(daemon_pid, &status, 0),
+		 _ret == daemon_pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+	TEST_ERRNO(read(fuse_fd, req_buf, sizeof(req_buf)), ENODEV);
+
+	TEST_SUCC(close(fuse_fd));
+	TEST_SUCC(rmdir(MNT_DIR));
+	TEST_SUCC(munmap(handles, sizeof(*handles)));
+}
+END_TEST()
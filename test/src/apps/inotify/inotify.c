@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <fcntl.h>
+#include <unistd.h>
+#include <sys/inotify.h>
+#include <sys/ioctl.h>
+#include <sys/stat.h>
+
+#include "../test.h"
+
+#define TEST_DIR "/tmp/inotify_test"
+#define TEST_FILE TEST_DIR "/file"
+#define TEST_FILE2 TEST_DIR "/file2"
+#define TEST_SUBDIR TEST_DIR "/subdir"
+
+static char event_buf[4096]
+	__attribute__((aligned(__alignof__(struct inotify_event))));
+static ssize_t event_len;
+static ssize_t event_off;
+
+// Returns the next event, or NULL if no events are available.
+static struct inotify_event *next_event(int fd)
+{
+	struct inotify_event *event;
+
+	if (event_off >= event_len) {
+		event_off = 0;
+		event_len = read(fd, event_buf, sizeof(event_buf));
+		if (event_len <= 0) {
+			event_len = 0;
+			return NULL;
+		}
+	}
+
+	event = (struct inotify_event *)(event_buf + event_off);
+	event_off += sizeof(struct inotify_event) + event->len;
+	return event;
+}
+
+static int event_is(struct inotify_event *event, int wd, uint32_t mask,
+		    const char *name)
+{
+	if (event == NULL || event->wd != wd || event->mask != mask)
+		return 0;
+	if (name == NULL)
+		return event->len == 0;
+	return event->len > strlen(name) && strcmp(event->name, name) == 0;
+}
+
+FN_SETUP(general)
+{
+	CHECK(mkdir(TEST_DIR, 0755));
+}
+END_SETUP()
+
+FN_TEST(invalid_args)
+{
+	int fd;
+
+	TEST_ERRNO(inotify_init1(O_RDWR), EINVAL);
+
+	fd = TEST_SUCC(inotify_init1(IN_NONBLOCK | IN_CLOEXEC));
+
+	TEST_ERRNO(inotify_add_watch(fd, TEST_DIR, 0), EINVAL);
+	TEST_ERRNO(inotify_add_watch(fd, TEST_DIR "/nonexistent", IN_CREATE),
+		   ENOENT);
+	TEST_ERRNO(inotify_add_watch(fd, TEST_DIR, IN_CREATE | IN_MASK_ADD |
+							   IN_MASK_CREATE),
+		   EINVAL);
+	TEST_ERRNO(inotify_add_watch(0, TEST_DIR, IN_CREATE), EINVAL);
+	TEST_ERRNO(inotify_rm_watch(fd, 12345), EINVAL);
+
+	TEST_ERRNO(read(fd, event_buf, sizeof(event_buf)), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(watch_descriptors)
+{
+	int fd, wd;
+
+	fd = TEST_SUCC(inotify_init1(IN_NONBLOCK));
+
+	wd = TEST_SUCC(inotify_add_watch(fd, TEST_DIR, IN_CREATE));
+	// Watching the same inode again modifies the existing watch.
+	TEST_RES(inotify_add_watch(fd, TEST_DIR, IN_DELETE), _ret == wd);
+	TEST_RES(inotify_add_watch(fd, TEST_DIR, IN_MODIFY | IN_MASK_ADD),
+		 _ret == wd);
+	TEST_ERRNO(inotify_add_watch(fd, TEST_DIR, IN_CREATE | IN_MASK_CREATE),
+		   EEXIST);
+
+	TEST_SUCC(inotify_rm_watch(fd, wd));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_IGNORED, NULL));
+	TEST_ERRNO(inotify_rm_watch(fd, wd), EINVAL);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(directory_events)
+{
+	int fd, wd, file;
+	uint32_t cookie;
+	struct inotify_event *event;
+	int avail;
+
+	fd = TEST_SUCC(inotify_init1(IN_NONBLOCK));
+	wd = TEST_SUCC(inotify_add_watch(fd, TEST_DIR,
+					 IN_CREATE | IN_DELETE | IN_MODIFY |
+						 IN_ATTRIB | IN_MOVE |
+						 IN_CLOSE_WRITE | IN_ONLYDIR));
+
+	file = TEST_SUCC(open(TEST_FILE, O_CREAT | O_WRONLY, 0644));
+	TEST_RES(write(file, "hello", 5), _ret == 5);
+	TEST_SUCC(close(file));
+	TEST_SUCC(chmod(TEST_FILE, 0600));
+
+	// The identical `IN_MODIFY` events are merged.
+	TEST_RES(ioctl(fd, FIONREAD, &avail),
+		 avail == 4 * (sizeof(struct inotify_event) + 16));
+
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_CREATE, "file"));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_MODIFY, "file"));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_CLOSE_WRITE, "file"));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_ATTRIB, "file"));
+
+	TEST_SUCC(rename(TEST_FILE, TEST_FILE2));
+	event = TEST_RES(next_event(fd),
+			 event_is(_ret, wd, IN_MOVED_FROM, "file") &&
+				 _ret->cookie != 0);
+	cookie = event != NULL ? event->cookie : 0;
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_MOVED_TO, "file2") &&
+					 _ret->cookie == cookie);
+
+	TEST_SUCC(unlink(TEST_FILE2));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_DELETE, "file2"));
+
+	TEST_SUCC(mkdir(TEST_SUBDIR, 0755));
+	TEST_SUCC(rmdir(TEST_SUBDIR));
+	TEST_RES(next_event(fd),
+		 event_is(_ret, wd, IN_CREATE | IN_ISDIR, "subdir"));
+	TEST_RES(next_event(fd),
+		 event_is(_ret, wd, IN_DELETE | IN_ISDIR, "subdir"));
+
+	TEST_ERRNO(next_event(fd), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(file_events)
+{
+	int fd, wd, file;
+	char buf[sizeof(struct inotify_event)];
+
+	file = TEST_SUCC(open(TEST_FILE, O_CREAT | O_WRONLY, 0644));
+
+	fd = TEST_SUCC(inotify_init1(IN_NONBLOCK));
+	TEST_ERRNO(inotify_add_watch(fd, TEST_FILE, IN_MODIFY | IN_ONLYDIR),
+		   ENOTDIR);
+	wd = TEST_SUCC(inotify_add_watch(fd, TEST_FILE,
+					 IN_MODIFY | IN_DELETE_SELF));
+
+	TEST_RES(write(file, "hello", 5), _ret == 5);
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_MODIFY, NULL));
+
+	// The watch is removed automatically after the file is deleted.
+	TEST_SUCC(unlink(TEST_FILE));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_DELETE_SELF, NULL));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_IGNORED, NULL));
+	TEST_ERRNO(inotify_rm_watch(fd, wd), EINVAL);
+
+	TEST_SUCC(close(file));
+	TEST_ERRNO(next_event(fd), EAGAIN);
+
+	// The buffer must be large enough for the first event.
+	TEST_SUCC(mkdir(TEST_SUBDIR, 0755));
+	TEST_SUCC(inotify_add_watch(fd, TEST_DIR, IN_CREATE));
+	TEST_SUCC(rmdir(TEST_SUBDIR));
+	TEST_SUCC(mkdir(TEST_SUBDIR, 0755));
+	TEST_ERRNO(read(fd, buf, sizeof(buf)), EINVAL);
+	TEST_SUCC(rmdir(TEST_SUBDIR));
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(oneshot)
+{
+	int fd, wd;
+
+	fd = TEST_SUCC(inotify_init1(IN_NONBLOCK));
+	wd = TEST_SUCC(inotify_add_watch(fd, TEST_DIR, IN_CREATE | IN_ONESHOT));
+
+	TEST_SUCC(mkdir(TEST_SUBDIR, 0755));
+	TEST_SUCC(rmdir(TEST_SUBDIR));
+	TEST_SUCC(mkdir(TEST_SUBDIR, 0755));
+	TEST_SUCC(rmdir(TEST_SUBDIR));
+
+	TEST_RES(next_event(fd),
+		 event_is(_ret, wd, IN_CREATE | IN_ISDIR, "subdir"));
+	TEST_RES(next_event(fd), event_is(_ret, wd, IN_IGNORED, NULL));
+	TEST_ERRNO(next_event(fd), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(rmdir(TEST_DIR));
+}
+END_SETUP()
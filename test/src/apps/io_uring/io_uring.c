@@ -0,0 +1,470 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <fcntl.h>
+#include <linux/io_uring.h>
+#include <poll.h>
+#include <stdatomic.h>
+#include <stdint.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/socket.h>
+#include <sys/syscall.h>
+#include <time.h>
+
+#define ENTRIES 4
+#define FILE_NAME "/tmp/io_uring_test.txt"
+
+static int ring_fd;
+static struct io_uring_params params;
+static char *ring;
+static struct io_uring_sqe *sqes;
+static size_t ring_size;
+
+static int io_uring_setup(unsigned int entries, struct io_uring_params *p)
+{
+	return syscall(SYS_io_uring_setup, entries, p);
+}
+
+static int io_uring_enter(int fd, unsigned int to_submit,
+			  unsigned int min_complete, unsigned int flags)
+{
+	return syscall(SYS_io_uring_enter, fd, to_submit, min_complete, flags,
+		       NULL, 0);
+}
+
+static int io_uring_register(int fd, unsigned int opcode, void *arg,
+			     unsigned int nr_args)
+{
+	return syscall(SYS_io_uring_register, fd, opcode, arg, nr_args);
+}
+
+#define RING_FIELD(off) ((_Atomic unsigned int *)(ring + (off)))
+
+static struct io_uring_sqe *get_sqe(void)
+{
+	unsigned int tail = atomic_load(RING_FIELD(params.sq_off.tail));
+	unsigned int mask = *RING_FIELD(params.sq_off.ring_mask);
+	struct io_uring_sqe *sqe = &sqes[tail & mask];
+
+	memset(sqe, 0, sizeof(*sqe));
+	((unsigned int *)(ring + params.sq_off.array))[tail & mask] =
+		tail & mask;
+	atomic_store(RING_FIELD(params.sq_off.tail), tail + 1);
+
+	return sqe;
+}
+
+static int submit_and_wait(unsigned int to_submit, unsigned int min_complete)
+{
+	return io_uring_enter(ring_fd, to_submit, min_complete,
+			      IORING_ENTER_GETEVENTS);
+}
+
+static int num_cqes(void)
+{
+	return atomic_load(RING_FIELD(params.cq_off.tail)) -
+	       atomic_load(RING_FIELD(params.cq_off.head));
+}
+
+struct cqe {
+	__u64 user_data;
+	__s32 res;
+	__u32 flags;
+};
+
+static struct cqe pop_cqe(void)
+{
+	unsigned int head = atomic_load(RING_FIELD(params.cq_off.head));
+	unsigned int mask = *RING_FIELD(params.cq_off.ring_mask);
+	struct io_uring_cqe *cqes =
+		(struct io_uring_cqe *)(ring + params.cq_off.cqes);
+	struct cqe cqe = {
+		.user_data = cqes[head & mask].user_data,
+		.res = cqes[head & mask].res,
+		.flags = cqes[head & mask].flags,
+	};
+
+	atomic_store(RING_FIELD(params.cq_off.head), head + 1);
+
+	return cqe;
+}
+
+FN_TEST(setup_invalid)
+{
+	struct io_uring_params p;
+
+	memset(&p, 0, sizeof(p));
+	TEST_ERRNO(io_uring_setup(0, &p), EINVAL);
+	TEST_ERRNO(io_uring_setup(100000, &p), EINVAL);
+
+	p.flags = IORING_SETUP_SQPOLL;
+	TEST_ERRNO(io_uring_setup(ENTRIES, &p), EINVAL);
+
+	memset(&p, 0, sizeof(p));
+	p.resv[0] = 1;
+	TEST_ERRNO(io_uring_setup(ENTRIES, &p), EINVAL);
+
+	memset(&p, 0, sizeof(p));
+	p.flags = IORING_SETUP_CLAMP;
+	TEST_RES(io_uring_setup(100000, &p),
+		 p.sq_entries == 32768 && p.cq_entries == 65536 &&
+			 close(_ret) == 0);
+
+	memset(&p, 0, sizeof(p));
+	p.flags = IORING_SETUP_CQSIZE;
+	p.cq_entries = 7;
+	TEST_RES(io_uring_setup(3, &p),
+		 p.sq_entries == 4 && p.cq_entries == 8 && close(_ret) == 0);
+}
+END_TEST()
+
+FN_SETUP(setup)
+{
+	ring_fd = CHECK(io_uring_setup(ENTRIES, &params));
+	CHECK_WITH(params.features, _ret & IORING_FEAT_SINGLE_MMAP);
+
+	ring_size = params.sq_off.array +
+		    params.sq_entries * sizeof(unsigned int);
+	ring = CHECK_WITH(mmap(NULL, ring_size, PROT_READ | PROT_WRITE,
+			       MAP_SHARED, ring_fd, IORING_OFF_SQ_RING),
+			  _ret != MAP_FAILED);
+	sqes = CHECK_WITH(mmap(NULL,
+			       params.sq_entries * sizeof(struct io_uring_sqe),
+			       PROT_READ | PROT_WRITE, MAP_SHARED, ring_fd,
+			       IORING_OFF_SQES),
+			  _ret != MAP_FAILED);
+}
+END_SETUP()
+
+FN_TEST(params)
+{
+	TEST_RES(params.sq_entries, _ret == ENTRIES);
+	TEST_RES(params.cq_entries, _ret == 2 * ENTRIES);
+	TEST_RES(*RING_FIELD(params.sq_off.ring_entries), _ret == ENTRIES);
+	TEST_RES(*RING_FIELD(params.cq_off.ring_entries), _ret == 2 * ENTRIES);
+
+	TEST_ERRNO(mmap(NULL, 4096, PROT_READ, MAP_SHARED, ring_fd, 4096),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(nop)
+{
+	struct io_uring_sqe *sqe;
+	struct cqe cqe;
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_NOP;
+	sqe->user_data = 0x1234;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	TEST_RES(num_cqes(), _ret == 1);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 0x1234);
+	TEST_RES(cqe.res, _ret == 0);
+	TEST_RES(num_cqes(), _ret == 0);
+
+	// Unknown opcodes are completed with `EINVAL`.
+	sqe = get_sqe();
+	sqe->opcode = 0xff;
+	sqe->user_data = 0x5678;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 0x5678);
+	TEST_RES(cqe.res, _ret == -EINVAL);
+
+	TEST_ERRNO(io_uring_enter(STDIN_FILENO, 0, 0, 0), EOPNOTSUPP);
+	TEST_ERRNO(io_uring_enter(ring_fd, 0, 0, 1 << 31), EINVAL);
+}
+END_TEST()
+
+FN_TEST(read_write)
+{
+	struct io_uring_sqe *sqe;
+	struct cqe cqe, cqe2;
+	char wbuf[] = "Hello, io_uring!";
+	char rbuf[sizeof(wbuf)] = { 0 };
+	struct iovec iov[2] = {
+		{ .iov_base = rbuf, .iov_len = 7 },
+		{ .iov_base = rbuf + 7, .iov_len = sizeof(rbuf) - 7 },
+	};
+	int fd;
+
+	fd = TEST_SUCC(open(FILE_NAME, O_RDWR | O_CREAT | O_TRUNC, 0644));
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_WRITE;
+	sqe->fd = fd;
+	sqe->addr = (uintptr_t)wbuf;
+	sqe->len = sizeof(wbuf);
+	sqe->off = 0;
+	sqe->user_data = 1;
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_FSYNC;
+	sqe->fd = fd;
+	sqe->user_data = 2;
+
+	// The requests may be executed in worker threads, so they can complete
+	// in any order.
+	TEST_RES(submit_and_wait(2, 2), _ret == 2);
+	TEST_RES(num_cqes(), _ret == 2);
+	cqe = pop_cqe();
+	cqe2 = pop_cqe();
+	if (cqe.user_data == 2) {
+		struct cqe tmp = cqe;
+		cqe = cqe2;
+		cqe2 = tmp;
+	}
+	TEST_RES(cqe.user_data, _ret == 1 && cqe.res == sizeof(wbuf));
+	TEST_RES(cqe2.user_data, _ret == 2 && cqe2.res == 0);
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_READV;
+	sqe->fd = fd;
+	sqe->addr = (uintptr_t)iov;
+	sqe->len = 2;
+	sqe->off = 0;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	cqe = pop_cqe();
+	TEST_RES(cqe.res, _ret == sizeof(wbuf));
+	TEST_RES(memcmp(rbuf, wbuf, sizeof(wbuf)), _ret == 0);
+
+	// An offset of -1 means the current file position.
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_READ;
+	sqe->fd = fd;
+	sqe->addr = (uintptr_t)rbuf;
+	sqe->len = sizeof(rbuf);
+	sqe->off = -1;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	TEST_RES(pop_cqe().res, _ret == sizeof(wbuf));
+	TEST_RES(lseek(fd, 0, SEEK_CUR), _ret == sizeof(wbuf));
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_READ;
+	sqe->fd = 1000;
+	sqe->addr = (uintptr_t)rbuf;
+	sqe->len = sizeof(rbuf);
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	TEST_RES(pop_cqe().res, _ret == -EBADF);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(FILE_NAME));
+}
+END_TEST()
+
+FN_TEST(socket)
+{
+	struct io_uring_sqe *sqe;
+	struct cqe cqe;
+	int fds[2];
+	char buf[16];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_STREAM, 0, fds));
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_POLL_ADD;
+	sqe->fd = fds[0];
+	sqe->poll_events = POLLIN;
+	sqe->user_data = 1;
+
+	// The request is pending because no data is available.
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+	TEST_RES(num_cqes(), _ret == 0);
+
+	TEST_RES(write(fds[1], "abc", 3), _ret == 3);
+
+	TEST_RES(submit_and_wait(0, 1), _ret == 0);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 1);
+	TEST_RES(cqe.res, _ret == POLLIN);
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_RECV;
+	sqe->fd = fds[0];
+	sqe->addr = (uintptr_t)buf;
+	sqe->len = sizeof(buf);
+	sqe->user_data = 2;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 2);
+	TEST_RES(cqe.res, _ret == 3);
+	TEST_RES(memcmp(buf, "abc", 3), _ret == 0);
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_RECV;
+	sqe->fd = fds[0];
+	sqe->addr = (uintptr_t)buf;
+	sqe->len = sizeof(buf);
+	sqe->user_data = 3;
+
+	TEST_RES(io_uring_enter(ring_fd, 1, 0, 0), _ret == 1);
+	TEST_RES(num_cqes(), _ret == 0);
+
+	TEST_RES(write(fds[1], "def", 3), _ret == 3);
+
+	TEST_RES(submit_and_wait(0, 1), _ret == 0);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 3);
+	TEST_RES(cqe.res, _ret == 3);
+	TEST_RES(memcmp(buf, "def", 3), _ret == 0);
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_SEND;
+	sqe->fd = fds[0];
+	sqe->addr = (uintptr_t) "xyz";
+	sqe->len = 3;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	TEST_RES(pop_cqe().res, _ret == 3);
+	TEST_RES(read(fds[1], buf, sizeof(buf)),
+		 _ret == 3 && memcmp(buf, "xyz", 3) == 0);
+
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_TEST(timeout)
+{
+	struct io_uring_sqe *sqe;
+	struct cqe cqe;
+	struct __kernel_timespec ts = { .tv_sec = 0, .tv_nsec = 10000000 };
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_TIMEOUT;
+	sqe->addr = (uintptr_t)&ts;
+	sqe->len = 1;
+	sqe->user_data = 1;
+
+	TEST_RES(submit_and_wait(1, 1), _ret == 1);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 1);
+	TEST_RES(cqe.res, _ret == -ETIME);
+
+	// The timeout completes early if enough requests are completed.
+	ts.tv_sec = 100;
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_TIMEOUT;
+	sqe->addr = (uintptr_t)&ts;
+	sqe->len = 1;
+	sqe->off = 1;
+	sqe->user_data = 2;
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_NOP;
+	sqe->user_data = 3;
+
+	TEST_RES(submit_and_wait(2, 2), _ret == 2);
+	TEST_RES(num_cqes(), _ret == 2);
+	TEST_RES(pop_cqe().user_data, _ret == 3);
+	cqe = pop_cqe();
+	TEST_RES(cqe.user_data, _ret == 2);
+	TEST_RES(cqe.res, _ret == 0);
+}
+END_TEST()
+
+FN_TEST(register)
+{
+	struct io_uring_sqe *sqe;
+	struct io_uring_probe *probe;
+	int fds[2] = { -1, STDOUT_FILENO };
+
+	probe = calloc(1, sizeof(*probe) + 256 * sizeof(struct io_uring_probe_op));
+	TEST_SUCC(io_uring_register(ring_fd, IORING_REGISTER_PROBE, probe, 256));
+	TEST_RES(probe->ops_len, _ret == probe->last_op + 1);
+	TEST_RES(probe->ops[IORING_OP_NOP].flags, _ret & IO_URING_OP_SUPPORTED);
+	TEST_RES(probe->ops[IORING_OP_READ].flags,
+		 _ret & IO_URING_OP_SUPPORTED);
+	TEST_RES(probe->ops[IORING_OP_OPENAT].flags,
+		 !(_ret & IO_URING_OP_SUPPORTED));
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_REGISTER_PROBE, probe,
+				     256),
+		   EINVAL);
+	free(probe);
+
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_UNREGISTER_FILES, NULL, 0),
+		   ENXIO);
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_REGISTER_FILES, &ring_fd,
+				     1),
+		   EBADF);
+	TEST_SUCC(io_uring_register(ring_fd, IORING_REGISTER_FILES, fds, 2));
+	TEST_ERRNO(io_uring_register(ring_fd, IORING_REGISTER_FILES, fds, 2),
+		   EBUSY);
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_WRITE;
+	sqe->flags = IOSQE_FIXED_FILE;
+	sqe->fd = 1;
+	sqe->addr = (uintptr_t) "";
+	sqe->len = 0;
+	sqe->off = -1;
+
+	sqe = get_sqe();
+	sqe->opcode = IORING_OP_WRITE;
+	sqe->flags = IOSQE_FIXED_FILE;
+	sqe->fd = 0;
+	sqe->off = -1;
+
+	TEST_RES(submit_and_wait(2, 2), _ret == 2);
+	TEST_RES(pop_cqe().res, _ret == 0);
+	TEST_RES(pop_cqe().res, _ret == -EBADF);
+
+	TEST_SUCC(io_uring_register(ring_fd, IORING_UNREGISTER_FILES, NULL, 0));
+	TEST_ERRNO(io_uring_register(ring_fd, 0xffff, NULL, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(cq_overflow)
+{
+	struct io_uring_sqe *sqe;
+	unsigned int i;
+
+	TEST_RES(params.features, _ret & IORING_FEAT_NODROP);
+
+	// Post more CQEs than the CQ can hold.
+	for (i = 0; i < params.cq_entries + ENTRIES; i++) {
+		sqe = get_sqe();
+		sqe->opcode = IORING_OP_NOP;
+		sqe->user_data = i;
+		if ((i + 1) % ENTRIES == 0)
+			TEST_RES(io_uring_enter(ring_fd, ENTRIES, 0, 0),
+				 _ret == ENTRIES);
+	}
+
+	// The overflowed CQEs are kept in the kernel instead of being dropped.
+	TEST_RES(num_cqes(), _ret == params.cq_entries);
+	TEST_RES(*RING_FIELD(params.sq_off.flags),
+		 _ret & IORING_SQ_CQ_OVERFLOW);
+	TEST_RES(*RING_FIELD(params.cq_off.overflow), _ret == 0);
+	for (i = 0; i < params.cq_entries; i++)
+		TEST_RES(pop_cqe().user_data, _ret == i);
+
+	// They are posted in order once there is room in the CQ.
+	TEST_RES(submit_and_wait(0, ENTRIES), _ret == 0);
+	TEST_RES(num_cqes(), _ret == ENTRIES);
+	TEST_RES(*RING_FIELD(params.sq_off.flags),
+		 !(_ret & IORING_SQ_CQ_OVERFLOW));
+	for (i = 0; i < ENTRIES; i++)
+		TEST_RES(pop_cqe().user_data, _ret == params.cq_entries + i);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(munmap(sqes, params.sq_entries * sizeof(struct io_uring_sqe)));
+	CHECK(munmap(ring, ring_size));
+	CHECK(close(ring_fd));
+}
+END_SETUP()
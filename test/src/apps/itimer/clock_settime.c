@@ -0,0 +1,346 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <poll.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <time.h>
+#include <unistd.h>
+#include <sys/syscall.h>
+#include <sys/time.h>
+#include <sys/timerfd.h>
+#include <sys/timex.h>
+#include <sys/wait.h>
+
+#define NSEC_PER_SEC 1000000000L
+#define JUMP_SECS 1000
+
+static struct timespec start_realtime;
+static struct timespec start_monotonic;
+
+static long long timespec_to_ns(const struct timespec *ts)
+{
+	return ts->tv_sec * NSEC_PER_SEC + ts->tv_nsec;
+}
+
+static long long now_ns(clockid_t clockid)
+{
+	struct timespec ts;
+
+	CHECK(clock_gettime(clockid, &ts));
+	return timespec_to_ns(&ts);
+}
+
+// Sets the real time to the current time plus `offset_secs` seconds.
+static int jump_realtime(long offset_secs)
+{
+	struct timespec ts;
+
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts));
+	ts.tv_sec += offset_secs;
+	return clock_settime(CLOCK_REALTIME, &ts);
+}
+
+FN_SETUP(init)
+{
+	CHECK(clock_gettime(CLOCK_REALTIME, &start_realtime));
+	CHECK(clock_gettime(CLOCK_MONOTONIC, &start_monotonic));
+}
+END_SETUP()
+
+FN_TEST(clock_settime)
+{
+	long long realtime, monotonic;
+
+	realtime = now_ns(CLOCK_REALTIME);
+	monotonic = now_ns(CLOCK_MONOTONIC);
+	TEST_SUCC(jump_realtime(JUMP_SECS));
+
+	// The real time jumps, but the monotonic time does not.
+	TEST_RES(now_ns(CLOCK_REALTIME) - realtime,
+		 _ret >= JUMP_SECS * NSEC_PER_SEC &&
+			 _ret < (JUMP_SECS + 1) * NSEC_PER_SEC);
+	TEST_RES(now_ns(CLOCK_REALTIME_COARSE) - realtime,
+		 _ret >= (JUMP_SECS - 1) * NSEC_PER_SEC &&
+			 _ret < (JUMP_SECS + 1) * NSEC_PER_SEC);
+	TEST_RES(now_ns(CLOCK_MONOTONIC) - monotonic, _ret < NSEC_PER_SEC);
+	TEST_RES(time(NULL) - realtime / NSEC_PER_SEC,
+		 _ret >= JUMP_SECS && _ret <= JUMP_SECS + 1);
+
+	TEST_SUCC(jump_realtime(-JUMP_SECS));
+	TEST_RES(now_ns(CLOCK_REALTIME) - realtime,
+		 _ret >= 0 && _ret < NSEC_PER_SEC);
+}
+END_TEST()
+
+FN_TEST(clock_settime_invalid)
+{
+	struct timespec ts;
+
+	CHECK(clock_gettime(CLOCK_REALTIME, &ts));
+
+	ts.tv_nsec = NSEC_PER_SEC;
+	TEST_ERRNO(clock_settime(CLOCK_REALTIME, &ts), EINVAL);
+	ts.tv_nsec = -1;
+	TEST_ERRNO(clock_settime(CLOCK_REALTIME, &ts), EINVAL);
+	ts.tv_nsec = 0;
+
+	TEST_ERRNO(clock_settime(CLOCK_MONOTONIC, &ts), EINVAL);
+	TEST_ERRNO(clock_settime(CLOCK_BOOTTIME, &ts), EINVAL);
+
+	// The real time cannot be earlier than the monotonic time.
+	ts.tv_sec = 0;
+	TEST_ERRNO(clock_settime(CLOCK_REALTIME, &ts), EINVAL);
+}
+END_TEST()
+
+FN_TEST(settimeofday)
+{
+	struct timeval tv;
+	struct timezone tz = { .tz_minuteswest = 16 * 60 };
+	long long realtime;
+
+	realtime = now_ns(CLOCK_REALTIME);
+	CHECK(gettimeofday(&tv, NULL));
+	tv.tv_sec += JUMP_SECS;
+	TEST_SUCC(settimeofday(&tv, NULL));
+	TEST_RES(now_ns(CLOCK_REALTIME) - realtime,
+		 _ret >= JUMP_SECS * NSEC_PER_SEC &&
+			 _ret < (JUMP_SECS + 1) * NSEC_PER_SEC);
+
+	tv.tv_sec -= JUMP_SECS;
+	TEST_SUCC(settimeofday(&tv, NULL));
+
+	TEST_ERRNO(settimeofday(&tv, &tz), EINVAL);
+	tv.tv_usec = 1000000;
+	TEST_ERRNO(settimeofday(&tv, NULL), EINVAL);
+	TEST_SUCC(syscall(SYS_settimeofday, NULL, NULL));
+}
+END_TEST()
+
+FN_TEST(adjtimex)
+{
+	struct timex tx = {};
+
+	TEST_RES(adjtimex(&tx),
+		 (_ret == TIME_OK || _ret == TIME_ERROR) &&
+			 tx.tolerance == 500 << 16);
+
+	// Set and restore the frequency offset.
+	tx.modes = ADJ_FREQUENCY;
+	tx.freq = 100 << 16;
+	TEST_RES(adjtimex(&tx), tx.freq == 100 << 16);
+	tx.modes = 0;
+	TEST_RES(adjtimex(&tx), tx.freq == 100 << 16);
+
+	// The frequency offset is clamped.
+	tx.modes = ADJ_FREQUENCY;
+	tx.freq = 1000 << 16;
+	TEST_RES(adjtimex(&tx), tx.freq == 500 << 16);
+	tx.freq = 0;
+	TEST_RES(adjtimex(&tx), tx.freq == 0);
+
+	tx.modes = ADJ_TICK;
+	tx.tick = 0;
+	TEST_ERRNO(adjtimex(&tx), EINVAL);
+
+	tx.modes = ADJ_SETOFFSET;
+	tx.time.tv_sec = 0;
+	tx.time.tv_usec = 1000000;
+	TEST_ERRNO(adjtimex(&tx), EINVAL);
+
+	tx.modes = 0x8000;
+	TEST_ERRNO(adjtimex(&tx), EINVAL);
+}
+END_TEST()
+
+FN_TEST(adjtimex_setoffset)
+{
+	struct timex tx = {};
+	long long realtime;
+
+	realtime = now_ns(CLOCK_REALTIME);
+	tx.modes = ADJ_SETOFFSET;
+	tx.time.tv_sec = JUMP_SECS;
+	tx.time.tv_usec = 0;
+	TEST_SUCC(adjtimex(&tx));
+	TEST_RES(now_ns(CLOCK_REALTIME) - realtime,
+		 _ret >= JUMP_SECS * NSEC_PER_SEC &&
+			 _ret < (JUMP_SECS + 1) * NSEC_PER_SEC);
+
+	// The offset is negative, but the microseconds are not.
+	tx.modes = ADJ_SETOFFSET | ADJ_NANO;
+	tx.time.tv_sec = -JUMP_SECS - 1;
+	tx.time.tv_usec = NSEC_PER_SEC / 2;
+	TEST_SUCC(adjtimex(&tx));
+	TEST_RES(now_ns(CLOCK_REALTIME) - realtime,
+		 _ret >= -NSEC_PER_SEC && _ret < NSEC_PER_SEC);
+
+	tx.modes = ADJ_MICRO;
+	TEST_SUCC(adjtimex(&tx));
+}
+END_TEST()
+
+FN_TEST(adjtime)
+{
+	struct timex tx = {};
+
+	// Slew the clock by 100 milliseconds.
+	tx.modes = ADJ_OFFSET_SINGLESHOT;
+	tx.offset = 100000;
+	TEST_SUCC(adjtimex(&tx));
+
+	tx.modes = ADJ_OFFSET_SS_READ;
+	TEST_RES(adjtimex(&tx), tx.offset > 0 && tx.offset <= 100000);
+
+	// Cancel the slewing.
+	tx.modes = ADJ_OFFSET_SINGLESHOT;
+	tx.offset = 0;
+	TEST_RES(adjtimex(&tx), tx.offset > 0 && tx.offset <= 100000);
+	tx.modes = ADJ_OFFSET_SS_READ;
+	TEST_RES(adjtimex(&tx), tx.offset == 0);
+}
+END_TEST()
+
+FN_TEST(clock_adjtime)
+{
+	struct timex tx = {};
+
+	TEST_RES(clock_adjtime(CLOCK_REALTIME, &tx),
+		 _ret == TIME_OK || _ret == TIME_ERROR);
+	TEST_ERRNO(clock_adjtime(CLOCK_MONOTONIC, &tx), EOPNOTSUPP);
+	TEST_ERRNO(clock_adjtime(100, &tx), EINVAL);
+}
+END_TEST()
+
+FN_TEST(no_cap_sys_time)
+{
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		struct timespec ts;
+		struct timeval tv;
+		struct timex tx = {};
+
+		// Drop the `CAP_SYS_TIME` capability.
+		CHECK(setuid(65534));
+
+		CHECK(clock_gettime(CLOCK_REALTIME, &ts));
+		CHECK_WITH(clock_settime(CLOCK_REALTIME, &ts),
+			   _ret < 0 && errno == EPERM);
+		CHECK(gettimeofday(&tv, NULL));
+		CHECK_WITH(settimeofday(&tv, NULL),
+			   _ret < 0 && errno == EPERM);
+
+		// Reading the clock state is allowed.
+		CHECK(adjtimex(&tx));
+		tx.modes = ADJ_OFFSET_SS_READ;
+		CHECK(adjtimex(&tx));
+
+		tx.modes = ADJ_FREQUENCY;
+		CHECK_WITH(adjtimex(&tx), _ret < 0 && errno == EPERM);
+		tx.modes = ADJ_OFFSET_SINGLESHOT;
+		CHECK_WITH(adjtimex(&tx), _ret < 0 && errno == EPERM);
+
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(timerfd_relative)
+{
+	struct itimerspec its = { .it_value = { .tv_sec = 2 } };
+	int fd;
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_REALTIME, TFD_NONBLOCK));
+	TEST_SUCC(timerfd_settime(fd, 0, &its, NULL));
+
+	// The relative timer does not expire when the clock jumps forward.
+	TEST_SUCC(jump_realtime(JUMP_SECS));
+	usleep(100 * 1000);
+	TEST_ERRNO(read(fd, &(uint64_t){ 0 }, sizeof(uint64_t)), EAGAIN);
+	TEST_RES(timerfd_gettime(fd, &its),
+		 its.it_value.tv_sec == 1 || its.it_value.tv_sec == 0);
+
+	TEST_SUCC(jump_realtime(-JUMP_SECS));
+	TEST_RES(timerfd_gettime(fd, &its),
+		 its.it_value.tv_sec == 1 || its.it_value.tv_sec == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(timerfd_absolute)
+{
+	struct itimerspec its = {};
+	uint64_t ticks;
+	int fd;
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_REALTIME, 0));
+	CHECK(clock_gettime(CLOCK_REALTIME, &its.it_value));
+	its.it_value.tv_sec += JUMP_SECS / 2;
+	TEST_SUCC(timerfd_settime(fd, TFD_TIMER_ABSTIME, &its, NULL));
+
+	// The absolute timer expires when the clock jumps over the expired time.
+	TEST_SUCC(jump_realtime(JUMP_SECS));
+	TEST_RES(read(fd, &ticks, sizeof(ticks)),
+		 _ret == sizeof(ticks) && ticks == 1);
+
+	TEST_SUCC(jump_realtime(-JUMP_SECS));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(timerfd_cancel_on_set)
+{
+	struct itimerspec its = {};
+	struct pollfd pfd = { .events = POLLIN };
+	uint64_t ticks;
+	int fd;
+
+	fd = TEST_SUCC(timerfd_create(CLOCK_REALTIME, TFD_NONBLOCK));
+	CHECK(clock_gettime(CLOCK_REALTIME, &its.it_value));
+	its.it_value.tv_sec += JUMP_SECS;
+	TEST_SUCC(timerfd_settime(
+		fd, TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET, &its, NULL));
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	pfd.fd = fd;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	// The timer is canceled when the clock is set.
+	TEST_SUCC(jump_realtime(1));
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLIN);
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), ECANCELED);
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	// The timer is not canceled if it is not an absolute timer.
+	its.it_value.tv_sec = JUMP_SECS;
+	TEST_SUCC(timerfd_settime(fd, TFD_TIMER_CANCEL_ON_SET, &its, NULL));
+	TEST_SUCC(jump_realtime(-1));
+	TEST_ERRNO(read(fd, &ticks, sizeof(ticks)), EAGAIN);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	struct timespec now;
+	long long realtime;
+
+	// Restore the real time.
+	realtime = timespec_to_ns(&start_realtime) + now_ns(CLOCK_MONOTONIC) -
+		   timespec_to_ns(&start_monotonic);
+	now.tv_sec = realtime / NSEC_PER_SEC;
+	now.tv_nsec = realtime % NSEC_PER_SEC;
+	CHECK(clock_settime(CLOCK_REALTIME, &now));
+}
+END_SETUP()
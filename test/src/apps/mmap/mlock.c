@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/resource.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <linux/capability.h>
+
+#define PAGE_SIZE 4096
+#define NUM_PAGES 4
+#define TOTAL_SIZE (PAGE_SIZE * NUM_PAGES)
+
+#define CHECK_MM(func) CHECK_WITH(func, _ret != MAP_FAILED)
+
+static char *addr;
+static unsigned char vec[NUM_PAGES];
+
+// Returns the locked memory size of the current process in kB.
+static long get_vm_locked_kb(void)
+{
+	FILE *f;
+	char line[256];
+	long locked_kb = -1;
+
+	f = CHECK_WITH(fopen("/proc/self/status", "r"), _ret != NULL);
+	while (fgets(line, sizeof(line), f)) {
+		if (strncmp(line, "VmLck:", 6) == 0) {
+			sscanf(line + 6, "%ld", &locked_kb);
+			break;
+		}
+	}
+	CHECK(fclose(f));
+
+	return locked_kb;
+}
+
+// Returns the number of resident pages in `vec`.
+static int count_resident(void)
+{
+	int i, count = 0;
+
+	for (i = 0; i < NUM_PAGES; i++)
+		count += vec[i] & 1;
+
+	return count;
+}
+
+FN_SETUP(init)
+{
+	addr = CHECK_MM(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+}
+END_SETUP()
+
+FN_TEST(mlock)
+{
+	TEST_RES(mincore(addr, TOTAL_SIZE, vec), count_resident() == 0);
+
+	// The pages are faulted in when they are locked.
+	TEST_SUCC(mlock(addr, TOTAL_SIZE));
+	TEST_RES(mincore(addr, TOTAL_SIZE, vec),
+		 count_resident() == NUM_PAGES);
+	TEST_RES(get_vm_locked_kb(), _ret == TOTAL_SIZE / 1024);
+
+	// Locking the pages again does not count them twice.
+	TEST_SUCC(mlock(addr, TOTAL_SIZE));
+	TEST_RES(get_vm_locked_kb(), _ret == TOTAL_SIZE / 1024);
+
+	// The pages stay resident after they are unlocked.
+	TEST_SUCC(munlock(addr, TOTAL_SIZE));
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+	TEST_RES(mincore(addr, TOTAL_SIZE, vec),
+		 count_resident() == NUM_PAGES);
+}
+END_TEST()
+
+FN_TEST(mlock_unaligned)
+{
+	// The range is extended to page boundaries.
+	TEST_SUCC(mlock(addr + PAGE_SIZE + 1, PAGE_SIZE));
+	TEST_RES(get_vm_locked_kb(), _ret == PAGE_SIZE * 2 / 1024);
+	TEST_SUCC(munlock(addr + 1, TOTAL_SIZE - 1));
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+
+	TEST_SUCC(mlock(addr, 0));
+	TEST_SUCC(munlock(addr, 0));
+}
+END_TEST()
+
+FN_TEST(mlock2_onfault)
+{
+	char *buf;
+
+	buf = TEST_SUCC(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+
+	// The pages are not faulted in when they are locked.
+	TEST_SUCC(mlock2(buf, TOTAL_SIZE, MLOCK_ONFAULT));
+	TEST_RES(get_vm_locked_kb(), _ret == TOTAL_SIZE / 1024);
+	TEST_RES(mincore(buf, TOTAL_SIZE, vec), count_resident() == 0);
+
+	buf[PAGE_SIZE] = 'a';
+	TEST_RES(mincore(buf, TOTAL_SIZE, vec),
+		 count_resident() == 1 && vec[1] == 1);
+
+	TEST_ERRNO(mlock2(buf, TOTAL_SIZE, 2), EINVAL);
+
+	TEST_SUCC(munmap(buf, TOTAL_SIZE));
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(unmapped)
+{
+	char *buf;
+
+	buf = TEST_SUCC(mmap(NULL, PAGE_SIZE * 3, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	TEST_SUCC(munmap(buf + PAGE_SIZE, PAGE_SIZE));
+
+	TEST_ERRNO(mlock(buf, PAGE_SIZE * 3), ENOMEM);
+	TEST_ERRNO(munlock(buf, PAGE_SIZE * 3), ENOMEM);
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+
+	TEST_ERRNO(mincore(buf, PAGE_SIZE * 3, vec), ENOMEM);
+
+	TEST_SUCC(munmap(buf, PAGE_SIZE * 3));
+}
+END_TEST()
+
+FN_TEST(mincore_invalid)
+{
+	TEST_ERRNO(mincore(addr + 1, PAGE_SIZE, vec), EINVAL);
+	TEST_ERRNO(mincore(addr, PAGE_SIZE, NULL), EFAULT);
+	TEST_SUCC(mincore(addr, 0, NULL));
+}
+END_TEST()
+
+FN_TEST(mlockall)
+{
+	char *buf;
+
+	TEST_ERRNO(mlockall(0), EINVAL);
+	TEST_ERRNO(mlockall(MCL_ONFAULT), EINVAL);
+	TEST_ERRNO(mlockall(8), EINVAL);
+
+	// New mappings are locked and faulted in.
+	TEST_SUCC(mlockall(MCL_CURRENT | MCL_FUTURE));
+	TEST_RES(get_vm_locked_kb(), _ret >= TOTAL_SIZE / 1024);
+	buf = TEST_SUCC(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	TEST_RES(mincore(buf, TOTAL_SIZE, vec), count_resident() == NUM_PAGES);
+	TEST_SUCC(munmap(buf, TOTAL_SIZE));
+
+	TEST_SUCC(munlockall());
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+
+	// New mappings are locked but not faulted in.
+	TEST_SUCC(mlockall(MCL_FUTURE | MCL_ONFAULT));
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+	buf = TEST_SUCC(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	TEST_RES(get_vm_locked_kb(), _ret == TOTAL_SIZE / 1024);
+	TEST_RES(mincore(buf, TOTAL_SIZE, vec), count_resident() == 0);
+	TEST_SUCC(munmap(buf, TOTAL_SIZE));
+
+	// New mappings are no longer locked.
+	TEST_SUCC(munlockall());
+	buf = TEST_SUCC(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+	TEST_SUCC(munmap(buf, TOTAL_SIZE));
+}
+END_TEST()
+
+FN_TEST(map_locked)
+{
+	char *buf;
+
+	buf = TEST_SUCC(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS | MAP_LOCKED, -1, 0));
+	TEST_RES(get_vm_locked_kb(), _ret == TOTAL_SIZE / 1024);
+	TEST_RES(mincore(buf, TOTAL_SIZE, vec), count_resident() == NUM_PAGES);
+
+	TEST_SUCC(munmap(buf, TOTAL_SIZE));
+	TEST_RES(get_vm_locked_kb(), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(fork_unlocks)
+{
+	int status;
+	pid_t pid;
+
+	TEST_SUCC(mlock(addr, TOTAL_SIZE));
+
+	// Memory locks are not inherited by the child.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(get_vm_locked_kb(), _ret == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+
+	TEST_SUCC(munlock(addr, TOTAL_SIZE));
+}
+END_TEST()
+
+FN_TEST(rlimit_memlock)
+{
+	struct rlimit rlimit = { .rlim_cur = PAGE_SIZE * 2,
+				 .rlim_max = PAGE_SIZE * 2 };
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setrlimit(RLIMIT_MEMLOCK, &rlimit));
+		// Drop the `CAP_IPC_LOCK` capability.
+		CHECK(setuid(65534));
+
+		CHECK_WITH(mlock(addr, TOTAL_SIZE),
+			   _ret < 0 && errno == ENOMEM);
+		CHECK(mlock(addr, PAGE_SIZE * 2));
+		CHECK_WITH(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+				MAP_PRIVATE | MAP_ANONYMOUS | MAP_LOCKED, -1,
+				0),
+			   _ret == MAP_FAILED && errno == EAGAIN);
+		CHECK_WITH(mlockall(MCL_CURRENT), _ret < 0 && errno == ENOMEM);
+		CHECK(munlock(addr, PAGE_SIZE * 2));
+
+		rlimit.rlim_cur = 0;
+		CHECK(setrlimit(RLIMIT_MEMLOCK, &rlimit));
+		CHECK_WITH(mlock(addr, PAGE_SIZE), _ret < 0 && errno == EPERM);
+		CHECK_WITH(mlockall(MCL_FUTURE), _ret < 0 && errno == EPERM);
+		CHECK_WITH(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+				MAP_PRIVATE | MAP_ANONYMOUS | MAP_LOCKED, -1,
+				0),
+			   _ret == MAP_FAILED && errno == EPERM);
+
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(rlimit_memlock_root)
+{
+	struct rlimit rlimit = { .rlim_cur = 0, .rlim_max = 0 };
+	struct __user_cap_header_struct hdr = {
+		.version = _LINUX_CAPABILITY_VERSION_3,
+		.pid = 0,
+	};
+	struct __user_cap_data_struct data[2] = {};
+	int status;
+	pid_t pid;
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setrlimit(RLIMIT_MEMLOCK, &rlimit));
+		// Drop all the capabilities, but the root user can still lock memory.
+		CHECK(syscall(SYS_capset, &hdr, data));
+
+		CHECK(mlock(addr, PAGE_SIZE));
+		CHECK(munlock(addr, PAGE_SIZE));
+
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(munmap(addr, TOTAL_SIZE));
+}
+END_SETUP()
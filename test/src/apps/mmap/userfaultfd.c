@@ -0,0 +1,395 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <fcntl.h>
+#include <linux/userfaultfd.h>
+#include <poll.h>
+#include <pthread.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define PAGE_SIZE 4096
+#define NUM_PAGES 4
+#define TOTAL_SIZE (PAGE_SIZE * NUM_PAGES)
+
+static int uffd;
+static char *addr;
+static char page[PAGE_SIZE];
+
+static int create_uffd(int flags, __u64 features)
+{
+	struct uffdio_api api = { .api = UFFD_API, .features = features };
+	int fd;
+
+	fd = CHECK(syscall(SYS_userfaultfd, flags));
+	CHECK(ioctl(fd, UFFDIO_API, &api));
+
+	return fd;
+}
+
+static void register_range(int fd, char *start, size_t len)
+{
+	struct uffdio_register reg = {
+		.range = { .start = (unsigned long)start, .len = len },
+		.mode = UFFDIO_REGISTER_MODE_MISSING,
+	};
+
+	CHECK(ioctl(fd, UFFDIO_REGISTER, &reg));
+}
+
+// A thread that reads a byte from the memory, which may cause a page fault.
+struct fault_thread {
+	pthread_t thread;
+	volatile char *ptr;
+	volatile char value;
+	volatile int is_done;
+};
+
+static void *fault_thread_func(void *arg)
+{
+	struct fault_thread *ft = arg;
+
+	ft->value = *ft->ptr;
+	ft->is_done = 1;
+
+	return NULL;
+}
+
+static void start_fault_thread(struct fault_thread *ft, char *ptr)
+{
+	ft->ptr = ptr;
+	ft->value = 0;
+	ft->is_done = 0;
+	CHECK_WITH(pthread_create(&ft->thread, NULL, fault_thread_func, ft),
+		   _ret == 0);
+}
+
+static void join_fault_thread(struct fault_thread *ft)
+{
+	CHECK_WITH(pthread_join(ft->thread, NULL), _ret == 0);
+}
+
+FN_SETUP(init)
+{
+	addr = CHECK_WITH(mmap(NULL, TOTAL_SIZE, PROT_READ | PROT_WRITE,
+			       MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+			  _ret != MAP_FAILED);
+	memset(page, 'a', sizeof(page));
+}
+END_SETUP()
+
+FN_TEST(api)
+{
+	struct uffdio_api api;
+	struct uffdio_register reg = {
+		.range = { .start = (unsigned long)addr, .len = TOTAL_SIZE },
+		.mode = UFFDIO_REGISTER_MODE_MISSING,
+	};
+	struct pollfd pfd;
+	int fd;
+
+	TEST_ERRNO(syscall(SYS_userfaultfd, 0x4000), EINVAL);
+
+	fd = TEST_SUCC(syscall(SYS_userfaultfd, O_CLOEXEC | O_NONBLOCK));
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+	TEST_RES(fcntl(fd, F_GETFL), (_ret & O_NONBLOCK) != 0);
+
+	// Other ioctls are not allowed before the API handshake.
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+
+	pfd.fd = fd;
+	pfd.events = POLLIN;
+	TEST_RES(poll(&pfd, 1, 0), _ret == 1 && pfd.revents == POLLERR);
+
+	api.api = 0xAB;
+	api.features = 0;
+	TEST_ERRNO(ioctl(fd, UFFDIO_API, &api), EINVAL);
+	TEST_RES(0, api.api == 0 && api.features == 0 && api.ioctls == 0);
+
+	api.api = UFFD_API;
+	api.features = 1ULL << 63;
+	TEST_ERRNO(ioctl(fd, UFFDIO_API, &api), EINVAL);
+
+	api.api = UFFD_API;
+	api.features = UFFD_FEATURE_THREAD_ID;
+	TEST_RES(ioctl(fd, UFFDIO_API, &api),
+		 api.api == UFFD_API &&
+			 (api.features & UFFD_FEATURE_THREAD_ID) &&
+			 (api.ioctls & (1ULL << _UFFDIO_REGISTER)) &&
+			 (api.ioctls & (1ULL << _UFFDIO_UNREGISTER)) &&
+			 (api.ioctls & (1ULL << _UFFDIO_API)));
+
+	// The API handshake can only be done once.
+	api.api = UFFD_API;
+	api.features = 0;
+	TEST_ERRNO(ioctl(fd, UFFDIO_API, &api), EINVAL);
+
+	TEST_RES(poll(&pfd, 1, 0), _ret == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(register_invalid)
+{
+	struct uffdio_register reg;
+	char *unmapped_addr, *file_addr;
+	int fd, file_fd;
+
+	fd = create_uffd(O_CLOEXEC, 0);
+
+	reg.range.start = (unsigned long)addr + 1;
+	reg.range.len = PAGE_SIZE;
+	reg.mode = UFFDIO_REGISTER_MODE_MISSING;
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+
+	reg.range.start = (unsigned long)addr;
+	reg.range.len = 0;
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+
+	reg.range.len = PAGE_SIZE;
+	reg.mode = 0;
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+	reg.mode = UFFDIO_REGISTER_MODE_MINOR;
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+
+	// The range is not mapped.
+	unmapped_addr = TEST_SUCC(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+				       MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	TEST_SUCC(munmap(unmapped_addr, PAGE_SIZE));
+	reg.range.start = (unsigned long)unmapped_addr;
+	reg.mode = UFFDIO_REGISTER_MODE_MISSING;
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+
+	// Private file-backed mappings are not supported.
+	file_fd = TEST_SUCC(open("/proc/self/exe", O_RDONLY));
+	file_addr = TEST_SUCC(
+		mmap(NULL, PAGE_SIZE, PROT_READ, MAP_PRIVATE, file_fd, 0));
+	reg.range.start = (unsigned long)file_addr;
+	TEST_ERRNO(ioctl(fd, UFFDIO_REGISTER, &reg), EINVAL);
+	TEST_SUCC(munmap(file_addr, PAGE_SIZE));
+	TEST_SUCC(close(file_fd));
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(user_mode_only)
+{
+	char *buf;
+	int fd, pipe_fds[2], status;
+	pid_t pid;
+
+	// Unprivileged users can only create userfaultfds that handle user-mode page faults.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setuid(1000));
+		CHECK_WITH(syscall(SYS_userfaultfd, O_CLOEXEC),
+			   _ret == -1 && errno == EPERM);
+		CHECK(syscall(SYS_userfaultfd, O_CLOEXEC | UFFD_USER_MODE_ONLY));
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+
+	fd = create_uffd(O_CLOEXEC | UFFD_USER_MODE_ONLY, 0);
+	buf = TEST_SUCC(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	register_range(fd, buf, PAGE_SIZE);
+
+	// The page faults caused by the kernel on the missing pages fail.
+	TEST_SUCC(pipe(pipe_fds));
+	TEST_ERRNO(write(pipe_fds[1], buf, 1), EFAULT);
+	TEST_SUCC(close(pipe_fds[0]));
+	TEST_SUCC(close(pipe_fds[1]));
+
+	TEST_SUCC(munmap(buf, PAGE_SIZE));
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_SETUP(register)
+{
+	uffd = create_uffd(O_CLOEXEC, UFFD_FEATURE_THREAD_ID);
+	register_range(uffd, addr, TOTAL_SIZE);
+}
+END_SETUP()
+
+FN_TEST(copy)
+{
+	struct fault_thread ft;
+	struct uffd_msg msg;
+	struct uffdio_copy copy;
+
+	start_fault_thread(&ft, addr + 123);
+
+	TEST_RES(read(uffd, &msg, sizeof(msg)),
+		 _ret == sizeof(msg) && msg.event == UFFD_EVENT_PAGEFAULT &&
+			 msg.arg.pagefault.address == (unsigned long)addr &&
+			 msg.arg.pagefault.flags == 0 &&
+			 msg.arg.pagefault.feat.ptid != 0);
+	TEST_RES(ft.is_done, _ret == 0);
+
+	copy.dst = (unsigned long)addr;
+	copy.src = (unsigned long)page;
+	copy.len = PAGE_SIZE;
+	copy.mode = 0;
+	copy.copy = 0;
+	TEST_RES(ioctl(uffd, UFFDIO_COPY, &copy), copy.copy == PAGE_SIZE);
+
+	join_fault_thread(&ft);
+	TEST_RES(ft.value, _ret == 'a');
+
+	// The page has been filled.
+	copy.copy = 0;
+	TEST_ERRNO(ioctl(uffd, UFFDIO_COPY, &copy), EEXIST);
+	TEST_RES(copy.copy, _ret == -EEXIST);
+}
+END_TEST()
+
+FN_TEST(zeropage)
+{
+	struct fault_thread ft;
+	struct uffd_msg msg;
+	struct uffdio_zeropage zeropage;
+
+	start_fault_thread(&ft, addr + PAGE_SIZE);
+
+	TEST_RES(read(uffd, &msg, sizeof(msg)),
+		 _ret == sizeof(msg) && msg.event == UFFD_EVENT_PAGEFAULT &&
+			 msg.arg.pagefault.address ==
+				 (unsigned long)addr + PAGE_SIZE);
+
+	zeropage.range.start = (unsigned long)addr + PAGE_SIZE;
+	zeropage.range.len = PAGE_SIZE;
+	zeropage.mode = 0;
+	zeropage.zeropage = 0;
+	TEST_RES(ioctl(uffd, UFFDIO_ZEROPAGE, &zeropage),
+		 zeropage.zeropage == PAGE_SIZE);
+
+	join_fault_thread(&ft);
+	TEST_RES(ft.value, _ret == 0);
+
+	// The page can be written to.
+	addr[PAGE_SIZE] = 'b';
+	TEST_RES(addr[PAGE_SIZE], _ret == 'b');
+}
+END_TEST()
+
+FN_TEST(dontwake)
+{
+	struct fault_thread ft;
+	struct uffd_msg msg;
+	struct uffdio_copy copy;
+	struct uffdio_range range;
+
+	start_fault_thread(&ft, addr + PAGE_SIZE * 2);
+
+	TEST_RES(read(uffd, &msg, sizeof(msg)), _ret == sizeof(msg));
+
+	copy.dst = (unsigned long)addr + PAGE_SIZE * 2;
+	copy.src = (unsigned long)page;
+	copy.len = PAGE_SIZE;
+	copy.mode = UFFDIO_COPY_MODE_DONTWAKE;
+	copy.copy = 0;
+	TEST_RES(ioctl(uffd, UFFDIO_COPY, &copy), copy.copy == PAGE_SIZE);
+
+	// The faulting thread is not woken up.
+	usleep(100 * 1000);
+	TEST_RES(ft.is_done, _ret == 0);
+
+	range.start = (unsigned long)addr + PAGE_SIZE * 2;
+	range.len = PAGE_SIZE;
+	TEST_SUCC(ioctl(uffd, UFFDIO_WAKE, &range));
+
+	join_fault_thread(&ft);
+	TEST_RES(ft.value, _ret == 'a');
+}
+END_TEST()
+
+FN_TEST(nonblocking_read)
+{
+	struct uffd_msg msg;
+	int flags;
+
+	flags = TEST_SUCC(fcntl(uffd, F_GETFL));
+	TEST_SUCC(fcntl(uffd, F_SETFL, flags | O_NONBLOCK));
+
+	TEST_ERRNO(read(uffd, &msg, sizeof(msg)), EAGAIN);
+	TEST_ERRNO(read(uffd, &msg, sizeof(msg) - 1), EINVAL);
+
+	TEST_SUCC(fcntl(uffd, F_SETFL, flags));
+}
+END_TEST()
+
+FN_TEST(fork)
+{
+	int status;
+	pid_t pid;
+
+	// The registrations are not inherited by the child.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(addr[PAGE_SIZE * 3], _ret == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == 0);
+}
+END_TEST()
+
+FN_TEST(unregister)
+{
+	struct fault_thread ft;
+	struct uffd_msg msg;
+	struct uffdio_range range;
+
+	start_fault_thread(&ft, addr + PAGE_SIZE * 3);
+
+	TEST_RES(read(uffd, &msg, sizeof(msg)), _ret == sizeof(msg));
+
+	// The faulting thread is woken up and the fault is handled as usual.
+	range.start = (unsigned long)addr;
+	range.len = TOTAL_SIZE;
+	TEST_SUCC(ioctl(uffd, UFFDIO_UNREGISTER, &range));
+
+	join_fault_thread(&ft);
+	TEST_RES(ft.value, _ret == 0);
+
+	TEST_SUCC(munmap(addr, TOTAL_SIZE));
+}
+END_TEST()
+
+FN_TEST(close)
+{
+	struct fault_thread ft;
+	struct uffd_msg msg;
+	char *buf;
+
+	buf = TEST_SUCC(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	register_range(uffd, buf, PAGE_SIZE);
+
+	start_fault_thread(&ft, buf);
+
+	TEST_RES(read(uffd, &msg, sizeof(msg)),
+		 _ret == sizeof(msg) &&
+			 msg.arg.pagefault.address == (unsigned long)buf);
+
+	// The faulting thread is woken up and the fault is handled as usual.
+	TEST_SUCC(close(uffd));
+
+	join_fault_thread(&ft);
+	TEST_RES(ft.value, _ret == 0);
+
+	TEST_SUCC(munmap(buf, PAGE_SIZE));
+}
+END_TEST()
@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/signal.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <arpa/inet.h>
+
+#include "../test.h"
+
+#define TCP_PORT htons(0x1234)
+#define UDP_PORT htons(0x1235)
+#define TCP4_PORT htons(0x1236)
+#define DUAL_PORT htons(0x1237)
+#define V6ONLY_PORT htons(0x1238)
+
+static struct sockaddr_in6 sk_addr6;
+static struct sockaddr_in sk_addr4;
+
+FN_SETUP(general)
+{
+	sk_addr6.sin6_family = AF_INET6;
+	sk_addr6.sin6_addr = in6addr_loopback;
+
+	sk_addr4.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &sk_addr4.sin_addr));
+
+	signal(SIGPIPE, SIG_IGN);
+}
+END_SETUP()
+
+static int sk_tcp_listen;
+static int sk_tcp_connected;
+static int sk_tcp_accepted;
+
+FN_SETUP(tcp)
+{
+	sk_tcp_listen = CHECK(socket(PF_INET6, SOCK_STREAM, 0));
+
+	sk_addr6.sin6_port = TCP_PORT;
+	CHECK(bind(sk_tcp_listen, (struct sockaddr *)&sk_addr6,
+		   sizeof(sk_addr6)));
+	CHECK(listen(sk_tcp_listen, 1));
+
+	sk_tcp_connected = CHECK(socket(PF_INET6, SOCK_STREAM, 0));
+	CHECK(connect(sk_tcp_connected, (struct sockaddr *)&sk_addr6,
+		      sizeof(sk_addr6)));
+
+	sk_tcp_accepted = CHECK(accept(sk_tcp_listen, NULL, NULL));
+}
+END_SETUP()
+
+FN_TEST(tcp_addr)
+{
+	struct sockaddr_in6 saddr;
+	struct sockaddr *psaddr = (struct sockaddr *)&saddr;
+	socklen_t addrlen = sizeof(saddr);
+
+	TEST_RES(getsockname(sk_tcp_listen, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == TCP_PORT &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+
+	TEST_RES(getpeername(sk_tcp_connected, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == TCP_PORT &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+
+	TEST_RES(getsockname(sk_tcp_accepted, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == TCP_PORT &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+}
+END_TEST()
+
+FN_TEST(tcp_send_recv)
+{
+	char buf[6];
+
+	TEST_RES(send(sk_tcp_connected, "hello", 6, 0), _ret == 6);
+	TEST_RES(recv(sk_tcp_accepted, buf, sizeof(buf), 0),
+		 _ret == 6 && strcmp(buf, "hello") == 0);
+
+	TEST_RES(send(sk_tcp_accepted, "world", 6, 0), _ret == 6);
+	TEST_RES(recv(sk_tcp_connected, buf, sizeof(buf), 0),
+		 _ret == 6 && strcmp(buf, "world") == 0);
+}
+END_TEST()
+
+FN_TEST(udp_send_recv)
+{
+	int sk_server, sk_client;
+	struct sockaddr_in6 saddr;
+	struct sockaddr *psaddr = (struct sockaddr *)&saddr;
+	socklen_t addrlen = sizeof(saddr);
+	char buf[6];
+
+	sk_server = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+	sk_client = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+
+	sk_addr6.sin6_port = UDP_PORT;
+	TEST_SUCC(bind(sk_server, (struct sockaddr *)&sk_addr6,
+		       sizeof(sk_addr6)));
+
+	TEST_RES(sendto(sk_client, "hello", 6, 0, (struct sockaddr *)&sk_addr6,
+			sizeof(sk_addr6)),
+		 _ret == 6);
+	TEST_RES(recvfrom(sk_server, buf, sizeof(buf), 0, psaddr, &addrlen),
+		 _ret == 6 && strcmp(buf, "hello") == 0 &&
+			 addrlen == sizeof(saddr) &&
+			 saddr.sin6_family == AF_INET6 &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+
+	TEST_SUCC(close(sk_server));
+	TEST_SUCC(close(sk_client));
+}
+END_TEST()
+
+FN_TEST(ipv4_mapped)
+{
+	int sk_listen, sk_connect, sk_accept;
+	struct sockaddr_in6 saddr;
+	struct sockaddr *psaddr = (struct sockaddr *)&saddr;
+	socklen_t addrlen = sizeof(saddr);
+
+	sk_listen = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	sk_addr4.sin_port = TCP4_PORT;
+	TEST_SUCC(bind(sk_listen, (struct sockaddr *)&sk_addr4,
+		       sizeof(sk_addr4)));
+	TEST_SUCC(listen(sk_listen, 1));
+
+	memset(&saddr, 0, sizeof(saddr));
+	saddr.sin6_family = AF_INET6;
+	saddr.sin6_port = TCP4_PORT;
+	TEST_SUCC(inet_pton(AF_INET6, "::ffff:127.0.0.1", &saddr.sin6_addr));
+
+	sk_connect = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	TEST_SUCC(connect(sk_connect, psaddr, sizeof(saddr)));
+	sk_accept = TEST_SUCC(accept(sk_listen, NULL, NULL));
+
+	TEST_RES(getsockname(sk_connect, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 IN6_IS_ADDR_V4MAPPED(&saddr.sin6_addr));
+
+	TEST_SUCC(close(sk_accept));
+	TEST_SUCC(close(sk_connect));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
+
+FN_TEST(dual_stack_listener)
+{
+	int sk_listen, sk_connect4, sk_connect6, sk_accept4, sk_accept6;
+	struct sockaddr_in6 saddr;
+	struct sockaddr *psaddr = (struct sockaddr *)&saddr;
+	socklen_t addrlen = sizeof(saddr);
+
+	sk_listen = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+
+	memset(&saddr, 0, sizeof(saddr));
+	saddr.sin6_family = AF_INET6;
+	saddr.sin6_addr = in6addr_any;
+	saddr.sin6_port = DUAL_PORT;
+	TEST_SUCC(bind(sk_listen, psaddr, sizeof(saddr)));
+	TEST_SUCC(listen(sk_listen, 2));
+
+	TEST_RES(getsockname(sk_listen, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == DUAL_PORT &&
+			 IN6_IS_ADDR_UNSPECIFIED(&saddr.sin6_addr));
+
+	sk_connect4 = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	sk_addr4.sin_port = DUAL_PORT;
+	TEST_SUCC(connect(sk_connect4, (struct sockaddr *)&sk_addr4,
+			  sizeof(sk_addr4)));
+
+	addrlen = sizeof(saddr);
+	sk_accept4 = TEST_RES(accept(sk_listen, psaddr, &addrlen),
+			      addrlen == sizeof(saddr) &&
+				      saddr.sin6_family == AF_INET6 &&
+				      IN6_IS_ADDR_V4MAPPED(&saddr.sin6_addr));
+	TEST_RES(getsockname(sk_accept4, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == DUAL_PORT &&
+			 IN6_IS_ADDR_V4MAPPED(&saddr.sin6_addr));
+
+	sk_connect6 = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	sk_addr6.sin6_port = DUAL_PORT;
+	TEST_SUCC(connect(sk_connect6, (struct sockaddr *)&sk_addr6,
+			  sizeof(sk_addr6)));
+
+	addrlen = sizeof(saddr);
+	sk_accept6 = TEST_RES(accept(sk_listen, psaddr, &addrlen),
+			      addrlen == sizeof(saddr) &&
+				      saddr.sin6_family == AF_INET6 &&
+				      IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+	TEST_RES(getsockname(sk_accept6, psaddr, &addrlen),
+		 addrlen == sizeof(saddr) && saddr.sin6_family == AF_INET6 &&
+			 saddr.sin6_port == DUAL_PORT &&
+			 IN6_IS_ADDR_LOOPBACK(&saddr.sin6_addr));
+
+	TEST_SUCC(close(sk_accept6));
+	TEST_SUCC(close(sk_connect6));
+	TEST_SUCC(close(sk_accept4));
+	TEST_SUCC(close(sk_connect4));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
+
+FN_TEST(v6only_listener)
+{
+	int sk_listen, sk_connect4;
+	int opt = 1;
+	struct sockaddr_in6 saddr;
+	struct sockaddr *psaddr = (struct sockaddr *)&saddr;
+
+	sk_listen = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+	TEST_SUCC(setsockopt(sk_listen, IPPROTO_IPV6, IPV6_V6ONLY, &opt,
+			     sizeof(opt)));
+
+	memset(&saddr, 0, sizeof(saddr));
+	saddr.sin6_family = AF_INET6;
+	saddr.sin6_addr = in6addr_any;
+	saddr.sin6_port = V6ONLY_PORT;
+	TEST_SUCC(bind(sk_listen, psaddr, sizeof(saddr)));
+	TEST_SUCC(listen(sk_listen, 1));
+
+	sk_connect4 = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	sk_addr4.sin_port = V6ONLY_PORT;
+	TEST_ERRNO(connect(sk_connect4, (struct sockaddr *)&sk_addr4,
+			   sizeof(sk_addr4)),
+		   ECONNREFUSED);
+
+	TEST_SUCC(close(sk_connect4));
+	TEST_SUCC(close(sk_listen));
+}
+END_TEST()
+
+FN_TEST(family_mismatch)
+{
+	int sk4, sk6;
+
+	sk4 = TEST_SUCC(socket(PF_INET, SOCK_DGRAM, 0));
+	sk6 = TEST_SUCC(socket(PF_INET6, SOCK_DGRAM, 0));
+
+	TEST_ERRNO(bind(sk4, (struct sockaddr *)&sk_addr6, sizeof(sk_addr6)),
+		   EAFNOSUPPORT);
+	TEST_ERRNO(bind(sk6, (struct sockaddr *)&sk_addr4, sizeof(sk_addr4)),
+		   EINVAL);
+
+	TEST_SUCC(close(sk4));
+	TEST_SUCC(close(sk6));
+}
+END_TEST()
+
+FN_TEST(v6only)
+{
+	int sk4, sk6;
+	int opt;
+	socklen_t optlen = sizeof(opt);
+
+	sk4 = TEST_SUCC(socket(PF_INET, SOCK_STREAM, 0));
+	sk6 = TEST_SUCC(socket(PF_INET6, SOCK_STREAM, 0));
+
+	TEST_RES(getsockopt(sk6, IPPROTO_IPV6, IPV6_V6ONLY, &opt, &optlen),
+		 optlen == sizeof(opt) && opt == 0);
+
+	opt = 1;
+	TEST_SUCC(setsockopt(sk6, IPPROTO_IPV6, IPV6_V6ONLY, &opt,
+			     sizeof(opt)));
+	TEST_RES(getsockopt(sk6, IPPROTO_IPV6, IPV6_V6ONLY, &opt, &optlen),
+		 optlen == sizeof(opt) && opt == 1);
+
+	TEST_ERRNO(setsockopt(sk4, IPPROTO_IPV6, IPV6_V6ONLY, &opt,
+			      sizeof(opt)),
+		   ENOPROTOOPT);
+
+	sk_addr6.sin6_port = 0;
+	TEST_SUCC(bind(sk6, (struct sockaddr *)&sk_addr6, sizeof(sk_addr6)));
+
+	opt = 0;
+	TEST_ERRNO(setsockopt(sk6, IPPROTO_IPV6, IPV6_V6ONLY, &opt,
+			      sizeof(opt)),
+		   EINVAL);
+
+	TEST_SUCC(close(sk4));
+	TEST_SUCC(close(sk6));
+}
+END_TEST()
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/socket.h>
+#include <net/if.h>
+#include <net/if_arp.h>
+#include <netinet/in.h>
+#include <netinet/ip.h>
+#include <arpa/inet.h>
+#include <linux/if_packet.h>
+#include <linux/if_ether.h>
+
+#include "../test.h"
+
+static int lo_index;
+
+FN_SETUP(general)
+{
+	lo_index = CHECK(if_nametoindex("lo"));
+}
+END_SETUP()
+
+static void build_ip_packet(unsigned char *buf, size_t len)
+{
+	struct iphdr *ip = (struct iphdr *)buf;
+
+	memset(buf, 0, len);
+	ip->version = 4;
+	ip->ihl = 5;
+	ip->ttl = 64;
+	ip->tot_len = htons(len);
+	// Use a protocol number reserved for experimentation.
+	ip->protocol = 253;
+	ip->saddr = htonl(INADDR_LOOPBACK);
+	ip->daddr = htonl(INADDR_LOOPBACK);
+	memcpy(buf + sizeof(struct iphdr), "packet!", 8);
+}
+
+FN_TEST(bind_and_getsockname)
+{
+	int sk;
+	struct sockaddr_ll addr;
+	socklen_t addrlen;
+
+	sk = TEST_SUCC(socket(AF_PACKET, SOCK_RAW, htons(ETH_P_ALL)));
+
+	memset(&addr, 0, sizeof(addr));
+	addr.sll_family = AF_PACKET;
+	addr.sll_ifindex = 12345;
+	TEST_ERRNO(bind(sk, (struct sockaddr *)&addr, sizeof(addr)), ENODEV);
+
+	addr.sll_ifindex = lo_index;
+	TEST_SUCC(bind(sk, (struct sockaddr *)&addr, sizeof(addr)));
+
+	// A zero protocol keeps the protocol specified when creating the socket.
+	memset(&addr, 0, sizeof(addr));
+	addrlen = sizeof(addr);
+	TEST_RES(getsockname(sk, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(addr) && addr.sll_family == AF_PACKET &&
+			 addr.sll_protocol == htons(ETH_P_ALL) &&
+			 addr.sll_ifindex == lo_index &&
+			 addr.sll_hatype == ARPHRD_LOOPBACK);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(send_unbound)
+{
+	int sk;
+	unsigned char buf[sizeof(struct iphdr) + 8];
+
+	sk = TEST_SUCC(socket(AF_PACKET, SOCK_DGRAM, 0));
+
+	build_ip_packet(buf, sizeof(buf));
+	TEST_ERRNO(send(sk, buf, sizeof(buf), 0), ENXIO);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(inject_and_capture)
+{
+	int sk_send, sk_recv;
+	struct sockaddr_ll addr;
+	socklen_t addrlen;
+	unsigned char pkt[sizeof(struct iphdr) + 8];
+	unsigned char buf[sizeof(pkt) + 64];
+
+	sk_recv = TEST_SUCC(socket(AF_PACKET, SOCK_DGRAM, htons(ETH_P_ALL)));
+	sk_send = TEST_SUCC(socket(AF_PACKET, SOCK_DGRAM, 0));
+
+	memset(&addr, 0, sizeof(addr));
+	addr.sll_family = AF_PACKET;
+	addr.sll_ifindex = lo_index;
+	TEST_SUCC(bind(sk_recv, (struct sockaddr *)&addr, sizeof(addr)));
+
+	memset(&addr, 0, sizeof(addr));
+	addr.sll_family = AF_PACKET;
+	addr.sll_protocol = htons(ETH_P_IP);
+	addr.sll_ifindex = lo_index;
+	addr.sll_halen = ETH_ALEN;
+
+	build_ip_packet(pkt, sizeof(pkt));
+	TEST_RES(sendto(sk_send, pkt, sizeof(pkt), 0, (struct sockaddr *)&addr,
+			sizeof(addr)),
+		 _ret == sizeof(pkt));
+
+	// The link-layer header is removed because the socket is `SOCK_DGRAM`.
+	memset(&addr, 0, sizeof(addr));
+	addrlen = sizeof(addr);
+	TEST_RES(recvfrom(sk_recv, buf, sizeof(buf), 0,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == sizeof(pkt) && memcmp(buf, pkt, sizeof(pkt)) == 0 &&
+			 addrlen == sizeof(addr) &&
+			 addr.sll_family == AF_PACKET &&
+			 addr.sll_protocol == htons(ETH_P_IP) &&
+			 addr.sll_ifindex == lo_index);
+
+	TEST_SUCC(close(sk_send));
+	TEST_SUCC(close(sk_recv));
+}
+END_TEST()
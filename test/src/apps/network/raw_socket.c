@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include <unistd.h>
+#include <sys/socket.h>
+#include <netinet/in.h>
+#include <netinet/ip.h>
+#include <netinet/ip_icmp.h>
+#include <arpa/inet.h>
+
+#include "../test.h"
+
+#define ECHO_SEQUENCE 0x4321
+#define ECHO_PAYLOAD "ping!"
+
+static struct sockaddr_in lo_addr;
+
+struct echo_message {
+	struct icmphdr hdr;
+	char payload[sizeof(ECHO_PAYLOAD)];
+};
+
+static unsigned short icmp_checksum(const void *data, size_t len)
+{
+	const unsigned short *words = data;
+	unsigned int sum = 0;
+
+	for (; len > 1; len -= 2)
+		sum += *words++;
+	if (len == 1)
+		sum += *(const unsigned char *)words;
+
+	sum = (sum >> 16) + (sum & 0xffff);
+	sum += sum >> 16;
+
+	return ~sum;
+}
+
+static void build_echo_request(struct echo_message *msg)
+{
+	memset(msg, 0, sizeof(*msg));
+	msg->hdr.type = ICMP_ECHO;
+	msg->hdr.un.echo.id = htons(0x1234);
+	msg->hdr.un.echo.sequence = htons(ECHO_SEQUENCE);
+	memcpy(msg->payload, ECHO_PAYLOAD, sizeof(ECHO_PAYLOAD));
+	msg->hdr.checksum = icmp_checksum(msg, sizeof(*msg));
+}
+
+FN_SETUP(general)
+{
+	lo_addr.sin_family = AF_INET;
+	CHECK(inet_aton("127.0.0.1", &lo_addr.sin_addr));
+}
+END_SETUP()
+
+FN_TEST(invalid_protocol)
+{
+	TEST_ERRNO(socket(AF_INET, SOCK_RAW, IPPROTO_IP), EPROTONOSUPPORT);
+	TEST_ERRNO(socket(AF_INET, SOCK_RAW, 256), EINVAL);
+}
+END_TEST()
+
+FN_TEST(ping_socket)
+{
+	int sk;
+	struct echo_message msg;
+	struct sockaddr_in addr;
+	socklen_t addrlen;
+	int hdrincl = 1;
+
+	sk = TEST_SUCC(socket(AF_INET, SOCK_DGRAM | SOCK_NONBLOCK,
+			      IPPROTO_ICMP));
+
+	TEST_ERRNO(setsockopt(sk, IPPROTO_IP, IP_HDRINCL, &hdrincl,
+			      sizeof(hdrincl)),
+		   ENOPROTOOPT);
+
+	// Only echo requests can be sent.
+	build_echo_request(&msg);
+	msg.hdr.type = ICMP_ECHOREPLY;
+	TEST_ERRNO(sendto(sk, &msg, sizeof(msg), 0, (struct sockaddr *)&lo_addr,
+			  sizeof(lo_addr)),
+		   EINVAL);
+	TEST_ERRNO(sendto(sk, &msg, 4, 0, (struct sockaddr *)&lo_addr,
+			  sizeof(lo_addr)),
+		   EINVAL);
+
+	build_echo_request(&msg);
+	TEST_RES(sendto(sk, &msg, sizeof(msg), 0, (struct sockaddr *)&lo_addr,
+			sizeof(lo_addr)),
+		 _ret == sizeof(msg));
+
+	// The echo identifier is the "port" of the socket.
+	addrlen = sizeof(addr);
+	TEST_RES(getsockname(sk, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(addr) && addr.sin_port != 0);
+
+	memset(&msg, 0, sizeof(msg));
+	TEST_RES(recv(sk, &msg, sizeof(msg), 0),
+		 _ret == sizeof(msg) && msg.hdr.type == ICMP_ECHOREPLY &&
+			 msg.hdr.un.echo.id == addr.sin_port &&
+			 msg.hdr.un.echo.sequence == htons(ECHO_SEQUENCE) &&
+			 strcmp(msg.payload, ECHO_PAYLOAD) == 0);
+
+	TEST_ERRNO(recv(sk, &msg, sizeof(msg), 0), EAGAIN);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(raw_icmp_socket)
+{
+	int sk;
+	struct echo_message msg;
+	char buf[sizeof(struct iphdr) + sizeof(msg)];
+	struct iphdr *ip = (struct iphdr *)buf;
+	struct echo_message *reply =
+		(struct echo_message *)(buf + sizeof(struct iphdr));
+	struct sockaddr_in addr;
+	socklen_t addrlen;
+	int hdrincl;
+	socklen_t optlen;
+	int i;
+
+	sk = TEST_SUCC(socket(AF_INET, SOCK_RAW | SOCK_NONBLOCK, IPPROTO_ICMP));
+
+	optlen = sizeof(hdrincl);
+	TEST_RES(getsockopt(sk, IPPROTO_IP, IP_HDRINCL, &hdrincl, &optlen),
+		 optlen == sizeof(hdrincl) && hdrincl == 0);
+
+	build_echo_request(&msg);
+	TEST_RES(sendto(sk, &msg, sizeof(msg), 0, (struct sockaddr *)&lo_addr,
+			sizeof(lo_addr)),
+		 _ret == sizeof(msg));
+
+	// The raw socket receives a copy of the request and the reply, both with
+	// the IP header.
+	for (i = 0; i < 2; ++i) {
+		addrlen = sizeof(addr);
+		TEST_RES(recvfrom(sk, buf, sizeof(buf), 0,
+				  (struct sockaddr *)&addr, &addrlen),
+			 _ret == sizeof(buf) && ip->version == 4 &&
+				 ip->protocol == IPPROTO_ICMP &&
+				 addr.sin_addr.s_addr ==
+					 lo_addr.sin_addr.s_addr);
+		if (reply->hdr.type == ICMP_ECHOREPLY)
+			break;
+	}
+	TEST_RES(i, i < 2 && reply->hdr.un.echo.id == htons(0x1234) &&
+			    reply->hdr.un.echo.sequence ==
+				    htons(ECHO_SEQUENCE));
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(raw_hdrincl)
+{
+	int sk;
+	int hdrincl;
+	socklen_t optlen;
+
+	sk = TEST_SUCC(socket(AF_INET, SOCK_RAW, IPPROTO_RAW));
+
+	// IP_HDRINCL is enabled by default for IPPROTO_RAW.
+	optlen = sizeof(hdrincl);
+	TEST_RES(getsockopt(sk, IPPROTO_IP, IP_HDRINCL, &hdrincl, &optlen),
+		 optlen == sizeof(hdrincl) && hdrincl == 1);
+
+	hdrincl = 0;
+	TEST_SUCC(setsockopt(sk, IPPROTO_IP, IP_HDRINCL, &hdrincl,
+			     sizeof(hdrincl)));
+	optlen = sizeof(hdrincl);
+	TEST_RES(getsockopt(sk, IPPROTO_IP, IP_HDRINCL, &hdrincl, &optlen),
+		 optlen == sizeof(hdrincl) && hdrincl == 0);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <unistd.h>
+#include <stddef.h>
+#include <sys/socket.h>
+#include <sys/un.h>
+
+#include "../test.h"
+
+#define PATH_NAME "/tmp/unix_dgram.sock"
+#define ABSTRACT_NAME "\0unix_dgram"
+
+static struct sockaddr_un path_addr = { .sun_family = AF_UNIX,
+					.sun_path = PATH_NAME };
+#define PATH_ADDRLEN (offsetof(struct sockaddr_un, sun_path) + sizeof(PATH_NAME))
+
+static struct sockaddr_un abstract_addr = { .sun_family = AF_UNIX,
+					    .sun_path = ABSTRACT_NAME };
+#define ABSTRACT_ADDRLEN \
+	(offsetof(struct sockaddr_un, sun_path) + sizeof(ABSTRACT_NAME) - 1)
+
+static int sk_path;
+static int sk_abstract;
+static int sk_unbound;
+
+FN_SETUP(sockets)
+{
+	unlink(PATH_NAME);
+
+	sk_path = CHECK(socket(AF_UNIX, SOCK_DGRAM, 0));
+	CHECK(bind(sk_path, (struct sockaddr *)&path_addr, PATH_ADDRLEN));
+
+	sk_abstract = CHECK(socket(AF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+	CHECK(bind(sk_abstract, (struct sockaddr *)&abstract_addr,
+		   ABSTRACT_ADDRLEN));
+
+	sk_unbound = CHECK(socket(AF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0));
+}
+END_SETUP()
+
+FN_TEST(getsockname)
+{
+	struct sockaddr_un addr;
+	socklen_t addrlen;
+
+	addrlen = sizeof(addr);
+	TEST_RES(getsockname(sk_path, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == PATH_ADDRLEN &&
+			 strcmp(addr.sun_path, PATH_NAME) == 0);
+
+	addrlen = sizeof(addr);
+	TEST_RES(getsockname(sk_unbound, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == sizeof(sa_family_t));
+
+	TEST_ERRNO(getpeername(sk_unbound, (struct sockaddr *)&addr, &addrlen),
+		   ENOTCONN);
+}
+END_TEST()
+
+FN_TEST(sendto_recvfrom)
+{
+	struct sockaddr_un addr;
+	socklen_t addrlen;
+	char buf[16];
+
+	TEST_RES(sendto(sk_unbound, "hello", 6, 0,
+			(struct sockaddr *)&abstract_addr, ABSTRACT_ADDRLEN),
+		 _ret == 6);
+	TEST_RES(sendto(sk_path, "world", 6, 0,
+			(struct sockaddr *)&abstract_addr, ABSTRACT_ADDRLEN),
+		 _ret == 6);
+
+	// Message boundaries are preserved.
+	addrlen = sizeof(addr);
+	TEST_RES(recvfrom(sk_abstract, buf, sizeof(buf), 0,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == 6 && strcmp(buf, "hello") == 0 &&
+			 addrlen == sizeof(sa_family_t));
+
+	addrlen = sizeof(addr);
+	TEST_RES(recvfrom(sk_abstract, buf, sizeof(buf), 0,
+			  (struct sockaddr *)&addr, &addrlen),
+		 _ret == 6 && strcmp(buf, "world") == 0 &&
+			 addrlen == PATH_ADDRLEN &&
+			 strcmp(addr.sun_path, PATH_NAME) == 0);
+
+	TEST_ERRNO(recv(sk_abstract, buf, sizeof(buf), 0), EAGAIN);
+}
+END_TEST()
+
+FN_TEST(send_unconnected)
+{
+	TEST_ERRNO(send(sk_unbound, "x", 1, 0), ENOTCONN);
+}
+END_TEST()
+
+FN_TEST(connect)
+{
+	int sk;
+	struct sockaddr_un addr;
+	socklen_t addrlen;
+	char buf[16];
+
+	sk = TEST_SUCC(socket(AF_UNIX, SOCK_DGRAM, 0));
+
+	TEST_SUCC(
+		connect(sk, (struct sockaddr *)&path_addr, PATH_ADDRLEN));
+
+	addrlen = sizeof(addr);
+	TEST_RES(getpeername(sk, (struct sockaddr *)&addr, &addrlen),
+		 addrlen == PATH_ADDRLEN &&
+			 strcmp(addr.sun_path, PATH_NAME) == 0);
+
+	TEST_RES(send(sk, "hello", 6, 0), _ret == 6);
+	TEST_RES(recv(sk_path, buf, sizeof(buf), 0),
+		 _ret == 6 && strcmp(buf, "hello") == 0);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(connect_refused)
+{
+	int sk, sk_peer;
+	struct sockaddr_un addr = { .sun_family = AF_UNIX,
+				    .sun_path = "\0unix_dgram_peer" };
+	socklen_t addrlen = offsetof(struct sockaddr_un, sun_path) +
+			    sizeof("\0unix_dgram_peer") - 1;
+
+	sk = TEST_SUCC(socket(AF_UNIX, SOCK_DGRAM, 0));
+	TEST_ERRNO(connect(sk, (struct sockaddr *)&addr, addrlen),
+		   ECONNREFUSED);
+
+	sk_peer = TEST_SUCC(socket(AF_UNIX, SOCK_DGRAM, 0));
+	TEST_SUCC(bind(sk_peer, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(connect(sk, (struct sockaddr *)&addr, addrlen));
+	TEST_SUCC(close(sk_peer));
+
+	TEST_ERRNO(send(sk, "x", 1, 0), ECONNREFUSED);
+
+	TEST_SUCC(close(sk));
+}
+END_TEST()
+
+FN_TEST(socketpair)
+{
+	int fildes[2];
+	char buf[16];
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_DGRAM | SOCK_NONBLOCK, 0, fildes));
+
+	TEST_RES(send(fildes[0], "abc", 3, 0), _ret == 3);
+	TEST_RES(send(fildes[0], "defg", 4, 0), _ret == 4);
+
+	// A short buffer truncates the message instead of splitting it.
+	TEST_RES(recv(fildes[1], buf, 2, 0),
+		 _ret == 2 && buf[0] == 'a' && buf[1] == 'b');
+	TEST_RES(recv(fildes[1], buf, sizeof(buf), MSG_PEEK),
+		 _ret == 4 && memcmp(buf, "defg", 4) == 0);
+	TEST_RES(recv(fildes[1], buf, sizeof(buf), 0),
+		 _ret == 4 && memcmp(buf, "defg", 4) == 0);
+	TEST_ERRNO(recv(fildes[1], buf, sizeof(buf), 0), EAGAIN);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_ERRNO(send(fildes[1], "x", 1, 0), ECONNREFUSED);
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(scm_rights)
+{
+	int fildes[2];
+	int pipefds[2];
+	char buf[1] = { 'z' };
+	char cbuf[CMSG_SPACE(sizeof(int))];
+	struct iovec iov = { .iov_base = buf, .iov_len = 1 };
+	struct msghdr mhdr;
+	struct cmsghdr *chdr;
+	int fd;
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_DGRAM, 0, fildes));
+	TEST_SUCC(pipe(pipefds));
+
+	memset(&mhdr, 0, sizeof(mhdr));
+	mhdr.msg_iov = &iov;
+	mhdr.msg_iovlen = 1;
+	mhdr.msg_control = cbuf;
+	mhdr.msg_controllen = sizeof(cbuf);
+
+	chdr = CMSG_FIRSTHDR(&mhdr);
+	chdr->cmsg_level = SOL_SOCKET;
+	chdr->cmsg_type = SCM_RIGHTS;
+	chdr->cmsg_len = CMSG_LEN(sizeof(int));
+	*(int *)CMSG_DATA(chdr) = pipefds[1];
+
+	TEST_RES(sendmsg(fildes[0], &mhdr, 0), _ret == 1);
+
+	memset(cbuf, 0, sizeof(cbuf));
+	mhdr.msg_controllen = sizeof(cbuf);
+	TEST_RES(recvmsg(fildes[1], &mhdr, 0),
+		 _ret == 1 && (chdr = CMSG_FIRSTHDR(&mhdr)) &&
+			 chdr->cmsg_level == SOL_SOCKET &&
+			 chdr->cmsg_type == SCM_RIGHTS);
+
+	fd = *(int *)CMSG_DATA(chdr);
+	TEST_RES(write(fd, "a", 1), _ret == 1);
+	TEST_RES(read(pipefds[0], buf, 1), _ret == 1 && buf[0] == 'a');
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(close(pipefds[0]));
+	TEST_SUCC(close(pipefds[1]));
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(scm_credentials)
+{
+	int fildes[2];
+	int one = 1;
+	char buf[1] = { 'z' };
+	char cbuf[CMSG_SPACE(sizeof(struct ucred))];
+	struct iovec iov = { .iov_base = buf, .iov_len = 1 };
+	struct msghdr mhdr;
+	struct cmsghdr *chdr;
+	struct ucred *cred;
+
+	TEST_SUCC(socketpair(AF_UNIX, SOCK_DGRAM, 0, fildes));
+	TEST_SUCC(setsockopt(fildes[1], SOL_SOCKET, SO_PASSCRED, &one,
+			     sizeof(one)));
+
+	TEST_RES(send(fildes[0], buf, 1, 0), _ret == 1);
+
+	memset(&mhdr, 0, sizeof(mhdr));
+	mhdr.msg_iov = &iov;
+	mhdr.msg_iovlen = 1;
+	mhdr.msg_control = cbuf;
+	mhdr.msg_controllen = sizeof(cbuf);
+
+	TEST_RES(recvmsg(fildes[1], &mhdr, 0),
+		 _ret == 1 && (chdr = CMSG_FIRSTHDR(&mhdr)) &&
+			 chdr->cmsg_level == SOL_SOCKET &&
+			 chdr->cmsg_type == SCM_CREDENTIALS &&
+			 (cred = (struct ucred *)CMSG_DATA(chdr)) &&
+			 cred->pid == getpid() && cred->uid == getuid() &&
+			 cred->gid == getgid());
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(sk_path));
+	CHECK(close(sk_abstract));
+	CHECK(close(sk_unbound));
+
+	CHECK(unlink(PATH_NAME));
+}
+END_SETUP()
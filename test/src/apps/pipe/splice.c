@@ -0,0 +1,172 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include <fcntl.h>
+#include <string.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#include "../test.h"
+
+#define SRC_FILE "/tmp/splice_src.txt"
+#define DST_FILE "/tmp/splice_dst.txt"
+#define CONTENT "Hello, splice!"
+#define CONTENT_LEN (sizeof(CONTENT) - 1)
+
+static int src_fd, dst_fd;
+static int rfd, wfd;
+
+FN_SETUP(open_files)
+{
+	int fildes[2];
+
+	src_fd = CHECK(open(SRC_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	dst_fd = CHECK(open(DST_FILE, O_RDWR | O_CREAT | O_TRUNC, 0644));
+	CHECK_WITH(write(src_fd, CONTENT, CONTENT_LEN), _ret == CONTENT_LEN);
+
+	CHECK(pipe(fildes));
+	rfd = fildes[0];
+	wfd = fildes[1];
+}
+END_SETUP()
+
+FN_TEST(splice_file_to_pipe_to_file)
+{
+	char buf[CONTENT_LEN];
+	loff_t off_in = 7;
+	loff_t off_out = 0;
+
+	TEST_RES(splice(src_fd, &off_in, wfd, NULL, 100, 0),
+		 _ret == CONTENT_LEN - 7 && off_in == CONTENT_LEN);
+	TEST_RES(splice(rfd, NULL, dst_fd, &off_out, 100, 0),
+		 _ret == CONTENT_LEN - 7 && off_out == CONTENT_LEN - 7);
+
+	TEST_RES(pread(dst_fd, buf, sizeof(buf), 0),
+		 _ret == CONTENT_LEN - 7 &&
+			 memcmp(buf, CONTENT + 7, CONTENT_LEN - 7) == 0);
+
+	// Without an offset, the file position is used and updated.
+	TEST_SUCC(lseek(src_fd, 0, SEEK_SET));
+	TEST_RES(splice(src_fd, NULL, wfd, NULL, 5, 0), _ret == 5);
+	TEST_RES(lseek(src_fd, 0, SEEK_CUR), _ret == 5);
+	TEST_RES(read(rfd, buf, sizeof(buf)),
+		 _ret == 5 && memcmp(buf, CONTENT, 5) == 0);
+}
+END_TEST()
+
+FN_TEST(splice_errors)
+{
+	loff_t off = 0;
+
+	TEST_ERRNO(splice(rfd, &off, dst_fd, NULL, 1, 0), ESPIPE);
+	TEST_ERRNO(splice(src_fd, NULL, wfd, &off, 1, 0), ESPIPE);
+	TEST_ERRNO(splice(src_fd, NULL, dst_fd, NULL, 1, 0), EINVAL);
+	TEST_ERRNO(splice(wfd, NULL, dst_fd, NULL, 1, 0), EBADF);
+	TEST_ERRNO(splice(rfd, NULL, dst_fd, NULL, 1, SPLICE_F_NONBLOCK),
+		   EAGAIN);
+}
+END_TEST()
+
+FN_TEST(tee)
+{
+	char buf[CONTENT_LEN];
+	int fildes[2];
+
+	TEST_SUCC(pipe(fildes));
+	TEST_RES(write(wfd, CONTENT, CONTENT_LEN), _ret == CONTENT_LEN);
+
+	// The data is duplicated and still remains in the source pipe.
+	TEST_RES(tee(rfd, fildes[1], 5, 0), _ret == 5);
+	TEST_RES(read(fildes[0], buf, sizeof(buf)),
+		 _ret == 5 && memcmp(buf, CONTENT, 5) == 0);
+	TEST_RES(read(rfd, buf, sizeof(buf)),
+		 _ret == CONTENT_LEN && memcmp(buf, CONTENT, CONTENT_LEN) == 0);
+
+	TEST_ERRNO(tee(src_fd, fildes[1], 1, 0), EINVAL);
+	TEST_ERRNO(tee(rfd, rfd, 1, 0), EBADF);
+	TEST_ERRNO(tee(rfd, fildes[1], 1, SPLICE_F_NONBLOCK), EAGAIN);
+
+	TEST_SUCC(close(fildes[0]));
+	TEST_SUCC(close(fildes[1]));
+}
+END_TEST()
+
+FN_TEST(vmsplice)
+{
+	char buf1[5], buf2[CONTENT_LEN];
+	struct iovec iov[2] = {
+		{ .iov_base = CONTENT, .iov_len = 7 },
+		{ .iov_base = CONTENT + 7, .iov_len = CONTENT_LEN - 7 },
+	};
+
+	TEST_RES(vmsplice(wfd, iov, 2, 0), _ret == CONTENT_LEN);
+
+	iov[0].iov_base = buf1;
+	iov[0].iov_len = sizeof(buf1);
+	iov[1].iov_base = buf2;
+	iov[1].iov_len = sizeof(buf2);
+	TEST_RES(vmsplice(rfd, iov, 2, 0),
+		 _ret == CONTENT_LEN && memcmp(buf1, CONTENT, 5) == 0 &&
+			 memcmp(buf2, CONTENT + 5, CONTENT_LEN - 5) == 0);
+
+	TEST_ERRNO(vmsplice(src_fd, iov, 2, 0), EBADF);
+}
+END_TEST()
+
+FN_TEST(copy_file_range)
+{
+	char buf[CONTENT_LEN];
+	loff_t off_in = 0;
+	loff_t off_out = 3;
+
+	TEST_SUCC(ftruncate(dst_fd, 0));
+	TEST_RES(copy_file_range(src_fd, &off_in, dst_fd, &off_out, 100, 0),
+		 _ret == CONTENT_LEN && off_in == CONTENT_LEN &&
+			 off_out == CONTENT_LEN + 3);
+	TEST_RES(pread(dst_fd, buf, sizeof(buf), 3),
+		 _ret == CONTENT_LEN && memcmp(buf, CONTENT, CONTENT_LEN) == 0);
+
+	// Without an offset, the file position is used and updated.
+	TEST_SUCC(lseek(src_fd, 7, SEEK_SET));
+	TEST_SUCC(lseek(dst_fd, 0, SEEK_SET));
+	TEST_RES(copy_file_range(src_fd, NULL, dst_fd, NULL, 5, 0), _ret == 5);
+	TEST_RES(lseek(src_fd, 0, SEEK_CUR), _ret == 12);
+	TEST_RES(lseek(dst_fd, 0, SEEK_CUR), _ret == 5);
+	TEST_RES(pread(dst_fd, buf, 5, 0),
+		 _ret == 5 && memcmp(buf, CONTENT + 7, 5) == 0);
+
+	// Copying from the end of the file returns zero.
+	off_in = CONTENT_LEN;
+	TEST_RES(copy_file_range(src_fd, &off_in, dst_fd, NULL, 5, 0),
+		 _ret == 0);
+}
+END_TEST()
+
+FN_TEST(copy_file_range_errors)
+{
+	loff_t off_in = 0;
+	loff_t off_out = 2;
+	int dir_fd;
+
+	TEST_ERRNO(copy_file_range(src_fd, NULL, dst_fd, NULL, 1, 1), EINVAL);
+	TEST_ERRNO(copy_file_range(src_fd, &off_in, src_fd, &off_out, 5, 0),
+		   EINVAL);
+	TEST_ERRNO(copy_file_range(rfd, NULL, dst_fd, NULL, 1, 0), EINVAL);
+
+	dir_fd = TEST_SUCC(open("/tmp", O_RDONLY | O_DIRECTORY));
+	TEST_ERRNO(copy_file_range(dir_fd, NULL, dst_fd, NULL, 1, 0), EISDIR);
+	TEST_SUCC(close(dir_fd));
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK(close(src_fd));
+	CHECK(close(dst_fd));
+	CHECK(close(rfd));
+	CHECK(close(wfd));
+	CHECK(unlink(SRC_FILE));
+	CHECK(unlink(DST_FILE));
+}
+END_SETUP()
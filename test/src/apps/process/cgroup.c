@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <fcntl.h>
+#include <linux/sched.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+#ifndef CLONE_INTO_CGROUP
+#define CLONE_INTO_CGROUP 0x200000000ULL
+#endif
+
+#define CGROUP_DIR "/sys/fs/cgroup/test_cgroup"
+#define PAGE_SIZE 4096
+
+static pid_t pid;
+static int status;
+static char buf[256];
+
+static int write_attr(const char *name, const char *value)
+{
+	char path[128];
+	int fd, ret, saved_errno;
+
+	snprintf(path, sizeof(path), CGROUP_DIR "/%s", name);
+	fd = open(path, O_WRONLY);
+	if (fd < 0)
+		return -1;
+
+	ret = write(fd, value, strlen(value));
+	saved_errno = errno;
+	close(fd);
+	errno = saved_errno;
+
+	return ret;
+}
+
+static int read_attr(const char *name)
+{
+	char path[128];
+	int fd, ret, saved_errno;
+
+	snprintf(path, sizeof(path), CGROUP_DIR "/%s", name);
+	fd = open(path, O_RDONLY);
+	if (fd < 0)
+		return -1;
+
+	ret = read(fd, buf, sizeof(buf) - 1);
+	saved_errno = errno;
+	close(fd);
+	errno = saved_errno;
+
+	buf[ret < 0 ? 0 : ret] = '\0';
+	return ret;
+}
+
+static int is_in_procs(pid_t target)
+{
+	char *line;
+
+	if (read_attr("cgroup.procs") < 0)
+		return 0;
+
+	for (line = strtok(buf, "\n"); line; line = strtok(NULL, "\n"))
+		if (atoi(line) == target)
+			return 1;
+	return 0;
+}
+
+FN_SETUP(mkdir)
+{
+	CHECK(mkdir(CGROUP_DIR, 0755));
+}
+END_SETUP()
+
+FN_TEST(controllers)
+{
+	TEST_RES(read_attr("cgroup.controllers"),
+		 strcmp(buf, "cpu memory pids\n") == 0);
+}
+END_TEST()
+
+FN_TEST(procs)
+{
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(write_attr("cgroup.procs", "0"));
+		CHECK_WITH(is_in_procs(getpid()), _ret == 1);
+		CHECK_WITH(read_attr("pids.current"),
+			   strcmp(buf, "1\n") == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// The exited process is no longer in the cgroup.
+	TEST_RES(read_attr("cgroup.procs"), _ret == 0);
+	TEST_RES(read_attr("pids.current"), strcmp(buf, "0\n") == 0);
+
+	TEST_ERRNO(write_attr("cgroup.procs", "abc"), EINVAL);
+}
+END_TEST()
+
+FN_TEST(pids_max)
+{
+	TEST_RES(read_attr("pids.max"), strcmp(buf, "max\n") == 0);
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		pid_t child;
+
+		CHECK(write_attr("cgroup.procs", "0"));
+		CHECK(write_attr("pids.max", "1"));
+		CHECK_WITH(fork(), _ret == -1 && errno == EAGAIN);
+
+		CHECK(write_attr("pids.max", "max"));
+		child = CHECK(fork());
+		if (child == 0)
+			exit(EXIT_SUCCESS);
+		CHECK_WITH(waitpid(child, &status, 0),
+			   _ret == child && WIFEXITED(status) &&
+				   WEXITSTATUS(status) == EXIT_SUCCESS);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_ERRNO(write_attr("pids.max", "-1"), EINVAL);
+}
+END_TEST()
+
+FN_TEST(memory_max)
+{
+	TEST_RES(read_attr("memory.max"), strcmp(buf, "max\n") == 0);
+
+	TEST_SUCC(write_attr("memory.max", "1M"));
+	TEST_RES(read_attr("memory.max"), strcmp(buf, "1048576\n") == 0);
+	TEST_SUCC(write_attr("memory.max", "4097"));
+	TEST_RES(read_attr("memory.max"), strcmp(buf, "4096\n") == 0);
+	TEST_ERRNO(write_attr("memory.max", "1T"), EINVAL);
+	TEST_SUCC(write_attr("memory.max", "max"));
+	TEST_RES(read_attr("memory.max"), strcmp(buf, "max\n") == 0);
+}
+END_TEST()
+
+FN_TEST(memory_current)
+{
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		char *addr;
+		int i;
+
+		CHECK(write_attr("cgroup.procs", "0"));
+		CHECK_WITH(read_attr("memory.current"),
+			   strcmp(buf, "0\n") == 0);
+
+		addr = mmap(NULL, 4 * PAGE_SIZE, PROT_READ | PROT_WRITE,
+			    MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+		CHECK_WITH(addr, _ret != MAP_FAILED);
+		for (i = 0; i < 4; i++)
+			addr[i * PAGE_SIZE] = 1;
+		CHECK_WITH(read_attr("memory.current"),
+			   atoi(buf) == 4 * PAGE_SIZE);
+
+		CHECK(munmap(addr, 4 * PAGE_SIZE));
+		CHECK_WITH(read_attr("memory.current"),
+			   strcmp(buf, "0\n") == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(cpu_weight)
+{
+	TEST_RES(read_attr("cpu.weight"), strcmp(buf, "100\n") == 0);
+
+	TEST_ERRNO(write_attr("cpu.weight", "0"), EINVAL);
+	TEST_ERRNO(write_attr("cpu.weight", "10001"), EINVAL);
+	TEST_SUCC(write_attr("cpu.weight", "200"));
+	TEST_RES(read_attr("cpu.weight"), strcmp(buf, "200\n") == 0);
+	TEST_SUCC(write_attr("cpu.weight", "100"));
+}
+END_TEST()
+
+FN_TEST(cpu_max)
+{
+	TEST_RES(read_attr("cpu.max"), strcmp(buf, "max 100000\n") == 0);
+
+	TEST_SUCC(write_attr("cpu.max", "50000 200000"));
+	TEST_RES(read_attr("cpu.max"), strcmp(buf, "50000 200000\n") == 0);
+	TEST_SUCC(write_attr("cpu.max", "20000"));
+	TEST_RES(read_attr("cpu.max"), strcmp(buf, "20000 200000\n") == 0);
+
+	TEST_ERRNO(write_attr("cpu.max", "500 100000"), EINVAL);
+	TEST_ERRNO(write_attr("cpu.max", "max 100"), EINVAL);
+	TEST_ERRNO(write_attr("cpu.max", "max 100000 1"), EINVAL);
+
+	TEST_SUCC(write_attr("cpu.max", "max 100000"));
+	TEST_RES(read_attr("cpu.max"), strcmp(buf, "max 100000\n") == 0);
+}
+END_TEST()
+
+FN_TEST(clone_into_cgroup)
+{
+	struct clone_args args = { 0 };
+	int cgroup_fd;
+
+	cgroup_fd = TEST_SUCC(open(CGROUP_DIR, O_RDONLY | O_DIRECTORY));
+
+	args.flags = CLONE_INTO_CGROUP;
+	args.exit_signal = SIGCHLD;
+	args.cgroup = cgroup_fd;
+
+	pid = TEST_SUCC(syscall(SYS_clone3, &args, sizeof(args)));
+	if (pid == 0) {
+		CHECK_WITH(is_in_procs(getpid()), _ret == 1);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// The file descriptor must refer to a cgroup directory.
+	args.cgroup = STDIN_FILENO;
+	TEST_ERRNO(syscall(SYS_clone3, &args, sizeof(args)), EBADF);
+
+	TEST_SUCC(close(cgroup_fd));
+}
+END_TEST()
@@ -0,0 +1,292 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <elf.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/prctl.h>
+#include <sys/resource.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+
+#define PAGE_SIZE 4096
+#define CORE_PATTERN_PATH "/proc/sys/kernel/core_pattern"
+
+static const char core_pattern[] = "core.%p\n";
+static const size_t core_pattern_len = sizeof(core_pattern) - 1;
+
+static char *dump_page;
+static char *dont_dump_page;
+
+static int write_core_pattern(const char *pattern)
+{
+	int fd, ret;
+
+	fd = CHECK(open(CORE_PATTERN_PATH, O_WRONLY));
+	ret = write(fd, pattern, strlen(pattern));
+	CHECK(close(fd));
+
+	return ret;
+}
+
+static void get_core_path(pid_t pid, char *path, size_t len)
+{
+	snprintf(path, len, "/tmp/core.%d", pid);
+}
+
+// Forks a child that is terminated by `SIGABRT` with the specified core size limit.
+static pid_t fork_aborted_child(rlim_t core_limit, int dumpable)
+{
+	struct rlimit rlimit = { .rlim_cur = core_limit, .rlim_max = core_limit };
+	pid_t pid;
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(setrlimit(RLIMIT_CORE, &rlimit));
+		CHECK(prctl(PR_SET_DUMPABLE, dumpable, 0, 0, 0));
+		abort();
+	}
+
+	return pid;
+}
+
+FN_SETUP(init)
+{
+	CHECK(chdir("/tmp"));
+
+	dump_page = CHECK_WITH(mmap(NULL, PAGE_SIZE, PROT_READ | PROT_WRITE,
+				    MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+			       _ret != MAP_FAILED);
+	memset(dump_page, 'x', PAGE_SIZE);
+
+	dont_dump_page = CHECK_WITH(mmap(NULL, PAGE_SIZE,
+					 PROT_READ | PROT_WRITE,
+					 MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+				    _ret != MAP_FAILED);
+	memset(dont_dump_page, 'y', PAGE_SIZE);
+	CHECK(madvise(dont_dump_page, PAGE_SIZE, MADV_DONTDUMP));
+}
+END_SETUP()
+
+FN_TEST(prctl_dumpable)
+{
+	TEST_RES(prctl(PR_GET_DUMPABLE, 0, 0, 0, 0), _ret == 1);
+
+	TEST_SUCC(prctl(PR_SET_DUMPABLE, 0, 0, 0, 0));
+	TEST_RES(prctl(PR_GET_DUMPABLE, 0, 0, 0, 0), _ret == 0);
+	TEST_SUCC(prctl(PR_SET_DUMPABLE, 1, 0, 0, 0));
+	TEST_RES(prctl(PR_GET_DUMPABLE, 0, 0, 0, 0), _ret == 1);
+
+	TEST_ERRNO(prctl(PR_SET_DUMPABLE, 2, 0, 0, 0), EINVAL);
+	TEST_ERRNO(prctl(PR_SET_DUMPABLE, 3, 0, 0, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(core_pattern)
+{
+	char buf[64];
+	int fd;
+
+	TEST_RES(write_core_pattern(core_pattern), _ret == core_pattern_len);
+
+	fd = TEST_SUCC(open(CORE_PATTERN_PATH, O_RDONLY));
+	TEST_RES(read(fd, buf, sizeof(buf)),
+		 _ret == core_pattern_len &&
+			 memcmp(buf, core_pattern, core_pattern_len) == 0);
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(madvise_dont_dump)
+{
+	char *addr;
+
+	addr = TEST_SUCC(mmap(NULL, PAGE_SIZE * 2, PROT_READ | PROT_WRITE,
+			      MAP_PRIVATE | MAP_ANONYMOUS, -1, 0));
+	TEST_SUCC(madvise(addr, PAGE_SIZE * 2, MADV_DONTDUMP));
+	TEST_SUCC(madvise(addr, PAGE_SIZE * 2, MADV_DODUMP));
+
+	// The range must be fully mapped.
+	TEST_SUCC(munmap(addr + PAGE_SIZE, PAGE_SIZE));
+	TEST_ERRNO(madvise(addr, PAGE_SIZE * 2, MADV_DONTDUMP), ENOMEM);
+
+	TEST_SUCC(munmap(addr, PAGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(dump_core)
+{
+	char path[64];
+	Elf64_Ehdr ehdr;
+	Elf64_Phdr phdrs[256];
+	char buf[PAGE_SIZE];
+	int status, fd, i;
+	int found_dump_page = 0, found_dont_dump_page = 0;
+	pid_t pid;
+
+	pid = fork_aborted_child(RLIM_INFINITY, 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGABRT && WCOREDUMP(status));
+
+	get_core_path(pid, path, sizeof(path));
+	fd = TEST_SUCC(open(path, O_RDONLY));
+
+	TEST_RES(pread(fd, &ehdr, sizeof(ehdr), 0),
+		 _ret == sizeof(ehdr) &&
+			 memcmp(ehdr.e_ident, ELFMAG, SELFMAG) == 0 &&
+			 ehdr.e_type == ET_CORE &&
+			 ehdr.e_phentsize == sizeof(Elf64_Phdr) &&
+			 ehdr.e_phnum > 1 && ehdr.e_phnum <= 256);
+	TEST_RES(pread(fd, phdrs, sizeof(Elf64_Phdr) * ehdr.e_phnum,
+		       ehdr.e_phoff),
+		 _ret == sizeof(Elf64_Phdr) * ehdr.e_phnum &&
+			 phdrs[0].p_type == PT_NOTE && phdrs[0].p_filesz > 0);
+
+	for (i = 1; i < ehdr.e_phnum; i++) {
+		Elf64_Phdr *phdr = &phdrs[i];
+		unsigned long start = phdr->p_vaddr;
+		unsigned long end = phdr->p_vaddr + phdr->p_memsz;
+
+		if (phdr->p_type != PT_LOAD)
+			continue;
+
+		// The page is dumped with its content.
+		if (start <= (unsigned long)dump_page &&
+		    (unsigned long)dump_page < end) {
+			found_dump_page = 1;
+			TEST_RES(pread(fd, buf, PAGE_SIZE,
+				       phdr->p_offset +
+					       (unsigned long)dump_page -
+					       start),
+				 _ret == PAGE_SIZE && buf[0] == 'x' &&
+					 buf[PAGE_SIZE - 1] == 'x');
+		}
+
+		// The page is excluded by `MADV_DONTDUMP`.
+		if (start <= (unsigned long)dont_dump_page &&
+		    (unsigned long)dont_dump_page < end) {
+			found_dont_dump_page = 1;
+			TEST_RES(phdr->p_filesz, _ret == 0);
+		}
+	}
+	TEST_RES(found_dump_page, _ret == 1);
+	TEST_RES(found_dont_dump_page, _ret == 1);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(unlink(path));
+}
+END_TEST()
+
+FN_TEST(waitid_dumped)
+{
+	char path[64];
+	siginfo_t info;
+	pid_t pid;
+
+	pid = fork_aborted_child(RLIM_INFINITY, 1);
+	TEST_SUCC(waitid(P_PID, pid, &info, WEXITED));
+	TEST_RES(info.si_code, _ret == CLD_DUMPED);
+	TEST_RES(info.si_status, _ret == SIGABRT);
+
+	get_core_path(pid, path, sizeof(path));
+	TEST_SUCC(unlink(path));
+}
+END_TEST()
+
+FN_TEST(truncated_core)
+{
+	char path[64];
+	struct stat stat;
+	int status;
+	pid_t pid;
+
+	// The core file is truncated at the limit, but the core is still dumped.
+	pid = fork_aborted_child(2 * PAGE_SIZE, 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGABRT && WCOREDUMP(status));
+
+	get_core_path(pid, path, sizeof(path));
+	TEST_RES(lstat(path, &stat), stat.st_size == 2 * PAGE_SIZE);
+	TEST_SUCC(unlink(path));
+}
+END_TEST()
+
+FN_TEST(no_core_rlimit)
+{
+	char path[64];
+	int status;
+	pid_t pid;
+
+	pid = fork_aborted_child(0, 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGABRT && !WCOREDUMP(status));
+
+	get_core_path(pid, path, sizeof(path));
+	TEST_ERRNO(access(path, F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(no_core_not_dumpable)
+{
+	char path[64];
+	int status;
+	pid_t pid;
+
+	pid = fork_aborted_child(RLIM_INFINITY, 0);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGABRT && !WCOREDUMP(status));
+
+	get_core_path(pid, path, sizeof(path));
+	TEST_ERRNO(access(path, F_OK), ENOENT);
+}
+END_TEST()
+
+FN_TEST(setuid_not_dumpable)
+{
+	int status;
+	pid_t pid;
+
+	// Changing the effective user ID makes the process not dumpable.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(prctl(PR_GET_DUMPABLE, 0, 0, 0, 0), _ret == 1);
+		CHECK(setresuid(-1, 1000, -1));
+		CHECK_WITH(prctl(PR_GET_DUMPABLE, 0, 0, 0, 0), _ret == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// Setting the same user ID does not affect the process.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(setuid(getuid()));
+		CHECK_WITH(prctl(PR_GET_DUMPABLE, 0, 0, 0, 0), _ret == 1);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_SETUP(cleanup)
+{
+	CHECK_WITH(write_core_pattern("core"), _ret == 4);
+	CHECK(munmap(dump_page, PAGE_SIZE));
+	CHECK(munmap(dont_dump_page, PAGE_SIZE));
+}
+END_SETUP()
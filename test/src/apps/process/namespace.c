@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <fcntl.h>
+#include <sched.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/ipc.h>
+#include <sys/sem.h>
+#include <sys/syscall.h>
+#include <sys/utsname.h>
+#include <sys/wait.h>
+
+static pid_t pid;
+static int status;
+
+#define SEM_KEY 0x4e53
+
+FN_TEST(invalid_flags)
+{
+	TEST_ERRNO(unshare(0x00000001), EINVAL);
+	TEST_ERRNO(syscall(SYS_clone, CLONE_NEWNS | CLONE_FS | SIGCHLD, 0, 0, 0,
+			   0),
+		   EINVAL);
+	TEST_ERRNO(syscall(SYS_clone,
+			   CLONE_NEWPID | CLONE_THREAD | CLONE_SIGHAND |
+				   CLONE_VM,
+			   0, 0, 0, 0),
+		   EINVAL);
+}
+END_TEST()
+
+FN_TEST(uts_namespace)
+{
+	struct utsname old_name, new_name;
+
+	TEST_SUCC(uname(&old_name));
+	TEST_ERRNO(sethostname("0123456789012345678901234567890123456789"
+			       "0123456789012345678901234567890123456789",
+			       80),
+		   EINVAL);
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(unshare(CLONE_NEWUTS));
+		CHECK(sethostname("namespace", 9));
+		CHECK(setdomainname("domain", 6));
+		CHECK(uname(&new_name));
+		CHECK_WITH(strcmp(new_name.nodename, "namespace"), _ret == 0);
+		CHECK_WITH(strcmp(new_name.domainname, "domain"), _ret == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// The names of the parent are not affected.
+	TEST_SUCC(uname(&new_name));
+	TEST_RES(strcmp(new_name.nodename, old_name.nodename), _ret == 0);
+	TEST_RES(strcmp(new_name.domainname, old_name.domainname), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(ns_links)
+{
+	char old_link[64], new_link[64];
+	ssize_t len;
+
+	len = CHECK(readlink("/proc/self/ns/uts", old_link,
+			     sizeof(old_link) - 1));
+	old_link[len] = '\0';
+	TEST_RES(strncmp(old_link, "uts:[", 5), _ret == 0);
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(unshare(CLONE_NEWUTS));
+		len = CHECK(readlink("/proc/self/ns/uts", new_link,
+				     sizeof(new_link) - 1));
+		new_link[len] = '\0';
+		CHECK_WITH(strcmp(old_link, new_link), _ret != 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(setns_ns_file)
+{
+	struct utsname old_name, new_name;
+	char buf[1];
+	int fd;
+
+	TEST_SUCC(uname(&old_name));
+	fd = TEST_SUCC(open("/proc/self/ns/uts", O_RDONLY));
+	TEST_ERRNO(read(fd, buf, sizeof(buf)), EINVAL);
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(unshare(CLONE_NEWUTS));
+		CHECK(sethostname("namespace", 9));
+
+		CHECK_WITH(setns(fd, CLONE_NEWIPC),
+			   _ret == -1 && errno == EINVAL);
+		CHECK(setns(fd, CLONE_NEWUTS));
+		CHECK(uname(&new_name));
+		CHECK_WITH(strcmp(new_name.nodename, old_name.nodename),
+			   _ret == 0);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(ipc_namespace)
+{
+	int semid;
+
+	semid = TEST_SUCC(semget(SEM_KEY, 1, IPC_CREAT | 0600));
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(semget(SEM_KEY, 1, 0));
+		CHECK(unshare(CLONE_NEWIPC));
+		CHECK_WITH(semget(SEM_KEY, 1, 0),
+			   _ret == -1 && errno == ENOENT);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_SUCC(semctl(semid, 0, IPC_RMID));
+}
+END_TEST()
+
+FN_TEST(pid_namespace)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		pid_t self = getpid();
+		pid_t child;
+
+		// The PID namespace of the caller itself does not change.
+		CHECK(unshare(CLONE_NEWPID));
+		CHECK_WITH(getpid(), _ret == self);
+
+		child = CHECK(fork());
+		if (child == 0) {
+			CHECK_WITH(getpid(), _ret == 1);
+			CHECK_WITH(getppid(), _ret == 0);
+			// The process group and the session are invisible
+			// until a new session is created.
+			CHECK_WITH(getpgrp(), _ret == 0);
+			CHECK_WITH(getsid(0), _ret == 0);
+			CHECK_WITH(setsid(), _ret == 1);
+			CHECK_WITH(getpgid(0), _ret == 1);
+			CHECK_WITH(getsid(1), _ret == 1);
+			exit(EXIT_SUCCESS);
+		}
+		CHECK_WITH(child, _ret != 1);
+		CHECK_WITH(waitpid(child, &status, 0),
+			   _ret == child && WIFEXITED(status) &&
+				   WEXITSTATUS(status) == EXIT_SUCCESS);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
@@ -1,7 +1,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
+#define _GNU_SOURCE
+
 #include "../test.h"
 
+#include <linux/sched.h>
+#include <sched.h>
 #include <unistd.h>
 #include <sys/wait.h>
 #include <sys/stat.h>
@@ -9,20 +13,34 @@
 #include <sys/poll.h>
 #include <fcntl.h>
 #include <signal.h>
+#include <string.h>
+
+#define CHILD_FD 100
 
 static int child_pid;
 static int pid_fd;
+static int pipe_fds[2];
 
 FN_SETUP(create_child)
 {
+	char buf[1];
+
+	CHECK(pipe(pipe_fds));
+
 	child_pid = CHECK(fork());
 
 	if (child_pid == 0) {
+		// Install the pipe's write end at a file descriptor only in the child.
+		CHECK(dup2(pipe_fds[1], CHILD_FD));
+		CHECK(write(pipe_fds[1], "r", 1));
 		while (1) {
 			usleep(100);
 		}
 		exit(EXIT_SUCCESS);
 	}
+
+	// Wait for the child to be ready.
+	CHECK_WITH(read(pipe_fds[0], buf, 1), _ret == 1 && buf[0] == 'r');
 }
 END_SETUP()
 
@@ -60,6 +78,168 @@ FN_TEST(file_stat)
 }
 END_TEST()
 
+FN_TEST(getfd)
+{
+	struct stat child_stat, parent_stat;
+	char buf[1];
+	int fd;
+
+	TEST_ERRNO(syscall(SYS_pidfd_getfd, pid_fd, CHILD_FD, 1), EINVAL);
+	TEST_ERRNO(syscall(SYS_pidfd_getfd, pipe_fds[0], CHILD_FD, 0), EBADF);
+	TEST_ERRNO(syscall(SYS_pidfd_getfd, pid_fd, CHILD_FD + 1, 0), EBADF);
+
+	// The file is duplicated from the child's file table.
+	fd = TEST_RES(syscall(SYS_pidfd_getfd, pid_fd, CHILD_FD, 0),
+		      _ret != CHILD_FD);
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+	TEST_SUCC(fstat(fd, &child_stat));
+	TEST_SUCC(fstat(pipe_fds[1], &parent_stat));
+	TEST_RES(0, child_stat.st_dev == parent_stat.st_dev &&
+			    child_stat.st_ino == parent_stat.st_ino);
+
+	TEST_RES(write(fd, "x", 1), _ret == 1);
+	TEST_RES(read(pipe_fds[0], buf, 1), _ret == 1 && buf[0] == 'x');
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+static siginfo_t received_info[2];
+
+static void sigusr_handler(int sig, siginfo_t *info, void *ucontext)
+{
+	received_info[sig == SIGUSR1 ? 0 : 1] = *info;
+}
+
+FN_TEST(send_signal)
+{
+	struct sigaction sa = {};
+	siginfo_t info;
+	int self_fd;
+
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pid_fd, 0, NULL, 8), EINVAL);
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pipe_fds[0], 0, NULL, 0),
+		   EBADF);
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pid_fd, 1000, NULL, 0),
+		   EINVAL);
+	TEST_SUCC(syscall(SYS_pidfd_send_signal, pid_fd, 0, NULL, 0));
+
+	// The signal number in the information must match.
+	memset(&info, 0, sizeof(info));
+	info.si_signo = SIGUSR2;
+	info.si_code = SI_QUEUE;
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pid_fd, SIGUSR1, &info, 0),
+		   EINVAL);
+
+	// The signal code cannot be faked when signaling other processes.
+	info.si_signo = SIGUSR1;
+	info.si_code = SI_USER;
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pid_fd, SIGUSR1, &info, 0),
+		   EPERM);
+	info.si_code = SI_TKILL;
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pid_fd, SIGUSR1, &info, 0),
+		   EPERM);
+
+	sa.sa_sigaction = sigusr_handler;
+	sa.sa_flags = SA_SIGINFO;
+	TEST_SUCC(sigaction(SIGUSR1, &sa, NULL));
+	self_fd = TEST_SUCC(syscall(SYS_pidfd_open, getpid(), 0));
+
+	// Without the information, the signal is reported as sent by `kill`.
+	memset(received_info, 0, sizeof(received_info));
+	TEST_SUCC(syscall(SYS_pidfd_send_signal, self_fd, SIGUSR1, NULL, 0));
+	TEST_RES(received_info[0].si_signo,
+		 _ret == SIGUSR1 && received_info[0].si_code == SI_USER &&
+			 received_info[0].si_pid == getpid() &&
+			 received_info[0].si_uid == getuid());
+
+	// The information provided by the sender is delivered as is.
+	info.si_code = SI_QUEUE;
+	info.si_pid = 12345;
+	info.si_uid = 54321;
+	info.si_value.sival_int = 42;
+	TEST_SUCC(syscall(SYS_pidfd_send_signal, self_fd, SIGUSR1, &info, 0));
+	TEST_RES(received_info[0].si_signo,
+		 _ret == SIGUSR1 && received_info[0].si_code == SI_QUEUE &&
+			 received_info[0].si_pid == 12345 &&
+			 received_info[0].si_uid == 54321 &&
+			 received_info[0].si_value.sival_int == 42);
+
+	sa.sa_handler = SIG_DFL;
+	sa.sa_flags = 0;
+	TEST_SUCC(sigaction(SIGUSR1, &sa, NULL));
+	TEST_SUCC(close(self_fd));
+}
+END_TEST()
+
+FN_TEST(send_signal_pid_ns)
+{
+	sigset_t mask, old_mask;
+	int status;
+	pid_t pid;
+
+	// Block the signals so that they stay pending until the receiver is ready.
+	sigemptyset(&mask);
+	sigaddset(&mask, SIGUSR1);
+	sigaddset(&mask, SIGUSR2);
+	TEST_SUCC(sigprocmask(SIG_BLOCK, &mask, &old_mask));
+
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		struct sigaction sa = {
+			.sa_sigaction = sigusr_handler,
+			.sa_flags = SA_SIGINFO,
+		};
+		siginfo_t info;
+		pid_t child;
+		int fd;
+
+		CHECK(unshare(CLONE_NEWPID));
+		child = CHECK(fork());
+		if (child == 0) {
+			CHECK(sigaction(SIGUSR1, &sa, NULL));
+			CHECK(sigaction(SIGUSR2, &sa, NULL));
+			memset(received_info, 0, sizeof(received_info));
+			sigemptyset(&mask);
+			while (received_info[0].si_signo == 0 ||
+			       received_info[1].si_signo == 0)
+				sigsuspend(&mask);
+
+			// The sender is invisible in the PID namespace of the
+			// receiver, so the PID of the sender is reported as zero.
+			CHECK_WITH(received_info[0].si_code,
+				   _ret == SI_USER &&
+					   received_info[0].si_pid == 0);
+			CHECK_WITH(received_info[1].si_code,
+				   _ret == SI_QUEUE &&
+					   received_info[1].si_pid == 0 &&
+					   received_info[1].si_value.sival_int ==
+						   42);
+			exit(EXIT_SUCCESS);
+		}
+
+		fd = CHECK(syscall(SYS_pidfd_open, child, 0));
+		CHECK(syscall(SYS_pidfd_send_signal, fd, SIGUSR1, NULL, 0));
+		memset(&info, 0, sizeof(info));
+		info.si_signo = SIGUSR2;
+		info.si_code = SI_QUEUE;
+		info.si_pid = getpid();
+		info.si_value.sival_int = 42;
+		CHECK(syscall(SYS_pidfd_send_signal, fd, SIGUSR2, &info, 0));
+
+		CHECK_WITH(waitpid(child, &status, 0),
+			   _ret == child && WIFEXITED(status) &&
+				   WEXITSTATUS(status) == EXIT_SUCCESS);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_SUCC(sigprocmask(SIG_SETMASK, &old_mask, NULL));
+}
+END_TEST()
+
 #define POLL_EVENTS (POLLIN | POLLOUT | POLLHUP | POLLERR)
 static struct pollfd pfd;
 
@@ -69,7 +249,7 @@ FN_TEST(poll)
 	pfd.events = POLL_EVENTS;
 	TEST_RES(poll(&pfd, 1, 0), pfd.revents == 0);
 
-	TEST_SUCC(kill(child_pid, SIGKILL));
+	TEST_SUCC(syscall(SYS_pidfd_send_signal, pid_fd, SIGKILL, NULL, 0));
 	sleep(1);
 	TEST_RES(poll(&pfd, 1, 0), pfd.revents == POLLIN);
 }
@@ -82,11 +262,44 @@ FN_TEST(wait)
 	pfd.revents = 0;
 	TEST_RES(poll(&pfd, 1, 0), pfd.revents == POLLIN);
 	TEST_ERRNO(waitid(P_PIDFD, pid_fd, NULL, WNOHANG | WEXITED), ECHILD);
+
+	// The process has been reaped.
+	TEST_ERRNO(syscall(SYS_pidfd_send_signal, pid_fd, 0, NULL, 0), ESRCH);
+	TEST_ERRNO(syscall(SYS_pidfd_getfd, pid_fd, CHILD_FD, 0), ESRCH);
+}
+END_TEST()
+
+FN_TEST(clone_pidfd)
+{
+	struct clone_args args = {
+		.flags = CLONE_PIDFD,
+		.exit_signal = SIGCHLD,
+	};
+	siginfo_t info;
+	int fd = -1;
+	pid_t pid;
+
+	args.pidfd = (unsigned long)&fd;
+	pid = TEST_SUCC(syscall(SYS_clone3, &args, sizeof(args)));
+	if (pid == 0) {
+		pause();
+		exit(EXIT_FAILURE);
+	}
+	TEST_RES(fcntl(fd, F_GETFD), _ret == FD_CLOEXEC);
+
+	TEST_SUCC(syscall(SYS_pidfd_send_signal, fd, SIGTERM, NULL, 0));
+	TEST_RES(waitid(P_PIDFD, fd, &info, WEXITED),
+		 info.si_pid == pid && info.si_code == CLD_KILLED &&
+			 info.si_status == SIGTERM);
+
+	TEST_SUCC(close(fd));
 }
 END_TEST()
 
 FN_SETUP(cleanup)
 {
 	CHECK(close(pid_fd));
+	CHECK(close(pipe_fds[0]));
+	CHECK(close(pipe_fds[1]));
 }
 END_SETUP()
\ No newline at end of file
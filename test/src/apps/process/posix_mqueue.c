@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <dirent.h>
+#include <fcntl.h>
+#include <mqueue.h>
+#include <signal.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+#include <unistd.h>
+#include <sys/epoll.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+
+#define MQ_NAME "/mq_test"
+#define MQ_PATH "/dev/mqueue/mq_test"
+#define MQ_MAXMSG 4
+#define MQ_MSGSIZE 64
+
+static mqd_t mqd;
+static pid_t pid;
+static int status;
+
+static int recv_prio(mqd_t mqd, const char *text)
+{
+	char buf[MQ_MSGSIZE];
+	unsigned int prio;
+
+	if (mq_receive(mqd, buf, sizeof(buf), &prio) != strlen(text) + 1)
+		return -1;
+	if (strcmp(buf, text) != 0)
+		return -1;
+	return prio;
+}
+
+FN_SETUP(create)
+{
+	struct mq_attr attr = { .mq_maxmsg = MQ_MAXMSG,
+				.mq_msgsize = MQ_MSGSIZE };
+
+	mqd = CHECK(mq_open(MQ_NAME, O_RDWR | O_CREAT | O_EXCL, 0600, &attr));
+}
+END_SETUP()
+
+FN_TEST(open)
+{
+	struct mq_attr attr = { .mq_maxmsg = 0, .mq_msgsize = MQ_MSGSIZE };
+
+	TEST_ERRNO(mq_open(MQ_NAME, O_RDWR | O_CREAT | O_EXCL, 0600, NULL),
+		   EEXIST);
+	TEST_ERRNO(mq_open("/mq_none", O_RDWR), ENOENT);
+	TEST_ERRNO(mq_open("/mq/none", O_RDWR | O_CREAT, 0600, NULL), EACCES);
+	TEST_ERRNO(mq_open("/mq_none", O_RDWR | O_CREAT, 0600, &attr), EINVAL);
+
+	TEST_RES(mq_open(MQ_NAME, O_RDWR | O_CREAT, 0600, NULL),
+		 _ret != mqd && mq_close(_ret) == 0);
+	TEST_RES(fcntl(mqd, F_GETFD), _ret == FD_CLOEXEC);
+}
+END_TEST()
+
+FN_TEST(attr)
+{
+	struct mq_attr attr, old_attr;
+
+	TEST_RES(mq_getattr(mqd, &attr),
+		 attr.mq_flags == 0 && attr.mq_maxmsg == MQ_MAXMSG &&
+			 attr.mq_msgsize == MQ_MSGSIZE && attr.mq_curmsgs == 0);
+
+	attr.mq_flags = O_NONBLOCK;
+	TEST_SUCC(mq_setattr(mqd, &attr, NULL));
+	TEST_RES(fcntl(mqd, F_GETFL), _ret & O_NONBLOCK);
+
+	attr.mq_flags = 0;
+	TEST_RES(mq_setattr(mqd, &attr, &old_attr),
+		 old_attr.mq_flags == O_NONBLOCK);
+	TEST_RES(mq_getattr(mqd, &attr), attr.mq_flags == 0);
+
+	attr.mq_flags = O_APPEND;
+	TEST_ERRNO(mq_setattr(mqd, &attr, NULL), EINVAL);
+}
+END_TEST()
+
+FN_TEST(priority)
+{
+	char buf[MQ_MSGSIZE + 1] = { 0 };
+	struct mq_attr attr;
+
+	TEST_SUCC(mq_send(mqd, "a", 2, 1));
+	TEST_SUCC(mq_send(mqd, "b", 2, 3));
+	TEST_SUCC(mq_send(mqd, "c", 2, 3));
+	TEST_SUCC(mq_send(mqd, "d", 2, 2));
+	TEST_RES(mq_getattr(mqd, &attr), attr.mq_curmsgs == 4);
+
+	// Messages of higher priorities are received first.
+	TEST_RES(recv_prio(mqd, "b"), _ret == 3);
+	TEST_RES(recv_prio(mqd, "c"), _ret == 3);
+	TEST_RES(recv_prio(mqd, "d"), _ret == 2);
+	TEST_RES(recv_prio(mqd, "a"), _ret == 1);
+	TEST_RES(mq_getattr(mqd, &attr), attr.mq_curmsgs == 0);
+
+	TEST_ERRNO(mq_send(mqd, buf, MQ_MSGSIZE + 1, 0), EMSGSIZE);
+	TEST_ERRNO(mq_send(mqd, "a", 2, 32768), EINVAL);
+	TEST_ERRNO(mq_receive(mqd, buf, MQ_MSGSIZE - 1, NULL), EMSGSIZE);
+}
+END_TEST()
+
+FN_TEST(nonblocking)
+{
+	char buf[MQ_MSGSIZE];
+	mqd_t nb_mqd;
+	int i;
+
+	nb_mqd = TEST_SUCC(mq_open(MQ_NAME, O_RDWR | O_NONBLOCK));
+
+	TEST_ERRNO(mq_receive(nb_mqd, buf, sizeof(buf), NULL), EAGAIN);
+	for (i = 0; i < MQ_MAXMSG; ++i)
+		TEST_SUCC(mq_send(nb_mqd, "a", 2, 0));
+	TEST_ERRNO(mq_send(nb_mqd, "a", 2, 0), EAGAIN);
+	for (i = 0; i < MQ_MAXMSG; ++i)
+		TEST_RES(mq_receive(nb_mqd, buf, sizeof(buf), NULL), _ret == 2);
+	TEST_ERRNO(mq_receive(nb_mqd, buf, sizeof(buf), NULL), EAGAIN);
+
+	TEST_SUCC(mq_close(nb_mqd));
+}
+END_TEST()
+
+FN_TEST(timeout)
+{
+	char buf[MQ_MSGSIZE];
+	struct timespec ts;
+
+	TEST_SUCC(clock_gettime(CLOCK_REALTIME, &ts));
+	ts.tv_nsec += 10 * 1000 * 1000;
+	if (ts.tv_nsec >= 1000 * 1000 * 1000) {
+		ts.tv_sec += 1;
+		ts.tv_nsec -= 1000 * 1000 * 1000;
+	}
+	TEST_ERRNO(mq_timedreceive(mqd, buf, sizeof(buf), NULL, &ts),
+		   ETIMEDOUT);
+
+	ts.tv_nsec = 1000 * 1000 * 1000;
+	TEST_ERRNO(mq_timedreceive(mqd, buf, sizeof(buf), NULL, &ts), EINVAL);
+}
+END_TEST()
+
+FN_TEST(blocking)
+{
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(100 * 1000);
+		CHECK(mq_send(mqd, "hello", 6, 5));
+		exit(EXIT_SUCCESS);
+	}
+
+	// The receiver is blocked until the child sends the message.
+	TEST_RES(recv_prio(mqd, "hello"), _ret == 5);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(access_mode)
+{
+	char buf[MQ_MSGSIZE];
+	mqd_t rd_mqd, wr_mqd;
+	int fds[2];
+
+	rd_mqd = TEST_SUCC(mq_open(MQ_NAME, O_RDONLY));
+	wr_mqd = TEST_SUCC(mq_open(MQ_NAME, O_WRONLY));
+
+	TEST_ERRNO(mq_send(rd_mqd, "a", 2, 0), EBADF);
+	TEST_SUCC(mq_send(wr_mqd, "a", 2, 0));
+	TEST_ERRNO(mq_receive(wr_mqd, buf, sizeof(buf), NULL), EBADF);
+	TEST_RES(mq_receive(rd_mqd, buf, sizeof(buf), NULL), _ret == 2);
+
+	TEST_SUCC(mq_close(rd_mqd));
+	TEST_SUCC(mq_close(wr_mqd));
+
+	// Other files are not message queues.
+	TEST_SUCC(pipe(fds));
+	TEST_ERRNO(mq_send(fds[1], "a", 2, 0), EBADF);
+	TEST_SUCC(close(fds[0]));
+	TEST_SUCC(close(fds[1]));
+}
+END_TEST()
+
+FN_TEST(epoll)
+{
+	struct epoll_event ev = { .events = EPOLLIN | EPOLLOUT };
+	char buf[MQ_MSGSIZE];
+	int epfd;
+
+	epfd = TEST_SUCC(epoll_create1(0));
+	TEST_SUCC(epoll_ctl(epfd, EPOLL_CTL_ADD, mqd, &ev));
+
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == EPOLLOUT);
+
+	TEST_SUCC(mq_send(mqd, "a", 2, 0));
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == (EPOLLIN | EPOLLOUT));
+
+	TEST_RES(mq_receive(mqd, buf, sizeof(buf), NULL), _ret == 2);
+	TEST_RES(epoll_wait(epfd, &ev, 1, 0),
+		 _ret == 1 && ev.events == EPOLLOUT);
+
+	TEST_SUCC(close(epfd));
+}
+END_TEST()
+
+FN_TEST(notify)
+{
+	struct sigevent sev = { .sigev_notify = SIGEV_SIGNAL,
+				.sigev_signo = SIGUSR1,
+				.sigev_value.sival_int = 42 };
+	struct timespec ts = { .tv_sec = 0, .tv_nsec = 0 };
+	char buf[MQ_MSGSIZE];
+	siginfo_t info;
+	sigset_t mask;
+
+	sigemptyset(&mask);
+	sigaddset(&mask, SIGUSR1);
+	TEST_SUCC(sigprocmask(SIG_BLOCK, &mask, NULL));
+
+	TEST_SUCC(mq_notify(mqd, &sev));
+	TEST_ERRNO(mq_notify(mqd, &sev), EBUSY);
+
+	// Only one process can be registered.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(mq_notify(mqd, &sev), _ret == -1 && errno == EBUSY);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// The notification is sent when a message arrives at the empty queue.
+	TEST_SUCC(mq_send(mqd, "a", 2, 0));
+	TEST_RES(sigtimedwait(&mask, &info, &ts),
+		 _ret == SIGUSR1 && info.si_code == SI_MESGQ &&
+			 info.si_pid == getpid() &&
+			 info.si_value.sival_int == 42);
+
+	// The registration is removed after the notification.
+	TEST_SUCC(mq_send(mqd, "b", 2, 0));
+	TEST_ERRNO(sigtimedwait(&mask, &info, &ts), EAGAIN);
+	TEST_SUCC(mq_notify(mqd, &sev));
+
+	// No notification is sent if the queue is not empty.
+	TEST_SUCC(mq_send(mqd, "c", 2, 0));
+	TEST_ERRNO(sigtimedwait(&mask, &info, &ts), EAGAIN);
+	TEST_RES(mq_receive(mqd, buf, sizeof(buf), NULL), _ret == 2);
+	TEST_RES(mq_receive(mqd, buf, sizeof(buf), NULL), _ret == 2);
+	TEST_RES(mq_receive(mqd, buf, sizeof(buf), NULL), _ret == 2);
+
+	// The registration can be removed explicitly.
+	TEST_SUCC(mq_notify(mqd, NULL));
+	TEST_SUCC(mq_send(mqd, "a", 2, 0));
+	TEST_ERRNO(sigtimedwait(&mask, &info, &ts), EAGAIN);
+	TEST_RES(mq_receive(mqd, buf, sizeof(buf), NULL), _ret == 2);
+
+	sev.sigev_signo = 100;
+	TEST_ERRNO(mq_notify(mqd, &sev), EINVAL);
+
+	TEST_SUCC(sigprocmask(SIG_UNBLOCK, &mask, NULL));
+}
+END_TEST()
+
+FN_TEST(file)
+{
+	char buf[128] = { 0 };
+	struct dirent *dirent;
+	struct stat st;
+	int found = 0;
+	DIR *dir;
+	int fd;
+
+	TEST_RES(stat(MQ_PATH, &st),
+		 S_ISREG(st.st_mode) && (st.st_mode & 0777) == 0600);
+
+	dir = TEST_RES(opendir("/dev/mqueue"), _ret != NULL);
+	while ((dirent = readdir(dir)) != NULL)
+		found |= strcmp(dirent->d_name, "mq_test") == 0;
+	TEST_RES(found, _ret == 1);
+	TEST_SUCC(closedir(dir));
+
+	// Reading the file reports the status of the queue.
+	TEST_SUCC(mq_send(mqd, "hello", 6, 0));
+	fd = TEST_SUCC(open(MQ_PATH, O_RDONLY));
+	TEST_RES(read(fd, buf, sizeof(buf) - 1),
+		 _ret > 0 && strncmp(buf, "QSIZE:6 ", 8) == 0);
+	TEST_ERRNO(write(fd, "a", 1), EBADF);
+	TEST_SUCC(close(fd));
+	TEST_RES(recv_prio(mqd, "hello"), _ret == 0);
+}
+END_TEST()
+
+FN_SETUP(unlink)
+{
+	char buf[MQ_MSGSIZE];
+
+	CHECK(mq_unlink(MQ_NAME));
+	CHECK_WITH(mq_open(MQ_NAME, O_RDWR), _ret == -1 && errno == ENOENT);
+	CHECK_WITH(mq_unlink(MQ_NAME), _ret == -1 && errno == ENOENT);
+
+	// The queue is still usable via the existing descriptor.
+	CHECK(mq_send(mqd, "a", 2, 0));
+	CHECK_WITH(mq_receive(mqd, buf, sizeof(buf), NULL), _ret == 2);
+	CHECK(mq_close(mqd));
+}
+END_SETUP()
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/mman.h>
+#include <sys/uio.h>
+#include <sys/wait.h>
+
+#define PAGE_SIZE 4096
+
+static char buffer[32] = "Hello, child!";
+static const char readonly_data[PAGE_SIZE]
+	__attribute__((aligned(PAGE_SIZE))) = "Read-only data";
+static pid_t pid;
+static int to_child[2];
+static char mem_path[64];
+
+// Forks a child that waits for a byte from the pipe and exits with zero if
+// `buffer` has been changed to "Hello, parent!" by the parent.
+FN_SETUP(fork_child)
+{
+	char byte;
+
+	CHECK(pipe(to_child));
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(close(to_child[1]));
+		CHECK_WITH(read(to_child[0], &byte, 1), _ret == 1);
+		if (strcmp(buffer, "Hello, parent!") != 0)
+			exit(EXIT_FAILURE);
+		exit(EXIT_SUCCESS);
+	}
+
+	CHECK(close(to_child[0]));
+	snprintf(mem_path, sizeof(mem_path), "/proc/%d/mem", pid);
+}
+END_SETUP()
+
+FN_TEST(process_vm_readv)
+{
+	char buf1[5], buf2[32];
+	struct iovec local[2] = {
+		{ .iov_base = buf1, .iov_len = sizeof(buf1) },
+		{ .iov_base = buf2, .iov_len = sizeof(buf2) },
+	};
+	struct iovec remote[2] = {
+		{ .iov_base = buffer, .iov_len = 7 },
+		{ .iov_base = buffer + 7, .iov_len = 7 },
+	};
+
+	TEST_RES(process_vm_readv(pid, local, 2, remote, 2, 0),
+		 _ret == 14 && memcmp(buf1, "Hello", 5) == 0 &&
+			 strcmp(buf2, ", child!") == 0);
+}
+END_TEST()
+
+FN_TEST(process_vm_writev)
+{
+	char buf[32] = "Hello, parent!";
+	struct iovec local = { .iov_base = buf, .iov_len = 15 };
+	struct iovec remote = { .iov_base = buffer, .iov_len = 15 };
+
+	TEST_RES(process_vm_writev(pid, &local, 1, &remote, 1, 0), _ret == 15);
+	TEST_RES(process_vm_readv(pid, &local, 1, &remote, 1, 0),
+		 _ret == 15 && strcmp(buf, "Hello, parent!") == 0);
+
+	// The memory of the current process is not affected.
+	TEST_RES(strcmp(buffer, "Hello, child!"), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(process_vm_partial)
+{
+	char *page;
+	char buf[PAGE_SIZE * 2];
+	struct iovec local = { .iov_base = buf, .iov_len = sizeof(buf) };
+	struct iovec remote;
+
+	page = mmap(NULL, PAGE_SIZE * 2, PROT_READ | PROT_WRITE,
+		    MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+	TEST_SUCC(page == MAP_FAILED ? -1 : 0);
+	TEST_SUCC(munmap(page + PAGE_SIZE, PAGE_SIZE));
+	page[PAGE_SIZE - 1] = 'x';
+
+	// The transfer stops at the first page that cannot be accessed.
+	remote.iov_base = page + PAGE_SIZE - 1;
+	remote.iov_len = 2;
+	TEST_RES(process_vm_readv(getpid(), &local, 1, &remote, 1, 0),
+		 _ret == 1 && buf[0] == 'x');
+
+	remote.iov_base = page + PAGE_SIZE;
+	TEST_ERRNO(process_vm_readv(getpid(), &local, 1, &remote, 1, 0),
+		   EFAULT);
+
+	TEST_SUCC(munmap(page, PAGE_SIZE));
+}
+END_TEST()
+
+FN_TEST(process_vm_errors)
+{
+	char buf[8];
+	struct iovec local = { .iov_base = buf, .iov_len = sizeof(buf) };
+	struct iovec remote = { .iov_base = buffer, .iov_len = sizeof(buf) };
+
+	TEST_ERRNO(process_vm_readv(pid, &local, 1, &remote, 1, 1), EINVAL);
+	TEST_ERRNO(process_vm_readv(pid, &local, 1025, &remote, 1, 0), EINVAL);
+	TEST_ERRNO(process_vm_readv(0x7fffffff, &local, 1, &remote, 1, 0),
+		   ESRCH);
+
+	// The memory protection is respected.
+	remote.iov_base = (void *)readonly_data;
+	TEST_ERRNO(process_vm_writev(getpid(), &local, 1, &remote, 1, 0),
+		   EFAULT);
+}
+END_TEST()
+
+FN_TEST(proc_pid_mem)
+{
+	char buf[32];
+	int fd;
+
+	fd = TEST_SUCC(open(mem_path, O_RDWR));
+
+	TEST_RES(pread(fd, buf, 15, (off_t)buffer),
+		 _ret == 15 && strcmp(buf, "Hello, parent!") == 0);
+	TEST_RES(pwrite(fd, "HELLO", 5, (off_t)buffer), _ret == 5);
+	TEST_RES(pread(fd, buf, 15, (off_t)buffer),
+		 _ret == 15 && strcmp(buf, "HELLO, parent!") == 0);
+	TEST_RES(pwrite(fd, "Hello", 5, (off_t)buffer), _ret == 5);
+
+	TEST_ERRNO(pread(fd, buf, sizeof(buf), 0), EIO);
+	TEST_ERRNO(pwrite(fd, buf, sizeof(buf), 0), EIO);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(proc_self_mem_force_write)
+{
+	char buf[16];
+	int fd;
+
+	fd = TEST_SUCC(open("/proc/self/mem", O_RDWR));
+
+	// Like debuggers, private read-only mappings can be written.
+	TEST_RES(pwrite(fd, "READ", 4, (off_t)readonly_data), _ret == 4);
+	TEST_RES(pread(fd, buf, 14, (off_t)readonly_data),
+		 _ret == 14 && memcmp(buf, "READ-only data", 14) == 0);
+
+	TEST_RES(lseek(fd, (off_t)buffer, SEEK_SET), _ret == (off_t)buffer);
+	TEST_RES(read(fd, buf, 14),
+		 _ret == 14 && memcmp(buf, "Hello, child!", 14) == 0);
+
+	TEST_SUCC(close(fd));
+}
+END_TEST()
+
+FN_TEST(proc_pid_mem_exec)
+{
+	int exec_pipe[2], stdin_pipe[2];
+	char buf[16];
+	pid_t child;
+	int fd, status;
+
+	TEST_SUCC(pipe2(exec_pipe, O_CLOEXEC));
+	TEST_SUCC(pipe(stdin_pipe));
+
+	// Forks a child that executes `cat` after the parent opens its memory.
+	child = TEST_SUCC(fork());
+	if (child == 0) {
+		CHECK(dup2(stdin_pipe[0], STDIN_FILENO));
+		CHECK(close(stdin_pipe[0]));
+		CHECK(close(stdin_pipe[1]));
+		CHECK(close(exec_pipe[0]));
+		CHECK_WITH(read(STDIN_FILENO, buf, 1), _ret == 1);
+		CHECK(execlp("cat", "cat", NULL));
+	}
+	TEST_SUCC(close(stdin_pipe[0]));
+	TEST_SUCC(close(exec_pipe[1]));
+
+	snprintf(buf, sizeof(buf), "/proc/%d/mem", child);
+	fd = TEST_SUCC(open(buf, O_RDWR));
+	TEST_RES(pread(fd, buf, 14, (off_t)buffer),
+		 _ret == 14 && memcmp(buf, "Hello, child!", 14) == 0);
+
+	// The opened file cannot access the memory after `execve`.
+	TEST_RES(write(stdin_pipe[1], "", 1), _ret == 1);
+	TEST_RES(read(exec_pipe[0], buf, 1), _ret == 0);
+	TEST_RES(pread(fd, buf, 14, (off_t)buffer), _ret == 0);
+	TEST_RES(pwrite(fd, buf, 14, (off_t)buffer), _ret == 0);
+
+	TEST_SUCC(close(fd));
+	TEST_SUCC(close(exec_pipe[0]));
+	TEST_SUCC(close(stdin_pipe[1]));
+	TEST_RES(waitpid(child, &status, 0),
+		 _ret == child && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(wait_child)
+{
+	int status;
+
+	TEST_RES(write(to_child[1], "", 1), _ret == 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+	TEST_SUCC(close(to_child[1]));
+}
+END_TEST()
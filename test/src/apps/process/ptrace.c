@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#include "../test.h"
+
+#include <signal.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include <sys/ptrace.h>
+#include <sys/syscall.h>
+#include <sys/user.h>
+#include <sys/wait.h>
+
+static volatile long shared_word = 0x1234;
+static pid_t pid;
+static int status;
+
+// Forks a child that calls `PTRACE_TRACEME` and stops itself with `sig`.
+//
+// After being resumed, the child invokes `getppid` with a raw syscall and exits with zero if
+// `shared_word` has been changed to `0x5678` by the tracer.
+static pid_t fork_tracee(int sig)
+{
+	pid_t child = CHECK(fork());
+
+	if (child == 0) {
+		CHECK(ptrace(PTRACE_TRACEME, 0, NULL, NULL));
+		CHECK(kill(getpid(), sig));
+		CHECK(syscall(SYS_getppid));
+		exit(shared_word == 0x5678 ? EXIT_SUCCESS : EXIT_FAILURE);
+	}
+
+	return child;
+}
+
+FN_TEST(invalid_requests)
+{
+	TEST_ERRNO(ptrace(PTRACE_CONT, getpid(), NULL, NULL), ESRCH);
+	TEST_ERRNO(ptrace(PTRACE_PEEKDATA, 0x7fffffff, NULL, NULL), ESRCH);
+	TEST_ERRNO(ptrace(PTRACE_ATTACH, getpid(), NULL, NULL), EPERM);
+	TEST_ERRNO(ptrace(0x7fff, getpid(), NULL, NULL), EIO);
+}
+END_TEST()
+
+FN_TEST(traceme_stop)
+{
+	pid = fork_tracee(SIGSTOP);
+
+	// The stop is reported to the tracer even without `WSTOPPED`.
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSTOPPED(status) &&
+			 WSTOPSIG(status) == SIGSTOP);
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(peek_and_poke)
+{
+	TEST_RES(ptrace(PTRACE_PEEKDATA, pid, &shared_word, NULL),
+		 _ret == 0x1234);
+	TEST_SUCC(ptrace(PTRACE_POKEDATA, pid, &shared_word, (void *)0x5678));
+	TEST_RES(ptrace(PTRACE_PEEKDATA, pid, &shared_word, NULL),
+		 _ret == 0x5678);
+
+	// The memory of the tracer is not affected.
+	TEST_RES(shared_word, _ret == 0x1234);
+
+	TEST_ERRNO(ptrace(PTRACE_PEEKDATA, pid, NULL, NULL), EIO);
+	TEST_ERRNO(ptrace(PTRACE_POKEDATA, pid, NULL, NULL), EIO);
+}
+END_TEST()
+
+FN_TEST(get_siginfo)
+{
+	siginfo_t siginfo;
+
+	TEST_RES(ptrace(PTRACE_GETSIGINFO, pid, NULL, &siginfo),
+		 siginfo.si_signo == SIGSTOP);
+}
+END_TEST()
+
+FN_TEST(syscall_stops)
+{
+	TEST_ERRNO(ptrace(PTRACE_SETOPTIONS, pid, NULL, (void *)0xdead0000),
+		   EINVAL);
+	TEST_SUCC(ptrace(PTRACE_SETOPTIONS, pid, NULL,
+			 (void *)(long)PTRACE_O_TRACESYSGOOD));
+
+	// Suppress `SIGSTOP` and stop at the entry of `getppid`.
+	TEST_SUCC(ptrace(PTRACE_SYSCALL, pid, NULL, NULL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSTOPPED(status) &&
+			 WSTOPSIG(status) == (SIGTRAP | 0x80));
+
+	// The tracee is running, so it cannot be inspected.
+	TEST_SUCC(ptrace(PTRACE_SYSCALL, pid, NULL, NULL));
+	TEST_ERRNO(ptrace(PTRACE_PEEKDATA, pid, &shared_word, NULL), ESRCH);
+
+	// Stop at the exit of `getppid`.
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSTOPPED(status) &&
+			 WSTOPSIG(status) == (SIGTRAP | 0x80));
+}
+END_TEST()
+
+#ifdef __x86_64__
+FN_TEST(get_and_set_regs)
+{
+	struct user_regs_struct regs;
+
+	TEST_RES(ptrace(PTRACE_GETREGS, pid, NULL, &regs),
+		 regs.orig_rax == SYS_getppid && regs.rax == getpid() &&
+			 regs.rip != 0);
+
+	// Change the return value of `getppid`.
+	regs.rax = 42;
+	TEST_SUCC(ptrace(PTRACE_SETREGS, pid, NULL, &regs));
+	TEST_RES(ptrace(PTRACE_GETREGS, pid, NULL, &regs), regs.rax == 42);
+}
+END_TEST()
+#endif
+
+FN_TEST(traceme_exit)
+{
+	TEST_SUCC(ptrace(PTRACE_CONT, pid, NULL, NULL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_ERRNO(ptrace(PTRACE_CONT, pid, NULL, NULL), ESRCH);
+}
+END_TEST()
+
+FN_TEST(signal_delivery)
+{
+	pid = fork_tracee(SIGUSR1);
+
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSTOPPED(status) &&
+			 WSTOPSIG(status) == SIGUSR1);
+
+	// Deliver the signal instead of suppressing it.
+	TEST_SUCC(ptrace(PTRACE_CONT, pid, NULL, (void *)(long)SIGUSR1));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGUSR1);
+}
+END_TEST()
+
+FN_TEST(attach_and_detach)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		while (1) {
+			usleep(100);
+		}
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_SUCC(ptrace(PTRACE_ATTACH, pid, NULL, NULL));
+	TEST_ERRNO(ptrace(PTRACE_ATTACH, pid, NULL, NULL), EPERM);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSTOPPED(status) &&
+			 WSTOPSIG(status) == SIGSTOP);
+
+	TEST_SUCC(ptrace(PTRACE_DETACH, pid, NULL, NULL));
+	TEST_ERRNO(ptrace(PTRACE_CONT, pid, NULL, NULL), ESRCH);
+
+	TEST_SUCC(kill(pid, SIGKILL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
+
+FN_TEST(seize_and_kill)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		while (1) {
+			usleep(100);
+		}
+		exit(EXIT_SUCCESS);
+	}
+
+	TEST_ERRNO(ptrace(PTRACE_SEIZE, pid, (void *)1, NULL), EIO);
+	TEST_SUCC(ptrace(PTRACE_SEIZE, pid, NULL,
+			 (void *)(long)PTRACE_O_TRACESYSGOOD));
+
+	// `PTRACE_SEIZE` does not stop the tracee.
+	TEST_RES(waitpid(pid, &status, WNOHANG), _ret == 0);
+
+	TEST_SUCC(ptrace(PTRACE_KILL, pid, NULL, NULL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
@@ -0,0 +1,274 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <signal.h>
+#include <stddef.h>
+#include <stdlib.h>
+#include <unistd.h>
+#include <linux/filter.h>
+#include <linux/seccomp.h>
+#include <sys/prctl.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+
+static pid_t pid;
+static int status;
+
+#define ARRAY_LEN(array) (sizeof(array) / sizeof((array)[0]))
+
+#ifndef SYS_SECCOMP
+#define SYS_SECCOMP 1
+#endif
+
+// Installs a filter that returns `action` for syscall `nr` and allows all other syscalls.
+static int install_filter(int nr, unsigned int action, unsigned int flags)
+{
+	struct sock_filter filter[] = {
+		BPF_STMT(BPF_LD | BPF_W | BPF_ABS,
+			 offsetof(struct seccomp_data, nr)),
+		BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, nr, 0, 1),
+		BPF_STMT(BPF_RET | BPF_K, action),
+		BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+	};
+	struct sock_fprog prog = {
+		.len = ARRAY_LEN(filter),
+		.filter = filter,
+	};
+
+	return syscall(SYS_seccomp, SECCOMP_SET_MODE_FILTER, flags, &prog);
+}
+
+static int install_prog(struct sock_filter *filter, unsigned short len)
+{
+	struct sock_fprog prog = {
+		.len = len,
+		.filter = filter,
+	};
+
+	return syscall(SYS_seccomp, SECCOMP_SET_MODE_FILTER, 0, &prog);
+}
+
+FN_TEST(filter_without_no_new_privs)
+{
+	TEST_RES(prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0), _ret == 0);
+
+	// Unprivileged threads cannot install filters without `no_new_privs`.
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(setuid(65534));
+		CHECK_WITH(install_filter(SYS_getppid, SECCOMP_RET_KILL_PROCESS,
+					  0),
+			   _ret == -1 && errno == EACCES);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(no_new_privs)
+{
+	TEST_ERRNO(prctl(PR_SET_NO_NEW_PRIVS, 0, 0, 0, 0), EINVAL);
+	TEST_ERRNO(prctl(PR_SET_NO_NEW_PRIVS, 1, 1, 0, 0), EINVAL);
+	TEST_ERRNO(prctl(PR_GET_NO_NEW_PRIVS, 1, 0, 0, 0), EINVAL);
+
+	TEST_SUCC(prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0));
+	TEST_RES(prctl(PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0), _ret == 1);
+}
+END_TEST()
+
+FN_TEST(invalid_filters)
+{
+	struct sock_filter no_ret[] = {
+		BPF_STMT(BPF_LD | BPF_W | BPF_ABS, 0),
+	};
+	struct sock_filter bad_jump[] = {
+		BPF_JUMP(BPF_JMP | BPF_JEQ | BPF_K, 0, 0, 1),
+		BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+	};
+	struct sock_filter bad_load[] = {
+		BPF_STMT(BPF_LD | BPF_W | BPF_ABS, sizeof(struct seccomp_data)),
+		BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+	};
+	struct sock_filter bad_div[] = {
+		BPF_STMT(BPF_ALU | BPF_DIV | BPF_K, 0),
+		BPF_STMT(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+	};
+
+	TEST_ERRNO(install_prog(no_ret, 0), EINVAL);
+	TEST_ERRNO(install_prog(no_ret, ARRAY_LEN(no_ret)), EINVAL);
+	TEST_ERRNO(install_prog(bad_jump, ARRAY_LEN(bad_jump)), EINVAL);
+	TEST_ERRNO(install_prog(bad_load, ARRAY_LEN(bad_load)), EINVAL);
+	TEST_ERRNO(install_prog(bad_div, ARRAY_LEN(bad_div)), EINVAL);
+	TEST_ERRNO(install_filter(SYS_getppid, SECCOMP_RET_ALLOW, 0x80000000),
+		   EINVAL);
+	TEST_ERRNO(syscall(SYS_seccomp, SECCOMP_SET_MODE_STRICT, 1, NULL),
+		   EINVAL);
+	TEST_ERRNO(syscall(SYS_seccomp, 0x1234, 0, NULL), EINVAL);
+
+	TEST_RES(prctl(PR_GET_SECCOMP, 0, 0, 0, 0), _ret == 0);
+}
+END_TEST()
+
+FN_TEST(action_avail)
+{
+	unsigned int action;
+
+	action = SECCOMP_RET_ALLOW;
+	TEST_SUCC(syscall(SYS_seccomp, SECCOMP_GET_ACTION_AVAIL, 0, &action));
+	action = SECCOMP_RET_KILL_PROCESS;
+	TEST_SUCC(syscall(SYS_seccomp, SECCOMP_GET_ACTION_AVAIL, 0, &action));
+	action = 0x12340000;
+	TEST_ERRNO(syscall(SYS_seccomp, SECCOMP_GET_ACTION_AVAIL, 0, &action),
+		   EOPNOTSUPP);
+}
+END_TEST()
+
+FN_TEST(filter_errno)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(install_filter(SYS_getppid, SECCOMP_RET_ERRNO | EPERM,
+				     SECCOMP_FILTER_FLAG_TSYNC));
+		CHECK_WITH(prctl(PR_GET_SECCOMP, 0, 0, 0, 0), _ret == 2);
+		CHECK_WITH(syscall(SYS_getppid), _ret == -1 && errno == EPERM);
+		CHECK(syscall(SYS_getpid));
+
+		// The newest filter takes precedence over the older ones if the
+		// actions are the same.
+		CHECK(install_filter(SYS_getppid, SECCOMP_RET_ERRNO | ENOENT,
+				     0));
+		CHECK_WITH(syscall(SYS_getppid),
+			   _ret == -1 && errno == ENOENT);
+
+		// The filters are inherited by the child process.
+		pid = CHECK(fork());
+		if (pid == 0) {
+			CHECK_WITH(syscall(SYS_getppid),
+				   _ret == -1 && errno == ENOENT);
+			exit(EXIT_SUCCESS);
+		}
+		CHECK_WITH(waitpid(pid, &status, 0),
+			   _ret == pid && WIFEXITED(status) &&
+				   WEXITSTATUS(status) == EXIT_SUCCESS);
+
+		// The strict mode cannot be set after filters are installed.
+		CHECK_WITH(syscall(SYS_seccomp, SECCOMP_SET_MODE_STRICT, 0,
+				   NULL),
+			   _ret == -1 && errno == EINVAL);
+
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	// The filters of the child process do not affect the parent.
+	TEST_SUCC(syscall(SYS_getppid));
+}
+END_TEST()
+
+static volatile int sigsys_code;
+static volatile int sigsys_errno;
+static volatile int sigsys_syscall;
+
+static void sigsys_handler(int sig, siginfo_t *info, void *ucontext)
+{
+	sigsys_code = info->si_code;
+	sigsys_errno = info->si_errno;
+	sigsys_syscall = info->si_syscall;
+}
+
+FN_TEST(filter_trap)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		struct sigaction sa = {
+			.sa_sigaction = sigsys_handler,
+			.sa_flags = SA_SIGINFO,
+		};
+
+		CHECK(sigaction(SIGSYS, &sa, NULL));
+		CHECK(install_filter(SYS_getppid, SECCOMP_RET_TRAP | 7, 0));
+
+		syscall(SYS_getppid);
+		CHECK_WITH(sigsys_code, _ret == SYS_SECCOMP);
+		CHECK_WITH(sigsys_errno, _ret == 7);
+		CHECK_WITH(sigsys_syscall, _ret == SYS_getppid);
+
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(filter_log)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(install_filter(SYS_getppid, SECCOMP_RET_LOG,
+				     SECCOMP_FILTER_FLAG_LOG));
+		CHECK_WITH(syscall(SYS_getppid), _ret == getppid());
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(filter_kill)
+{
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(install_filter(SYS_getppid, SECCOMP_RET_KILL_PROCESS, 0));
+		syscall(SYS_getppid);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGSYS);
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(install_filter(SYS_getppid, SECCOMP_RET_KILL_THREAD, 0));
+		syscall(SYS_getppid);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGSYS);
+}
+END_TEST()
+
+FN_TEST(strict_mode)
+{
+	// Only `read`, `write`, `exit`, and `rt_sigreturn` are allowed. Note that
+	// `exit_group` is not allowed, so the child must call `exit` directly.
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(prctl(PR_SET_SECCOMP, SECCOMP_MODE_STRICT, 0, 0, 0));
+		syscall(SYS_write, STDERR_FILENO, "", 0);
+		syscall(SYS_exit, EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	pid = CHECK(fork());
+	if (pid == 0) {
+		CHECK(syscall(SYS_seccomp, SECCOMP_SET_MODE_STRICT, 0, NULL));
+		syscall(SYS_getppid);
+		syscall(SYS_exit, EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFSIGNALED(status) &&
+			 WTERMSIG(status) == SIGKILL);
+}
+END_TEST()
@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <grp.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/ipc.h>
+#include <sys/msg.h>
+#include <sys/wait.h>
+
+#define MSG_KEY 0x1234
+
+struct msg {
+	long mtype;
+	char mtext[64];
+};
+
+static int msqid;
+static pid_t pid;
+static int status;
+
+static int send_msg(long mtype, const char *text, int flags)
+{
+	struct msg msg = { .mtype = mtype };
+
+	strcpy(msg.mtext, text);
+	return msgsnd(msqid, &msg, strlen(text) + 1, flags);
+}
+
+static long recv_type(long msgtyp, int flags)
+{
+	struct msg msg;
+
+	if (msgrcv(msqid, &msg, sizeof(msg.mtext), msgtyp, flags) < 0)
+		return -1;
+	return msg.mtype;
+}
+
+static long read_sysctl(const char *path)
+{
+	FILE *file;
+	long value;
+
+	file = fopen(path, "r");
+	if (file == NULL)
+		return -1;
+	if (fscanf(file, "%ld", &value) != 1)
+		value = -1;
+	fclose(file);
+
+	return value;
+}
+
+FN_SETUP(create)
+{
+	msqid = CHECK(msgget(MSG_KEY, IPC_CREAT | IPC_EXCL | 0600));
+}
+END_SETUP()
+
+FN_TEST(get)
+{
+	TEST_RES(msgget(MSG_KEY, 0), _ret == msqid);
+	TEST_RES(msgget(MSG_KEY, IPC_CREAT), _ret == msqid);
+	TEST_ERRNO(msgget(MSG_KEY, IPC_CREAT | IPC_EXCL), EEXIST);
+	TEST_ERRNO(msgget(MSG_KEY + 1, 0), ENOENT);
+
+	// Private queues are always new.
+	TEST_RES(msgget(IPC_PRIVATE, 0600),
+		 _ret != msqid && msgctl(_ret, IPC_RMID, NULL) == 0);
+}
+END_TEST()
+
+FN_TEST(limits)
+{
+	TEST_RES(read_sysctl("/proc/sys/kernel/msgmax"), _ret == 8192);
+	TEST_RES(read_sysctl("/proc/sys/kernel/msgmnb"), _ret == 16384);
+}
+END_TEST()
+
+FN_TEST(receive_by_type)
+{
+	TEST_SUCC(send_msg(3, "three", 0));
+	TEST_SUCC(send_msg(1, "one", 0));
+	TEST_SUCC(send_msg(2, "two", 0));
+	TEST_SUCC(send_msg(1, "another one", 0));
+
+	TEST_RES(recv_type(1, 0), _ret == 1);
+	TEST_RES(recv_type(-2, 0), _ret == 1);
+	TEST_RES(recv_type(3, MSG_EXCEPT), _ret == 2);
+	TEST_ERRNO(recv_type(4, IPC_NOWAIT), ENOMSG);
+	TEST_RES(recv_type(0, 0), _ret == 3);
+
+	TEST_ERRNO(recv_type(0, IPC_NOWAIT), ENOMSG);
+}
+END_TEST()
+
+FN_TEST(receive_text)
+{
+	struct msg msg;
+
+	TEST_SUCC(send_msg(1, "Hello", 0));
+	TEST_ERRNO(msgrcv(msqid, &msg, 3, 0, 0), E2BIG);
+	TEST_RES(msgrcv(msqid, &msg, 3, 0, MSG_NOERROR),
+		 _ret == 3 && msg.mtype == 1 && memcmp(msg.mtext, "Hel", 3) == 0);
+	TEST_ERRNO(msgrcv(msqid, &msg, sizeof(msg.mtext), 0, IPC_NOWAIT),
+		   ENOMSG);
+
+	TEST_SUCC(send_msg(1, "World", 0));
+	TEST_RES(msgrcv(msqid, &msg, sizeof(msg.mtext), 0, 0),
+		 _ret == 6 && strcmp(msg.mtext, "World") == 0);
+}
+END_TEST()
+
+FN_TEST(send_invalid)
+{
+	struct msg msg = { .mtype = 0 };
+	char *big;
+
+	TEST_ERRNO(msgsnd(msqid, &msg, 1, 0), EINVAL);
+	msg.mtype = -1;
+	TEST_ERRNO(msgsnd(msqid, &msg, 1, 0), EINVAL);
+
+	big = malloc(sizeof(long) + 8193);
+	*(long *)big = 1;
+	TEST_ERRNO(msgsnd(msqid, big, 8193, 0), EINVAL);
+	TEST_SUCC(msgsnd(msqid, big, 8192, 0));
+	TEST_RES(msgrcv(msqid, big, 8192, 0, 0), _ret == 8192);
+	free(big);
+
+	msg.mtype = 1;
+	TEST_ERRNO(msgsnd(-1, &msg, 1, 0), EINVAL);
+	TEST_ERRNO(msgsnd(msqid + 1, &msg, 1, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(queue_full)
+{
+	struct msqid_ds ds;
+
+	TEST_SUCC(msgctl(msqid, IPC_STAT, &ds));
+	TEST_RES(ds.msg_qbytes, _ret == 16384);
+
+	ds.msg_qbytes = 16;
+	TEST_SUCC(msgctl(msqid, IPC_SET, &ds));
+	TEST_SUCC(send_msg(1, "123456789", 0));
+	TEST_ERRNO(send_msg(1, "123456789", IPC_NOWAIT), EAGAIN);
+	TEST_RES(msgctl(msqid, IPC_STAT, &ds),
+		 ds.msg_qbytes == 16 && ds.msg_cbytes == 10 &&
+			 ds.msg_qnum == 1 && ds.msg_lspid == getpid());
+
+	// A blocked sender is woken up after a message is received.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK(send_msg(2, "123456789", 0));
+		exit(EXIT_SUCCESS);
+	}
+	usleep(100 * 1000);
+	TEST_RES(recv_type(0, 0), _ret == 1);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+	TEST_RES(recv_type(0, IPC_NOWAIT), _ret == 2);
+
+	// Privileged processes can exceed the limit.
+	ds.msg_qbytes = 32768;
+	TEST_SUCC(msgctl(msqid, IPC_SET, &ds));
+	TEST_RES(msgctl(msqid, IPC_STAT, &ds),
+		 ds.msg_qbytes == 32768 && ds.msg_cbytes == 0 &&
+			 ds.msg_qnum == 0 && ds.msg_lrpid == getpid());
+}
+END_TEST()
+
+FN_TEST(blocking_receive)
+{
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		usleep(100 * 1000);
+		CHECK(send_msg(5, "five", 0));
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(recv_type(5, 0), _ret == 5);
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(permission)
+{
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		struct msqid_ds ds;
+
+		CHECK(setgroups(0, NULL));
+		CHECK(setgid(1000));
+		CHECK(setuid(1000));
+		CHECK_WITH(send_msg(1, "denied", 0),
+			   _ret == -1 && errno == EACCES);
+		CHECK_WITH(recv_type(0, IPC_NOWAIT),
+			   _ret == -1 && errno == EACCES);
+		CHECK_WITH(msgctl(msqid, IPC_STAT, &ds),
+			   _ret == -1 && errno == EACCES);
+		CHECK_WITH(msgctl(msqid, IPC_RMID, NULL),
+			   _ret == -1 && errno == EPERM);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(remove)
+{
+	struct msqid_ds ds;
+
+	// A blocked receiver fails after the queue is removed.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(recv_type(0, 0), _ret == -1 && errno == EIDRM);
+		exit(EXIT_SUCCESS);
+	}
+	usleep(100 * 1000);
+	TEST_SUCC(msgctl(msqid, IPC_RMID, NULL));
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+
+	TEST_ERRNO(msgctl(msqid, IPC_STAT, &ds), EINVAL);
+	TEST_ERRNO(send_msg(1, "removed", 0), EINVAL);
+	TEST_ERRNO(msgget(MSG_KEY, 0), ENOENT);
+}
+END_TEST()
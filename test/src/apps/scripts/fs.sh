@@ -44,6 +44,35 @@ test_ext2() {
     cd -
 }
 
+test_ext2_dir() {
+    local ext2_dir="$1"
+    local test_dir="$2"
+
+    cd ${ext2_dir}
+    mkdir ${test_dir}
+
+    # Test case for the directory spanning multiple blocks
+    for i in $(seq 1 300); do
+        touch ${test_dir}/file_with_a_long_name_${i}
+    done
+    [ "$(ls ${test_dir} | wc -l)" -eq 300 ]
+
+    # Removes the entries at the beginning of the blocks and the trailing blocks
+    for i in $(seq 1 2 300); do
+        rm ${test_dir}/file_with_a_long_name_${i}
+    done
+    [ "$(ls ${test_dir} | wc -l)" -eq 150 ]
+    for i in $(seq 2 2 300); do
+        rm ${test_dir}/file_with_a_long_name_${i}
+    done
+    [ "$(ls ${test_dir} | wc -l)" -eq 0 ]
+
+    # Clean up
+    rmdir ${test_dir}
+    sync
+    cd -
+}
+
 test_fdatasync() {
     fdatasync/fdatasync /
     rm -f /test_fdatasync.txt
@@ -55,6 +84,7 @@ test_fdatasync() {
 
 echo "Start ext2 fs test......"
 test_ext2 "/ext2" "test_file.txt"
+test_ext2_dir "/ext2" "test_dir"
 echo "All ext2 fs test passed."
 
 echo "Start fdatasync test......"
@@ -63,5 +93,9 @@ echo "All fdatasync test passed."
 
 pipe/pipe_err
 pipe/short_rw
+pipe/splice
 epoll/epoll_err
 epoll/poll_err
+inotify/inotify
+io_uring/io_uring
+fuse/fuse
@@ -34,8 +34,12 @@ sleep 0.2
 ./tcp_poll
 ./tcp_reuseaddr
 ./udp_err
+./ipv6
+./raw_socket
+./packet_socket
 ./unix_stream_err
 ./unix_seqpacket_err
+./unix_datagram_err
 
 ./netlink_route
 ./rtnl_err
@@ -25,17 +25,28 @@ getcpu/getcpu
 getpid/getpid
 hello_pie/hello
 hello_world/hello_world
+itimer/clock_settime
 itimer/setitimer
 itimer/timer_create
+mmap/mlock
 mmap/mmap_and_fork
 mmap/mmap_and_mremap
 mmap/mmap_beyond_the_file
 mmap/mmap_shared_filebacked
 mmap/mmap_readahead
 mmap/mmap_vmrss
+mmap/userfaultfd
+process/cgroup
+process/coredump
 process/group_session
 process/job_control
+process/namespace
 process/pidfd
+process/posix_mqueue
+process/process_vm
+process/ptrace
+process/seccomp
+process/sysv_msg
 process/wait4
 pthread/pthread_test
 pty/open_pty
@@ -43,6 +54,7 @@ pty/pty_blocking
 sched/sched_attr
 sched/sched_attr_idle
 shm/posix_shm
+shm/sysv_shm
 signal_c/parent_death_signal
 signal_c/sigaltstack
 signal_c/signal_fpu
@@ -0,0 +1,77 @@
+#!/bin/sh
+
+# SPDX-License-Identifier: MPL-2.0
+
+# The virtio-fs test requires `virtiofsd` on the host. `make run AUTO_TEST=virtiofs` starts it
+# to share an empty directory, as long as `virtiofsd` is installed. Alternatively, you can
+# 1. Run `virtiofsd` on the host to share an empty directory, e.g.,
+#    `virtiofsd --socket-path=/tmp/virtiofsd.sock --shared-dir=/tmp/share`;
+# 2. Set `VIRTIOFS_SOCKET` to the socket path of `virtiofsd` when running the test.
+#
+# Since `virtiofsd` is not installed in the development container, the test is not run in CI.
+
+set -e
+set -x
+
+VIRTIOFS_TAG=${VIRTIOFS_TAG:-hostshare}
+MNT_DIR=/tmp/virtiofs
+TEST_DIR=${MNT_DIR}/virtiofs_test
+CONTENT="Hello, virtio-fs!"
+
+echo "Start virtio-fs test......"
+
+mkdir -p ${MNT_DIR}
+mount -t virtiofs ${VIRTIOFS_TAG} ${MNT_DIR}
+
+# The shared file system cannot be mounted again with different options
+mkdir -p ${MNT_DIR}2
+if mount -t virtiofs -o max_read=4096 ${VIRTIOFS_TAG} ${MNT_DIR}2; then
+    echo "Error: The file system is mounted with different options."
+    exit 1
+fi
+rmdir ${MNT_DIR}2
+
+rm -rf ${TEST_DIR}
+mkdir ${TEST_DIR}
+cd ${TEST_DIR}
+
+# Write and read a small file
+echo "${CONTENT}" > file
+[ "$(cat file)" = "${CONTENT}" ]
+echo "${CONTENT}" >> file
+[ "$(wc -l < file)" -eq 2 ]
+
+# Write and read a file larger than a single request
+dd if=/dev/urandom of=/tmp/virtiofs_big bs=4096 count=256
+cp /tmp/virtiofs_big big
+cmp /tmp/virtiofs_big big
+truncate -s 5000 big
+[ "$(wc -c < big)" -eq 5000 ]
+
+# List a directory
+mkdir dir
+for i in $(seq 1 100); do
+    touch dir/file_${i}
+done
+[ "$(ls dir | wc -l)" -eq 100 ]
+[ "$(ls | tr '\n' ' ')" = "big dir file " ]
+
+# Rename and remove
+mv file renamed
+[ ! -e file ]
+[ "$(head -n 1 renamed)" = "${CONTENT}" ]
+rm dir/file_*
+rmdir dir
+[ "$(ls | tr '\n' ' ')" = "big renamed " ]
+
+# The data persists after remounting
+cd /
+umount ${MNT_DIR}
+mount -t virtiofs ${VIRTIOFS_TAG} ${MNT_DIR}
+[ "$(head -n 1 ${TEST_DIR}/renamed)" = "${CONTENT}" ]
+
+rm -rf ${TEST_DIR} /tmp/virtiofs_big
+umount ${MNT_DIR}
+rmdir ${MNT_DIR}
+
+echo "Virtio-fs test passed."
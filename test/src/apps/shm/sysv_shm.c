@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MPL-2.0
+
+#define _GNU_SOURCE
+
+#include "../test.h"
+
+#include <grp.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+#include <sys/ipc.h>
+#include <sys/mman.h>
+#include <sys/shm.h>
+#include <sys/wait.h>
+
+#define PAGE_SIZE 4096
+#define SHM_KEY 0x1234
+#define SHM_SIZE (PAGE_SIZE + 100)
+
+static int shmid;
+static pid_t pid;
+static int status;
+
+FN_SETUP(create)
+{
+	shmid = CHECK(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT | IPC_EXCL | 0600));
+}
+END_SETUP()
+
+FN_TEST(get)
+{
+	TEST_RES(shmget(SHM_KEY, 0, 0), _ret == shmid);
+	TEST_RES(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT), _ret == shmid);
+	TEST_ERRNO(shmget(SHM_KEY, SHM_SIZE, IPC_CREAT | IPC_EXCL), EEXIST);
+	TEST_ERRNO(shmget(SHM_KEY, SHM_SIZE + 1, 0), EINVAL);
+
+	TEST_ERRNO(shmget(SHM_KEY + 1, SHM_SIZE, 0), ENOENT);
+	TEST_ERRNO(shmget(SHM_KEY + 1, 0, IPC_CREAT), EINVAL);
+
+	// Private segments are always new.
+	TEST_RES(shmget(IPC_PRIVATE, SHM_SIZE, 0600),
+		 _ret != shmid && shmctl(_ret, IPC_RMID, NULL) == 0);
+}
+END_TEST()
+
+FN_TEST(attach)
+{
+	struct shmid_ds ds;
+	char *addr1, *addr2;
+
+	addr1 = TEST_RES(shmat(shmid, NULL, 0), _ret != (void *)-1);
+	addr2 = TEST_RES(shmat(shmid, NULL, SHM_RDONLY), _ret != (void *)-1);
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_nattch == 2 && ds.shm_segsz == SHM_SIZE &&
+			 ds.shm_cpid == getpid() && ds.shm_lpid == getpid());
+
+	// The attachments share the same memory.
+	strcpy(addr1 + PAGE_SIZE, "Hello");
+	TEST_RES(strcmp(addr2 + PAGE_SIZE, "Hello"), _ret == 0);
+
+	// The attachments are inherited by the child process.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(shmctl(shmid, IPC_STAT, &ds), ds.shm_nattch == 4);
+		strcpy(addr1, "World");
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+	TEST_RES(strcmp(addr2, "World"), _ret == 0);
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds), ds.shm_nattch == 2);
+
+	TEST_ERRNO(shmdt(addr1 + 1), EINVAL);
+	TEST_ERRNO(shmdt(addr1 + PAGE_SIZE), EINVAL);
+	TEST_SUCC(shmdt(addr1));
+	TEST_ERRNO(shmdt(addr1), EINVAL);
+	TEST_SUCC(shmdt(addr2));
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds), ds.shm_nattch == 0);
+}
+END_TEST()
+
+FN_TEST(attach_addr)
+{
+	char *addr;
+
+	addr = TEST_RES(mmap(NULL, 4 * PAGE_SIZE, PROT_NONE,
+			     MAP_PRIVATE | MAP_ANONYMOUS, -1, 0),
+			_ret != MAP_FAILED);
+
+	TEST_ERRNO(shmat(shmid, addr + 1, 0), EINVAL);
+	TEST_ERRNO(shmat(shmid, addr, 0), EINVAL);
+	TEST_ERRNO(shmat(shmid, NULL, SHM_REMAP), EINVAL);
+
+	TEST_RES(shmat(shmid, addr + 1, SHM_RND | SHM_REMAP), _ret == addr);
+	TEST_RES(strcmp(addr, "World"), _ret == 0);
+	TEST_SUCC(shmdt(addr));
+
+	TEST_SUCC(munmap(addr, 4 * PAGE_SIZE));
+	TEST_RES(shmat(shmid, addr + PAGE_SIZE, 0), _ret == addr + PAGE_SIZE);
+	TEST_SUCC(shmdt(addr + PAGE_SIZE));
+
+	TEST_ERRNO(shmat(-1, NULL, 0), EINVAL);
+	TEST_ERRNO(shmat(shmid + 1, NULL, 0), EINVAL);
+}
+END_TEST()
+
+FN_TEST(ctl)
+{
+	struct shmid_ds ds;
+
+	TEST_SUCC(shmctl(shmid, IPC_STAT, &ds));
+	TEST_RES(ds.shm_perm.__key, _ret == SHM_KEY);
+	TEST_RES(ds.shm_perm.mode, _ret == 0600);
+
+	ds.shm_perm.mode = 0640;
+	TEST_SUCC(shmctl(shmid, IPC_SET, &ds));
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds), ds.shm_perm.mode == 0640);
+
+	TEST_SUCC(shmctl(shmid, SHM_LOCK, NULL));
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_perm.mode == (0640 | SHM_LOCKED));
+	TEST_SUCC(shmctl(shmid, SHM_UNLOCK, NULL));
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds), ds.shm_perm.mode == 0640);
+
+	TEST_ERRNO(shmctl(shmid, 100, &ds), EINVAL);
+	TEST_ERRNO(shmctl(shmid + 1, IPC_STAT, &ds), EINVAL);
+}
+END_TEST()
+
+FN_TEST(permission)
+{
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		struct shmid_ds ds;
+
+		CHECK(setgroups(0, NULL));
+		CHECK(setgid(1000));
+		CHECK(setuid(1000));
+		CHECK_WITH(shmat(shmid, NULL, 0),
+			   _ret == (void *)-1 && errno == EACCES);
+		CHECK_WITH(shmctl(shmid, IPC_STAT, &ds),
+			   _ret == -1 && errno == EACCES);
+		CHECK_WITH(shmctl(shmid, IPC_RMID, NULL),
+			   _ret == -1 && errno == EPERM);
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(waitpid(pid, &status, 0),
+		 _ret == pid && WIFEXITED(status) &&
+			 WEXITSTATUS(status) == EXIT_SUCCESS);
+}
+END_TEST()
+
+FN_TEST(remove)
+{
+	struct shmid_ds ds;
+	char *addr;
+	int new_shmid;
+
+	addr = TEST_RES(shmat(shmid, NULL, 0), _ret != (void *)-1);
+	TEST_SUCC(shmctl(shmid, IPC_RMID, NULL));
+
+	// The segment is still attached, but its key can be reused.
+	TEST_RES(shmctl(shmid, IPC_STAT, &ds),
+		 ds.shm_nattch == 1 && ds.shm_perm.__key == IPC_PRIVATE &&
+			 (ds.shm_perm.mode & SHM_DEST));
+	TEST_ERRNO(shmget(SHM_KEY, 0, 0), ENOENT);
+	new_shmid = TEST_RES(shmget(SHM_KEY, PAGE_SIZE, IPC_CREAT | 0600),
+			     _ret != shmid);
+	TEST_RES(strcmp(addr, "World"), _ret == 0);
+
+	// The segment is destroyed after the last detach.
+	TEST_SUCC(shmdt(addr));
+	TEST_ERRNO(shmctl(shmid, IPC_STAT, &ds), EINVAL);
+
+	TEST_SUCC(shmctl(new_shmid, IPC_RMID, NULL));
+	TEST_ERRNO(shmctl(new_shmid, IPC_STAT, &ds), EINVAL);
+}
+END_TEST()
+
+FN_TEST(remove_on_unmap_and_exit)
+{
+	struct shmid_ds ds;
+	char *addr;
+	int new_shmid;
+
+	new_shmid = TEST_RES(shmget(IPC_PRIVATE, PAGE_SIZE, IPC_CREAT | 0600),
+			     _ret >= 0);
+
+	// The segment is destroyed after it is unmapped.
+	addr = TEST_RES(shmat(new_shmid, NULL, 0), _ret != (void *)-1);
+	TEST_SUCC(shmctl(new_shmid, IPC_RMID, NULL));
+	TEST_SUCC(munmap(addr, PAGE_SIZE));
+	TEST_ERRNO(shmctl(new_shmid, IPC_STAT, &ds), EINVAL);
+
+	new_shmid = TEST_RES(shmget(IPC_PRIVATE, PAGE_SIZE, IPC_CREAT | 0600),
+			     _ret >= 0);
+
+	// The segment is destroyed after the process that attaches it exits.
+	pid = TEST_SUCC(fork());
+	if (pid == 0) {
+		CHECK_WITH(shmat(new_shmid, NULL, 0), _ret != (void *)-1);
+		CHECK(shmctl(new_shmid, IPC_RMID, NULL));
+		exit(EXIT_SUCCESS);
+	}
+	TEST_RES(wait(&status), _ret == pid && WIFEXITED(status) &&
+					WEXITSTATUS(status) == EXIT_SUCCESS);
+	TEST_ERRNO(shmctl(new_shmid, IPC_STAT, &ds), EINVAL);
+}
+END_TEST()
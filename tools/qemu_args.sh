@@ -11,6 +11,8 @@
 #  - NETDEV: "user" or "tap";
 #  - VHOST: "off" or "on";
 #  - VSOCK: "off" or "on";
+#  - VIRTIOFS_SOCKET: the socket of a running `virtiofsd`, which enables virtio-fs if set;
+#  - VIRTIOFS_TAG: the tag of the virtio-fs device, default is "hostshare";
 #  - SMP: number of CPUs;
 #  - MEM: amount of memory, e.g. "8G";
 #  - VNC_PORT: VNC port, default is "42".
@@ -18,6 +20,7 @@
 OVMF=${OVMF:-"on"}
 VHOST=${VHOST:-"off"}
 VSOCK=${VSOCK:-"off"}
+VIRTIOFS_TAG=${VIRTIOFS_TAG:-"hostshare"}
 NETDEV=${NETDEV:-"user"}
 
 SSH_RAND_PORT=${SSH_PORT:-$(shuf -i 1024-65535 -n 1)}
@@ -144,6 +147,28 @@ if [ "$VSOCK" = "on" ]; then
     fi
 fi
 
+if [ -n "$VIRTIOFS_SOCKET" ]; then
+    # The guest memory must be shared with `virtiofsd`.
+    echo "[$1] Shared the directory of $VIRTIOFS_SOCKET with tag $VIRTIOFS_TAG" 1>&2
+    VIRTIOFS_ARGS="\
+        -chardev socket,id=virtiofs0,path=$VIRTIOFS_SOCKET \
+        -object memory-backend-memfd,id=mem0,size=${MEM:-8G},share=on \
+        -numa node,memdev=mem0 \
+    "
+    if [ "$1" = "microvm" ]; then
+        MICROVM_QEMU_ARGS="
+            $MICROVM_QEMU_ARGS \
+            $VIRTIOFS_ARGS \
+            -device vhost-user-fs-device,chardev=virtiofs0,tag=$VIRTIOFS_TAG \
+        "
+    else
+        QEMU_ARGS="
+            $QEMU_ARGS \
+            $VIRTIOFS_ARGS \
+            -device vhost-user-fs-pci,chardev=virtiofs0,tag=$VIRTIOFS_TAG$IOMMU_DEV_EXTRA \
+        "
+    fi
+fi
 
 if [ "$1" = "microvm" ]; then
     QEMU_ARGS=$MICROVM_QEMU_ARGS